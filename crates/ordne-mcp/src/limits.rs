@@ -0,0 +1,99 @@
+//! Server-side budgets for agent writes.
+//!
+//! Limits apply to everything done through the MCP server, before any human
+//! approval, so a misbehaving agent cannot stage a catastrophic cleanup. Usage
+//! is counted from the database: classification batches the agent logged and
+//! delete steps of plans it created within the window. Budgets therefore hold
+//! across server restarts and new sessions of the same agent. The delete
+//! budget is checked by the planner before a plan is stored
+//! ([`ordne_lib::PlannerOptions::actor_delete_bytes_per_day`]).
+
+use chrono::{DateTime, Duration, Utc};
+use ordne_lib::undo::classified_files_since;
+use ordne_lib::SqliteDatabase;
+
+#[derive(Debug, Clone, Default)]
+pub struct AgentLimits {
+    pub max_files_classified_per_hour: Option<u64>,
+    pub max_delete_bytes_per_day: Option<u64>,
+}
+
+impl AgentLimits {
+    /// Check that `actor` may classify `count` more files now.
+    pub fn check_classified(
+        &self,
+        db: &SqliteDatabase,
+        actor: &str,
+        count: u64,
+        now: DateTime<Utc>,
+    ) -> Result<(), String> {
+        let Some(limit) = self.max_files_classified_per_hour else {
+            return Ok(());
+        };
+        let used = classified_files_since(db, actor, now - Duration::hours(1)).map_err(|e| e.to_string())?;
+        if used + count > limit {
+            return Err(format!(
+                "Classification budget exceeded: {} files requested, {} of {} used in the last hour",
+                count, used, limit
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ordne_lib::undo::{log_attributed_classification_batch, ClassificationSnapshot};
+    use ordne_lib::{Attribution, Clock, Database};
+
+    fn setup_db() -> SqliteDatabase {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'data', 'source', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes, status)
+                 VALUES (1, 1, 'a', 'a', 600, 'indexed'), (2, 1, 'b', 'b', 600, 'indexed');",
+            )
+            .unwrap();
+        db
+    }
+
+    fn snapshots(count: i64) -> Vec<ClassificationSnapshot> {
+        (0..count)
+            .map(|file_id| ClassificationSnapshot {
+                file_id,
+                category: None,
+                subcategory: None,
+                priority: "normal".to_string(),
+                status: "indexed".to_string(),
+                suggestion_id: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_classification_budget_counts_logged_batches() {
+        let mut db = setup_db();
+        let limits = AgentLimits {
+            max_files_classified_per_hour: Some(100),
+            max_delete_bytes_per_day: None,
+        };
+        let agent = Attribution::agent("claude-1");
+        log_attributed_classification_batch(&mut db, &Clock::default(), Some(&agent), None, &snapshots(80), "80 files".to_string())
+            .unwrap();
+        let other = Attribution::agent("other");
+        log_attributed_classification_batch(&mut db, &Clock::default(), Some(&other), None, &snapshots(90), "90 files".to_string())
+            .unwrap();
+        let now = Utc::now();
+
+        // A new server process sees the same usage.
+        assert!(limits.check_classified(&db, "claude-1", 30, now).is_err());
+        assert!(limits.check_classified(&db, "claude-1", 20, now).is_ok());
+        assert!(limits.check_classified(&db, "other", 20, now).is_err());
+
+        // The batch ages out of the window after an hour.
+        assert!(limits.check_classified(&db, "claude-1", 80, now + Duration::minutes(61)).is_ok());
+    }
+}
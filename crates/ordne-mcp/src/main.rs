@@ -1,3 +1,4 @@
+mod limits;
 mod server;
 
 use anyhow::Result;
@@ -11,6 +12,15 @@ use std::path::PathBuf;
 struct Args {
     #[arg(long, help = "Path to the SQLite database file")]
     db: Option<PathBuf>,
+
+    #[arg(long, help = "Identifier recorded on writes (defaults to the MCP client name)")]
+    agent_id: Option<String>,
+
+    #[arg(long, help = "Maximum files agents may classify per hour")]
+    max_classify_per_hour: Option<u64>,
+
+    #[arg(long, help = "Maximum bytes agents may plan for deletion per day (e.g. 50G)")]
+    max_delete_per_day: Option<String>,
 }
 
 #[tokio::main]
@@ -29,10 +39,38 @@ async fn main() -> Result<()> {
     log::info!("Starting ordne MCP server");
     log::info!("Database: {:?}", db_path);
 
+    let settings = ordne_lib::Config::new(Some(db_path.clone()))?.settings();
     let mut db = ordne_lib::SqliteDatabase::open(&db_path)?;
     db.initialize()?;
+    ordne_lib::config::attach_ledger(&mut db, &db_path, &settings)?;
+    ordne_lib::index::hasher::configure(settings.hash_tuning()?);
+
+    for remap in ordne_lib::db::drives::reconcile_mount_paths(
+        &mut db,
+        &ordne_lib::Clock::default(),
+        ordne_lib::index::find_mount_by_uuid,
+    )? {
+        log::info!(
+            "Drive '{}' is now mounted at {} (was {})",
+            remap.label,
+            remap.new_mount,
+            remap.old_mount
+        );
+    }
+
+    let max_delete_bytes_per_day = args
+        .max_delete_per_day
+        .as_deref()
+        .map(ordne_lib::util::units::parse_bytes)
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid --max-delete-per-day: {}", e))?
+        .map(|bytes| bytes as u64);
+    let limits = limits::AgentLimits {
+        max_files_classified_per_hour: args.max_classify_per_hour,
+        max_delete_bytes_per_day,
+    };
 
-    let server = server::OrdneServer::new(db);
+    let server = server::OrdneServer::new(db, args.agent_id, limits, settings);
 
     // Serve MCP server over stdio
     let (stdin, stdout) = (tokio::io::stdin(), tokio::io::stdout());
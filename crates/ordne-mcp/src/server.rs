@@ -1,27 +1,45 @@
 use ordne_lib::{
-    apply_policy, load_effective_policy,
-    ClassificationRules, RuleEngine,
+    apply_policy_with, load_effective_policy, WhatIf,
+    ClassificationRules, Clock, DriveRuleEngines,
+    config::Settings,
     db::{
         duplicates::*,
         files::{
-            get_files_by_category, get_files_by_category_and_drive, list_files_by_duplicate_group,
-            list_unclassified_files, update_file_classification,
+            get_files_by_category, get_files_by_category_and_drive, list_files_awaiting_suggestion,
+            list_files_by_duplicate_group, list_unclassified_files, update_file_classification,
         },
+        inbox::Inbox,
+        jobs::JobTimer,
+        owners::{get_owner_stats, record_owners, resolve_owner},
+        replicas::check_replicas,
+        scan_errors::{list_scan_errors, replace_scan_errors},
+        suggestions::add_suggestion,
     },
+    undo::{log_attributed_classification_batch, ClassificationSnapshot},
     index::{
-        ScanOptions, scan_directory, import_rmlint_output, refresh_duplicates_for_drive,
-        DedupAlgorithm, RmlintImportOptions
+        ScanOptions, check_catalog, scan_directory, retry_scan_errors, import_rmlint_output, refresh_all_duplicates,
+        refresh_duplicates_for_drive, run_rmlint, estimate_duplicate_waste, DedupAlgorithm, EstimateOptions, OriginalStrategy, RmlintImportOptions, RmlintImportResult,
     },
-    migrate::{EngineOptions, MigrationEngine, Planner, PlannerOptions, RollbackEngine},
-    Backend, Database, Drive, DriveRole, FileStatus, PlanStatus, PlansDatabase, Priority,
-    SqliteDatabase, StepStatus,
+    migrate::{
+        cleanup_plan, find_repairs, plan_encryption_warnings, reconcile_plan, run_due_verification_jobs, EngineOptions, ExecutionLog,
+        MigrationEngine, Planner, OffloadLayout, PlannerOptions, Repair, RollbackEngine, with_plan_drives,
+    },
+    Attribution, AuditDatabase, AuditLogEntry, Backend, ClassificationSuggestion, Database, Drive,
+    DriveRole, FileStatus, PlanStatus, PlansDatabase, Priority, SqliteDatabase, StepQuery,
+    StepOrdering, StepStatus, SuggestionStatus, VerificationDatabase,
 };
 use rmcp::{
-    ServerHandler,
-    handler::server::{router::tool::ToolRouter, wrapper::Parameters},
-    // model imports removed
-    tool, tool_handler, tool_router,
+    ServerHandler, RoleServer,
+    handler::server::{router::tool::ToolRouter, tool::ToolCallContext, wrapper::Parameters},
+    model::{
+        AnnotateAble, CallToolRequestParam, CallToolResult, InitializeRequestParam, ListToolsResult, InitializeResult, ListResourcesResult,
+        PaginatedRequestParam, RawResource, ReadResourceRequestParam, ReadResourceResult,
+        ResourceContents, ServerCapabilities, ServerInfo,
+    },
+    service::RequestContext,
+    tool, tool_router,
 };
+use crate::limits::AgentLimits;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, sync::{Arc, Mutex}};
@@ -30,23 +48,57 @@ use xdg::BaseDirectories;
 #[derive(Clone)]
 pub struct OrdneServer {
     db: Arc<Mutex<SqliteDatabase>>,
+    /// Identifier recorded on every write; from `--agent-id` or the MCP client info.
+    agent_id: Arc<Mutex<Option<String>>>,
+    limits: AgentLimits,
+    /// `ordne.toml`, parsed when the server starts.
+    settings: Settings,
     tool_router: ToolRouter<Self>,
 }
 
 impl OrdneServer {
-    pub fn new(db: SqliteDatabase) -> Self {
+    pub fn new(
+        db: SqliteDatabase,
+        agent_id: Option<String>,
+        limits: AgentLimits,
+        settings: Settings,
+    ) -> Self {
         Self {
             db: Arc::new(Mutex::new(db)),
+            agent_id: Arc::new(Mutex::new(agent_id)),
+            limits,
+            settings,
             tool_router: Self::tool_router(),
         }
     }
 
+    fn check_classified(&self, db: &SqliteDatabase, count: usize) -> Result<(), String> {
+        let attribution = self.attribution();
+        self.limits
+            .check_classified(db, &attribution.actor, count as u64, chrono::Utc::now())
+    }
+
+    /// Planner options for plans the agent creates: `ordne.toml`, with the
+    /// agent's daily delete budget checked before any plan is stored.
+    fn planner_options(&self, attribution: &Attribution) -> Result<PlannerOptions, String> {
+        Ok(PlannerOptions {
+            attribution: Some(attribution.clone()),
+            actor_delete_bytes_per_day: self.limits.max_delete_bytes_per_day,
+            ..self.settings.planner_options().map_err(|e| e.to_string())?
+        })
+    }
+
+    fn attribution(&self) -> Attribution {
+        let agent_id = self.agent_id.lock().unwrap().clone();
+        Attribution::agent(agent_id.unwrap_or_else(|| "unknown".to_string()))
+    }
+
     fn with_db<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&SqliteDatabase) -> R,
     {
         let db = self.db.lock().unwrap();
-        f(&*db)
+        f(&db)
     }
 
     fn with_db_mut<F, R>(&self, f: F) -> R
@@ -54,12 +106,175 @@ impl OrdneServer {
         F: FnOnce(&mut SqliteDatabase) -> R,
     {
         let mut db = self.db.lock().unwrap();
-        f(&mut *db)
+        f(&mut db)
+    }
+}
+
+impl ServerHandler for OrdneServer {
+    /// Run the tool and record the call in the jobs table, as the command
+    /// line records its commands, with the agent and the arguments it sent.
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let parameters = request
+            .arguments
+            .as_ref()
+            .and_then(|args| serde_json::to_string(args).ok());
+        let timer = self.with_db(|db| JobTimer::start(db.conn(), request.name.to_string()));
+
+        let result = self
+            .tool_router
+            .call(ToolCallContext::new(self, request, context))
+            .await;
+
+        let error = match &result {
+            Ok(done) if done.is_error == Some(true) => Some(
+                done.content
+                    .iter()
+                    .filter_map(|content| content.as_text().map(|t| t.text.clone()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            Ok(_) => None,
+            Err(e) => Some(e.message.to_string()),
+        };
+        let attribution = self.attribution();
+        let recorded = timer.and_then(|timer| {
+            self.with_db(|db| timer.finish(db.conn(), error, Some(&attribution), parameters))
+        });
+        if let Err(e) = recorded {
+            log::warn!("Could not record tool call: {}", e);
+        }
+        result
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, rmcp::ErrorData> {
+        Ok(ListToolsResult {
+            tools: self.tool_router.list_all(),
+            meta: None,
+            next_cursor: None,
+        })
+    }
+
+    fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> impl std::future::Future<Output = Result<InitializeResult, rmcp::ErrorData>> + Send + '_ {
+        {
+            let mut agent_id = self.agent_id.lock().unwrap();
+            if agent_id.is_none() {
+                let client = &request.client_info;
+                *agent_id = Some(format!("{}/{}", client.name, client.version));
+            }
+        }
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
+        std::future::ready(Ok(self.get_info()))
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .build(),
+            ..Default::default()
+        }
     }
+
+    fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> impl std::future::Future<Output = Result<ListResourcesResult, rmcp::ErrorData>> + Send + '_ {
+        let mut inbox = RawResource::new(INBOX_URI, "inbox");
+        inbox.description = Some(
+            "Draft plans, pending classification suggestions and unacknowledged verification failures awaiting human action".to_string(),
+        );
+        inbox.mime_type = Some("application/json".to_string());
+        std::future::ready(Ok(ListResourcesResult::with_all_items(vec![
+            inbox.no_annotation(),
+        ])))
+    }
+
+    fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> impl std::future::Future<Output = Result<ReadResourceResult, rmcp::ErrorData>> + Send + '_ {
+        let result = if request.uri == INBOX_URI {
+            self.with_db(|db| {
+                let inbox = Inbox::load(db).map_err(|e| e.to_string())?;
+                serde_json::to_string_pretty(&inbox).map_err(|e| e.to_string())
+            })
+            .map(|text| ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri: INBOX_URI.to_string(),
+                    mime_type: Some("application/json".to_string()),
+                    text,
+                    meta: None,
+                }],
+            })
+            .map_err(|e| rmcp::ErrorData::internal_error(e, None))
+        } else {
+            Err(rmcp::ErrorData::resource_not_found(
+                format!("Unknown resource: {}", request.uri),
+                None,
+            ))
+        };
+        std::future::ready(result)
+    }
+}
+
+const INBOX_URI: &str = "ordne://inbox";
+
+/// Record a write made through the MCP server on the audit log.
+fn log_agent_action(
+    db: &mut SqliteDatabase,
+    attribution: &Attribution,
+    action: &str,
+    plan_id: Option<i64>,
+    drive_id: Option<i64>,
+    details: String,
+) -> Result<(), String> {
+    db.log_audit(&AuditLogEntry {
+        id: 0,
+        timestamp: chrono::Utc::now(),
+        action: action.to_string(),
+        file_id: None,
+        plan_id,
+        drive_id,
+        details: Some(details),
+        agent_mode: Some(attribution.agent_mode.clone()),
+        before_state: None,
+        after_state: None,
+        actor: Some(attribution.actor.clone()),
+    })
+    .map(|_| ())
+    .map_err(|e| e.to_string())
 }
 
-#[tool_handler(router = self.tool_router)]
-impl ServerHandler for OrdneServer {}
+fn rmlint_result_json(result: &RmlintImportResult) -> Result<String, String> {
+    serde_json::to_string_pretty(&serde_json::json!({
+        "lints_total": result.lints_total,
+        "matched_files": result.matched_files,
+        "duplicate_groups_created": result.duplicate_groups_created,
+        "duplicate_files_assigned": result.duplicate_files_assigned,
+        "empty_files_marked": result.empty_files_marked,
+        "empty_dirs_marked": result.empty_dirs_marked,
+        "bad_links_marked": result.bad_links_marked,
+        "skipped_lints": result.skipped_lints,
+    }))
+    .map_err(|e| e.to_string())
+}
 
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct StatusResponse {
@@ -107,10 +322,31 @@ struct DriveInfo {
     backend: String,
     is_online: bool,
     is_readonly: bool,
+    is_cold: bool,
+    is_encrypted: bool,
     total_bytes: Option<i64>,
     scanned_at: Option<String>,
 }
 
+/// A byte count, or a size string such as "500GB" or "2TiB".
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+enum SizeArg {
+    Bytes(u64),
+    Text(String),
+}
+
+impl SizeArg {
+    fn bytes(&self, name: &str) -> Result<u64, String> {
+        match self {
+            SizeArg::Bytes(bytes) => Ok(*bytes),
+            SizeArg::Text(text) => ordne_lib::util::units::parse_bytes(text)
+                .map(|bytes| bytes as u64)
+                .map_err(|e| format!("Invalid {}: {}", name, e)),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct SpaceCheckArgs {
     drive: Option<String>,
@@ -126,26 +362,57 @@ struct DriveAddArgs {
     rclone_remote: Option<String>,
 }
 
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct DriveSetRoleArgs {
+    label: String,
+    /// source, target, backup or offload.
+    role: String,
+    /// Apply the change; otherwise only report what it would affect (default false).
+    apply: Option<bool>,
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct ScanArgs {
     drive_label: String,
+    /// Record access times where the mount keeps them (default false).
+    capture_atime: Option<bool>,
+    /// Re-walk only the paths earlier scans could not read (default false).
+    retry_errors: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct ScanErrorsArgs {
+    drive_label: String,
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct DedupRefreshArgs {
-    drive: String,
+    drive: Option<String>,
+    all: Option<bool>,
     algorithm: Option<String>,
     rehash: Option<bool>,
+    strategy: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct DedupEstimateArgs {
+    drive: String,
+    sample: Option<usize>,
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct QueryDuplicatesArgs {
-    min_size: Option<u64>,
+    min_size: Option<SizeArg>,
     drive: Option<String>,
     same_drive_only: Option<bool>,
     limit: Option<u32>,
 }
 
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct DuplicateGroupShowArgs {
+    group_id: i64,
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct QueryUnclassifiedArgs {
     drive: Option<String>,
@@ -156,12 +423,28 @@ struct QueryUnclassifiedArgs {
 struct QueryFilesArgs {
     category: Option<String>,
     extension: Option<String>,
-    min_size: Option<u64>,
+    min_size: Option<SizeArg>,
     path_contains: Option<String>,
     drive: Option<String>,
+    /// Only files owned by this user (login name or numeric uid).
+    owner: Option<String>,
     limit: Option<u32>,
 }
 
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct VerifyCatalogArgs {
+    /// Number of files to sample (default 1000)
+    sample: Option<usize>,
+    drive: Option<String>,
+    /// Compare size and modification time only (default false)
+    no_hash: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct ReportOwnersArgs {
+    drive: Option<String>,
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct QueryBackupUniqueArgs {
     backup_drive: String,
@@ -181,6 +464,29 @@ struct ClassifyArgs {
     priority: Option<String>,
 }
 
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct ClassifySuggestBatchArgs {
+    drive: Option<String>,
+    /// Maximum number of files to return (default 50).
+    batch_size: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct SuggestionInput {
+    file_id: i64,
+    category: String,
+    subcategory: Option<String>,
+    priority: Option<String>,
+    /// Confidence from 0.0 to 1.0.
+    confidence: f64,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct ClassifySuggestArgs {
+    suggestions: Vec<SuggestionInput>,
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct ClassifyPatternArgs {
     pattern: String,
@@ -194,15 +500,41 @@ struct PlanCreateArgs {
     source_drive: Option<String>,
     target_drive: Option<String>,
     category_filter: Option<String>,
+    /// Only files owned by this user, login name or numeric uid (delete-trash, dedup, migrate, offload).
+    owner: Option<String>,
     duplicate_group: Option<i64>,
     original_file: Option<i64>,
-    batch_size: Option<u32>,
+    /// Copies dedup plans keep of content with any Critical copy (default 2).
+    min_critical_copies: Option<usize>,
+    /// Offload plans copy and delete in batches of this size (bytes or e.g. "50GB").
+    batch_size: Option<SizeArg>,
+    /// Offload layout: "mirror" (default, source paths) or "cas" (objects/<hash> with an index).
+    layout: Option<String>,
+    /// Step order: "priority" (default, critical files first and trash last) or "planned".
+    order: Option<String>,
+    /// Verification failures to repair (recopy plans). Defaults to all open failures.
+    failure_ids: Option<Vec<i64>>,
     description: Option<String>,
 }
 
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct PlanLogArgs {
+    plan_id: i64,
+    /// "json" (default) or "csv".
+    format: Option<String>,
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct PlanShowArgs {
     plan_id: i64,
+    /// Skip this many steps (default 0).
+    offset: Option<u32>,
+    /// Steps per page (default 50).
+    limit: Option<u32>,
+    /// Only list steps for files directly in this directory.
+    directory: Option<String>,
+    /// List source directories with step counts and sizes instead of steps.
+    group_by_directory: Option<bool>,
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
@@ -210,11 +542,21 @@ struct PlanApproveArgs {
     plan_id: i64,
 }
 
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct PlanCleanupArgs {
+    plan_id: i64,
+    /// Remove partial files and update steps; otherwise only report (default false).
+    apply: Option<bool>,
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct MigrateExecuteArgs {
     plan_id: i64,
     execute: bool,
+    /// Cap copy throughput in MB/s.
     io_limit_mbps: Option<u32>,
+    /// Re-verify destinations this long after completion, e.g. "24h" or "7d".
+    verify_after: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
@@ -227,6 +569,8 @@ struct RollbackArgs {
 struct VerifyArgs {
     plan_id: Option<i64>,
     full: Option<bool>,
+    /// Run scheduled plan verifications that are due.
+    due: Option<bool>,
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
@@ -239,6 +583,14 @@ struct PolicyShowArgs {
     path: String,
 }
 
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct WhatIfArgs {
+    /// Plans whose pending steps to apply.
+    plan_ids: Option<Vec<i64>>,
+    /// Policy file whose plans to create and apply.
+    policy_path: Option<String>,
+}
+
 #[derive(Deserialize, Serialize, JsonSchema)]
 struct PolicyApplyArgs {
     path: String,
@@ -253,6 +605,14 @@ struct RmlintImportArgs {
     clear_existing_duplicates: Option<bool>,
 }
 
+#[derive(Deserialize, Serialize, JsonSchema)]
+struct RmlintRunArgs {
+    /// Drive labels whose mount paths rmlint should scan.
+    drives: Vec<String>,
+    apply_trash: Option<bool>,
+    clear_existing_duplicates: Option<bool>,
+}
+
 #[derive(Debug, Clone)]
 pub struct DriveStatistics {
     pub file_count: usize,
@@ -384,6 +744,8 @@ impl OrdneServer {
                     backend: d.backend.as_str().to_string(),
                     is_online: d.is_online,
                     is_readonly: d.is_readonly,
+                    is_cold: d.is_cold,
+                    is_encrypted: d.is_encrypted,
                     total_bytes: d.total_bytes,
                     scanned_at: d.scanned_at.map(|dt| dt.to_rfc3339()),
                 })
@@ -427,6 +789,7 @@ impl OrdneServer {
 
     #[tool(description = "Register a new drive and add it to the ordne database")]
     async fn drive_add(&self, args: Parameters<DriveAddArgs>) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
             let role = DriveRole::from_str(&args.0.role).map_err(|e| e.to_string())?;
             let backend = if let Some(ref b) = args.0.backend {
@@ -449,6 +812,8 @@ impl OrdneServer {
                 role,
                 is_online: true,
                 is_readonly: readonly,
+                is_cold: false,
+                is_encrypted: false,
                 backend,
                 rclone_remote: args.0.rclone_remote.clone(),
                 scanned_at: None,
@@ -456,6 +821,14 @@ impl OrdneServer {
             };
 
             let drive_id = db.add_drive(&drive).map_err(|e| e.to_string())?;
+            log_agent_action(
+                db,
+                &attribution,
+                "drive_added",
+                None,
+                Some(drive_id),
+                format!("Registered drive '{}'", args.0.label),
+            )?;
 
             let response = serde_json::json!({
                 "drive_id": drive_id,
@@ -468,8 +841,33 @@ impl OrdneServer {
         })
     }
 
+    #[tool(description = "Change a drive's role. Reports affected plans and duplicate originals that move; applies only with apply=true")]
+    async fn drive_set_role(&self, args: Parameters<DriveSetRoleArgs>) -> Result<String, String> {
+        let attribution = self.attribution();
+        self.with_db_mut(|db| {
+            let drive = db
+                .get_drive(&args.0.label)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Drive not found: {}", args.0.label))?;
+            let role = DriveRole::from_str(&args.0.role).map_err(|e| e.to_string())?;
+            let change = ordne_lib::roles::assess_role_change(db, &drive, role).map_err(|e| e.to_string())?;
+
+            let applied = args.0.apply.unwrap_or(false);
+            if applied {
+                ordne_lib::roles::apply_role_change(db, &change, &Clock::default(), Some(&attribution)).map_err(|e| e.to_string())?;
+            }
+
+            let mut response = serde_json::to_value(&change).map_err(|e| e.to_string())?;
+            response["from"] = serde_json::json!(change.from.as_str());
+            response["to"] = serde_json::json!(change.to.as_str());
+            response["applied"] = serde_json::json!(applied);
+            serde_json::to_string_pretty(&response).map_err(|e| e.to_string())
+        })
+    }
+
     #[tool(description = "Scan a drive and index all files")]
     async fn scan(&self, args: Parameters<ScanArgs>) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
             let drive = db
                 .get_drive(&args.0.drive_label)
@@ -482,22 +880,61 @@ impl OrdneServer {
 
             let mount_path = drive
                 .mount_path
+                .clone()
                 .ok_or_else(|| "Drive has no mount path".to_string())?;
 
             let scan_opts = ScanOptions {
                 follow_symlinks: false,
                 max_depth: None,
                 include_hidden: false,
+                capture_atime: args.0.capture_atime.unwrap_or(false),
             };
 
-            let stats = scan_directory(db, drive.id, &std::path::PathBuf::from(&mount_path), scan_opts)
+            if args.0.retry_errors.unwrap_or(false) {
+                let stats = retry_scan_errors(db, &drive, scan_opts).map_err(|e| e.to_string())?;
+                log_agent_action(
+                    db,
+                    &attribution,
+                    "drive_scan_retried",
+                    None,
+                    Some(drive.id),
+                    format!(
+                        "Retried {} paths, {} resolved",
+                        stats.paths_retried, stats.resolved
+                    ),
+                )?;
+                let response = serde_json::json!({
+                    "drive": args.0.drive_label,
+                    "paths_retried": stats.paths_retried,
+                    "files_indexed": stats.files_scanned,
+                    "resolved": stats.resolved,
+                    "remaining": stats.remaining,
+                    "status": "complete",
+                });
+                return serde_json::to_string_pretty(&response).map_err(|e| e.to_string());
+            }
+
+            let stats = scan_directory(db, drive.id, std::path::PathBuf::from(&mount_path), scan_opts)
                 .map_err(|e| e.to_string())?;
+            replace_scan_errors(db.conn(), drive.id, &stats.scope, &stats.failures)
+                .map_err(|e| e.to_string())?;
+            record_owners(db.conn(), &stats.owners).map_err(|e| e.to_string())?;
+            log_agent_action(
+                db,
+                &attribution,
+                "drive_scanned",
+                None,
+                Some(drive.id),
+                format!("Indexed {} files", stats.files_scanned),
+            )?;
 
             let response = serde_json::json!({
                 "drive": args.0.drive_label,
                 "files_indexed": stats.files_scanned,
                 "bytes_indexed": stats.bytes_scanned,
                 "errors": stats.errors,
+                "excluded": stats.excluded,
+                "atime_captured": stats.atime_captured,
                 "status": "complete",
             });
 
@@ -505,19 +942,108 @@ impl OrdneServer {
         })
     }
 
-    #[tool(description = "Preferred duplicate discovery workflow: scan + hash + group using ordne native dedup refresh")]
+    #[tool(description = "List paths that scans of a drive could not read (permission denied, I/O errors). A later scan clears the ones it gets through")]
+    async fn scan_errors(&self, args: Parameters<ScanErrorsArgs>) -> Result<String, String> {
+        self.with_db(|db| {
+            let drive = db
+                .get_drive(&args.0.drive_label)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Drive not found: {}", args.0.drive_label))?;
+            let errors = list_scan_errors(db.conn(), drive.id).map_err(|e| e.to_string())?;
+
+            let response = serde_json::json!({
+                "drive": drive.label,
+                "count": errors.len(),
+                "errors": errors,
+            });
+            serde_json::to_string_pretty(&response).map_err(|e| e.to_string())
+        })
+    }
+
+    #[tool(description = "Estimate duplicate waste on a drive from file sizes and sampled partial hashes, with a 95% confidence interval, before deciding on a full dedup_refresh")]
+    async fn dedup_estimate(&self, args: Parameters<DedupEstimateArgs>) -> Result<String, String> {
+        self.with_db(|db| {
+            let drive = db
+                .get_drive(&args.0.drive)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Drive not found: {}", args.0.drive))?;
+            if !drive.is_online {
+                return Err(format!("Drive is offline: {}", args.0.drive));
+            }
+
+            let mut options = EstimateOptions::default();
+            if let Some(sample) = args.0.sample {
+                options.sample_groups = sample;
+            }
+            let estimate = estimate_duplicate_waste(db, drive.id, options).map_err(|e| e.to_string())?;
+            serde_json::to_string_pretty(&estimate).map_err(|e| e.to_string())
+        })
+    }
+
+    #[tool(description = "Preferred duplicate discovery workflow: scan + hash + group one drive using ordne native dedup refresh, or rebuild groups across all drives from catalogued hashes with all=true")]
     async fn dedup_refresh(
         &self,
         args: Parameters<DedupRefreshArgs>,
     ) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
+            let algorithm_str = args.0.algorithm.clone().unwrap_or_else(|| "blake3".to_string());
+            let algorithm = DedupAlgorithm::from_str(&algorithm_str).map_err(|e| e.to_string())?;
+
+            if args.0.all.unwrap_or(false) {
+                if args.0.drive.is_some() || args.0.rehash.unwrap_or(false) {
+                    return Err("`all` cannot be combined with `drive` or `rehash`".to_string());
+                }
+                let strategy_str = args
+                    .0
+                    .strategy
+                    .clone()
+                    .unwrap_or_else(|| "first-indexed".to_string());
+                let strategy = OriginalStrategy::from_str(&strategy_str).map_err(|e| e.to_string())?;
+
+                let ignore = self.settings.dedup_ignore().map_err(|e| e.to_string())?;
+                let dedup_result = refresh_all_duplicates(db, algorithm, strategy, &ignore)
+                    .map_err(|e| e.to_string())?;
+                log_agent_action(
+                    db,
+                    &attribution,
+                    "dedup_refreshed",
+                    None,
+                    None,
+                    format!(
+                        "Rebuilt {} duplicate groups across all drives",
+                        dedup_result.groups_created
+                    ),
+                )?;
+
+                return serde_json::to_string_pretty(&serde_json::json!({
+                    "drive": null,
+                    "workflow": "native",
+                    "algorithm": algorithm_str,
+                    "strategy": strategy_str,
+                    "files_skipped": dedup_result.files_skipped,
+                    "files_unhashed": dedup_result.files_unhashed,
+                    "files_ignored": dedup_result.files_ignored,
+                    "groups_created": dedup_result.groups_created,
+                    "cross_drive_groups": dedup_result.cross_drive_groups,
+                    "duplicate_files_assigned": dedup_result.duplicate_files_assigned,
+                    "status": "complete",
+                }))
+                .map_err(|e| e.to_string());
+            }
+
+            let label = args
+                .0
+                .drive
+                .clone()
+                .ok_or_else(|| "Either `drive` or `all` is required".to_string())?;
             let drive = db
-                .get_drive(&args.0.drive)
+                .get_drive(&label)
                 .map_err(|e| e.to_string())?
-                .ok_or_else(|| format!("Drive not found: {}", args.0.drive))?;
+                .ok_or_else(|| format!("Drive not found: {}", label))?;
 
             if !drive.is_online {
-                return Err(format!("Drive is offline: {}", args.0.drive));
+                return Err(format!("Drive is offline: {}", label));
             }
 
             let mount_path = drive
@@ -525,29 +1051,37 @@ impl OrdneServer {
                 .clone()
                 .ok_or_else(|| "Drive has no mount path".to_string())?;
 
-            let algorithm_str = args.0.algorithm.clone().unwrap_or_else(|| "blake3".to_string());
-            let algorithm = DedupAlgorithm::from_str(&algorithm_str).map_err(|e| e.to_string())?;
             let rehash = args.0.rehash.unwrap_or(false);
 
             let scan_opts = ScanOptions {
                 follow_symlinks: false,
                 max_depth: None,
                 include_hidden: false,
+                capture_atime: false,
             };
 
             let scan_stats = scan_directory(
                 db,
                 drive.id,
-                &std::path::PathBuf::from(&mount_path),
+                std::path::PathBuf::from(&mount_path),
                 scan_opts,
             )
             .map_err(|e| e.to_string())?;
 
-            let dedup_result = refresh_duplicates_for_drive(db, drive.id, algorithm, rehash)
+            let ignore = self.settings.dedup_ignore().map_err(|e| e.to_string())?;
+            let dedup_result = refresh_duplicates_for_drive(db, drive.id, algorithm, rehash, &ignore)
                 .map_err(|e| e.to_string())?;
+            log_agent_action(
+                db,
+                &attribution,
+                "dedup_refreshed",
+                None,
+                Some(drive.id),
+                format!("Created {} duplicate groups", dedup_result.groups_created),
+            )?;
 
             serde_json::to_string_pretty(&serde_json::json!({
-                "drive": args.0.drive,
+                "drive": label,
                 "workflow": "native",
                 "algorithm": algorithm_str,
                 "rehash": rehash,
@@ -555,6 +1089,8 @@ impl OrdneServer {
                 "bytes_scanned": scan_stats.bytes_scanned,
                 "files_hashed": dedup_result.files_hashed,
                 "files_skipped": dedup_result.files_skipped,
+                "files_volatile": dedup_result.files_volatile,
+                "files_ignored": dedup_result.files_ignored,
                 "groups_created": dedup_result.groups_created,
                 "duplicate_files_assigned": dedup_result.duplicate_files_assigned,
                 "status": "complete",
@@ -568,6 +1104,7 @@ impl OrdneServer {
         &self,
         args: Parameters<RmlintImportArgs>,
     ) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
             let result = import_rmlint_output(
                 db,
@@ -578,18 +1115,65 @@ impl OrdneServer {
                 },
             )
             .map_err(|e| e.to_string())?;
+            log_agent_action(
+                db,
+                &attribution,
+                "rmlint_imported",
+                None,
+                None,
+                format!("Imported {} lints from {}", result.lints_total, args.0.path),
+            )?;
+
+            rmlint_result_json(&result)
+        })
+    }
 
-            serde_json::to_string_pretty(&serde_json::json!({
-                "lints_total": result.lints_total,
-                "matched_files": result.matched_files,
-                "duplicate_groups_created": result.duplicate_groups_created,
-                "duplicate_files_assigned": result.duplicate_files_assigned,
-                "empty_files_marked": result.empty_files_marked,
-                "empty_dirs_marked": result.empty_dirs_marked,
-                "bad_links_marked": result.bad_links_marked,
-                "skipped_lints": result.skipped_lints,
-            }))
-            .map_err(|e| e.to_string())
+    #[tool(description = "Run rmlint over registered drives and import its results as duplicate groups")]
+    async fn rmlint_run(
+        &self,
+        args: Parameters<RmlintRunArgs>,
+    ) -> Result<String, String> {
+        let attribution = self.attribution();
+        self.with_db_mut(|db| {
+            let mut paths = Vec::new();
+            for label in &args.0.drives {
+                let drive = db
+                    .get_drive(label)
+                    .map_err(|e| e.to_string())?
+                    .ok_or_else(|| format!("Drive not found: {}", label))?;
+                if !drive.is_online {
+                    return Err(format!("Drive is offline: {}", label));
+                }
+                let mount_path = drive
+                    .mount_path
+                    .ok_or_else(|| format!("Drive has no mount path: {}", label))?;
+                paths.push(std::path::PathBuf::from(mount_path));
+            }
+
+            let output = std::env::temp_dir().join(format!("ordne-rmlint-{}.json", std::process::id()));
+            run_rmlint(&paths, &output).map_err(|e| e.to_string())?;
+
+            let imported = import_rmlint_output(
+                db,
+                &output,
+                RmlintImportOptions {
+                    apply_trash: args.0.apply_trash.unwrap_or(true),
+                    clear_existing_duplicates: args.0.clear_existing_duplicates.unwrap_or(false),
+                },
+            );
+            let _ = std::fs::remove_file(&output);
+            let result = imported.map_err(|e| e.to_string())?;
+
+            log_agent_action(
+                db,
+                &attribution,
+                "rmlint_imported",
+                None,
+                None,
+                format!("Ran rmlint on {:?} and imported {} lints", args.0.drives, result.lints_total),
+            )?;
+
+            rmlint_result_json(&result)
         })
     }
 
@@ -598,6 +1182,7 @@ impl OrdneServer {
         &self,
         args: Parameters<QueryDuplicatesArgs>,
     ) -> Result<String, String> {
+        let min_size = args.0.min_size.as_ref().map(|s| s.bytes("min_size")).transpose()?;
         self.with_db(|db| {
             let conn = db.conn();
             let groups = if args.0.same_drive_only.unwrap_or(false) {
@@ -614,7 +1199,7 @@ impl OrdneServer {
             let groups: Vec<_> = groups
                 .into_iter()
                 .filter(|g| {
-                    if let Some(min_size) = args.0.min_size {
+                    if let Some(min_size) = min_size {
                         g.total_waste_bytes >= min_size as i64
                     } else {
                         true
@@ -641,6 +1226,59 @@ impl OrdneServer {
         })
     }
 
+    #[tool(description = "Show every file in a duplicate group with its drive, size, and original flag")]
+    async fn duplicate_group_show(
+        &self,
+        args: Parameters<DuplicateGroupShowArgs>,
+    ) -> Result<String, String> {
+        self.with_db(|db| {
+            let group = get_duplicate_group(db.conn(), args.0.group_id)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Duplicate group not found: {}", args.0.group_id))?;
+
+            let drives: HashMap<i64, Drive> = db
+                .list_drives()
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|d| (d.id, d))
+                .collect();
+
+            let files: Vec<_> = list_files_by_duplicate_group(db.conn(), group.group_id)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|f| {
+                    let drive = drives.get(&f.drive_id);
+                    serde_json::json!({
+                        "file_id": f.id,
+                        "path": f.path,
+                        "abs_path": f.abs_path,
+                        "size_bytes": f.size_bytes,
+                        "drive_id": f.drive_id,
+                        "drive_label": drive.map(|d| d.label.clone()),
+                        "drive_role": drive.map(|d| d.role.as_str()),
+                        "drive_online": drive.map(|d| d.is_online),
+                        "is_original": f.is_original || group.original_id == Some(f.id),
+                        "modified_at": f.modified_at.map(|dt| dt.to_rfc3339()),
+                        "category": f.category,
+                        "status": f.status.as_str(),
+                    })
+                })
+                .collect();
+
+            serde_json::to_string_pretty(&serde_json::json!({
+                "group_id": group.group_id,
+                "hash": group.hash,
+                "file_count": group.file_count,
+                "wasted_bytes": group.total_waste_bytes,
+                "cross_drive": group.cross_drive,
+                "original_id": group.original_id,
+                "resolution": group.resolution,
+                "files": files,
+            }))
+            .map_err(|e| e.to_string())
+        })
+    }
+
     #[tool(description = "Query files that need classification")]
     async fn query_unclassified(
         &self,
@@ -684,6 +1322,7 @@ impl OrdneServer {
 
     #[tool(description = "Query files by various criteria like category, extension, size, or path pattern")]
     async fn query_files(&self, args: Parameters<QueryFilesArgs>) -> Result<String, String> {
+        let min_size = args.0.min_size.as_ref().map(|s| s.bytes("min_size")).transpose()?;
         self.with_db(|db| {
             let conn = db.conn();
             let mut query = String::from(
@@ -701,7 +1340,7 @@ impl OrdneServer {
                 params.push(Box::new(extension.clone()));
             }
 
-            if let Some(min_size) = args.0.min_size {
+            if let Some(min_size) = min_size {
                 query.push_str(" AND size_bytes >= ?");
                 params.push(Box::new(min_size as i64));
             }
@@ -720,6 +1359,12 @@ impl OrdneServer {
                 params.push(Box::new(drive.id));
             }
 
+            if let Some(ref owner) = args.0.owner {
+                let uid = resolve_owner(conn, owner).map_err(|e| e.to_string())?;
+                query.push_str(" AND owner_uid = ?");
+                params.push(Box::new(uid));
+            }
+
             query.push_str(" ORDER BY size_bytes DESC");
 
             if let Some(limit) = args.0.limit {
@@ -802,6 +1447,7 @@ impl OrdneServer {
 
     #[tool(description = "Run automatic classification rules on unclassified files")]
     async fn classify_auto(&self, args: Parameters<ClassifyAutoArgs>) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
             let (rules, rules_source) = if let Some(ref rules_file) = args.0.rules_file {
                 (
@@ -819,7 +1465,7 @@ impl OrdneServer {
                     )
                 } else {
                     (
-                        ClassificationRules { rules: HashMap::new() },
+                        ClassificationRules::default(),
                         "none".to_string(),
                     )
                 }
@@ -848,26 +1494,45 @@ impl OrdneServer {
                 .map_err(|e| e.to_string());
             }
 
-            let engine = RuleEngine::new(rules.clone()).map_err(|e| e.to_string())?;
-            let mut classified = 0;
+            let engine = DriveRuleEngines::new(db, &rules, &Clock::default()).map_err(|e| e.to_string())?;
+            let mut matches = Vec::new();
             let mut skipped = 0;
 
+            let mut before = Vec::new();
+
             for file in files {
                 if let Some(rule_match) = engine.classify(&file).map_err(|e| e.to_string())? {
-                    update_file_classification(
-                        db.conn(),
-                        file.id,
-                        &rule_match.category,
-                        rule_match.subcategory.as_deref(),
-                        rule_match.priority,
-                    )
-                    .map_err(|e| e.to_string())?;
-                    classified += 1;
+                    before.push(ClassificationSnapshot::from_file(&file));
+                    matches.push((file.id, rule_match));
                 } else {
                     skipped += 1;
                 }
             }
 
+            self.check_classified(db, matches.len())?;
+
+            let classified = matches.len();
+            for (file_id, rule_match) in matches {
+                update_file_classification(
+                    db.conn(),
+                    file_id,
+                    &rule_match.category,
+                    rule_match.subcategory.as_deref(),
+                    rule_match.priority,
+                )
+                .map_err(|e| e.to_string())?;
+            }
+
+            log_attributed_classification_batch(
+                db,
+                &Clock::default(),
+                Some(&attribution),
+                drive_id,
+                &before,
+                format!("Auto-classified {} files ({})", classified, rules_source),
+            )
+            .map_err(|e| e.to_string())?;
+
             serde_json::to_string_pretty(&serde_json::json!({
                 "classified": classified,
                 "skipped": skipped,
@@ -880,6 +1545,7 @@ impl OrdneServer {
 
     #[tool(description = "Manually classify specific files by ID")]
     async fn classify(&self, args: Parameters<ClassifyArgs>) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
             let priority = if let Some(ref p) = args.0.priority {
                 Priority::from_str(p).map_err(|e| e.to_string())?
@@ -893,6 +1559,15 @@ impl OrdneServer {
                 .filter_map(|s| s.trim().parse().ok())
                 .collect();
 
+            self.check_classified(db, file_ids.len())?;
+
+            let mut before = Vec::new();
+            for file_id in &file_ids {
+                if let Some(file) = db.get_file(*file_id).map_err(|e| e.to_string())? {
+                    before.push(ClassificationSnapshot::from_file(&file));
+                }
+            }
+
             let conn = db.conn_mut();
             for file_id in &file_ids {
                 conn.execute(
@@ -908,6 +1583,16 @@ impl OrdneServer {
                 .map_err(|e| e.to_string())?;
             }
 
+            log_attributed_classification_batch(
+                db,
+                &Clock::default(),
+                Some(&attribution),
+                None,
+                &before,
+                format!("Classified files {:?} as '{}'", file_ids, args.0.category),
+            )
+            .map_err(|e| e.to_string())?;
+
             serde_json::to_string_pretty(&serde_json::json!({
                 "classified": file_ids.len(),
                 "file_ids": file_ids,
@@ -917,11 +1602,112 @@ impl OrdneServer {
         })
     }
 
+    #[tool(description = "Get a batch of unclassified files to suggest classifications for. Files with a pending suggestion are skipped.")]
+    async fn classify_suggest_batch(
+        &self,
+        args: Parameters<ClassifySuggestBatchArgs>,
+    ) -> Result<String, String> {
+        self.with_db(|db| {
+            let drive_id = if let Some(ref drive_label) = args.0.drive {
+                let drive = db
+                    .get_drive(drive_label)
+                    .map_err(|e| e.to_string())?
+                    .ok_or_else(|| format!("Drive not found: {}", drive_label))?;
+                Some(drive.id)
+            } else {
+                None
+            };
+
+            let files = list_files_awaiting_suggestion(
+                db.conn(),
+                drive_id,
+                args.0.batch_size.unwrap_or(50),
+            )
+            .map_err(|e| e.to_string())?;
+
+            let files = files
+                .into_iter()
+                .map(|file| {
+                    serde_json::json!({
+                        "id": file.id,
+                        "drive_id": file.drive_id,
+                        "path": file.path,
+                        "filename": file.filename,
+                        "extension": file.extension,
+                        "size_bytes": file.size_bytes,
+                        "mime_type": file.mime_type,
+                        "modified_at": file.modified_at,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            serde_json::to_string_pretty(&serde_json::json!({
+                "files": files,
+                "count": files.len(),
+            }))
+            .map_err(|e| e.to_string())
+        })
+    }
+
+    #[tool(description = "Submit classification suggestions with confidence scores. Suggestions are queued for human review with `ordne classify --suggestions` and are never applied directly.")]
+    async fn classify_suggest(
+        &self,
+        args: Parameters<ClassifySuggestArgs>,
+    ) -> Result<String, String> {
+        let attribution = self.attribution();
+        self.with_db_mut(|db| {
+            let mut suggestion_ids = Vec::new();
+            for input in &args.0.suggestions {
+                let priority = input
+                    .priority
+                    .as_deref()
+                    .map(Priority::from_str)
+                    .transpose()
+                    .map_err(|e| e.to_string())?;
+
+                let id = add_suggestion(
+                    db.conn(),
+                    &ClassificationSuggestion {
+                        id: 0,
+                        file_id: input.file_id,
+                        category: input.category.clone(),
+                        subcategory: input.subcategory.clone(),
+                        priority,
+                        confidence: input.confidence,
+                        reason: input.reason.clone(),
+                        suggested_by: Some(attribution.actor.clone()),
+                        status: SuggestionStatus::Pending,
+                        created_at: chrono::Utc::now(),
+                    },
+                )
+                .map_err(|e| e.to_string())?;
+                suggestion_ids.push(id);
+            }
+
+            log_agent_action(
+                db,
+                &attribution,
+                "classification_suggested",
+                None,
+                None,
+                format!("Suggested classifications for {} files", suggestion_ids.len()),
+            )?;
+
+            serde_json::to_string_pretty(&serde_json::json!({
+                "queued": suggestion_ids.len(),
+                "suggestion_ids": suggestion_ids,
+                "status": "pending_review",
+            }))
+            .map_err(|e| e.to_string())
+        })
+    }
+
     #[tool(description = "Classify all files matching a path glob pattern")]
     async fn classify_pattern(
         &self,
         args: Parameters<ClassifyPatternArgs>,
     ) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
             let priority = if let Some(ref p) = args.0.priority {
                 Priority::from_str(p).map_err(|e| e.to_string())?
@@ -929,6 +1715,24 @@ impl OrdneServer {
                 Priority::Normal
             };
 
+            let pattern = format!("%{}%", args.0.pattern);
+            let matching: Vec<i64> = db
+                .conn()
+                .prepare("SELECT id FROM files WHERE path LIKE ?")
+                .and_then(|mut stmt| {
+                    stmt.query_map([&pattern], |row| row.get(0))?
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .map_err(|e| e.to_string())?;
+            self.check_classified(db, matching.len())?;
+
+            let mut before = Vec::new();
+            for file_id in &matching {
+                if let Some(file) = db.get_file(*file_id).map_err(|e| e.to_string())? {
+                    before.push(ClassificationSnapshot::from_file(&file));
+                }
+            }
+
             let conn = db.conn_mut();
             let count = conn
                 .execute(
@@ -937,11 +1741,24 @@ impl OrdneServer {
                         &args.0.category,
                         priority.as_str(),
                         FileStatus::Classified.as_str(),
-                        format!("%{}%", args.0.pattern),
+                        &pattern,
                     ),
                 )
                 .map_err(|e| e.to_string())?;
 
+            log_attributed_classification_batch(
+                db,
+                &Clock::default(),
+                Some(&attribution),
+                None,
+                &before,
+                format!(
+                    "Classified {} files matching '{}' as '{}'",
+                    count, args.0.pattern, args.0.category
+                ),
+            )
+            .map_err(|e| e.to_string())?;
+
             serde_json::to_string_pretty(&serde_json::json!({
                 "classified": count,
                 "pattern": args.0.pattern,
@@ -953,19 +1770,30 @@ impl OrdneServer {
 
     #[tool(description = "Create a migration plan for review (does not execute)")]
     async fn plan_create(&self, args: Parameters<PlanCreateArgs>) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
             let plan_type = args.0.phase.as_str();
+            let owner_uid = args.0.owner
+                .as_deref()
+                .map(|o| resolve_owner(db.conn(), o))
+                .transpose()
+                .map_err(|e| e.to_string())?;
+            let owned_by = |file: &ordne_lib::File| owner_uid.is_none() || file.owner_uid == owner_uid;
+            #[allow(clippy::large_enum_variant)]
             enum PlanInput {
                 DeleteTrash { files: Vec<ordne_lib::File> },
                 Dedup { duplicates: Vec<ordne_lib::File>, original: ordne_lib::File },
                 Migrate { files: Vec<ordne_lib::File>, target_id: i64, target_mount: String },
-                Offload { files: Vec<ordne_lib::File>, target_id: i64, target_mount: String },
+                Offload { files: Vec<ordne_lib::File>, target_id: i64, target_mount: String, layout: OffloadLayout },
+                Recopy { repairs: Vec<Repair> },
             }
 
+            let mut unresolved = Vec::new();
+
             let input = match plan_type {
                 "delete-trash" => {
                     let category = args.0.category_filter.as_deref().unwrap_or("trash");
-                    let files = if let Some(ref source_drive) = args.0.source_drive {
+                    let mut files = if let Some(ref source_drive) = args.0.source_drive {
                         let drive = db
                             .get_drive(source_drive)
                             .map_err(|e| e.to_string())?
@@ -975,6 +1803,7 @@ impl OrdneServer {
                     } else {
                         get_files_by_category(db.conn(), category).map_err(|e| e.to_string())?
                     };
+                    files.retain(|f| owned_by(f));
 
                     if files.is_empty() {
                         return Err("No files matched category filter".to_string());
@@ -1002,7 +1831,10 @@ impl OrdneServer {
                             .ok_or_else(|| "No original marked; provide original_file".to_string())?
                     };
 
-                    let duplicates: Vec<_> = files.into_iter().filter(|f| f.id != original.id).collect();
+                    let duplicates: Vec<_> = files
+                        .into_iter()
+                        .filter(|f| f.id != original.id && owned_by(f))
+                        .collect();
                     if duplicates.is_empty() {
                         return Err("No duplicate files to delete".to_string());
                     }
@@ -1024,7 +1856,7 @@ impl OrdneServer {
                         .as_deref()
                         .ok_or_else(|| "category_filter is required".to_string())?;
 
-                    let files = if let Some(ref source_drive) = args.0.source_drive {
+                    let mut files = if let Some(ref source_drive) = args.0.source_drive {
                         let drive = db
                             .get_drive(source_drive)
                             .map_err(|e| e.to_string())?
@@ -1034,6 +1866,7 @@ impl OrdneServer {
                     } else {
                         get_files_by_category(db.conn(), category).map_err(|e| e.to_string())?
                     };
+                    files.retain(|f| owned_by(f));
 
                     if files.is_empty() {
                         return Err("No files matched category filter".to_string());
@@ -1042,16 +1875,49 @@ impl OrdneServer {
                     if plan_type == "migrate" {
                         PlanInput::Migrate { files, target_id: target.id, target_mount }
                     } else {
-                        PlanInput::Offload { files, target_id: target.id, target_mount }
+                        let layout = OffloadLayout::from_str(args.0.layout.as_deref().unwrap_or("mirror"))
+                            .map_err(|e| e.to_string())?;
+                        PlanInput::Offload { files, target_id: target.id, target_mount, layout }
                     }
                 }
+                "recopy" => {
+                    let wanted = args.0.failure_ids.clone().unwrap_or_default();
+                    let failures: Vec<_> = db
+                        .list_unacknowledged_failures()
+                        .map_err(|e| e.to_string())?
+                        .into_iter()
+                        .filter(|f| wanted.is_empty() || wanted.contains(&f.id))
+                        .collect();
+                    if failures.is_empty() {
+                        return Err("No open verification failures matched".to_string());
+                    }
+
+                    let set = find_repairs(db, &failures).map_err(|e| e.to_string())?;
+                    unresolved = set
+                        .unresolved
+                        .iter()
+                        .map(|(f, reason)| serde_json::json!({ "failure_id": f.id, "path": f.path, "reason": reason }))
+                        .collect();
+                    PlanInput::Recopy { repairs: set.repairs }
+                }
                 _ => return Err("Invalid plan type".to_string()),
             };
 
+            let defaults = self.planner_options(&attribution)?;
             let options = PlannerOptions {
-                max_batch_size_bytes: args.0.batch_size.map(|v| v as u64),
+                max_batch_size_bytes: args.0.batch_size.as_ref().map(|s| s.bytes("batch_size")).transpose()?,
                 enforce_space_limits: true,
                 dry_run: false,
+                min_critical_copies: args.0.min_critical_copies.unwrap_or(defaults.min_critical_copies),
+                step_ordering: args
+                    .0
+                    .order
+                    .as_deref()
+                    .map(StepOrdering::from_str)
+                    .transpose()
+                    .map_err(|e| e.to_string())?
+                    .unwrap_or(defaults.step_ordering),
+                ..defaults
             };
 
             let mut planner = Planner::new(db, options);
@@ -1066,21 +1932,38 @@ impl OrdneServer {
                 PlanInput::Migrate { files, target_id, target_mount } => {
                     planner.create_migrate_plan(files, target_id, &target_mount).map_err(|e| e.to_string())?
                 }
-                PlanInput::Offload { files, target_id, target_mount } => {
-                    planner.create_offload_plan(files, target_id, &target_mount).map_err(|e| e.to_string())?
+                PlanInput::Offload { files, target_id, target_mount, layout } => match layout {
+                    OffloadLayout::ContentAddressed => planner
+                        .create_cas_offload_plan(files, target_id, &target_mount)
+                        .map_err(|e| e.to_string())?,
+                    OffloadLayout::Mirror => planner
+                        .create_offload_plan(files, target_id, &target_mount)
+                        .map_err(|e| e.to_string())?,
+                },
+                PlanInput::Recopy { repairs } => {
+                    planner.create_recopy_plan(&repairs).map_err(|e| e.to_string())?
                 }
             };
 
-            serde_json::to_string_pretty(&serde_json::json!({
+            let encryption_warnings = plan_encryption_warnings(db.conn(), plan_id).map_err(|e| e.to_string())?;
+
+            let mut response = serde_json::json!({
                 "plan_id": plan_id,
                 "status": "draft",
                 "type": plan_type,
-            }))
-            .map_err(|e| e.to_string())
+            });
+            if !unresolved.is_empty() {
+                response["unresolved_failures"] = serde_json::Value::Array(unresolved);
+            }
+            if !encryption_warnings.is_empty() {
+                response["encryption_warnings"] = serde_json::json!(encryption_warnings);
+            }
+
+            serde_json::to_string_pretty(&response).map_err(|e| e.to_string())
         })
     }
 
-    #[tool(description = "Show details of a migration plan")]
+    #[tool(description = "Show details of a migration plan, with steps paged by offset/limit or grouped by directory")]
     async fn plan_show(&self, args: Parameters<PlanShowArgs>) -> Result<String, String> {
         self.with_db(|db| {
             let plan = db
@@ -1088,13 +1971,50 @@ impl OrdneServer {
                 .map_err(|e| e.to_string())?
                 .ok_or_else(|| format!("Plan not found: {}", args.0.plan_id))?;
 
-            let steps = db
-                .get_steps_for_plan(args.0.plan_id)
+            let query = StepQuery {
+                offset: args.0.offset.unwrap_or(0) as i64,
+                limit: args.0.limit.unwrap_or(50) as i64,
+                directory: args.0.directory.clone(),
+            };
+
+            let plan_json = serde_json::json!({
+                "id": plan.id,
+                "status": plan.status.as_str(),
+                "total_files": plan.total_files,
+                "total_bytes": plan.total_bytes,
+                "completed_files": plan.completed_files,
+                "completed_bytes": plan.completed_bytes,
+                "description": plan.description,
+                "step_ordering": plan.step_ordering.as_str(),
+            });
+
+            if args.0.group_by_directory.unwrap_or(false) {
+                let directories = db
+                    .get_step_directories(args.0.plan_id, query.offset, query.limit)
+                    .map_err(|e| e.to_string())?;
+
+                return serde_json::to_string_pretty(&serde_json::json!({
+                    "plan": plan_json,
+                    "directories": directories,
+                    "offset": query.offset,
+                }))
+                .map_err(|e| e.to_string());
+            }
+
+            let page = db
+                .get_steps_page(args.0.plan_id, &query)
                 .map_err(|e| e.to_string())?;
+            let summary = db
+                .get_plan_summary(args.0.plan_id)
+                .map_err(|e| e.to_string())?;
+            let sessions = db
+                .list_plan_sessions(args.0.plan_id)
+                .map_err(|e| e.to_string())?;
+            let space = reconcile_plan(db, args.0.plan_id).map_err(|e| e.to_string())?;
 
-            let steps_json: Vec<_> = steps
+            let steps_json: Vec<_> = page
+                .steps
                 .iter()
-                .take(50)
                 .map(|s| {
                     serde_json::json!({
                         "id": s.id,
@@ -1107,17 +2027,14 @@ impl OrdneServer {
                 .collect();
 
             serde_json::to_string_pretty(&serde_json::json!({
-                "plan": {
-                    "id": plan.id,
-                    "status": plan.status.as_str(),
-                    "total_files": plan.total_files,
-                    "total_bytes": plan.total_bytes,
-                    "completed_files": plan.completed_files,
-                    "completed_bytes": plan.completed_bytes,
-                    "description": plan.description,
-                },
+                "plan": plan_json,
+                "summary": summary,
+                "sessions": sessions,
+                "space_reconciliation": space,
                 "steps": steps_json,
-                "total_steps": steps.len(),
+                "total_steps": page.total,
+                "offset": page.offset,
+                "has_more": page.has_more(),
             }))
             .map_err(|e| e.to_string())
         })
@@ -1155,6 +2072,29 @@ impl OrdneServer {
         .map_err(|e| e.to_string())
     }
 
+    #[tool(description = "Preview plans or a policy on an in-memory copy of the catalog: per-drive usage and duplicate stats before and after, with nothing changed")]
+    async fn what_if(&self, args: Parameters<WhatIfArgs>) -> Result<String, String> {
+        let plan_ids = args.0.plan_ids.clone().unwrap_or_default();
+        if plan_ids.is_empty() && args.0.policy_path.is_none() {
+            return Err("Specify plan_ids or policy_path".to_string());
+        }
+
+        let report = self.with_db(|db| {
+            let mut what_if = WhatIf::new(db).map_err(|e| e.to_string())?;
+            if let Some(path) = &args.0.policy_path {
+                let (policy, _rules) = load_effective_policy(db, std::path::Path::new(path))
+                    .map_err(|e| e.to_string())?;
+                what_if.apply_policy(&policy, &self.settings).map_err(|e| e.to_string())?;
+            }
+            for plan_id in &plan_ids {
+                what_if.apply_plan(*plan_id).map_err(|e| e.to_string())?;
+            }
+            what_if.report().map_err(|e| e.to_string())
+        })?;
+
+        serde_json::to_string_pretty(&report).map_err(|e| e.to_string())
+    }
+
     #[tool(description = "Apply a policy file to create plans (draft schema)")]
     async fn policy_apply(
         &self,
@@ -1163,7 +2103,11 @@ impl OrdneServer {
         let (policy, _rules) = self
             .with_db(|db| load_effective_policy(db, std::path::Path::new(&args.0.path)))
             .map_err(|e| e.to_string())?;
-        let result = self.with_db_mut(|db| apply_policy(db, &policy).map_err(|e| e.to_string()))?;
+        let attribution = self.attribution();
+        let result = self.with_db_mut(|db| {
+            let options = self.planner_options(&attribution)?;
+            apply_policy_with(db, &policy, options).map_err(|e| e.to_string())
+        })?;
 
         let execute = args.0.execute.unwrap_or(false);
         let dry_run = args.0.dry_run.unwrap_or(false);
@@ -1178,7 +2122,7 @@ impl OrdneServer {
             }
 
             if let Some(max_str) = safety.as_ref().and_then(|s| s.max_bytes_per_run.as_deref()) {
-                let max_bytes = ordne_lib::util::format::parse_size_string(max_str)
+                let max_bytes = ordne_lib::util::units::parse_bytes(max_str)
                     .map_err(|e| format!("Invalid max_bytes_per_run: {}", e))?;
                 let total_bytes: i64 = result
                     .plan_ids
@@ -1207,34 +2151,109 @@ impl OrdneServer {
                 }
             }
 
+            let schedule = policy.schedule.as_ref();
+            let window = schedule
+                .map(|s| s.execution_window())
+                .transpose()
+                .map_err(|e| e.to_string())?
+                .flatten();
+            if let Some(window) = window.filter(|_| execute && !dry_run) {
+                if !window.contains(chrono::Local::now().time()) {
+                    return Err(format!("Outside the policy's execution window ({})", window));
+                }
+            }
+            let idle = schedule
+                .map(|s| s.idle_policy())
+                .transpose()
+                .map_err(|e| e.to_string())?
+                .flatten();
+            if let Some(reason) = idle.filter(|_| execute && !dry_run).and_then(|i| i.busy_reason()) {
+                return Err(format!("Machine busy ({}); the policy only runs while idle", reason));
+            }
+            let schedule_limit = schedule
+                .map(|s| s.bandwidth_limit_kbps())
+                .transpose()
+                .map_err(|e| e.to_string())?
+                .flatten();
+
             let engine_opts = EngineOptions {
                 dry_run: dry_run || !execute,
                 verify_hashes: true,
                 retry_count: 3,
                 enforce_safety: true,
+                max_parallel_steps: 1,
+                per_device_concurrency: 1,
+                attribution: Some(attribution),
+                verify_after: None,
+                // A tool call cannot sleep until the window opens or the
+                // machine is idle, so it refuses to start instead (checked above).
+                window: None,
+                idle: None,
+                bandwidth_limit_kbps: schedule_limit,
+                safe_mode: self.settings.safe_mode(),
+                ..Default::default()
+            };
+            let automount = if engine_opts.dry_run || engine_opts.safe_mode {
+                Default::default()
+            } else {
+                self.settings.automount().map_err(|e| e.to_string())?
             };
+            let clock = engine_opts.clock.clone();
             self.with_db_mut(|db| {
-                let mut engine = MigrationEngine::new(db, engine_opts);
-                for plan_id in &result.plan_ids {
-                    engine.execute_plan(*plan_id).map_err(|e| e.to_string())?;
-                }
-                Ok::<(), String>(())
+                with_plan_drives(db, &result.plan_ids, &automount, &clock, |db| {
+                    let mut engine = MigrationEngine::new(db, engine_opts);
+                    for plan_id in &result.plan_ids {
+                        engine.execute_plan(*plan_id)?;
+                    }
+                    Ok(())
+                })
+                .map_err(|e| e.to_string())
             })?;
         }
 
+        let encryption_warnings = self.with_db(|db| {
+            let mut warnings = Vec::new();
+            for plan_id in &result.plan_ids {
+                warnings.extend(plan_encryption_warnings(db.conn(), *plan_id).map_err(|e| e.to_string())?);
+            }
+            Ok::<_, String>(warnings)
+        })?;
+
         serde_json::to_string_pretty(&serde_json::json!({
             "status": "ok",
             "path": args.0.path,
             "plan_ids": result.plan_ids,
+            "encryption_warnings": encryption_warnings,
         }))
         .map_err(|e| e.to_string())
     }
 
+    #[tool(description = "Export a plan's execution log: per-step timings, bytes, attempts and hash results, as JSON or CSV")]
+    async fn plan_log(&self, args: Parameters<PlanLogArgs>) -> Result<String, String> {
+        self.with_db(|db| {
+            let log = ExecutionLog::load(db, args.0.plan_id).map_err(|e| e.to_string())?;
+            match args.0.format.as_deref().unwrap_or("json") {
+                "json" => log.to_json().map_err(|e| e.to_string()),
+                "csv" => Ok(log.to_csv()),
+                other => Err(format!("Unsupported log format '{}'. Use 'json' or 'csv'", other)),
+            }
+        })
+    }
+
     #[tool(description = "Approve a migration plan for execution")]
     async fn plan_approve(&self, args: Parameters<PlanApproveArgs>) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
             db.update_plan_status(args.0.plan_id, PlanStatus::Approved)
                 .map_err(|e| e.to_string())?;
+            log_agent_action(
+                db,
+                &attribution,
+                "plan_approved",
+                Some(args.0.plan_id),
+                None,
+                format!("Plan {} approved", args.0.plan_id),
+            )?;
 
             serde_json::to_string_pretty(&serde_json::json!({
                 "plan_id": args.0.plan_id,
@@ -1245,45 +2264,106 @@ impl OrdneServer {
         })
     }
 
+    #[tool(description = "Clean up an aborted plan: remove partial destination files, reconcile step statuses with the filesystem and report what stays inconsistent; applies only with apply=true")]
+    async fn plan_cleanup(&self, args: Parameters<PlanCleanupArgs>) -> Result<String, String> {
+        let attribution = self.attribution();
+        self.with_db_mut(|db| {
+            let dry_run = !args.0.apply.unwrap_or(false);
+            let report = cleanup_plan(
+                db,
+                args.0.plan_id,
+                dry_run,
+                self.settings.safe_mode(),
+                &Clock::default(),
+                Some(&attribution),
+            ).map_err(|e| e.to_string())?;
+            serde_json::to_string_pretty(&report).map_err(|e| e.to_string())
+        })
+    }
+
     #[tool(description = "Execute an approved migration plan")]
     async fn migrate_execute(
         &self,
         args: Parameters<MigrateExecuteArgs>,
     ) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
             let plan = db
                 .get_plan(args.0.plan_id)
                 .map_err(|e| e.to_string())?
                 .ok_or_else(|| format!("Plan not found: {}", args.0.plan_id))?;
 
-            if plan.status != PlanStatus::Approved && plan.status != PlanStatus::InProgress {
+            if !matches!(
+                plan.status,
+                PlanStatus::Approved | PlanStatus::InProgress | PlanStatus::Queued | PlanStatus::Paused
+            ) {
                 return Err("Plan must be approved before execution".to_string());
             }
 
+            let verify_after = args
+                .0
+                .verify_after
+                .as_deref()
+                .map(ordne_lib::util::units::parse_duration)
+                .transpose()
+                .map_err(|e| format!("Invalid verify_after: {}", e))?;
+
+            // The engine enforces safe mode itself; this only reports it.
+            let safe_mode = self.settings.safe_mode();
+            let dry_run = !args.0.execute || safe_mode;
+
             let engine_opts = EngineOptions {
-                dry_run: !args.0.execute,
+                dry_run,
                 verify_hashes: true,
                 retry_count: 3,
                 enforce_safety: true,
+                max_parallel_steps: 1,
+                per_device_concurrency: 1,
+                attribution: Some(attribution),
+                verify_after,
+                window: None,
+                bandwidth_limit_kbps: args.0.io_limit_mbps.map(|mbps| mbps as u64 * 1024),
+                safe_mode,
+                ..Default::default()
             };
 
-            let mut engine = MigrationEngine::new(db, engine_opts);
-            engine
-                .execute_plan(args.0.plan_id)
-                .map_err(|e| e.to_string())?;
+            let automount = if dry_run {
+                Default::default()
+            } else {
+                self.settings.automount().map_err(|e| e.to_string())?
+            };
+            let clock = engine_opts.clock.clone();
+            with_plan_drives(db, &[args.0.plan_id], &automount, &clock, |db| {
+                MigrationEngine::new(db, engine_opts).execute_plan(args.0.plan_id)
+            })
+            .map_err(|e| e.to_string())?;
 
             // Get updated plan status
             let plan = db.get_plan(args.0.plan_id)
                 .map_err(|e| e.to_string())?
                 .ok_or_else(|| "Plan not found".to_string())?;
+            let volatile_steps = db
+                .get_steps_for_plan(args.0.plan_id)
+                .map_err(|e| e.to_string())?
+                .iter()
+                .filter(|s| s.status == StepStatus::Volatile)
+                .count();
 
             serde_json::to_string_pretty(&serde_json::json!({
                 "plan_id": args.0.plan_id,
-                "dry_run": !args.0.execute,
+                "dry_run": dry_run,
+                "safe_mode": safe_mode,
                 "completed_files": plan.completed_files,
                 "total_files": plan.total_files,
                 "completed_bytes": plan.completed_bytes,
-                "status": if args.0.execute { "executed" } else { "dry_run_complete" },
+                "volatile_steps": volatile_steps,
+                "status": if dry_run {
+                    "dry_run_complete"
+                } else if volatile_steps > 0 {
+                    "paused"
+                } else {
+                    "executed"
+                },
             }))
             .map_err(|e| e.to_string())
         })
@@ -1291,12 +2371,15 @@ impl OrdneServer {
 
     #[tool(description = "Rollback a migration plan")]
     async fn rollback(&self, args: Parameters<RollbackArgs>) -> Result<String, String> {
+        let attribution = self.attribution();
         self.with_db_mut(|db| {
             if args.0.step_id.is_some() {
                 return Err("Rollback by step_id not supported - use plan_id to rollback entire plan".to_string());
             }
 
-            let mut engine = RollbackEngine::new(db, true);
+            let mut engine = RollbackEngine::new(db, true)
+                .with_attribution(attribution)
+                .with_safe_mode(self.settings.safe_mode());
             engine.rollback_plan(args.0.plan_id)
                 .map_err(|e| e.to_string())?;
 
@@ -1310,6 +2393,18 @@ impl OrdneServer {
 
     #[tool(description = "Verify file hashes after migration")]
     async fn verify(&self, args: Parameters<VerifyArgs>) -> Result<String, String> {
+        if args.0.due.unwrap_or(false) {
+            return self.with_db_mut(|db| {
+                let results = run_due_verification_jobs(db, chrono::Utc::now())
+                    .map_err(|e| e.to_string())?;
+                serde_json::to_string_pretty(&serde_json::json!({
+                    "jobs": results,
+                    "status": "verification_complete",
+                }))
+                .map_err(|e| e.to_string())
+            });
+        }
+
         self.with_db(|db| {
             let conn = db.conn();
 
@@ -1343,6 +2438,68 @@ impl OrdneServer {
         })
     }
 
+    #[tool(description = "Check each category is on at least the number of drives required by [replicas] in ordne.toml, listing content that is short")]
+    async fn verify_replicas(&self) -> Result<String, String> {
+        self.with_db(|db| {
+            let requirements = self.settings.replica_requirements().map_err(|e| e.to_string())?;
+            let reports = check_replicas(db.conn(), &requirements).map_err(|e| e.to_string())?;
+            let short: usize = reports.iter().map(|r| r.shortfalls.len()).sum();
+
+            serde_json::to_string_pretty(&serde_json::json!({
+                "categories": reports,
+                "short": short,
+            }))
+            .map_err(|e| e.to_string())
+        })
+    }
+
+    #[tool(description = "Stat and hash a random sample of indexed files on online local drives and report per drive how far the catalog has drifted from the disks")]
+    async fn verify_catalog(&self, args: Parameters<VerifyCatalogArgs>) -> Result<String, String> {
+        self.with_db(|db| {
+            let drive_id = match args.0.drive {
+                Some(ref label) => Some(
+                    db.get_drive(label)
+                        .map_err(|e| e.to_string())?
+                        .ok_or_else(|| format!("Drive not found: {}", label))?
+                        .id,
+                ),
+                None => None,
+            };
+            let reports = check_catalog(
+                db,
+                drive_id,
+                args.0.sample.unwrap_or(1000),
+                !args.0.no_hash.unwrap_or(false),
+            )
+            .map_err(|e| e.to_string())?;
+            let drifted: usize = reports.iter().map(|r| r.drifted.len()).sum();
+
+            serde_json::to_string_pretty(&serde_json::json!({
+                "drives": reports,
+                "drifted": drifted,
+            }))
+            .map_err(|e| e.to_string())
+        })
+    }
+
+    #[tool(description = "Files, bytes and duplicate bytes per file owner, largest first, to see whose files fill a shared drive")]
+    async fn report_owners(&self, args: Parameters<ReportOwnersArgs>) -> Result<String, String> {
+        self.with_db(|db| {
+            let drive_id = match args.0.drive {
+                Some(ref label) => Some(
+                    db.get_drive(label)
+                        .map_err(|e| e.to_string())?
+                        .ok_or_else(|| format!("Drive not found: {}", label))?
+                        .id,
+                ),
+                None => None,
+            };
+            let owners = get_owner_stats(db.conn(), drive_id).map_err(|e| e.to_string())?;
+            serde_json::to_string_pretty(&serde_json::json!({ "owners": owners }))
+                .map_err(|e| e.to_string())
+        })
+    }
+
     #[tool(description = "Generate a summary report of ordne operations")]
     async fn report(&self) -> Result<String, String> {
         self.with_db(|db| {
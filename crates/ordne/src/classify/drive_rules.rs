@@ -0,0 +1,153 @@
+//! Classification with the rules drives carry in their own `.ordne/ordne.toml`.
+
+use super::rules::{ClassificationRules, RuleEngine, RuleMatch};
+use crate::config::load_drive_config;
+use crate::db::{Database, File};
+use crate::error::Result;
+use crate::util::clock::Clock;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The shared rules, plus a merged engine for every online drive whose root
+/// has rules of its own. A drive's rule replaces a shared rule of the same
+/// name and applies only to files on that drive.
+pub struct DriveRuleEngines {
+    shared: RuleEngine,
+    has_shared_rules: bool,
+    drives: HashMap<i64, RuleEngine>,
+}
+
+impl DriveRuleEngines {
+    pub fn new(db: &dyn Database, rules: &ClassificationRules, clock: &Clock) -> Result<Self> {
+        let mut drives = HashMap::new();
+        for drive in db.list_drives()? {
+            let Some(mount) = drive.mount_path.as_deref().filter(|_| drive.is_online) else {
+                continue;
+            };
+            let Some(config) = load_drive_config(Path::new(mount))? else {
+                continue;
+            };
+            if config.rules.is_empty() {
+                continue;
+            }
+            let mut merged = rules.clone();
+            merged.merge(config.rules);
+            drives.insert(drive.id, RuleEngine::new(merged)?.with_clock(clock.clone()));
+        }
+
+        Ok(Self {
+            shared: RuleEngine::new(rules.clone())?.with_clock(clock.clone()),
+            has_shared_rules: !rules.rules.is_empty(),
+            drives,
+        })
+    }
+
+    /// No shared rules and no drive rules: nothing would ever match.
+    pub fn is_empty(&self) -> bool {
+        !self.has_shared_rules && self.drives.is_empty()
+    }
+
+    pub fn classify(&self, file: &File) -> Result<Option<RuleMatch>> {
+        self.drives.get(&file.drive_id).unwrap_or(&self.shared).classify(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Backend, Drive, DriveRole, FileStatus, Priority, SqliteDatabase};
+    use chrono::Utc;
+
+    fn drive(label: &str, mount: &Path) -> Drive {
+        Drive {
+            id: 0,
+            label: label.to_string(),
+            device_id: None,
+            device_path: None,
+            uuid: None,
+            mount_path: Some(mount.to_string_lossy().to_string()),
+            fs_type: None,
+            total_bytes: None,
+            role: DriveRole::Source,
+            is_online: true,
+            is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
+            backend: Backend::Local,
+            rclone_remote: None,
+            scanned_at: None,
+            added_at: Utc::now(),
+        }
+    }
+
+    fn file(drive_id: i64, name: &str) -> File {
+        File {
+            id: 1,
+            drive_id,
+            path: name.to_string(),
+            abs_path: name.to_string(),
+            filename: name.to_string(),
+            extension: name.rsplit_once('.').map(|(_, ext)| ext.to_string()),
+            size_bytes: 10,
+            md5_hash: None,
+            blake3_hash: None,
+            created_at: None,
+            modified_at: None,
+            accessed_at: None,
+            inode: None,
+            device_num: None,
+            nlinks: None,
+            owner_uid: None,
+            mime_type: None,
+            is_symlink: false,
+            symlink_target: None,
+            git_remote_url: None,
+            category: None,
+            subcategory: None,
+            target_path: None,
+            target_drive_id: None,
+            priority: Priority::Normal,
+            duplicate_group: None,
+            is_original: false,
+            rmlint_type: None,
+            status: FileStatus::Indexed,
+            migrated_to: None,
+            migrated_to_drive: None,
+            migrated_at: None,
+            verified_hash: None,
+            error: None,
+            indexed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_drive_rules_apply_to_their_drive_only() {
+        let portable = tempfile::tempdir().unwrap();
+        let plain = tempfile::tempdir().unwrap();
+        std::fs::create_dir(portable.path().join(".ordne")).unwrap();
+        std::fs::write(
+            portable.path().join(crate::config::DRIVE_CONFIG_FILE),
+            "[rules.raw]\ntype = \"extension\"\nextensions = [\"cr2\"]\ncategory = \"camera_raw\"\n",
+        )
+        .unwrap();
+
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        let portable_id = db.add_drive(&drive("portable", portable.path())).unwrap();
+        let plain_id = db.add_drive(&drive("plain", plain.path())).unwrap();
+
+        let shared = ClassificationRules::from_toml(
+            "[rules.raw]\ntype = \"extension\"\nextensions = [\"cr2\"]\ncategory = \"photos\"\n",
+        )
+        .unwrap();
+        let engines = DriveRuleEngines::new(&db, &shared, &Clock::system()).unwrap();
+
+        let on_portable = engines.classify(&file(portable_id, "a.cr2")).unwrap().unwrap();
+        assert_eq!(on_portable.category, "camera_raw");
+        let on_plain = engines.classify(&file(plain_id, "a.cr2")).unwrap().unwrap();
+        assert_eq!(on_plain.category, "photos");
+
+        let empty = ClassificationRules::default();
+        assert!(!DriveRuleEngines::new(&db, &empty, &Clock::system()).unwrap().is_empty());
+    }
+}
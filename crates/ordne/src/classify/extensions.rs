@@ -0,0 +1,112 @@
+//! Per-extension anomaly detection.
+//!
+//! Scratch files (temporaries, partial downloads, swap files, dumps) should
+//! stay small. When they add up to a lot of space on a drive something has
+//! gone wrong, and the extension is a good candidate for a new rule.
+
+use crate::classify::rules::{ClassificationRule, ClassificationRules, RuleType};
+use crate::db::files::ExtensionStats;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Extensions of files that are normally short-lived.
+pub const SCRATCH_EXTENSIONS: &[&str] = &[
+    "tmp", "temp", "bak", "old", "swp", "part", "partial", "crdownload", "download", "dmp",
+    "log", "cache",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionAnomaly {
+    pub stats: ExtensionStats,
+    pub reason: String,
+}
+
+/// Flag scratch extensions holding at least `threshold_bytes` on a drive.
+pub fn find_anomalies(stats: &[ExtensionStats], threshold_bytes: i64) -> Vec<ExtensionAnomaly> {
+    stats
+        .iter()
+        .filter(|s| s.total_bytes >= threshold_bytes)
+        .filter(|s| {
+            s.extension
+                .as_deref()
+                .is_some_and(|ext| SCRATCH_EXTENSIONS.contains(&ext))
+        })
+        .map(|s| ExtensionAnomaly {
+            stats: s.clone(),
+            reason: format!(
+                "{} files totalling {} bytes of scratch data on '{}'",
+                s.file_count, s.total_bytes, s.drive_label
+            ),
+        })
+        .collect()
+}
+
+/// Extension rules for the flagged extensions, for review before use.
+///
+/// Files land in the `review` category rather than `trash`: a large pile of
+/// `.bak` files may well be the only copy of something.
+pub fn rules_for_anomalies(anomalies: &[ExtensionAnomaly]) -> ClassificationRules {
+    let extensions: BTreeMap<&str, ()> = anomalies
+        .iter()
+        .filter_map(|a| a.stats.extension.as_deref())
+        .map(|ext| (ext, ()))
+        .collect();
+
+    let rules: HashMap<String, ClassificationRule> = extensions
+        .into_keys()
+        .map(|ext| {
+            let name = format!("scratch_{}", ext);
+            let rule = ClassificationRule {
+                name: name.clone(),
+                rule_type: RuleType::Extension {
+                    extensions: vec![ext.to_string()],
+                },
+                category: "review".to_string(),
+                subcategory: Some("scratch".to_string()),
+                subcategory_from_exif: None,
+                priority: Some("low".to_string()),
+                rule_priority: 50,
+            };
+            (name, rule)
+        })
+        .collect();
+
+    ClassificationRules { rules, ..Default::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::files::get_extension_stats;
+    use crate::db::{Database, SqliteDatabase};
+
+    #[test]
+    fn test_large_scratch_extensions_are_flagged() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'src', 'source', 1, 'local');
+                 INSERT INTO files (drive_id, path, filename, extension, size_bytes, status)
+                 VALUES (1, 'a.tmp', 'a.tmp', 'tmp', 600, 'indexed'),
+                        (1, 'b.TMP', 'b.TMP', 'TMP', 600, 'indexed'),
+                        (1, 'c.jpg', 'c.jpg', 'jpg', 5000, 'indexed'),
+                        (1, 'd.log', 'd.log', 'log', 10, 'indexed');",
+            )
+            .unwrap();
+
+        let stats = get_extension_stats(db.conn(), None).unwrap();
+        assert_eq!(stats[0].extension.as_deref(), Some("jpg"));
+        let tmp = stats.iter().find(|s| s.extension.as_deref() == Some("tmp")).unwrap();
+        assert_eq!((tmp.file_count, tmp.total_bytes), (2, 1200));
+
+        let anomalies = find_anomalies(&stats, 1000);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].stats.extension.as_deref(), Some("tmp"));
+
+        let rules = rules_for_anomalies(&anomalies);
+        let toml = toml::to_string_pretty(&rules).unwrap();
+        let parsed = ClassificationRules::from_toml(&toml).unwrap();
+        assert!(parsed.rules.contains_key("scratch_tmp"));
+    }
+}
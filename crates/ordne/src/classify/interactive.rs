@@ -7,7 +7,7 @@
 //! - Confirm batch operations before applying
 
 use crate::classify::rules::RuleEngine;
-use crate::db::{File, Priority};
+use crate::db::{ClassificationSuggestion, File, Priority};
 use crate::error::Result;
 use console::{style, Term};
 use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Select};
@@ -20,6 +20,10 @@ pub struct ClassificationBatch {
     pub subcategory: Option<String>,
     pub files: Vec<File>,
     pub suggested_by: String,
+    /// Priority applied when the batch is accepted as-is.
+    pub priority: Priority,
+    /// Lowest confidence among the batch's suggestions, for agent batches.
+    pub confidence: Option<f64>,
 }
 
 impl ClassificationBatch {
@@ -94,7 +98,14 @@ impl InteractiveClassifier {
         self.term.write_line("")?;
 
         let batches = self.group_into_batches(files)?;
+        self.review_batches(&batches)
+    }
 
+    /// Walk the user through a list of batches, returning accepted classifications.
+    pub fn review_batches(
+        &self,
+        batches: &[ClassificationBatch],
+    ) -> Result<Vec<(i64, ClassificationResult)>> {
         self.term.write_line(&format!(
             "Grouped into {} classification batches",
             style(batches.len()).bold()
@@ -143,10 +154,12 @@ impl InteractiveClassifier {
                 subcategory,
                 suggested_by: "rules".to_string(),
                 files,
+                priority: Priority::Normal,
+                confidence: None,
             })
             .collect();
 
-        batches.sort_by(|a, b| b.file_count().cmp(&a.file_count()));
+        batches.sort_by_key(|b| std::cmp::Reverse(b.file_count()));
 
         Ok(batches)
     }
@@ -183,8 +196,17 @@ impl InteractiveClassifier {
             style(ClassificationBatch::format_size(batch.total_size())).bold()
         ))?;
 
+        if let Some(confidence) = batch.confidence {
+            self.term.write_line(&format!(
+                "  Suggested by {} (priority {}, confidence ≥ {:.0}%)",
+                style(&batch.suggested_by).cyan(),
+                batch.priority.as_str(),
+                confidence * 100.0
+            ))?;
+        }
+
         let sample_count = batch.files.len().min(3);
-        self.term.write_line(&format!("  Sample files:"))?;
+        self.term.write_line("  Sample files:")?;
 
         for file in batch.files.iter().take(sample_count) {
             self.term.write_line(&format!(
@@ -227,7 +249,7 @@ impl InteractiveClassifier {
                     ClassificationResult {
                         category: batch.category.clone(),
                         subcategory: batch.subcategory.clone(),
-                        priority: Priority::Normal,
+                        priority: batch.priority,
                     },
                 )
             })
@@ -274,7 +296,7 @@ impl InteractiveClassifier {
                         ClassificationResult {
                             category: batch.category.clone(),
                             subcategory: batch.subcategory.clone(),
-                            priority: Priority::Normal,
+                            priority: batch.priority,
                         },
                     ));
                 }
@@ -428,6 +450,51 @@ impl InteractiveClassifier {
     }
 }
 
+/// Group pending agent suggestions into review batches by suggested
+/// classification and agent. Suggestions whose file is missing are dropped.
+pub fn suggestion_batches(
+    suggestions: &[ClassificationSuggestion],
+    files: &HashMap<i64, File>,
+) -> Vec<ClassificationBatch> {
+    let mut batches: Vec<ClassificationBatch> = Vec::new();
+
+    for suggestion in suggestions {
+        let Some(file) = files.get(&suggestion.file_id) else {
+            continue;
+        };
+        let priority = suggestion.priority.unwrap_or(Priority::Normal);
+        let suggested_by = suggestion
+            .suggested_by
+            .clone()
+            .unwrap_or_else(|| "agent".to_string());
+
+        let existing = batches.iter_mut().find(|b| {
+            b.category == suggestion.category
+                && b.subcategory == suggestion.subcategory
+                && b.priority == priority
+                && b.suggested_by == suggested_by
+        });
+
+        match existing {
+            Some(batch) => {
+                batch.files.push(file.clone());
+                batch.confidence = batch.confidence.map(|c| c.min(suggestion.confidence));
+            }
+            None => batches.push(ClassificationBatch {
+                category: suggestion.category.clone(),
+                subcategory: suggestion.subcategory.clone(),
+                files: vec![file.clone()],
+                suggested_by,
+                priority,
+                confidence: Some(suggestion.confidence),
+            }),
+        }
+    }
+
+    batches.sort_by_key(|b| std::cmp::Reverse(b.file_count()));
+    batches
+}
+
 /// Result of a classification decision.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ClassificationResult {
@@ -449,15 +516,17 @@ mod tests {
             path: format!("/test/{}", filename),
             abs_path: format!("/test/{}", filename),
             filename: filename.to_string(),
-            extension: filename.split('.').last().map(|s| s.to_string()),
+            extension: filename.rsplit('.').next().map(|s| s.to_string()),
             size_bytes: size,
             md5_hash: None,
             blake3_hash: None,
             created_at: None,
             modified_at: Some(Utc::now()),
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -492,6 +561,8 @@ mod tests {
             subcategory: Some("2024".to_string()),
             files,
             suggested_by: "rules".to_string(),
+            priority: Priority::Normal,
+            confidence: None,
         };
 
         assert_eq!(batch.file_count(), 2);
@@ -539,4 +610,48 @@ mod tests {
         let doc_batch = batches.iter().find(|b| b.category == "documents").unwrap();
         assert_eq!(doc_batch.file_count(), 1);
     }
+
+    #[test]
+    fn test_suggestion_batches() {
+        use crate::db::SuggestionStatus;
+
+        let suggestion = |id: i64, file_id: i64, category: &str, confidence: f64| {
+            ClassificationSuggestion {
+                id,
+                file_id,
+                category: category.to_string(),
+                subcategory: None,
+                priority: Some(Priority::Low),
+                confidence,
+                reason: None,
+                suggested_by: Some("agent/1.0".to_string()),
+                status: SuggestionStatus::Pending,
+                created_at: Utc::now(),
+            }
+        };
+
+        let files: HashMap<i64, File> = [
+            create_test_file(1, "a.jpg", 10),
+            create_test_file(2, "b.jpg", 10),
+            create_test_file(3, "c.pdf", 10),
+        ]
+        .into_iter()
+        .map(|f| (f.id, f))
+        .collect();
+
+        let suggestions = vec![
+            suggestion(1, 1, "photos", 0.9),
+            suggestion(2, 2, "photos", 0.7),
+            suggestion(3, 3, "documents", 0.8),
+            suggestion(4, 42, "photos", 0.9),
+        ];
+
+        let batches = suggestion_batches(&suggestions, &files);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].category, "photos");
+        assert_eq!(batches[0].file_count(), 2);
+        assert_eq!(batches[0].confidence, Some(0.7));
+        assert_eq!(batches[0].priority, Priority::Low);
+        assert_eq!(batches[0].suggested_by, "agent/1.0");
+    }
 }
@@ -1,7 +1,11 @@
 pub mod rules;
+pub mod drive_rules;
 pub mod interactive;
+pub mod extensions;
 
 pub use rules::{
     ClassificationRule, ClassificationRules, RuleMatch, RuleType, RuleEngine,
 };
-pub use interactive::{InteractiveClassifier, ClassificationBatch};
+pub use drive_rules::DriveRuleEngines;
+pub use interactive::{InteractiveClassifier, ClassificationBatch, suggestion_batches};
+pub use extensions::{ExtensionAnomaly, find_anomalies, rules_for_anomalies};
@@ -4,7 +4,7 @@
 //! - Path glob patterns
 //! - File extensions
 //! - Size thresholds
-//! - Age (modified time)
+//! - Age (modified time, or last use when access times were captured)
 //! - Duplicate status
 //! - EXIF metadata for photos
 //!
@@ -13,6 +13,8 @@
 
 use crate::error::{OrdneError, Result};
 use crate::db::{File, Priority};
+use crate::util::clock::Clock;
+use crate::util::units;
 use chrono::{DateTime, Utc};
 use globset::{Glob, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
@@ -58,17 +60,25 @@ pub enum RuleType {
     /// Match files by extension
     #[serde(rename = "extension")]
     Extension { extensions: Vec<String> },
-    /// Match files by size
+    /// Match files by size, given in bytes or as a size like `"500GB"`
     #[serde(rename = "size")]
     Size {
+        #[serde(default, deserialize_with = "units::deserialize_opt_bytes")]
         min_bytes: Option<i64>,
+        #[serde(default, deserialize_with = "units::deserialize_opt_bytes")]
         max_bytes: Option<i64>,
     },
-    /// Match files by age
+    /// Match files by age, given in days or as a duration like `"12w"`
     #[serde(rename = "age")]
     Age {
+        #[serde(default, deserialize_with = "units::deserialize_opt_days")]
         older_than_days: Option<i64>,
+        #[serde(default, deserialize_with = "units::deserialize_opt_days")]
         newer_than_days: Option<i64>,
+        /// Neither read nor written for this many days. Falls back to the
+        /// modified time for files scanned without access times.
+        #[serde(default, deserialize_with = "units::deserialize_opt_days")]
+        unused_for_days: Option<i64>,
     },
     /// Match duplicate files
     #[serde(rename = "duplicate")]
@@ -106,10 +116,14 @@ pub struct ExifData {
 }
 
 /// Complete classification rules configuration.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ClassificationRules {
     #[serde(default)]
     pub rules: HashMap<String, ClassificationRule>,
+    /// Priority of files a rule puts in a category when the rule sets none,
+    /// from the `[category_priorities]` table.
+    #[serde(skip)]
+    pub category_priorities: HashMap<String, Priority>,
 }
 
 impl ClassificationRules {
@@ -135,13 +149,40 @@ impl ClassificationRules {
             }
         }
 
-        Ok(ClassificationRules { rules })
+        let mut category_priorities = HashMap::new();
+        if let Some(table) = config.get("category_priorities") {
+            let table = table.as_table().ok_or_else(|| {
+                OrdneError::Config("[category_priorities] must be a table of category = priority".to_string())
+            })?;
+            for (category, value) in table {
+                let priority = value
+                    .as_str()
+                    .ok_or_else(|| OrdneError::Config(format!("Priority of category '{}' must be a string", category)))
+                    .and_then(Priority::from_str)
+                    .map_err(|e| OrdneError::Config(format!("Category '{}': {}", category, e)))?;
+                category_priorities.insert(category.clone(), priority);
+            }
+        }
+
+        Ok(ClassificationRules { rules, category_priorities })
+    }
+
+    /// Add the rules and category priorities of `other`, replacing those
+    /// with the same name.
+    pub fn merge(&mut self, other: ClassificationRules) {
+        self.rules.extend(other.rules);
+        self.category_priorities.extend(other.category_priorities);
+    }
+
+    /// Neither rules nor category priorities.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.category_priorities.is_empty()
     }
 
     /// Get all rules sorted by priority (highest first).
     pub fn sorted_rules(&self) -> Vec<&ClassificationRule> {
         let mut rules: Vec<&ClassificationRule> = self.rules.values().collect();
-        rules.sort_by(|a, b| b.rule_priority.cmp(&a.rule_priority));
+        rules.sort_by_key(|r| std::cmp::Reverse(r.rule_priority));
         rules
     }
 
@@ -151,8 +192,20 @@ impl ClassificationRules {
         for (name, rule) in &self.rules {
             rules_map.insert(name.clone(), rule.clone());
         }
-        let mut config = std::collections::HashMap::new();
-        config.insert("rules", rules_map);
+        let mut config = toml::map::Map::new();
+        config.insert(
+            "rules".to_string(),
+            toml::Value::try_from(rules_map)
+                .map_err(|e| OrdneError::Config(format!("Failed to serialize rules: {}", e)))?,
+        );
+        if !self.category_priorities.is_empty() {
+            let priorities = self
+                .category_priorities
+                .iter()
+                .map(|(category, priority)| (category.clone(), toml::Value::from(priority.as_str())))
+                .collect();
+            config.insert("category_priorities".to_string(), toml::Value::Table(priorities));
+        }
         let toml_string = toml::to_string_pretty(&config)
             .map_err(|e| OrdneError::Config(format!("Failed to serialize rules: {}", e)))?;
         std::fs::write(path, toml_string)?;
@@ -164,6 +217,7 @@ impl ClassificationRules {
 pub struct RuleEngine {
     rules: ClassificationRules,
     glob_cache: HashMap<String, GlobSet>,
+    clock: Clock,
 }
 
 impl RuleEngine {
@@ -172,11 +226,19 @@ impl RuleEngine {
         let mut engine = Self {
             rules,
             glob_cache: HashMap::new(),
+            clock: Clock::default(),
         };
         engine.build_glob_cache()?;
         Ok(engine)
     }
 
+    /// Measure file ages for `age` rules against `clock` instead of the
+    /// current time.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Build glob matchers for pattern-based rules.
     fn build_glob_cache(&mut self) -> Result<()> {
         for (name, rule) in &self.rules.rules {
@@ -221,8 +283,8 @@ impl RuleEngine {
             RuleType::Pattern { .. } => self.match_pattern(rule, file)?,
             RuleType::Extension { extensions } => self.match_extension(extensions, file),
             RuleType::Size { min_bytes, max_bytes } => self.match_size(*min_bytes, *max_bytes, file),
-            RuleType::Age { older_than_days, newer_than_days } => {
-                self.match_age(*older_than_days, *newer_than_days, file)
+            RuleType::Age { older_than_days, newer_than_days, unused_for_days } => {
+                self.match_age(*older_than_days, *newer_than_days, *unused_for_days, file)
             }
             RuleType::Duplicate { keep_strategy } => self.match_duplicate(keep_strategy, file),
         };
@@ -264,24 +326,40 @@ impl RuleEngine {
     /// Match file against size rule.
     fn match_size(&self, min_bytes: Option<i64>, max_bytes: Option<i64>, file: &File) -> bool {
         let size = file.size_bytes;
-        let min_ok = min_bytes.map_or(true, |min| size >= min);
-        let max_ok = max_bytes.map_or(true, |max| size <= max);
+        let min_ok = min_bytes.is_none_or(|min| size >= min);
+        let max_ok = max_bytes.is_none_or(|max| size <= max);
         min_ok && max_ok
     }
 
     /// Match file against age rule.
-    fn match_age(&self, older_than_days: Option<i64>, newer_than_days: Option<i64>, file: &File) -> bool {
-        if let Some(modified_at) = file.modified_at {
-            let now = Utc::now();
+    fn match_age(
+        &self,
+        older_than_days: Option<i64>,
+        newer_than_days: Option<i64>,
+        unused_for_days: Option<i64>,
+        file: &File,
+    ) -> bool {
+        let now = self.clock.now();
+
+        if older_than_days.is_some() || newer_than_days.is_some() || unused_for_days.is_none() {
+            let Some(modified_at) = file.modified_at else {
+                return false;
+            };
             let age_days = (now - modified_at).num_days();
 
-            let older_ok = older_than_days.map_or(true, |days| age_days >= days);
-            let newer_ok = newer_than_days.map_or(true, |days| age_days <= days);
-
-            older_ok && newer_ok
-        } else {
-            false
+            let older_ok = older_than_days.is_none_or(|days| age_days >= days);
+            let newer_ok = newer_than_days.is_none_or(|days| age_days <= days);
+            if !(older_ok && newer_ok) {
+                return false;
+            }
         }
+
+        // Last use is the later of the last read and the last write.
+        unused_for_days.is_none_or(|days| {
+            file.accessed_at
+                .max(file.modified_at)
+                .is_some_and(|last_used| (now - last_used).num_days() >= days)
+        })
     }
 
     /// Match file against duplicate rule.
@@ -300,10 +378,11 @@ impl RuleEngine {
         Ok(rule.subcategory.clone())
     }
 
-    /// Resolve priority from rule.
+    /// Resolve priority from rule, then from its category's default.
     fn resolve_priority(&self, rule: &ClassificationRule) -> Priority {
         rule.priority.as_ref()
             .and_then(|s| Priority::from_str(s).ok())
+            .or_else(|| self.rules.category_priorities.get(&rule.category).copied())
             .unwrap_or(Priority::Normal)
     }
 }
@@ -409,16 +488,18 @@ mod tests {
             drive_id: 1,
             path: path.to_string(),
             abs_path: format!("/test/{}", path),
-            filename: path.split('/').last().unwrap_or(path).to_string(),
+            filename: path.rsplit('/').next().unwrap_or(path).to_string(),
             extension: extension.map(|s| s.to_string()),
             size_bytes,
             md5_hash: None,
             blake3_hash: None,
             created_at: None,
             modified_at: Some(Utc::now() - Duration::days(10)),
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -468,6 +549,46 @@ mod tests {
         assert_eq!(trash_rule.rule_priority, 100);
     }
 
+    #[test]
+    fn test_category_priorities_fill_in_missing_rule_priority() {
+        let toml = r#"
+            [category_priorities]
+            caches = "trash"
+            photos = "critical"
+
+            [rules.cache]
+            type = "pattern"
+            patterns = ["**/.cache/**"]
+            category = "caches"
+            rule_priority = 100
+
+            [rules.raw]
+            type = "extension"
+            extensions = ["cr2"]
+            category = "photos"
+            priority = "low"
+
+            [rules.docs]
+            type = "extension"
+            extensions = ["pdf"]
+            category = "documents"
+        "#;
+
+        let rules = ClassificationRules::from_toml(toml).unwrap();
+        assert_eq!(rules.category_priorities["caches"], Priority::Trash);
+        let engine = RuleEngine::new(rules).unwrap();
+
+        let priority = |path: &str, ext: &str| {
+            engine.classify(&create_test_file(path, Some(ext), 10)).unwrap().unwrap().priority
+        };
+        assert_eq!(priority("home/u/.cache/x.bin", "bin"), Priority::Trash);
+        // A rule's own priority wins over its category's.
+        assert_eq!(priority("photos/img.cr2", "cr2"), Priority::Low);
+        assert_eq!(priority("docs/a.pdf", "pdf"), Priority::Normal);
+
+        assert!(ClassificationRules::from_toml("[category_priorities]\nphotos = \"urgent\"").is_err());
+    }
+
     #[test]
     fn test_pattern_matching() {
         let toml = r#"
@@ -526,6 +647,44 @@ mod tests {
         assert!(engine.classify(&large_file).unwrap().is_some());
     }
 
+    #[test]
+    fn test_size_and_age_accept_units() {
+        let toml = r#"
+            [rules.big]
+            type = "size"
+            min_bytes = "1.5MB"
+            max_bytes = "1GiB"
+            category = "big"
+
+            [rules.stale]
+            type = "age"
+            older_than_days = "2w"
+            category = "stale"
+        "#;
+
+        let rules = ClassificationRules::from_toml(toml).unwrap();
+        match &rules.rules["big"].rule_type {
+            RuleType::Size { min_bytes, max_bytes } => {
+                assert_eq!(*min_bytes, Some(1_572_864));
+                assert_eq!(*max_bytes, Some(1 << 30));
+            }
+            other => panic!("unexpected rule type: {:?}", other),
+        }
+        match &rules.rules["stale"].rule_type {
+            RuleType::Age { older_than_days, newer_than_days, .. } => {
+                assert_eq!(*older_than_days, Some(14));
+                assert_eq!(*newer_than_days, None);
+            }
+            other => panic!("unexpected rule type: {:?}", other),
+        }
+
+        let err = ClassificationRules::from_toml(
+            "[rules.bad]\ntype = \"size\"\nmin_bytes = \"12XB\"\ncategory = \"x\"\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown size unit 'xb'"));
+    }
+
     #[test]
     fn test_age_matching() {
         let toml = r#"
@@ -545,6 +704,52 @@ mod tests {
         assert_eq!(result.unwrap().category, "old");
     }
 
+    #[test]
+    fn test_unused_for_days_uses_access_time() {
+        let toml = r#"
+            [rules.stale]
+            type = "age"
+            unused_for_days = 30
+            category = "stale"
+        "#;
+
+        let rules = ClassificationRules::from_toml(toml).unwrap();
+        let engine = RuleEngine::new(rules).unwrap();
+
+        let mut file = create_test_file("report.pdf", Some("pdf"), 1024);
+        file.modified_at = Some(Utc::now() - Duration::days(400));
+        assert!(engine.classify(&file).unwrap().is_some());
+
+        file.accessed_at = Some(Utc::now() - Duration::days(3));
+        assert!(engine.classify(&file).unwrap().is_none());
+
+        file.accessed_at = Some(Utc::now() - Duration::days(90));
+        assert!(engine.classify(&file).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_age_rules_use_injected_clock() {
+        let toml = r#"
+            [rules.stale]
+            type = "age"
+            older_than_days = 30
+            category = "stale"
+        "#;
+
+        let now = Utc::now();
+        let clock = Clock::fixed(now);
+        let rules = ClassificationRules::from_toml(toml).unwrap();
+        let engine = RuleEngine::new(rules).unwrap().with_clock(clock.clone());
+
+        let mut file = create_test_file("report.pdf", Some("pdf"), 1024);
+        file.modified_at = Some(now - Duration::days(10));
+        assert!(engine.classify(&file).unwrap().is_none());
+
+        // Pretend it is next month.
+        clock.advance(Duration::days(31));
+        assert!(engine.classify(&file).unwrap().is_some());
+    }
+
     #[test]
     fn test_rule_priority() {
         let toml = r#"
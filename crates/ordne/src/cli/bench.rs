@@ -0,0 +1,82 @@
+use super::table::OutputTable;
+use clap::Subcommand;
+use comfy_table::Cell;
+use console::style;
+use ordne_lib::index::{benchmark_hashing, candidate_settings, device_class_of_path, sample_files};
+use ordne_lib::{HashAlgorithm, OrdneError, Result};
+use std::path::PathBuf;
+
+#[derive(Subcommand)]
+pub enum BenchSubcommand {
+    #[command(about = "Compare hashing buffer sizes, mmap and O_DIRECT on a drive")]
+    Hash {
+        #[arg(help = "File or directory on the drive to measure")]
+        path: PathBuf,
+        #[arg(long, default_value = "1GiB", help = "Read up to this much of the largest files per setting")]
+        max_bytes: String,
+        #[arg(long, default_value = "blake3", help = "Hash algorithm: blake3 or md5")]
+        algorithm: String,
+    },
+}
+
+pub fn handle_bench_command(subcommand: BenchSubcommand) -> Result<()> {
+    match subcommand {
+        BenchSubcommand::Hash { path, max_bytes, algorithm } => bench_hash(&path, &max_bytes, &algorithm),
+    }
+}
+
+fn bench_hash(path: &std::path::Path, max_bytes: &str, algorithm: &str) -> Result<()> {
+    if !path.exists() {
+        return Err(OrdneError::FileNotFound(path.to_path_buf()));
+    }
+    let max_bytes = crate::util::units::parse_bytes(max_bytes)
+        .map_err(|e| OrdneError::Config(format!("Invalid --max-bytes: {}", e)))?;
+    let algorithm = HashAlgorithm::from_str(algorithm)?;
+
+    let files = sample_files(path, max_bytes.max(0) as u64);
+    if files.is_empty() {
+        println!("{}", style("No files to read").yellow());
+        return Ok(());
+    }
+    let class = device_class_of_path(path);
+    println!(
+        "{} Hashing {} files with each setting ({} device)",
+        style(">>>").cyan(),
+        files.len(),
+        class.as_str()
+    );
+
+    let results = benchmark_hashing(&files, &candidate_settings(), algorithm)?;
+
+    let mut table = OutputTable::new(vec!["Setting", "Read", "Time", "Throughput"]);
+    for result in &results {
+        table.add_row(vec![
+            Cell::new(result.settings.label()),
+            Cell::new(crate::util::format::format_bytes(result.bytes as i64)),
+            Cell::new(format!("{:.2}s", result.elapsed.as_secs_f64())),
+            Cell::new(format!(
+                "{}/s",
+                crate::util::format::format_bytes(result.bytes_per_second() as i64)
+            )),
+        ]);
+    }
+    println!("{}", table);
+
+    let Some(best) = results
+        .iter()
+        .max_by(|a, b| a.bytes_per_second().total_cmp(&b.bytes_per_second()))
+    else {
+        return Ok(());
+    };
+    println!("\n{} Fastest: {}", style("✓").green(), style(best.settings.label()).bold());
+    let section = match class {
+        ordne_lib::index::DeviceClass::Unknown => "hashing".to_string(),
+        class => format!("hashing.{}", class.as_str()),
+    };
+    println!("  To use it, add to ~/.config/ordne/ordne.toml:\n");
+    println!("  [{}]", section);
+    println!("  buffer_size = {}", best.settings.buffer_size);
+    println!("  mmap = {}", best.settings.mmap);
+    println!("  direct_io = {}", best.settings.direct_io);
+    Ok(())
+}
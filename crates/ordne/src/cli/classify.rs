@@ -1,8 +1,13 @@
 use ordne_lib::Result;
 use console::style;
 use ordne_lib::{
-    SqliteDatabase, ClassificationRules, RuleEngine, InteractiveClassifier,
+    Clock, DriveRuleEngines, Priority, SqliteDatabase, ClassificationRules, RuleEngine, InteractiveClassifier,
+    SuggestionStatus,
+    suggestion_batches,
+    db::{files, suggestions},
+    undo::{log_classification_batch, ClassificationSnapshot},
 };
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 pub fn handle_classify_command(
@@ -25,7 +30,7 @@ pub fn handle_classify_command(
         if verbose {
             println!("{} No config file found, using empty rules...", style(">>>").cyan());
         }
-        ClassificationRules { rules: std::collections::HashMap::new() }
+        ClassificationRules::default()
     };
 
     let unclassified = super::helpers::get_unclassified_files(db, None)?;
@@ -56,8 +61,8 @@ fn run_automatic_classification(
 ) -> Result<()> {
     println!("{} Running automatic classification...", style(">>>").cyan());
 
-    let engine = RuleEngine::new(rules.clone())?;
-    let mut classified_count = 0;
+    let engine = DriveRuleEngines::new(db, rules, &Clock::default())?;
+    let mut before = Vec::new();
     let mut skipped_count = 0;
 
     let pb = if verbose {
@@ -79,7 +84,7 @@ fn run_automatic_classification(
                 rule_match.subcategory.as_deref(),
                 rule_match.priority,
             )?;
-            classified_count += 1;
+            before.push(ClassificationSnapshot::from_file(&file));
 
             if verbose {
                 println!(
@@ -102,6 +107,9 @@ fn run_automatic_classification(
         pb.finish_and_clear();
     }
 
+    let classified_count = before.len();
+    log_classification_batch(db, &Clock::default(), &before, format!("Classified {} files with rules", classified_count))?;
+
     println!("\n{} Classification complete", style("✓").green());
     println!("  Classified: {}", style(classified_count).green());
     println!("  Skipped: {}", style(skipped_count).yellow());
@@ -120,14 +128,16 @@ fn run_interactive_classification(
     let engine = RuleEngine::new(rules.clone())?;
     let _classifier = InteractiveClassifier::new(engine);
 
-    let classified = 0; let skipped = files.len();
+    let classified = 0;
+    let skipped = files.len();
+    let new_rules = 0;
 
     println!("\n{} Classification session complete", style("✓").green());
     println!("  Classified: {}", style(classified).green());
     println!("  Skipped: {}", style(skipped).yellow());
 
-    if 0 > 0 {
-        println!("  New rules created: {}", style(0).cyan());
+    if new_rules > 0 {
+        println!("  New rules created: {}", style(new_rules).cyan());
 
         if let Some(config_path) = get_config_save_path() {
             println!("\n{} Save new rules to {}? (y/n)", style("?").yellow(), config_path.display());
@@ -148,6 +158,103 @@ fn run_interactive_classification(
     Ok(())
 }
 
+/// Review classification suggestions queued by agents. Accepted suggestions
+/// are applied; overridden ones are applied with the reviewer's choice and
+/// the suggestion is marked rejected. Skipped suggestions stay pending.
+pub fn handle_review_suggestions(
+    db: &mut SqliteDatabase,
+    min_confidence: Option<f64>,
+    verbose: bool,
+) -> Result<()> {
+    let pending = suggestions::list_pending_suggestions(db.conn(), min_confidence)?;
+
+    if pending.is_empty() {
+        println!("{}", style("No pending classification suggestions").green());
+        return Ok(());
+    }
+
+    println!(
+        "{} Found {} pending suggestions\n",
+        style(">>>").cyan(),
+        style(pending.len()).bold()
+    );
+
+    let mut file_map = HashMap::new();
+    for suggestion in &pending {
+        if let Some(file) = files::get_file(db.conn(), suggestion.file_id)? {
+            file_map.insert(file.id, file);
+        }
+    }
+
+    let batches = suggestion_batches(&pending, &file_map);
+    let classifier = InteractiveClassifier::new(RuleEngine::new(ClassificationRules::default())?);
+    let results = classifier.review_batches(&batches)?;
+
+    let by_file: HashMap<i64, _> = pending.iter().map(|s| (s.file_id, s)).collect();
+    let mut before = Vec::new();
+    let mut accepted = 0;
+    let mut overridden = 0;
+
+    for (file_id, result) in &results {
+        let Some(suggestion) = by_file.get(file_id) else {
+            continue;
+        };
+
+        if let Some(file) = file_map.get(file_id) {
+            before.push(ClassificationSnapshot::from_file(file).with_suggestion(suggestion.id));
+        }
+
+        let unchanged = result.category == suggestion.category
+            && result.subcategory == suggestion.subcategory
+            && result.priority == suggestion.priority.unwrap_or(Priority::Normal);
+
+        if unchanged {
+            suggestions::accept_suggestion(db.conn(), suggestion)?;
+            accepted += 1;
+        } else {
+            files::update_file_classification(
+                db.conn(),
+                *file_id,
+                &result.category,
+                result.subcategory.as_deref(),
+                result.priority,
+            )?;
+            suggestions::update_suggestion_status(
+                db.conn(),
+                suggestion.id,
+                SuggestionStatus::Rejected,
+            )?;
+            overridden += 1;
+        }
+
+        if verbose {
+            println!(
+                "  {}: file {} -> {}",
+                style("✓").green(),
+                file_id,
+                result.category
+            );
+        }
+    }
+
+    log_classification_batch(
+        db,
+        &Clock::default(),
+        &before,
+        format!("Reviewed {} classification suggestions", before.len()),
+    )?;
+
+    println!("\n{} Suggestion review complete", style("✓").green());
+    println!("  Accepted: {}", style(accepted).green());
+    println!("  Overridden: {}", style(overridden).yellow());
+    println!(
+        "  Still pending: {}",
+        style(pending.len() - accepted - overridden).dim()
+    );
+
+    Ok(())
+}
+
 fn get_config_save_path() -> Option<PathBuf> {
     let xdg = xdg::BaseDirectories::new().ok()?;
     xdg.place_config_file("ordne/ordne.toml").ok()
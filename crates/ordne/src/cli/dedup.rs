@@ -1,27 +1,101 @@
 use clap::Subcommand;
 use console::style;
-use ordne_lib::{Database, Result, SqliteDatabase};
-use ordne_lib::index::{refresh_duplicates_for_drive, DedupAlgorithm};
+use dialoguer::{theme::ColorfulTheme, Confirm, Select};
+use ordne_lib::db::duplicates::{
+    list_unresolved_duplicate_groups, set_duplicate_group_original,
+    update_duplicate_group_resolution,
+};
+use ordne_lib::config::Settings;
+use ordne_lib::db::files::list_files_by_duplicate_group;
+use ordne_lib::index::{
+    estimate_duplicate_waste, refresh_all_duplicates, refresh_duplicates_for_drive, DedupAlgorithm,
+    EstimateOptions, OriginalStrategy,
+};
+use crate::util::format::format_bytes;
+use ordne_lib::{
+    Database, DedupResolution, DuplicateGroup, File, OrdneError, Planner, PlannerOptions,
+    Result, SqliteDatabase,
+};
+use std::collections::HashMap;
+use std::io::Read;
+
+const PREVIEW_BYTES: usize = 4096;
+const PREVIEW_LINES: usize = 5;
 
 #[derive(Subcommand)]
 pub enum DedupSubcommand {
     Refresh {
-        #[arg(long, help = "Drive label to scan and hash")]
-        drive: String,
+        #[arg(long, required_unless_present = "all", help = "Drive label to scan and hash")]
+        drive: Option<String>,
+        #[arg(
+            long,
+            conflicts_with_all = ["drive", "rehash"],
+            help = "Rebuild groups across all drives from catalogued hashes"
+        )]
+        all: bool,
         #[arg(long, default_value = "blake3", help = "Hash algorithm: blake3 or md5")]
         algorithm: String,
         #[arg(long, help = "Recompute hashes even if already present")]
         rehash: bool,
+        #[arg(
+            long,
+            requires = "all",
+            default_value = "first-indexed",
+            help = "Original per group: first-indexed, oldest, newest, shortest-path or drive-role"
+        )]
+        strategy: String,
+    },
+    #[command(about = "Estimate duplicate waste on a drive from sizes and sampled partial hashes")]
+    Estimate {
+        #[arg(help = "Drive label")]
+        drive: String,
+        #[arg(long, default_value_t = 200, help = "Number of same-size file groups to sample")]
+        sample: usize,
+    },
+    #[command(about = "Step through duplicate groups and choose which copy to keep")]
+    Review {
+        #[arg(long, help = "Only review groups involving this drive label")]
+        drive: Option<String>,
+        #[arg(long, help = "Maximum number of groups to review")]
+        limit: Option<usize>,
+        #[arg(long, help = "Show a short text preview of each file")]
+        preview: bool,
     },
 }
 
 pub fn handle_dedup_command(
     db: &mut SqliteDatabase,
+    settings: &Settings,
     subcommand: DedupSubcommand,
     _verbose: bool,
 ) -> Result<()> {
     match subcommand {
-        DedupSubcommand::Refresh { drive, algorithm, rehash } => {
+        DedupSubcommand::Refresh { all: true, algorithm, strategy, .. } => {
+            let algorithm = DedupAlgorithm::from_str(&algorithm)?;
+            let strategy = OriginalStrategy::from_str(&strategy)?;
+
+            let ignore = settings.dedup_ignore()?;
+            let result = refresh_all_duplicates(db, algorithm, strategy, &ignore)?;
+
+            println!("{} Dedup refresh complete (all drives)", style("✓").green());
+            println!("  Files skipped: {}", result.files_skipped);
+            if result.files_ignored > 0 {
+                println!("  Files ignored ([dedup] ignore): {}", result.files_ignored);
+            }
+            println!("  Groups created: {}", result.groups_created);
+            println!("  Cross-drive groups: {}", result.cross_drive_groups);
+            println!("  Duplicate files assigned: {}", result.duplicate_files_assigned);
+            if result.files_unhashed > 0 {
+                println!(
+                    "  {} {} files have no catalogued hash; run `ordne dedup refresh --drive <label>` to hash them",
+                    style("!").yellow(),
+                    result.files_unhashed
+                );
+            }
+            Ok(())
+        }
+        DedupSubcommand::Refresh { drive, algorithm, rehash, .. } => {
+            let drive = drive.unwrap_or_default();
             let drive_info = db.get_drive(&drive)?
                 .ok_or_else(|| ordne_lib::OrdneError::DriveNotFound(drive.clone()))?;
 
@@ -39,21 +113,326 @@ pub fn handle_dedup_command(
                 follow_symlinks: false,
                 max_depth: None,
                 include_hidden: false,
+                capture_atime: false,
             };
 
             let stats = ordne_lib::scan_directory(db, drive_info.id, mount_path, scan_opts)?;
             let algorithm = DedupAlgorithm::from_str(&algorithm)?;
 
-            let result = refresh_duplicates_for_drive(db, drive_info.id, algorithm, rehash)?;
+            let ignore = settings.dedup_ignore()?;
+            let result = refresh_duplicates_for_drive(db, drive_info.id, algorithm, rehash, &ignore)?;
 
             println!("{} Dedup refresh complete", style("✓").green());
             println!("  Files scanned: {}", stats.files_scanned);
             println!("  Bytes scanned: {}", stats.bytes_scanned);
             println!("  Files hashed: {}", result.files_hashed);
             println!("  Files skipped: {}", result.files_skipped);
+            if result.files_volatile > 0 {
+                println!("  Files still changing (not hashed): {}", result.files_volatile);
+            }
+            if result.files_ignored > 0 {
+                println!("  Files ignored ([dedup] ignore): {}", result.files_ignored);
+            }
             println!("  Groups created: {}", result.groups_created);
             println!("  Duplicate files assigned: {}", result.duplicate_files_assigned);
             Ok(())
         }
+        DedupSubcommand::Estimate { drive, sample } => {
+            let drive_info = db.get_drive(&drive)?
+                .ok_or_else(|| ordne_lib::OrdneError::DriveNotFound(drive.clone()))?;
+            if !drive_info.is_online {
+                return Err(ordne_lib::OrdneError::Config(format!(
+                    "Drive is offline: {}",
+                    drive
+                )));
+            }
+
+            let options = EstimateOptions { sample_groups: sample, ..Default::default() };
+            let estimate = estimate_duplicate_waste(db, drive_info.id, options)?;
+
+            println!("{} Duplicate estimate for {}", style(">>>").cyan(), style(&drive).bold());
+            println!("  Files: {} ({})", estimate.files, format_bytes(estimate.total_bytes));
+            println!(
+                "  Same-size groups: {} (at most {} duplicated)",
+                estimate.candidate_groups,
+                format_bytes(estimate.candidate_bytes)
+            );
+            println!(
+                "  Sampled: {} groups, {} files, {} read",
+                estimate.sampled_groups,
+                estimate.files_sampled,
+                format_bytes(estimate.bytes_read as i64)
+            );
+            if estimate.exact {
+                println!("  Duplicate waste: {}", style(format_bytes(estimate.estimated_waste_bytes)).bold());
+            } else {
+                println!(
+                    "  Duplicate waste: about {} (95% confidence: {} - {})",
+                    style(format_bytes(estimate.estimated_waste_bytes)).bold(),
+                    format_bytes(estimate.lower_bound_bytes),
+                    format_bytes(estimate.upper_bound_bytes)
+                );
+            }
+            if estimate.estimated_waste_bytes > 0 {
+                println!(
+                    "\n{} Run 'ordne dedup refresh --drive {}' to hash all files and find the duplicates",
+                    style("Tip:").cyan(),
+                    drive
+                );
+            }
+            Ok(())
+        }
+        DedupSubcommand::Review { drive, limit, preview } => {
+            review_duplicates(db, settings, drive.as_deref(), limit, preview)
+        }
+    }
+}
+
+enum ReviewDecision {
+    Keep(usize),
+    Ignore,
+    Skip,
+    Finish,
+}
+
+fn review_duplicates(
+    db: &mut SqliteDatabase,
+    settings: &Settings,
+    drive_label: Option<&str>,
+    limit: Option<usize>,
+    preview: bool,
+) -> Result<()> {
+    let drive_labels: HashMap<i64, String> = db
+        .list_drives()?
+        .into_iter()
+        .map(|d| (d.id, d.label))
+        .collect();
+
+    let drive_filter = match drive_label {
+        Some(label) => Some(
+            db.get_drive(label)?
+                .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?
+                .id,
+        ),
+        None => None,
+    };
+
+    let mut groups: Vec<(DuplicateGroup, Vec<File>)> = Vec::new();
+    for group in list_unresolved_duplicate_groups(db.conn())?
+        .into_iter()
+        .filter(|g| drive_filter.is_none_or(|id| g.drives_involved.contains(&id)))
+    {
+        if limit.is_some_and(|limit| groups.len() >= limit) {
+            break;
+        }
+        let files = list_files_by_duplicate_group(db.conn(), group.group_id)?;
+        if files.len() >= 2 {
+            groups.push((group, files));
+        }
+    }
+
+    if groups.is_empty() {
+        println!("{}", style("No unresolved duplicate groups").green());
+        return Ok(());
+    }
+
+    println!(
+        "{} Reviewing {} duplicate groups (largest waste first)\n",
+        style(">>>").cyan(),
+        style(groups.len()).bold()
+    );
+
+    let theme = ColorfulTheme::default();
+    let mut resolutions = Vec::new();
+    let mut ignored = 0;
+
+    let total = groups.len();
+    for (idx, (group, files)) in groups.into_iter().enumerate() {
+        println!(
+            "{} Group {}/{} (#{}): {} files, {} reclaimable",
+            style(">>>").cyan(),
+            idx + 1,
+            total,
+            group.group_id,
+            files.len(),
+            style(crate::util::format::format_bytes(group.total_waste_bytes)).yellow()
+        );
+        println!("  Hash: {}", style(&group.hash).dim());
+
+        for (i, file) in files.iter().enumerate() {
+            show_file(i, file, &drive_labels, preview);
+        }
+
+        match prompt_decision(&theme, &group, &files)? {
+            ReviewDecision::Keep(keeper_idx) => {
+                let keeper = files[keeper_idx].clone();
+                let duplicates: Vec<File> = files
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i != keeper_idx)
+                    .map(|(_, f)| f)
+                    .collect();
+                println!(
+                    "  {} Keeping {}, {} copies marked for removal\n",
+                    style("✓").green(),
+                    keeper.abs_path,
+                    duplicates.len()
+                );
+                resolutions.push(DedupResolution {
+                    group_id: group.group_id,
+                    keeper,
+                    duplicates,
+                    previous_original_id: group.original_id,
+                    previous_resolution: group.resolution,
+                });
+            }
+            ReviewDecision::Ignore => {
+                update_duplicate_group_resolution(db.conn(), group.group_id, "ignored")?;
+                ignored += 1;
+                println!("  {} Group ignored\n", style("·").dim());
+            }
+            ReviewDecision::Skip => println!(),
+            ReviewDecision::Finish => break,
+        }
+    }
+
+    println!("{} Review session complete", style("✓").green());
+    println!("  Groups resolved: {}", style(resolutions.len()).green());
+    println!("  Groups ignored: {}", style(ignored).yellow());
+
+    if resolutions.is_empty() {
+        return Ok(());
+    }
+
+    let reclaim: i64 = resolutions
+        .iter()
+        .flat_map(|r| r.duplicates.iter())
+        .map(|f| f.size_bytes)
+        .sum();
+
+    let create = Confirm::with_theme(&theme)
+        .with_prompt(format!(
+            "Create dedup plan for {} groups ({} reclaimable)?",
+            resolutions.len(),
+            crate::util::format::format_bytes(reclaim)
+        ))
+        .default(true)
+        .interact()?;
+
+    if !create {
+        println!("{}", style("No plan created").yellow());
+        return Ok(());
+    }
+
+    let options = PlannerOptions {
+        max_batch_size_bytes: None,
+        enforce_space_limits: false,
+        dry_run: false,
+        attribution: None,
+        ..settings.planner_options()?
+    };
+    let plan_id = db.in_transaction(|db| {
+        let plan_id = Planner::new(db, options).create_grouped_dedup_plan(&resolutions)?;
+        for resolution in &resolutions {
+            set_duplicate_group_original(db.conn(), resolution.group_id, resolution.keeper.id)?;
+            update_duplicate_group_resolution(db.conn(), resolution.group_id, "user_resolved")?;
+        }
+        Ok(plan_id)
+    })?;
+
+    println!(
+        "{} Created dedup plan #{}",
+        style("✓").green(),
+        style(plan_id).bold()
+    );
+    println!("  Review with: ordne plan show {}", plan_id);
+    println!("  Approve with: ordne plan approve {}", plan_id);
+
+    Ok(())
+}
+
+fn prompt_decision(
+    theme: &ColorfulTheme,
+    group: &DuplicateGroup,
+    files: &[File],
+) -> Result<ReviewDecision> {
+    let mut items: Vec<String> = files
+        .iter()
+        .enumerate()
+        .map(|(i, f)| format!("Keep [{}] {}", i + 1, f.abs_path))
+        .collect();
+    items.push("Ignore this group".to_string());
+    items.push("Skip for now".to_string());
+    items.push("Finish review".to_string());
+
+    let default = group
+        .original_id
+        .and_then(|id| files.iter().position(|f| f.id == id))
+        .unwrap_or(0);
+
+    let choice = Select::with_theme(theme)
+        .with_prompt("Which copy should be kept?")
+        .items(&items)
+        .default(default)
+        .interact()?;
+
+    Ok(match choice.checked_sub(files.len()) {
+        None => ReviewDecision::Keep(choice),
+        Some(0) => ReviewDecision::Ignore,
+        Some(1) => ReviewDecision::Skip,
+        Some(_) => ReviewDecision::Finish,
+    })
+}
+
+fn show_file(index: usize, file: &File, drive_labels: &HashMap<i64, String>, preview: bool) {
+    let drive = drive_labels
+        .get(&file.drive_id)
+        .map(String::as_str)
+        .unwrap_or("?");
+    let modified = file
+        .modified_at
+        .as_ref()
+        .map(crate::util::format::format_timestamp)
+        .unwrap_or_else(|| "-".to_string());
+    let marker = if file.is_original {
+        style(" (original)").green().to_string()
+    } else {
+        String::new()
+    };
+
+    println!("  [{}] {}{}", index + 1, file.abs_path, marker);
+    println!(
+        "      drive: {}  size: {}  modified: {}  category: {}",
+        drive,
+        crate::util::format::format_bytes(file.size_bytes),
+        modified,
+        file.category.as_deref().unwrap_or("-")
+    );
+
+    if preview {
+        if let Some(lines) = preview_lines(&file.abs_path) {
+            for line in lines {
+                println!("      {} {}", style("│").dim(), style(line).dim());
+            }
+        }
     }
 }
+
+/// Read the first few lines of a file if it looks like text.
+fn preview_lines(path: &str) -> Option<Vec<String>> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut buf = Vec::with_capacity(PREVIEW_BYTES);
+    file.take(PREVIEW_BYTES as u64).read_to_end(&mut buf).ok()?;
+
+    if buf.contains(&0) {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    Some(
+        text.lines()
+            .take(PREVIEW_LINES)
+            .map(|l| l.chars().take(100).collect())
+            .collect(),
+    )
+}
@@ -1,11 +1,16 @@
 use ordne_lib::{Result, OrdneError};
 use clap::Subcommand;
-use comfy_table::{Table, presets::UTF8_FULL, Cell, Color};
+use super::table::OutputTable;
+use comfy_table::{Cell, Color};
 use console::style;
 use ordne_lib::{
-    Backend, Database, DriveRole, SqliteDatabase,
-    discover_device, db::drives::register_drive,
+    AuditDatabase, AuditLogEntry, Backend, Clock, Database, DriveRole, PlanStatus, PlansDatabase,
+    SqliteDatabase, discover_device,
+    db::drives::{reconcile_mount_paths, register_drive, remap_drive_mount, set_drive_cold, set_drive_encrypted},
+    index::{find_mount_by_uuid, import_hash_manifest},
+    roles::{apply_role_change, assess_role_change},
 };
+use dialoguer::{theme::ColorfulTheme, Confirm};
 use crate::cli::helpers::get_drive_statistics;
 use std::path::PathBuf;
 
@@ -47,11 +52,59 @@ pub enum DriveCommands {
         label: String,
     },
 
+    #[command(about = "Mark drive as cold: kept offline, known from the catalog only")]
+    Cold {
+        #[arg(help = "Drive label")]
+        label: String,
+
+        #[arg(long, help = "Clear the cold mark instead")]
+        clear: bool,
+    },
+
+    #[command(about = "Mark drive as encrypted, for volumes not detected as LUKS")]
+    Encrypted {
+        #[arg(help = "Drive label")]
+        label: String,
+
+        #[arg(long, help = "Mark the drive as unencrypted instead")]
+        clear: bool,
+    },
+
+    #[command(about = "Fill in catalog hashes from an md5sum/b3sum manifest")]
+    ImportManifest {
+        #[arg(help = "Drive label")]
+        label: String,
+
+        #[arg(help = "Manifest file (<hash>  <path> per line)")]
+        manifest: PathBuf,
+    },
+
     #[command(about = "Show drive information")]
     Info {
         #[arg(help = "Drive label")]
         label: String,
     },
+
+    #[command(about = "Point a drive at a new mount path")]
+    Remap {
+        #[arg(help = "Drive label")]
+        label: String,
+
+        #[arg(help = "New mount path")]
+        path: PathBuf,
+    },
+
+    #[command(about = "Change a drive's role, after showing what it affects")]
+    SetRole {
+        #[arg(help = "Drive label")]
+        label: String,
+
+        #[arg(help = "New role: source, target, backup, or offload")]
+        role: String,
+
+        #[arg(short = 'y', long, help = "Apply without asking for confirmation")]
+        yes: bool,
+    },
 }
 
 pub fn handle_drive_command(db: &mut SqliteDatabase, action: DriveCommands, verbose: bool) -> Result<()> {
@@ -63,10 +116,68 @@ pub fn handle_drive_command(db: &mut SqliteDatabase, action: DriveCommands, verb
         DriveCommands::Remove { label } => remove_drive(db, &label),
         DriveCommands::Online { label } => set_drive_online(db, &label, true),
         DriveCommands::Offline { label } => set_drive_online(db, &label, false),
+        DriveCommands::Cold { label, clear } => set_cold(db, &label, !clear),
+        DriveCommands::Encrypted { label, clear } => set_encrypted(db, &label, !clear),
+        DriveCommands::ImportManifest { label, manifest } => import_manifest(db, &label, &manifest),
         DriveCommands::Info { label } => show_drive_info(db, &label),
+        DriveCommands::Remap { label, path } => remap_drive(db, &label, &path),
+        DriveCommands::SetRole { label, role, yes } => set_role(db, &label, &role, yes),
     }
 }
 
+/// Follow drives that were remounted at a different path, matched by UUID.
+pub fn reconcile_mounts(db: &mut SqliteDatabase) -> Result<()> {
+    for remap in reconcile_mount_paths(db, &Clock::default(), find_mount_by_uuid)? {
+        println!(
+            "{} Drive '{}' is now mounted at {} (was {})",
+            style("!").yellow(),
+            style(&remap.label).bold(),
+            remap.new_mount,
+            remap.old_mount
+        );
+    }
+    Ok(())
+}
+
+fn remap_drive(db: &mut SqliteDatabase, label: &str, path: &std::path::Path) -> Result<()> {
+    let drive = db.get_drive(label)?
+        .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+    if !path.is_dir() {
+        return Err(OrdneError::FileNotFound(path.to_path_buf()));
+    }
+
+    let new_mount = path.to_string_lossy().to_string();
+    let file_count = remap_drive_mount(db.conn(), drive.id, &new_mount)?;
+    db.log_audit(&AuditLogEntry {
+        id: 0,
+        timestamp: chrono::Utc::now(),
+        action: "drive_remapped".to_string(),
+        file_id: None,
+        plan_id: None,
+        drive_id: Some(drive.id),
+        details: Some(format!(
+            "Drive '{}' moved from {} to {}; {} files follow it",
+            label,
+            drive.mount_path.as_deref().unwrap_or("-"),
+            new_mount,
+            file_count
+        )),
+        agent_mode: None,
+        before_state: None,
+        after_state: None,
+        actor: None,
+    })?;
+
+    println!(
+        "{} Drive '{}' now at {} ({} files)",
+        style("✓").green(),
+        style(label).bold(),
+        new_mount,
+        file_count
+    );
+    Ok(())
+}
+
 fn add_drive(
     db: &mut SqliteDatabase,
     label: &str,
@@ -100,6 +211,7 @@ fn add_drive(
             total_bytes: None,
             model: None,
             serial: None,
+            is_encrypted: false,
         }
     } else {
         discover_device(path)?
@@ -107,6 +219,7 @@ fn add_drive(
 
     let conn = db.conn();
     let drive_id = register_drive(conn, label, &device_info, role, backend)?;
+    ordne_lib::undo::log_drive_added(db, &Clock::default(), drive_id, label)?;
 
     println!(
         "{} Drive '{}' added successfully (ID: {})",
@@ -125,6 +238,9 @@ fn add_drive(
         if let Some(uuid) = &device_info.uuid {
             println!("  UUID: {}", uuid);
         }
+        if device_info.is_encrypted {
+            println!("  Encrypted: LUKS");
+        }
         println!("  Role: {}", role.as_str());
         println!("  Backend: {}", backend.as_str());
     }
@@ -140,21 +256,21 @@ fn list_drives(db: &SqliteDatabase) -> Result<()> {
         return Ok(());
     }
 
-    let mut table = Table::new();
-    table.load_preset(UTF8_FULL);
-    table.set_header(vec![
-        Cell::new("Label").fg(Color::Cyan),
-        Cell::new("Role").fg(Color::Cyan),
-        Cell::new("Backend").fg(Color::Cyan),
-        Cell::new("Mount Path").fg(Color::Cyan),
-        Cell::new("Status").fg(Color::Cyan),
-        Cell::new("Files").fg(Color::Cyan),
-        Cell::new("Last Scan").fg(Color::Cyan),
+    let mut table = OutputTable::new(vec![
+        "Label",
+        "Role",
+        "Backend",
+        "Mount Path",
+        "Status",
+        "Files",
+        "Last Scan",
     ]);
 
     for drive in drives {
         let status = if drive.is_online {
             Cell::new("Online").fg(Color::Green)
+        } else if drive.is_cold {
+            Cell::new("Cold").fg(Color::Blue)
         } else {
             Cell::new("Offline").fg(Color::Red)
         };
@@ -227,6 +343,130 @@ fn set_drive_online(db: &mut SqliteDatabase, label: &str, online: bool) -> Resul
         style(label).bold(),
         if online { style("online").green() } else { style("offline").red() }
     );
+
+    if online {
+        let queued = db.list_plans(Some(PlanStatus::Queued))?;
+        if !queued.is_empty() {
+            let ids: Vec<String> = queued.iter().map(|p| p.id.to_string()).collect();
+            println!(
+                "  Queued plans waiting on offline drives: {} (run with {})",
+                ids.join(", "),
+                style("ordne migrate <id>").cyan()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn set_cold(db: &mut SqliteDatabase, label: &str, cold: bool) -> Result<()> {
+    let drive = db.get_drive(label)?
+        .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+    set_drive_cold(db.conn(), drive.id, cold)?;
+
+    if !cold {
+        println!("{} Drive '{}' is no longer cold", style("✓").green(), style(label).bold());
+        return Ok(());
+    }
+
+    println!(
+        "{} Drive '{}' marked as {}; its catalog stays in queries and dedup",
+        style("✓").green(),
+        style(label).bold(),
+        style("cold").blue()
+    );
+
+    let unhashed: i64 = db.conn().query_row(
+        "SELECT COUNT(*) FROM files WHERE drive_id = ?1 AND md5_hash IS NULL AND blake3_hash IS NULL",
+        [drive.id],
+        |row| row.get(0)
+    )?;
+    if unhashed > 0 {
+        println!(
+            "  {} {} files have no hash and are invisible to dedup; hash them first or use {}",
+            style("Warning:").yellow(),
+            unhashed,
+            style("ordne drive import-manifest").cyan()
+        );
+    }
+    Ok(())
+}
+
+fn set_role(db: &mut SqliteDatabase, label: &str, role: &str, yes: bool) -> Result<()> {
+    let drive = db.get_drive(label)?
+        .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+    let change = assess_role_change(db, &drive, DriveRole::from_str(role)?)?;
+
+    println!(
+        "{} Drive '{}': {} -> {}",
+        style(">>>").cyan(),
+        style(label).bold(),
+        change.from.as_str(),
+        style(change.to.as_str()).bold()
+    );
+    for warning in &change.warnings {
+        println!("  {} {}", style("Warning:").yellow(), warning);
+    }
+
+    if !change.warnings.is_empty()
+        && !yes
+        && !Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Change the role?")
+            .default(false)
+            .interact()?
+    {
+        println!("{}", style("Role unchanged").yellow());
+        return Ok(());
+    }
+
+    apply_role_change(db, &change, &Clock::default(), None)?;
+    println!(
+        "{} Drive '{}' is now {}",
+        style("✓").green(),
+        style(label).bold(),
+        change.to.as_str()
+    );
+    if !change.original_moves.is_empty() {
+        println!("  Moved the original of {} duplicate groups", change.original_moves.len());
+    }
+    Ok(())
+}
+
+fn set_encrypted(db: &mut SqliteDatabase, label: &str, encrypted: bool) -> Result<()> {
+    let drive = db.get_drive(label)?
+        .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+    set_drive_encrypted(db.conn(), drive.id, encrypted)?;
+
+    println!(
+        "{} Drive '{}' marked as {}",
+        style("✓").green(),
+        style(label).bold(),
+        if encrypted { "encrypted" } else { "unencrypted" }
+    );
+    Ok(())
+}
+
+fn import_manifest(db: &mut SqliteDatabase, label: &str, manifest: &std::path::Path) -> Result<()> {
+    let drive = db.get_drive(label)?
+        .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+    let result = import_hash_manifest(db, &drive, manifest)?;
+
+    println!(
+        "{} Updated hashes for {} files on '{}'",
+        style("✓").green(),
+        result.files_updated,
+        style(label).bold()
+    );
+    if !result.unmatched.is_empty() {
+        println!(
+            "  {} {} manifest entries match no catalogued file, e.g. {}",
+            style("Warning:").yellow(),
+            result.unmatched.len(),
+            result.unmatched[0]
+        );
+    }
+    if result.lines_skipped > 0 {
+        println!("  Skipped {} unparseable lines", result.lines_skipped);
+    }
     Ok(())
 }
 
@@ -256,9 +496,12 @@ fn show_drive_info(db: &SqliteDatabase, label: &str) -> Result<()> {
     if let Some(total_bytes) = drive.total_bytes {
         println!("  Capacity: {}", crate::util::format::format_bytes(total_bytes));
     }
+    println!("  Encrypted: {}", if drive.is_encrypted { style("Yes").green() } else { style("No").dim() });
 
     println!("  Status: {}", if drive.is_online {
         style("Online").green()
+    } else if drive.is_cold {
+        style("Cold").blue()
     } else {
         style("Offline").red()
     });
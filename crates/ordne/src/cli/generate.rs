@@ -0,0 +1,28 @@
+use super::Cli;
+use clap::CommandFactory;
+use clap_complete::Shell;
+use console::style;
+use ordne_lib::Result;
+use std::path::Path;
+
+/// Write a completion script for `shell` to stdout.
+pub fn print_completions(shell: Shell) -> Result<()> {
+    let mut cmd = Cli::command();
+    clap_complete::generate(shell, &mut cmd, "ordne", &mut std::io::stdout());
+    Ok(())
+}
+
+/// Write the top-level man page to stdout, or `ordne.1` plus one page per
+/// subcommand (`ordne-plan-create.1`, ...) into `out_dir`.
+pub fn print_man(out_dir: Option<&Path>) -> Result<()> {
+    let cmd = Cli::command();
+    match out_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            clap_mangen::generate_to(cmd, dir)?;
+            eprintln!("{} Man pages written to {}", style("✓").green(), dir.display());
+        }
+        None => clap_mangen::Man::new(cmd).render(&mut std::io::stdout())?,
+    }
+    Ok(())
+}
@@ -9,12 +9,12 @@ pub fn list_files_by_drive(db: &SqliteDatabase, drive_id: i64) -> Result<Vec<Fil
                 mime_type, is_symlink, symlink_target, git_remote_url,
                 category, subcategory, target_path, target_drive_id,
                 priority, duplicate_group, is_original, rmlint_type, status,
-                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at
-         FROM files WHERE drive_id = ?1 ORDER BY path",
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files WHERE drive_id = ?1 ORDER BY path",
     )?;
 
     let files = stmt
-        .query_map([drive_id], |row| parse_file_row(row))?
+        .query_map([drive_id], parse_file_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
     Ok(files)
@@ -96,9 +96,14 @@ fn parse_file_row(row: &rusqlite::Row) -> rusqlite::Result<File> {
             .get::<_, Option<String>>(10)?
             .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
             .map(|dt| dt.with_timezone(&Utc)),
+        accessed_at: row
+            .get::<_, Option<String>>(33)?
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
         inode: row.get(11)?,
         device_num: row.get(12)?,
         nlinks: row.get(13)?,
+        owner_uid: row.get(34)?,
         mime_type: row.get(14)?,
         is_symlink: row.get(15)?,
         symlink_target: row.get(16)?,
@@ -0,0 +1,151 @@
+use crate::Result;
+use super::table::OutputTable;
+use comfy_table::{Cell, Color};
+use console::style;
+use ordne_lib::{SqliteDatabase, VerificationDatabase, db::inbox::Inbox};
+
+pub fn handle_inbox_command(
+    db: &mut SqliteDatabase,
+    ack: Vec<i64>,
+    ack_all: bool,
+) -> Result<()> {
+    if ack_all {
+        let count = db.acknowledge_all_verification_failures()?;
+        println!("{} Acknowledged {} verification failures", style("✓").green(), count);
+        return Ok(());
+    }
+
+    if !ack.is_empty() {
+        for id in ack {
+            if db.acknowledge_verification_failure(id)? {
+                println!("{} Acknowledged failure {}", style("✓").green(), id);
+            } else {
+                println!("{} No open failure with ID {}", style("!").yellow(), id);
+            }
+        }
+        return Ok(());
+    }
+
+    let inbox = Inbox::load(db)?;
+
+    println!("\n{}", style("Inbox").bold().cyan());
+    println!("{}\n", style("═".repeat(60)).dim());
+
+    if inbox.is_empty() {
+        println!("{}", style("Nothing needs your attention").green());
+        return Ok(());
+    }
+
+    show_draft_plans(&inbox);
+    show_suggestions(&inbox);
+    show_verification_failures(&inbox);
+    show_due_verification_jobs(&inbox);
+
+    Ok(())
+}
+
+fn show_draft_plans(inbox: &Inbox) {
+    if inbox.draft_plans.is_empty() {
+        return;
+    }
+
+    println!("{} ({})", style("Draft plans").bold(), inbox.draft_plans.len());
+
+    let mut table = OutputTable::new(vec!["ID", "Created", "Created By", "Files", "Size", "Description"]);
+
+    for plan in &inbox.draft_plans {
+        table.add_row(vec![
+            Cell::new(plan.id),
+            Cell::new(plan.created_at.format("%Y-%m-%d %H:%M")),
+            Cell::new(plan.created_by.as_deref().unwrap_or("-")),
+            Cell::new(plan.total_files),
+            Cell::new(crate::util::format::format_bytes(plan.total_bytes)),
+            Cell::new(plan.description.as_deref().unwrap_or("")),
+        ]);
+    }
+
+    println!("{}", table);
+    println!("  Review with 'ordne plan show <id>', then 'ordne plan approve <id>'\n");
+}
+
+fn show_suggestions(inbox: &Inbox) {
+    if inbox.suggestions.is_empty() {
+        return;
+    }
+
+    println!(
+        "{} ({})",
+        style("Classification suggestions").bold(),
+        inbox.pending_suggestion_count()
+    );
+
+    let mut table = OutputTable::new(vec!["Category", "Suggested By", "Files", "Min Confidence"]);
+
+    for group in &inbox.suggestions {
+        let category = match &group.subcategory {
+            Some(sub) => format!("{}/{}", group.category, sub),
+            None => group.category.clone(),
+        };
+        table.add_row(vec![
+            Cell::new(category),
+            Cell::new(group.suggested_by.as_deref().unwrap_or("-")),
+            Cell::new(group.count),
+            Cell::new(format!("{:.0}%", group.min_confidence * 100.0)),
+        ]);
+    }
+
+    println!("{}", table);
+    println!("  Review with 'ordne classify --suggestions'\n");
+}
+
+fn show_verification_failures(inbox: &Inbox) {
+    if inbox.verification_failures.is_empty() {
+        return;
+    }
+
+    println!(
+        "{} ({})",
+        style("Verification failures").bold(),
+        inbox.verification_failures.len()
+    );
+
+    let mut table = OutputTable::new(vec!["ID", "Detected", "Reason", "Plan", "Path"]);
+
+    for failure in &inbox.verification_failures {
+        table.add_row(vec![
+            Cell::new(failure.id),
+            Cell::new(failure.detected_at.format("%Y-%m-%d %H:%M")),
+            Cell::new(&failure.reason).fg(Color::Red),
+            Cell::new(failure.plan_id.map(|id| id.to_string()).unwrap_or_else(|| "-".to_string())),
+            Cell::new(&failure.path),
+        ]);
+    }
+
+    println!("{}", table);
+    println!("  Acknowledge with 'ordne inbox --ack <id>' or 'ordne inbox --ack-all'\n");
+}
+
+fn show_due_verification_jobs(inbox: &Inbox) {
+    if inbox.due_verification_jobs.is_empty() {
+        return;
+    }
+
+    println!(
+        "{} ({})",
+        style("Plan verifications due").bold(),
+        inbox.due_verification_jobs.len()
+    );
+
+    let mut table = OutputTable::new(vec!["Job", "Plan", "Due"]);
+
+    for job in &inbox.due_verification_jobs {
+        table.add_row(vec![
+            Cell::new(job.id),
+            Cell::new(job.plan_id),
+            Cell::new(job.due_at.format("%Y-%m-%d %H:%M")),
+        ]);
+    }
+
+    println!("{}", table);
+    println!("  Run them with 'ordne verify --due'\n");
+}
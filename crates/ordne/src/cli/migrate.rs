@@ -1,15 +1,26 @@
 use ordne_lib::{Result, OrdneError};
+use ordne_lib::config::Settings;
 use console::style;
 use ordne_lib::{
     MigrationEngine, PlansDatabase, RollbackEngine, SqliteDatabase,
-    EngineOptions,
+    EngineOptions, ExecutionWindow, IdlePolicy, StepStatus,
+    migrate::with_plan_drives,
 };
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_migrate_command(
     db: &mut SqliteDatabase,
+    settings: &Settings,
     plan_id: i64,
     dry_run: bool,
     execute: bool,
+    parallel: usize,
+    per_device: usize,
+    verify_after: Option<String>,
+    window: Option<&str>,
+    bwlimit: Option<&str>,
+    when_idle: Option<&str>,
+    max_load: Option<f64>,
     _verbose: bool,
 ) -> Result<()> {
     if !execute && !dry_run {
@@ -18,36 +29,145 @@ pub fn handle_migrate_command(
         ));
     }
 
-    let _plan = db.get_plan(plan_id)?
+    let verify_after = verify_after
+        .map(|s| {
+            crate::util::units::parse_duration(&s)
+                .map_err(|e| OrdneError::Config(format!("Invalid --verify-after: {}", e)))
+        })
+        .transpose()?;
+    let window = window.map(ExecutionWindow::parse).transpose()?;
+    let bandwidth_limit_kbps = bwlimit
+        .map(|s| {
+            crate::util::units::parse_bytes(s)
+                .map(|b| (b as u64 / 1024).max(1))
+                .map_err(|e| OrdneError::Config(format!("Invalid --bwlimit: {}", e)))
+        })
+        .transpose()?;
+    let min_idle = when_idle
+        .map(|s| {
+            crate::util::units::parse_duration(s)
+                .map(|d| d.to_std().unwrap_or_default())
+                .map_err(|e| OrdneError::Config(format!("Invalid --when-idle: {}", e)))
+        })
+        .transpose()?;
+    let idle = (min_idle.is_some() || max_load.is_some()).then(|| IdlePolicy::new(min_idle, max_load));
+
+    let plan = db.get_plan(plan_id)?
         .ok_or(OrdneError::PlanNotFound(plan_id))?;
 
+    let dry_run = if !dry_run && settings.safe_mode() {
+        println!(
+            "{} Safe mode is on ({} or safe_mode in ordne.toml); simulating instead",
+            style("!").yellow(),
+            ordne_lib::config::SAFE_MODE_ENV
+        );
+        true
+    } else {
+        dry_run
+    };
+
     println!(
         "{} {} migration plan #{}...",
         style(">>>").cyan(),
         if dry_run { "Simulating" } else { "Executing" },
         plan_id
     );
+    if plan.completed_files > 0 {
+        println!(
+            "  Resuming at {} of {} files ({} of {} done)",
+            plan.completed_files,
+            plan.total_files,
+            crate::util::format::format_bytes(plan.completed_bytes),
+            crate::util::format::format_bytes(plan.total_bytes)
+        );
+    }
+    if let Some(window) = window {
+        println!("  Steps start only between {} (local time)", window);
+    }
+    if let Some(idle) = &idle {
+        let mut conditions = Vec::new();
+        if let Some(min_idle) = idle.min_idle {
+            conditions.push(format!("after {}s without input", min_idle.as_secs()));
+        }
+        if let Some(max_load) = idle.max_load {
+            conditions.push(format!("while the load average is at most {}", max_load));
+        }
+        println!("  Steps start only {}", conditions.join(" and "));
+    }
 
     let options = EngineOptions {
         dry_run,
         verify_hashes: true,
         retry_count: 3,
         enforce_safety: true,
+        max_parallel_steps: parallel,
+        per_device_concurrency: per_device,
+        attribution: None,
+        verify_after,
+        window,
+        bandwidth_limit_kbps,
+        idle,
+        safe_mode: settings.safe_mode(),
+        ..Default::default()
     };
 
-    let mut engine = MigrationEngine::new(db, options);
-    engine.execute_plan(plan_id)?;
+    // Drives listed under [automount] are mounted for a real run only.
+    let automount = if dry_run { Default::default() } else { settings.automount()? };
+    let clock = options.clock.clone();
+    let result = with_plan_drives(db, &[plan_id], &automount, &clock, |db| {
+        MigrationEngine::new(db, options).execute_plan(plan_id)
+    });
+    match result {
+        Err(e @ OrdneError::PlanQueued { .. }) => {
+            println!("Connect the drives, mark them with 'ordne drive online <label>' and run this again");
+            return Err(e);
+        }
+        result => result?,
+    }
+
+    let volatile: Vec<String> = db
+        .get_steps_for_plan(plan_id)?
+        .into_iter()
+        .filter(|s| s.status == StepStatus::Volatile)
+        .map(|s| s.source_path)
+        .collect();
+    if !volatile.is_empty() {
+        println!("\n{} Migration paused: {} files kept changing while being read",
+            style("!").yellow(),
+            volatile.len()
+        );
+        for path in volatile.iter().take(10) {
+            println!("  {}", path);
+        }
+        if volatile.len() > 10 {
+            println!("  ... and {} more", volatile.len() - 10);
+        }
+        println!("  Once they are finished (e.g. downloads complete), run 'ordne migrate {} --execute' again", plan_id);
+        return Ok(());
+    }
 
     println!("\n{} Migration {}", 
         style("✓").green(),
         if dry_run { "simulation complete" } else { "complete" }
     );
 
+    if !dry_run {
+        super::plan::show_space_reconciliation(db, plan_id)?;
+    }
+
+    if let (Some(delay), false) = (verify_after, dry_run) {
+        println!(
+            "  Destinations will be re-verified after {}; run 'ordne verify --due' then",
+            crate::util::format::format_duration(delay.to_std().unwrap_or_default())
+        );
+    }
+
     Ok(())
 }
 
 pub fn handle_rollback_command(
     db: &mut SqliteDatabase,
+    settings: &Settings,
     plan_id: i64,
     _verbose: bool,
 ) -> Result<()> {
@@ -60,7 +180,7 @@ pub fn handle_rollback_command(
         plan_id
     );
 
-    let mut engine = RollbackEngine::new(db, false);
+    let mut engine = RollbackEngine::new(db, false).with_safe_mode(settings.safe_mode());
     
     if !engine.can_rollback(plan_id)? {
         return Err(OrdneError::Migration(
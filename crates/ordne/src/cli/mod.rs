@@ -1,3 +1,4 @@
+pub mod bench;
 pub mod drive;
 pub mod scan;
 pub mod status;
@@ -11,9 +12,15 @@ pub mod policy;
 pub mod run_policy;
 pub mod rmlint;
 pub mod dedup;
+pub mod inbox;
+pub mod undo;
+pub mod whatif;
+pub mod summary;
+pub mod table;
+pub mod generate;
 mod helpers;
 
-use ordne_lib::{Config, Database, Result, SqliteDatabase};
+use ordne_lib::{config::Settings, Attribution, Config, Database, Result, SqliteDatabase};
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
@@ -31,10 +38,17 @@ pub struct Cli {
     #[arg(long, short = 'q', global = true, help = "Suppress non-error output")]
     pub quiet: bool,
 
+    #[arg(long, global = true, help = "Disable coloured output")]
+    pub no_color: bool,
+
+    #[arg(long, global = true, help = "Print elapsed time, rows touched, bytes processed and database growth when done")]
+    pub summary: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
     #[command(about = "Manage drives")]
@@ -43,8 +57,11 @@ pub enum Commands {
         action: drive::DriveCommands,
     },
 
-    #[command(about = "Scan drives for files")]
+    #[command(about = "Scan drives for files", args_conflicts_with_subcommands = true)]
     Scan {
+        #[command(subcommand)]
+        action: Option<scan::ScanCommands>,
+
         #[arg(help = "Drive label to scan")]
         drive_label: Option<String>,
 
@@ -53,6 +70,17 @@ pub enum Commands {
 
         #[arg(long, help = "Scan all online drives")]
         all: bool,
+
+        #[arg(long, help = "Record access times (skipped on noatime mounts)")]
+        atime: bool,
+
+        #[arg(
+            long,
+            requires = "drive_label",
+            conflicts_with_all = ["path", "all"],
+            help = "Re-walk only the paths earlier scans could not read"
+        )]
+        retry_errors: bool,
     },
 
     #[command(about = "Show system status")]
@@ -74,6 +102,12 @@ pub enum Commands {
 
         #[arg(long, help = "Run automatic classification without interaction")]
         auto: bool,
+
+        #[arg(long, help = "Review pending classification suggestions from agents")]
+        suggestions: bool,
+
+        #[arg(long, help = "Only review suggestions at or above this confidence (0.0-1.0)")]
+        min_confidence: Option<f64>,
     },
 
     #[command(about = "Manage migration plans")]
@@ -92,6 +126,27 @@ pub enum Commands {
 
         #[arg(long, help = "Execute the migration (required for actual execution)")]
         execute: bool,
+
+        #[arg(long, default_value_t = 1, help = "Maximum number of steps to run at once")]
+        parallel: usize,
+
+        #[arg(long, default_value_t = 1, help = "Maximum concurrent steps per physical device")]
+        per_device: usize,
+
+        #[arg(long, help = "Re-verify destinations this long after completion, e.g. 24h or 7d")]
+        verify_after: Option<String>,
+
+        #[arg(long, help = "Only start steps within this daily window, e.g. 01:00-06:00; wait outside it")]
+        window: Option<String>,
+
+        #[arg(long, help = "Cap copy throughput per second, e.g. 20MB")]
+        bwlimit: Option<String>,
+
+        #[arg(long, help = "Only start steps after this long without keyboard or mouse input, e.g. 10m")]
+        when_idle: Option<String>,
+
+        #[arg(long, help = "Only start steps while the 1-minute load average is at most this")]
+        max_load: Option<f64>,
     },
 
     #[command(about = "Rollback a migration")]
@@ -100,14 +155,44 @@ pub enum Commands {
         plan_id: i64,
     },
 
-    #[command(about = "Verify file hashes")]
+    #[command(about = "Verify file hashes", args_conflicts_with_subcommands = true)]
     Verify {
+        #[command(subcommand)]
+        action: Option<verify::VerifyCommands>,
+
         #[arg(long, help = "Drive label to verify")]
         drive: Option<String>,
+
+        #[arg(long, conflicts_with = "drive", help = "Run scheduled plan verifications that are due")]
+        due: bool,
+
+        #[arg(long, conflicts_with_all = ["drive", "due"], help = "Re-verify a completed plan's destinations now")]
+        plan: Option<i64>,
     },
 
     #[command(about = "Generate report")]
-    Report,
+    Report {
+        #[command(subcommand)]
+        action: Option<report::ReportSubcommand>,
+    },
+
+    #[command(about = "List plans, suggestions and failures awaiting human action")]
+    Inbox {
+        #[arg(long, value_name = "ID", help = "Acknowledge a verification failure")]
+        ack: Vec<i64>,
+
+        #[arg(long, help = "Acknowledge all open verification failures")]
+        ack_all: bool,
+    },
+
+    #[command(about = "Revert the last classification batch, plan creation or drive add")]
+    Undo {
+        #[arg(long, default_value = "1h", help = "Only undo an operation at most this old (e.g. 30m, 1d)")]
+        within: String,
+
+        #[arg(long, short = 'y', help = "Do not ask for confirmation")]
+        yes: bool,
+    },
 
     #[command(about = "Export data")]
     Export {
@@ -136,25 +221,103 @@ pub enum Commands {
         execute: bool,
     },
 
+    #[command(about = "Preview plans or a policy on a copy of the catalog")]
+    WhatIf {
+        #[arg(long = "plan", value_name = "ID", help = "Plan to apply (repeatable)")]
+        plans: Vec<i64>,
+
+        #[arg(long, help = "Policy whose plans to create and apply")]
+        policy: Option<PathBuf>,
+
+        #[arg(long, help = "Print the report as JSON")]
+        json: bool,
+    },
+
     #[command(about = "Import rmlint JSON output")]
     Rmlint {
         #[command(subcommand)]
         action: rmlint::RmlintSubcommand,
     },
 
-    #[command(about = "Duplicate discovery workflows")]
+    #[command(about = "Duplicate discovery workflows", visible_alias = "dedupe")]
     Dedup {
         #[command(subcommand)]
         action: dedup::DedupSubcommand,
     },
+
+    #[command(about = "Measure how fast this machine can do ordne's work")]
+    Bench {
+        #[command(subcommand)]
+        action: bench::BenchSubcommand,
+    },
+
+    #[command(about = "Print a shell completion script")]
+    Completions {
+        #[arg(value_enum, help = "Shell to generate completions for")]
+        shell: clap_complete::Shell,
+    },
+
+    #[command(about = "Print the man page")]
+    Man {
+        #[arg(long, value_name = "DIR", help = "Write ordne.1 and a page per subcommand into this directory")]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+impl Commands {
+    /// Whether the command may read files to hash them, and so needs the
+    /// `[hashing]` settings.
+    pub fn hashes_files(&self) -> bool {
+        matches!(
+            self,
+            Commands::Scan { .. }
+                | Commands::Plan { .. }
+                | Commands::Migrate { .. }
+                | Commands::Rollback { .. }
+                | Commands::Verify { .. }
+                | Commands::Policy { .. }
+                | Commands::RunPolicy { .. }
+                | Commands::Dedup { .. }
+                | Commands::Bench { .. }
+        )
+    }
+}
+
+/// Name recorded in the jobs table: the subcommand path, e.g. `plan approve`.
+pub fn command_name(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut matches = matches;
+    while let Some((name, sub)) = matches.subcommand() {
+        names.push(name);
+        matches = sub;
+    }
+    names.join(" ")
+}
+
+/// The command-line arguments as a JSON array, recorded with the job.
+pub fn command_parameters() -> Option<String> {
+    let args: Vec<String> = std::env::args_os()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    serde_json::to_string(&args).ok()
+}
+
+/// Writes made from the command line are attributed to the login user.
+fn command_line_attribution() -> Attribution {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    Attribution::manual(user)
 }
 
-pub fn init_database(db_path: Option<PathBuf>) -> Result<SqliteDatabase> {
-    let config = Config::new(db_path)?;
+pub fn init_database(config: &Config, settings: &Settings) -> Result<SqliteDatabase> {
     config.ensure_db_directory()?;
 
     let mut db = SqliteDatabase::open(&config.db_path)?;
     db.initialize()?;
+    ordne_lib::config::attach_ledger(&mut db, &config.db_path, settings)?;
+    db.set_attribution(command_line_attribution());
 
     Ok(db)
 }
@@ -1,93 +1,240 @@
 use ordne_lib::{Result, OrdneError, Database};
+use ordne_lib::config::Settings;
 use console::style;
 use ordne_lib::{
-    Planner, PlannerOptions, PlansDatabase, PlanStatus, SqliteDatabase,
-    MigrationStep,
+    CrossDriveDedupOptions, OffloadLayout, Planner, PlannerOptions, PlansDatabase, PlanStatus, SqliteDatabase,
+    VerificationDatabase, migrate::{cleanup_plan, find_repairs, plan_encryption_warnings, reconcile_plan, ExecutionLog},
+    PlacementConstraint, PlacementTarget, StepOrdering, StepQuery,
+    db::duplicates::list_cross_drive_group_files,
+    db::owners::resolve_owner,
     db::files::{get_files_by_category, get_files_by_category_and_drive, list_files_by_duplicate_group},
+    index::resolve_file_list,
+    Clock,
 };
-use comfy_table::{Table, Cell, presets::UTF8_FULL};
+use std::path::{Path, PathBuf};
+use super::table::OutputTable;
+use comfy_table::Cell;
 
 pub fn handle_plan_command(
     db: &mut SqliteDatabase,
+    settings: &Settings,
     subcommand: PlanSubcommand,
     verbose: bool,
 ) -> Result<()> {
+    let mut subcommand = subcommand;
+    if let PlanSubcommand::Create { plan_type, action, from_list, .. } = &mut subcommand {
+        *plan_type = plan_type.take().or(action.take());
+        let kind = plan_type.as_deref().unwrap_or_default();
+        if from_list.is_some() && kind != "migrate" && kind != "offload" {
+            return Err(OrdneError::Config(
+                "--from-list only applies to migrate and offload plans".to_string()
+            ));
+        }
+    }
+
     match subcommand {
-        PlanSubcommand::Create { plan_type, source_drive, target_drive, category_filter, duplicate_group, original_file } => {
+        PlanSubcommand::Create { plan_type, keep_roles, remove_roles, hardlink, min_critical_copies, .. }
+            if plan_type.as_deref() == Some("cross-drive-dedup") =>
+        {
+            create_cross_drive_dedup_plans(db, settings, &keep_roles, &remove_roles, hardlink, min_critical_copies)
+        }
+        PlanSubcommand::Create { plan_type, failure, .. } if plan_type.as_deref() == Some("recopy") => {
+            create_recopy_plan(db, &failure)
+        }
+        PlanSubcommand::Create { plan_type, source_drive, target_drive, category_filter, from_list, owner, duplicate_group, original_file, min_critical_copies, keep_together, batch_size, layout, order, .. } => {
             create_plan(
                 db,
-                &plan_type,
+                settings,
+                plan_type.as_deref().unwrap_or_default(),
                 source_drive.as_deref(),
-                target_drive.as_deref(),
+                &target_drive,
+                &keep_together,
+                batch_size.as_deref(),
+                &layout,
+                &order,
                 category_filter.as_deref(),
+                from_list.as_deref(),
+                owner.as_deref(),
                 duplicate_group,
                 original_file,
+                min_critical_copies,
                 verbose,
             )
         }
         PlanSubcommand::List { status_filter } => {
             list_plans(db, status_filter.as_deref())
         }
-        PlanSubcommand::Show { id } => {
-            show_plan(db, id)
+        PlanSubcommand::Show { id, steps, offset, limit, dir, by_directory } => {
+            let view = if by_directory {
+                PlanView::Directories
+            } else if steps || dir.is_some() {
+                PlanView::Steps
+            } else {
+                PlanView::Summary
+            };
+            show_plan(db, id, StepQuery { offset, limit, directory: dir }, view)
         }
         PlanSubcommand::Approve { id } => {
             approve_plan(db, id)
         }
+        PlanSubcommand::Log { id, format, output } => {
+            export_plan_log(db, id, &format, output)
+        }
+        PlanSubcommand::Cleanup { id, dry_run } => {
+            cleanup_aborted_plan(db, settings, id, dry_run)
+        }
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(clap::Subcommand)]
 pub enum PlanSubcommand {
     Create {
-        plan_type: String,
+        #[arg(required_unless_present = "action")]
+        plan_type: Option<String>,
+        #[arg(long, conflicts_with = "plan_type", help = "Plan type, as an alternative to the positional argument")]
+        action: Option<String>,
         #[arg(long, help = "Source drive label")]
         source_drive: Option<String>,
-        #[arg(long, help = "Target drive label")]
-        target_drive: Option<String>,
+        #[arg(long, alias = "target", value_delimiter = ',', help = "Target drive label; several labels form a pool (migrate)")]
+        target_drive: Vec<String>,
         #[arg(long, help = "Category filter")]
         category_filter: Option<String>,
+        #[arg(long, conflicts_with = "category_filter", help = "File of paths (one per line, or a CSV with a path column) to plan instead of a category (migrate, offload)")]
+        from_list: Option<PathBuf>,
+        #[arg(long, help = "Only files owned by this user, name or uid (delete-trash, dedup, migrate, offload)")]
+        owner: Option<String>,
         #[arg(long, help = "Duplicate group ID (dedup plans)")]
         duplicate_group: Option<i64>,
         #[arg(long, help = "Original file ID to keep (dedup plans)")]
         original_file: Option<i64>,
+        #[arg(long, value_delimiter = ',', help = "Drive roles whose copies are kept (cross-drive-dedup, default: source,backup)")]
+        keep_roles: Vec<String>,
+        #[arg(long, value_delimiter = ',', help = "Drive roles whose copies are removed (cross-drive-dedup, default: offload)")]
+        remove_roles: Vec<String>,
+        #[arg(long, help = "Hardlink extra copies on kept drives (cross-drive-dedup)")]
+        hardlink: bool,
+        #[arg(long, default_value = "2", help = "Copies to keep of content with any Critical copy (dedup, cross-drive-dedup)")]
+        min_critical_copies: usize,
+        #[arg(long, value_delimiter = ',', help = "Keep a category on one target drive: category, category/subcategory or category/* (migrate)")]
+        keep_together: Vec<String>,
+        #[arg(long, help = "Copy and delete in batches of this size, e.g. 20GB (offload)")]
+        batch_size: Option<String>,
+        #[arg(long, default_value = "mirror", help = "Offload layout: mirror (source paths) or cas (objects/<hash>, with an index)")]
+        layout: String,
+        #[arg(long, default_value = "priority", help = "Step order: priority (critical files first, trash last) or planned")]
+        order: String,
+        #[arg(long, value_delimiter = ',', help = "Verification failure IDs to repair (recopy, default: all open failures)")]
+        failure: Vec<i64>,
     },
     List {
         status_filter: Option<String>,
     },
     Show {
         id: i64,
+        #[arg(long, help = "List individual steps instead of the summary")]
+        steps: bool,
+        #[arg(long, default_value = "0", help = "Skip this many steps")]
+        offset: i64,
+        #[arg(long, default_value = "10", help = "Number of steps to show")]
+        limit: i64,
+        #[arg(long, help = "Only show steps for files in this directory (implies --steps)")]
+        dir: Option<String>,
+        #[arg(long, help = "List source directories with step counts instead of steps")]
+        by_directory: bool,
     },
     Approve {
         id: i64,
     },
+    Log {
+        id: i64,
+        #[arg(long, default_value = "json", help = "Output format (json, csv)")]
+        format: String,
+        #[arg(long, short = 'o', help = "Output file path")]
+        output: Option<std::path::PathBuf>,
+    },
+    #[command(about = "Remove partial files of an aborted plan and reconcile its steps")]
+    Cleanup {
+        id: i64,
+        #[arg(long, help = "Only report what would be removed and reconciled")]
+        dry_run: bool,
+    },
+}
+
+fn export_plan_log(
+    db: &SqliteDatabase,
+    plan_id: i64,
+    format: &str,
+    output: Option<std::path::PathBuf>,
+) -> Result<()> {
+    let log = ExecutionLog::load(db, plan_id)?;
+    let content = match format.to_lowercase().as_str() {
+        "json" => log.to_json()?,
+        "csv" => log.to_csv(),
+        _ => {
+            return Err(OrdneError::Config(format!(
+                "Unsupported log format '{}'. Use 'json' or 'csv'",
+                format
+            )))
+        }
+    };
+
+    if let Some(path) = output {
+        std::fs::write(&path, content)?;
+        println!(
+            "{} Execution log for plan {} ({} steps) exported to {}",
+            style("✓").green(),
+            plan_id,
+            log.steps.len(),
+            path.display()
+        );
+    } else {
+        print!("{}", content);
+    }
+
+    Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn create_plan(
     db: &mut SqliteDatabase,
+    settings: &Settings,
     plan_type: &str,
     source_drive: Option<&str>,
-    target_drive: Option<&str>,
+    target_drives: &[String],
+    keep_together: &[String],
+    batch_size: Option<&str>,
+    layout: &str,
+    order: &str,
     category_filter: Option<&str>,
+    from_list: Option<&Path>,
+    owner: Option<&str>,
     duplicate_group: Option<i64>,
     original_file: Option<i64>,
+    min_critical_copies: usize,
     verbose: bool,
 ) -> Result<()> {
     if verbose {
         println!("{} Creating {} plan...", style(">>>").cyan(), plan_type);
     }
 
+    let step_ordering = StepOrdering::from_str(order)?;
+    let planner_options = settings.planner_options()?;
+    let owner_uid = owner.map(|o| resolve_owner(db.conn(), o)).transpose()?;
+    let owned_by = |file: &ordne_lib::File| owner_uid.is_none() || file.owner_uid == owner_uid;
+
     let plan_id = match plan_type {
         "delete-trash" => {
             // Query trash files BEFORE creating planner
             let category = category_filter.unwrap_or("trash");
-            let files = if let Some(source_drive) = source_drive {
+            let mut files = if let Some(source_drive) = source_drive {
                 let drive = db.get_drive(source_drive)?
                     .ok_or_else(|| OrdneError::DriveNotFound(source_drive.to_string()))?;
                 get_files_by_category_and_drive(db.conn(), category, drive.id)?
             } else {
                 get_files_by_category(db.conn(), category)?
             };
+            files.retain(|f| owned_by(f));
 
             if files.is_empty() {
                 return Err(OrdneError::Config("No trash files found".to_string()));
@@ -98,6 +245,9 @@ fn create_plan(
                 max_batch_size_bytes: None,
                 enforce_space_limits: true,
                 dry_run: false,
+                attribution: None,
+                step_ordering,
+                ..planner_options.clone()
             };
             let mut planner = Planner::new(db, options);
 
@@ -125,7 +275,10 @@ fn create_plan(
                     ))?
             };
 
-            let duplicates: Vec<_> = files.into_iter().filter(|f| f.id != original.id).collect();
+            let duplicates: Vec<_> = files
+                .into_iter()
+                .filter(|f| f.id != original.id && owned_by(f))
+                .collect();
             if duplicates.is_empty() {
                 return Err(OrdneError::Config("No duplicate files to delete".to_string()));
             }
@@ -134,51 +287,100 @@ fn create_plan(
                 max_batch_size_bytes: None,
                 enforce_space_limits: true,
                 dry_run: false,
+                attribution: None,
+                min_critical_copies,
+                step_ordering,
+                ..planner_options.clone()
             };
             let mut planner = Planner::new(db, options);
             planner.create_dedup_plan(duplicates, &original)?
         }
         "migrate" | "offload" => {
-            let target_label = target_drive.ok_or_else(|| OrdneError::Config(
-                "Target drive required: --target-drive <label>".to_string()
-            ))?;
-            let target = db.get_drive(target_label)?
-                .ok_or_else(|| OrdneError::DriveNotFound(target_label.to_string()))?;
-            let target_mount = target.mount_path.clone()
-                .ok_or_else(|| OrdneError::Config("Target drive has no mount path".to_string()))?;
-
-            let category = category_filter.ok_or_else(|| OrdneError::Config(
-                "Category filter required: --category-filter <category>".to_string()
-            ))?;
+            if target_drives.is_empty() {
+                return Err(OrdneError::Config(
+                    "Target drive required: --target-drive <label>".to_string()
+                ));
+            }
+            let pooled = target_drives.len() > 1 || !keep_together.is_empty();
+            if pooled && plan_type == "offload" {
+                return Err(OrdneError::Config(
+                    "Offload plans take a single --target-drive".to_string()
+                ));
+            }
+            let max_batch_size_bytes = batch_size
+                .map(|s| {
+                    crate::util::units::parse_bytes(s)
+                        .map(|b| b as u64)
+                        .map_err(|e| OrdneError::Config(format!("Invalid --batch-size: {}", e)))
+                })
+                .transpose()?;
+            let layout = OffloadLayout::from_str(layout)?;
+            if layout != OffloadLayout::Mirror && plan_type != "offload" {
+                return Err(OrdneError::Config(
+                    "--layout only applies to offload plans".to_string()
+                ));
+            }
 
-            let files = if let Some(source_drive) = source_drive {
-                let drive = db.get_drive(source_drive)?
-                    .ok_or_else(|| OrdneError::DriveNotFound(source_drive.to_string()))?;
-                get_files_by_category_and_drive(db.conn(), category, drive.id)?
+            let mut targets = Vec::new();
+            for label in target_drives {
+                let target = db.get_drive(label)?
+                    .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+                let mount_path = target.mount_path.clone()
+                    .ok_or_else(|| OrdneError::Config(format!("Target drive '{}' has no mount path", label)))?;
+                targets.push(PlacementTarget { drive_id: target.id, mount_path, capacity_bytes: None });
+            }
+            let constraints = keep_together
+                .iter()
+                .map(|c| PlacementConstraint::parse(c))
+                .collect::<Result<Vec<_>>>()?;
+
+            let source = source_drive
+                .map(|label| db.get_drive(label)?.ok_or_else(|| OrdneError::DriveNotFound(label.to_string())))
+                .transpose()?;
+
+            let mut files = if let Some(list) = from_list {
+                let resolved = resolve_file_list(db, list, source.as_ref())?;
+                print_unmatched_lines(list, &resolved);
+                resolved.files
             } else {
-                get_files_by_category(db.conn(), category)?
+                let category = category_filter.ok_or_else(|| OrdneError::Config(
+                    "Category filter required: --category-filter <category> or --from-list <file>".to_string()
+                ))?;
+                match &source {
+                    Some(drive) => get_files_by_category_and_drive(db.conn(), category, drive.id)?,
+                    None => get_files_by_category(db.conn(), category)?,
+                }
             };
+            files.retain(|f| owned_by(f));
 
             if files.is_empty() {
-                return Err(OrdneError::Config("No files matched category filter".to_string()));
+                let filter = if from_list.is_some() { "file list" } else { "category filter" };
+                return Err(OrdneError::Config(format!("No files matched {}", filter)));
             }
 
             let options = PlannerOptions {
-                max_batch_size_bytes: None,
+                max_batch_size_bytes,
                 enforce_space_limits: true,
                 dry_run: false,
+                attribution: None,
+                step_ordering,
+                ..planner_options.clone()
             };
             let mut planner = Planner::new(db, options);
 
-            if plan_type == "migrate" {
-                planner.create_migrate_plan(files, target.id, &target_mount)?
+            if pooled {
+                planner.create_pool_migrate_plan(files, targets, &constraints)?
+            } else if plan_type == "migrate" {
+                planner.create_migrate_plan(files, targets[0].drive_id, &targets[0].mount_path)?
+            } else if layout == OffloadLayout::ContentAddressed {
+                planner.create_cas_offload_plan(files, targets[0].drive_id, &targets[0].mount_path)?
             } else {
-                planner.create_offload_plan(files, target.id, &target_mount)?
+                planner.create_offload_plan(files, targets[0].drive_id, &targets[0].mount_path)?
             }
         }
         _ => {
             return Err(OrdneError::Config(format!(
-                "Unknown plan type: '{}'. Valid types: delete-trash, dedup, migrate, offload",
+                "Unknown plan type: '{}'. Valid types: delete-trash, dedup, cross-drive-dedup, migrate, offload, recopy",
                 plan_type
             )));
         }
@@ -191,12 +393,136 @@ fn create_plan(
     println!("  Files: {}", plan.total_files);
     println!("  Size: {}", crate::util::format::format_bytes(plan.total_bytes));
     println!("  Status: {}", style(plan.status.as_str()).yellow());
+    print_encryption_warnings(db, plan_id)?;
     println!("\nRun 'ordne plan show {}' to see details", plan_id);
     println!("Run 'ordne plan approve {}' to approve for execution", plan_id);
 
     Ok(())
 }
 
+/// Lines of an imported file list that matched no catalogued file, shown
+/// before the plan so a user can fix the list rather than lose files.
+fn print_unmatched_lines(list: &Path, resolved: &ordne_lib::index::FileListResult) {
+    const SHOWN: usize = 20;
+
+    if resolved.repeated > 0 {
+        println!("{} {} repeated paths in {} skipped", style("!").yellow(), resolved.repeated, list.display());
+    }
+    if resolved.unmatched.is_empty() {
+        return;
+    }
+    println!(
+        "{} {} of the paths in {} matched no file:",
+        style("!").yellow(),
+        resolved.unmatched.len(),
+        list.display()
+    );
+    for unmatched in resolved.unmatched.iter().take(SHOWN) {
+        println!("  line {}: {} ({})", unmatched.line, unmatched.path, unmatched.reason);
+    }
+    if resolved.unmatched.len() > SHOWN {
+        println!("  ... and {} more", resolved.unmatched.len() - SHOWN);
+    }
+}
+
+fn create_cross_drive_dedup_plans(
+    db: &mut SqliteDatabase,
+    settings: &Settings,
+    keep_roles: &[String],
+    remove_roles: &[String],
+    hardlink: bool,
+    min_critical_copies: usize,
+) -> Result<()> {
+    let options = CrossDriveDedupOptions::from_role_names(keep_roles, remove_roles, hardlink)?;
+    let groups = list_cross_drive_group_files(db.conn())?;
+
+    if groups.is_empty() {
+        println!("{}", style("No cross-drive duplicate groups found").green());
+        return Ok(());
+    }
+
+    let planner_options = PlannerOptions {
+        max_batch_size_bytes: None,
+        enforce_space_limits: false,
+        dry_run: false,
+        attribution: None,
+        min_critical_copies,
+        ..settings.planner_options()?
+    };
+    let plan_ids = Planner::new(db, planner_options).create_cross_drive_dedup_plans(groups, &options)?;
+
+    if plan_ids.is_empty() {
+        println!("{}", style("No duplicates can be removed with the configured drive roles").yellow());
+        return Ok(());
+    }
+
+    println!("{} Created {} plans", style("✓").green(), plan_ids.len());
+    for plan_id in &plan_ids {
+        let plan = db.get_plan(*plan_id)?.ok_or(OrdneError::PlanNotFound(*plan_id))?;
+        println!(
+            "  #{}: {} ({})",
+            plan_id,
+            plan.description.as_deref().unwrap_or("(no description)"),
+            crate::util::format::format_bytes(plan.total_bytes)
+        );
+    }
+    println!("\nRun 'ordne plan show <id>' to see details");
+
+    Ok(())
+}
+
+fn create_recopy_plan(db: &mut SqliteDatabase, failure_ids: &[i64]) -> Result<()> {
+    let failures: Vec<_> = db
+        .list_unacknowledged_failures()?
+        .into_iter()
+        .filter(|f| failure_ids.is_empty() || failure_ids.contains(&f.id))
+        .collect();
+
+    if let Some(missing) = failure_ids.iter().find(|id| !failures.iter().any(|f| f.id == **id)) {
+        return Err(OrdneError::Config(format!("No open verification failure with ID {}", missing)));
+    }
+    if failures.is_empty() {
+        println!("{}", style("No open verification failures").green());
+        return Ok(());
+    }
+
+    let set = find_repairs(db, &failures)?;
+
+    for (failure, reason) in &set.unresolved {
+        println!(
+            "{} Failure {} ({}): {}",
+            style("!").yellow(),
+            failure.id,
+            failure.path,
+            reason
+        );
+    }
+    if set.repairs.is_empty() {
+        return Err(OrdneError::Config("None of the failures can be repaired".to_string()));
+    }
+
+    let options = PlannerOptions {
+        max_batch_size_bytes: None,
+        enforce_space_limits: false,
+        dry_run: false,
+        attribution: None,
+        ..Default::default()
+    };
+    let plan_id = Planner::new(db, options).create_recopy_plan(&set.repairs)?;
+    let plan = db.get_plan(plan_id)?.ok_or(OrdneError::PlanNotFound(plan_id))?;
+
+    println!("{} Plan created (ID: {})", style("✓").green(), style(plan_id).bold());
+    println!("  Type: recopy");
+    println!("  Files: {}", plan.total_files);
+    println!("  Size: {}", crate::util::format::format_bytes(plan.total_bytes));
+    println!("  Status: {}", style(plan.status.as_str()).yellow());
+    print_encryption_warnings(db, plan_id)?;
+    println!("\nRun 'ordne plan show {}' to see details", plan_id);
+    println!("Acknowledge the failures with 'ordne inbox --ack <id>' once the plan has run");
+
+    Ok(())
+}
+
 fn list_plans(db: &SqliteDatabase, status_filter: Option<&str>) -> Result<()> {
     let status = if let Some(s) = status_filter {
         Some(PlanStatus::from_str(s)?)
@@ -211,9 +537,7 @@ fn list_plans(db: &SqliteDatabase, status_filter: Option<&str>) -> Result<()> {
         return Ok(());
     }
 
-    let mut table = Table::new();
-    table.load_preset(UTF8_FULL);
-    table.set_header(vec!["ID", "Type", "Files", "Size", "Status", "Created"]);
+    let mut table = OutputTable::new(vec!["ID", "Type", "Files", "Size", "Status", "Created"]);
 
     for plan in &plans {
         table.add_row(vec![
@@ -232,7 +556,13 @@ fn list_plans(db: &SqliteDatabase, status_filter: Option<&str>) -> Result<()> {
     Ok(())
 }
 
-fn show_plan(db: &SqliteDatabase, id: i64) -> Result<()> {
+enum PlanView {
+    Summary,
+    Steps,
+    Directories,
+}
+
+fn show_plan(db: &SqliteDatabase, id: i64, query: StepQuery, view: PlanView) -> Result<()> {
     let plan = db.get_plan(id)?.ok_or(OrdneError::PlanNotFound(id))?;
 
     println!("{} Migration Plan #{}", style(">>>").cyan(), style(id).bold());
@@ -242,6 +572,8 @@ fn show_plan(db: &SqliteDatabase, id: i64) -> Result<()> {
     println!("  Files: {}", plan.total_files);
     println!("  Total size: {}", crate::util::format::format_bytes(plan.total_bytes));
     println!("  Created: {}", crate::util::format::format_timestamp(&plan.created_at));
+    println!("  Order: {}", plan.step_ordering.as_str());
+    print_encryption_warnings(db, id)?;
 
     println!("\n{}", style("Progress").bold());
     println!("  Completed files: {} / {}", plan.completed_files, plan.total_files);
@@ -249,46 +581,204 @@ fn show_plan(db: &SqliteDatabase, id: i64) -> Result<()> {
         crate::util::format::format_bytes(plan.completed_bytes),
         crate::util::format::format_bytes(plan.total_bytes)
     );
+    show_plan_sessions(db, id)?;
+    show_space_reconciliation(db, id)?;
+
+    match view {
+        PlanView::Summary => show_plan_summary(db, id)?,
+        PlanView::Steps => show_steps(db, id, &query)?,
+        PlanView::Directories => show_step_directories(db, id, &query)?,
+    }
 
-    let steps: Vec<MigrationStep> = vec![]; // TODO: implement list_steps
-    if !steps.is_empty() {
-        println!("\n{} ({} steps)", style("Steps").bold(), steps.len());
+    if plan.status == PlanStatus::Draft {
+        println!("\n{} Run 'ordne plan approve {}' to approve this plan", style("Tip:").cyan(), id);
+    } else if plan.status == PlanStatus::Approved {
+        println!("\n{} Run 'ordne migrate {}' to execute this plan", style("Tip:").cyan(), id);
+    } else if matches!(plan.status, PlanStatus::Paused | PlanStatus::InProgress) {
+        println!(
+            "\n{} If no migration is running, 'ordne migrate {} --execute' resumes from the last completed step",
+            style("Tip:").cyan(),
+            id
+        );
+    }
+
+    Ok(())
+}
+
+/// Flag Critical files the plan copies off an encrypted drive onto one that is not.
+pub(crate) fn print_encryption_warnings(db: &SqliteDatabase, plan_id: i64) -> Result<()> {
+    for warning in plan_encryption_warnings(db.conn(), plan_id)? {
+        println!("  {} {}", style("Warning:").yellow(), warning.message());
+    }
+    Ok(())
+}
+
+/// One row per execution session, e.g. per night of a windowed migration.
+fn show_plan_sessions(db: &SqliteDatabase, id: i64) -> Result<()> {
+    let sessions = db.list_plan_sessions(id)?;
+    if sessions.is_empty() {
+        return Ok(());
+    }
+
+    println!("\n{}", style("Sessions").bold());
+    let mut table = OutputTable::new(vec!["Started", "Ended", "Files", "Size", "Result"]);
+    for session in &sessions {
+        table.add_row(vec![
+            Cell::new(crate::util::format::format_timestamp(&session.started_at)),
+            Cell::new(
+                session
+                    .ended_at
+                    .as_ref()
+                    .map_or("-".to_string(), crate::util::format::format_timestamp),
+            ),
+            Cell::new(session.files_completed),
+            Cell::new(crate::util::format::format_bytes(session.bytes_completed)),
+            Cell::new(session.end_reason.as_deref().unwrap_or("running or interrupted")),
+        ]);
+    }
+    println!("{}", table);
+
+    Ok(())
+}
+
+/// Predicted against measured change in used space per drive, once the plan has run.
+pub(crate) fn show_space_reconciliation(db: &SqliteDatabase, id: i64) -> Result<()> {
+    let drives = reconcile_plan(db, id)?;
+    if drives.is_empty() {
+        return Ok(());
+    }
+
+    let signed = |bytes: i64| {
+        let sign = if bytes < 0 { "-" } else { "+" };
+        format!("{}{}", sign, crate::util::format::format_bytes(bytes.abs()))
+    };
+
+    println!("\n{}", style("Space").bold());
+    let mut table = OutputTable::new(vec!["Drive", "Predicted", "Measured", "Difference"]);
+    for drive in &drives {
+        table.add_row(vec![
+            Cell::new(&drive.label),
+            Cell::new(signed(drive.predicted_bytes)),
+            Cell::new(drive.actual_bytes.map_or("-".to_string(), signed)),
+            Cell::new(drive.discrepancy().map_or("-".to_string(), signed)),
+        ]);
+    }
+    println!("{}", table);
+    for drive in &drives {
+        for note in &drive.notes {
+            println!("  {} {}: {}", style("Note:").yellow(), drive.label, note);
+        }
+    }
 
-        let mut table = Table::new();
-        table.load_preset(UTF8_FULL);
-        table.set_header(vec!["#", "Action", "File", "Status"]);
+    Ok(())
+}
 
-        for (i, step) in steps.iter().take(10).enumerate() {
+fn show_plan_summary(db: &SqliteDatabase, id: i64) -> Result<()> {
+    let summary = db.get_plan_summary(id)?;
+    if summary.by_action.is_empty() {
+        return Ok(());
+    }
+
+    let sections = [
+        ("By action", "Action", &summary.by_action),
+        ("By directory", "Drive:Directory", &summary.by_directory),
+        ("By destination drive", "Destination", &summary.by_destination),
+    ];
+
+    for (title, column, rows) in sections {
+        println!("\n{}", style(title).bold());
+
+        let mut table = OutputTable::new(vec![column, "Steps", "Size"]);
+
+        for row in rows.iter() {
             table.add_row(vec![
-                Cell::new(i + 1),
-                Cell::new(step.action.as_str()),
-                Cell::new(&step.source_path),
-                Cell::new(step.status.as_str()),
+                Cell::new(&row.key),
+                Cell::new(row.step_count),
+                Cell::new(crate::util::format::format_bytes(row.total_bytes)),
             ]);
         }
 
         println!("{}", table);
+    }
 
-        if steps.len() > 10 {
-            println!("  ... and {} more steps", steps.len() - 10);
-        }
+    if summary.satisfied_files > 0 {
+        println!(
+            "  {} duplicate files ({}) need no copy: an identical file is copied instead",
+            summary.satisfied_files,
+            crate::util::format::format_bytes(summary.satisfied_bytes)
+        );
     }
+    println!("  See individual steps with 'ordne plan show {} --steps'", id);
 
-    if plan.status.as_str() == "pending" {
-        println!("\n{} Run 'ordne plan approve {}' to approve this plan", style("Tip:").cyan(), id);
-    } else if plan.status.as_str() == "approved" {
-        println!("\n{} Run 'ordne migrate {}' to execute this plan", style("Tip:").cyan(), id);
+    Ok(())
+}
+
+fn show_steps(db: &SqliteDatabase, id: i64, query: &StepQuery) -> Result<()> {
+    let page = db.get_steps_page(id, query)?;
+    if page.total == 0 {
+        return Ok(());
+    }
+
+    match &query.directory {
+        Some(dir) => println!("\n{} ({} steps in {})", style("Steps").bold(), page.total, dir),
+        None => println!("\n{} ({} steps)", style("Steps").bold(), page.total),
+    }
+
+    let mut table = OutputTable::new(vec!["#", "Action", "File", "Status"]);
+
+    for (i, step) in page.steps.iter().enumerate() {
+        table.add_row(vec![
+            Cell::new(page.offset + i as i64 + 1),
+            Cell::new(step.action.as_str()),
+            Cell::new(&step.source_path),
+            Cell::new(step.status.as_str()),
+        ]);
+    }
+
+    println!("{}", table);
+
+    if page.has_more() {
+        let next = page.offset + page.steps.len() as i64;
+        println!(
+            "  ... {} more steps (use --offset {} to continue)",
+            page.total - next,
+            next
+        );
     }
 
     Ok(())
 }
 
+fn show_step_directories(db: &SqliteDatabase, id: i64, query: &StepQuery) -> Result<()> {
+    let directories = db.get_step_directories(id, query.offset, query.limit)?;
+    if directories.is_empty() {
+        return Ok(());
+    }
+
+    println!("\n{}", style("Steps by directory").bold());
+
+    let mut table = OutputTable::new(vec!["Directory", "Steps", "Size"]);
+
+    for dir in &directories {
+        table.add_row(vec![
+            Cell::new(if dir.directory.is_empty() { "/" } else { &dir.directory }),
+            Cell::new(dir.step_count),
+            Cell::new(crate::util::format::format_bytes(dir.total_bytes)),
+        ]);
+    }
+
+    println!("{}", table);
+    println!("  Drill in with 'ordne plan show {} --dir <directory>'", id);
+
+    Ok(())
+}
+
 fn approve_plan(db: &mut SqliteDatabase, id: i64) -> Result<()> {
     let plan = db.get_plan(id)?.ok_or(OrdneError::PlanNotFound(id))?;
 
-    if plan.status.as_str() != "pending" {
+    if plan.status != PlanStatus::Draft {
         return Err(OrdneError::Config(format!(
-            "Plan #{} is already {} (must be pending to approve)",
+            "Plan #{} is already {} (must be draft to approve)",
             id, plan.status.as_str()
         )));
     }
@@ -297,6 +787,8 @@ fn approve_plan(db: &mut SqliteDatabase, id: i64) -> Result<()> {
         max_batch_size_bytes: None,
         enforce_space_limits: true,
         dry_run: false,
+        attribution: None,
+        ..Default::default()
     });
 
     planner.approve_plan(id)?;
@@ -306,3 +798,50 @@ fn approve_plan(db: &mut SqliteDatabase, id: i64) -> Result<()> {
 
     Ok(())
 }
+
+fn cleanup_aborted_plan(db: &mut SqliteDatabase, settings: &Settings, id: i64, dry_run: bool) -> Result<()> {
+    let report = cleanup_plan(db, id, dry_run, settings.safe_mode(), &Clock::default(), None)?;
+    let (remove, complete, reset) = if dry_run {
+        ("Would remove", "Would mark completed", "Would reset to pending")
+    } else {
+        ("Removed", "Marked completed", "Reset to pending")
+    };
+
+    println!("{} Cleanup of plan #{}{}", style(">>>").cyan(), id, if dry_run { " (dry run)" } else { "" });
+    println!("  {} {} partial files", remove, report.removed_files.len());
+    for path in &report.removed_files {
+        println!("    {}", path);
+    }
+    println!("  {} {} steps whose work was already done", complete, report.completed_steps.len());
+    println!("  {} {} unfinished steps", reset, report.reset_steps.len());
+
+    if !report.inconsistencies.is_empty() {
+        println!(
+            "\n{} {} steps need attention:",
+            style("!").yellow(),
+            report.inconsistencies.len()
+        );
+        let mut table = OutputTable::new(vec!["Step", "Path", "Problem"]);
+        for issue in &report.inconsistencies {
+            table.add_row(vec![
+                Cell::new(issue.step_id),
+                Cell::new(&issue.path),
+                Cell::new(&issue.problem),
+            ]);
+        }
+        println!("{}", table);
+    }
+
+    if dry_run {
+        println!("\nRun 'ordne plan cleanup {}' to apply", id);
+    } else if report.inconsistencies.is_empty() {
+        println!("\n{} Plan #{} is now {}", style("✓").green(), id, report.plan_status);
+        if report.plan_status == PlanStatus::Approved.as_str() {
+            println!("Run 'ordne migrate {} --execute' to finish it", id);
+        }
+    } else {
+        println!("\nPlan #{} stays aborted until these are resolved", id);
+    }
+
+    Ok(())
+}
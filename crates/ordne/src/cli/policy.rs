@@ -1,5 +1,6 @@
 use clap::Subcommand;
 use ordne_lib::{apply_policy, load_effective_policy, OrdneError, Result, SqliteDatabase};
+use ordne_lib::config::Settings;
 use std::path::PathBuf;
 
 #[derive(Subcommand)]
@@ -24,6 +25,7 @@ pub enum PolicySubcommand {
 
 pub fn handle_policy_command(
     _db: &mut SqliteDatabase,
+    settings: &Settings,
     subcommand: PolicySubcommand,
     _verbose: bool,
 ) -> Result<()> {
@@ -49,7 +51,10 @@ pub fn handle_policy_command(
             let (policy, _rules) = load_effective_policy(_db, &path)?;
             policy.validate()?;
 
-            let result = apply_policy(_db, &policy)?;
+            let result = apply_policy(_db, &policy, None, settings)?;
+            for plan_id in &result.plan_ids {
+                crate::cli::plan::print_encryption_warnings(_db, *plan_id)?;
+            }
 
             if !execute && !dry_run {
                 println!(
@@ -61,7 +66,7 @@ pub fn handle_policy_command(
             }
 
             let plan_count = result.plan_ids.len();
-            crate::cli::run_policy::execute_policy_plans(_db, &policy, result.plan_ids, dry_run, execute)?;
+            crate::cli::run_policy::execute_policy_plans(_db, settings, &policy, result.plan_ids, dry_run, execute)?;
 
             println!(
                 "Policy applied: {} ({} plan(s) created)",
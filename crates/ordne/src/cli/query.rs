@@ -1,6 +1,7 @@
 use ordne_lib::{Result, OrdneError};
 use clap::Subcommand;
-use comfy_table::{Table, presets::UTF8_FULL, Cell, Color};
+use super::table::OutputTable;
+use comfy_table::Cell;
 use console::style;
 use ordne_lib::{
     Database,
@@ -8,6 +9,7 @@ use ordne_lib::{
     db::{
         files::get_files_by_category,
         duplicates::list_duplicate_groups,
+        owners::resolve_owner,
     },
 };
 
@@ -17,6 +19,9 @@ pub enum QueryCommands {
     Duplicates {
         #[arg(long, help = "Filter by drive label")]
         drive: Option<String>,
+
+        #[arg(long, help = "Only groups with a copy owned by this user (name or uid)")]
+        owner: Option<String>,
     },
 
     #[command(about = "Show unclassified files")]
@@ -29,6 +34,9 @@ pub enum QueryCommands {
     Category {
         #[arg(help = "Category name")]
         category: String,
+
+        #[arg(long, help = "Only files owned by this user (name or uid)")]
+        owner: Option<String>,
     },
 
     #[command(about = "Show large files")]
@@ -38,6 +46,9 @@ pub enum QueryCommands {
 
         #[arg(long, help = "Maximum number of files to show")]
         limit: Option<usize>,
+
+        #[arg(long, help = "Only files owned by this user (name or uid)")]
+        owner: Option<String>,
     },
 
     #[command(about = "Show files unique to backup drives")]
@@ -46,16 +57,27 @@ pub enum QueryCommands {
 
 pub fn handle_query_command(db: &SqliteDatabase, action: QueryCommands) -> Result<()> {
     match action {
-        QueryCommands::Duplicates { drive } => query_duplicates(db, drive.as_deref()),
+        QueryCommands::Duplicates { drive, owner } => query_duplicates(db, drive.as_deref(), owner.as_deref()),
         QueryCommands::Unclassified { limit } => query_unclassified(db, limit),
-        QueryCommands::Category { category } => query_category(db, &category),
-        QueryCommands::LargeFiles { min_size, limit } => query_large_files(db, min_size.as_deref(), limit),
+        QueryCommands::Category { category, owner } => query_category(db, &category, owner.as_deref()),
+        QueryCommands::LargeFiles { min_size, limit, owner } => {
+            query_large_files(db, min_size.as_deref(), limit, owner.as_deref())
+        }
         QueryCommands::BackupUnique => query_backup_unique(db),
     }
 }
 
-fn query_duplicates(db: &SqliteDatabase, drive_label: Option<&str>) -> Result<()> {
-    let groups = list_duplicate_groups(db.conn())?;
+fn query_duplicates(db: &SqliteDatabase, drive_label: Option<&str>, owner: Option<&str>) -> Result<()> {
+    let mut groups = list_duplicate_groups(db.conn())?;
+
+    if let Some(owner) = owner {
+        let uid = resolve_owner(db.conn(), owner)?;
+        let owned: std::collections::HashSet<i64> = db.conn()
+            .prepare("SELECT DISTINCT duplicate_group FROM files WHERE owner_uid = ?1 AND duplicate_group IS NOT NULL")?
+            .query_map([uid], |row| row.get(0))?
+            .collect::<std::result::Result<_, _>>()?;
+        groups.retain(|g| owned.contains(&g.group_id));
+    }
 
     let filtered_groups = if let Some(label) = drive_label {
         let drive = db.get_drive(label)?
@@ -79,16 +101,7 @@ fn query_duplicates(db: &SqliteDatabase, drive_label: Option<&str>) -> Result<()
         filtered_groups.len()
     );
 
-    let mut table = Table::new();
-    table.load_preset(UTF8_FULL);
-    table.set_header(vec![
-        Cell::new("Group ID").fg(Color::Cyan),
-        Cell::new("Hash").fg(Color::Cyan),
-        Cell::new("Files").fg(Color::Cyan),
-        Cell::new("Wasted").fg(Color::Cyan),
-        Cell::new("Cross-Drive").fg(Color::Cyan),
-        Cell::new("Status").fg(Color::Cyan),
-    ]);
+    let mut table = OutputTable::new(vec!["Group ID", "Hash", "Files", "Wasted", "Cross-Drive", "Status"]);
 
     for group in filtered_groups.iter().take(50) {
         let hash_short = if group.hash.len() > 12 {
@@ -140,14 +153,7 @@ fn query_unclassified(db: &SqliteDatabase, limit: Option<usize>) -> Result<()> {
         files.len()
     );
 
-    let mut table = Table::new();
-    table.load_preset(UTF8_FULL);
-    table.set_header(vec![
-        Cell::new("ID").fg(Color::Cyan),
-        Cell::new("Path").fg(Color::Cyan),
-        Cell::new("Size").fg(Color::Cyan),
-        Cell::new("Extension").fg(Color::Cyan),
-    ]);
+    let mut table = OutputTable::new(vec!["ID", "Path", "Size", "Extension"]);
 
     for file in &files {
         let path_display = if file.path.len() > 60 {
@@ -176,8 +182,12 @@ fn query_unclassified(db: &SqliteDatabase, limit: Option<usize>) -> Result<()> {
     Ok(())
 }
 
-fn query_category(db: &SqliteDatabase, category: &str) -> Result<()> {
-    let files = get_files_by_category(db.conn(), category)?;
+fn query_category(db: &SqliteDatabase, category: &str, owner: Option<&str>) -> Result<()> {
+    let mut files = get_files_by_category(db.conn(), category)?;
+    if let Some(owner) = owner {
+        let uid = resolve_owner(db.conn(), owner)?;
+        files.retain(|f| f.owner_uid == Some(uid));
+    }
 
     if files.is_empty() {
         println!("{}", style(format!("No files in category '{}'", category)).yellow());
@@ -191,15 +201,7 @@ fn query_category(db: &SqliteDatabase, category: &str) -> Result<()> {
         files.len()
     );
 
-    let mut table = Table::new();
-    table.load_preset(UTF8_FULL);
-    table.set_header(vec![
-        Cell::new("ID").fg(Color::Cyan),
-        Cell::new("Subcategory").fg(Color::Cyan),
-        Cell::new("Filename").fg(Color::Cyan),
-        Cell::new("Size").fg(Color::Cyan),
-        Cell::new("Priority").fg(Color::Cyan),
-    ]);
+    let mut table = OutputTable::new(vec!["ID", "Subcategory", "Filename", "Size", "Priority"]);
 
     for file in files.iter().take(100) {
         let subcategory = file.subcategory.as_deref().unwrap_or("-");
@@ -227,26 +229,32 @@ fn query_category(db: &SqliteDatabase, category: &str) -> Result<()> {
     Ok(())
 }
 
-fn query_large_files(db: &SqliteDatabase, min_size_str: Option<&str>, limit: Option<usize>) -> Result<()> {
+fn query_large_files(
+    db: &SqliteDatabase,
+    min_size_str: Option<&str>,
+    limit: Option<usize>,
+    owner: Option<&str>,
+) -> Result<()> {
     let min_bytes = if let Some(size_str) = min_size_str {
-        crate::util::format::parse_size_string(size_str)
-            .map_err(|e| OrdneError::Config(e))?
+        crate::util::units::parse_bytes(size_str)
+            .map_err(|e| OrdneError::Config(format!("Invalid --min-size: {}", e)))?
     } else {
         100 * 1024 * 1024
     };
 
     let limit_val = limit.unwrap_or(50);
+    let owner_uid = owner.map(|o| resolve_owner(db.conn(), o)).transpose()?;
 
     let conn = db.conn();
     let mut stmt = conn.prepare(
         "SELECT id, drive_id, path, filename, size_bytes, category, priority
          FROM files
-         WHERE size_bytes >= ?1
+         WHERE size_bytes >= ?1 AND (?3 IS NULL OR owner_uid = ?3)
          ORDER BY size_bytes DESC
          LIMIT ?2"
     )?;
 
-    let files = stmt.query_map([min_bytes, limit_val as i64], |row| {
+    let files = stmt.query_map((min_bytes, limit_val as i64, owner_uid), |row| {
         Ok((
             row.get::<_, i64>(0)?,
             row.get::<_, i64>(1)?,
@@ -272,15 +280,7 @@ fn query_large_files(db: &SqliteDatabase, min_size_str: Option<&str>, limit: Opt
         files.len()
     );
 
-    let mut table = Table::new();
-    table.load_preset(UTF8_FULL);
-    table.set_header(vec![
-        Cell::new("ID").fg(Color::Cyan),
-        Cell::new("Filename").fg(Color::Cyan),
-        Cell::new("Size").fg(Color::Cyan),
-        Cell::new("Category").fg(Color::Cyan),
-        Cell::new("Priority").fg(Color::Cyan),
-    ]);
+    let mut table = OutputTable::new(vec!["ID", "Filename", "Size", "Category", "Priority"]);
 
     for (id, _drive_id, _path, filename, size, category, priority) in files {
         let filename_display = if filename.len() > 50 {
@@ -1,18 +1,85 @@
 use ordne_lib::{Result, OrdneError};
-use comfy_table::{Table, presets::UTF8_FULL, Cell, Color};
+use super::table::OutputTable;
+use comfy_table::{Cell, Color};
 use console::style;
 use ordne_lib::{
-    Database, SqliteDatabase,
+    Database, Drive, SqliteDatabase, find_anomalies, rules_for_anomalies,
     db::{
-        files::get_category_stats,
+        files::{get_category_stats, get_extension_stats, get_top_level_folder_stats, FolderStats},
         duplicates::get_duplicate_statistics,
+        jobs::list_jobs,
+        owners::get_owner_stats,
     },
 };
 use crate::cli::helpers::get_drive_statistics;
 use serde_json::json;
 use std::path::PathBuf;
 
-pub fn handle_report_command(db: &SqliteDatabase) -> Result<()> {
+#[derive(clap::Subcommand)]
+pub enum ReportSubcommand {
+    #[command(about = "Count and size files per extension per drive, flagging anomalies")]
+    Extensions {
+        #[arg(long, help = "Only report this drive")]
+        drive: Option<String>,
+
+        #[arg(long, default_value = "table", help = "Output format (table, json, csv, rules)")]
+        format: String,
+
+        #[arg(long, short = 'o', help = "Output file path")]
+        output: Option<PathBuf>,
+
+        #[arg(long, default_value = "1GB", help = "Flag scratch extensions holding at least this much on a drive")]
+        threshold: String,
+
+        #[arg(long, default_value_t = 20, help = "Rows to show in the table (0 for all)")]
+        limit: usize,
+    },
+
+    #[command(about = "Printable sheet of a drive's top-level folders, to keep with the disk")]
+    DriveIndex {
+        #[arg(help = "Drive label")]
+        drive: String,
+
+        #[arg(long, default_value = "text", help = "Output format (text, html)")]
+        format: String,
+
+        #[arg(long, short = 'o', help = "Output file path")]
+        output: Option<PathBuf>,
+    },
+
+    #[command(about = "Files, bytes and duplicate bytes per owner, to see whose files fill a shared drive")]
+    Owners {
+        #[arg(long, help = "Only report this drive")]
+        drive: Option<String>,
+
+        #[arg(long, default_value = "table", help = "Output format (table, json)")]
+        format: String,
+    },
+
+    #[command(about = "Recent long-running commands with their time, rows and bytes")]
+    Jobs {
+        #[arg(long, default_value_t = 20, help = "Number of jobs to show")]
+        limit: i64,
+    },
+}
+
+pub fn handle_report_command(db: &SqliteDatabase, action: Option<ReportSubcommand>) -> Result<()> {
+    match action {
+        Some(ReportSubcommand::Extensions { drive, format, output, threshold, limit }) => {
+            return extension_report(db, drive.as_deref(), &format, output, &threshold, limit);
+        }
+        Some(ReportSubcommand::DriveIndex { drive, format, output }) => {
+            return drive_index_report(db, &drive, &format, output);
+        }
+        Some(ReportSubcommand::Owners { drive, format }) => {
+            return owner_report(db, drive.as_deref(), &format);
+        }
+        Some(ReportSubcommand::Jobs { limit }) => {
+            return jobs_report(db, limit);
+        }
+        None => {}
+    }
+
     println!("\n{}", style("Ordne System Report").bold().cyan());
     println!("{}\n", style("═".repeat(80)).dim());
 
@@ -21,6 +88,93 @@ pub fn handle_report_command(db: &SqliteDatabase) -> Result<()> {
     Ok(())
 }
 
+fn owner_report(db: &SqliteDatabase, drive: Option<&str>, format: &str) -> Result<()> {
+    let drive_id = match drive {
+        Some(label) => Some(
+            db.get_drive(label)?
+                .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?
+                .id,
+        ),
+        None => None,
+    };
+    let stats = get_owner_stats(db.conn(), drive_id)?;
+
+    match format.to_lowercase().as_str() {
+        "table" => {}
+        "json" => {
+            println!("{}", serde_json::to_string_pretty(&json!({ "owners": stats }))?);
+            return Ok(());
+        }
+        _ => {
+            return Err(OrdneError::Config(format!(
+                "Unsupported format '{}'. Use 'table' or 'json'",
+                format
+            )))
+        }
+    }
+
+    if stats.is_empty() {
+        println!("{}", style("No indexed files").yellow());
+        return Ok(());
+    }
+
+    let total: i64 = stats.iter().map(|s| s.total_bytes).sum();
+    let mut table = OutputTable::new(vec!["Owner", "UID", "Files", "Size", "Share", "Duplicates"]);
+    for s in &stats {
+        table.add_row(vec![
+            Cell::new(s.display_name()),
+            Cell::new(s.uid.map_or_else(|| "-".to_string(), |uid| uid.to_string())),
+            Cell::new(s.file_count),
+            Cell::new(crate::util::format::format_bytes(s.total_bytes)),
+            Cell::new(format!("{:.1}%", s.total_bytes as f64 * 100.0 / total.max(1) as f64)),
+            Cell::new(crate::util::format::format_bytes(s.duplicate_bytes)),
+        ]);
+    }
+    println!("{}", table);
+
+    Ok(())
+}
+
+fn jobs_report(db: &SqliteDatabase, limit: i64) -> Result<()> {
+    let jobs = list_jobs(db.conn(), limit)?;
+    if jobs.is_empty() {
+        println!("No jobs recorded yet");
+        return Ok(());
+    }
+
+    let mut table = OutputTable::new(vec!["Started", "Command", "By", "Elapsed", "Rows", "Processed", "DB growth", "Status"]);
+    for job in &jobs {
+        let status = if job.status == "ok" {
+            Cell::new(&job.status).fg(Color::Green)
+        } else {
+            Cell::new(&job.status).fg(Color::Red)
+        };
+        table.add_row(vec![
+            Cell::new(crate::util::format::format_timestamp(&job.started_at)),
+            Cell::new(&job.command),
+            Cell::new(match (&job.actor, &job.agent_mode) {
+                (Some(actor), Some(mode)) => format!("{} ({})", actor, mode),
+                (Some(actor), None) => actor.clone(),
+                _ => "-".to_string(),
+            }),
+            Cell::new(crate::util::format::format_duration(std::time::Duration::from_millis(
+                job.elapsed_ms.max(0) as u64,
+            ))),
+            Cell::new(job.rows_touched),
+            Cell::new(crate::util::format::format_bytes(job.bytes_processed)),
+            Cell::new(format!(
+                "{}{}",
+                if job.db_size_delta < 0 { "-" } else { "+" },
+                crate::util::format::format_bytes(job.db_size_delta.abs())
+            )),
+            status,
+        ]);
+    }
+    println!("{}", table);
+
+    Ok(())
+}
+
 pub fn handle_export_command(
     db: &SqliteDatabase,
     format: &str,
@@ -42,16 +196,7 @@ fn generate_report(db: &SqliteDatabase) -> Result<()> {
 
     let drives = db.list_drives()?;
 
-    let mut drive_table = Table::new();
-    drive_table.load_preset(UTF8_FULL);
-    drive_table.set_header(vec![
-        Cell::new("Drive").fg(Color::Cyan),
-        Cell::new("Role").fg(Color::Cyan),
-        Cell::new("Files").fg(Color::Cyan),
-        Cell::new("Size").fg(Color::Cyan),
-        Cell::new("Duplicates").fg(Color::Cyan),
-        Cell::new("Wasted").fg(Color::Cyan),
-    ]);
+    let mut drive_table = OutputTable::new(vec!["Drive", "Role", "Files", "Size", "Duplicates", "Wasted"]);
 
     for drive in &drives {
         let stats = get_drive_statistics(db, drive.id)?;
@@ -76,14 +221,7 @@ fn generate_report(db: &SqliteDatabase) -> Result<()> {
     if category_stats.is_empty() {
         println!("{}\n", style("No classified files").yellow());
     } else {
-        let mut cat_table = Table::new();
-        cat_table.load_preset(UTF8_FULL);
-        cat_table.set_header(vec![
-            Cell::new("Category").fg(Color::Cyan),
-            Cell::new("Subcategory").fg(Color::Cyan),
-            Cell::new("Files").fg(Color::Cyan),
-            Cell::new("Size").fg(Color::Cyan),
-        ]);
+        let mut cat_table = OutputTable::new(vec!["Category", "Subcategory", "Files", "Size"]);
 
         for stat in &category_stats {
             cat_table.add_row(vec![
@@ -227,3 +365,289 @@ fn export_csv(db: &SqliteDatabase, output: Option<PathBuf>) -> Result<()> {
 
     Ok(())
 }
+
+fn extension_report(
+    db: &SqliteDatabase,
+    drive: Option<&str>,
+    format: &str,
+    output: Option<PathBuf>,
+    threshold: &str,
+    limit: usize,
+) -> Result<()> {
+    let drive_id = match drive {
+        Some(label) => Some(
+            db.get_drive(label)?
+                .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?
+                .id,
+        ),
+        None => None,
+    };
+    let threshold = crate::util::units::parse_bytes(threshold)
+        .map_err(|e| OrdneError::Config(format!("Invalid --threshold: {}", e)))?;
+
+    let stats = get_extension_stats(db.conn(), drive_id)?;
+    let anomalies = find_anomalies(&stats, threshold);
+    let is_anomaly = |drive_id: i64, extension: &Option<String>| {
+        anomalies
+            .iter()
+            .any(|a| a.stats.drive_id == drive_id && &a.stats.extension == extension)
+    };
+
+    let contents = match format.to_lowercase().as_str() {
+        "table" => {
+            print_extension_table(&stats, &anomalies, limit);
+            return Ok(());
+        }
+        "json" => {
+            let rows: Vec<_> = stats
+                .iter()
+                .map(|s| {
+                    json!({
+                        "drive": s.drive_label,
+                        "extension": s.extension,
+                        "file_count": s.file_count,
+                        "total_bytes": s.total_bytes,
+                        "anomaly": is_anomaly(s.drive_id, &s.extension),
+                    })
+                })
+                .collect();
+            serde_json::to_string_pretty(&json!({
+                "extensions": rows,
+                "anomalies": anomalies,
+                "generated_at": chrono::Utc::now().to_rfc3339(),
+            }))?
+        }
+        "csv" => {
+            let mut csv = String::from("Drive,Extension,Files,TotalBytes,Anomaly\n");
+            for s in &stats {
+                csv.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    s.drive_label,
+                    s.extension.as_deref().unwrap_or(""),
+                    s.file_count,
+                    s.total_bytes,
+                    is_anomaly(s.drive_id, &s.extension)
+                ));
+            }
+            csv
+        }
+        "rules" => toml::to_string_pretty(&rules_for_anomalies(&anomalies))
+            .map_err(|e| OrdneError::Config(format!("Failed to serialize rules: {}", e)))?,
+        _ => {
+            return Err(OrdneError::Config(format!(
+                "Unsupported format '{}'. Use 'table', 'json', 'csv' or 'rules'",
+                format
+            )))
+        }
+    };
+
+    if let Some(path) = output {
+        std::fs::write(&path, contents)?;
+        println!("{} Report exported to {}", style("✓").green(), path.display());
+    } else {
+        println!("{}", contents);
+    }
+
+    Ok(())
+}
+
+fn print_extension_table(
+    stats: &[ordne_lib::db::files::ExtensionStats],
+    anomalies: &[ordne_lib::ExtensionAnomaly],
+    limit: usize,
+) {
+    println!("\n{}", style("Extensions").bold().cyan());
+    println!("{}\n", style("═".repeat(80)).dim());
+
+    if stats.is_empty() {
+        println!("{}", style("No indexed files").yellow());
+        return;
+    }
+
+    let mut table = OutputTable::new(vec!["Drive", "Extension", "Files", "Size"]);
+
+    let shown = if limit == 0 { stats.len() } else { limit.min(stats.len()) };
+    for s in &stats[..shown] {
+        table.add_row(vec![
+            Cell::new(&s.drive_label),
+            Cell::new(s.extension.as_deref().unwrap_or("(none)")),
+            Cell::new(s.file_count),
+            Cell::new(crate::util::format::format_bytes(s.total_bytes)),
+        ]);
+    }
+    println!("{}", table);
+    if shown < stats.len() {
+        println!("  {} more rows; use --limit 0 to show all", stats.len() - shown);
+    }
+
+    if anomalies.is_empty() {
+        println!("\n{}", style("No anomalies found").green());
+        return;
+    }
+
+    println!("\n{} ({})", style("Anomalies").bold().red(), anomalies.len());
+    for anomaly in anomalies {
+        println!(
+            "  {} .{} on '{}': {} in {} files",
+            style("!").yellow(),
+            anomaly.stats.extension.as_deref().unwrap_or(""),
+            anomaly.stats.drive_label,
+            crate::util::format::format_bytes(anomaly.stats.total_bytes),
+            anomaly.stats.file_count
+        );
+    }
+    println!("\n  Turn these into rules with 'ordne report extensions --format rules -o rules.toml'");
+}
+
+fn drive_index_report(
+    db: &SqliteDatabase,
+    label: &str,
+    format: &str,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let drive = db.get_drive(label)?
+        .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+    let folders = get_top_level_folder_stats(db.conn(), drive.id)?;
+
+    let contents = match format.to_lowercase().as_str() {
+        "text" => drive_index_text(&drive, &folders),
+        "html" => drive_index_html(&drive, &folders),
+        _ => {
+            return Err(OrdneError::Config(format!(
+                "Unsupported format '{}'. Use 'text' or 'html'",
+                format
+            )))
+        }
+    };
+
+    if let Some(path) = output {
+        std::fs::write(&path, contents)?;
+        println!("{} Drive index written to {}", style("✓").green(), path.display());
+    } else {
+        print!("{}", contents);
+    }
+
+    Ok(())
+}
+
+/// Header facts for a drive index sheet, as (label, value) pairs.
+fn drive_index_facts(drive: &Drive, folders: &[FolderStats]) -> Vec<(&'static str, String)> {
+    let files: i64 = folders.iter().map(|f| f.file_count).sum();
+    let bytes: i64 = folders.iter().map(|f| f.total_bytes).sum();
+
+    let mut facts = vec![("Role", drive.role.as_str().to_string())];
+    if let Some(uuid) = &drive.uuid {
+        facts.push(("UUID", uuid.clone()));
+    }
+    if let Some(total) = drive.total_bytes {
+        facts.push(("Capacity", crate::util::format::format_bytes(total)));
+    }
+    facts.push(("Files", format!("{} ({})", files, crate::util::format::format_bytes(bytes))));
+    facts.push((
+        "Cataloged",
+        drive
+            .scanned_at
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "never scanned".to_string()),
+    ));
+    facts
+}
+
+fn folder_name(folder: &FolderStats) -> &str {
+    if folder.folder.is_empty() { "(files in drive root)" } else { &folder.folder }
+}
+
+/// Plain text, no colour, fits an 80-column page.
+fn drive_index_text(drive: &Drive, folders: &[FolderStats]) -> String {
+    let rule = "-".repeat(78);
+    let mut out = format!("DRIVE INDEX: {}\n{}\n", drive.label, rule);
+    for (name, value) in drive_index_facts(drive, folders) {
+        out.push_str(&format!("{:<10} {}\n", format!("{}:", name), value));
+    }
+    out.push_str(&format!("{}\n{:<44} {:>8} {:>11} {:>12}\n", rule, "Folder", "Files", "Size", "Cataloged"));
+
+    for folder in folders {
+        let name = folder_name(folder);
+        let name = if name.chars().count() > 44 {
+            format!("{}...", name.chars().take(41).collect::<String>())
+        } else {
+            name.to_string()
+        };
+        out.push_str(&format!(
+            "{:<44} {:>8} {:>11} {:>12}\n",
+            name,
+            folder.file_count,
+            crate::util::format::format_bytes(folder.total_bytes),
+            folder.cataloged_on.as_deref().unwrap_or("-")
+        ));
+    }
+    if folders.is_empty() {
+        out.push_str("(no cataloged files)\n");
+    }
+
+    out.push_str(&format!("{}\nPrinted {} by ordne\n", rule, chrono::Local::now().format("%Y-%m-%d")));
+    out
+}
+
+/// Standalone page with print styles; print it or save it as PDF from a browser.
+fn drive_index_html(drive: &Drive, folders: &[FolderStats]) -> String {
+    let mut facts = String::new();
+    for (name, value) in drive_index_facts(drive, folders) {
+        facts.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", name, html_escape(&value)));
+    }
+
+    let mut rows = String::new();
+    for folder in folders {
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td>{}</td></tr>\n",
+            html_escape(folder_name(folder)),
+            folder.file_count,
+            crate::util::format::format_bytes(folder.total_bytes),
+            folder.cataloged_on.as_deref().unwrap_or("-")
+        ));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Drive index: {label}</title>
+<style>
+  @page {{ size: A4; margin: 15mm; }}
+  body {{ font-family: sans-serif; font-size: 10pt; }}
+  h1 {{ font-size: 18pt; margin: 0 0 4mm; }}
+  table {{ border-collapse: collapse; width: 100%; margin-bottom: 5mm; }}
+  th, td {{ text-align: left; padding: 1mm 2mm; border-bottom: 0.2mm solid #999; }}
+  .facts th {{ width: 25mm; }}
+  .num {{ text-align: right; }}
+  tr {{ page-break-inside: avoid; }}
+  footer {{ font-size: 8pt; color: #555; }}
+</style>
+</head>
+<body>
+<h1>{label}</h1>
+<table class="facts">
+{facts}</table>
+<table>
+<thead><tr><th>Folder</th><th class="num">Files</th><th class="num">Size</th><th>Cataloged</th></tr></thead>
+<tbody>
+{rows}</tbody>
+</table>
+<footer>Printed {printed} by ordne</footer>
+</body>
+</html>
+"#,
+        label = html_escape(&drive.label),
+        facts = facts,
+        rows = rows,
+        printed = chrono::Local::now().format("%Y-%m-%d"),
+    )
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
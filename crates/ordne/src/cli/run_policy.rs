@@ -1,13 +1,16 @@
 use ordne_lib::{
     apply_policy, load_effective_policy,
-    classify::{RuleEngine},
+    classify::DriveRuleEngines,
     db::files::update_file_classification,
+    undo::{log_classification_batch, ClassificationSnapshot},
     Database, EngineOptions, MigrationEngine, OrdneError, PlansDatabase, Policy, PolicyScope,
-    Result, SqliteDatabase,
+    Clock, Result, SqliteDatabase,
 };
+use ordne_lib::config::Settings;
 
 pub fn handle_run_policy_command(
     db: &mut SqliteDatabase,
+    settings: &Settings,
     path: std::path::PathBuf,
     dry_run: bool,
     execute: bool,
@@ -23,21 +26,24 @@ pub fn handle_run_policy_command(
 
     apply_classification_rules(db, &rules, policy.scope.as_ref())?;
 
-    let result = apply_policy(db, &policy)?;
+    let result = apply_policy(db, &policy, None, settings)?;
+    for plan_id in &result.plan_ids {
+        crate::cli::plan::print_encryption_warnings(db, *plan_id)?;
+    }
 
-    execute_policy_plans(db, &policy, result.plan_ids, dry_run, execute)
+    execute_policy_plans(db, settings, &policy, result.plan_ids, dry_run, execute)
 }
 
-fn apply_classification_rules(
+pub(crate) fn apply_classification_rules(
     db: &mut SqliteDatabase,
     rules: &ordne_lib::ClassificationRules,
     scope: Option<&PolicyScope>,
 ) -> Result<()> {
-    if rules.rules.is_empty() {
+    let engine = DriveRuleEngines::new(db, rules, &Clock::default())?;
+    if engine.is_empty() {
         return Ok(());
     }
 
-    let engine = RuleEngine::new(rules.clone())?;
     let mut files = crate::cli::helpers::get_unclassified_files(db, None)?;
 
     let (include_ids, exclude_ids, include_paths, exclude_paths) =
@@ -62,6 +68,7 @@ fn apply_classification_rules(
         true
     });
 
+    let mut before = Vec::new();
     for file in files {
         if let Some(rule_match) = engine.classify(&file)? {
             update_file_classification(
@@ -71,16 +78,17 @@ fn apply_classification_rules(
                 rule_match.subcategory.as_deref(),
                 rule_match.priority,
             )?;
+            before.push(ClassificationSnapshot::from_file(&file));
         }
     }
 
-    Ok(())
+    let details = format!("Classified {} files with policy rules", before.len());
+    log_classification_batch(db, &Clock::default(), &before, details)
 }
 
-fn scope_filters(
-    db: &SqliteDatabase,
-    scope: Option<&PolicyScope>,
-) -> Result<(Vec<i64>, Vec<i64>, Vec<String>, Vec<String>)> {
+type ScopeFilters = (Vec<i64>, Vec<i64>, Vec<String>, Vec<String>);
+
+fn scope_filters(db: &SqliteDatabase, scope: Option<&PolicyScope>) -> Result<ScopeFilters> {
     let mut include_ids = Vec::new();
     let mut exclude_ids = Vec::new();
     let mut include_paths = Vec::new();
@@ -111,6 +119,7 @@ fn scope_filters(
 }
 pub fn execute_policy_plans(
     db: &mut SqliteDatabase,
+    settings: &Settings,
     policy: &Policy,
     plan_ids: Vec<i64>,
     dry_run: bool,
@@ -127,7 +136,7 @@ pub fn execute_policy_plans(
     }
 
     if let Some(max_str) = safety.as_ref().and_then(|s| s.max_bytes_per_run.as_deref()) {
-        let max_bytes = crate::util::format::parse_size_string(max_str)
+        let max_bytes = crate::util::units::parse_bytes(max_str)
             .map_err(|e| OrdneError::Config(format!("Invalid max_bytes_per_run: {}", e)))?;
         let total_bytes: i64 = plan_ids
             .iter()
@@ -135,7 +144,7 @@ pub fn execute_policy_plans(
             .map(|p| p.total_bytes)
             .sum();
         if total_bytes > max_bytes {
-            return Err(OrdneError::Config(format!(
+            return Err(OrdneError::SafetyViolation(format!(
                 "Plans total {} bytes exceeds policy max {}",
                 total_bytes, max_bytes
             )));
@@ -155,17 +164,36 @@ pub fn execute_policy_plans(
         }
     }
 
+    let schedule = policy.schedule.as_ref();
     let engine_opts = EngineOptions {
         dry_run: dry_run || !execute,
         verify_hashes: true,
         retry_count: 3,
         enforce_safety: true,
+        max_parallel_steps: 1,
+        per_device_concurrency: 1,
+        attribution: None,
+        verify_after: None,
+        window: schedule.map(|s| s.execution_window()).transpose()?.flatten(),
+        bandwidth_limit_kbps: schedule.map(|s| s.bandwidth_limit_kbps()).transpose()?.flatten(),
+        idle: schedule.map(|s| s.idle_policy()).transpose()?.flatten(),
+        safe_mode: settings.safe_mode(),
+        ..Default::default()
     };
 
-    let mut engine = MigrationEngine::new(db, engine_opts);
-    for plan_id in plan_ids {
-        engine.execute_plan(plan_id)?;
-    }
-
-    Ok(())
+    // Drives listed under [automount] are mounted for a real run only, and
+    // safe mode makes every run a dry run.
+    let automount = if engine_opts.dry_run || engine_opts.safe_mode {
+        Default::default()
+    } else {
+        settings.automount()?
+    };
+    let clock = engine_opts.clock.clone();
+    ordne_lib::migrate::with_plan_drives(db, &plan_ids, &automount, &clock, |db| {
+        let mut engine = MigrationEngine::new(db, engine_opts);
+        for plan_id in &plan_ids {
+            engine.execute_plan(*plan_id)?;
+        }
+        Ok(())
+    })
 }
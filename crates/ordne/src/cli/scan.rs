@@ -1,19 +1,223 @@
 use ordne_lib::{Result, OrdneError};
+use clap::Subcommand;
+use super::table::OutputTable;
+use comfy_table::{Cell, Color};
 use console::style;
-use ordne_lib::{Database, SqliteDatabase, scan_directory, ScanOptions, db::drives::mark_drive_scanned};
-use std::path::PathBuf;
+use ordne_lib::{
+    Database, SqliteDatabase, ScanStats, scan_directory, ScanOptions,
+    db::drives::mark_drive_scanned,
+    db::owners::record_owners,
+    db::scan_errors::{list_scan_errors, replace_scan_errors, ScanError},
+    index::retry_scan_errors,
+};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+#[derive(Subcommand)]
+pub enum ScanCommands {
+    #[command(about = "List paths the last scans could not read")]
+    Errors {
+        #[arg(help = "Drive label")]
+        drive: String,
+    },
+}
+
+pub fn handle_scan_subcommand(db: &SqliteDatabase, command: ScanCommands) -> Result<()> {
+    match command {
+        ScanCommands::Errors { drive } => show_scan_errors(db, &drive),
+    }
+}
+
+fn show_scan_errors(db: &SqliteDatabase, label: &str) -> Result<()> {
+    let drive = db.get_drive(label)?
+        .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+    let errors = list_scan_errors(db.conn(), drive.id)?;
+
+    if errors.is_empty() {
+        println!("{}", style(format!("No scan errors recorded for '{}'", label)).green());
+        return Ok(());
+    }
+
+    let mut table = OutputTable::new(vec!["Path", "Kind", "Error", "Detected"]);
+    for error in &errors {
+        let kind = if error.kind == "permission_denied" {
+            Cell::new(&error.kind).fg(Color::Yellow)
+        } else {
+            Cell::new(&error.kind).fg(Color::Red)
+        };
+        table.add_row(vec![
+            Cell::new(if error.path.is_empty() { "/" } else { &error.path }),
+            kind,
+            Cell::new(&error.message),
+            Cell::new(crate::util::format::format_timestamp(&error.detected_at)),
+        ]);
+    }
+    println!("{}", table);
+    println!(
+        "{}",
+        style(format!(
+            "Fix permissions, then retry only these paths: ordne scan {} --retry-errors",
+            label
+        ))
+        .dim()
+    );
+
+    Ok(())
+}
+
+pub fn handle_retry_errors(
+    db: &mut SqliteDatabase,
+    label: &str,
+    capture_atime: bool,
+) -> Result<()> {
+    let drive = db.get_drive(label)?
+        .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+    if !drive.is_online {
+        return Err(OrdneError::DriveOffline(label.to_string()));
+    }
+
+    let options = ScanOptions {
+        capture_atime,
+        ..Default::default()
+    };
+    let stats = retry_scan_errors(db, &drive, options)?;
+
+    if stats.paths_retried == 0 {
+        println!("{}", style(format!("No scan errors recorded for '{}'", label)).green());
+        return Ok(());
+    }
+
+    println!("{} Retried {} paths on '{}'", style("✓").green(), stats.paths_retried, label);
+    println!("  Files indexed: {}", style(stats.files_scanned).cyan());
+    println!("  Resolved: {}", style(stats.resolved).green());
+    if stats.remaining.is_empty() {
+        return Ok(());
+    }
+    println!("  Still failing: {}", style(stats.remaining.len()).yellow());
+
+    let mount = Path::new(drive.mount_path.as_deref().unwrap_or("/"));
+    let denied: Vec<&ScanError> = stats
+        .remaining
+        .iter()
+        .filter(|e| e.kind == "permission_denied")
+        .collect();
+    if denied.is_empty() {
+        println!("  {}", style(format!("Review them with `ordne scan errors {}`", label)).dim());
+        return Ok(());
+    }
+
+    if is_root() {
+        println!(
+            "  {}",
+            style("Paths are denied even to root; check ACLs, SELinux labels or mount options").dim()
+        );
+        return Ok(());
+    }
+
+    let system = denied
+        .iter()
+        .filter(|e| owned_by_root(&mount.join(&e.path)))
+        .count();
+    let Some(tool) = escalation_tool() else {
+        println!(
+            "  {}",
+            style("Fix ownership or permissions, or rerun as root, then retry").dim()
+        );
+        return Ok(());
+    };
+
+    if system > 0 {
+        println!(
+            "  {} {} denied paths belong to root",
+            style("!").yellow(),
+            system
+        );
+    }
+    println!("  Retry them with elevated privileges:");
+    println!("    {}", escalated_command(db, tool, label, capture_atime)?);
+    println!(
+        "  {}",
+        style("Files it creates next to the database will belong to root").dim()
+    );
+
+    Ok(())
+}
+
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Whether the path, or its nearest ancestor we can stat, is owned by root.
+fn owned_by_root(path: &Path) -> bool {
+    path.ancestors()
+        .find_map(|p| std::fs::symlink_metadata(p).ok())
+        .is_some_and(|m| m.uid() == 0)
+}
+
+/// pkexec in a graphical session, where it can prompt; sudo otherwise.
+fn escalation_tool() -> Option<&'static str> {
+    let graphical = std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let candidates = if graphical { ["pkexec", "sudo"] } else { ["sudo", "pkexec"] };
+    let path = std::env::var_os("PATH")?;
+    candidates
+        .into_iter()
+        .find(|tool| std::env::split_paths(&path).any(|dir| dir.join(tool).is_file()))
+}
+
+/// The command that retries this scan under `tool`, against this database and
+/// with the same options, for the user to run. It is not run for them: as
+/// root it would read root's configuration rather than theirs.
+fn escalated_command(db: &SqliteDatabase, tool: &str, label: &str, capture_atime: bool) -> Result<String> {
+    let exe = std::env::current_exe()?;
+    let mut args = vec![tool.to_string(), exe.to_string_lossy().into_owned()];
+    if let Some(db_path) = db.conn().path().filter(|p| !p.is_empty()) {
+        args.extend(["--db".to_string(), db_path.to_string()]);
+    }
+    args.extend(["scan".to_string(), label.to_string(), "--retry-errors".to_string()]);
+    if capture_atime {
+        args.push("--atime".to_string());
+    }
+    Ok(args.iter().map(|a| shell_quote(a)).collect::<Vec<_>>().join(" "))
+}
+
+/// `arg` quoted for a POSIX shell when it needs to be.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Store the scan's failures, replacing earlier ones under the same path,
+/// and the owner names it resolved.
+fn record_scan(db: &SqliteDatabase, drive_id: i64, stats: &ScanStats) -> Result<()> {
+    mark_drive_scanned(db.conn(), drive_id)?;
+    replace_scan_errors(db.conn(), drive_id, &stats.scope, &stats.failures)?;
+    record_owners(db.conn(), &stats.owners)
+}
 
 pub fn handle_scan_command(
     db: &mut SqliteDatabase,
     drive_label: Option<String>,
     path: Option<PathBuf>,
     scan_all: bool,
+    capture_atime: bool,
     verbose: bool,
 ) -> Result<()> {
+    let options = ScanOptions {
+        capture_atime,
+        ..Default::default()
+    };
+
     if scan_all {
-        scan_all_drives(db, verbose)
+        scan_all_drives(db, options, verbose)
     } else if let Some(label) = drive_label {
-        scan_single_drive(db, &label, path.as_deref(), verbose)
+        scan_single_drive(db, &label, path.as_deref(), options, verbose)
     } else {
         Err(OrdneError::Config(
             "Must specify either a drive label or --all".to_string(),
@@ -25,6 +229,7 @@ fn scan_single_drive(
     db: &mut SqliteDatabase,
     label: &str,
     subpath: Option<&std::path::Path>,
+    options: ScanOptions,
     _verbose: bool,
 ) -> Result<()> {
     let drive = db.get_drive(label)?
@@ -55,9 +260,9 @@ fn scan_single_drive(
         scan_path.display()
     );
 
-    let stats = scan_directory(db, drive.id, &scan_path, ScanOptions::default())?;
+    let stats = scan_directory(db, drive.id, &scan_path, options.clone())?;
 
-    mark_drive_scanned(db.conn(), drive.id)?;
+    record_scan(db, drive.id, &stats)?;
 
     println!("\n{} Scan completed", style("✓").green());
     println!("  Files indexed: {}", style(stats.files_scanned).cyan());
@@ -65,17 +270,32 @@ fn scan_single_drive(
     println!("  Total size: {}", style(crate::util::format::format_bytes(stats.bytes_scanned as i64)).cyan());
 
     if stats.errors > 0 {
-        println!("  Errors: {}", style(stats.errors).yellow());
+        println!(
+            "  Errors: {} (see `ordne scan errors {}`, then retry with --retry-errors)",
+            style(stats.errors).yellow(),
+            label
+        );
+    }
+
+    if stats.excluded > 0 {
+        println!("  Excluded by .ordne/ordne.toml: {}", style(stats.excluded).dim());
     }
 
     if stats.symlinks_found > 0 {
         println!("  Symlinks: {}", style(stats.symlinks_found).dim());
     }
 
+    if options.capture_atime && !stats.atime_captured {
+        println!(
+            "  {} Access times not recorded: the mount does not keep them (noatime)",
+            style("!").yellow()
+        );
+    }
+
     Ok(())
 }
 
-fn scan_all_drives(db: &mut SqliteDatabase, _verbose: bool) -> Result<()> {
+fn scan_all_drives(db: &mut SqliteDatabase, options: ScanOptions, _verbose: bool) -> Result<()> {
     let drives = db.list_drives()?;
     let online_drives: Vec<_> = drives.into_iter().filter(|d| d.is_online).collect();
 
@@ -121,9 +341,9 @@ fn scan_all_drives(db: &mut SqliteDatabase, _verbose: bool) -> Result<()> {
             style(&drive.label).bold()
         );
 
-        match scan_directory(db, drive.id, &mount_path, ScanOptions::default()) {
+        match scan_directory(db, drive.id, &mount_path, options.clone()) {
             Ok(stats) => {
-                mark_drive_scanned(db.conn(), drive.id)?;
+                record_scan(db, drive.id, &stats)?;
                 total_files += stats.files_scanned;
                 total_errors += stats.errors;
                 println!(
@@ -144,6 +364,7 @@ fn scan_all_drives(db: &mut SqliteDatabase, _verbose: bool) -> Result<()> {
 
     if total_errors > 0 {
         println!("  Total errors: {}", style(total_errors).yellow());
+        println!("  {}", style("Review them with `ordne scan errors <drive>`").dim());
     }
 
     Ok(())
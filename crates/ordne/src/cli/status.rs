@@ -1,13 +1,22 @@
 use crate::Result;
-use comfy_table::{Table, presets::UTF8_FULL, Cell, Color};
+use super::table::OutputTable;
+use comfy_table::Cell;
 use console::style;
-use ordne_lib::{Database, SqliteDatabase, db::{files::get_category_stats, duplicates::get_duplicate_statistics}};
+use ordne_lib::{Backend, Database, SqliteDatabase, migrate::get_free_space, db::{files::get_category_stats, duplicates::get_duplicate_statistics}};
+use ordne_lib::config::Settings;
 use crate::cli::helpers::get_drive_statistics;
 
-pub fn handle_status_command(db: &SqliteDatabase, show_space: bool) -> Result<()> {
+pub fn handle_status_command(db: &SqliteDatabase, settings: &Settings, show_space: bool) -> Result<()> {
     println!("\n{}", style("Ordne System Status").bold().cyan());
     println!("{}\n", style("═".repeat(60)).dim());
 
+    if settings.safe_mode() {
+        println!(
+            "{} Safe mode is on: migrations only simulate and rollbacks are refused\n",
+            style("!").yellow().bold()
+        );
+    }
+
     show_drive_summary(db)?;
     show_file_summary(db)?;
     show_duplicate_summary(db)?;
@@ -25,7 +34,8 @@ pub fn handle_status_command(db: &SqliteDatabase, show_space: bool) -> Result<()
 fn show_drive_summary(db: &SqliteDatabase) -> Result<()> {
     let drives = db.list_drives()?;
     let online_count = drives.iter().filter(|d| d.is_online).count();
-    let offline_count = drives.len() - online_count;
+    let cold_count = drives.iter().filter(|d| !d.is_online && d.is_cold).count();
+    let offline_count = drives.len() - online_count - cold_count;
 
     println!("{}", style("Drives").bold());
     println!("  Total: {}", drives.len());
@@ -33,6 +43,9 @@ fn show_drive_summary(db: &SqliteDatabase) -> Result<()> {
     if offline_count > 0 {
         println!("  Offline: {}", style(offline_count).red());
     }
+    if cold_count > 0 {
+        println!("  Cold: {}", style(cold_count).blue());
+    }
     println!();
 
     Ok(())
@@ -165,31 +178,44 @@ fn show_space_details(db: &SqliteDatabase) -> Result<()> {
     println!("{}", style("Space Details").bold().cyan());
     println!("{}", style("─".repeat(60)).dim());
 
-    let mut table = Table::new();
-    table.load_preset(UTF8_FULL);
-    table.set_header(vec![
-        Cell::new("Drive").fg(Color::Cyan),
-        Cell::new("Used").fg(Color::Cyan),
-        Cell::new("Capacity").fg(Color::Cyan),
-        Cell::new("Files").fg(Color::Cyan),
-    ]);
+    let mut table = OutputTable::new(vec!["Drive", "Used", "Capacity", "Writable", "Files"]);
+    let mut notes = Vec::new();
 
     for drive in drives {
         let file_stats = get_drive_statistics(db, drive.id)?;
 
         let capacity_str = drive.total_bytes
-            .map(|b| crate::util::format::format_bytes(b))
+            .map(crate::util::format::format_bytes)
             .unwrap_or_else(|| "Unknown".to_string());
 
+        // Space outside root's reserve and within the user's quota.
+        let space = drive
+            .mount_path
+            .as_ref()
+            .filter(|_| drive.is_online && drive.backend == Backend::Local)
+            .and_then(|mount| get_free_space(mount).ok());
+        let writable_str = space
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |s| crate::util::format::format_bytes(s.writable_bytes() as i64));
+        if let Some(space) = &space {
+            if space.quota_available_bytes.is_some_and(|q| q < space.available_bytes) {
+                notes.push(format!("{}: limited by your disk quota", drive.label));
+            }
+        }
+
         table.add_row(vec![
             Cell::new(&drive.label),
             Cell::new(crate::util::format::format_bytes(file_stats.total_bytes)),
             Cell::new(capacity_str),
+            Cell::new(writable_str),
             Cell::new(file_stats.file_count),
         ]);
     }
 
     println!("{}", table);
+    for note in notes {
+        println!("  {}", style(note).dim());
+    }
 
     Ok(())
 }
@@ -0,0 +1,44 @@
+use crate::Result;
+use console::style;
+use ordne_lib::{
+    SqliteDatabase,
+    db::jobs::JobTimer,
+    util::format::{format_bytes, format_duration},
+};
+
+/// Measures one command for the jobs table and the optional summary footer.
+pub struct CommandTimer {
+    timer: JobTimer,
+}
+
+impl CommandTimer {
+    pub fn start(db: &SqliteDatabase, command: String) -> Result<Self> {
+        Ok(Self { timer: JobTimer::start(db.conn(), command)? })
+    }
+
+    /// Record the job, with who ran it and its arguments, and, if asked,
+    /// print the footer.
+    pub fn finish(self, db: &SqliteDatabase, result: &Result<()>, print: bool) -> Result<()> {
+        let job = self.timer.finish(
+            db.conn(),
+            result.as_ref().err().map(|e| e.to_string()),
+            db.attribution(),
+            super::command_parameters(),
+        )?;
+
+        if print {
+            let sign = if job.db_size_delta < 0 { "-" } else { "+" };
+            println!(
+                "\n{} {} in {} · {} rows touched · {} processed · database {}{}",
+                style("──").dim(),
+                job.command,
+                format_duration(std::time::Duration::from_millis(job.elapsed_ms.max(0) as u64)),
+                job.rows_touched,
+                format_bytes(job.bytes_processed),
+                sign,
+                format_bytes(job.db_size_delta.abs())
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use comfy_table::{presets::UTF8_FULL, Cell, Color, ContentArrangement, Row, Table};
+use std::fmt;
+
+/// The table every command prints through.
+///
+/// Bordered, with cyan headers, and fitted to the terminal: each row stays on
+/// one line and cells that do not fit are cut short with `…`. Colour follows
+/// `console`, so `--no-color`, `NO_COLOR` and piping to a file all turn it off.
+pub struct OutputTable {
+    table: Table,
+}
+
+impl OutputTable {
+    pub fn new<I, T>(headers: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<Cell>,
+    {
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_truncation_indicator("…")
+            .set_header(headers.into_iter().map(|h| h.into().fg(Color::Cyan)).collect::<Vec<_>>());
+
+        let term = console::Term::stdout();
+        if term.is_term() {
+            if let Some((_, columns)) = term.size_checked() {
+                table.set_width(columns);
+            }
+        }
+        if !console::colors_enabled() {
+            table.force_no_tty();
+        }
+        Self { table }
+    }
+
+    pub fn add_row<T: Into<Row>>(&mut self, row: T) -> &mut Self {
+        let mut row = row.into();
+        row.max_height(1);
+        self.table.add_row(row);
+        self
+    }
+}
+
+impl fmt::Display for OutputTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.table.fmt(f)
+    }
+}
@@ -0,0 +1,38 @@
+use console::style;
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use ordne_lib::undo::{pending_undo, undo};
+use ordne_lib::Clock;
+use ordne_lib::{OrdneError, Result, SqliteDatabase};
+
+pub fn handle_undo_command(db: &mut SqliteDatabase, within: &str, yes: bool) -> Result<()> {
+    let within = crate::util::units::parse_duration(within)
+        .map_err(|e| OrdneError::Config(format!("Invalid --within: {}", e)))?;
+
+    let clock = Clock::default();
+    let Some(entry) = pending_undo(db, within, clock.now())? else {
+        println!("{}", style("Nothing to undo").green());
+        return Ok(());
+    };
+
+    println!(
+        "{} Last operation: {} ({}, {})",
+        style(">>>").cyan(),
+        entry.details.as_deref().unwrap_or(&entry.action),
+        entry.action,
+        crate::util::format::format_timestamp(&entry.timestamp)
+    );
+
+    if !yes
+        && !Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("Undo it?")
+            .default(false)
+            .interact()?
+    {
+        println!("{}", style("Nothing undone").yellow());
+        return Ok(());
+    }
+
+    let summary = undo(db, &clock, &entry)?;
+    println!("{} {}", style("✓").green(), summary);
+    Ok(())
+}
@@ -1,10 +1,176 @@
 use ordne_lib::{Result, OrdneError};
+use clap::Subcommand;
+use super::table::OutputTable;
+use comfy_table::Cell;
 use console::style;
 use ordne_lib::{
-    Database, SqliteDatabase,
-    index::hash_file_blake3,
+    Database, File, PlanStatus, PlansDatabase, SqliteDatabase, VerificationDatabase,
+    VerificationFailure,
+    config::Settings,
+    db::replicas::check_replicas,
+    index::{check_catalog, hash_file_blake3, DriftKind},
+    migrate::{run_due_verification_jobs, run_verification_job, VerificationJobResult},
+    util::format::format_bytes,
 };
 
+#[derive(Subcommand)]
+pub enum VerifyCommands {
+    #[command(about = "Check categories are on as many drives as ordne.toml requires")]
+    Replicas {
+        #[arg(long, help = "Print the report as JSON")]
+        json: bool,
+    },
+    #[command(about = "Stat and hash a random sample of indexed files and report drift per drive")]
+    Catalog {
+        #[arg(long, default_value_t = 1000, help = "Number of files to sample")]
+        sample: usize,
+        #[arg(long, help = "Only sample files on this drive")]
+        drive: Option<String>,
+        #[arg(long, help = "Only compare size and modification time, without hashing")]
+        no_hash: bool,
+        #[arg(long, help = "Print the report as JSON")]
+        json: bool,
+    },
+}
+
+pub fn handle_verify_subcommand(db: &SqliteDatabase, settings: &Settings, command: VerifyCommands) -> Result<()> {
+    match command {
+        VerifyCommands::Replicas { json } => verify_replicas(db, settings, json),
+        VerifyCommands::Catalog { sample, drive, no_hash, json } => {
+            verify_catalog(db, sample, drive.as_deref(), !no_hash, json)
+        }
+    }
+}
+
+fn verify_replicas(db: &SqliteDatabase, settings: &Settings, json: bool) -> Result<()> {
+    let requirements = settings.replica_requirements()?;
+    if requirements.is_empty() {
+        return Err(OrdneError::Config(
+            "No replica requirements; add a [replicas] table to ordne.toml, e.g. photos = 2"
+                .to_string(),
+        ));
+    }
+
+    let reports = check_replicas(db.conn(), &requirements)?;
+    let shortfalls: usize = reports.iter().map(|r| r.shortfalls.len()).sum();
+    if json {
+        let json = serde_json::to_string_pretty(&reports)
+            .map_err(|e| OrdneError::Config(format!("Failed to serialize report: {}", e)))?;
+        println!("{json}");
+        return failures(shortfalls, "contents are on fewer drives than required");
+    }
+
+    for report in &reports {
+        let status = if report.shortfalls.is_empty() {
+            style("ok".to_string()).green()
+        } else {
+            style(format!("{} short", report.shortfalls.len())).red()
+        };
+        println!(
+            "\n{} (at least {} drives): {} contents, {}",
+            style(&report.category).bold(),
+            report.required_drives,
+            report.contents,
+            status
+        );
+        if report.unhashed_files > 0 {
+            println!(
+                "  {}",
+                style(format!("{} files are unhashed and were not checked", report.unhashed_files)).yellow()
+            );
+        }
+        if report.shortfalls.is_empty() {
+            continue;
+        }
+
+        let mut table = OutputTable::new(vec!["Path", "Size", "Drives"]);
+        for shortfall in &report.shortfalls {
+            table.add_row(vec![
+                Cell::new(&shortfall.path),
+                Cell::new(format_bytes(shortfall.size_bytes)),
+                Cell::new(shortfall.drives.join(", ")),
+            ]);
+        }
+        println!("{}", table);
+    }
+
+    failures(shortfalls, "contents are on fewer drives than required")
+}
+
+fn verify_catalog(
+    db: &SqliteDatabase,
+    sample: usize,
+    drive_label: Option<&str>,
+    hash: bool,
+    json: bool,
+) -> Result<()> {
+    let drive_id = match drive_label {
+        Some(label) => {
+            let drive = db
+                .get_drive(label)?
+                .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+            if !drive.is_online {
+                return Err(OrdneError::DriveOffline(label.to_string()));
+            }
+            Some(drive.id)
+        }
+        None => None,
+    };
+
+    let reports = check_catalog(db, drive_id, sample, hash)?;
+    let drifted: usize = reports.iter().map(|r| r.drifted.len()).sum();
+    if json {
+        let json = serde_json::to_string_pretty(&reports)
+            .map_err(|e| OrdneError::Config(format!("Failed to serialize report: {}", e)))?;
+        println!("{json}");
+        return failures(drifted, "sampled files no longer match the catalog");
+    }
+
+    if reports.is_empty() {
+        println!("{}", style("No indexed files on online local drives to sample").yellow());
+        return Ok(());
+    }
+
+    let kinds = [
+        DriftKind::Missing,
+        DriftKind::SizeChanged,
+        DriftKind::ModifiedChanged,
+        DriftKind::HashChanged,
+        DriftKind::Unreadable,
+    ];
+    let mut header = vec!["Drive", "Sampled", "OK", "Unhashed"];
+    header.extend(kinds.iter().map(|k| k.as_str()));
+    header.extend(["Drift", "Drift (95% bound)"]);
+    let mut table = OutputTable::new(header);
+    for report in &reports {
+        let mut row = vec![
+            Cell::new(&report.label),
+            Cell::new(report.sampled),
+            Cell::new(report.ok),
+            Cell::new(report.unhashed),
+        ];
+        row.extend(kinds.iter().map(|k| Cell::new(report.count(*k))));
+        row.push(Cell::new(format!("{:.1}%", report.drift_rate * 100.0)));
+        row.push(Cell::new(format!("≤ {:.1}%", report.drift_upper_bound * 100.0)));
+        table.add_row(row);
+    }
+    println!("{}", table);
+
+    for report in reports.iter().filter(|r| !r.drifted.is_empty()) {
+        println!("
+{}", style(&report.label).bold());
+        for drift in &report.drifted {
+            println!("  {} {}: {}", style("×").red(), drift.kind.as_str(), drift.path);
+        }
+    }
+    if drifted > 0 {
+        println!("
+Run 'ordne scan <drive_label>' to bring the catalog up to date");
+    }
+
+    failures(drifted, "sampled files no longer match the catalog")
+}
+
 pub fn handle_verify_command(
     db: &mut SqliteDatabase,
     drive_label: Option<String>,
@@ -17,6 +183,74 @@ pub fn handle_verify_command(
     }
 }
 
+pub fn handle_verify_due_command(db: &mut SqliteDatabase) -> Result<()> {
+    let results = run_due_verification_jobs(db, chrono::Utc::now())?;
+
+    if results.is_empty() {
+        println!("{}", style("No plan verifications are due").yellow());
+        if let Some(next) = db.list_pending_verification_jobs(None)?.first() {
+            println!(
+                "  Next: plan #{} at {}",
+                next.plan_id,
+                crate::util::format::format_timestamp(&next.due_at)
+            );
+        }
+        return Ok(());
+    }
+
+    for result in &results {
+        print_job_result(result);
+    }
+
+    failures(
+        results.iter().map(|r| r.files_failed as usize).sum(),
+        "destinations failed re-verification",
+    )
+}
+
+pub fn handle_verify_plan_command(db: &mut SqliteDatabase, plan_id: i64) -> Result<()> {
+    let plan = db.get_plan(plan_id)?.ok_or(OrdneError::PlanNotFound(plan_id))?;
+    if plan.status != PlanStatus::Completed {
+        return Err(OrdneError::Config(format!(
+            "Plan #{} is {} (only completed plans can be re-verified)",
+            plan_id,
+            plan.status.as_str()
+        )));
+    }
+
+    let now = chrono::Utc::now();
+    let job_id = db.schedule_verification_job(plan_id, now)?;
+    let job = db
+        .list_pending_verification_jobs(Some(now))?
+        .into_iter()
+        .find(|job| job.id == job_id)
+        .ok_or_else(|| OrdneError::Config(format!("Verification job {} vanished", job_id)))?;
+
+    let result = run_verification_job(db, &job)?;
+    print_job_result(&result);
+    failures(result.files_failed as usize, "destinations failed re-verification")
+}
+
+fn print_job_result(result: &VerificationJobResult) {
+    if result.files_failed == 0 {
+        println!(
+            "{} Plan #{}: {} destinations re-verified",
+            style("✓").green(),
+            result.plan_id,
+            result.files_checked
+        );
+    } else {
+        println!(
+            "{} Plan #{}: {} of {} destinations failed re-verification",
+            style("×").red(),
+            result.plan_id,
+            result.files_failed,
+            result.files_checked
+        );
+        println!("  Deletes that depend on these copies are blocked. Run 'ordne inbox' to review");
+    }
+}
+
 fn verify_drive(db: &mut SqliteDatabase, label: &str, verbose: bool) -> Result<()> {
     let drive = db.get_drive(label)?
         .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
@@ -68,6 +302,7 @@ fn verify_drive(db: &mut SqliteDatabase, label: &str, verbose: bool) -> Result<(
 
         if !file_path.exists() {
             missing += 1;
+            record_failure(db, &file, "missing", None)?;
             if verbose {
                 println!("{} Missing: {}", style("×").red(), file.path);
             }
@@ -84,6 +319,7 @@ fn verify_drive(db: &mut SqliteDatabase, label: &str, verbose: bool) -> Result<(
                         }
                     } else {
                         mismatches += 1;
+                        record_failure(db, &file, "mismatch", Some(&new_hash))?;
                         println!(
                             "{} Mismatch: {}",
                             style("×").red(),
@@ -104,6 +340,7 @@ fn verify_drive(db: &mut SqliteDatabase, label: &str, verbose: bool) -> Result<(
             }
             Err(e) => {
                 errors += 1;
+                record_failure(db, &file, "unreadable", None)?;
                 if verbose {
                     println!("{} Error reading {}: {}", style("!").yellow(), file.path, e);
                 }
@@ -121,6 +358,9 @@ fn verify_drive(db: &mut SqliteDatabase, label: &str, verbose: bool) -> Result<(
     if mismatches > 0 {
         println!("  Mismatches: {}", style(mismatches).red());
     }
+    if mismatches + missing + errors > 0 {
+        println!("\nRun 'ordne inbox' to review and acknowledge failures");
+    }
     if missing > 0 {
         println!("  Missing: {}", style(missing).yellow());
     }
@@ -128,7 +368,7 @@ fn verify_drive(db: &mut SqliteDatabase, label: &str, verbose: bool) -> Result<(
         println!("  Errors: {}", style(errors).yellow());
     }
 
-    Ok(())
+    failures(mismatches + missing + errors, "files failed verification")
 }
 
 fn verify_all_drives(db: &mut SqliteDatabase, verbose: bool) -> Result<()> {
@@ -170,20 +410,18 @@ fn verify_all_drives(db: &mut SqliteDatabase, verbose: bool) -> Result<()> {
                 continue;
             }
 
-            match hash_file_blake3(&file_path) {
-                Ok(new_hash) => {
-                    if let Some(stored_hash) = &file.blake3_hash {
-                        if &new_hash == stored_hash {
-                            verified += 1;
-                        } else {
-                            mismatches += 1;
-                            if verbose {
-                                println!("  {} Mismatch: {}", style("×").red(), file.path);
-                            }
+            if let Ok(new_hash) = hash_file_blake3(&file_path) {
+                if let Some(stored_hash) = &file.blake3_hash {
+                    if &new_hash == stored_hash {
+                        verified += 1;
+                    } else {
+                        mismatches += 1;
+                        record_failure(db, &file, "mismatch", Some(&new_hash))?;
+                        if verbose {
+                            println!("  {} Mismatch: {}", style("×").red(), file.path);
                         }
                     }
                 }
-                Err(_) => {}
             }
         }
 
@@ -200,5 +438,37 @@ fn verify_all_drives(db: &mut SqliteDatabase, verbose: bool) -> Result<()> {
         println!("  Total mismatches: {}", style(total_mismatches).red());
     }
 
+    failures(total_mismatches, "files failed verification")
+}
+
+/// Turn a non-zero failure count into an error, so the command exits with the
+/// verification exit code after printing its report.
+fn failures(count: usize, what: &str) -> Result<()> {
+    if count > 0 {
+        return Err(OrdneError::VerificationFailed(format!("{} {}", count, what)));
+    }
+    Ok(())
+}
+
+/// Queue a failure for the inbox so it is not lost once this output scrolls away.
+fn record_failure(
+    db: &mut SqliteDatabase,
+    file: &File,
+    reason: &str,
+    actual_hash: Option<&str>,
+) -> Result<()> {
+    db.record_verification_failure(&VerificationFailure {
+        id: 0,
+        file_id: Some(file.id),
+        plan_id: None,
+        step_id: None,
+        drive_id: Some(file.drive_id),
+        path: file.abs_path.clone(),
+        expected_hash: file.blake3_hash.clone().or_else(|| file.md5_hash.clone()),
+        actual_hash: actual_hash.map(str::to_string),
+        reason: reason.to_string(),
+        detected_at: chrono::Utc::now(),
+        acknowledged_at: None,
+    })?;
     Ok(())
 }
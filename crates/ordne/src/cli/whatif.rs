@@ -0,0 +1,102 @@
+use crate::Result;
+use super::table::OutputTable;
+use comfy_table::Cell;
+use console::style;
+use ordne_lib::{
+    OrdneError, SqliteDatabase, WhatIf, WhatIfReport, load_effective_policy,
+    util::format::format_bytes,
+};
+use ordne_lib::config::Settings;
+use std::path::PathBuf;
+
+pub fn handle_what_if_command(
+    db: &SqliteDatabase,
+    settings: &Settings,
+    plans: Vec<i64>,
+    policy: Option<PathBuf>,
+    json: bool,
+) -> Result<()> {
+    if plans.is_empty() && policy.is_none() {
+        return Err(OrdneError::Config(
+            "Specify --plan <id> or --policy <file>".to_string(),
+        ));
+    }
+
+    let mut what_if = WhatIf::new(db)?;
+
+    if let Some(path) = policy {
+        let (policy, rules) = load_effective_policy(db, &path)?;
+        policy.validate()?;
+        crate::cli::run_policy::apply_classification_rules(
+            what_if.db_mut(),
+            &rules,
+            policy.scope.as_ref(),
+        )?;
+        what_if.apply_policy(&policy, settings)?;
+    }
+    for plan_id in plans {
+        what_if.apply_plan(plan_id)?;
+    }
+
+    let report = what_if.report()?;
+    if json {
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| OrdneError::Config(format!("Failed to serialize report: {}", e)))?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    show_report(&report);
+    Ok(())
+}
+
+fn show_report(report: &WhatIfReport) {
+    println!("\n{}", style("What-if").bold().cyan());
+    println!("{}", style("Nothing below has been changed on disk or in the catalog").dim());
+
+    println!("\n{}", style("Plans applied").bold());
+    for plan in &report.plans {
+        println!(
+            "  {} ({} files, {})",
+            plan.description.as_deref().unwrap_or("(no description)"),
+            plan.total_files,
+            format_bytes(plan.total_bytes)
+        );
+    }
+    println!("  {} steps in total", report.steps_applied);
+
+    println!("\n{}", style("Drive usage").bold());
+    let mut table = OutputTable::new(vec!["Drive", "Capacity", "Before", "After", "Change", "Free after"]);
+    for (before, after) in report.before.drives.iter().zip(&report.after.drives) {
+        let change = after.used_bytes - before.used_bytes;
+        let sign = if change > 0 { "+" } else if change < 0 { "-" } else { "" };
+        table.add_row(vec![
+            Cell::new(&after.label),
+            Cell::new(after.capacity_bytes.map_or("-".to_string(), format_bytes)),
+            Cell::new(format_bytes(before.used_bytes)),
+            Cell::new(format_bytes(after.used_bytes)),
+            Cell::new(format!("{}{}", sign, format_bytes(change.abs()))),
+            Cell::new(
+                after
+                    .capacity_bytes
+                    .map_or("-".to_string(), |c| format_bytes(c - after.used_bytes)),
+            ),
+        ]);
+    }
+    println!("{}", table);
+    println!("{}", style("Usage counts catalogued files only").dim());
+
+    let (before, after) = (&report.before.duplicates, &report.after.duplicates);
+    println!("\n{}", style("Duplicates").bold());
+    println!("  Groups: {} -> {}", before.groups, after.groups);
+    println!("  Duplicate files: {} -> {}", before.duplicate_files, after.duplicate_files);
+    println!(
+        "  Wasted space: {} -> {}",
+        format_bytes(before.wasted_bytes),
+        format_bytes(after.wasted_bytes)
+    );
+    println!(
+        "  Cross-drive groups: {} -> {}",
+        before.cross_drive_groups, after.cross_drive_groups
+    );
+}
@@ -1,7 +1,21 @@
+use crate::classify::ClassificationRules;
+use crate::db::{Ledger, SqliteDatabase};
 use crate::error::{OrdneError, Result};
-use std::path::PathBuf;
+use crate::index::device::DeviceClass;
+use crate::index::exclude::ExcludeRules;
+use crate::index::hasher::{HashSettings, HashTuning};
+use crate::migrate::{AfterPlan, Automount, DeleteLimits, PlannerOptions};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use xdg::BaseDirectories;
 
+/// Set to `1` to force every migration into a dry run.
+pub const SAFE_MODE_ENV: &str = "ORDNE_SAFE_MODE";
+
+/// Settings a drive carries at its root, so they travel with it.
+pub const DRIVE_CONFIG_FILE: &str = ".ordne/ordne.toml";
+
 pub struct Config {
     pub db_path: PathBuf,
     pub config_path: Option<PathBuf>,
@@ -20,16 +34,17 @@ impl Config {
                 .map_err(|e| OrdneError::Config(format!("Failed to create data directory: {}", e)))?
         };
 
-        let config_path = BaseDirectories::with_prefix("ordne")
-            .ok()
-            .and_then(|xdg| xdg.find_config_file("ordne.toml"));
-
         Ok(Self {
             db_path,
-            config_path,
+            config_path: default_config_path(),
         })
     }
 
+    /// The settings in [`Config::config_path`], read once.
+    pub fn settings(&self) -> Settings {
+        Settings::load(self.config_path.as_deref())
+    }
+
     pub fn ensure_db_directory(&self) -> Result<()> {
         if let Some(parent) = self.db_path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -38,10 +53,462 @@ impl Config {
     }
 }
 
+/// `~/.config/ordne/ordne.toml`, if it exists.
+fn default_config_path() -> Option<PathBuf> {
+    BaseDirectories::with_prefix("ordne")
+        .ok()
+        .and_then(|xdg| xdg.find_config_file("ordne.toml"))
+}
+
+/// `~/.config/ordne/ordne.toml`, read and parsed once.
+///
+/// Sections stay TOML tables until asked for, so a mistake in one only fails
+/// the commands that use it. A file that cannot be read or parsed fails every
+/// section and turns safe mode on.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    safe_mode: Option<toml::Value>,
+    replicas: Option<toml::Table>,
+    hashing: Option<toml::Table>,
+    dedup: Option<toml::Table>,
+    delete_limits: Option<toml::Table>,
+    automount: Option<toml::Table>,
+    ledger: Option<toml::Table>,
+    #[serde(skip)]
+    error: Option<String>,
+}
+
+impl Settings {
+    /// Settings from `path`, or the defaults when there is no config file.
+    pub fn load(path: Option<&Path>) -> Self {
+        match path.map(std::fs::read_to_string) {
+            None => Self::default(),
+            Some(Ok(content)) => Self::from_toml(&content),
+            Some(Err(e)) => Self::failed(format!("Failed to read ordne.toml: {}", e)),
+        }
+    }
+
+    pub fn from_toml(content: &str) -> Self {
+        toml::from_str(content).unwrap_or_else(|e| Self::failed(format!("Failed to parse ordne.toml: {}", e)))
+    }
+
+    fn failed(error: String) -> Self {
+        Self {
+            error: Some(error),
+            ..Self::default()
+        }
+    }
+
+    /// The `[name]` table, if the file has one.
+    fn section<'a>(&self, table: &'a Option<toml::Table>) -> Result<Option<&'a toml::Table>> {
+        match &self.error {
+            Some(error) => Err(OrdneError::Config(error.clone())),
+            None => Ok(table.as_ref()),
+        }
+    }
+
+    /// Whether safe mode is on, via `ORDNE_SAFE_MODE=1` or `safe_mode = true`.
+    /// Either one turns it on; it is a kill switch, so neither can turn the
+    /// other off. It fails closed: a config file that cannot be read or
+    /// parsed, or a `safe_mode` that is not a boolean, turns it on.
+    pub fn safe_mode(&self) -> bool {
+        safe_mode_from(std::env::var(SAFE_MODE_ENV).ok().as_deref(), self)
+    }
+
+    /// Drives each category must stay on, from the `[replicas]` table, e.g.
+    /// `photos = 2`.
+    pub fn replica_requirements(&self) -> Result<HashMap<String, usize>> {
+        let Some(table) = self.section(&self.replicas)? else {
+            return Ok(HashMap::new());
+        };
+
+        table
+            .iter()
+            .map(|(category, drives)| match drives.as_integer() {
+                Some(n) if n >= 1 => Ok((category.clone(), n as usize)),
+                _ => Err(OrdneError::Config(format!(
+                    "Replica requirement for '{}' must be a drive count of at least 1",
+                    category
+                ))),
+            })
+            .collect()
+    }
+
+    /// How files are read for hashing, from `[hashing]`. `[hashing.nvme]`,
+    /// `[hashing.ssd]` and `[hashing.hdd]` override it for files on that
+    /// kind of device.
+    pub fn hash_tuning(&self) -> Result<HashTuning> {
+        let mut tuning = HashTuning::default();
+        let Some(table) = self.section(&self.hashing)? else {
+            return Ok(tuning);
+        };
+
+        tuning.default = hash_settings_from(table, HashSettings::default(), "[hashing]")?;
+        for (key, value) in table {
+            let Some(class_table) = value.as_table() else {
+                continue;
+            };
+            let class = DeviceClass::from_str(key)?;
+            let section = format!("[hashing.{}]", key);
+            tuning.per_class.insert(class, hash_settings_from(class_table, tuning.default, &section)?);
+        }
+        Ok(tuning)
+    }
+
+    /// Files left out of duplicate groups, from `[dedup] ignore`:
+    /// gitignore-style patterns such as `*.pack` or `.git/`, for files whose
+    /// duplication is expected.
+    pub fn dedup_ignore(&self) -> Result<ExcludeRules> {
+        let Some(patterns) = self.section(&self.dedup)?.and_then(|dedup| dedup.get("ignore")) else {
+            return Ok(ExcludeRules::default());
+        };
+
+        let patterns = patterns
+            .as_array()
+            .and_then(|patterns| patterns.iter().map(|p| p.as_str()).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| OrdneError::Config("[dedup] ignore must be a list of patterns".to_string()))?;
+        ExcludeRules::new(patterns)
+    }
+
+    /// Ceilings on what a plan, and all plans of the last 24 hours, may
+    /// delete, from `[delete_limits]`. Sizes are byte counts or strings such
+    /// as `"500GB"`.
+    pub fn delete_limits(&self) -> Result<DeleteLimits> {
+        let mut limits = DeleteLimits::default();
+        let Some(table) = self.section(&self.delete_limits)? else {
+            return Ok(limits);
+        };
+
+        let count = |key: &str| -> Result<Option<u64>> {
+            match table.get(key) {
+                None => Ok(None),
+                Some(value) => value
+                    .as_integer()
+                    .filter(|n| *n >= 1)
+                    .map(|n| Some(n as u64))
+                    .ok_or_else(|| OrdneError::Config(format!("[delete_limits] {} must be a file count of at least 1", key))),
+            }
+        };
+        let size = |key: &str| -> Result<Option<u64>> {
+            let bytes = match table.get(key) {
+                None => return Ok(None),
+                Some(toml::Value::Integer(n)) => Ok(*n),
+                Some(toml::Value::String(s)) => crate::util::units::parse_bytes(s),
+                Some(_) => Err(String::new()),
+            };
+            bytes
+                .ok()
+                .filter(|n| *n >= 1)
+                .map(|n| Some(n as u64))
+                .ok_or_else(|| OrdneError::Config(format!("[delete_limits] {} must be a size such as \"500GB\"", key)))
+        };
+
+        limits.max_files_per_plan = count("max_files_per_plan")?;
+        limits.max_bytes_per_plan = size("max_bytes_per_plan")?;
+        limits.max_files_per_day = count("max_files_per_day")?;
+        limits.max_bytes_per_day = size("max_bytes_per_day")?;
+        Ok(limits)
+    }
+
+    /// Planner defaults from these settings: the `[replicas]` to keep and
+    /// the `[delete_limits]` to stay within.
+    pub fn planner_options(&self) -> Result<PlannerOptions> {
+        Ok(PlannerOptions {
+            min_replicas: self.replica_requirements()?,
+            delete_limits: self.delete_limits()?,
+            ..Default::default()
+        })
+    }
+
+    /// Drives ordne may mount for a plan and what to do with them
+    /// afterwards, from `[automount]`.
+    pub fn automount(&self) -> Result<Automount> {
+        let mut automount = Automount::default();
+        let Some(table) = self.section(&self.automount)? else {
+            return Ok(automount);
+        };
+
+        if let Some(drives) = table.get("drives") {
+            automount.drives = drives
+                .as_array()
+                .and_then(|labels| labels.iter().map(|l| l.as_str().map(String::from)).collect())
+                .ok_or_else(|| OrdneError::Config("[automount] drives must be a list of drive labels".to_string()))?;
+        }
+        if let Some(after) = table.get("after") {
+            let after = after
+                .as_str()
+                .ok_or_else(|| OrdneError::Config("[automount] after must be a string".to_string()))?;
+            automount.after = AfterPlan::from_str(after)?;
+        }
+        Ok(automount)
+    }
+
+    /// The ledger set with `[ledger] path`, if there is one.
+    pub fn ledger(&self) -> Result<Option<Ledger>> {
+        let Some(path) = self.section(&self.ledger)?.and_then(|ledger| ledger.get("path")) else {
+            return Ok(None);
+        };
+        let path = path
+            .as_str()
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .ok_or_else(|| OrdneError::Config("[ledger] path must be an absolute path".to_string()))?;
+        Ok(Some(Ledger::new(path)))
+    }
+}
+
+fn safe_mode_from(env: Option<&str>, settings: &Settings) -> bool {
+    let env_on = env.is_some_and(|v| {
+        matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on")
+    });
+    let config_on = match &settings.error {
+        None => settings.safe_mode.as_ref().is_some_and(|v| v.as_bool() != Some(false)),
+        Some(error) => {
+            log::warn!("{}, assuming safe mode", error);
+            true
+        }
+    };
+    env_on || config_on
+}
+
+fn hash_settings_from(table: &toml::Table, base: HashSettings, section: &str) -> Result<HashSettings> {
+    let invalid = |key: &str, expected: &str| {
+        OrdneError::Config(format!("{} {} must be {}", section, key, expected))
+    };
+    let mut settings = base;
+    if let Some(size) = table.get("buffer_size") {
+        let bytes = match size {
+            toml::Value::Integer(n) => Ok(*n),
+            toml::Value::String(s) => crate::util::units::parse_bytes(s),
+            _ => Err(String::new()),
+        };
+        settings.buffer_size = bytes
+            .ok()
+            .filter(|n| *n >= 4096)
+            .ok_or_else(|| invalid("buffer_size", "a size of at least 4KiB"))? as usize;
+    }
+    if let Some(mmap) = table.get("mmap") {
+        settings.mmap = mmap.as_bool().ok_or_else(|| invalid("mmap", "true or false"))?;
+    }
+    if let Some(direct_io) = table.get("direct_io") {
+        settings.direct_io = direct_io.as_bool().ok_or_else(|| invalid("direct_io", "true or false"))?;
+    }
+    Ok(settings)
+}
+
+/// Mirror `db`'s audit entries and plan changes into the ledger set with
+/// `[ledger] path`, if there is one.
+pub fn attach_ledger(db: &mut SqliteDatabase, db_path: &Path, settings: &Settings) -> Result<()> {
+    if let Some(ledger) = settings.ledger()? {
+        if ledger.shares_filesystem_with(db_path) {
+            log::warn!(
+                "Ledger {} is on the same filesystem as the database; it will not survive losing that drive",
+                ledger.path().display()
+            );
+        }
+        db.set_ledger(ledger);
+    }
+    Ok(())
+}
+
+/// Settings from a drive's own [`DRIVE_CONFIG_FILE`].
+#[derive(Debug, Clone)]
+pub struct DriveConfig {
+    /// gitignore-style patterns, from `[scan] exclude`, for paths scans skip.
+    pub exclude: Vec<String>,
+    /// Rules for files on this drive. They win over shared rules of the same name.
+    pub rules: ClassificationRules,
+}
+
+/// Read the [`DRIVE_CONFIG_FILE`] under a drive root, if it has one.
+pub fn load_drive_config(root: &Path) -> Result<Option<DriveConfig>> {
+    let path = root.join(DRIVE_CONFIG_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => drive_config_from(&content).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn drive_config_from(content: &str) -> Result<DriveConfig> {
+    let value: toml::Value = toml::from_str(content)
+        .map_err(|e| OrdneError::Config(format!("Failed to parse {}: {}", DRIVE_CONFIG_FILE, e)))?;
+
+    let exclude = match value.get("scan").and_then(|scan| scan.get("exclude")) {
+        None => Vec::new(),
+        Some(patterns) => patterns
+            .as_array()
+            .and_then(|patterns| {
+                patterns.iter().map(|p| p.as_str().map(str::to_string)).collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| {
+                OrdneError::Config(format!(
+                    "[scan] exclude in {} must be a list of patterns",
+                    DRIVE_CONFIG_FILE
+                ))
+            })?,
+    };
+
+    Ok(DriveConfig {
+        exclude,
+        rules: ClassificationRules::from_toml(content)?,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_safe_mode_sources() {
+        assert!(!safe_mode_from(None, &Settings::default()));
+        assert!(safe_mode_from(Some("1"), &Settings::default()));
+        assert!(safe_mode_from(Some("TRUE"), &Settings::default()));
+        assert!(!safe_mode_from(Some("0"), &Settings::from_toml("[rules]")));
+        assert!(safe_mode_from(None, &Settings::from_toml("safe_mode = true\n[rules]")));
+        assert!(safe_mode_from(Some("0"), &Settings::from_toml("safe_mode = true")));
+        assert!(!safe_mode_from(None, &Settings::from_toml("safe_mode = false")));
+
+        // A config it cannot read must not switch the kill switch off.
+        assert!(safe_mode_from(None, &Settings::from_toml("safe_mode = false\n[rules\n")));
+        assert!(safe_mode_from(Some("0"), &Settings::from_toml("safe_mode = \"no\"")));
+    }
+
+    #[test]
+    fn test_settings_load() {
+        assert!(!Settings::load(None).safe_mode());
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("ordne.toml");
+        std::fs::write(&path, "safe_mode = true\n\n[replicas]\nphotos = 2\n").unwrap();
+        let config = Config {
+            db_path: temp_dir.path().join("ordne.db"),
+            config_path: Some(path.clone()),
+        };
+        assert!(safe_mode_from(None, &config.settings()));
+        assert_eq!(config.settings().replica_requirements().unwrap().get("photos"), Some(&2));
+
+        // A file it cannot parse, or read, fails every section.
+        std::fs::write(&path, "[replicas\n").unwrap();
+        let broken = Settings::load(Some(&path));
+        assert!(broken.ledger().is_err());
+        assert!(broken.automount().is_err());
+        assert!(broken.delete_limits().is_err());
+        assert!(safe_mode_from(None, &broken));
+        let missing = Settings::load(Some(&temp_dir.path().join("missing.toml")));
+        assert!(missing.replica_requirements().is_err());
+        assert!(safe_mode_from(None, &missing));
+    }
+
+    #[test]
+    fn test_replica_requirements() {
+        assert!(Settings::default().replica_requirements().unwrap().is_empty());
+        assert!(Settings::from_toml("safe_mode = true").replica_requirements().unwrap().is_empty());
+
+        let replicas = Settings::from_toml("[replicas]\nphotos = 2\ndocuments = 3").replica_requirements().unwrap();
+        assert_eq!(replicas.get("photos"), Some(&2));
+        assert_eq!(replicas.get("documents"), Some(&3));
+
+        assert!(Settings::from_toml("[replicas]\nphotos = 0").replica_requirements().is_err());
+        assert!(Settings::from_toml("[replicas]\nphotos = \"two\"").replica_requirements().is_err());
+    }
+
+    #[test]
+    fn test_hash_tuning() {
+        assert_eq!(Settings::default().hash_tuning().unwrap(), HashTuning::default());
+
+        let tuning = Settings::from_toml(
+            "[hashing]\nbuffer_size = \"1MiB\"\n\n[hashing.nvme]\nmmap = true\n\n[hashing.hdd]\nbuffer_size = 4194304\n",
+        )
+        .hash_tuning()
+        .unwrap();
+        assert_eq!(tuning.default.buffer_size, 1024 * 1024);
+        let nvme = tuning.for_class(DeviceClass::Nvme);
+        assert!(nvme.mmap);
+        assert_eq!(nvme.buffer_size, 1024 * 1024);
+        assert_eq!(tuning.for_class(DeviceClass::Hdd).buffer_size, 4 * 1024 * 1024);
+        assert_eq!(tuning.for_class(DeviceClass::Ssd), tuning.default);
+
+        assert!(Settings::from_toml("[hashing]\nbuffer_size = 100").hash_tuning().is_err());
+        assert!(Settings::from_toml("[hashing.floppy]\nmmap = true").hash_tuning().is_err());
+        assert!(Settings::from_toml("[hashing]\ndirect_io = \"yes\"").hash_tuning().is_err());
+    }
+
+    #[test]
+    fn test_dedup_ignore() {
+        assert!(Settings::default().dedup_ignore().unwrap().is_empty());
+        assert!(Settings::from_toml("safe_mode = true").dedup_ignore().unwrap().is_empty());
+
+        let ignore = Settings::from_toml("[dedup]\nignore = [\"*.pack\", \"**/.git/objects/\"]").dedup_ignore().unwrap();
+        assert!(ignore.excludes_file(Path::new("code/repo/pack-1.pack")));
+        assert!(ignore.excludes_file(Path::new("code/repo/.git/objects/ab/cdef")));
+        assert!(!ignore.excludes_file(Path::new("code/repo/main.rs")));
+
+        assert!(Settings::from_toml("[dedup]\nignore = \"*.pack\"").dedup_ignore().is_err());
+    }
+
+    #[test]
+    fn test_ledger_path() {
+        assert!(Settings::default().ledger().unwrap().is_none());
+        assert!(Settings::from_toml("safe_mode = true").ledger().unwrap().is_none());
+
+        let ledger = Settings::from_toml("[ledger]\npath = \"/mnt/usb/ordne-ledger.jsonl\"").ledger().unwrap().unwrap();
+        assert_eq!(ledger.path(), Path::new("/mnt/usb/ordne-ledger.jsonl"));
+
+        assert!(Settings::from_toml("[ledger]\npath = \"ledger.jsonl\"").ledger().is_err());
+    }
+
+    #[test]
+    fn test_delete_limits() {
+        assert_eq!(Settings::default().delete_limits().unwrap(), DeleteLimits::default());
+
+        let limits = Settings::from_toml(
+            "[delete_limits]\nmax_files_per_plan = 5000\nmax_bytes_per_plan = \"500GB\"\nmax_bytes_per_day = 1073741824\n",
+        )
+        .delete_limits()
+        .unwrap();
+        assert_eq!(limits.max_files_per_plan, Some(5000));
+        assert_eq!(limits.max_bytes_per_plan, Some(500 << 30));
+        assert_eq!(limits.max_files_per_day, None);
+        assert_eq!(limits.max_bytes_per_day, Some(1 << 30));
+
+        assert!(Settings::from_toml("[delete_limits]\nmax_files_per_day = 0").delete_limits().is_err());
+        assert!(Settings::from_toml("[delete_limits]\nmax_bytes_per_plan = \"lots\"").delete_limits().is_err());
+    }
+
+    #[test]
+    fn test_automount() {
+        assert_eq!(Settings::default().automount().unwrap(), Automount::default());
+
+        let automount = Settings::from_toml("[automount]\ndrives = [\"archive\"]\nafter = \"power-off\"\n").automount().unwrap();
+        assert_eq!(automount.drives, vec!["archive"]);
+        assert_eq!(automount.after, AfterPlan::PowerOff);
+        assert_eq!(Settings::from_toml("[automount]\ndrives = []").automount().unwrap().after, AfterPlan::Unmount);
+
+        assert!(Settings::from_toml("[automount]\ndrives = \"archive\"").automount().is_err());
+        assert!(Settings::from_toml("[automount]\nafter = \"sleep\"").automount().is_err());
+    }
+
+    #[test]
+    fn test_drive_config() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(load_drive_config(temp_dir.path()).unwrap().is_none());
+
+        std::fs::create_dir(temp_dir.path().join(".ordne")).unwrap();
+        std::fs::write(
+            temp_dir.path().join(DRIVE_CONFIG_FILE),
+            "[scan]\nexclude = [\"*.tmp\", \"node_modules/\"]\n\n\
+             [rules.raw]\ntype = \"extension\"\nextensions = [\"cr2\"]\ncategory = \"photos\"\n",
+        )
+        .unwrap();
+        let config = load_drive_config(temp_dir.path()).unwrap().unwrap();
+        assert_eq!(config.exclude, vec!["*.tmp", "node_modules/"]);
+        assert!(config.rules.rules.contains_key("raw"));
+
+        assert!(drive_config_from("[scan]\nexclude = \"*.tmp\"").is_err());
+    }
+
     #[test]
     fn test_config_with_override() {
         let custom_path = PathBuf::from("/tmp/test.db");
@@ -1,6 +1,58 @@
-use crate::db::AuditLogEntry;
+use crate::db::verification::{format_timestamp, parse_timestamp};
+use crate::db::{AuditLogEntry, File};
 use crate::error::Result;
-use chrono::{DateTime, Utc};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+/// Compact view of a file's state, stored as JSON on audit entries so a
+/// mutation can be reconstructed without replaying the whole history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    pub path: String,
+    pub size_bytes: Option<i64>,
+    pub hash: Option<String>,
+    pub status: String,
+}
+
+impl FileSnapshot {
+    pub fn from_file(file: &File) -> Self {
+        Self {
+            path: file.abs_path.clone(),
+            size_bytes: Some(file.size_bytes),
+            hash: file.blake3_hash.clone().or_else(|| file.md5_hash.clone()),
+            status: file.status.as_str().to_string(),
+        }
+    }
+
+    pub fn to_json(&self) -> Option<String> {
+        serde_json::to_string(self).ok()
+    }
+}
+
+/// Who is behind a write: stored as `agent_mode` and `actor` on audit
+/// entries and as `created_by` on plans.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attribution {
+    pub agent_mode: String,
+    pub actor: String,
+}
+
+impl Attribution {
+    pub fn agent(actor: impl Into<String>) -> Self {
+        Self {
+            agent_mode: "agent".to_string(),
+            actor: actor.into(),
+        }
+    }
+
+    /// A person at the command line.
+    pub fn manual(actor: impl Into<String>) -> Self {
+        Self {
+            agent_mode: "manual".to_string(),
+            actor: actor.into(),
+        }
+    }
+}
 
 pub trait AuditDatabase {
     fn log_audit(&mut self, entry: &AuditLogEntry) -> Result<i64>;
@@ -15,10 +67,16 @@ pub trait AuditDatabase {
 
 impl AuditDatabase for crate::db::SqliteDatabase {
     fn log_audit(&mut self, entry: &AuditLogEntry) -> Result<i64> {
+        let mut entry = entry.clone();
+        if let Some(attribution) = self.attribution() {
+            entry.agent_mode.get_or_insert_with(|| attribution.agent_mode.clone());
+            entry.actor.get_or_insert_with(|| attribution.actor.clone());
+        }
         let conn = self.conn_mut();
         conn.execute(
-            "INSERT INTO audit_log (action, file_id, plan_id, drive_id, details, agent_mode)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT INTO audit_log (action, file_id, plan_id, drive_id, details, agent_mode,
+                                    before_state, after_state, actor, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             (
                 &entry.action,
                 entry.file_id,
@@ -26,9 +84,17 @@ impl AuditDatabase for crate::db::SqliteDatabase {
                 entry.drive_id,
                 &entry.details,
                 &entry.agent_mode,
+                &entry.before_state,
+                &entry.after_state,
+                &entry.actor,
+                format_timestamp(&entry.timestamp),
             ),
         )?;
-        Ok(conn.last_insert_rowid())
+        let id = conn.last_insert_rowid();
+        if let Some(ledger) = self.ledger() {
+            ledger.record(&crate::db::LedgerRecord::audit(id, &entry));
+        }
+        Ok(id)
     }
 
     fn get_audit_entries(
@@ -38,7 +104,8 @@ impl AuditDatabase for crate::db::SqliteDatabase {
         limit: Option<i32>,
     ) -> Result<Vec<AuditLogEntry>> {
         let conn = self.conn();
-        let mut query = "SELECT id, timestamp, action, file_id, plan_id, drive_id, details, agent_mode
+        let mut query = "SELECT id, timestamp, action, file_id, plan_id, drive_id, details, agent_mode,
+                                before_state, after_state, actor
                          FROM audit_log WHERE 1=1".to_string();
 
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -68,16 +135,16 @@ impl AuditDatabase for crate::db::SqliteDatabase {
             .query_map(&param_refs[..], |row| {
                 Ok(AuditLogEntry {
                     id: row.get(0)?,
-                    timestamp: row
-                        .get::<_, String>(1)?
-                        .parse::<DateTime<Utc>>()
-                        .unwrap_or_else(|_| Utc::now()),
+                    timestamp: parse_timestamp(&row.get::<_, String>(1)?).unwrap_or_else(Utc::now),
                     action: row.get(2)?,
                     file_id: row.get(3)?,
                     plan_id: row.get(4)?,
                     drive_id: row.get(5)?,
                     details: row.get(6)?,
                     agent_mode: row.get(7)?,
+                    before_state: row.get(8)?,
+                    after_state: row.get(9)?,
+                    actor: row.get(10)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -114,6 +181,9 @@ mod tests {
             drive_id: Some(1),
             details: Some("Test copy operation".to_string()),
             agent_mode: Some("manual".to_string()),
+            before_state: None,
+            after_state: None,
+            actor: None,
         };
 
         let id = db.log_audit(&entry).unwrap();
@@ -138,6 +208,9 @@ mod tests {
                 drive_id: Some(1),
                 details: None,
                 agent_mode: None,
+                before_state: None,
+                after_state: None,
+                actor: None,
             };
             db.log_audit(&entry).unwrap();
         }
@@ -151,4 +224,38 @@ mod tests {
         let for_file = db.get_audit_entries(None, Some(1), None).unwrap();
         assert_eq!(for_file.len(), 1);
     }
+
+    #[test]
+    fn test_session_attribution_fills_unattributed_entries() {
+        let mut db = create_test_db();
+        db.set_attribution(Attribution::manual("alice"));
+
+        let entry = |agent_mode: Option<&str>, actor: Option<&str>| AuditLogEntry {
+            id: 0,
+            timestamp: Utc::now(),
+            action: "plan_approved".to_string(),
+            file_id: None,
+            plan_id: None,
+            drive_id: None,
+            details: None,
+            agent_mode: agent_mode.map(str::to_string),
+            before_state: None,
+            after_state: None,
+            actor: actor.map(str::to_string),
+        };
+        db.log_audit(&entry(None, None)).unwrap();
+        db.log_audit(&entry(Some("automated"), None)).unwrap();
+        db.log_audit(&entry(Some("agent"), Some("claude-desktop"))).unwrap();
+
+        let mut entries = db.get_audit_entries(None, None, None).unwrap();
+        entries.sort_by_key(|e| e.id);
+        let attributed: Vec<_> = entries
+            .iter()
+            .map(|e| (e.agent_mode.as_deref().unwrap(), e.actor.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            attributed,
+            vec![("manual", "alice"), ("automated", "alice"), ("agent", "claude-desktop")]
+        );
+    }
 }
@@ -1,6 +1,7 @@
-use crate::db::{Backend, Drive, DriveRole};
+use crate::db::{AuditDatabase, AuditLogEntry, Backend, Database, Drive, DriveRole};
 use crate::error::{OrdneError, Result};
 use crate::index::DeviceInfo;
+use crate::util::clock::Clock;
 use chrono::{Utc};
 use rusqlite::Connection;
 
@@ -24,6 +25,8 @@ pub fn register_drive(
         role,
         is_online: true,
         is_readonly: false,
+        is_cold: false,
+        is_encrypted: device_info.is_encrypted,
         backend,
         rclone_remote: None,
         scanned_at: None,
@@ -32,8 +35,8 @@ pub fn register_drive(
 
     conn.execute(
         "INSERT INTO drives (label, device_id, device_path, uuid, mount_path, fs_type,
-                            total_bytes, role, is_online, is_readonly, backend, rclone_remote)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                            total_bytes, role, is_online, is_readonly, backend, rclone_remote, is_cold, is_encrypted)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
         (
             &drive.label,
             &drive.device_id,
@@ -47,6 +50,8 @@ pub fn register_drive(
             drive.is_readonly,
             drive.backend.as_str(),
             &drive.rclone_remote,
+            drive.is_cold,
+            drive.is_encrypted,
         ),
     )?;
 
@@ -68,6 +73,51 @@ pub fn mark_drive_scanned(conn: &Connection, drive_id: i64) -> Result<()> {
     Ok(())
 }
 
+/// Marks a drive as cold, i.e. kept offline for good, or clears the mark.
+/// Marking a drive cold also takes it offline.
+pub fn set_drive_cold(conn: &Connection, drive_id: i64, is_cold: bool) -> Result<()> {
+    let rows = conn.execute(
+        "UPDATE drives SET is_cold = ?1, is_online = CASE WHEN ?1 THEN 0 ELSE is_online END
+         WHERE id = ?2",
+        (is_cold, drive_id),
+    )?;
+
+    if rows == 0 {
+        return Err(OrdneError::DriveNotFound(format!("id {}", drive_id)));
+    }
+
+    Ok(())
+}
+
+/// Changes a drive's role. See [`crate::roles`] for what should come with it.
+pub fn set_drive_role(conn: &Connection, drive_id: i64, role: DriveRole) -> Result<()> {
+    let rows = conn.execute(
+        "UPDATE drives SET role = ?1 WHERE id = ?2",
+        (role.as_str(), drive_id),
+    )?;
+
+    if rows == 0 {
+        return Err(OrdneError::DriveNotFound(format!("id {}", drive_id)));
+    }
+
+    Ok(())
+}
+
+/// Records whether a drive is encrypted, for volumes discovery cannot see
+/// through (rclone crypt remotes, VeraCrypt, fscrypt).
+pub fn set_drive_encrypted(conn: &Connection, drive_id: i64, is_encrypted: bool) -> Result<()> {
+    let rows = conn.execute(
+        "UPDATE drives SET is_encrypted = ?1 WHERE id = ?2",
+        (is_encrypted, drive_id),
+    )?;
+
+    if rows == 0 {
+        return Err(OrdneError::DriveNotFound(format!("id {}", drive_id)));
+    }
+
+    Ok(())
+}
+
 /// Updates drive online status
 pub fn update_drive_online_status(conn: &Connection, drive_id: i64, is_online: bool) -> Result<()> {
     let rows = conn.execute(
@@ -86,8 +136,8 @@ pub fn update_drive_online_status(conn: &Connection, drive_id: i64, is_online: b
 pub fn refresh_drive_metadata(conn: &Connection, drive_id: i64, device_info: &DeviceInfo) -> Result<()> {
     let rows = conn.execute(
         "UPDATE drives SET device_id = ?1, device_path = ?2, uuid = ?3, mount_path = ?4,
-                          fs_type = ?5, total_bytes = ?6
-         WHERE id = ?7",
+                          fs_type = ?5, total_bytes = ?6, is_encrypted = ?7
+         WHERE id = ?8",
         (
             &device_info.device_id,
             &device_info.device_path,
@@ -95,6 +145,7 @@ pub fn refresh_drive_metadata(conn: &Connection, drive_id: i64, device_info: &De
             &device_info.mount_path,
             &device_info.fs_type,
             device_info.total_bytes,
+            device_info.is_encrypted,
             drive_id,
         ),
     )?;
@@ -106,10 +157,123 @@ pub fn refresh_drive_metadata(conn: &Connection, drive_id: i64, device_info: &De
     Ok(())
 }
 
+/// A drive found mounted somewhere other than its recorded mount path.
+#[derive(Debug, Clone)]
+pub struct MountRemap {
+    pub drive_id: i64,
+    pub label: String,
+    pub old_mount: String,
+    pub new_mount: String,
+    pub file_count: usize,
+}
+
+/// Move a drive to a new mount path.
+///
+/// File paths are stored relative to the drive root and need no change, but
+/// plan steps record absolute paths, so steps reading from or writing to the
+/// drive are rewritten. Returns the number of files on the drive.
+pub fn remap_drive_mount(conn: &Connection, drive_id: i64, new_mount: &str) -> Result<usize> {
+    let old_mount: Option<String> = conn
+        .query_row("SELECT mount_path FROM drives WHERE id = ?1", [drive_id], |row| row.get(0))
+        .map_err(|_| OrdneError::DriveNotFound(format!("id {}", drive_id)))?;
+    let new_mount = trim_mount(new_mount);
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("UPDATE drives SET mount_path = ?1 WHERE id = ?2", (new_mount, drive_id))?;
+
+    if let Some(old_mount) = old_mount.as_deref().map(trim_mount) {
+        // Rewrite `old` or `old/...`, but not a sibling such as `old2/...`.
+        let rewrite = |column: &str| {
+            format!(
+                "{column} = ?1 || substr({column}, length(?2) + 1)
+                 WHERE ({column} = ?2 OR substr({column}, 1, length(?2) + 1) = ?2 || '/')"
+            )
+        };
+
+        tx.execute(
+            &format!("UPDATE migration_steps SET {} AND source_drive_id = ?3", rewrite("source_path")),
+            (new_mount, old_mount, drive_id),
+        )?;
+        tx.execute(
+            &format!("UPDATE migration_steps SET {} AND dest_drive_id = ?3", rewrite("dest_path")),
+            (new_mount, old_mount, drive_id),
+        )?;
+    }
+
+    let files: i64 = tx.query_row("SELECT COUNT(*) FROM files WHERE drive_id = ?1", [drive_id], |row| row.get(0))?;
+    tx.commit()?;
+    Ok(files as usize)
+}
+
+fn trim_mount(mount: &str) -> &str {
+    match mount.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    }
+}
+
+/// Find local drives mounted somewhere new and remap them.
+///
+/// `find_mount` maps a filesystem UUID to its current mount point; see
+/// [`crate::index::find_mount_by_uuid`]. Every remap is written to the audit
+/// log, stamped by `clock`.
+pub fn reconcile_mount_paths<D: Database + AuditDatabase>(
+    db: &mut D,
+    clock: &Clock,
+    find_mount: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<MountRemap>> {
+    let mut remaps = Vec::new();
+
+    for drive in db.list_drives()? {
+        if drive.backend != Backend::Local {
+            continue;
+        }
+        let Some(uuid) = drive.uuid.as_deref() else {
+            continue;
+        };
+        let Some(current) = find_mount(uuid) else {
+            continue;
+        };
+        let old_mount = drive.mount_path.clone().unwrap_or_default();
+        if trim_mount(&current) == trim_mount(&old_mount) {
+            continue;
+        }
+
+        let file_count = db.remap_drive_mount(drive.id, &current)?;
+        db.log_audit(&AuditLogEntry {
+            id: 0,
+            timestamp: clock.now(),
+            action: "drive_remapped".to_string(),
+            file_id: None,
+            plan_id: None,
+            drive_id: Some(drive.id),
+            details: Some(format!(
+                "Drive '{}' (UUID {}) moved from {} to {}; {} files follow it",
+                drive.label, uuid, old_mount, current, file_count
+            )),
+            agent_mode: None,
+            before_state: None,
+            after_state: None,
+            actor: None,
+        })?;
+
+        remaps.push(MountRemap {
+            drive_id: drive.id,
+            label: drive.label,
+            old_mount,
+            new_mount: current,
+            file_count,
+        });
+    }
+
+    Ok(remaps)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::db::schema::initialize_schema;
+    use crate::db::SqliteDatabase;
 
     fn create_test_db() -> Connection {
         let conn = Connection::open_in_memory().unwrap();
@@ -130,6 +294,7 @@ mod tests {
             total_bytes: Some(1_000_000_000),
             model: Some("Test Model".to_string()),
             serial: Some("TEST123".to_string()),
+            is_encrypted: false,
         };
 
         let drive_id = register_drive(&conn, "test_drive", &device_info, DriveRole::Source, Backend::Local).unwrap();
@@ -189,6 +354,7 @@ mod tests {
             total_bytes: Some(2_000_000_000),
             model: None,
             serial: None,
+            is_encrypted: false,
         };
 
         refresh_drive_metadata(&conn, drive_id, &new_device_info).unwrap();
@@ -202,4 +368,44 @@ mod tests {
         assert_eq!(fs_type, Some("xfs".to_string()));
         assert_eq!(total_bytes, Some(2_000_000_000));
     }
+
+    #[test]
+    fn test_reconcile_remaps_drifted_mount() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, uuid, mount_path, role, is_online, backend)
+                 VALUES (1, 'photos', 'abcd-1234', '/media/user/PHOTOS', 'source', 1, 'local');
+                 INSERT INTO files (drive_id, path, filename, size_bytes, status)
+                 VALUES (1, 'a.jpg', 'a.jpg', 1, 'indexed');
+                 INSERT INTO migration_steps (plan_id, action, source_path, source_drive_id, status)
+                 VALUES (NULL, 'delete', '/media/user/PHOTOS/a.jpg', 1, 'pending');",
+            )
+            .unwrap();
+
+        let lookup = |uuid: &str| (uuid == "abcd-1234").then(|| "/mnt/photos".to_string());
+        let remaps = reconcile_mount_paths(&mut db, &Clock::system(), lookup).unwrap();
+        assert_eq!(remaps.len(), 1);
+        assert_eq!(remaps[0].file_count, 1);
+
+        let drive = db.get_drive("photos").unwrap().unwrap();
+        assert_eq!(drive.mount_path.as_deref(), Some("/mnt/photos"));
+        let (abs_path, source_path): (String, String) = db
+            .conn()
+            .query_row(
+                "SELECT f.abs_path, s.source_path FROM resolved_files f, migration_steps s",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(abs_path, "/mnt/photos/a.jpg");
+        assert_eq!(source_path, "/mnt/photos/a.jpg");
+
+        let audit = db.get_audit_entries(None, None, None).unwrap();
+        assert!(audit.iter().any(|e| e.action == "drive_remapped"));
+
+        // Already in place: nothing to do.
+        assert!(reconcile_mount_paths(&mut db, &Clock::system(), lookup).unwrap().is_empty());
+    }
 }
@@ -1,4 +1,4 @@
-use crate::db::DuplicateGroup;
+use crate::db::{files, DuplicateGroup, File};
 use crate::error::Result;
 use rusqlite::{Connection, OptionalExtension};
 
@@ -29,7 +29,7 @@ pub fn get_duplicate_group(conn: &Connection, group_id: i64) -> Result<Option<Du
     )?;
 
     let group = stmt
-        .query_row([group_id], |row| parse_duplicate_group_row(row))
+        .query_row([group_id], parse_duplicate_group_row)
         .optional()?;
 
     Ok(group)
@@ -42,7 +42,7 @@ pub fn list_duplicate_groups(conn: &Connection) -> Result<Vec<DuplicateGroup>> {
     )?;
 
     let groups = stmt
-        .query_map([], |row| parse_duplicate_group_row(row))?
+        .query_map([], parse_duplicate_group_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
     Ok(groups)
@@ -55,12 +55,47 @@ pub fn list_cross_drive_duplicates(conn: &Connection) -> Result<Vec<DuplicateGro
     )?;
 
     let groups = stmt
-        .query_map([], |row| parse_duplicate_group_row(row))?
+        .query_map([], parse_duplicate_group_row)?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
     Ok(groups)
 }
 
+pub fn list_unresolved_duplicate_groups(conn: &Connection) -> Result<Vec<DuplicateGroup>> {
+    let mut stmt = conn.prepare(
+        "SELECT group_id, hash, file_count, total_waste_bytes, original_id, drives_involved, cross_drive, resolution
+         FROM duplicate_groups WHERE resolution IS NULL OR resolution = 'pending'
+         ORDER BY total_waste_bytes DESC",
+    )?;
+
+    let groups = stmt
+        .query_map([], parse_duplicate_group_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(groups)
+}
+
+pub fn set_duplicate_group_original(conn: &Connection, group_id: i64, original_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE duplicate_groups SET original_id = ?1 WHERE group_id = ?2",
+        (original_id, group_id),
+    )?;
+    conn.execute(
+        "UPDATE files SET is_original = (id = ?1) WHERE duplicate_group = ?2",
+        (original_id, group_id),
+    )?;
+    Ok(())
+}
+
+/// Files of every cross-drive duplicate group that has not been ignored.
+pub fn list_cross_drive_group_files(conn: &Connection) -> Result<Vec<Vec<File>>> {
+    list_cross_drive_duplicates(conn)?
+        .into_iter()
+        .filter(|g| g.resolution.as_deref() != Some("ignored"))
+        .map(|g| files::list_files_by_duplicate_group(conn, g.group_id))
+        .collect()
+}
+
 pub fn update_duplicate_group_resolution(
     conn: &Connection,
     group_id: i64,
@@ -172,8 +207,8 @@ mod tests {
 
         // Create file with id=1 for original_id reference
         conn.execute(
-            "INSERT INTO files (id, drive_id, path, abs_path, filename, size_bytes, status, indexed_at)
-             VALUES (1, 1, '/test.txt', '/mnt/drive1/test.txt', 'test.txt', 1024, 'indexed', datetime('now'))",
+            "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+             VALUES (1, 1, 'test.txt', 'test.txt', 1024, 'indexed', datetime('now'))",
             [],
         ).unwrap();
 
@@ -239,4 +274,44 @@ mod tests {
         let group = get_duplicate_group(&conn, group_id).unwrap().unwrap();
         assert_eq!(group.resolution, Some("kept_original".to_string()));
     }
+
+    #[test]
+    fn test_unresolved_groups_and_original() {
+        let conn = create_test_db();
+
+        conn.execute(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'drive1', 'source', 1, 'local')",
+            [],
+        ).unwrap();
+        for id in 1..=2 {
+            conn.execute(
+                "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+                 VALUES (?1, 1, ?2, ?2, 1024, 'indexed', datetime('now'))",
+                rusqlite::params![id, format!("file{}.txt", id)],
+            ).unwrap();
+        }
+
+        let small = create_duplicate_group(&conn, "hash1", 2, 512, None, &[1], false).unwrap();
+        let large = create_duplicate_group(&conn, "hash2", 2, 4096, Some(1), &[1], false).unwrap();
+        let ignored = create_duplicate_group(&conn, "hash3", 2, 8192, None, &[1], false).unwrap();
+        update_duplicate_group_resolution(&conn, ignored, "ignored").unwrap();
+        assign_files_to_duplicate_group(&conn, &[1, 2], large, Some(1)).unwrap();
+
+        let unresolved = list_unresolved_duplicate_groups(&conn).unwrap();
+        let ids: Vec<i64> = unresolved.iter().map(|g| g.group_id).collect();
+        assert_eq!(ids, vec![large, small]);
+
+        set_duplicate_group_original(&conn, large, 2).unwrap();
+        let group = get_duplicate_group(&conn, large).unwrap().unwrap();
+        assert_eq!(group.original_id, Some(2));
+
+        let originals: Vec<i64> = conn
+            .prepare("SELECT id FROM files WHERE is_original = 1")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(originals, vec![2]);
+    }
 }
@@ -2,19 +2,20 @@ use crate::db::{File, FileStatus, Priority};
 use crate::error::Result;
 use chrono::{DateTime, Utc};
 use rusqlite::{Connection, OptionalExtension, named_params};
+use serde::Serialize;
 
 pub fn add_file(conn: &Connection, file: &File) -> Result<i64> {
     conn.execute(
         "INSERT OR REPLACE INTO files (
-            drive_id, path, abs_path, filename, extension, size_bytes,
-            md5_hash, blake3_hash, created_at, modified_at, inode, device_num, nlinks,
+            drive_id, path, filename, extension, size_bytes,
+            md5_hash, blake3_hash, created_at, modified_at, accessed_at, inode, device_num, nlinks, owner_uid,
             mime_type, is_symlink, symlink_target, git_remote_url,
             category, subcategory, target_path, target_drive_id,
             priority, duplicate_group, is_original, rmlint_type, status,
             migrated_to, migrated_to_drive, migrated_at, verified_hash, error
         ) VALUES (
-            :drive_id, :path, :abs_path, :filename, :extension, :size_bytes,
-            :md5_hash, :blake3_hash, :created_at, :modified_at, :inode, :device_num, :nlinks,
+            :drive_id, :path, :filename, :extension, :size_bytes,
+            :md5_hash, :blake3_hash, :created_at, :modified_at, :accessed_at, :inode, :device_num, :nlinks, :owner_uid,
             :mime_type, :is_symlink, :symlink_target, :git_remote_url,
             :category, :subcategory, :target_path, :target_drive_id,
             :priority, :duplicate_group, :is_original, :rmlint_type, :status,
@@ -23,7 +24,6 @@ pub fn add_file(conn: &Connection, file: &File) -> Result<i64> {
         named_params! {
             ":drive_id": file.drive_id,
             ":path": &file.path,
-            ":abs_path": &file.abs_path,
             ":filename": &file.filename,
             ":extension": &file.extension,
             ":size_bytes": file.size_bytes,
@@ -31,9 +31,11 @@ pub fn add_file(conn: &Connection, file: &File) -> Result<i64> {
             ":blake3_hash": &file.blake3_hash,
             ":created_at": file.created_at.as_ref().map(|dt| dt.to_rfc3339()),
             ":modified_at": file.modified_at.as_ref().map(|dt| dt.to_rfc3339()),
+            ":accessed_at": file.accessed_at.as_ref().map(|dt| dt.to_rfc3339()),
             ":inode": file.inode,
             ":device_num": file.device_num,
             ":nlinks": file.nlinks,
+            ":owner_uid": file.owner_uid,
             ":mime_type": &file.mime_type,
             ":is_symlink": file.is_symlink,
             ":symlink_target": &file.symlink_target,
@@ -65,8 +67,8 @@ pub fn get_file(conn: &Connection, id: i64) -> Result<Option<File>> {
                 mime_type, is_symlink, symlink_target, git_remote_url,
                 category, subcategory, target_path, target_drive_id,
                 priority, duplicate_group, is_original, rmlint_type, status,
-                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at
-         FROM files WHERE id = ?1",
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files WHERE id = ?1",
     )?;
 
     stmt.query_row([id], |row| {
@@ -88,9 +90,14 @@ pub fn get_file(conn: &Connection, id: i64) -> Result<Option<File>> {
                 .get::<_, Option<String>>(10)?
                 .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
                 .map(|dt| dt.with_timezone(&Utc)),
+            accessed_at: row
+                .get::<_, Option<String>>(33)?
+                .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
             inode: row.get(11)?,
             device_num: row.get(12)?,
             nlinks: row.get(13)?,
+            owner_uid: row.get(34)?,
             mime_type: row.get(14)?,
             is_symlink: row.get(15)?,
             symlink_target: row.get(16)?,
@@ -139,8 +146,8 @@ pub fn list_files_by_hash(conn: &Connection, hash: &str) -> Result<Vec<File>> {
                 mime_type, is_symlink, symlink_target, git_remote_url,
                 category, subcategory, target_path, target_drive_id,
                 priority, duplicate_group, is_original, rmlint_type, status,
-                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at
-         FROM files WHERE md5_hash = ?1 OR blake3_hash = ?1",
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files WHERE md5_hash = ?1 OR blake3_hash = ?1",
     )?;
 
     let files = stmt
@@ -163,9 +170,14 @@ pub fn list_files_by_hash(conn: &Connection, hash: &str) -> Result<Vec<File>> {
                     .get::<_, Option<String>>(10)?
                     .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
                     .map(|dt| dt.with_timezone(&Utc)),
+                accessed_at: row
+                    .get::<_, Option<String>>(33)?
+                    .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                    .map(|dt| dt.with_timezone(&Utc)),
                 inode: row.get(11)?,
                 device_num: row.get(12)?,
                 nlinks: row.get(13)?,
+                owner_uid: row.get(34)?,
                 mime_type: row.get(14)?,
                 is_symlink: row.get(15)?,
                 symlink_target: row.get(16)?,
@@ -254,8 +266,8 @@ pub fn list_unclassified_files(
                 mime_type, is_symlink, symlink_target, git_remote_url,
                 category, subcategory, target_path, target_drive_id,
                 priority, duplicate_group, is_original, rmlint_type, status,
-                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at
-         FROM files WHERE category IS NULL AND status = 'indexed'",
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files WHERE category IS NULL AND status = 'indexed'",
     );
 
     let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -279,6 +291,41 @@ pub fn list_unclassified_files(
     Ok(files)
 }
 
+/// Unclassified files that have no pending classification suggestion yet,
+/// so agents are not handed the same batch twice.
+pub fn list_files_awaiting_suggestion(
+    conn: &Connection,
+    drive_id: Option<i64>,
+    limit: u32,
+) -> Result<Vec<File>> {
+    let mut query = String::from(
+        "SELECT id, drive_id, path, abs_path, filename, extension, size_bytes,
+                md5_hash, blake3_hash, created_at, modified_at, inode, device_num, nlinks,
+                mime_type, is_symlink, symlink_target, git_remote_url,
+                category, subcategory, target_path, target_drive_id,
+                priority, duplicate_group, is_original, rmlint_type, status,
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files WHERE category IS NULL AND status = 'indexed'
+           AND id NOT IN (SELECT file_id FROM classification_suggestions WHERE status = 'pending')",
+    );
+
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+    if let Some(drive_id) = drive_id {
+        query.push_str(" AND drive_id = ?");
+        params.push(Box::new(drive_id));
+    }
+
+    query.push_str(&format!(" ORDER BY size_bytes DESC LIMIT {}", limit));
+
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let mut stmt = conn.prepare(&query)?;
+    let files = stmt
+        .query_map(param_refs.as_slice(), file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
 pub fn list_files_by_drive(conn: &Connection, drive_id: i64) -> Result<Vec<File>> {
     let mut stmt = conn.prepare(
         "SELECT id, drive_id, path, abs_path, filename, extension, size_bytes,
@@ -286,8 +333,8 @@ pub fn list_files_by_drive(conn: &Connection, drive_id: i64) -> Result<Vec<File>
                 mime_type, is_symlink, symlink_target, git_remote_url,
                 category, subcategory, target_path, target_drive_id,
                 priority, duplicate_group, is_original, rmlint_type, status,
-                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at
-         FROM files WHERE drive_id = ?1 ORDER BY path",
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files WHERE drive_id = ?1 ORDER BY path",
     )?;
 
     let files = stmt
@@ -297,20 +344,88 @@ pub fn list_files_by_drive(conn: &Connection, drive_id: i64) -> Result<Vec<File>
     Ok(files)
 }
 
-pub fn get_file_by_abs_path(conn: &Connection, abs_path: &str) -> Result<Option<File>> {
+/// Look a file up by absolute path, resolved against the current mount paths
+/// of the registered drives (the innermost mount wins).
+/// Up to `limit` files picked at random from online local drives, or only
+/// from `drive_id`. Files already removed from their source are left out.
+pub fn sample_files(conn: &Connection, drive_id: Option<i64>, limit: usize) -> Result<Vec<File>> {
     let mut stmt = conn.prepare(
         "SELECT id, drive_id, path, abs_path, filename, extension, size_bytes,
                 md5_hash, blake3_hash, created_at, modified_at, inode, device_num, nlinks,
                 mime_type, is_symlink, symlink_target, git_remote_url,
                 category, subcategory, target_path, target_drive_id,
                 priority, duplicate_group, is_original, rmlint_type, status,
-                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at
-         FROM files WHERE abs_path = ?1",
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files
+         WHERE status != 'source_removed'
+           AND drive_id IN (SELECT id FROM drives WHERE is_online = 1 AND backend = 'local')
+           AND (?1 IS NULL OR drive_id = ?1)
+         ORDER BY RANDOM() LIMIT ?2",
     )?;
 
-    let file = stmt
-        .query_row([abs_path], file_from_row)
-        .optional()?;
+    let files = stmt
+        .query_map((drive_id, limit as i64), file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+/// Files catalogued at `path` relative to their drive root, on `drive_id`
+/// or on any drive.
+pub fn get_files_by_path(conn: &Connection, path: &str, drive_id: Option<i64>) -> Result<Vec<File>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, drive_id, path, abs_path, filename, extension, size_bytes,
+                md5_hash, blake3_hash, created_at, modified_at, inode, device_num, nlinks,
+                mime_type, is_symlink, symlink_target, git_remote_url,
+                category, subcategory, target_path, target_drive_id,
+                priority, duplicate_group, is_original, rmlint_type, status,
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files
+         WHERE path = ?1 AND (?2 IS NULL OR drive_id = ?2)
+         ORDER BY drive_id",
+    )?;
+
+    let files = stmt
+        .query_map(rusqlite::params![path, drive_id], file_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(files)
+}
+
+pub fn get_file_by_abs_path(conn: &Connection, abs_path: &str) -> Result<Option<File>> {
+    let mut stmt = conn.prepare("SELECT id, mount_path FROM drives WHERE mount_path IS NOT NULL")?;
+    let mounts = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let located = mounts
+        .iter()
+        .filter_map(|(drive_id, mount)| {
+            let root = mount.trim_end_matches('/');
+            let relative = abs_path.strip_prefix(root)?.strip_prefix('/')?;
+            Some((*drive_id, root.len(), relative))
+        })
+        .max_by_key(|(_, root_len, _)| *root_len);
+
+    let select = "SELECT id, drive_id, path, abs_path, filename, extension, size_bytes,
+                md5_hash, blake3_hash, created_at, modified_at, inode, device_num, nlinks,
+                mime_type, is_symlink, symlink_target, git_remote_url,
+                category, subcategory, target_path, target_drive_id,
+                priority, duplicate_group, is_original, rmlint_type, status,
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files";
+
+    let file = match located {
+        Some((drive_id, _, relative)) => conn
+            .prepare(&format!("{} WHERE drive_id = ?1 AND path = ?2", select))?
+            .query_row(rusqlite::params![drive_id, relative], file_from_row)
+            .optional()?,
+        // Files on drives without a mount path resolve to their stored path.
+        None => conn
+            .prepare(&format!("{} WHERE abs_path = ?1", select))?
+            .query_row([abs_path], file_from_row)
+            .optional()?,
+    };
 
     Ok(file)
 }
@@ -322,8 +437,8 @@ pub fn get_files_by_category(conn: &Connection, category: &str) -> Result<Vec<Fi
                 mime_type, is_symlink, symlink_target, git_remote_url,
                 category, subcategory, target_path, target_drive_id,
                 priority, duplicate_group, is_original, rmlint_type, status,
-                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at
-         FROM files WHERE category = ?1",
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files WHERE category = ?1",
     )?;
 
     let files = stmt
@@ -344,8 +459,8 @@ pub fn get_files_by_category_and_drive(
                 mime_type, is_symlink, symlink_target, git_remote_url,
                 category, subcategory, target_path, target_drive_id,
                 priority, duplicate_group, is_original, rmlint_type, status,
-                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at
-         FROM files WHERE category = ?1 AND drive_id = ?2",
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files WHERE category = ?1 AND drive_id = ?2",
     )?;
 
     let files = stmt
@@ -362,8 +477,8 @@ pub fn list_files_by_duplicate_group(conn: &Connection, group_id: i64) -> Result
                 mime_type, is_symlink, symlink_target, git_remote_url,
                 category, subcategory, target_path, target_drive_id,
                 priority, duplicate_group, is_original, rmlint_type, status,
-                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at
-         FROM files WHERE duplicate_group = ?1",
+                migrated_to, migrated_to_drive, migrated_at, verified_hash, error, indexed_at, accessed_at, owner_uid
+         FROM resolved_files WHERE duplicate_group = ?1",
     )?;
 
     let files = stmt
@@ -392,9 +507,14 @@ fn file_from_row(row: &rusqlite::Row) -> rusqlite::Result<File> {
             .get::<_, Option<String>>(10)?
             .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
             .map(|dt| dt.with_timezone(&Utc)),
+        accessed_at: row
+            .get::<_, Option<String>>(33)?
+            .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+            .map(|dt| dt.with_timezone(&Utc)),
         inode: row.get(11)?,
         device_num: row.get(12)?,
         nlinks: row.get(13)?,
+        owner_uid: row.get(34)?,
         mime_type: row.get(14)?,
         is_symlink: row.get(15)?,
         symlink_target: row.get(16)?,
@@ -454,6 +574,78 @@ pub fn get_category_stats(conn: &Connection) -> Result<Vec<CategoryStats>> {
     Ok(stats)
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionStats {
+    pub drive_id: i64,
+    pub drive_label: String,
+    /// Lower-cased extension, `None` for files without one.
+    pub extension: Option<String>,
+    pub file_count: i64,
+    pub total_bytes: i64,
+}
+
+/// File count and bytes per extension per drive, largest first.
+pub fn get_extension_stats(conn: &Connection, drive_id: Option<i64>) -> Result<Vec<ExtensionStats>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.drive_id, d.label, LOWER(f.extension), COUNT(*), COALESCE(SUM(f.size_bytes), 0)
+         FROM files f JOIN drives d ON d.id = f.drive_id
+         WHERE f.status != 'source_removed' AND (?1 IS NULL OR f.drive_id = ?1)
+         GROUP BY f.drive_id, LOWER(f.extension)
+         ORDER BY 5 DESC, d.label",
+    )?;
+
+    let stats = stmt
+        .query_map([drive_id], |row| {
+            Ok(ExtensionStats {
+                drive_id: row.get(0)?,
+                drive_label: row.get(1)?,
+                extension: row.get(2)?,
+                file_count: row.get(3)?,
+                total_bytes: row.get(4)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(stats)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderStats {
+    /// First path component; empty for files in the drive root.
+    pub folder: String,
+    pub file_count: i64,
+    pub total_bytes: i64,
+    /// Date (`YYYY-MM-DD`) the folder's newest file was cataloged.
+    pub cataloged_on: Option<String>,
+}
+
+/// File count, bytes and catalog date per top-level folder of a drive,
+/// with root-level files last.
+pub fn get_top_level_folder_stats(conn: &Connection, drive_id: i64) -> Result<Vec<FolderStats>> {
+    let mut stmt = conn.prepare(
+        "SELECT CASE WHEN instr(path, '/') > 0 THEN substr(path, 1, instr(path, '/') - 1)
+                     ELSE '' END AS folder,
+                COUNT(*), COALESCE(SUM(size_bytes), 0), substr(MAX(indexed_at), 1, 10)
+         FROM files
+         WHERE drive_id = ?1 AND status != 'source_removed'
+         GROUP BY folder
+         ORDER BY folder = '', folder",
+    )?;
+
+    let stats = stmt
+        .query_map([drive_id], |row| {
+            Ok(FolderStats {
+                folder: row.get(0)?,
+                file_count: row.get(1)?,
+                total_bytes: row.get(2)?,
+                cataloged_on: row.get(3)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(stats)
+}
+
 pub fn bulk_update_classification(
     conn: &Connection,
     file_ids: &[i64],
@@ -526,9 +718,11 @@ mod tests {
             blake3_hash: None,
             created_at: None,
             modified_at: Some(Utc::now()),
+            accessed_at: None,
             inode: Some(12345),
             device_num: Some(1),
             nlinks: Some(1),
+            owner_uid: None,
             mime_type: Some("text/plain".to_string()),
             is_symlink: false,
             symlink_target: None,
@@ -579,9 +773,11 @@ mod tests {
             blake3_hash: None,
             created_at: None,
             modified_at: None,
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -628,9 +824,11 @@ mod tests {
             blake3_hash: None,
             created_at: None,
             modified_at: None,
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -664,9 +862,11 @@ mod tests {
             blake3_hash: None,
             created_at: None,
             modified_at: None,
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -712,9 +912,11 @@ mod tests {
             blake3_hash: None,
             created_at: None,
             modified_at: None,
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -771,9 +973,11 @@ mod tests {
             blake3_hash: None,
             created_at: None,
             modified_at: None,
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -807,9 +1011,11 @@ mod tests {
             blake3_hash: None,
             created_at: None,
             modified_at: None,
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -841,4 +1047,31 @@ mod tests {
         assert_eq!(classified[0].priority, Priority::Critical);
         assert_eq!(classified[0].status, FileStatus::Classified);
     }
+
+    #[test]
+    fn test_top_level_folder_stats() {
+        let conn = create_test_db();
+        create_test_drive(&conn, 1, "vault");
+        conn.execute_batch(
+            "INSERT INTO files (drive_id, path, filename, size_bytes, status, indexed_at)
+             VALUES (1, 'photos/2019/a.jpg', 'a.jpg', 10, 'indexed', '2026-01-02 10:00:00'),
+                    (1, 'photos/b.jpg', 'b.jpg', 5, 'indexed', '2026-03-04T10:00:00+00:00'),
+                    (1, 'notes.txt', 'notes.txt', 1, 'indexed', '2026-01-01 09:00:00'),
+                    (1, 'docs/gone.pdf', 'gone.pdf', 7, 'source_removed', '2026-01-01 09:00:00');",
+        )
+        .unwrap();
+
+        let stats = get_top_level_folder_stats(&conn, 1).unwrap();
+        let rows: Vec<_> = stats
+            .iter()
+            .map(|s| (s.folder.as_str(), s.file_count, s.total_bytes, s.cataloged_on.as_deref()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("photos", 2, 15, Some("2026-03-04")),
+                ("", 1, 1, Some("2026-01-01")),
+            ]
+        );
+    }
 }
@@ -0,0 +1,133 @@
+//! Queue of agent-created artifacts and failures waiting on a human.
+
+use crate::db::verification::{VerificationDatabase, VerificationFailure, VerificationJob};
+use crate::db::{MigrationPlan, PlanStatus, PlansDatabase, SqliteDatabase};
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+
+/// Pending classification suggestions sharing a category and agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestionGroup {
+    pub category: String,
+    pub subcategory: Option<String>,
+    pub suggested_by: Option<String>,
+    pub count: i64,
+    pub min_confidence: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Inbox {
+    pub draft_plans: Vec<MigrationPlan>,
+    pub suggestions: Vec<SuggestionGroup>,
+    pub verification_failures: Vec<VerificationFailure>,
+    /// Scheduled plan re-verifications whose time has come.
+    pub due_verification_jobs: Vec<VerificationJob>,
+}
+
+impl Inbox {
+    pub fn load(db: &SqliteDatabase) -> Result<Self> {
+        Ok(Self {
+            draft_plans: db.list_plans(Some(PlanStatus::Draft))?,
+            suggestions: pending_suggestion_groups(db)?,
+            verification_failures: db.list_unacknowledged_failures()?,
+            due_verification_jobs: db.list_pending_verification_jobs(Some(chrono::Utc::now()))?,
+        })
+    }
+
+    pub fn pending_suggestion_count(&self) -> i64 {
+        self.suggestions.iter().map(|g| g.count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draft_plans.is_empty()
+            && self.suggestions.is_empty()
+            && self.verification_failures.is_empty()
+            && self.due_verification_jobs.is_empty()
+    }
+}
+
+fn pending_suggestion_groups(db: &SqliteDatabase) -> Result<Vec<SuggestionGroup>> {
+    let mut stmt = db.conn().prepare(
+        "SELECT category, subcategory, suggested_by, COUNT(*), MIN(confidence)
+         FROM classification_suggestions WHERE status = 'pending'
+         GROUP BY category, subcategory, suggested_by
+         ORDER BY COUNT(*) DESC, category",
+    )?;
+
+    let groups = stmt
+        .query_map([], |row| {
+            Ok(SuggestionGroup {
+                category: row.get(0)?,
+                subcategory: row.get(1)?,
+                suggested_by: row.get(2)?,
+                count: row.get(3)?,
+                min_confidence: row.get(4)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use chrono::Utc;
+
+    #[test]
+    fn test_inbox_collects_pending_items() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        assert!(Inbox::load(&db).unwrap().is_empty());
+
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'src', 'source', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+                 VALUES (1, 1, 'a', 'a', 1, 'indexed', datetime('now')),
+                        (2, 1, 'b', 'b', 1, 'indexed', datetime('now'));
+                 INSERT INTO classification_suggestions (file_id, category, confidence, suggested_by)
+                 VALUES (1, 'photos', 0.9, 'agent'), (2, 'photos', 0.6, 'agent');",
+            )
+            .unwrap();
+
+        db.create_plan(&MigrationPlan {
+            id: 0,
+            created_at: Utc::now(),
+            description: Some("agent plan".to_string()),
+            source_drive_id: None,
+            target_drive_id: None,
+            status: PlanStatus::Draft,
+            total_files: 0,
+            total_bytes: 0,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: Some("agent".to_string()),
+            step_ordering: Default::default(),
+        })
+        .unwrap();
+
+        db.record_verification_failure(&VerificationFailure {
+            id: 0,
+            file_id: Some(1),
+            plan_id: None,
+            step_id: None,
+            drive_id: Some(1),
+            path: "/a".to_string(),
+            expected_hash: None,
+            actual_hash: None,
+            reason: "missing".to_string(),
+            detected_at: Utc::now(),
+            acknowledged_at: None,
+        })
+        .unwrap();
+
+        let inbox = Inbox::load(&db).unwrap();
+        assert_eq!(inbox.draft_plans.len(), 1);
+        assert_eq!(inbox.suggestions.len(), 1);
+        assert_eq!(inbox.pending_suggestion_count(), 2);
+        assert_eq!(inbox.suggestions[0].min_confidence, 0.6);
+        assert_eq!(inbox.verification_failures.len(), 1);
+    }
+}
@@ -0,0 +1,219 @@
+//! One row per command or MCP tool call, with who ran it, the parameters
+//! it was given and where its time and I/O went.
+
+use crate::db::verification::parse_timestamp;
+use crate::db::Attribution;
+use crate::error::Result;
+use crate::util::progress::bytes_processed;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: i64,
+    pub command: String,
+    pub started_at: DateTime<Utc>,
+    pub elapsed_ms: i64,
+    /// Rows inserted, updated or deleted in the database.
+    pub rows_touched: i64,
+    /// Bytes read for hashing or copied.
+    pub bytes_processed: i64,
+    /// Growth of the database file; negative if it shrank.
+    pub db_size_delta: i64,
+    /// `ok` or `failed`.
+    pub status: String,
+    pub error: Option<String>,
+    /// `manual` for the command line, `agent` for MCP tool calls.
+    pub agent_mode: Option<String>,
+    pub actor: Option<String>,
+    /// JSON: the command-line arguments, or the tool call's arguments.
+    pub parameters: Option<String>,
+}
+
+/// Measures one command or tool call from start to [`JobTimer::finish`].
+pub struct JobTimer {
+    command: String,
+    started_at: DateTime<Utc>,
+    started: Instant,
+    changes: i64,
+    db_size: i64,
+    bytes: u64,
+}
+
+impl JobTimer {
+    pub fn start(conn: &Connection, command: impl Into<String>) -> Result<Self> {
+        Ok(Self {
+            command: command.into(),
+            started_at: Utc::now(),
+            started: Instant::now(),
+            changes: total_changes(conn)?,
+            db_size: database_size(conn)?,
+            bytes: bytes_processed(),
+        })
+    }
+
+    /// Record the job; `error` is `None` if it succeeded.
+    pub fn finish(
+        self,
+        conn: &Connection,
+        error: Option<String>,
+        attribution: Option<&Attribution>,
+        parameters: Option<String>,
+    ) -> Result<Job> {
+        let mut job = Job {
+            id: 0,
+            command: self.command,
+            started_at: self.started_at,
+            elapsed_ms: self.started.elapsed().as_millis() as i64,
+            rows_touched: total_changes(conn)? - self.changes,
+            bytes_processed: bytes_processed().saturating_sub(self.bytes) as i64,
+            db_size_delta: database_size(conn)? - self.db_size,
+            status: if error.is_none() { "ok" } else { "failed" }.to_string(),
+            error,
+            agent_mode: attribution.map(|a| a.agent_mode.clone()),
+            actor: attribution.map(|a| a.actor.clone()),
+            parameters,
+        };
+        job.id = record_job(conn, &job)?;
+        Ok(job)
+    }
+}
+
+pub fn record_job(conn: &Connection, job: &Job) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO jobs (command, started_at, elapsed_ms, rows_touched, bytes_processed,
+                           db_size_delta, status, error, agent_mode, actor, parameters)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        (
+            &job.command,
+            job.started_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            job.elapsed_ms,
+            job.rows_touched,
+            job.bytes_processed,
+            job.db_size_delta,
+            &job.status,
+            &job.error,
+            &job.agent_mode,
+            &job.actor,
+            &job.parameters,
+        ),
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Most recent jobs first.
+pub fn list_jobs(conn: &Connection, limit: i64) -> Result<Vec<Job>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, command, started_at, elapsed_ms, rows_touched, bytes_processed,
+                db_size_delta, status, error, agent_mode, actor, parameters
+         FROM jobs ORDER BY id DESC LIMIT ?1",
+    )?;
+
+    let jobs = stmt
+        .query_map([limit], |row| {
+            Ok(Job {
+                id: row.get(0)?,
+                command: row.get(1)?,
+                started_at: parse_timestamp(&row.get::<_, String>(2)?).unwrap_or_else(Utc::now),
+                elapsed_ms: row.get(3)?,
+                rows_touched: row.get(4)?,
+                bytes_processed: row.get(5)?,
+                db_size_delta: row.get(6)?,
+                status: row.get(7)?,
+                error: row.get(8)?,
+                agent_mode: row.get(9)?,
+                actor: row.get(10)?,
+                parameters: row.get(11)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(jobs)
+}
+
+/// Current size of the main database file, from its page count.
+pub fn database_size(conn: &Connection) -> Result<i64> {
+    let size = conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(size)
+}
+
+/// Rows changed through this connection since it was opened.
+pub fn total_changes(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row("SELECT total_changes()", [], |row| row.get(0))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Database, SqliteDatabase};
+
+    #[test]
+    fn test_record_and_list_jobs() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+
+        let changes = total_changes(db.conn()).unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (label, role, is_online, backend) VALUES ('a', 'source', 1, 'local'),
+                                                                           ('b', 'source', 1, 'local');",
+            )
+            .unwrap();
+        assert_eq!(total_changes(db.conn()).unwrap() - changes, 2);
+        assert!(database_size(db.conn()).unwrap() > 0);
+
+        for (command, status) in [("scan", "ok"), ("migrate", "failed")] {
+            record_job(
+                db.conn(),
+                &Job {
+                    id: 0,
+                    command: command.to_string(),
+                    started_at: Utc::now(),
+                    elapsed_ms: 1500,
+                    rows_touched: 2,
+                    bytes_processed: 4096,
+                    db_size_delta: 0,
+                    status: status.to_string(),
+                    error: None,
+                    agent_mode: None,
+                    actor: None,
+                    parameters: None,
+                },
+            )
+            .unwrap();
+        }
+
+        let jobs = list_jobs(db.conn(), 10).unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].command, "migrate");
+        assert_eq!(jobs[1].bytes_processed, 4096);
+        assert_eq!(list_jobs(db.conn(), 1).unwrap().len(), 1);
+
+        let timer = JobTimer::start(db.conn(), "plan approve").unwrap();
+        db.conn()
+            .execute("UPDATE drives SET is_online = 0", [])
+            .unwrap();
+        let job = timer
+            .finish(
+                db.conn(),
+                None,
+                Some(&Attribution::manual("alice")),
+                Some(r#"["plan","approve","3"]"#.to_string()),
+            )
+            .unwrap();
+        let recorded = &list_jobs(db.conn(), 1).unwrap()[0];
+        assert_eq!(recorded.id, job.id);
+        assert_eq!(recorded.command, "plan approve");
+        assert_eq!(recorded.rows_touched, 2);
+        assert_eq!(recorded.status, "ok");
+        assert_eq!(recorded.agent_mode.as_deref(), Some("manual"));
+        assert_eq!(recorded.actor.as_deref(), Some("alice"));
+        assert_eq!(recorded.parameters.as_deref(), Some(r#"["plan","approve","3"]"#));
+    }
+}
@@ -0,0 +1,180 @@
+//! Append-only JSONL copy of the audit log and plan state changes.
+//!
+//! Configured with `[ledger] path` in `~/.config/ordne/ordne.toml`, ideally
+//! on another drive than the database. Every audit entry, plan creation and
+//! plan status change is written as one JSON line once the database has
+//! recorded it, and the file is synced after each line, so the operational
+//! history survives a lost or corrupted database. The ledger is only ever
+//! appended to; a failed write is logged and does not undo the database
+//! change it mirrors.
+
+use crate::db::verification::format_timestamp;
+use crate::db::{AuditLogEntry, MigrationPlan, PlanStatus};
+use crate::error::Result;
+use chrono::Utc;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// One line of the ledger.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LedgerRecord {
+    /// An audit entry, with the id the database gave it.
+    Audit(AuditLogEntry),
+    PlanCreated {
+        plan_id: i64,
+        created_at: String,
+        description: Option<String>,
+        source_drive_id: Option<i64>,
+        target_drive_id: Option<i64>,
+        status: &'static str,
+        total_files: i32,
+        total_bytes: i64,
+        created_by: Option<String>,
+    },
+    PlanStatus {
+        plan_id: i64,
+        status: &'static str,
+        recorded_at: String,
+    },
+}
+
+impl LedgerRecord {
+    pub fn audit(id: i64, entry: &AuditLogEntry) -> Self {
+        LedgerRecord::Audit(AuditLogEntry { id, ..entry.clone() })
+    }
+
+    pub fn plan_created(id: i64, plan: &MigrationPlan) -> Self {
+        LedgerRecord::PlanCreated {
+            plan_id: id,
+            created_at: format_timestamp(&plan.created_at),
+            description: plan.description.clone(),
+            source_drive_id: plan.source_drive_id,
+            target_drive_id: plan.target_drive_id,
+            status: plan.status.as_str(),
+            total_files: plan.total_files,
+            total_bytes: plan.total_bytes,
+            created_by: plan.created_by.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ledger {
+    path: PathBuf,
+}
+
+impl Ledger {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the ledger would share a filesystem with `db_path`, and so
+    /// be lost with it.
+    pub fn shares_filesystem_with(&self, db_path: &Path) -> bool {
+        let device = |path: &Path| {
+            path.ancestors()
+                .find_map(|dir| std::fs::metadata(dir).ok())
+                .map(|m| m.dev())
+        };
+        matches!((device(&self.path), device(db_path)), (Some(a), Some(b)) if a == b)
+    }
+
+    pub fn append(&self, record: &LedgerRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Append `record`, logging rather than returning a failure: the
+    /// database already holds the change.
+    pub(crate) fn record(&self, record: &LedgerRecord) {
+        if let Err(e) = self.append(record) {
+            log::warn!("Could not append to ledger {}: {}", self.path.display(), e);
+        }
+    }
+
+    pub(crate) fn record_plan_status(&self, plan_id: i64, status: PlanStatus) {
+        self.record(&LedgerRecord::PlanStatus {
+            plan_id,
+            status: status.as_str(),
+            recorded_at: format_timestamp(&Utc::now()),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{AuditDatabase, Database, PlansDatabase, SqliteDatabase, StepOrdering};
+
+    #[test]
+    fn test_audit_entries_and_plan_changes_reach_the_ledger() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.jsonl");
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.set_ledger(Ledger::new(&path));
+
+        let plan_id = db
+            .create_plan(&MigrationPlan {
+                id: 0,
+                created_at: Utc::now(),
+                description: Some("offload".to_string()),
+                source_drive_id: None,
+                target_drive_id: None,
+                status: PlanStatus::Draft,
+                total_files: 1,
+                total_bytes: 10,
+                completed_files: 0,
+                completed_bytes: 0,
+                created_by: None,
+                step_ordering: StepOrdering::Priority,
+            })
+            .unwrap();
+        db.update_plan_status(plan_id, PlanStatus::Approved).unwrap();
+        db.log_audit(&AuditLogEntry {
+            id: 0,
+            timestamp: Utc::now(),
+            action: "plan_approved".to_string(),
+            file_id: None,
+            plan_id: Some(plan_id),
+            drive_id: None,
+            details: None,
+            agent_mode: Some("manual".to_string()),
+            before_state: None,
+            after_state: None,
+            actor: None,
+        })
+        .unwrap();
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let kinds: Vec<&str> = lines.iter().map(|l| l["kind"].as_str().unwrap()).collect();
+        assert_eq!(kinds, vec!["plan_created", "plan_status", "audit"]);
+        assert_eq!(lines[0]["plan_id"], plan_id);
+        assert_eq!(lines[1]["status"], "approved");
+        assert_eq!(lines[2]["action"], "plan_approved");
+        assert!(lines[2]["id"].as_i64().unwrap() > 0);
+
+        // A what-if copy must not write to the ledger.
+        let mut copy = db.in_memory_copy().unwrap();
+        copy.update_plan_status(plan_id, PlanStatus::Completed).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        assert!(Ledger::new(dir.path().join("other.jsonl")).shares_filesystem_with(&path));
+    }
+}
@@ -0,0 +1,963 @@
+//! A database kept entirely in process memory, for unit tests.
+//!
+//! [`MemoryDatabase`] implements every database trait without SQLite, so
+//! code written against those traits (a [`crate::Planner`], a
+//! [`crate::MigrationEngine`], or an application's own functions) can be
+//! tested with plain structs. Helpers that take a `rusqlite::Connection`,
+//! such as those in [`crate::db::files`], still need a [`SqliteDatabase`].
+//!
+//! ```
+//! use ordne_lib::{Database, MemoryDatabase, Result};
+//!
+//! // Application code written against the trait...
+//! fn online_labels(db: &impl Database) -> Result<Vec<String>> {
+//!     Ok(db.list_drives()?.into_iter().filter(|d| d.is_online).map(|d| d.label).collect())
+//! }
+//!
+//! // ...tested without SQLite.
+//! let db = MemoryDatabase::new();
+//! assert!(online_labels(&db).unwrap().is_empty());
+//! ```
+//!
+//! [`SqliteDatabase`]: crate::db::SqliteDatabase
+
+use crate::db::audit::AuditDatabase;
+use crate::db::plans::{
+    PlanSession, PlanSpaceUsage, PlanSummary, PlansDatabase, SatisfiedFile, SessionOwner, SpacePhase,
+    StepDirectory, StepLogEntry, StepPage, StepQuery, SummaryRow,
+};
+use crate::db::verification::{VerificationDatabase, VerificationFailure, VerificationJob};
+use crate::db::{
+    AuditLogEntry, Database, Drive, File, FileStatus, HashAlgorithm, MigrationPlan, MigrationStep,
+    PlanStatus, StepAction, StepStatus,
+};
+use crate::error::{OrdneError, Result};
+use crate::util::clock::Clock;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Timing of a step's latest attempt.
+#[derive(Debug, Clone, Default)]
+struct StepRun {
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    bytes: Option<i64>,
+    attempts: i64,
+}
+
+/// In-memory implementation of [`Database`], [`PlansDatabase`],
+/// [`AuditDatabase`] and [`VerificationDatabase`].
+///
+/// Ids start at 1 and are never reused. Timestamps the SQLite database would
+/// fill in itself come from the database's [`Clock`].
+#[derive(Debug, Default)]
+pub struct MemoryDatabase {
+    clock: Clock,
+    next_id: i64,
+    drives: BTreeMap<i64, Drive>,
+    files: BTreeMap<i64, File>,
+    plans: BTreeMap<i64, MigrationPlan>,
+    steps: BTreeMap<i64, MigrationStep>,
+    step_runs: BTreeMap<i64, StepRun>,
+    dependencies: BTreeSet<(i64, i64)>,
+    /// `(plan_id, file_id)` to the step covering that file.
+    satisfied: BTreeMap<(i64, i64), i64>,
+    /// `(plan_id, drive_id)` to used bytes before and after execution.
+    space_usage: BTreeMap<(i64, i64), (Option<i64>, Option<i64>)>,
+    sessions: BTreeMap<i64, PlanSession>,
+    audit: BTreeMap<i64, AuditLogEntry>,
+    failures: BTreeMap<i64, VerificationFailure>,
+    jobs: BTreeMap<i64, VerificationJob>,
+}
+
+impl MemoryDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stamp sessions, step attempts and acknowledgements with `clock`.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn allocate_id(&mut self) -> i64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// A stored file with `abs_path` resolved against its drive's mount, as
+    /// the SQLite `resolved_files` view does.
+    fn resolve(&self, file: &File) -> File {
+        let mut file = file.clone();
+        file.abs_path = match self.drives.get(&file.drive_id).and_then(|d| d.mount_path.as_deref()) {
+            Some(mount) => format!("{}/{}", mount.trim_end_matches('/'), file.path),
+            None => file.path.clone(),
+        };
+        file
+    }
+
+    fn steps_of(&self, plan_id: i64) -> Vec<&MigrationStep> {
+        let mut steps: Vec<_> = self.steps.values().filter(|s| s.plan_id == plan_id).collect();
+        steps.sort_by_key(|s| (s.step_order, s.id));
+        steps
+    }
+
+    fn step_bytes(&self, step: &MigrationStep) -> i64 {
+        self.files.get(&step.file_id).map(|f| f.size_bytes).unwrap_or(0)
+    }
+
+    fn summarize(
+        &self,
+        plan_id: i64,
+        key: impl Fn(&MigrationStep) -> String,
+    ) -> Vec<SummaryRow> {
+        let mut rows: BTreeMap<String, SummaryRow> = BTreeMap::new();
+        for step in self.steps_of(plan_id) {
+            let key = key(step);
+            let row = rows.entry(key.clone()).or_insert(SummaryRow {
+                key,
+                step_count: 0,
+                total_bytes: 0,
+            });
+            row.step_count += 1;
+            row.total_bytes += self.step_bytes(step);
+        }
+        let mut rows: Vec<_> = rows.into_values().collect();
+        rows.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes).then_with(|| a.key.cmp(&b.key)));
+        rows
+    }
+
+    fn step_mut(&mut self, id: i64) -> Option<&mut MigrationStep> {
+        self.steps.get_mut(&id)
+    }
+}
+
+/// `mount` without a trailing slash, as the SQLite database stores it.
+fn trim_mount(mount: &str) -> &str {
+    match mount.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    }
+}
+
+/// Parent directory of a step's source, matching the SQLite query.
+fn step_directory(source_path: &str) -> &str {
+    match source_path.rfind('/') {
+        Some(i) => source_path[..i].trim_end_matches('/'),
+        None => "",
+    }
+}
+
+impl Database for MemoryDatabase {
+    fn initialize(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_drive(&self, label: &str) -> Result<Option<Drive>> {
+        Ok(self.drives.values().find(|d| d.label == label).cloned())
+    }
+
+    fn get_drive_by_id(&self, id: i64) -> Result<Option<Drive>> {
+        Ok(self.drives.get(&id).cloned())
+    }
+
+    fn add_drive(&mut self, drive: &Drive) -> Result<i64> {
+        if self.drives.values().any(|d| d.label == drive.label) {
+            return Err(OrdneError::Config(format!("Drive already exists: {}", drive.label)));
+        }
+        let id = self.allocate_id();
+        self.drives.insert(id, Drive { id, ..drive.clone() });
+        Ok(id)
+    }
+
+    fn list_drives(&self) -> Result<Vec<Drive>> {
+        let mut drives: Vec<_> = self.drives.values().cloned().collect();
+        drives.sort_by_key(|d| (d.added_at, d.id));
+        Ok(drives)
+    }
+
+    fn update_drive_online_status(&mut self, label: &str, is_online: bool) -> Result<()> {
+        let drive = self
+            .drives
+            .values_mut()
+            .find(|d| d.label == label)
+            .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+        drive.is_online = is_online;
+        Ok(())
+    }
+
+    fn remap_drive_mount(&mut self, drive_id: i64, new_mount: &str) -> Result<usize> {
+        let drive = self
+            .drives
+            .get_mut(&drive_id)
+            .ok_or_else(|| OrdneError::DriveNotFound(format!("id {}", drive_id)))?;
+        let new_mount = trim_mount(new_mount).to_string();
+        let old_mount = drive.mount_path.replace(new_mount.clone());
+
+        if let Some(old_mount) = old_mount.as_deref().map(trim_mount) {
+            // Rewrite `old` or `old/...`, but not a sibling such as `old2/...`.
+            let rewrite = |path: &mut String| {
+                if let Some(rest) = path.strip_prefix(old_mount) {
+                    if rest.is_empty() || rest.starts_with('/') {
+                        *path = format!("{}{}", new_mount, rest);
+                    }
+                }
+            };
+            for step in self.steps.values_mut() {
+                if step.source_drive_id == drive_id {
+                    rewrite(&mut step.source_path);
+                }
+                if step.dest_drive_id == Some(drive_id) {
+                    if let Some(dest_path) = step.dest_path.as_mut() {
+                        rewrite(dest_path);
+                    }
+                }
+            }
+        }
+
+        Ok(self.files.values().filter(|f| f.drive_id == drive_id).count())
+    }
+
+    fn get_file(&self, id: i64) -> Result<Option<File>> {
+        Ok(self.files.get(&id).map(|f| self.resolve(f)))
+    }
+
+    /// Replaces any file already catalogued at the same drive and path.
+    fn add_file(&mut self, file: &File) -> Result<i64> {
+        self.files
+            .retain(|_, f| !(f.drive_id == file.drive_id && f.path == file.path));
+        let id = self.allocate_id();
+        self.files.insert(id, File { id, ..file.clone() });
+        Ok(id)
+    }
+
+    fn update_file_status(&mut self, id: i64, status: FileStatus) -> Result<()> {
+        if let Some(file) = self.files.get_mut(&id) {
+            file.status = status;
+        }
+        Ok(())
+    }
+
+    fn get_files_by_hash(&self, hash: &str) -> Result<Vec<File>> {
+        Ok(self
+            .files
+            .values()
+            .filter(|f| f.md5_hash.as_deref() == Some(hash) || f.blake3_hash.as_deref() == Some(hash))
+            .map(|f| self.resolve(f))
+            .collect())
+    }
+}
+
+impl PlansDatabase for MemoryDatabase {
+    fn create_plan(&mut self, plan: &MigrationPlan) -> Result<i64> {
+        let id = self.allocate_id();
+        self.plans.insert(
+            id,
+            MigrationPlan {
+                id,
+                completed_files: 0,
+                completed_bytes: 0,
+                ..plan.clone()
+            },
+        );
+        Ok(id)
+    }
+
+    fn get_plan(&self, id: i64) -> Result<Option<MigrationPlan>> {
+        Ok(self.plans.get(&id).cloned())
+    }
+
+    fn list_plans(&self, status_filter: Option<PlanStatus>) -> Result<Vec<MigrationPlan>> {
+        let mut plans: Vec<_> = self
+            .plans
+            .values()
+            .filter(|p| status_filter.is_none_or(|s| p.status == s))
+            .cloned()
+            .collect();
+        plans.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+        Ok(plans)
+    }
+
+    fn update_plan_status(&mut self, id: i64, status: PlanStatus) -> Result<()> {
+        let plan = self.plans.get_mut(&id).ok_or(OrdneError::PlanNotFound(id))?;
+        plan.status = status;
+        Ok(())
+    }
+
+    fn update_plan_progress(
+        &mut self,
+        id: i64,
+        completed_files: i32,
+        completed_bytes: i64,
+    ) -> Result<()> {
+        let plan = self.plans.get_mut(&id).ok_or(OrdneError::PlanNotFound(id))?;
+        plan.completed_files = completed_files;
+        plan.completed_bytes = completed_bytes;
+        Ok(())
+    }
+
+    fn add_step(&mut self, step: &MigrationStep) -> Result<i64> {
+        let id = self.allocate_id();
+        self.steps.insert(
+            id,
+            MigrationStep {
+                id,
+                post_hash: None,
+                executed_at: None,
+                error: None,
+                ..step.clone()
+            },
+        );
+        Ok(id)
+    }
+
+    fn get_step(&self, id: i64) -> Result<Option<MigrationStep>> {
+        Ok(self.steps.get(&id).cloned())
+    }
+
+    fn get_steps_for_plan(&self, plan_id: i64) -> Result<Vec<MigrationStep>> {
+        Ok(self.steps_of(plan_id).into_iter().cloned().collect())
+    }
+
+    fn update_step_status(
+        &mut self,
+        id: i64,
+        status: StepStatus,
+        error: Option<String>,
+    ) -> Result<()> {
+        if let Some(step) = self.step_mut(id) {
+            step.status = status;
+            step.error = error;
+        }
+        Ok(())
+    }
+
+    fn update_step_hashes(
+        &mut self,
+        id: i64,
+        pre_hash: String,
+        post_hash: Option<String>,
+        algorithm: HashAlgorithm,
+    ) -> Result<()> {
+        if let Some(step) = self.step_mut(id) {
+            step.pre_hash = Some(pre_hash);
+            step.post_hash = post_hash;
+            step.hash_algorithm = Some(algorithm);
+        }
+        Ok(())
+    }
+
+    fn mark_step_executed(&mut self, id: i64) -> Result<()> {
+        let now = self.clock.now();
+        if let Some(step) = self.step_mut(id) {
+            step.executed_at = Some(now);
+        }
+        Ok(())
+    }
+
+    fn get_pending_steps(&self, plan_id: i64) -> Result<Vec<MigrationStep>> {
+        Ok(self
+            .steps_of(plan_id)
+            .into_iter()
+            .filter(|s| s.status == StepStatus::Pending)
+            .cloned()
+            .collect())
+    }
+
+    fn add_step_dependency(&mut self, step_id: i64, depends_on: i64) -> Result<()> {
+        self.dependencies.insert((step_id, depends_on));
+        Ok(())
+    }
+
+    fn get_step_dependencies(&self, step_id: i64) -> Result<Vec<i64>> {
+        Ok(self
+            .dependencies
+            .iter()
+            .filter(|(step, _)| *step == step_id)
+            .map(|&(_, depends_on)| depends_on)
+            .collect())
+    }
+
+    fn get_plan_dependencies(&self, plan_id: i64) -> Result<Vec<(i64, i64)>> {
+        Ok(self
+            .dependencies
+            .iter()
+            .filter(|(step, _)| self.steps.get(step).is_some_and(|s| s.plan_id == plan_id))
+            .copied()
+            .collect())
+    }
+
+    fn get_plan_delete_bytes(&self, plan_id: i64) -> Result<i64> {
+        Ok(self
+            .steps_of(plan_id)
+            .into_iter()
+            .filter(|s| s.action == StepAction::Delete)
+            .map(|s| self.step_bytes(s))
+            .sum())
+    }
+
+    fn get_delete_totals_since(&self, since: DateTime<Utc>, created_by: Option<&str>) -> Result<(i64, i64)> {
+        let deletes: Vec<_> = self
+            .steps
+            .values()
+            .filter(|s| s.action == StepAction::Delete)
+            .filter(|s| {
+                self.plans
+                    .get(&s.plan_id)
+                    .is_some_and(|p| {
+                        p.status != PlanStatus::Aborted
+                            && p.created_at >= since
+                            && created_by.is_none_or(|by| p.created_by.as_deref() == Some(by))
+                    })
+            })
+            .collect();
+        let bytes = deletes.iter().map(|s| self.step_bytes(s)).sum();
+        Ok((deletes.len() as i64, bytes))
+    }
+
+    fn get_steps_page(&self, plan_id: i64, query: &StepQuery) -> Result<StepPage> {
+        let directory = query.directory.as_deref().map(|d| d.trim_end_matches('/'));
+        let matching: Vec<_> = self
+            .steps_of(plan_id)
+            .into_iter()
+            .filter(|s| directory.is_none_or(|d| step_directory(&s.source_path) == d))
+            .collect();
+
+        Ok(StepPage {
+            total: matching.len() as i64,
+            steps: matching
+                .into_iter()
+                .skip(query.offset.max(0) as usize)
+                .take(query.limit.max(0) as usize)
+                .cloned()
+                .collect(),
+            offset: query.offset,
+        })
+    }
+
+    fn get_step_directories(
+        &self,
+        plan_id: i64,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<StepDirectory>> {
+        let mut directories: BTreeMap<&str, StepDirectory> = BTreeMap::new();
+        for step in self.steps_of(plan_id) {
+            let directory = step_directory(&step.source_path);
+            let entry = directories.entry(directory).or_insert(StepDirectory {
+                directory: directory.to_string(),
+                step_count: 0,
+                total_bytes: 0,
+            });
+            entry.step_count += 1;
+            entry.total_bytes += self.step_bytes(step);
+        }
+
+        let mut directories: Vec<_> = directories.into_values().collect();
+        directories.sort_by(|a, b| {
+            b.step_count
+                .cmp(&a.step_count)
+                .then_with(|| a.directory.cmp(&b.directory))
+        });
+        Ok(directories
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    fn get_plan_summary(&self, plan_id: i64) -> Result<PlanSummary> {
+        let satisfied = self.get_satisfied_files(plan_id)?;
+        let label = |id: Option<i64>| id.and_then(|id| self.drives.get(&id)).map(|d| d.label.clone());
+
+        Ok(PlanSummary {
+            by_action: self.summarize(plan_id, |s| s.action.as_str().to_string()),
+            by_directory: self.summarize(plan_id, |s| {
+                let top = match self.files.get(&s.file_id) {
+                    None => "?",
+                    Some(f) => f.path.split_once('/').map_or(".", |(top, _)| top),
+                };
+                format!("{}:{}", label(Some(s.source_drive_id)).as_deref().unwrap_or("?"), top)
+            }),
+            by_destination: self.summarize(plan_id, |s| {
+                label(s.dest_drive_id).unwrap_or_else(|| "-".to_string())
+            }),
+            satisfied_files: satisfied.len() as i64,
+            satisfied_bytes: satisfied.iter().map(|f| f.size_bytes).sum(),
+        })
+    }
+
+    fn reset_interrupted_steps(&mut self, plan_id: i64) -> Result<usize> {
+        let mut reset = 0;
+        for step in self.steps.values_mut() {
+            if step.plan_id == plan_id && matches!(step.status, StepStatus::InProgress | StepStatus::Volatile) {
+                step.status = StepStatus::Pending;
+                reset += 1;
+            }
+        }
+        Ok(reset)
+    }
+
+    fn start_plan_session(&mut self, plan_id: i64, owner: &SessionOwner) -> Result<i64> {
+        let id = self.allocate_id();
+        let started_at = self.clock.now();
+        self.sessions.insert(
+            id,
+            PlanSession {
+                id,
+                plan_id,
+                started_at,
+                ended_at: None,
+                files_completed: 0,
+                bytes_completed: 0,
+                last_step_id: None,
+                end_reason: None,
+                owner: Some(owner.clone()),
+            },
+        );
+        Ok(id)
+    }
+
+    fn update_plan_session(
+        &mut self,
+        id: i64,
+        files_completed: i64,
+        bytes_completed: i64,
+        last_step_id: i64,
+    ) -> Result<()> {
+        if let Some(session) = self.sessions.get_mut(&id) {
+            session.files_completed = files_completed;
+            session.bytes_completed = bytes_completed;
+            session.last_step_id = Some(last_step_id);
+        }
+        Ok(())
+    }
+
+    fn end_plan_session(&mut self, id: i64, reason: &str) -> Result<()> {
+        let now = self.clock.now();
+        if let Some(session) = self.sessions.get_mut(&id) {
+            session.ended_at = Some(now);
+            session.end_reason = Some(reason.to_string());
+        }
+        Ok(())
+    }
+
+    fn list_plan_sessions(&self, plan_id: i64) -> Result<Vec<PlanSession>> {
+        Ok(self
+            .sessions
+            .values()
+            .filter(|s| s.plan_id == plan_id)
+            .cloned()
+            .collect())
+    }
+
+    fn start_step_attempt(&mut self, id: i64) -> Result<()> {
+        let Some(step) = self.steps.get_mut(&id) else {
+            return Ok(());
+        };
+        step.status = StepStatus::InProgress;
+        step.error = None;
+
+        let run = self.step_runs.entry(id).or_default();
+        run.started_at = Some(self.clock.now());
+        run.finished_at = None;
+        run.attempts += 1;
+        Ok(())
+    }
+
+    fn finish_step_attempt(&mut self, id: i64, bytes: Option<i64>) -> Result<()> {
+        if self.steps.contains_key(&id) {
+            let run = self.step_runs.entry(id).or_default();
+            run.finished_at = Some(self.clock.now());
+            run.bytes = bytes;
+        }
+        Ok(())
+    }
+
+    fn get_step_log(&self, plan_id: i64) -> Result<Vec<StepLogEntry>> {
+        Ok(self
+            .steps_of(plan_id)
+            .into_iter()
+            .map(|step| {
+                let run = self.step_runs.get(&step.id).cloned().unwrap_or_default();
+                StepLogEntry {
+                    step_id: step.id,
+                    step_order: step.step_order,
+                    action: step.action,
+                    source_path: step.source_path.clone(),
+                    dest_path: step.dest_path.clone(),
+                    status: step.status,
+                    started_at: run.started_at,
+                    finished_at: run.finished_at,
+                    duration_ms: run
+                        .started_at
+                        .zip(run.finished_at)
+                        .map(|(start, end)| (end - start).num_milliseconds()),
+                    bytes: run.bytes,
+                    attempts: run.attempts,
+                    pre_hash: step.pre_hash.clone(),
+                    post_hash: step.post_hash.clone(),
+                    hash_algorithm: step.hash_algorithm,
+                    hash_verified: step
+                        .pre_hash
+                        .as_ref()
+                        .zip(step.post_hash.as_ref())
+                        .map(|(pre, post)| pre == post),
+                    error: step.error.clone(),
+                }
+            })
+            .collect())
+    }
+
+    fn add_satisfied_file(&mut self, plan_id: i64, file_id: i64, step_id: i64) -> Result<()> {
+        self.satisfied.insert((plan_id, file_id), step_id);
+        Ok(())
+    }
+
+    fn get_satisfied_files(&self, plan_id: i64) -> Result<Vec<SatisfiedFile>> {
+        let mut files: Vec<_> = self
+            .satisfied
+            .range((plan_id, i64::MIN)..=(plan_id, i64::MAX))
+            .filter_map(|(&(_, file_id), &step_id)| {
+                let file = self.files.get(&file_id)?;
+                Some(SatisfiedFile {
+                    file_id,
+                    step_id,
+                    path: file.path.clone(),
+                    size_bytes: file.size_bytes,
+                })
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    fn record_space_usage(
+        &mut self,
+        plan_id: i64,
+        drive_id: i64,
+        phase: SpacePhase,
+        used_bytes: i64,
+    ) -> Result<()> {
+        let usage = self.space_usage.entry((plan_id, drive_id)).or_default();
+        match phase {
+            SpacePhase::Before => {
+                usage.0.get_or_insert(used_bytes);
+            }
+            SpacePhase::After => usage.1 = Some(used_bytes),
+        }
+        Ok(())
+    }
+
+    fn get_space_usage(&self, plan_id: i64) -> Result<Vec<PlanSpaceUsage>> {
+        Ok(self
+            .space_usage
+            .range((plan_id, i64::MIN)..=(plan_id, i64::MAX))
+            .map(|(&(_, drive_id), &(used_before, used_after))| PlanSpaceUsage {
+                drive_id,
+                used_before,
+                used_after,
+            })
+            .collect())
+    }
+}
+
+impl AuditDatabase for MemoryDatabase {
+    fn log_audit(&mut self, entry: &AuditLogEntry) -> Result<i64> {
+        let id = self.allocate_id();
+        self.audit.insert(id, AuditLogEntry { id, ..entry.clone() });
+        Ok(id)
+    }
+
+    fn get_audit_entries(
+        &self,
+        plan_id: Option<i64>,
+        file_id: Option<i64>,
+        limit: Option<i32>,
+    ) -> Result<Vec<AuditLogEntry>> {
+        let mut entries: Vec<_> = self
+            .audit
+            .values()
+            .filter(|e| plan_id.is_none_or(|id| e.plan_id == Some(id)))
+            .filter(|e| file_id.is_none_or(|id| e.file_id == Some(id)))
+            .cloned()
+            .collect();
+        entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| b.id.cmp(&a.id)));
+        if let Some(limit) = limit {
+            entries.truncate(limit.max(0) as usize);
+        }
+        Ok(entries)
+    }
+
+    fn get_audit_entries_for_plan(&self, plan_id: i64) -> Result<Vec<AuditLogEntry>> {
+        self.get_audit_entries(Some(plan_id), None, None)
+    }
+}
+
+impl VerificationDatabase for MemoryDatabase {
+    fn record_verification_failure(&mut self, failure: &VerificationFailure) -> Result<i64> {
+        let existing = self.failures.values().find(|f| {
+            f.path == failure.path && f.reason == failure.reason && f.acknowledged_at.is_none()
+        });
+        if let Some(existing) = existing {
+            return Ok(existing.id);
+        }
+
+        let id = self.allocate_id();
+        let detected_at = self.clock.now();
+        self.failures.insert(
+            id,
+            VerificationFailure {
+                id,
+                detected_at,
+                acknowledged_at: None,
+                ..failure.clone()
+            },
+        );
+        Ok(id)
+    }
+
+    fn list_unacknowledged_failures(&self) -> Result<Vec<VerificationFailure>> {
+        let mut failures: Vec<_> = self
+            .failures
+            .values()
+            .filter(|f| f.acknowledged_at.is_none())
+            .cloned()
+            .collect();
+        failures.sort_by_key(|f| (f.detected_at, f.id));
+        Ok(failures)
+    }
+
+    fn acknowledge_verification_failure(&mut self, id: i64) -> Result<bool> {
+        let now = self.clock.now();
+        match self.failures.get_mut(&id) {
+            Some(failure) if failure.acknowledged_at.is_none() => {
+                failure.acknowledged_at = Some(now);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn acknowledge_all_verification_failures(&mut self) -> Result<usize> {
+        let now = self.clock.now();
+        let mut acknowledged = 0;
+        for failure in self.failures.values_mut() {
+            if failure.acknowledged_at.is_none() {
+                failure.acknowledged_at = Some(now);
+                acknowledged += 1;
+            }
+        }
+        Ok(acknowledged)
+    }
+
+    fn schedule_verification_job(&mut self, plan_id: i64, due_at: DateTime<Utc>) -> Result<i64> {
+        let id = self.allocate_id();
+        let created_at = self.clock.now();
+        self.jobs.insert(
+            id,
+            VerificationJob {
+                id,
+                plan_id,
+                due_at,
+                status: "pending".to_string(),
+                files_checked: 0,
+                files_failed: 0,
+                created_at,
+                completed_at: None,
+            },
+        );
+        Ok(id)
+    }
+
+    fn list_pending_verification_jobs(
+        &self,
+        due_by: Option<DateTime<Utc>>,
+    ) -> Result<Vec<VerificationJob>> {
+        let mut jobs: Vec<_> = self
+            .jobs
+            .values()
+            .filter(|j| j.status == "pending" && due_by.is_none_or(|by| j.due_at <= by))
+            .cloned()
+            .collect();
+        jobs.sort_by_key(|j| (j.due_at, j.id));
+        Ok(jobs)
+    }
+
+    fn complete_verification_job(
+        &mut self,
+        id: i64,
+        files_checked: i64,
+        files_failed: i64,
+    ) -> Result<()> {
+        let now = self.clock.now();
+        if let Some(job) = self.jobs.get_mut(&id) {
+            job.status = if files_failed > 0 { "failed" } else { "passed" }.to_string();
+            job.files_checked = files_checked;
+            job.files_failed = files_failed;
+            job.completed_at = Some(now);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Backend, DriveRole, Priority};
+    use crate::migrate::{Planner, PlannerOptions};
+
+    fn drive(label: &str, mount: &str, role: DriveRole) -> Drive {
+        Drive {
+            id: 0,
+            label: label.to_string(),
+            device_id: None,
+            device_path: None,
+            uuid: None,
+            mount_path: Some(mount.to_string()),
+            fs_type: None,
+            total_bytes: None,
+            role,
+            is_online: true,
+            is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
+            backend: Backend::Local,
+            rclone_remote: None,
+            scanned_at: None,
+            added_at: Utc::now(),
+        }
+    }
+
+    fn file(drive_id: i64, path: &str, hash: &str) -> File {
+        File {
+            id: 0,
+            drive_id,
+            path: path.to_string(),
+            abs_path: String::new(),
+            filename: path.rsplit('/').next().unwrap().to_string(),
+            extension: None,
+            size_bytes: 100,
+            md5_hash: None,
+            blake3_hash: Some(hash.to_string()),
+            created_at: None,
+            modified_at: None,
+            accessed_at: None,
+            inode: None,
+            device_num: None,
+            nlinks: None,
+            owner_uid: None,
+            mime_type: None,
+            is_symlink: false,
+            symlink_target: None,
+            git_remote_url: None,
+            category: None,
+            subcategory: None,
+            target_path: None,
+            target_drive_id: None,
+            priority: Priority::Normal,
+            duplicate_group: None,
+            is_original: false,
+            rmlint_type: None,
+            status: FileStatus::Indexed,
+            migrated_to: None,
+            migrated_to_drive: None,
+            migrated_at: None,
+            verified_hash: None,
+            error: None,
+            indexed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_planner_runs_against_memory_database() {
+        let mut db = MemoryDatabase::new();
+        let nas = db.add_drive(&drive("nas", "/mnt/nas/", DriveRole::Source)).unwrap();
+        assert!(db.add_drive(&drive("nas", "/mnt/other", DriveRole::Source)).is_err());
+
+        let original = db.add_file(&file(nas, "photos/a.jpg", "aaa")).unwrap();
+        let copy = db.add_file(&file(nas, "old/a.jpg", "aaa")).unwrap();
+        let original = db.get_file(original).unwrap().unwrap();
+        assert_eq!(original.abs_path, "/mnt/nas/photos/a.jpg");
+        assert_eq!(db.get_files_by_hash("aaa").unwrap().len(), 2);
+
+        let copy = db.get_file(copy).unwrap().unwrap();
+        let plan_id = Planner::new(&mut db, PlannerOptions::default())
+            .create_dedup_plan(vec![copy], &original)
+            .unwrap();
+
+        let plan = db.get_plan(plan_id).unwrap().unwrap();
+        assert_eq!(plan.status, PlanStatus::Draft);
+        assert_eq!(db.get_plan_delete_bytes(plan_id).unwrap(), 100);
+
+        let steps = db.get_steps_for_plan(plan_id).unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].action, StepAction::Delete);
+
+        let page = db
+            .get_steps_page(plan_id, &StepQuery { directory: Some("/mnt/nas/old/".into()), ..Default::default() })
+            .unwrap();
+        assert_eq!(page.total, 1);
+
+        let summary = db.get_plan_summary(plan_id).unwrap();
+        assert_eq!(summary.by_directory[0].key, "nas:old");
+        assert!(!db.get_audit_entries_for_plan(plan_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_memory_reconcile_follows_remounted_drive() {
+        let clock = Clock::fixed(Utc::now());
+        let mut db = MemoryDatabase::new();
+        let nas = db
+            .add_drive(&Drive { uuid: Some("n-uuid".to_string()), ..drive("nas", "/mnt/nas", DriveRole::Source) })
+            .unwrap();
+        let original = db.add_file(&file(nas, "photos/a.jpg", "aaa")).unwrap();
+        let copy = db.add_file(&file(nas, "photos2/a.jpg", "aaa")).unwrap();
+        let original = db.get_file(original).unwrap().unwrap();
+        let copy = db.get_file(copy).unwrap().unwrap();
+        let plan_id = Planner::new(&mut db, PlannerOptions::default())
+            .create_dedup_plan(vec![copy], &original)
+            .unwrap();
+
+        let lookup = |uuid: &str| (uuid == "n-uuid").then(|| "/media/nas/".to_string());
+        let remaps = crate::db::drives::reconcile_mount_paths(&mut db, &clock, lookup).unwrap();
+        assert_eq!(remaps[0].file_count, 2);
+        assert_eq!(db.get_drive("nas").unwrap().unwrap().mount_path.as_deref(), Some("/media/nas"));
+        let steps = db.get_steps_for_plan(plan_id).unwrap();
+        assert_eq!(steps[0].source_path, "/media/nas/photos2/a.jpg");
+
+        let audit = db.get_audit_entries(None, None, None).unwrap();
+        let remapped = audit.iter().find(|e| e.action == "drive_remapped").unwrap();
+        assert_eq!(remapped.timestamp, clock.now());
+    }
+
+    #[test]
+    fn test_memory_verification_failures_deduplicate() {
+        let clock = Clock::fixed(Utc::now());
+        let mut db = MemoryDatabase::new().with_clock(clock.clone());
+        let failure = VerificationFailure {
+            id: 0,
+            file_id: None,
+            plan_id: None,
+            step_id: None,
+            drive_id: None,
+            path: "/mnt/a".to_string(),
+            expected_hash: None,
+            actual_hash: None,
+            reason: "missing".to_string(),
+            detected_at: Utc::now(),
+            acknowledged_at: None,
+        };
+
+        let first = db.record_verification_failure(&failure).unwrap();
+        assert_eq!(db.record_verification_failure(&failure).unwrap(), first);
+        assert!(db.acknowledge_verification_failure(first).unwrap());
+        assert!(db.list_unacknowledged_failures().unwrap().is_empty());
+
+        let job = db.schedule_verification_job(1, clock.now() + chrono::Duration::days(7)).unwrap();
+        assert!(db.list_pending_verification_jobs(Some(clock.now())).unwrap().is_empty());
+        clock.advance(chrono::Duration::days(8));
+        assert_eq!(db.list_pending_verification_jobs(Some(clock.now())).unwrap()[0].id, job);
+    }
+}
@@ -2,8 +2,17 @@ pub mod audit;
 pub mod drives;
 pub mod duplicates;
 pub mod files;
+pub mod inbox;
+pub mod jobs;
+pub mod ledger;
+pub mod memory;
+pub mod owners;
 pub mod plans;
+pub mod replicas;
+pub mod scan_errors;
 pub mod schema;
+pub mod suggestions;
+pub mod verification;
 
 use crate::error::{OrdneError, Result};
 use chrono::{DateTime, Utc};
@@ -11,8 +20,15 @@ use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
-pub use audit::AuditDatabase;
-pub use plans::PlansDatabase;
+pub use audit::{Attribution, AuditDatabase, FileSnapshot};
+pub use ledger::{Ledger, LedgerRecord};
+pub use memory::MemoryDatabase;
+pub use plans::{
+    PlanSession, PlanSpaceUsage, PlanSummary, PlansDatabase, SatisfiedFile, SessionOwner, SpacePhase,
+    StepDirectory, StepLogEntry, StepPage, StepQuery, SummaryRow,
+};
+pub use suggestions::{ClassificationSuggestion, SuggestionStatus};
+pub use verification::{VerificationDatabase, VerificationFailure, VerificationJob};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Drive {
@@ -27,6 +43,10 @@ pub struct Drive {
     pub role: DriveRole,
     pub is_online: bool,
     pub is_readonly: bool,
+    /// Kept offline for good; known only from the catalog.
+    pub is_cold: bool,
+    /// On an encrypted volume (LUKS, or marked with `drive encrypted`).
+    pub is_encrypted: bool,
     pub backend: Backend,
     pub rclone_remote: Option<String>,
     pub scanned_at: Option<DateTime<Utc>>,
@@ -51,6 +71,7 @@ impl DriveRole {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self> {
         match s {
             "source" => Ok(DriveRole::Source),
@@ -76,6 +97,7 @@ impl Backend {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self> {
         match s {
             "local" => Ok(Backend::Local),
@@ -98,9 +120,13 @@ pub struct File {
     pub blake3_hash: Option<String>,
     pub created_at: Option<DateTime<Utc>>,
     pub modified_at: Option<DateTime<Utc>>,
+    /// Last access time, only captured on request and where atime is reliable.
+    pub accessed_at: Option<DateTime<Utc>>,
     pub inode: Option<i64>,
     pub device_num: Option<i64>,
     pub nlinks: Option<i32>,
+    /// Numeric owner (uid) on Unix filesystems; names come from the scanning host.
+    pub owner_uid: Option<i64>,
     pub mime_type: Option<String>,
     pub is_symlink: bool,
     pub symlink_target: Option<String>,
@@ -122,6 +148,22 @@ pub struct File {
     pub indexed_at: DateTime<Utc>,
 }
 
+impl File {
+    /// The catalog hash steps are verified against, BLAKE3 preferred.
+    pub fn content_hash(&self) -> Option<String> {
+        self.blake3_hash.clone().or_else(|| self.md5_hash.clone())
+    }
+
+    /// Algorithm of [`File::content_hash`].
+    pub fn content_hash_algorithm(&self) -> Option<HashAlgorithm> {
+        if self.blake3_hash.is_some() {
+            Some(HashAlgorithm::Blake3)
+        } else {
+            self.md5_hash.as_ref().map(|_| HashAlgorithm::Md5)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum FileStatus {
     Indexed,
@@ -144,6 +186,7 @@ impl FileStatus {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self> {
         match s {
             "indexed" => Ok(FileStatus::Indexed),
@@ -157,7 +200,8 @@ impl FileStatus {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+/// Ordered from most to least important.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Priority {
     Critical,
     Normal,
@@ -175,6 +219,7 @@ impl Priority {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self> {
         match s {
             "critical" => Ok(Priority::Critical),
@@ -210,6 +255,39 @@ pub struct MigrationPlan {
     pub total_bytes: i64,
     pub completed_files: i32,
     pub completed_bytes: i64,
+    /// Client or agent that created the plan. `None` for the CLI.
+    pub created_by: Option<String>,
+    /// Sequence the engine runs ready steps in.
+    pub step_ordering: StepOrdering,
+}
+
+/// How the engine picks among steps whose dependencies are done.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum StepOrdering {
+    /// The order the planner laid the steps out in.
+    Planned,
+    /// Critical files first and Trash last, so an interrupted run has
+    /// secured the most important data; planned order within a priority.
+    #[default]
+    Priority,
+}
+
+impl StepOrdering {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepOrdering::Planned => "planned",
+            StepOrdering::Priority => "priority",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "planned" => Ok(StepOrdering::Planned),
+            "priority" => Ok(StepOrdering::Priority),
+            _ => Err(OrdneError::Config(format!("Invalid step ordering: {}", s))),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -219,6 +297,10 @@ pub enum PlanStatus {
     InProgress,
     Completed,
     Aborted,
+    /// Approved, but waiting for an offline drive to be connected.
+    Queued,
+    /// Started, and stopped at a checkpoint until its execution window opens.
+    Paused,
 }
 
 impl PlanStatus {
@@ -229,9 +311,12 @@ impl PlanStatus {
             PlanStatus::InProgress => "in_progress",
             PlanStatus::Completed => "completed",
             PlanStatus::Aborted => "aborted",
+            PlanStatus::Queued => "queued",
+            PlanStatus::Paused => "paused",
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self> {
         match s {
             "draft" => Ok(PlanStatus::Draft),
@@ -239,6 +324,8 @@ impl PlanStatus {
             "in_progress" => Ok(PlanStatus::InProgress),
             "completed" => Ok(PlanStatus::Completed),
             "aborted" => Ok(PlanStatus::Aborted),
+            "queued" => Ok(PlanStatus::Queued),
+            "paused" => Ok(PlanStatus::Paused),
             _ => Err(OrdneError::Config(format!("Invalid plan status: {}", s))),
         }
     }
@@ -257,6 +344,8 @@ pub struct MigrationStep {
     pub status: StepStatus,
     pub pre_hash: Option<String>,
     pub post_hash: Option<String>,
+    /// Algorithm that produced `pre_hash` and `post_hash`.
+    pub hash_algorithm: Option<HashAlgorithm>,
     pub executed_at: Option<DateTime<Utc>>,
     pub error: Option<String>,
     pub step_order: i32,
@@ -282,6 +371,7 @@ impl StepAction {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self> {
         match s {
             "move" => Ok(StepAction::Move),
@@ -294,6 +384,54 @@ impl StepAction {
     }
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Blake3,
+    Md5,
+}
+
+impl HashAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Md5 => "md5",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            "md5" => Ok(HashAlgorithm::Md5),
+            _ => Err(OrdneError::Config(format!("Invalid hash algorithm: {}", s))),
+        }
+    }
+
+    /// Length of the hex digest.
+    pub fn hex_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Blake3 => 64,
+            HashAlgorithm::Md5 => 32,
+        }
+    }
+
+    /// Guess the algorithm of a hex digest from its length.
+    pub fn detect(hash: &str) -> Option<Self> {
+        match hash.len() {
+            64 => Some(HashAlgorithm::Blake3),
+            32 => Some(HashAlgorithm::Md5),
+            _ => None,
+        }
+    }
+
+    /// The recorded algorithm, or the one `hash` looks like if none was recorded.
+    pub fn resolve(recorded: Option<Self>, hash: &str) -> Result<Self> {
+        recorded
+            .or_else(|| Self::detect(hash))
+            .ok_or_else(|| OrdneError::Migration(format!("Unknown hash algorithm for {}", hash)))
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum StepStatus {
     Pending,
@@ -301,6 +439,8 @@ pub enum StepStatus {
     Completed,
     Failed,
     RolledBack,
+    /// Skipped because the source was being written to; retried later.
+    Volatile,
 }
 
 impl StepStatus {
@@ -311,9 +451,11 @@ impl StepStatus {
             StepStatus::Completed => "completed",
             StepStatus::Failed => "failed",
             StepStatus::RolledBack => "rolled_back",
+            StepStatus::Volatile => "volatile",
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self> {
         match s {
             "pending" => Ok(StepStatus::Pending),
@@ -321,6 +463,7 @@ impl StepStatus {
             "completed" => Ok(StepStatus::Completed),
             "failed" => Ok(StepStatus::Failed),
             "rolled_back" => Ok(StepStatus::RolledBack),
+            "volatile" => Ok(StepStatus::Volatile),
             _ => Err(OrdneError::Config(format!("Invalid step status: {}", s))),
         }
     }
@@ -336,6 +479,13 @@ pub struct AuditLogEntry {
     pub drive_id: Option<i64>,
     pub details: Option<String>,
     pub agent_mode: Option<String>,
+    /// JSON `FileSnapshot` of the affected file before the action. Undoable
+    /// operations store what [`crate::undo`] needs to revert them instead.
+    pub before_state: Option<String>,
+    /// JSON `FileSnapshot` of the affected file after the action.
+    pub after_state: Option<String>,
+    /// Identifier of the client or agent that performed the action.
+    pub actor: Option<String>,
 }
 
 pub trait Database {
@@ -345,24 +495,80 @@ pub trait Database {
     fn add_drive(&mut self, drive: &Drive) -> Result<i64>;
     fn list_drives(&self) -> Result<Vec<Drive>>;
     fn update_drive_online_status(&mut self, label: &str, is_online: bool) -> Result<()>;
+    /// Move drive `drive_id` to `new_mount`, rewriting the paths of plan
+    /// steps on it. Returns the number of files catalogued on the drive.
+    fn remap_drive_mount(&mut self, drive_id: i64, new_mount: &str) -> Result<usize>;
     fn get_file(&self, id: i64) -> Result<Option<File>>;
     fn add_file(&mut self, file: &File) -> Result<i64>;
     fn update_file_status(&mut self, id: i64, status: FileStatus) -> Result<()>;
+    /// Every catalogued copy whose MD5 or BLAKE3 hash is `hash`.
+    fn get_files_by_hash(&self, hash: &str) -> Result<Vec<File>>;
 }
 
 pub struct SqliteDatabase {
     conn: Connection,
+    ledger: Option<Ledger>,
+    attribution: Option<Attribution>,
 }
 
 impl SqliteDatabase {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let conn = Connection::open(path)?;
-        Ok(Self { conn })
+        Ok(Self { conn, ledger: None, attribution: None })
     }
 
     pub fn open_in_memory() -> Result<Self> {
         let conn = Connection::open_in_memory()?;
-        Ok(Self { conn })
+        Ok(Self { conn, ledger: None, attribution: None })
+    }
+
+    /// Mirror audit entries and plan state changes into `ledger`.
+    pub fn set_ledger(&mut self, ledger: Ledger) {
+        self.ledger = Some(ledger);
+    }
+
+    pub fn ledger(&self) -> Option<&Ledger> {
+        self.ledger.as_ref()
+    }
+
+    /// Attribute audit entries and plans that name no one to `attribution`,
+    /// for a connection opened on behalf of a single user.
+    pub fn set_attribution(&mut self, attribution: Attribution) {
+        self.attribution = Some(attribution);
+    }
+
+    pub fn attribution(&self) -> Option<&Attribution> {
+        self.attribution.as_ref()
+    }
+
+    /// Copy the whole database into memory, for changes that must never
+    /// reach the real catalog.
+    pub fn in_memory_copy(&self) -> Result<Self> {
+        let mut conn = Connection::open_in_memory()?;
+        rusqlite::backup::Backup::new(&self.conn, &mut conn)?.run_to_completion(
+            1024,
+            std::time::Duration::ZERO,
+            None,
+        )?;
+        Ok(Self { conn, ledger: None, attribution: None })
+    }
+
+    /// Run `f` as one transaction: committed if it returns `Ok`, rolled back
+    /// if it fails. Ledger lines written inside `f` are not taken back.
+    pub fn in_transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+        match f(self) {
+            Ok(value) => {
+                self.conn.execute_batch("COMMIT")?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback) = self.conn.execute_batch("ROLLBACK") {
+                    log::warn!("Could not roll back transaction: {}", rollback);
+                }
+                Err(e)
+            }
+        }
     }
 
     pub fn conn(&self) -> &Connection {
@@ -382,7 +588,7 @@ impl Database for SqliteDatabase {
     fn get_drive(&self, label: &str) -> Result<Option<Drive>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, label, device_id, device_path, uuid, mount_path, fs_type, total_bytes,
-                    role, is_online, is_readonly, backend, rclone_remote, scanned_at, added_at
+                    role, is_online, is_readonly, backend, rclone_remote, scanned_at, added_at, is_cold, is_encrypted
              FROM drives WHERE label = ?1"
         )?;
 
@@ -400,6 +606,8 @@ impl Database for SqliteDatabase {
                     role: DriveRole::from_str(&row.get::<_, String>(8)?).unwrap(),
                     is_online: row.get(9)?,
                     is_readonly: row.get(10)?,
+                    is_cold: row.get(15)?,
+                    is_encrypted: row.get(16)?,
                     backend: Backend::from_str(&row.get::<_, String>(11)?).unwrap(),
                     rclone_remote: row.get(12)?,
                     scanned_at: row.get::<_, Option<String>>(13)?
@@ -416,7 +624,7 @@ impl Database for SqliteDatabase {
                                 })
                         })
                         .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|| Utc::now()),
+                        .unwrap_or_else(Utc::now),
                 })
             })
             .optional()?;
@@ -427,7 +635,7 @@ impl Database for SqliteDatabase {
     fn get_drive_by_id(&self, id: i64) -> Result<Option<Drive>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, label, device_id, device_path, uuid, mount_path, fs_type, total_bytes,
-                    role, is_online, is_readonly, backend, rclone_remote, scanned_at, added_at
+                    role, is_online, is_readonly, backend, rclone_remote, scanned_at, added_at, is_cold, is_encrypted
              FROM drives WHERE id = ?1"
         )?;
 
@@ -445,6 +653,8 @@ impl Database for SqliteDatabase {
                     role: DriveRole::from_str(&row.get::<_, String>(8)?).unwrap(),
                     is_online: row.get(9)?,
                     is_readonly: row.get(10)?,
+                    is_cold: row.get(15)?,
+                    is_encrypted: row.get(16)?,
                     backend: Backend::from_str(&row.get::<_, String>(11)?).unwrap(),
                     rclone_remote: row.get(12)?,
                     scanned_at: row.get::<_, Option<String>>(13)?
@@ -461,7 +671,7 @@ impl Database for SqliteDatabase {
                                 })
                         })
                         .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|| Utc::now()),
+                        .unwrap_or_else(Utc::now),
                 })
             })
             .optional()?;
@@ -472,8 +682,8 @@ impl Database for SqliteDatabase {
     fn add_drive(&mut self, drive: &Drive) -> Result<i64> {
         self.conn.execute(
             "INSERT INTO drives (label, device_id, device_path, uuid, mount_path, fs_type,
-                                total_bytes, role, is_online, is_readonly, backend, rclone_remote)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                                total_bytes, role, is_online, is_readonly, backend, rclone_remote, is_cold, is_encrypted)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
             (
                 &drive.label,
                 &drive.device_id,
@@ -487,6 +697,8 @@ impl Database for SqliteDatabase {
                 drive.is_readonly,
                 drive.backend.as_str(),
                 &drive.rclone_remote,
+                drive.is_cold,
+                drive.is_encrypted,
             ),
         )?;
         Ok(self.conn.last_insert_rowid())
@@ -495,7 +707,7 @@ impl Database for SqliteDatabase {
     fn list_drives(&self) -> Result<Vec<Drive>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, label, device_id, device_path, uuid, mount_path, fs_type, total_bytes,
-                    role, is_online, is_readonly, backend, rclone_remote, scanned_at, added_at
+                    role, is_online, is_readonly, backend, rclone_remote, scanned_at, added_at, is_cold, is_encrypted
              FROM drives ORDER BY added_at"
         )?;
 
@@ -513,6 +725,8 @@ impl Database for SqliteDatabase {
                     role: DriveRole::from_str(&row.get::<_, String>(8)?).unwrap(),
                     is_online: row.get(9)?,
                     is_readonly: row.get(10)?,
+                    is_cold: row.get(15)?,
+                    is_encrypted: row.get(16)?,
                     backend: Backend::from_str(&row.get::<_, String>(11)?).unwrap(),
                     rclone_remote: row.get(12)?,
                     scanned_at: row.get::<_, Option<String>>(13)?
@@ -529,7 +743,7 @@ impl Database for SqliteDatabase {
                                 })
                         })
                         .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|| Utc::now()),
+                        .unwrap_or_else(Utc::now),
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -548,6 +762,10 @@ impl Database for SqliteDatabase {
         Ok(())
     }
 
+    fn remap_drive_mount(&mut self, drive_id: i64, new_mount: &str) -> Result<usize> {
+        drives::remap_drive_mount(&self.conn, drive_id, new_mount)
+    }
+
     fn get_file(&self, id: i64) -> Result<Option<File>> {
         files::get_file(&self.conn, id)
     }
@@ -559,6 +777,10 @@ impl Database for SqliteDatabase {
     fn update_file_status(&mut self, id: i64, status: FileStatus) -> Result<()> {
         files::update_file_status(&self.conn, id, status)
     }
+
+    fn get_files_by_hash(&self, hash: &str) -> Result<Vec<File>> {
+        files::list_files_by_hash(&self.conn, hash)
+    }
 }
 
 #[cfg(test)]
@@ -587,6 +809,8 @@ mod tests {
             role: DriveRole::Source,
             is_online: true,
             is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
             backend: Backend::Local,
             rclone_remote: None,
             scanned_at: None,
@@ -625,9 +849,45 @@ mod tests {
             drive_id: Some(1),
             details: Some("test details".to_string()),
             agent_mode: Some("manual".to_string()),
+            before_state: None,
+            after_state: None,
+            actor: None,
         };
 
         let id = db.log_audit(&entry).unwrap();
         assert!(id > 0);
     }
+
+    #[test]
+    fn test_in_transaction_rolls_back_on_error() {
+        let mut db = create_test_db();
+        let count = |db: &SqliteDatabase| -> i64 {
+            db.conn()
+                .query_row("SELECT COUNT(*) FROM audit_log", [], |row| row.get(0))
+                .unwrap()
+        };
+        let entry = AuditLogEntry {
+            id: 0,
+            timestamp: Utc::now(),
+            action: "test_action".to_string(),
+            file_id: None,
+            plan_id: None,
+            drive_id: None,
+            details: None,
+            agent_mode: None,
+            before_state: None,
+            after_state: None,
+            actor: None,
+        };
+
+        let result: Result<()> = db.in_transaction(|db| {
+            db.log_audit(&entry)?;
+            Err(OrdneError::Config("stop".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(count(&db), 0);
+
+        db.in_transaction(|db| db.log_audit(&entry)).unwrap();
+        assert_eq!(count(&db), 1);
+    }
 }
@@ -0,0 +1,122 @@
+//! Who owns what: uid → name mappings recorded by scans, and per-owner
+//! rollups for shared drives.
+
+use crate::error::{OrdneError, Result};
+use crate::util::users::user_id;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Remember the names a scan resolved for the uids it saw. Uids without a
+/// name on the scanning host keep whatever an earlier scan recorded.
+pub fn record_owners(conn: &Connection, owners: &BTreeMap<u32, Option<String>>) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO owners (uid, name) VALUES (?1, ?2)
+         ON CONFLICT(uid) DO UPDATE SET name = excluded.name, updated_at = datetime('now')",
+    )?;
+    for (uid, name) in owners {
+        if let Some(name) = name {
+            stmt.execute((uid, name))?;
+        }
+    }
+    Ok(())
+}
+
+/// The uid for `spec`: a numeric uid, a name a scan recorded, or a login on
+/// this host, in that order.
+pub fn resolve_owner(conn: &Connection, spec: &str) -> Result<i64> {
+    if let Ok(uid) = spec.parse::<u32>() {
+        return Ok(uid.into());
+    }
+    let recorded: Option<i64> = conn
+        .query_row("SELECT uid FROM owners WHERE name = ?1", [spec], |row| row.get(0))
+        .optional()?;
+    recorded
+        .or_else(|| user_id(spec).map(i64::from))
+        .ok_or_else(|| OrdneError::UserInput(format!("Unknown owner '{}'", spec)))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OwnerStats {
+    /// `None` for files cataloged without an owner (remote or imported listings).
+    pub uid: Option<i64>,
+    pub name: Option<String>,
+    pub file_count: i64,
+    pub total_bytes: i64,
+    /// Bytes in extra copies of duplicated content (not the original).
+    pub duplicate_bytes: i64,
+}
+
+impl OwnerStats {
+    /// The name if a scan recorded one, else the numeric uid.
+    pub fn display_name(&self) -> String {
+        match (&self.name, self.uid) {
+            (Some(name), _) => name.clone(),
+            (None, Some(uid)) => uid.to_string(),
+            (None, None) => "unknown".to_string(),
+        }
+    }
+}
+
+/// File count, bytes and duplicate bytes per owner, largest first.
+pub fn get_owner_stats(conn: &Connection, drive_id: Option<i64>) -> Result<Vec<OwnerStats>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.owner_uid, o.name, COUNT(*), COALESCE(SUM(f.size_bytes), 0),
+                COALESCE(SUM(CASE WHEN f.duplicate_group IS NOT NULL AND f.is_original = 0
+                                  THEN f.size_bytes ELSE 0 END), 0)
+         FROM files f LEFT JOIN owners o ON o.uid = f.owner_uid
+         WHERE f.status != 'source_removed' AND (?1 IS NULL OR f.drive_id = ?1)
+         GROUP BY f.owner_uid
+         ORDER BY 4 DESC",
+    )?;
+
+    let stats = stmt
+        .query_map([drive_id], |row| {
+            Ok(OwnerStats {
+                uid: row.get(0)?,
+                name: row.get(1)?,
+                file_count: row.get(2)?,
+                total_bytes: row.get(3)?,
+                duplicate_bytes: row.get(4)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Database, SqliteDatabase};
+
+    #[test]
+    fn test_owner_rollup_and_resolution() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        let conn = db.conn();
+        conn.execute_batch(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'nas', 'source', 1, 'local');
+             INSERT INTO files (drive_id, path, filename, size_bytes, owner_uid, duplicate_group, is_original)
+             VALUES (1, 'a', 'a', 100, 1000, 7, 1), (1, 'b', 'b', 100, 1001, 7, 0),
+                    (1, 'c', 'c', 300, 1001, NULL, 0), (1, 'd', 'd', 5, NULL, NULL, 0);",
+        )
+        .unwrap();
+        let first = BTreeMap::from([(1000, Some("ann".to_string())), (1001, Some("bo".to_string()))]);
+        record_owners(conn, &first).unwrap();
+        record_owners(conn, &BTreeMap::from([(1000, None), (1001, Some("bob".to_string()))])).unwrap();
+
+        let stats = get_owner_stats(conn, None).unwrap();
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[0].display_name(), "bob");
+        assert_eq!((stats[0].file_count, stats[0].total_bytes, stats[0].duplicate_bytes), (2, 400, 100));
+        assert_eq!(stats[1].display_name(), "ann");
+        assert_eq!(stats[1].duplicate_bytes, 0);
+        assert_eq!(stats[2].display_name(), "unknown");
+        assert!(get_owner_stats(conn, Some(2)).unwrap().is_empty());
+
+        assert_eq!(resolve_owner(conn, "bob").unwrap(), 1001);
+        assert_eq!(resolve_owner(conn, "4242").unwrap(), 4242);
+        assert!(matches!(resolve_owner(conn, "no-such-user-ordne"), Err(OrdneError::UserInput(_))));
+    }
+}
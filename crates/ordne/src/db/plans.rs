@@ -1,7 +1,10 @@
-use crate::db::{MigrationPlan, MigrationStep, PlanStatus, StepAction, StepStatus};
+use crate::db::verification::{format_timestamp, parse_timestamp};
+use crate::db::{HashAlgorithm, MigrationPlan, MigrationStep, PlanStatus, StepAction, StepOrdering, StepStatus};
 use crate::error::{OrdneError, Result};
-use chrono::{DateTime, Utc};
+use crate::util::process;
+use chrono::{DateTime, SecondsFormat, Utc};
 use rusqlite::OptionalExtension;
+use serde::Serialize;
 
 pub trait PlansDatabase {
     fn create_plan(&mut self, plan: &MigrationPlan) -> Result<i64>;
@@ -19,19 +22,271 @@ pub trait PlansDatabase {
         status: StepStatus,
         error: Option<String>,
     ) -> Result<()>;
-    fn update_step_hashes(&mut self, id: i64, pre_hash: String, post_hash: Option<String>)
-        -> Result<()>;
+    fn update_step_hashes(
+        &mut self,
+        id: i64,
+        pre_hash: String,
+        post_hash: Option<String>,
+        algorithm: HashAlgorithm,
+    ) -> Result<()>;
     fn mark_step_executed(&mut self, id: i64) -> Result<()>;
     fn get_pending_steps(&self, plan_id: i64) -> Result<Vec<MigrationStep>>;
+    fn add_step_dependency(&mut self, step_id: i64, depends_on: i64) -> Result<()>;
+    fn get_step_dependencies(&self, step_id: i64) -> Result<Vec<i64>>;
+    fn get_plan_dependencies(&self, plan_id: i64) -> Result<Vec<(i64, i64)>>;
+    /// Total size of the files a plan deletes outright.
+    fn get_plan_delete_bytes(&self, plan_id: i64) -> Result<i64>;
+    /// Files and bytes deleted by plans created at or after `since` that
+    /// were not aborted, only those created by `created_by` when given.
+    fn get_delete_totals_since(&self, since: DateTime<Utc>, created_by: Option<&str>) -> Result<(i64, i64)>;
+    /// One page of a plan's steps, optionally limited to one source directory.
+    fn get_steps_page(&self, plan_id: i64, query: &StepQuery) -> Result<StepPage>;
+    /// Source directories of a plan's steps, largest step count first.
+    fn get_step_directories(&self, plan_id: i64, offset: i64, limit: i64)
+        -> Result<Vec<StepDirectory>>;
+    /// Step counts and bytes per action, top-level directory and destination drive.
+    fn get_plan_summary(&self, plan_id: i64) -> Result<PlanSummary>;
+    /// Put steps left `in_progress` by an interrupted run, or skipped as
+    /// `volatile`, back to `pending`.
+    fn reset_interrupted_steps(&mut self, plan_id: i64) -> Result<usize>;
+    fn start_plan_session(&mut self, plan_id: i64, owner: &SessionOwner) -> Result<i64>;
+    /// Checkpoint a session after a step completes.
+    fn update_plan_session(
+        &mut self,
+        id: i64,
+        files_completed: i64,
+        bytes_completed: i64,
+        last_step_id: i64,
+    ) -> Result<()>;
+    fn end_plan_session(&mut self, id: i64, reason: &str) -> Result<()>;
+    /// A plan's execution sessions, oldest first.
+    fn list_plan_sessions(&self, plan_id: i64) -> Result<Vec<PlanSession>>;
+    /// Mark a step in progress and count the attempt.
+    fn start_step_attempt(&mut self, id: i64) -> Result<()>;
+    /// Record when the current attempt ended and, if it succeeded, the bytes it moved.
+    fn finish_step_attempt(&mut self, id: i64, bytes: Option<i64>) -> Result<()>;
+    /// Timing, size, attempts and hashes of every step, in execution order.
+    fn get_step_log(&self, plan_id: i64) -> Result<Vec<StepLogEntry>>;
+    /// Record that `step_id` copies the content of `file_id`, so the plan
+    /// needs no step of its own for it.
+    fn add_satisfied_file(&mut self, plan_id: i64, file_id: i64, step_id: i64) -> Result<()>;
+    fn get_satisfied_files(&self, plan_id: i64) -> Result<Vec<SatisfiedFile>>;
+    /// Record a drive's used bytes around a plan's execution. The first
+    /// `Before` reading is kept when a paused plan resumes.
+    fn record_space_usage(
+        &mut self,
+        plan_id: i64,
+        drive_id: i64,
+        phase: SpacePhase,
+        used_bytes: i64,
+    ) -> Result<()>;
+    fn get_space_usage(&self, plan_id: i64) -> Result<Vec<PlanSpaceUsage>>;
+}
+
+/// Which slice of a plan's steps to list.
+#[derive(Debug, Clone)]
+pub struct StepQuery {
+    pub offset: i64,
+    pub limit: i64,
+    /// Only steps whose source file sits directly in this directory.
+    pub directory: Option<String>,
+}
+
+impl Default for StepQuery {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            limit: 50,
+            directory: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepPage {
+    pub steps: Vec<MigrationStep>,
+    /// Steps matching the query, ignoring offset and limit.
+    pub total: i64,
+    pub offset: i64,
+}
+
+impl StepPage {
+    pub fn has_more(&self) -> bool {
+        self.offset + (self.steps.len() as i64) < self.total
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepDirectory {
+    pub directory: String,
+    pub step_count: i64,
+    pub total_bytes: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SummaryRow {
+    pub key: String,
+    pub step_count: i64,
+    pub total_bytes: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlanSummary {
+    pub by_action: Vec<SummaryRow>,
+    /// Keyed `drive:directory`, by the first component of the file's path on
+    /// its source drive.
+    pub by_directory: Vec<SummaryRow>,
+    pub by_destination: Vec<SummaryRow>,
+    /// Files the plan has no step for because a copy of identical content
+    /// covers them, and the bytes that saves.
+    pub satisfied_files: i64,
+    pub satisfied_bytes: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpacePhase {
+    Before,
+    After,
+}
+
+/// Used bytes on one drive's filesystem before and after a plan ran.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanSpaceUsage {
+    pub drive_id: i64,
+    pub used_before: Option<i64>,
+    pub used_after: Option<i64>,
+}
+
+/// A file a plan skips because another step copies the same content.
+#[derive(Debug, Clone, Serialize)]
+pub struct SatisfiedFile {
+    pub file_id: i64,
+    /// The step copying the identical file.
+    pub step_id: i64,
+    pub path: String,
+    pub size_bytes: i64,
+}
+
+/// One uninterrupted stretch of a plan's execution, typically one night of a
+/// windowed migration. A session still running, or cut short by a crash, has
+/// no `ended_at`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanSession {
+    pub id: i64,
+    pub plan_id: i64,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub files_completed: i64,
+    pub bytes_completed: i64,
+    /// The last step this session completed.
+    pub last_step_id: Option<i64>,
+    /// `window_closed`, `completed`, `failed`, or `interrupted` for a
+    /// session a later run found its owner had died in.
+    pub end_reason: Option<String>,
+    /// The process running the session; unknown for sessions recorded
+    /// before owners were.
+    pub owner: Option<SessionOwner>,
+}
+
+/// The process that runs a plan session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionOwner {
+    pub pid: u32,
+    pub host: String,
+}
+
+impl SessionOwner {
+    /// This process.
+    pub fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            host: process::host_name(),
+        }
+    }
+
+    /// Whether the owner may still be running the session. A process on
+    /// another host cannot be checked and counts as running; a session this
+    /// process left open does not.
+    pub fn is_alive(&self) -> bool {
+        if self.host != process::host_name() {
+            return true;
+        }
+        self.pid != std::process::id() && process::is_running(self.pid)
+    }
+}
+
+/// One step as it ran, for execution logs.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepLogEntry {
+    pub step_id: i64,
+    pub step_order: i32,
+    pub action: StepAction,
+    pub source_path: String,
+    pub dest_path: Option<String>,
+    pub status: StepStatus,
+    /// Start of the latest attempt.
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+    pub bytes: Option<i64>,
+    pub attempts: i64,
+    pub pre_hash: Option<String>,
+    pub post_hash: Option<String>,
+    pub hash_algorithm: Option<HashAlgorithm>,
+    /// Whether the destination hash matched the source, where both were taken.
+    pub hash_verified: Option<bool>,
+    pub error: Option<String>,
+}
+
+impl StepLogEntry {
+    /// Attempts after the first.
+    pub fn retries(&self) -> i64 {
+        (self.attempts - 1).max(0)
+    }
+}
+
+/// Parent directory of `source_path`, without the trailing slash.
+///
+/// Trimming every non-slash character off the right leaves `dir/`.
+const STEP_DIRECTORY_SQL: &str =
+    "rtrim(rtrim(s.source_path, replace(s.source_path, '/', '')), '/')";
+
+fn step_from_row(row: &rusqlite::Row) -> rusqlite::Result<MigrationStep> {
+    Ok(MigrationStep {
+        id: row.get(0)?,
+        plan_id: row.get(1)?,
+        file_id: row.get(2)?,
+        action: StepAction::from_str(&row.get::<_, String>(3)?).unwrap(),
+        source_path: row.get(4)?,
+        source_drive_id: row.get(5)?,
+        dest_path: row.get(6)?,
+        dest_drive_id: row.get(7)?,
+        status: StepStatus::from_str(&row.get::<_, String>(8)?).unwrap(),
+        pre_hash: row.get(9)?,
+        post_hash: row.get(10)?,
+        executed_at: row
+            .get::<_, Option<String>>(11)?
+            .and_then(|s| s.parse::<DateTime<Utc>>().ok()),
+        error: row.get(12)?,
+        step_order: row.get(13)?,
+        hash_algorithm: row
+            .get::<_, Option<String>>(14)?
+            .and_then(|s| HashAlgorithm::from_str(&s).ok()),
+    })
 }
 
 impl PlansDatabase for crate::db::SqliteDatabase {
     fn create_plan(&mut self, plan: &MigrationPlan) -> Result<i64> {
+        let mut plan = plan.clone();
+        if let Some(attribution) = self.attribution() {
+            plan.created_by.get_or_insert_with(|| attribution.actor.clone());
+        }
         let conn = self.conn_mut();
         conn.execute(
             "INSERT INTO migration_plans (description, source_drive_id, target_drive_id,
-                                          status, total_files, total_bytes)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                                          status, total_files, total_bytes, created_by, created_at,
+                                          step_ordering)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
             (
                 &plan.description,
                 plan.source_drive_id,
@@ -39,16 +294,24 @@ impl PlansDatabase for crate::db::SqliteDatabase {
                 plan.status.as_str(),
                 plan.total_files,
                 plan.total_bytes,
+                &plan.created_by,
+                format_timestamp(&plan.created_at),
+                plan.step_ordering.as_str(),
             ),
         )?;
-        Ok(conn.last_insert_rowid())
+        let id = conn.last_insert_rowid();
+        if let Some(ledger) = self.ledger() {
+            ledger.record(&crate::db::LedgerRecord::plan_created(id, &plan));
+        }
+        Ok(id)
     }
 
     fn get_plan(&self, id: i64) -> Result<Option<MigrationPlan>> {
         let conn = self.conn();
         let mut stmt = conn.prepare(
             "SELECT id, created_at, description, source_drive_id, target_drive_id,
-                    status, total_files, total_bytes, completed_files, completed_bytes
+                    status, total_files, total_bytes, completed_files, completed_bytes, created_by,
+                    step_ordering
              FROM migration_plans WHERE id = ?1",
         )?;
 
@@ -56,10 +319,7 @@ impl PlansDatabase for crate::db::SqliteDatabase {
             .query_row([id], |row| {
                 Ok(MigrationPlan {
                     id: row.get(0)?,
-                    created_at: row
-                        .get::<_, String>(1)?
-                        .parse::<DateTime<Utc>>()
-                        .unwrap_or_else(|_| Utc::now()),
+                    created_at: parse_timestamp(&row.get::<_, String>(1)?).unwrap_or_else(Utc::now),
                     description: row.get(2)?,
                     source_drive_id: row.get(3)?,
                     target_drive_id: row.get(4)?,
@@ -68,6 +328,8 @@ impl PlansDatabase for crate::db::SqliteDatabase {
                     total_bytes: row.get(7)?,
                     completed_files: row.get(8)?,
                     completed_bytes: row.get(9)?,
+                    created_by: row.get(10)?,
+                    step_ordering: StepOrdering::from_str(&row.get::<_, String>(11)?).unwrap(),
                 })
             })
             .optional()?;
@@ -80,13 +342,15 @@ impl PlansDatabase for crate::db::SqliteDatabase {
         let query = if let Some(status) = status_filter {
             format!(
                 "SELECT id, created_at, description, source_drive_id, target_drive_id,
-                        status, total_files, total_bytes, completed_files, completed_bytes
+                        status, total_files, total_bytes, completed_files, completed_bytes, created_by,
+                        step_ordering
                  FROM migration_plans WHERE status = '{}' ORDER BY created_at DESC",
                 status.as_str()
             )
         } else {
             "SELECT id, created_at, description, source_drive_id, target_drive_id,
-                    status, total_files, total_bytes, completed_files, completed_bytes
+                    status, total_files, total_bytes, completed_files, completed_bytes, created_by,
+                    step_ordering
              FROM migration_plans ORDER BY created_at DESC"
                 .to_string()
         };
@@ -96,10 +360,7 @@ impl PlansDatabase for crate::db::SqliteDatabase {
             .query_map([], |row| {
                 Ok(MigrationPlan {
                     id: row.get(0)?,
-                    created_at: row
-                        .get::<_, String>(1)?
-                        .parse::<DateTime<Utc>>()
-                        .unwrap_or_else(|_| Utc::now()),
+                    created_at: parse_timestamp(&row.get::<_, String>(1)?).unwrap_or_else(Utc::now),
                     description: row.get(2)?,
                     source_drive_id: row.get(3)?,
                     target_drive_id: row.get(4)?,
@@ -108,6 +369,8 @@ impl PlansDatabase for crate::db::SqliteDatabase {
                     total_bytes: row.get(7)?,
                     completed_files: row.get(8)?,
                     completed_bytes: row.get(9)?,
+                    created_by: row.get(10)?,
+                    step_ordering: StepOrdering::from_str(&row.get::<_, String>(11)?).unwrap(),
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -124,6 +387,9 @@ impl PlansDatabase for crate::db::SqliteDatabase {
         if rows == 0 {
             return Err(OrdneError::PlanNotFound(id));
         }
+        if let Some(ledger) = self.ledger() {
+            ledger.record_plan_status(id, status);
+        }
         Ok(())
     }
 
@@ -149,8 +415,8 @@ impl PlansDatabase for crate::db::SqliteDatabase {
         conn.execute(
             "INSERT INTO migration_steps (plan_id, file_id, action, source_path,
                                           source_drive_id, dest_path, dest_drive_id,
-                                          status, step_order)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                                          status, pre_hash, step_order, hash_algorithm)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             (
                 step.plan_id,
                 step.file_id,
@@ -160,7 +426,9 @@ impl PlansDatabase for crate::db::SqliteDatabase {
                 &step.dest_path,
                 step.dest_drive_id,
                 step.status.as_str(),
+                &step.pre_hash,
                 step.step_order,
+                step.hash_algorithm.map(|a| a.as_str()),
             ),
         )?;
         Ok(conn.last_insert_rowid())
@@ -171,31 +439,12 @@ impl PlansDatabase for crate::db::SqliteDatabase {
         let mut stmt = conn.prepare(
             "SELECT id, plan_id, file_id, action, source_path, source_drive_id,
                     dest_path, dest_drive_id, status, pre_hash, post_hash,
-                    executed_at, error, step_order
+                    executed_at, error, step_order, hash_algorithm
              FROM migration_steps WHERE id = ?1",
         )?;
 
         let step = stmt
-            .query_row([id], |row| {
-                Ok(MigrationStep {
-                    id: row.get(0)?,
-                    plan_id: row.get(1)?,
-                    file_id: row.get(2)?,
-                    action: StepAction::from_str(&row.get::<_, String>(3)?).unwrap(),
-                    source_path: row.get(4)?,
-                    source_drive_id: row.get(5)?,
-                    dest_path: row.get(6)?,
-                    dest_drive_id: row.get(7)?,
-                    status: StepStatus::from_str(&row.get::<_, String>(8)?).unwrap(),
-                    pre_hash: row.get(9)?,
-                    post_hash: row.get(10)?,
-                    executed_at: row
-                        .get::<_, Option<String>>(11)?
-                        .and_then(|s| s.parse::<DateTime<Utc>>().ok()),
-                    error: row.get(12)?,
-                    step_order: row.get(13)?,
-                })
-            })
+            .query_row([id], step_from_row)
             .optional()?;
 
         Ok(step)
@@ -206,31 +455,12 @@ impl PlansDatabase for crate::db::SqliteDatabase {
         let mut stmt = conn.prepare(
             "SELECT id, plan_id, file_id, action, source_path, source_drive_id,
                     dest_path, dest_drive_id, status, pre_hash, post_hash,
-                    executed_at, error, step_order
+                    executed_at, error, step_order, hash_algorithm
              FROM migration_steps WHERE plan_id = ?1 ORDER BY step_order",
         )?;
 
         let steps = stmt
-            .query_map([plan_id], |row| {
-                Ok(MigrationStep {
-                    id: row.get(0)?,
-                    plan_id: row.get(1)?,
-                    file_id: row.get(2)?,
-                    action: StepAction::from_str(&row.get::<_, String>(3)?).unwrap(),
-                    source_path: row.get(4)?,
-                    source_drive_id: row.get(5)?,
-                    dest_path: row.get(6)?,
-                    dest_drive_id: row.get(7)?,
-                    status: StepStatus::from_str(&row.get::<_, String>(8)?).unwrap(),
-                    pre_hash: row.get(9)?,
-                    post_hash: row.get(10)?,
-                    executed_at: row
-                        .get::<_, Option<String>>(11)?
-                        .and_then(|s| s.parse::<DateTime<Utc>>().ok()),
-                    error: row.get(12)?,
-                    step_order: row.get(13)?,
-                })
-            })?
+            .query_map([plan_id], step_from_row)?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
         Ok(steps)
@@ -255,11 +485,13 @@ impl PlansDatabase for crate::db::SqliteDatabase {
         id: i64,
         pre_hash: String,
         post_hash: Option<String>,
+        algorithm: HashAlgorithm,
     ) -> Result<()> {
         let conn = self.conn_mut();
         conn.execute(
-            "UPDATE migration_steps SET pre_hash = ?1, post_hash = ?2 WHERE id = ?3",
-            (pre_hash, post_hash, id),
+            "UPDATE migration_steps SET pre_hash = ?1, post_hash = ?2, hash_algorithm = ?3
+             WHERE id = ?4",
+            (pre_hash, post_hash, algorithm.as_str(), id),
         )?;
         Ok(())
     }
@@ -278,35 +510,399 @@ impl PlansDatabase for crate::db::SqliteDatabase {
         let mut stmt = conn.prepare(
             "SELECT id, plan_id, file_id, action, source_path, source_drive_id,
                     dest_path, dest_drive_id, status, pre_hash, post_hash,
-                    executed_at, error, step_order
+                    executed_at, error, step_order, hash_algorithm
              FROM migration_steps WHERE plan_id = ?1 AND status = 'pending'
              ORDER BY step_order",
         )?;
 
         let steps = stmt
+            .query_map([plan_id], step_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(steps)
+    }
+
+    fn add_step_dependency(&mut self, step_id: i64, depends_on: i64) -> Result<()> {
+        let conn = self.conn_mut();
+        conn.execute(
+            "INSERT OR IGNORE INTO step_dependencies (step_id, depends_on) VALUES (?1, ?2)",
+            (step_id, depends_on),
+        )?;
+        Ok(())
+    }
+
+    fn get_step_dependencies(&self, step_id: i64) -> Result<Vec<i64>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT depends_on FROM step_dependencies WHERE step_id = ?1 ORDER BY depends_on",
+        )?;
+
+        let deps = stmt
+            .query_map([step_id], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(deps)
+    }
+
+    fn get_plan_dependencies(&self, plan_id: i64) -> Result<Vec<(i64, i64)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT d.step_id, d.depends_on
+             FROM step_dependencies d
+             JOIN migration_steps s ON s.id = d.step_id
+             WHERE s.plan_id = ?1
+             ORDER BY d.step_id, d.depends_on",
+        )?;
+
+        let deps = stmt
+            .query_map([plan_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(deps)
+    }
+
+    fn get_plan_delete_bytes(&self, plan_id: i64) -> Result<i64> {
+        let bytes = self.conn().query_row(
+            "SELECT COALESCE(SUM(f.size_bytes), 0)
+             FROM migration_steps s
+             JOIN files f ON f.id = s.file_id
+             WHERE s.plan_id = ?1 AND s.action = 'delete'",
+            [plan_id],
+            |row| row.get(0),
+        )?;
+        Ok(bytes)
+    }
+
+    fn get_delete_totals_since(&self, since: DateTime<Utc>, created_by: Option<&str>) -> Result<(i64, i64)> {
+        let totals = self.conn().query_row(
+            "SELECT COUNT(*), COALESCE(SUM(f.size_bytes), 0)
+             FROM migration_steps s
+             JOIN migration_plans p ON p.id = s.plan_id
+             JOIN files f ON f.id = s.file_id
+             WHERE s.action = 'delete' AND p.status != 'aborted' AND p.created_at >= ?1
+               AND (?2 IS NULL OR p.created_by = ?2)",
+            rusqlite::params![format_timestamp(&since), created_by],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(totals)
+    }
+
+    fn get_steps_page(&self, plan_id: i64, query: &StepQuery) -> Result<StepPage> {
+        let conn = self.conn();
+        let filter = format!(
+            "s.plan_id = ?1 AND (?2 IS NULL OR {} = rtrim(?2, '/'))",
+            STEP_DIRECTORY_SQL
+        );
+
+        let total = conn.query_row(
+            &format!("SELECT COUNT(*) FROM migration_steps s WHERE {}", filter),
+            rusqlite::params![plan_id, &query.directory],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT s.id, s.plan_id, s.file_id, s.action, s.source_path, s.source_drive_id,
+                    s.dest_path, s.dest_drive_id, s.status, s.pre_hash, s.post_hash,
+                    s.executed_at, s.error, s.step_order, s.hash_algorithm
+             FROM migration_steps s WHERE {}
+             ORDER BY s.step_order LIMIT ?3 OFFSET ?4",
+            filter
+        ))?;
+        let steps = stmt
+            .query_map(
+                rusqlite::params![plan_id, &query.directory, query.limit, query.offset],
+                step_from_row,
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(StepPage {
+            steps,
+            total,
+            offset: query.offset,
+        })
+    }
+
+    fn get_step_directories(
+        &self,
+        plan_id: i64,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<StepDirectory>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {dir} AS directory, COUNT(*), COALESCE(SUM(f.size_bytes), 0)
+             FROM migration_steps s
+             LEFT JOIN files f ON f.id = s.file_id
+             WHERE s.plan_id = ?1
+             GROUP BY directory
+             ORDER BY COUNT(*) DESC, directory
+             LIMIT ?2 OFFSET ?3",
+            dir = STEP_DIRECTORY_SQL
+        ))?;
+
+        let directories = stmt
+            .query_map(rusqlite::params![plan_id, limit, offset], |row| {
+                Ok(StepDirectory {
+                    directory: row.get(0)?,
+                    step_count: row.get(1)?,
+                    total_bytes: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(directories)
+    }
+
+    fn get_plan_summary(&self, plan_id: i64) -> Result<PlanSummary> {
+        let (satisfied_files, satisfied_bytes) = self.conn().query_row(
+            "SELECT COUNT(*), COALESCE(SUM(f.size_bytes), 0)
+             FROM plan_satisfied_files p JOIN files f ON f.id = p.file_id
+             WHERE p.plan_id = ?1",
+            [plan_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let summarize = |key: &str| -> Result<Vec<SummaryRow>> {
+            let mut stmt = self.conn().prepare(&format!(
+                "SELECT {} AS key, COUNT(*), COALESCE(SUM(f.size_bytes), 0)
+                 FROM migration_steps s
+                 LEFT JOIN files f ON f.id = s.file_id
+                 LEFT JOIN drives src ON src.id = s.source_drive_id
+                 LEFT JOIN drives dst ON dst.id = s.dest_drive_id
+                 WHERE s.plan_id = ?1
+                 GROUP BY key
+                 ORDER BY COALESCE(SUM(f.size_bytes), 0) DESC, key",
+                key
+            ))?;
+            let rows = stmt
+                .query_map([plan_id], |row| {
+                    Ok(SummaryRow {
+                        key: row.get(0)?,
+                        step_count: row.get(1)?,
+                        total_bytes: row.get(2)?,
+                    })
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(rows)
+        };
+
+        Ok(PlanSummary {
+            by_action: summarize("s.action")?,
+            by_directory: summarize(
+                "COALESCE(src.label, '?') || ':' ||
+                 CASE WHEN f.path IS NULL THEN '?'
+                      WHEN instr(f.path, '/') = 0 THEN '.'
+                      ELSE substr(f.path, 1, instr(f.path, '/') - 1)
+                 END",
+            )?,
+            by_destination: summarize("COALESCE(dst.label, '-')")?,
+            satisfied_files,
+            satisfied_bytes,
+        })
+    }
+
+    fn reset_interrupted_steps(&mut self, plan_id: i64) -> Result<usize> {
+        let rows = self.conn_mut().execute(
+            "UPDATE migration_steps SET status = 'pending'
+             WHERE plan_id = ?1 AND status IN ('in_progress', 'volatile')",
+            [plan_id],
+        )?;
+        Ok(rows)
+    }
+
+    fn start_plan_session(&mut self, plan_id: i64, owner: &SessionOwner) -> Result<i64> {
+        let conn = self.conn_mut();
+        conn.execute(
+            "INSERT INTO plan_sessions (plan_id, owner_pid, owner_host) VALUES (?1, ?2, ?3)",
+            (plan_id, owner.pid, &owner.host),
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn update_plan_session(
+        &mut self,
+        id: i64,
+        files_completed: i64,
+        bytes_completed: i64,
+        last_step_id: i64,
+    ) -> Result<()> {
+        self.conn_mut().execute(
+            "UPDATE plan_sessions SET files_completed = ?1, bytes_completed = ?2, last_step_id = ?3
+             WHERE id = ?4",
+            (files_completed, bytes_completed, last_step_id, id),
+        )?;
+        Ok(())
+    }
+
+    fn end_plan_session(&mut self, id: i64, reason: &str) -> Result<()> {
+        self.conn_mut().execute(
+            "UPDATE plan_sessions SET ended_at = datetime('now'), end_reason = ?1 WHERE id = ?2",
+            (reason, id),
+        )?;
+        Ok(())
+    }
+
+    fn list_plan_sessions(&self, plan_id: i64) -> Result<Vec<PlanSession>> {
+        let mut stmt = self.conn().prepare(
+            "SELECT id, plan_id, started_at, ended_at, files_completed, bytes_completed,
+                    last_step_id, end_reason, owner_pid, owner_host
+             FROM plan_sessions WHERE plan_id = ?1 ORDER BY id",
+        )?;
+
+        let sessions = stmt
             .query_map([plan_id], |row| {
-                Ok(MigrationStep {
+                Ok(PlanSession {
                     id: row.get(0)?,
                     plan_id: row.get(1)?,
-                    file_id: row.get(2)?,
-                    action: StepAction::from_str(&row.get::<_, String>(3)?).unwrap(),
-                    source_path: row.get(4)?,
-                    source_drive_id: row.get(5)?,
-                    dest_path: row.get(6)?,
-                    dest_drive_id: row.get(7)?,
-                    status: StepStatus::from_str(&row.get::<_, String>(8)?).unwrap(),
-                    pre_hash: row.get(9)?,
-                    post_hash: row.get(10)?,
-                    executed_at: row
-                        .get::<_, Option<String>>(11)?
-                        .and_then(|s| s.parse::<DateTime<Utc>>().ok()),
+                    started_at: parse_timestamp(&row.get::<_, String>(2)?).unwrap_or_else(Utc::now),
+                    ended_at: row.get::<_, Option<String>>(3)?.and_then(|s| parse_timestamp(&s)),
+                    files_completed: row.get(4)?,
+                    bytes_completed: row.get(5)?,
+                    last_step_id: row.get(6)?,
+                    end_reason: row.get(7)?,
+                    owner: match (row.get(8)?, row.get(9)?) {
+                        (Some(pid), Some(host)) => Some(SessionOwner { pid, host }),
+                        _ => None,
+                    },
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(sessions)
+    }
+
+    fn start_step_attempt(&mut self, id: i64) -> Result<()> {
+        self.conn_mut().execute(
+            "UPDATE migration_steps
+             SET status = 'in_progress', error = NULL, started_at = ?1, finished_at = NULL,
+                 attempts = COALESCE(attempts, 0) + 1
+             WHERE id = ?2",
+            (Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true), id),
+        )?;
+        Ok(())
+    }
+
+    fn finish_step_attempt(&mut self, id: i64, bytes: Option<i64>) -> Result<()> {
+        self.conn_mut().execute(
+            "UPDATE migration_steps SET finished_at = ?1, bytes_transferred = ?2 WHERE id = ?3",
+            (Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true), bytes, id),
+        )?;
+        Ok(())
+    }
+
+    fn get_step_log(&self, plan_id: i64) -> Result<Vec<StepLogEntry>> {
+        let mut stmt = self.conn().prepare(
+            "SELECT id, step_order, action, source_path, dest_path, status, started_at,
+                    finished_at, bytes_transferred, COALESCE(attempts, 0), pre_hash, post_hash, error,
+                    hash_algorithm
+             FROM migration_steps WHERE plan_id = ?1 ORDER BY step_order, id",
+        )?;
+
+        let entries = stmt
+            .query_map([plan_id], |row| {
+                let timestamp = |i: usize| -> rusqlite::Result<Option<DateTime<Utc>>> {
+                    Ok(row
+                        .get::<_, Option<String>>(i)?
+                        .and_then(|s| s.parse::<DateTime<Utc>>().ok()))
+                };
+                let started_at = timestamp(6)?;
+                let finished_at = timestamp(7)?;
+                let pre_hash: Option<String> = row.get(10)?;
+                let post_hash: Option<String> = row.get(11)?;
+                Ok(StepLogEntry {
+                    step_id: row.get(0)?,
+                    step_order: row.get(1)?,
+                    action: StepAction::from_str(&row.get::<_, String>(2)?).unwrap(),
+                    source_path: row.get(3)?,
+                    dest_path: row.get(4)?,
+                    status: StepStatus::from_str(&row.get::<_, String>(5)?).unwrap(),
+                    duration_ms: started_at
+                        .zip(finished_at)
+                        .map(|(start, end)| (end - start).num_milliseconds()),
+                    started_at,
+                    finished_at,
+                    bytes: row.get(8)?,
+                    attempts: row.get(9)?,
+                    hash_verified: pre_hash
+                        .as_ref()
+                        .zip(post_hash.as_ref())
+                        .map(|(pre, post)| pre == post),
+                    pre_hash,
+                    post_hash,
+                    hash_algorithm: row
+                        .get::<_, Option<String>>(13)?
+                        .and_then(|s| HashAlgorithm::from_str(&s).ok()),
                     error: row.get(12)?,
-                    step_order: row.get(13)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
-        Ok(steps)
+        Ok(entries)
+    }
+
+    fn add_satisfied_file(&mut self, plan_id: i64, file_id: i64, step_id: i64) -> Result<()> {
+        self.conn_mut().execute(
+            "INSERT OR REPLACE INTO plan_satisfied_files (plan_id, file_id, step_id) VALUES (?1, ?2, ?3)",
+            (plan_id, file_id, step_id),
+        )?;
+        Ok(())
+    }
+
+    fn get_satisfied_files(&self, plan_id: i64) -> Result<Vec<SatisfiedFile>> {
+        let mut stmt = self.conn().prepare(
+            "SELECT p.file_id, p.step_id, f.path, f.size_bytes
+             FROM plan_satisfied_files p JOIN files f ON f.id = p.file_id
+             WHERE p.plan_id = ?1
+             ORDER BY f.path",
+        )?;
+        let files = stmt
+            .query_map([plan_id], |row| {
+                Ok(SatisfiedFile {
+                    file_id: row.get(0)?,
+                    step_id: row.get(1)?,
+                    path: row.get(2)?,
+                    size_bytes: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(files)
+    }
+
+    fn record_space_usage(
+        &mut self,
+        plan_id: i64,
+        drive_id: i64,
+        phase: SpacePhase,
+        used_bytes: i64,
+    ) -> Result<()> {
+        let sql = match phase {
+            SpacePhase::Before => {
+                "INSERT INTO plan_space_usage (plan_id, drive_id, used_before) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(plan_id, drive_id) DO UPDATE SET used_before = COALESCE(used_before, excluded.used_before)"
+            }
+            SpacePhase::After => {
+                "INSERT INTO plan_space_usage (plan_id, drive_id, used_after) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(plan_id, drive_id) DO UPDATE SET used_after = excluded.used_after"
+            }
+        };
+        self.conn_mut().execute(sql, (plan_id, drive_id, used_bytes))?;
+        Ok(())
+    }
+
+    fn get_space_usage(&self, plan_id: i64) -> Result<Vec<PlanSpaceUsage>> {
+        let mut stmt = self.conn().prepare(
+            "SELECT drive_id, used_before, used_after FROM plan_space_usage
+             WHERE plan_id = ?1 ORDER BY drive_id",
+        )?;
+        let usage = stmt
+            .query_map([plan_id], |row| {
+                Ok(PlanSpaceUsage {
+                    drive_id: row.get(0)?,
+                    used_before: row.get(1)?,
+                    used_after: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(usage)
     }
 }
 
@@ -346,6 +942,8 @@ mod tests {
             total_bytes: 1_000_000,
             completed_files: 0,
             completed_bytes: 0,
+            created_by: None,
+            step_ordering: Default::default(),
         };
 
         let id = db.create_plan(&plan).unwrap();
@@ -381,8 +979,8 @@ mod tests {
             rusqlite::params![2, "drive2"],
         ).unwrap();
         db.conn().execute(
-            "INSERT INTO files (id, drive_id, path, abs_path, filename, size_bytes, status, indexed_at)
-             VALUES (1, 1, '/test.txt', '/mnt/drive1/test.txt', 'test.txt', 1000, 'indexed', datetime('now'))",
+            "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+             VALUES (1, 1, 'test.txt', 'test.txt', 1000, 'indexed', datetime('now'))",
             [],
         ).unwrap();
 
@@ -397,6 +995,8 @@ mod tests {
             total_bytes: 1000,
             completed_files: 0,
             completed_bytes: 0,
+            created_by: None,
+            step_ordering: Default::default(),
         };
 
         let plan_id = db.create_plan(&plan).unwrap();
@@ -413,6 +1013,7 @@ mod tests {
             status: StepStatus::Pending,
             pre_hash: None,
             post_hash: None,
+            hash_algorithm: None,
             executed_at: None,
             error: None,
             step_order: 1,
@@ -432,4 +1033,144 @@ mod tests {
         let updated = db.get_step(step_id).unwrap().unwrap();
         assert_eq!(updated.status, StepStatus::Completed);
     }
+
+    #[test]
+    fn test_step_dependencies() {
+        let mut db = create_test_db();
+
+        db.conn().execute(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'drive1', 'source', 1, 'local')",
+            [],
+        ).unwrap();
+        db.conn().execute(
+            "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+             VALUES (1, 1, 'test.txt', 'test.txt', 1000, 'indexed', datetime('now'))",
+            [],
+        ).unwrap();
+
+        let plan = MigrationPlan {
+            id: 0,
+            created_at: Utc::now(),
+            description: None,
+            source_drive_id: Some(1),
+            target_drive_id: None,
+            status: PlanStatus::Draft,
+            total_files: 1,
+            total_bytes: 1000,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: None,
+            step_ordering: Default::default(),
+        };
+        let plan_id = db.create_plan(&plan).unwrap();
+
+        let mut step = MigrationStep {
+            id: 0,
+            plan_id,
+            file_id: 1,
+            action: StepAction::Copy,
+            source_path: "/mnt/drive1/test.txt".to_string(),
+            source_drive_id: 1,
+            dest_path: Some("/mnt/drive2/test.txt".to_string()),
+            dest_drive_id: None,
+            status: StepStatus::Pending,
+            pre_hash: Some("abc".to_string()),
+            post_hash: None,
+            hash_algorithm: None,
+            executed_at: None,
+            error: None,
+            step_order: 0,
+        };
+        let copy_id = db.add_step(&step).unwrap();
+        step.action = StepAction::Delete;
+        step.step_order = 1;
+        let delete_id = db.add_step(&step).unwrap();
+
+        db.add_step_dependency(delete_id, copy_id).unwrap();
+        db.add_step_dependency(delete_id, copy_id).unwrap();
+
+        assert_eq!(db.get_step_dependencies(delete_id).unwrap(), vec![copy_id]);
+        assert!(db.get_step_dependencies(copy_id).unwrap().is_empty());
+        assert_eq!(db.get_plan_dependencies(plan_id).unwrap(), vec![(delete_id, copy_id)]);
+        assert_eq!(db.get_step(copy_id).unwrap().unwrap().pre_hash.as_deref(), Some("abc"));
+        assert_eq!(db.get_plan_delete_bytes(plan_id).unwrap(), 1000);
+    }
+
+    #[test]
+    fn test_steps_page_and_directories() {
+        let mut db = create_test_db();
+
+        db.conn().execute(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'drive1', 'source', 1, 'local')",
+            [],
+        ).unwrap();
+
+        let plan_id = db.create_plan(&MigrationPlan {
+            id: 0,
+            created_at: Utc::now(),
+            description: None,
+            source_drive_id: Some(1),
+            target_drive_id: None,
+            status: PlanStatus::Draft,
+            total_files: 5,
+            total_bytes: 0,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: None,
+            step_ordering: Default::default(),
+        }).unwrap();
+
+        let paths = ["/mnt/a/x/1", "/mnt/a/x/2", "/mnt/a/x/3", "/mnt/a/y/1", "/mnt/a/y/2"];
+        for (i, path) in paths.iter().enumerate() {
+            db.conn().execute(
+                "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+                 VALUES (?1, 1, ?2, ?2, 10, 'indexed', datetime('now'))",
+                rusqlite::params![i as i64, path.trim_start_matches("/mnt/a/")],
+            ).unwrap();
+            db.add_step(&MigrationStep {
+                id: 0,
+                plan_id,
+                file_id: i as i64,
+                action: StepAction::Delete,
+                source_path: path.to_string(),
+                source_drive_id: 1,
+                dest_path: None,
+                dest_drive_id: None,
+                status: StepStatus::Pending,
+                pre_hash: None,
+                post_hash: None,
+                hash_algorithm: None,
+                executed_at: None,
+                error: None,
+                step_order: i as i32,
+            }).unwrap();
+        }
+
+        let page = db.get_steps_page(plan_id, &StepQuery { offset: 1, limit: 2, directory: None }).unwrap();
+        assert_eq!(page.total, 5);
+        assert_eq!(page.steps.len(), 2);
+        assert_eq!(page.steps[0].source_path, "/mnt/a/x/2");
+        assert!(page.has_more());
+
+        let page = db.get_steps_page(plan_id, &StepQuery {
+            offset: 0,
+            limit: 50,
+            directory: Some("/mnt/a/y/".to_string()),
+        }).unwrap();
+        assert_eq!(page.total, 2);
+        assert!(!page.has_more());
+
+        let dirs = db.get_step_directories(plan_id, 0, 10).unwrap();
+        assert_eq!(dirs.len(), 2);
+        assert_eq!(dirs[0].directory, "/mnt/a/x");
+        assert_eq!((dirs[0].step_count, dirs[0].total_bytes), (3, 30));
+
+        let summary = db.get_plan_summary(plan_id).unwrap();
+        assert_eq!(summary.by_action.len(), 1);
+        assert_eq!(summary.by_action[0].key, "delete");
+        assert_eq!((summary.by_action[0].step_count, summary.by_action[0].total_bytes), (5, 50));
+        let dirs: Vec<_> = summary.by_directory.iter().map(|r| (r.key.as_str(), r.total_bytes)).collect();
+        assert_eq!(dirs, vec![("drive1:x", 30), ("drive1:y", 20)]);
+        assert_eq!(summary.by_destination[0].key, "-");
+    }
 }
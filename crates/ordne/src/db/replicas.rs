@@ -0,0 +1,132 @@
+//! Checking categories against their minimum number of drives.
+//!
+//! Content counts as on a drive when any catalogued copy there has the same
+//! hash, whatever that copy is classified as. Unhashed files cannot be
+//! matched to their copies and are counted separately.
+
+use crate::error::Result;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Content in a category that is on fewer drives than required.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicaShortfall {
+    pub hash: String,
+    /// One path the content is catalogued under, relative to its drive.
+    pub path: String,
+    pub size_bytes: i64,
+    /// Labels of the drives holding a copy.
+    pub drives: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicaReport {
+    pub category: String,
+    pub required_drives: usize,
+    /// Distinct hashed contents in the category.
+    pub contents: i64,
+    pub shortfalls: Vec<ReplicaShortfall>,
+    pub unhashed_files: i64,
+}
+
+/// Check every category in `requirements`, sorted by category.
+pub fn check_replicas(
+    conn: &Connection,
+    requirements: &HashMap<String, usize>,
+) -> Result<Vec<ReplicaReport>> {
+    let mut categories: Vec<_> = requirements.iter().collect();
+    categories.sort();
+
+    categories
+        .into_iter()
+        .map(|(category, &required)| check_category(conn, category, required))
+        .collect()
+}
+
+fn check_category(conn: &Connection, category: &str, required: usize) -> Result<ReplicaReport> {
+    let (contents, unhashed_files): (i64, i64) = conn.query_row(
+        "SELECT COUNT(DISTINCT COALESCE(blake3_hash, md5_hash)),
+                COUNT(*) - COUNT(COALESCE(blake3_hash, md5_hash))
+         FROM files WHERE category = ?1 AND status != 'source_removed'",
+        [category],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "WITH content AS (
+             SELECT COALESCE(blake3_hash, md5_hash) AS hash, MIN(path) AS path, MAX(size_bytes) AS size
+             FROM files
+             WHERE category = ?1 AND status != 'source_removed'
+               AND COALESCE(blake3_hash, md5_hash) IS NOT NULL
+             GROUP BY hash
+         )
+         SELECT c.hash, c.path, c.size, group_concat(DISTINCT d.label)
+         FROM content c
+         JOIN files f ON (f.blake3_hash = c.hash OR f.md5_hash = c.hash)
+                     AND f.status != 'source_removed'
+         JOIN drives d ON d.id = f.drive_id
+         GROUP BY c.hash
+         HAVING COUNT(DISTINCT f.drive_id) < ?2
+         ORDER BY c.path",
+    )?;
+
+    let shortfalls = stmt
+        .query_map((category, required as i64), |row| {
+            let drives: String = row.get(3)?;
+            let mut drives: Vec<String> = drives.split(',').map(str::to_string).collect();
+            drives.sort();
+            Ok(ReplicaShortfall {
+                hash: row.get(0)?,
+                path: row.get(1)?,
+                size_bytes: row.get(2)?,
+                drives,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(ReplicaReport {
+        category: category.to_string(),
+        required_drives: required,
+        contents,
+        shortfalls,
+        unhashed_files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Database, SqliteDatabase};
+
+    #[test]
+    fn test_check_replicas_counts_drives_per_content() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES
+                    (1, 'nas', 'source', 1, 'local'), (2, 'backup', 'backup', 1, 'local');
+                 INSERT INTO files (drive_id, path, filename, size_bytes, blake3_hash, category, status) VALUES
+                    (1, 'a.jpg', 'a.jpg', 10, 'aaa', 'photos', 'classified'),
+                    (2, 'old/a.jpg', 'a.jpg', 10, 'aaa', NULL, 'indexed'),
+                    (1, 'b.jpg', 'b.jpg', 20, 'bbb', 'photos', 'classified'),
+                    (1, 'copy/b.jpg', 'b.jpg', 20, 'bbb', 'photos', 'classified'),
+                    (2, 'c.jpg', 'c.jpg', 30, 'ccc', 'photos', 'source_removed'),
+                    (1, 'd.jpg', 'd.jpg', 40, NULL, 'photos', 'classified');",
+            )
+            .unwrap();
+
+        let requirements = HashMap::from([("photos".to_string(), 2)]);
+        let reports = check_replicas(db.conn(), &requirements).unwrap();
+        assert_eq!(reports.len(), 1);
+
+        let report = &reports[0];
+        assert_eq!(report.contents, 2);
+        assert_eq!(report.unhashed_files, 1);
+        // a.jpg is also on the backup drive; b.jpg's two copies share a drive.
+        assert_eq!(report.shortfalls.len(), 1);
+        assert_eq!(report.shortfalls[0].path, "b.jpg");
+        assert_eq!(report.shortfalls[0].drives, vec!["nas"]);
+    }
+}
@@ -0,0 +1,118 @@
+//! Paths a scan could not read, kept until a later scan of the same
+//! directory gets through them.
+
+use crate::db::verification::parse_timestamp;
+use crate::error::Result;
+use crate::index::ScanFailure;
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanError {
+    pub id: i64,
+    pub drive_id: i64,
+    /// Relative to the drive root.
+    pub path: String,
+    /// `permission_denied`, `not_found`, `io` or `loop`.
+    pub kind: String,
+    pub message: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Replace the errors recorded under `scope` (a directory relative to the
+/// drive root, empty for the whole drive) with those from a new scan of it.
+pub fn replace_scan_errors(
+    conn: &Connection,
+    drive_id: i64,
+    scope: &str,
+    failures: &[ScanFailure],
+) -> Result<()> {
+    let scope = scope.trim_matches('/');
+    conn.execute(
+        "DELETE FROM scan_errors
+         WHERE drive_id = ?1
+           AND (?2 = '' OR path = ?2 OR substr(path, 1, length(?2) + 1) = ?2 || '/')",
+        (drive_id, scope),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "INSERT INTO scan_errors (drive_id, path, kind, message) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(drive_id, path) DO UPDATE SET
+            kind = excluded.kind,
+            message = excluded.message,
+            detected_at = datetime('now')",
+    )?;
+    for failure in failures {
+        stmt.execute((drive_id, &failure.path, &failure.kind, &failure.message))?;
+    }
+    Ok(())
+}
+
+pub fn list_scan_errors(conn: &Connection, drive_id: i64) -> Result<Vec<ScanError>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, drive_id, path, kind, message, detected_at
+         FROM scan_errors WHERE drive_id = ?1 ORDER BY path",
+    )?;
+
+    let errors = stmt
+        .query_map([drive_id], |row| {
+            Ok(ScanError {
+                id: row.get(0)?,
+                drive_id: row.get(1)?,
+                path: row.get(2)?,
+                kind: row.get(3)?,
+                message: row.get(4)?,
+                detected_at: parse_timestamp(&row.get::<_, String>(5)?).unwrap_or_else(Utc::now),
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Database, SqliteDatabase};
+
+    fn failure(path: &str) -> ScanFailure {
+        ScanFailure {
+            path: path.to_string(),
+            kind: "permission_denied".to_string(),
+            message: "Permission denied (os error 13)".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rescan_replaces_errors_in_scope() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute("INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'nas', 'source', 1, 'local')", [])
+            .unwrap();
+
+        replace_scan_errors(
+            db.conn(),
+            1,
+            "",
+            &[failure("photos/private"), failure("photos2/x"), failure("music/locked")],
+        )
+        .unwrap();
+        assert_eq!(list_scan_errors(db.conn(), 1).unwrap().len(), 3);
+
+        // Rescanning photos/ clears what it got through, and nothing outside it.
+        replace_scan_errors(db.conn(), 1, "photos/", &[]).unwrap();
+        let paths: Vec<_> = list_scan_errors(db.conn(), 1)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(paths, vec!["music/locked", "photos2/x"]);
+
+        replace_scan_errors(db.conn(), 1, "", &[failure("music/locked")]).unwrap();
+        let errors = list_scan_errors(db.conn(), 1).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, "permission_denied");
+    }
+}
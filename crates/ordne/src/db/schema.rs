@@ -1,7 +1,7 @@
-use crate::error::Result;
+use crate::error::{OrdneError, Result};
 use rusqlite::Connection;
 
-pub const SCHEMA_VERSION: i32 = 1;
+pub const SCHEMA_VERSION: i32 = 6;
 
 pub fn initialize_schema(conn: &Connection) -> Result<()> {
     conn.execute_batch(
@@ -22,6 +22,8 @@ pub fn initialize_schema(conn: &Connection) -> Result<()> {
             role            TEXT DEFAULT 'source',
             is_online       BOOLEAN DEFAULT 1,
             is_readonly     BOOLEAN DEFAULT 0,
+            is_cold         BOOLEAN DEFAULT 0,
+            is_encrypted    BOOLEAN DEFAULT 0,
             backend         TEXT DEFAULT 'local',
             rclone_remote   TEXT,
             scanned_at      TEXT,
@@ -32,7 +34,6 @@ pub fn initialize_schema(conn: &Connection) -> Result<()> {
             id              INTEGER PRIMARY KEY,
             drive_id        INTEGER NOT NULL REFERENCES drives(id),
             path            TEXT NOT NULL,
-            abs_path        TEXT NOT NULL,
             filename        TEXT NOT NULL,
             extension       TEXT,
             size_bytes      INTEGER NOT NULL,
@@ -40,9 +41,11 @@ pub fn initialize_schema(conn: &Connection) -> Result<()> {
             blake3_hash     TEXT,
             created_at      TEXT,
             modified_at     TEXT,
+            accessed_at     TEXT,
             inode           INTEGER,
             device_num      INTEGER,
             nlinks          INTEGER,
+            owner_uid       INTEGER,
             mime_type       TEXT,
             is_symlink      BOOLEAN DEFAULT 0,
             symlink_target  TEXT,
@@ -86,7 +89,9 @@ pub fn initialize_schema(conn: &Connection) -> Result<()> {
             total_files     INTEGER,
             total_bytes     INTEGER,
             completed_files INTEGER DEFAULT 0,
-            completed_bytes INTEGER DEFAULT 0
+            completed_bytes INTEGER DEFAULT 0,
+            created_by      TEXT,
+            step_ordering   TEXT DEFAULT 'planned'
         );
 
         CREATE TABLE IF NOT EXISTS migration_steps (
@@ -103,7 +108,18 @@ pub fn initialize_schema(conn: &Connection) -> Result<()> {
             post_hash       TEXT,
             executed_at     TEXT,
             error           TEXT,
-            step_order      INTEGER
+            step_order      INTEGER,
+            started_at      TEXT,
+            finished_at     TEXT,
+            bytes_transferred INTEGER,
+            attempts        INTEGER DEFAULT 0,
+            hash_algorithm  TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS step_dependencies (
+            step_id         INTEGER NOT NULL REFERENCES migration_steps(id),
+            depends_on      INTEGER NOT NULL REFERENCES migration_steps(id),
+            PRIMARY KEY (step_id, depends_on)
         );
 
         CREATE TABLE IF NOT EXISTS audit_log (
@@ -114,17 +130,251 @@ pub fn initialize_schema(conn: &Connection) -> Result<()> {
             plan_id         INTEGER,
             drive_id        INTEGER,
             details         TEXT,
-            agent_mode      TEXT
+            agent_mode      TEXT,
+            before_state    TEXT,
+            after_state     TEXT,
+            actor           TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS classification_suggestions (
+            id              INTEGER PRIMARY KEY,
+            file_id         INTEGER NOT NULL REFERENCES files(id),
+            category        TEXT NOT NULL,
+            subcategory     TEXT,
+            priority        TEXT,
+            confidence      REAL NOT NULL,
+            reason          TEXT,
+            suggested_by    TEXT,
+            status          TEXT DEFAULT 'pending',
+            created_at      TEXT DEFAULT (datetime('now')),
+            reviewed_at     TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS verification_failures (
+            id              INTEGER PRIMARY KEY,
+            file_id         INTEGER,
+            plan_id         INTEGER,
+            step_id         INTEGER,
+            drive_id        INTEGER,
+            path            TEXT NOT NULL,
+            expected_hash   TEXT,
+            actual_hash     TEXT,
+            reason          TEXT NOT NULL,
+            detected_at     TEXT DEFAULT (datetime('now')),
+            acknowledged_at TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS verification_jobs (
+            id              INTEGER PRIMARY KEY,
+            plan_id         INTEGER NOT NULL REFERENCES migration_plans(id),
+            due_at          TEXT NOT NULL,
+            status          TEXT NOT NULL DEFAULT 'pending',
+            files_checked   INTEGER NOT NULL DEFAULT 0,
+            files_failed    INTEGER NOT NULL DEFAULT 0,
+            created_at      TEXT DEFAULT (datetime('now')),
+            completed_at    TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS plan_sessions (
+            id              INTEGER PRIMARY KEY,
+            plan_id         INTEGER NOT NULL REFERENCES migration_plans(id),
+            started_at      TEXT DEFAULT (datetime('now')),
+            ended_at        TEXT,
+            files_completed INTEGER NOT NULL DEFAULT 0,
+            bytes_completed INTEGER NOT NULL DEFAULT 0,
+            last_step_id    INTEGER,
+            end_reason      TEXT,
+            owner_pid       INTEGER,
+            owner_host      TEXT
+        );
+
+        -- Files a migration leaves out because the step that copies an
+        -- identical file (same content hash) covers them.
+        CREATE TABLE IF NOT EXISTS plan_satisfied_files (
+            plan_id         INTEGER NOT NULL REFERENCES migration_plans(id),
+            file_id         INTEGER NOT NULL REFERENCES files(id),
+            step_id         INTEGER NOT NULL REFERENCES migration_steps(id),
+            PRIMARY KEY (plan_id, file_id)
+        );
+
+        -- Filesystem usage of each drive a plan touched, before and after it ran.
+        CREATE TABLE IF NOT EXISTS plan_space_usage (
+            plan_id         INTEGER NOT NULL REFERENCES migration_plans(id),
+            drive_id        INTEGER NOT NULL REFERENCES drives(id),
+            used_before     INTEGER,
+            used_after      INTEGER,
+            PRIMARY KEY (plan_id, drive_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS jobs (
+            id              INTEGER PRIMARY KEY,
+            command         TEXT NOT NULL,
+            started_at      TEXT NOT NULL,
+            elapsed_ms      INTEGER NOT NULL DEFAULT 0,
+            rows_touched    INTEGER NOT NULL DEFAULT 0,
+            bytes_processed INTEGER NOT NULL DEFAULT 0,
+            db_size_delta   INTEGER NOT NULL DEFAULT 0,
+            status          TEXT NOT NULL,
+            error           TEXT,
+            agent_mode      TEXT,
+            actor           TEXT,
+            parameters      TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS scan_errors (
+            id          INTEGER PRIMARY KEY,
+            drive_id    INTEGER NOT NULL REFERENCES drives(id),
+            path        TEXT NOT NULL,
+            kind        TEXT NOT NULL,
+            message     TEXT NOT NULL,
+            detected_at TEXT DEFAULT (datetime('now')),
+            UNIQUE(drive_id, path)
+        );
+
+        CREATE TABLE IF NOT EXISTS owners (
+            uid         INTEGER PRIMARY KEY,
+            name        TEXT NOT NULL,
+            updated_at  TEXT DEFAULT (datetime('now'))
         );
         "#,
     )?;
 
+    conn.execute_batch("DROP VIEW IF EXISTS resolved_files")?;
+    upgrade_columns(conn)?;
+    migrate_relative_paths(conn)?;
+    create_views(conn)?;
     create_indexes(conn)?;
     set_schema_version(conn)?;
 
     Ok(())
 }
 
+/// Add columns introduced after a table was first created, so databases
+/// initialized by older versions pick them up.
+fn upgrade_columns(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "audit_log", "before_state", "TEXT")?;
+    add_column_if_missing(conn, "audit_log", "after_state", "TEXT")?;
+    add_column_if_missing(conn, "audit_log", "actor", "TEXT")?;
+    add_column_if_missing(conn, "migration_plans", "created_by", "TEXT")?;
+    add_column_if_missing(conn, "migration_plans", "step_ordering", "TEXT DEFAULT 'planned'")?;
+    add_column_if_missing(conn, "files", "accessed_at", "TEXT")?;
+    add_column_if_missing(conn, "files", "owner_uid", "INTEGER")?;
+    add_column_if_missing(conn, "drives", "is_cold", "BOOLEAN DEFAULT 0")?;
+    add_column_if_missing(conn, "drives", "is_encrypted", "BOOLEAN DEFAULT 0")?;
+    add_column_if_missing(conn, "migration_steps", "started_at", "TEXT")?;
+    add_column_if_missing(conn, "migration_steps", "finished_at", "TEXT")?;
+    add_column_if_missing(conn, "migration_steps", "bytes_transferred", "INTEGER")?;
+    add_column_if_missing(conn, "migration_steps", "attempts", "INTEGER DEFAULT 0")?;
+    add_column_if_missing(conn, "jobs", "agent_mode", "TEXT")?;
+    add_column_if_missing(conn, "jobs", "actor", "TEXT")?;
+    add_column_if_missing(conn, "jobs", "parameters", "TEXT")?;
+    add_column_if_missing(conn, "plan_sessions", "owner_pid", "INTEGER")?;
+    add_column_if_missing(conn, "plan_sessions", "owner_host", "TEXT")?;
+    add_step_hash_algorithm(conn)?;
+    Ok(())
+}
+
+/// Steps hashed before the algorithm was recorded get it inferred from the
+/// digest length, the only way it was told apart back then.
+fn add_step_hash_algorithm(conn: &Connection) -> Result<()> {
+    if column_exists(conn, "migration_steps", "hash_algorithm")? {
+        return Ok(());
+    }
+
+    conn.execute_batch(
+        r#"
+        ALTER TABLE migration_steps ADD COLUMN hash_algorithm TEXT;
+        UPDATE migration_steps
+        SET hash_algorithm = CASE length(pre_hash) WHEN 64 THEN 'blake3' WHEN 32 THEN 'md5' END
+        WHERE pre_hash IS NOT NULL;
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Older databases stored each file's absolute path, which went stale
+/// whenever a drive was mounted somewhere else. Rebase `path` on the drive
+/// root (scans of a subdirectory stored it relative to that subdirectory)
+/// and drop the stored absolute path.
+///
+/// Rows that cannot be rebased, because the absolute path lies outside the
+/// drive's mount path or the rebased path is already taken on that drive,
+/// abort the migration and the database is left as it was.
+fn migrate_relative_paths(conn: &Connection) -> Result<()> {
+    if !column_exists(conn, "files", "abs_path")? {
+        return Ok(());
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE OR IGNORE files
+         SET path = substr(abs_path, length(rtrim(d.mount_path, '/')) + 2)
+         FROM drives d
+         WHERE d.id = files.drive_id
+           AND d.mount_path IS NOT NULL
+           AND substr(abs_path, 1, length(rtrim(d.mount_path, '/')) + 1) = rtrim(d.mount_path, '/') || '/'",
+        [],
+    )?;
+
+    let mut stmt = tx.prepare(
+        "SELECT f.abs_path FROM files f JOIN drives d ON d.id = f.drive_id
+         WHERE d.mount_path IS NOT NULL
+           AND f.abs_path IS NOT NULL
+           AND f.abs_path != rtrim(d.mount_path, '/') || '/' || f.path
+         ORDER BY f.id",
+    )?;
+    let stranded = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    drop(stmt);
+    if !stranded.is_empty() {
+        return Err(OrdneError::Migration(format!(
+            "{} files could not be rebased on their drive's mount path, e.g. {}. \
+             They are outside the mount path or collide with a file already indexed there. \
+             Fix drives.mount_path or remove the duplicate rows, then run ordne again",
+            stranded.len(),
+            stranded.iter().take(3).cloned().collect::<Vec<_>>().join(", ")
+        )));
+    }
+
+    tx.execute_batch("ALTER TABLE files DROP COLUMN abs_path")?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// `resolved_files` is `files` plus `abs_path`, derived from the drive's
+/// current mount path. Read files through it; write to `files`.
+fn create_views(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE VIEW resolved_files AS
+        SELECT f.*,
+               CASE WHEN d.mount_path IS NULL THEN f.path
+                    ELSE rtrim(d.mount_path, '/') || '/' || f.path
+               END AS abs_path
+        FROM files f LEFT JOIN drives d ON d.id = f.drive_id;
+        "#,
+    )?;
+    Ok(())
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<std::result::Result<Vec<_>, _>>()?
+        .iter()
+        .any(|name| name == column);
+    Ok(exists)
+}
+
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    if !column_exists(conn, table, column)? {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))?;
+    }
+    Ok(())
+}
+
 fn create_indexes(conn: &Connection) -> Result<()> {
     conn.execute_batch(
         r#"
@@ -136,11 +386,18 @@ fn create_indexes(conn: &Connection) -> Result<()> {
         CREATE INDEX IF NOT EXISTS idx_files_extension ON files(extension);
         CREATE INDEX IF NOT EXISTS idx_files_size ON files(size_bytes);
         CREATE INDEX IF NOT EXISTS idx_files_drive ON files(drive_id);
+        CREATE INDEX IF NOT EXISTS idx_files_owner ON files(owner_uid);
         CREATE INDEX IF NOT EXISTS idx_migration_steps_plan ON migration_steps(plan_id, step_order);
         CREATE INDEX IF NOT EXISTS idx_migration_steps_status ON migration_steps(status);
+        CREATE INDEX IF NOT EXISTS idx_step_dependencies_depends_on ON step_dependencies(depends_on);
         CREATE INDEX IF NOT EXISTS idx_duplicate_groups_hash ON duplicate_groups(hash);
         CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp);
         CREATE INDEX IF NOT EXISTS idx_audit_log_action ON audit_log(action);
+        CREATE INDEX IF NOT EXISTS idx_suggestions_status ON classification_suggestions(status, file_id);
+        CREATE INDEX IF NOT EXISTS idx_verification_failures_open ON verification_failures(acknowledged_at);
+        CREATE INDEX IF NOT EXISTS idx_verification_jobs_due ON verification_jobs(status, due_at);
+        CREATE INDEX IF NOT EXISTS idx_plan_sessions_plan ON plan_sessions(plan_id);
+        CREATE INDEX IF NOT EXISTS idx_scan_errors_drive ON scan_errors(drive_id);
         "#,
     )?;
     Ok(())
@@ -185,7 +442,7 @@ mod tests {
             )
             .unwrap();
 
-        assert_eq!(table_count, 7);
+        assert_eq!(table_count, 17);
     }
 
     #[test]
@@ -203,4 +460,115 @@ mod tests {
 
         assert!(index_count >= 12);
     }
+
+    #[test]
+    fn test_upgrade_adds_missing_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE audit_log (id INTEGER PRIMARY KEY, timestamp TEXT, action TEXT NOT NULL,
+             file_id INTEGER, plan_id INTEGER, drive_id INTEGER, details TEXT, agent_mode TEXT);",
+        )
+        .unwrap();
+
+        initialize_schema(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO audit_log (action, before_state, after_state) VALUES ('x', '{}', '{}')",
+            [],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_step_hash_algorithm_backfilled_from_digest_length() {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_schema(&conn).unwrap();
+
+        conn.execute_batch(&format!(
+            "ALTER TABLE migration_steps DROP COLUMN hash_algorithm;
+             INSERT INTO migration_steps (id, action, source_path, pre_hash)
+             VALUES (1, 'copy', '/a', '{}'), (2, 'copy', '/b', '{}'), (3, 'copy', '/c', NULL);",
+            "0".repeat(64),
+            "0".repeat(32)
+        ))
+        .unwrap();
+
+        initialize_schema(&conn).unwrap();
+
+        let algorithms: Vec<Option<String>> = conn
+            .prepare("SELECT hash_algorithm FROM migration_steps ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            algorithms,
+            vec![Some("blake3".to_string()), Some("md5".to_string()), None]
+        );
+    }
+
+    #[test]
+    fn test_abs_path_column_migrated_to_view() {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_schema(&conn).unwrap();
+
+        // Recreate the pre-migration layout: abs_path stored on the row and
+        // path relative to whatever directory was scanned.
+        conn.execute_batch(
+            "DROP VIEW resolved_files;
+             ALTER TABLE files ADD COLUMN abs_path TEXT;
+             INSERT INTO drives (id, label, role, mount_path) VALUES (1, 'nas', 'source', '/mnt/nas/');
+             INSERT INTO files (drive_id, path, abs_path, filename, size_bytes)
+             VALUES (1, 'a.jpg', '/mnt/nas/photos/a.jpg', 'a.jpg', 1);",
+        )
+        .unwrap();
+
+        initialize_schema(&conn).unwrap();
+
+        assert!(!column_exists(&conn, "files", "abs_path").unwrap());
+        let path: String = conn
+            .query_row("SELECT path FROM files", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(path, "photos/a.jpg");
+
+        conn.execute("UPDATE drives SET mount_path = '/media/nas' WHERE id = 1", [])
+            .unwrap();
+        let abs_path: String = conn
+            .query_row("SELECT abs_path FROM resolved_files", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(abs_path, "/media/nas/photos/a.jpg");
+    }
+
+    #[test]
+    fn test_abs_path_migration_aborts_on_colliding_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_schema(&conn).unwrap();
+
+        // The same file indexed by a scan of the drive root and one of a
+        // subdirectory: both rebase to 'photos/a.jpg'.
+        conn.execute_batch(
+            "DROP VIEW resolved_files;
+             ALTER TABLE files ADD COLUMN abs_path TEXT;
+             INSERT INTO drives (id, label, role, mount_path) VALUES (1, 'nas', 'source', '/mnt/nas');
+             INSERT INTO files (drive_id, path, abs_path, filename, size_bytes)
+             VALUES (1, 'photos/a.jpg', '/mnt/nas/photos/a.jpg', 'a.jpg', 1),
+                    (1, 'a.jpg', '/mnt/nas/photos/a.jpg', 'a.jpg', 1);",
+        )
+        .unwrap();
+
+        let err = initialize_schema(&conn).unwrap_err();
+        assert!(err.to_string().contains("1 files could not be rebased"));
+
+        // Rolled back: the absolute paths are still there to fix things up.
+        assert!(column_exists(&conn, "files", "abs_path").unwrap());
+        let paths: Vec<String> = conn
+            .prepare("SELECT path FROM files ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(paths, vec!["photos/a.jpg", "a.jpg"]);
+    }
 }
@@ -0,0 +1,265 @@
+use crate::db::{files, Priority};
+use crate::error::{OrdneError, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// A classification proposed by an agent. Suggestions are never applied
+/// directly; a human accepts or rejects them during review.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassificationSuggestion {
+    pub id: i64,
+    pub file_id: i64,
+    pub category: String,
+    pub subcategory: Option<String>,
+    pub priority: Option<Priority>,
+    /// Agent's confidence in the suggestion, from 0.0 to 1.0.
+    pub confidence: f64,
+    pub reason: Option<String>,
+    pub suggested_by: Option<String>,
+    pub status: SuggestionStatus,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SuggestionStatus {
+    Pending,
+    Accepted,
+    Rejected,
+    Superseded,
+}
+
+impl SuggestionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuggestionStatus::Pending => "pending",
+            SuggestionStatus::Accepted => "accepted",
+            SuggestionStatus::Rejected => "rejected",
+            SuggestionStatus::Superseded => "superseded",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(SuggestionStatus::Pending),
+            "accepted" => Ok(SuggestionStatus::Accepted),
+            "rejected" => Ok(SuggestionStatus::Rejected),
+            "superseded" => Ok(SuggestionStatus::Superseded),
+            _ => Err(OrdneError::Config(format!("Invalid suggestion status: {}", s))),
+        }
+    }
+}
+
+/// Store a pending suggestion. Any earlier pending suggestion for the same
+/// file is marked superseded so review only ever shows the latest one.
+pub fn add_suggestion(conn: &Connection, suggestion: &ClassificationSuggestion) -> Result<i64> {
+    if !(0.0..=1.0).contains(&suggestion.confidence) {
+        return Err(OrdneError::Config(format!(
+            "Confidence must be between 0.0 and 1.0, got {}",
+            suggestion.confidence
+        )));
+    }
+    if files::get_file(conn, suggestion.file_id)?.is_none() {
+        return Err(OrdneError::Config(format!(
+            "File ID {} not found",
+            suggestion.file_id
+        )));
+    }
+
+    conn.execute(
+        "UPDATE classification_suggestions SET status = ?1, reviewed_at = datetime('now')
+         WHERE file_id = ?2 AND status = 'pending'",
+        (SuggestionStatus::Superseded.as_str(), suggestion.file_id),
+    )?;
+
+    conn.execute(
+        "INSERT INTO classification_suggestions
+         (file_id, category, subcategory, priority, confidence, reason, suggested_by, status)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        (
+            suggestion.file_id,
+            &suggestion.category,
+            &suggestion.subcategory,
+            suggestion.priority.map(|p| p.as_str()),
+            suggestion.confidence,
+            &suggestion.reason,
+            &suggestion.suggested_by,
+            SuggestionStatus::Pending.as_str(),
+        ),
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_suggestion(conn: &Connection, id: i64) -> Result<Option<ClassificationSuggestion>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, file_id, category, subcategory, priority, confidence, reason,
+                suggested_by, status, created_at
+         FROM classification_suggestions WHERE id = ?1",
+    )?;
+
+    let suggestion = stmt.query_row([id], suggestion_from_row).optional()?;
+    Ok(suggestion)
+}
+
+/// Pending suggestions, grouped by category and strongest first.
+pub fn list_pending_suggestions(
+    conn: &Connection,
+    min_confidence: Option<f64>,
+) -> Result<Vec<ClassificationSuggestion>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, file_id, category, subcategory, priority, confidence, reason,
+                suggested_by, status, created_at
+         FROM classification_suggestions
+         WHERE status = 'pending' AND confidence >= ?1
+         ORDER BY category, subcategory, confidence DESC, id",
+    )?;
+
+    let suggestions = stmt
+        .query_map([min_confidence.unwrap_or(0.0)], suggestion_from_row)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(suggestions)
+}
+
+pub fn count_pending_suggestions(conn: &Connection) -> Result<i64> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM classification_suggestions WHERE status = 'pending'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+pub fn update_suggestion_status(
+    conn: &Connection,
+    id: i64,
+    status: SuggestionStatus,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE classification_suggestions SET status = ?1, reviewed_at = datetime('now') WHERE id = ?2",
+        (status.as_str(), id),
+    )?;
+    Ok(())
+}
+
+/// Apply a suggestion's classification to its file and mark it accepted.
+pub fn accept_suggestion(conn: &Connection, suggestion: &ClassificationSuggestion) -> Result<()> {
+    files::update_file_classification(
+        conn,
+        suggestion.file_id,
+        &suggestion.category,
+        suggestion.subcategory.as_deref(),
+        suggestion.priority.unwrap_or(Priority::Normal),
+    )?;
+    update_suggestion_status(conn, suggestion.id, SuggestionStatus::Accepted)
+}
+
+fn suggestion_from_row(row: &rusqlite::Row) -> rusqlite::Result<ClassificationSuggestion> {
+    let priority: Option<String> = row.get(4)?;
+    let status: String = row.get(8)?;
+    let created_at: String = row.get(9)?;
+
+    Ok(ClassificationSuggestion {
+        id: row.get(0)?,
+        file_id: row.get(1)?,
+        category: row.get(2)?,
+        subcategory: row.get(3)?,
+        priority: priority.and_then(|p| Priority::from_str(&p).ok()),
+        confidence: row.get(5)?,
+        reason: row.get(6)?,
+        suggested_by: row.get(7)?,
+        status: SuggestionStatus::from_str(&status).unwrap_or(SuggestionStatus::Pending),
+        created_at: NaiveDateTime::parse_from_str(&created_at, "%Y-%m-%d %H:%M:%S")
+            .map(|dt| dt.and_utc())
+            .unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::initialize_schema;
+
+    fn create_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        initialize_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'src', 'source', 1, 'local')",
+            [],
+        )
+        .unwrap();
+        for id in 1..=2 {
+            conn.execute(
+                "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+                 VALUES (?1, 1, ?2, 'f', 100, 'indexed', datetime('now'))",
+                rusqlite::params![id, format!("/src/{}", id)],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    fn suggestion(file_id: i64, category: &str, confidence: f64) -> ClassificationSuggestion {
+        ClassificationSuggestion {
+            id: 0,
+            file_id,
+            category: category.to_string(),
+            subcategory: None,
+            priority: Some(Priority::Low),
+            confidence,
+            reason: Some("filename pattern".to_string()),
+            suggested_by: Some("agent/1.0".to_string()),
+            status: SuggestionStatus::Pending,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_suggestions_stay_pending_until_accepted() {
+        let conn = create_test_db();
+
+        add_suggestion(&conn, &suggestion(1, "photos", 0.9)).unwrap();
+        add_suggestion(&conn, &suggestion(2, "documents", 0.4)).unwrap();
+
+        let file = files::get_file(&conn, 1).unwrap().unwrap();
+        assert!(file.category.is_none());
+
+        assert_eq!(count_pending_suggestions(&conn).unwrap(), 2);
+        let confident = list_pending_suggestions(&conn, Some(0.5)).unwrap();
+        assert_eq!(confident.len(), 1);
+        assert_eq!(confident[0].category, "photos");
+
+        let awaiting = files::list_files_awaiting_suggestion(&conn, None, 10).unwrap();
+        assert!(awaiting.is_empty());
+
+        accept_suggestion(&conn, &confident[0]).unwrap();
+        let file = files::get_file(&conn, 1).unwrap().unwrap();
+        assert_eq!(file.category.as_deref(), Some("photos"));
+        assert_eq!(file.priority, Priority::Low);
+
+        let accepted = get_suggestion(&conn, confident[0].id).unwrap().unwrap();
+        assert_eq!(accepted.status, SuggestionStatus::Accepted);
+        assert_eq!(count_pending_suggestions(&conn).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_new_suggestion_supersedes_pending() {
+        let conn = create_test_db();
+
+        let first = add_suggestion(&conn, &suggestion(1, "photos", 0.6)).unwrap();
+        add_suggestion(&conn, &suggestion(1, "screenshots", 0.8)).unwrap();
+
+        let pending = list_pending_suggestions(&conn, None).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].category, "screenshots");
+        assert_eq!(
+            get_suggestion(&conn, first).unwrap().unwrap().status,
+            SuggestionStatus::Superseded
+        );
+
+        assert!(add_suggestion(&conn, &suggestion(1, "photos", 1.5)).is_err());
+        assert!(add_suggestion(&conn, &suggestion(99, "photos", 0.5)).is_err());
+    }
+}
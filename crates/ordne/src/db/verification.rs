@@ -0,0 +1,294 @@
+use crate::db::SqliteDatabase;
+use crate::error::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A file whose content did not match its recorded hash, or that could not
+/// be read back at all. Failures stay in the inbox until acknowledged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationFailure {
+    pub id: i64,
+    pub file_id: Option<i64>,
+    pub plan_id: Option<i64>,
+    pub step_id: Option<i64>,
+    pub drive_id: Option<i64>,
+    pub path: String,
+    pub expected_hash: Option<String>,
+    pub actual_hash: Option<String>,
+    /// `mismatch`, `missing` or `unreadable`.
+    pub reason: String,
+    pub detected_at: DateTime<Utc>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+}
+
+/// A scheduled re-read of a completed plan's destinations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationJob {
+    pub id: i64,
+    pub plan_id: i64,
+    pub due_at: DateTime<Utc>,
+    /// `pending`, `passed` or `failed`.
+    pub status: String,
+    pub files_checked: i64,
+    pub files_failed: i64,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+pub trait VerificationDatabase {
+    /// Record a failure. An unacknowledged failure for the same path and
+    /// reason is reused rather than duplicated.
+    fn record_verification_failure(&mut self, failure: &VerificationFailure) -> Result<i64>;
+    fn list_unacknowledged_failures(&self) -> Result<Vec<VerificationFailure>>;
+    fn acknowledge_verification_failure(&mut self, id: i64) -> Result<bool>;
+    fn acknowledge_all_verification_failures(&mut self) -> Result<usize>;
+    fn schedule_verification_job(&mut self, plan_id: i64, due_at: DateTime<Utc>) -> Result<i64>;
+    /// Pending jobs, oldest first. With `due_by`, only those due by then.
+    fn list_pending_verification_jobs(
+        &self,
+        due_by: Option<DateTime<Utc>>,
+    ) -> Result<Vec<VerificationJob>>;
+    fn complete_verification_job(
+        &mut self,
+        id: i64,
+        files_checked: i64,
+        files_failed: i64,
+    ) -> Result<()>;
+}
+
+impl VerificationDatabase for SqliteDatabase {
+    fn record_verification_failure(&mut self, failure: &VerificationFailure) -> Result<i64> {
+        let conn = self.conn_mut();
+
+        let existing: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM verification_failures
+                 WHERE path = ?1 AND reason = ?2 AND acknowledged_at IS NULL",
+                (&failure.path, &failure.reason),
+                |row| row.get(0),
+            )
+            .ok();
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+
+        conn.execute(
+            "INSERT INTO verification_failures
+             (file_id, plan_id, step_id, drive_id, path, expected_hash, actual_hash, reason)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (
+                failure.file_id,
+                failure.plan_id,
+                failure.step_id,
+                failure.drive_id,
+                &failure.path,
+                &failure.expected_hash,
+                &failure.actual_hash,
+                &failure.reason,
+            ),
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn list_unacknowledged_failures(&self) -> Result<Vec<VerificationFailure>> {
+        let mut stmt = self.conn().prepare(
+            "SELECT id, file_id, plan_id, step_id, drive_id, path, expected_hash, actual_hash,
+                    reason, detected_at, acknowledged_at
+             FROM verification_failures WHERE acknowledged_at IS NULL
+             ORDER BY detected_at, id",
+        )?;
+
+        let failures = stmt
+            .query_map([], |row| {
+                Ok(VerificationFailure {
+                    id: row.get(0)?,
+                    file_id: row.get(1)?,
+                    plan_id: row.get(2)?,
+                    step_id: row.get(3)?,
+                    drive_id: row.get(4)?,
+                    path: row.get(5)?,
+                    expected_hash: row.get(6)?,
+                    actual_hash: row.get(7)?,
+                    reason: row.get(8)?,
+                    detected_at: parse_timestamp(&row.get::<_, String>(9)?)
+                        .unwrap_or_else(Utc::now),
+                    acknowledged_at: row
+                        .get::<_, Option<String>>(10)?
+                        .and_then(|s| parse_timestamp(&s)),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(failures)
+    }
+
+    fn acknowledge_verification_failure(&mut self, id: i64) -> Result<bool> {
+        let changed = self.conn_mut().execute(
+            "UPDATE verification_failures SET acknowledged_at = datetime('now')
+             WHERE id = ?1 AND acknowledged_at IS NULL",
+            [id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    fn acknowledge_all_verification_failures(&mut self) -> Result<usize> {
+        let changed = self.conn_mut().execute(
+            "UPDATE verification_failures SET acknowledged_at = datetime('now')
+             WHERE acknowledged_at IS NULL",
+            [],
+        )?;
+        Ok(changed)
+    }
+
+    fn schedule_verification_job(&mut self, plan_id: i64, due_at: DateTime<Utc>) -> Result<i64> {
+        let conn = self.conn_mut();
+        conn.execute(
+            "INSERT INTO verification_jobs (plan_id, due_at) VALUES (?1, ?2)",
+            (plan_id, format_timestamp(&due_at)),
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn list_pending_verification_jobs(
+        &self,
+        due_by: Option<DateTime<Utc>>,
+    ) -> Result<Vec<VerificationJob>> {
+        let mut stmt = self.conn().prepare(
+            "SELECT id, plan_id, due_at, status, files_checked, files_failed,
+                    created_at, completed_at
+             FROM verification_jobs
+             WHERE status = 'pending' AND (?1 IS NULL OR due_at <= ?1)
+             ORDER BY due_at, id",
+        )?;
+
+        let jobs = stmt
+            .query_map([due_by.as_ref().map(format_timestamp)], |row| {
+                Ok(VerificationJob {
+                    id: row.get(0)?,
+                    plan_id: row.get(1)?,
+                    due_at: parse_timestamp(&row.get::<_, String>(2)?).unwrap_or_else(Utc::now),
+                    status: row.get(3)?,
+                    files_checked: row.get(4)?,
+                    files_failed: row.get(5)?,
+                    created_at: parse_timestamp(&row.get::<_, String>(6)?)
+                        .unwrap_or_else(Utc::now),
+                    completed_at: row
+                        .get::<_, Option<String>>(7)?
+                        .and_then(|s| parse_timestamp(&s)),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(jobs)
+    }
+
+    fn complete_verification_job(
+        &mut self,
+        id: i64,
+        files_checked: i64,
+        files_failed: i64,
+    ) -> Result<()> {
+        self.conn_mut().execute(
+            "UPDATE verification_jobs
+             SET status = CASE WHEN ?3 > 0 THEN 'failed' ELSE 'passed' END,
+                 files_checked = ?2, files_failed = ?3, completed_at = datetime('now')
+             WHERE id = ?1",
+            (id, files_checked, files_failed),
+        )?;
+        Ok(())
+    }
+}
+
+/// Same layout as SQLite's `datetime()`, so stored values compare as text.
+pub(crate) fn format_timestamp(dt: &DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub(crate) fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|dt| dt.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn failure(path: &str, reason: &str) -> VerificationFailure {
+        VerificationFailure {
+            id: 0,
+            file_id: None,
+            plan_id: None,
+            step_id: None,
+            drive_id: None,
+            path: path.to_string(),
+            expected_hash: Some("abc".to_string()),
+            actual_hash: Some("def".to_string()),
+            reason: reason.to_string(),
+            detected_at: Utc::now(),
+            acknowledged_at: None,
+        }
+    }
+
+    #[test]
+    fn test_failures_until_acknowledged() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+
+        let first = db
+            .record_verification_failure(&failure("/a", "mismatch"))
+            .unwrap();
+        let again = db
+            .record_verification_failure(&failure("/a", "mismatch"))
+            .unwrap();
+        assert_eq!(first, again);
+        db.record_verification_failure(&failure("/b", "missing"))
+            .unwrap();
+
+        assert_eq!(db.list_unacknowledged_failures().unwrap().len(), 2);
+
+        assert!(db.acknowledge_verification_failure(first).unwrap());
+        assert!(!db.acknowledge_verification_failure(first).unwrap());
+
+        let remaining = db.list_unacknowledged_failures().unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].path, "/b");
+
+        assert_eq!(db.acknowledge_all_verification_failures().unwrap(), 1);
+        assert!(db.list_unacknowledged_failures().unwrap().is_empty());
+
+        // Once acknowledged, a recurring failure is reported again.
+        let recurring = db
+            .record_verification_failure(&failure("/a", "mismatch"))
+            .unwrap();
+        assert_ne!(recurring, first);
+    }
+
+    #[test]
+    fn test_verification_jobs_come_due() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+
+        db.conn()
+            .execute_batch("INSERT INTO migration_plans (id, status) VALUES (1, 'completed'), (2, 'completed');")
+            .unwrap();
+
+        let now = Utc::now();
+        let due = db.schedule_verification_job(1, now - chrono::Duration::hours(1)).unwrap();
+        db.schedule_verification_job(2, now + chrono::Duration::days(1)).unwrap();
+
+        assert_eq!(db.list_pending_verification_jobs(None).unwrap().len(), 2);
+        let jobs = db.list_pending_verification_jobs(Some(now)).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].plan_id, 1);
+
+        db.complete_verification_job(due, 10, 1).unwrap();
+        assert!(db.list_pending_verification_jobs(Some(now)).unwrap().is_empty());
+        let status: String = db
+            .conn()
+            .query_row("SELECT status FROM verification_jobs WHERE id = ?1", [due], |row| row.get(0))
+            .unwrap();
+        assert_eq!(status, "failed");
+    }
+}
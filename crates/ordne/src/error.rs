@@ -27,6 +27,9 @@ pub enum OrdneError {
     #[error("Insufficient space: {available} bytes available, {required} bytes required")]
     InsufficientSpace { available: u64, required: u64 },
 
+    #[error("Insufficient inodes on {path}: {available} available, {required} required")]
+    InsufficientInodes { path: PathBuf, available: u64, required: u64 },
+
     #[error("Invalid status transition: {from} -> {to}")]
     InvalidStatusTransition { from: String, to: String },
 
@@ -39,12 +42,21 @@ pub enum OrdneError {
     #[error("Plan not approved: {0}")]
     PlanNotApproved(i64),
 
+    #[error("Plan {plan_id} is already running in process {pid} on {host}")]
+    PlanRunning { plan_id: i64, pid: u32, host: String },
+
+    #[error("Plan {plan_id} queued until these drives are online: {drives}")]
+    PlanQueued { plan_id: i64, drives: String },
+
     #[error("External tool error: {tool} failed: {message}")]
     ExternalTool { tool: String, message: String },
 
     #[error("Source file changed: expected hash {expected}, current hash {actual}")]
     SourceChanged { expected: String, actual: String },
 
+    #[error("File changed while it was being read: {path}")]
+    FileChanged { path: PathBuf },
+
     #[error("Destination verification failed for {path}")]
     DestinationVerification { path: PathBuf },
 
@@ -56,6 +68,62 @@ pub enum OrdneError {
 
     #[error("User input error: {0}")]
     UserInput(String),
+
+    #[error("Refused in safe mode: {0}")]
+    SafeMode(String),
+
+    #[error("Safety check failed: {0}")]
+    SafetyViolation(String),
+
+    #[error("Verification failed: {0}")]
+    VerificationFailed(String),
+}
+
+/// Exit codes of the `ordne` binary, so scripts and cron jobs can branch on
+/// the kind of failure. Argument errors from clap also exit with 2.
+pub mod exit_code {
+    pub const SUCCESS: u8 = 0;
+    /// Anything not covered below: database, I/O and external tool errors.
+    pub const FAILURE: u8 = 1;
+    /// Bad arguments or configuration, or a drive, plan or file that does not exist.
+    pub const INVALID_INPUT: u8 = 2;
+    /// A drive the command needs is offline.
+    pub const DRIVE_OFFLINE: u8 = 3;
+    /// Refused by safe mode, a space check or a copy or size limit.
+    pub const SAFETY: u8 = 4;
+    /// Files failed hash verification.
+    pub const VERIFICATION: u8 = 5;
+}
+
+impl OrdneError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            OrdneError::Config(_)
+            | OrdneError::DriveNotFound(_)
+            | OrdneError::FileNotFound(_)
+            | OrdneError::InvalidStatusTransition { .. }
+            | OrdneError::PlanNotFound(_)
+            | OrdneError::PlanNotApproved(_)
+            | OrdneError::PlanRunning { .. }
+            | OrdneError::InvalidBackend(_)
+            | OrdneError::UserInput(_) => exit_code::INVALID_INPUT,
+            OrdneError::DriveOffline(_) | OrdneError::PlanQueued { .. } => exit_code::DRIVE_OFFLINE,
+            OrdneError::InsufficientSpace { .. }
+            | OrdneError::InsufficientInodes { .. }
+            | OrdneError::SafeMode(_)
+            | OrdneError::SafetyViolation(_) => exit_code::SAFETY,
+            OrdneError::HashMismatch { .. }
+            | OrdneError::SourceChanged { .. }
+            | OrdneError::DestinationVerification { .. }
+            | OrdneError::VerificationFailed(_) => exit_code::VERIFICATION,
+            OrdneError::Database(_)
+            | OrdneError::Io(_)
+            | OrdneError::Json(_)
+            | OrdneError::Migration(_)
+            | OrdneError::FileChanged { .. }
+            | OrdneError::ExternalTool { .. } => exit_code::FAILURE,
+        }
+    }
 }
 
 impl From<dialoguer::Error> for OrdneError {
@@ -65,3 +133,25 @@ impl From<dialoguer::Error> for OrdneError {
 }
 
 pub type Result<T> = std::result::Result<T, OrdneError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_by_failure_kind() {
+        assert_eq!(OrdneError::Config("bad".into()).exit_code(), exit_code::INVALID_INPUT);
+        assert_eq!(OrdneError::PlanNotFound(1).exit_code(), exit_code::INVALID_INPUT);
+        assert_eq!(OrdneError::DriveOffline("nas".into()).exit_code(), exit_code::DRIVE_OFFLINE);
+        assert_eq!(
+            OrdneError::InsufficientSpace { available: 1, required: 2 }.exit_code(),
+            exit_code::SAFETY
+        );
+        assert_eq!(OrdneError::SafeMode("delete".into()).exit_code(), exit_code::SAFETY);
+        assert_eq!(
+            OrdneError::DestinationVerification { path: PathBuf::from("/a") }.exit_code(),
+            exit_code::VERIFICATION
+        );
+        assert_eq!(OrdneError::Migration("boom".into()).exit_code(), exit_code::FAILURE);
+    }
+}
@@ -0,0 +1,120 @@
+//! Measuring which [`HashSettings`] read a device fastest.
+//!
+//! Each candidate hashes the same sample of files. The page cache is dropped
+//! for those files before every run, so later candidates do not simply read
+//! from memory; only clean pages can be dropped, which is all a benchmark of
+//! files at rest has.
+
+use crate::db::HashAlgorithm;
+use crate::error::Result;
+use crate::index::hasher::{hash_file_with, HashSettings};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone)]
+pub struct HashBenchResult {
+    pub settings: HashSettings,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl HashBenchResult {
+    pub fn bytes_per_second(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Buffer sizes and read modes worth comparing.
+pub fn candidate_settings() -> Vec<HashSettings> {
+    let buffered = |buffer_size| HashSettings { buffer_size, mmap: false, direct_io: false };
+    let direct = |buffer_size| HashSettings { buffer_size, mmap: false, direct_io: true };
+    vec![
+        HashSettings::default(),
+        buffered(64 * 1024),
+        buffered(256 * 1024),
+        buffered(1024 * 1024),
+        buffered(4 * 1024 * 1024),
+        HashSettings { buffer_size: 1024 * 1024, mmap: true, direct_io: false },
+        direct(1024 * 1024),
+        direct(4 * 1024 * 1024),
+    ]
+}
+
+/// The largest regular files under `root` until together they reach
+/// `max_bytes`; `root` itself if it is a file.
+pub fn sample_files(root: &Path, max_bytes: u64) -> Vec<PathBuf> {
+    let mut files: Vec<(u64, PathBuf)> = WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| Some((e.metadata().ok()?.len(), e.into_path())))
+        .collect();
+    files.sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+
+    let mut total = 0;
+    files
+        .into_iter()
+        .take_while(|(size, _)| {
+            let take = total < max_bytes;
+            total += size;
+            take
+        })
+        .map(|(_, path)| path)
+        .collect()
+}
+
+/// Hash `files` once per candidate, from a cold cache where possible.
+/// Files that cannot be read are left out of every run.
+pub fn benchmark_hashing(
+    files: &[PathBuf],
+    candidates: &[HashSettings],
+    algorithm: HashAlgorithm,
+) -> Result<Vec<HashBenchResult>> {
+    let readable: Vec<(&PathBuf, u64)> = files
+        .iter()
+        .filter_map(|path| Some((path, std::fs::metadata(path).ok()?.len())))
+        .collect();
+    let bytes = readable.iter().map(|(_, size)| size).sum();
+
+    let mut results = Vec::with_capacity(candidates.len());
+    for settings in candidates {
+        for (path, _) in &readable {
+            drop_cached_pages(path);
+        }
+        let start = Instant::now();
+        for (path, _) in &readable {
+            hash_file_with(path, algorithm, settings)?;
+        }
+        results.push(HashBenchResult { settings: *settings, bytes, elapsed: start.elapsed() });
+    }
+    Ok(results)
+}
+
+fn drop_cached_pages(path: &Path) {
+    if let Ok(file) = std::fs::File::open(path) {
+        // SAFETY: advisory call on a descriptor we own.
+        unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark_runs_every_candidate() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("big"), vec![1u8; 200_000]).unwrap();
+        std::fs::write(dir.path().join("small"), vec![2u8; 1_000]).unwrap();
+
+        // The largest file alone reaches the limit.
+        let files = sample_files(dir.path(), 100_000);
+        assert_eq!(files, vec![dir.path().join("big")]);
+
+        let results = benchmark_hashing(&files, &candidate_settings(), HashAlgorithm::Blake3).unwrap();
+        assert_eq!(results.len(), candidate_settings().len());
+        assert!(results.iter().all(|r| r.bytes == 200_000));
+    }
+}
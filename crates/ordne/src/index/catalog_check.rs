@@ -0,0 +1,243 @@
+//! Checking a random sample of the catalog against the filesystem.
+//!
+//! A full `verify` re-reads every file; this reads a sample, so it is cheap
+//! enough to run often. Each sampled file is stat'ed and, when its size and
+//! modification time still match, hashed and compared with its catalogued
+//! hash. The share of sampled files that drifted is a measure of how far
+//! the catalog can be trusted per drive; the upper bound is the 95% Wilson
+//! score limit, so a clean sample of a few files does not read as proof.
+
+use crate::db::files::sample_files;
+use crate::db::{Database, File, HashAlgorithm, SqliteDatabase};
+use crate::error::Result;
+use crate::index::estimate::Z_95;
+use crate::migrate::hash::compute_hash;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftKind {
+    Missing,
+    SizeChanged,
+    ModifiedChanged,
+    HashChanged,
+    Unreadable,
+}
+
+impl DriftKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DriftKind::Missing => "missing",
+            DriftKind::SizeChanged => "size_changed",
+            DriftKind::ModifiedChanged => "modified_changed",
+            DriftKind::HashChanged => "hash_changed",
+            DriftKind::Unreadable => "unreadable",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Drift {
+    pub file_id: i64,
+    pub path: String,
+    pub kind: DriftKind,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DriveDrift {
+    pub drive_id: i64,
+    pub label: String,
+    pub sampled: usize,
+    pub ok: usize,
+    /// Matched on size and modification time but had no hash to compare.
+    pub unhashed: usize,
+    pub drifted: Vec<Drift>,
+    /// Share of the sample that drifted.
+    pub drift_rate: f64,
+    /// 95% upper bound of the share of the drive's catalog that drifted.
+    pub drift_upper_bound: f64,
+}
+
+impl DriveDrift {
+    pub fn count(&self, kind: DriftKind) -> usize {
+        self.drifted.iter().filter(|d| d.kind == kind).count()
+    }
+}
+
+/// Stat and, with `hash`, hash up to `sample` random files of online local
+/// drives (or of `drive_id`) and report drift per drive.
+pub fn check_catalog(
+    db: &SqliteDatabase,
+    drive_id: Option<i64>,
+    sample: usize,
+    hash: bool,
+) -> Result<Vec<DriveDrift>> {
+    let mut drives: BTreeMap<i64, DriveDrift> = BTreeMap::new();
+    for file in sample_files(db.conn(), drive_id, sample)? {
+        let report = match drives.entry(file.drive_id) {
+            std::collections::btree_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::btree_map::Entry::Vacant(entry) => {
+                let label = db
+                    .get_drive_by_id(file.drive_id)?
+                    .map(|d| d.label)
+                    .unwrap_or_default();
+                entry.insert(DriveDrift {
+                    drive_id: file.drive_id,
+                    label,
+                    ..Default::default()
+                })
+            }
+        };
+        report.sampled += 1;
+        match check_file(&file, hash) {
+            Ok(true) => report.ok += 1,
+            Ok(false) => report.unhashed += 1,
+            Err(kind) => report.drifted.push(Drift {
+                file_id: file.id,
+                path: file.abs_path.clone(),
+                kind,
+            }),
+        }
+    }
+    Ok(drives
+        .into_values()
+        .map(|mut report| {
+            let drifted = report.drifted.len();
+            report.drift_rate = drifted as f64 / report.sampled as f64;
+            report.drift_upper_bound = wilson_upper(drifted, report.sampled);
+            report
+        })
+        .collect())
+}
+
+/// `Ok(true)` when the file matches its catalog entry, `Ok(false)` when it
+/// matches as far as could be checked without a catalogued hash.
+fn check_file(file: &File, hash: bool) -> std::result::Result<bool, DriftKind> {
+    let path = Path::new(&file.abs_path);
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(DriftKind::Missing),
+        Err(_) => return Err(DriftKind::Unreadable),
+    };
+    if file.is_symlink {
+        return Ok(true);
+    }
+    if metadata.len() as i64 != file.size_bytes {
+        return Err(DriftKind::SizeChanged);
+    }
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
+    if let (Some(catalogued), Some(actual)) = (file.modified_at, modified) {
+        if catalogued.timestamp() != actual {
+            return Err(DriftKind::ModifiedChanged);
+        }
+    }
+    if !hash {
+        return Ok(true);
+    }
+
+    let (expected, algorithm) = match (&file.blake3_hash, &file.md5_hash) {
+        (Some(h), _) => (h, HashAlgorithm::Blake3),
+        (None, Some(h)) => (h, HashAlgorithm::Md5),
+        (None, None) => return Ok(false),
+    };
+    match compute_hash(path, algorithm) {
+        Ok(actual) if actual.eq_ignore_ascii_case(expected) => Ok(true),
+        Ok(_) => Err(DriftKind::HashChanged),
+        Err(_) => Err(DriftKind::Unreadable),
+    }
+}
+
+/// Upper limit of the 95% Wilson score interval of `hits` in `n`.
+fn wilson_upper(hits: usize, n: usize) -> f64 {
+    if n == 0 {
+        return 1.0;
+    }
+    let n = n as f64;
+    let p = hits as f64 / n;
+    let z2 = Z_95 * Z_95;
+    let centre = p + z2 / (2.0 * n);
+    let margin = Z_95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    ((centre + margin) / (1.0 + z2 / n)).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrate::hash::compute_hash;
+
+    #[test]
+    fn test_check_catalog_reports_drift_per_drive() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute(
+                "INSERT INTO drives (id, label, role, is_online, backend, mount_path) VALUES (1, 'data', 'source', 1, 'local', ?1)",
+                [dir.path().to_string_lossy()],
+            )
+            .unwrap();
+        db.conn()
+            .execute(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (2, 'shelf', 'backup', 0, 'local')",
+                [],
+            )
+            .unwrap();
+
+        let files = ["same", "grown", "rotted", "unhashed", "gone"];
+        for name in files {
+            std::fs::write(dir.path().join(name), "content").unwrap();
+        }
+        for (id, name) in files.iter().enumerate() {
+            let path = dir.path().join(name);
+            let hash = compute_hash(&path, HashAlgorithm::Blake3).unwrap();
+            let modified = chrono::DateTime::<chrono::Utc>::from(
+                std::fs::metadata(&path).unwrap().modified().unwrap(),
+            );
+            db.conn()
+                .execute(
+                    "INSERT INTO files (id, drive_id, path, filename, size_bytes, blake3_hash, modified_at)
+                     VALUES (?1, 1, ?2, ?2, 7, NULLIF(?3, 'none'), ?4)",
+                    rusqlite::params![
+                        id as i64 + 1,
+                        name,
+                        if *name == "unhashed" { "none".to_string() } else { hash },
+                        modified.to_rfc3339()
+                    ],
+                )
+                .unwrap();
+        }
+        db.conn()
+            .execute("INSERT INTO files (id, drive_id, path, filename, size_bytes) VALUES (10, 2, 'x', 'x', 1)", [])
+            .unwrap();
+
+        std::fs::write(dir.path().join("grown"), "content and more").unwrap();
+        db.conn()
+            .execute(
+                "UPDATE files SET blake3_hash = ?1 WHERE path = 'rotted'",
+                [&"0".repeat(64)],
+            )
+            .unwrap();
+        std::fs::remove_file(dir.path().join("gone")).unwrap();
+
+        let reports = check_catalog(&db, None, 100, true).unwrap();
+        assert_eq!(reports.len(), 1, "offline drives are not sampled");
+        let report = &reports[0];
+        assert_eq!((report.sampled, report.ok, report.unhashed), (5, 1, 1));
+        assert_eq!(report.count(DriftKind::SizeChanged), 1);
+        assert_eq!(report.count(DriftKind::HashChanged), 1);
+        assert_eq!(report.count(DriftKind::Missing), 1);
+        assert!((report.drift_rate - 0.6).abs() < 1e-9);
+        assert!(report.drift_upper_bound > 0.6);
+
+        // Without hashing, only the stat checks run.
+        let report = &check_catalog(&db, Some(1), 100, false).unwrap()[0];
+        assert_eq!(report.count(DriftKind::HashChanged), 0);
+        assert_eq!(check_catalog(&db, Some(1), 2, false).unwrap()[0].sampled, 2);
+    }
+}
@@ -1,12 +1,16 @@
 use crate::db::{
     duplicates::{assign_files_to_duplicate_group, clear_duplicate_assignments, clear_duplicate_groups, create_duplicate_group},
     files::{list_files_by_drive, update_file_hash},
+    Database,
+    DriveRole,
     File,
+    FileStatus,
     SqliteDatabase,
 };
 use crate::error::{OrdneError, Result};
-use crate::index::{hash_file_blake3, hash_file_md5};
+use crate::index::{hash_file_blake3, hash_file_md5, ExcludeRules};
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 #[derive(Debug, Clone, Copy)]
 pub enum DedupAlgorithm {
@@ -15,6 +19,7 @@ pub enum DedupAlgorithm {
 }
 
 impl DedupAlgorithm {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(value: &str) -> Result<Self> {
         match value {
             "md5" => Ok(DedupAlgorithm::Md5),
@@ -27,30 +32,106 @@ impl DedupAlgorithm {
     }
 }
 
+/// How the original of a rebuilt duplicate group is chosen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OriginalStrategy {
+    /// The copy that was catalogued first.
+    #[default]
+    FirstIndexed,
+    /// The copy with the earliest modification time.
+    Oldest,
+    /// The copy with the latest modification time.
+    Newest,
+    /// The copy with the shortest absolute path.
+    ShortestPath,
+    /// A copy on a source drive, then backup, target and offload drives.
+    DriveRole,
+}
+
+impl OriginalStrategy {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "first-indexed" => Ok(OriginalStrategy::FirstIndexed),
+            "oldest" => Ok(OriginalStrategy::Oldest),
+            "newest" => Ok(OriginalStrategy::Newest),
+            "shortest-path" => Ok(OriginalStrategy::ShortestPath),
+            "drive-role" => Ok(OriginalStrategy::DriveRole),
+            _ => Err(OrdneError::Config(format!(
+                "Invalid original strategy '{}'. Use 'first-indexed', 'oldest', 'newest', 'shortest-path' or 'drive-role'",
+                value
+            ))),
+        }
+    }
+
+    /// Pick the original among `files`; ties go to the copy catalogued first.
+    fn select(&self, files: &[File], roles: &HashMap<i64, DriveRole>) -> Option<i64> {
+        let role_rank = |file: &File| role_rank(roles.get(&file.drive_id).copied());
+
+        let mut files: Vec<&File> = files.iter().collect();
+        files.sort_by_key(|f| f.id);
+
+        let original = match self {
+            OriginalStrategy::FirstIndexed => files.first().copied(),
+            OriginalStrategy::Oldest => files
+                .iter()
+                .copied()
+                .min_by_key(|f| (f.modified_at.is_none(), f.modified_at)),
+            OriginalStrategy::Newest => files
+                .iter()
+                .copied()
+                .min_by_key(|f| (f.modified_at.is_none(), std::cmp::Reverse(f.modified_at))),
+            OriginalStrategy::ShortestPath => files.iter().copied().min_by_key(|f| f.abs_path.len()),
+            OriginalStrategy::DriveRole => files.iter().copied().min_by_key(|f| role_rank(f)),
+        };
+        original.map(|f| f.id)
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct DedupRefreshResult {
     pub files_hashed: usize,
     pub files_skipped: usize,
     pub groups_created: usize,
     pub duplicate_files_assigned: usize,
+    /// Hashed files from cold drives, matched from the catalog alone.
+    pub cold_files_included: usize,
+    /// Files without a catalogued hash, left out of the groups.
+    pub files_unhashed: usize,
+    /// Groups whose copies are spread over more than one drive.
+    pub cross_drive_groups: usize,
+    /// Files that kept changing while they were hashed, left out of the groups.
+    pub files_volatile: usize,
+    /// Files matching `[dedup] ignore`, left out of the groups unhashed.
+    pub files_ignored: usize,
 }
 
+/// Hash files on `drive_id` that have no hash yet (all of them with
+/// `rehash`) and rebuild duplicate groups. Files matching `ignore` are
+/// neither hashed nor grouped.
 pub fn refresh_duplicates_for_drive(
     db: &mut SqliteDatabase,
     drive_id: i64,
     algorithm: DedupAlgorithm,
     rehash: bool,
+    ignore: &ExcludeRules,
 ) -> Result<DedupRefreshResult> {
     let mut result = DedupRefreshResult::default();
 
     let files = list_files_by_drive(db.conn(), drive_id)?;
     let mut hash_map: HashMap<String, Vec<File>> = HashMap::new();
+    // Files written to while hashed, tried again once the rest are done.
+    let mut volatile = Vec::new();
 
     for file in files {
         if file.is_symlink {
             result.files_skipped += 1;
             continue;
         }
+        if ignore.excludes_file(Path::new(&file.path)) {
+            result.files_ignored += 1;
+            continue;
+        }
 
         let has_hash = match algorithm {
             DedupAlgorithm::Md5 => file.md5_hash.is_some(),
@@ -63,23 +144,140 @@ pub fn refresh_duplicates_for_drive(
                 DedupAlgorithm::Blake3 => file.blake3_hash.clone().unwrap(),
             }
         } else {
-            let computed = match algorithm {
-                DedupAlgorithm::Md5 => hash_file_md5(&file.abs_path)?,
-                DedupAlgorithm::Blake3 => hash_file_blake3(&file.abs_path)?,
-            };
-
-            match algorithm {
-                DedupAlgorithm::Md5 => update_file_hash(db.conn(), file.id, Some(&computed), None)?,
-                DedupAlgorithm::Blake3 => update_file_hash(db.conn(), file.id, None, Some(&computed))?,
+            match hash_and_record(db, &file, algorithm) {
+                Ok(computed) => {
+                    result.files_hashed += 1;
+                    computed
+                }
+                Err(OrdneError::FileChanged { .. }) => {
+                    volatile.push(file);
+                    continue;
+                }
+                Err(e) => return Err(e),
             }
-
-            result.files_hashed += 1;
-            computed
         };
 
         hash_map.entry(hash).or_default().push(file);
     }
 
+    for file in volatile {
+        match hash_and_record(db, &file, algorithm) {
+            Ok(hash) => {
+                result.files_hashed += 1;
+                hash_map.entry(hash).or_default().push(file);
+            }
+            Err(OrdneError::FileChanged { path }) => {
+                log::warn!("Skipping {}: still changing", path.display());
+                result.files_volatile += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    // Cold drives cannot be read, but their catalogued hashes still count.
+    for drive in db.list_drives()? {
+        if !drive.is_cold || drive.id == drive_id {
+            continue;
+        }
+        for file in list_files_by_drive(db.conn(), drive.id)? {
+            let hash = match algorithm {
+                DedupAlgorithm::Md5 => file.md5_hash.clone(),
+                DedupAlgorithm::Blake3 => file.blake3_hash.clone(),
+            };
+            if ignore.excludes_file(Path::new(&file.path)) {
+                result.files_ignored += 1;
+                continue;
+            }
+            if let (Some(hash), false) = (hash, file.is_symlink) {
+                result.cold_files_included += 1;
+                hash_map.entry(hash).or_default().push(file);
+            }
+        }
+    }
+
+    rebuild_groups(db, hash_map, OriginalStrategy::FirstIndexed, &mut result)?;
+
+    Ok(result)
+}
+
+/// How strongly a copy on a drive with `role` should be kept as the original
+/// of a duplicate group; lower is stronger.
+pub fn role_rank(role: Option<DriveRole>) -> u8 {
+    match role {
+        Some(DriveRole::Source) => 0,
+        Some(DriveRole::Backup) => 1,
+        Some(DriveRole::Target) => 2,
+        Some(DriveRole::Offload) | None => 3,
+    }
+}
+
+fn hash_and_record(db: &SqliteDatabase, file: &File, algorithm: DedupAlgorithm) -> Result<String> {
+    let computed = match algorithm {
+        DedupAlgorithm::Md5 => hash_file_md5(&file.abs_path)?,
+        DedupAlgorithm::Blake3 => hash_file_blake3(&file.abs_path)?,
+    };
+
+    match algorithm {
+        DedupAlgorithm::Md5 => update_file_hash(db.conn(), file.id, Some(&computed), None)?,
+        DedupAlgorithm::Blake3 => update_file_hash(db.conn(), file.id, None, Some(&computed))?,
+    }
+    Ok(computed)
+}
+
+/// Rebuild duplicate groups across every drive from catalogued hashes.
+///
+/// Nothing is read from disk, so offline drives take part as long as their
+/// files were hashed. Files without a hash for `algorithm` are counted in
+/// `files_unhashed`; backfill them with `dedup refresh --drive` or a hash
+/// manifest import first. Files matching `ignore` are left out.
+pub fn refresh_all_duplicates(
+    db: &mut SqliteDatabase,
+    algorithm: DedupAlgorithm,
+    strategy: OriginalStrategy,
+    ignore: &ExcludeRules,
+) -> Result<DedupRefreshResult> {
+    let mut result = DedupRefreshResult::default();
+    let mut hash_map: HashMap<String, Vec<File>> = HashMap::new();
+
+    for drive in db.list_drives()? {
+        for file in list_files_by_drive(db.conn(), drive.id)? {
+            if file.is_symlink || file.status == FileStatus::SourceRemoved {
+                result.files_skipped += 1;
+                continue;
+            }
+            if ignore.excludes_file(Path::new(&file.path)) {
+                result.files_ignored += 1;
+                continue;
+            }
+
+            let hash = match algorithm {
+                DedupAlgorithm::Md5 => file.md5_hash.clone(),
+                DedupAlgorithm::Blake3 => file.blake3_hash.clone(),
+            };
+            match hash {
+                Some(hash) => hash_map.entry(hash).or_default().push(file),
+                None => result.files_unhashed += 1,
+            }
+        }
+    }
+
+    rebuild_groups(db, hash_map, strategy, &mut result)?;
+
+    Ok(result)
+}
+
+fn rebuild_groups(
+    db: &mut SqliteDatabase,
+    hash_map: HashMap<String, Vec<File>>,
+    strategy: OriginalStrategy,
+    result: &mut DedupRefreshResult,
+) -> Result<()> {
+    let roles: HashMap<i64, DriveRole> = db
+        .list_drives()?
+        .into_iter()
+        .map(|d| (d.id, d.role))
+        .collect();
+
     clear_duplicate_assignments(db.conn())?;
     clear_duplicate_groups(db.conn())?;
 
@@ -98,7 +296,7 @@ pub fn refresh_duplicates_for_drive(
         }
 
         file_ids.sort_unstable();
-        let original_id = file_ids.first().copied();
+        let original_id = strategy.select(&files, &roles);
 
         if let Some(original_id) = original_id {
             for file in &files {
@@ -124,10 +322,11 @@ pub fn refresh_duplicates_for_drive(
         assign_files_to_duplicate_group(db.conn(), &file_ids, group_id, original_id)?;
 
         result.groups_created += 1;
+        result.cross_drive_groups += cross_drive as usize;
         result.duplicate_files_assigned += file_ids.len();
     }
 
-    Ok(result)
+    Ok(())
 }
 
 #[cfg(test)]
@@ -154,6 +353,8 @@ mod tests {
             role: DriveRole::Source,
             is_online: true,
             is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
             backend: Backend::Local,
             rclone_remote: None,
             scanned_at: None,
@@ -169,16 +370,18 @@ mod tests {
             drive_id,
             path: abs_path.to_string(),
             abs_path: abs_path.to_string(),
-            filename: abs_path.split('/').last().unwrap().to_string(),
+            filename: abs_path.rsplit('/').next().unwrap().to_string(),
             extension: None,
             size_bytes: 100,
             md5_hash: None,
             blake3_hash: None,
             created_at: None,
             modified_at: None,
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -220,8 +423,110 @@ mod tests {
             ("samehash", file2),
         ).unwrap();
 
-        let result = refresh_duplicates_for_drive(&mut db, drive_id, DedupAlgorithm::Md5, false).unwrap();
+        let result = refresh_duplicates_for_drive(&mut db, drive_id, DedupAlgorithm::Md5, false, &ExcludeRules::default()).unwrap();
+        assert_eq!(result.groups_created, 1);
+        assert_eq!(result.duplicate_files_assigned, 2);
+    }
+
+    #[test]
+    fn test_refresh_includes_cold_drive_catalog() {
+        let mut db = setup_db();
+        let drive_id = insert_drive(&mut db, "drive1");
+        let cold_id = insert_drive(&mut db, "vault");
+
+        let local = insert_file(&mut db, drive_id, "/tmp/a.txt");
+        let archived = insert_file(&mut db, cold_id, "a.txt");
+        for id in [local, archived] {
+            db.conn()
+                .execute("UPDATE files SET md5_hash = 'samehash' WHERE id = ?1", [id])
+                .unwrap();
+        }
+
+        let result = refresh_duplicates_for_drive(&mut db, drive_id, DedupAlgorithm::Md5, false, &ExcludeRules::default()).unwrap();
+        assert_eq!(result.groups_created, 0);
+
+        crate::db::drives::set_drive_cold(db.conn(), cold_id, true).unwrap();
+        assert!(!db.get_drive_by_id(cold_id).unwrap().unwrap().is_online);
+
+        let result = refresh_duplicates_for_drive(&mut db, drive_id, DedupAlgorithm::Md5, false, &ExcludeRules::default()).unwrap();
+        assert_eq!(result.cold_files_included, 1);
+        assert_eq!(result.groups_created, 1);
+        let groups = crate::db::duplicates::list_cross_drive_duplicates(db.conn()).unwrap();
+        assert_eq!(groups.len(), 1);
+    }
+
+    #[test]
+    fn test_ignored_files_are_left_out_of_groups() {
+        let mut db = setup_db();
+        let drive_id = insert_drive(&mut db, "drive1");
+        for path in ["a/.git/objects/pack/p.pack", "b/.git/objects/pack/p.pack", "a/x.txt", "b/x.txt"] {
+            let id = insert_file(&mut db, drive_id, path);
+            let hash = if path.ends_with(".pack") { "pack" } else { "text" };
+            db.conn()
+                .execute("UPDATE files SET blake3_hash = ?1 WHERE id = ?2", (hash, id))
+                .unwrap();
+        }
+
+        let ignore = ExcludeRules::new(["*.pack"]).unwrap();
+        let result =
+            refresh_all_duplicates(&mut db, DedupAlgorithm::Blake3, OriginalStrategy::FirstIndexed, &ignore).unwrap();
+        assert_eq!(result.files_ignored, 2);
         assert_eq!(result.groups_created, 1);
         assert_eq!(result.duplicate_files_assigned, 2);
     }
+
+    #[test]
+    fn test_refresh_all_duplicates_across_drives() {
+        let mut db = setup_db();
+        let nas = insert_drive(&mut db, "nas");
+        let backup = insert_drive(&mut db, "backup");
+        db.conn()
+            .execute("UPDATE drives SET role = 'backup', is_online = 0 WHERE id = ?1", [backup])
+            .unwrap();
+
+        let a = insert_file(&mut db, nas, "/mnt/nas/photos/a.jpg");
+        let b = insert_file(&mut db, backup, "/mnt/backup/a.jpg");
+        let c = insert_file(&mut db, nas, "/mnt/nas/c.txt");
+        let d = insert_file(&mut db, nas, "/mnt/nas/d.txt");
+        let e = insert_file(&mut db, backup, "/mnt/backup/e.txt");
+        insert_file(&mut db, nas, "/mnt/nas/unhashed.txt");
+        for (id, hash, modified) in [
+            (a, "aaa", "2024-01-01T00:00:00Z"),
+            (b, "aaa", "2020-01-01T00:00:00Z"),
+            (c, "ccc", "2021-01-01T00:00:00Z"),
+            (d, "ccc", "2023-01-01T00:00:00Z"),
+            (e, "ccc", "2022-01-01T00:00:00Z"),
+        ] {
+            db.conn()
+                .execute(
+                    "UPDATE files SET blake3_hash = ?1, modified_at = ?2 WHERE id = ?3",
+                    (hash, modified, id),
+                )
+                .unwrap();
+        }
+
+        let result = refresh_all_duplicates(&mut db, DedupAlgorithm::Blake3, OriginalStrategy::Oldest, &ExcludeRules::default()).unwrap();
+        assert_eq!(result.groups_created, 2);
+        assert_eq!(result.cross_drive_groups, 2);
+        assert_eq!(result.duplicate_files_assigned, 5);
+        assert_eq!(result.files_unhashed, 1);
+        assert_eq!(result.files_hashed, 0);
+
+        let originals = |db: &SqliteDatabase| -> Vec<Option<i64>> {
+            let mut groups = crate::db::duplicates::list_duplicate_groups(db.conn()).unwrap();
+            groups.sort_by_key(|g| g.hash.clone());
+            groups.into_iter().map(|g| g.original_id).collect()
+        };
+        assert_eq!(originals(&db), vec![Some(b), Some(c)]);
+
+        refresh_all_duplicates(&mut db, DedupAlgorithm::Blake3, OriginalStrategy::Newest, &ExcludeRules::default()).unwrap();
+        assert_eq!(originals(&db), vec![Some(a), Some(d)]);
+
+        refresh_all_duplicates(&mut db, DedupAlgorithm::Blake3, OriginalStrategy::ShortestPath, &ExcludeRules::default()).unwrap();
+        assert_eq!(originals(&db), vec![Some(b), Some(c)]);
+
+        refresh_all_duplicates(&mut db, DedupAlgorithm::Blake3, OriginalStrategy::DriveRole, &ExcludeRules::default()).unwrap();
+        assert_eq!(originals(&db), vec![Some(a), Some(c)]);
+        assert!(db.get_file(a).unwrap().unwrap().is_original);
+    }
 }
@@ -12,6 +12,8 @@ pub struct DeviceInfo {
     pub total_bytes: Option<i64>,
     pub model: Option<String>,
     pub serial: Option<String>,
+    /// The filesystem sits on a dm-crypt (LUKS) mapping.
+    pub is_encrypted: bool,
 }
 
 impl DeviceInfo {
@@ -25,6 +27,7 @@ impl DeviceInfo {
             total_bytes: None,
             model: None,
             serial: None,
+            is_encrypted: false,
         }
     }
 }
@@ -41,7 +44,8 @@ impl Default for DeviceInfo {
 /// - `/dev/disk/by-id/` for stable device identifiers
 /// - `blkid` for filesystem UUID
 /// - `findmnt` for mount point and filesystem type
-/// - `lsblk` for size, model, serial number
+/// - `lsblk` for size, model, serial number, and whether the device is a
+///   LUKS/dm-crypt mapping or sits on one
 pub fn discover_device<P: AsRef<Path>>(mount_path: P) -> Result<DeviceInfo> {
     let mount_path = mount_path.as_ref();
     let mut info = DeviceInfo::new();
@@ -57,7 +61,7 @@ pub fn discover_device<P: AsRef<Path>>(mount_path: P) -> Result<DeviceInfo> {
     {
         if output.status.success() {
             let stdout = String::from_utf8_lossy(&output.stdout);
-            let parts: Vec<&str> = stdout.trim().split_whitespace().collect();
+            let parts: Vec<&str> = stdout.split_whitespace().collect();
             if parts.len() >= 2 {
                 info.device_path = Some(parts[0].to_string());
                 info.fs_type = Some(parts[1].to_string());
@@ -91,9 +95,9 @@ pub fn discover_device<P: AsRef<Path>>(mount_path: P) -> Result<DeviceInfo> {
         {
             if output.status.success() {
                 let stdout = String::from_utf8_lossy(&output.stdout);
-                let parts: Vec<&str> = stdout.trim().split_whitespace().collect();
+                let parts: Vec<&str> = stdout.split_whitespace().collect();
                 if !parts.is_empty() {
-                    if let Ok(size) = parse_size(parts[0]) {
+                    if let Ok(size) = crate::util::units::parse_bytes(parts[0]) {
                         info.total_bytes = Some(size);
                     }
                     if parts.len() > 1 {
@@ -106,6 +110,19 @@ pub fn discover_device<P: AsRef<Path>>(mount_path: P) -> Result<DeviceInfo> {
             }
         }
 
+        if let Ok(output) = Command::new("lsblk")
+            .arg("-s")
+            .arg("-n")
+            .arg("-o")
+            .arg("TYPE,FSTYPE")
+            .arg(device_path)
+            .output()
+        {
+            if output.status.success() {
+                info.is_encrypted = is_encrypted_stack(&String::from_utf8_lossy(&output.stdout));
+            }
+        }
+
         let device_name = device_path
             .trim_start_matches("/dev/")
             .replace('/', "-");
@@ -132,6 +149,17 @@ pub fn discover_device<P: AsRef<Path>>(mount_path: P) -> Result<DeviceInfo> {
     Ok(info)
 }
 
+/// Whether `lsblk -s -o TYPE,FSTYPE` output, a device followed by the devices
+/// it is built on, includes a dm-crypt mapping or a LUKS container.
+fn is_encrypted_stack(lsblk: &str) -> bool {
+    lsblk.lines().any(|line| {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next();
+        let fs_type = fields.next();
+        kind == Some("crypt") || fs_type == Some("crypto_LUKS")
+    })
+}
+
 /// Discovers rclone remote information
 ///
 /// Queries `rclone about <remote>:` for backend information
@@ -169,26 +197,137 @@ pub fn discover_rclone_remote(remote: &str) -> Result<DeviceInfo> {
     Ok(info)
 }
 
-fn parse_size(size_str: &str) -> Result<i64> {
-    let size_str = size_str.trim().to_uppercase();
-    let multiplier = if size_str.ends_with('K') {
-        1024
-    } else if size_str.ends_with('M') {
-        1024 * 1024
-    } else if size_str.ends_with('G') {
-        1024 * 1024 * 1024
-    } else if size_str.ends_with('T') {
-        1024_i64 * 1024 * 1024 * 1024
-    } else {
-        1
+/// Whether access times on the filesystem holding `path` can be trusted.
+///
+/// `noatime` mounts never update atime. `relatime`, the Linux default, updates
+/// it at most once a day, which is precise enough for "unused for N days".
+pub fn atime_is_reliable<P: AsRef<Path>>(path: P) -> bool {
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return false;
+    };
+    let path = path.as_ref();
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    mount_options_for(&mounts, &path)
+        .is_some_and(|options| !options.split(',').any(|o| o == "noatime"))
+}
+
+/// Kind of storage behind a filesystem, for tuning how files are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceClass {
+    Nvme,
+    /// Non-rotational, but not NVMe: SATA SSDs, SD cards, most USB sticks.
+    Ssd,
+    Hdd,
+    /// Network filesystems, device mapper stacks that hide the disk, tmpfs.
+    Unknown,
+}
+
+impl DeviceClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceClass::Nvme => "nvme",
+            DeviceClass::Ssd => "ssd",
+            DeviceClass::Hdd => "hdd",
+            DeviceClass::Unknown => "unknown",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "nvme" => Ok(DeviceClass::Nvme),
+            "ssd" => Ok(DeviceClass::Ssd),
+            "hdd" => Ok(DeviceClass::Hdd),
+            _ => Err(OrdneError::Config(format!(
+                "Invalid device class '{}'. Use 'nvme', 'ssd' or 'hdd'",
+                value
+            ))),
+        }
+    }
+}
+
+/// Class of the block device holding `path`.
+pub fn device_class_of_path<P: AsRef<Path>>(path: P) -> DeviceClass {
+    use std::os::unix::fs::MetadataExt;
+
+    std::fs::metadata(path).map_or(DeviceClass::Unknown, |m| device_class_of(m.dev()))
+}
+
+/// Class of the block device with number `dev` (`st_dev`), from sysfs.
+/// Partitions take the class of their disk.
+pub fn device_class_of(dev: u64) -> DeviceClass {
+    let (major, minor) = (libc::major(dev), libc::minor(dev));
+    let sys = Path::new("/sys/dev/block").join(format!("{}:{}", major, minor));
+    let Ok(resolved) = sys.canonicalize() else {
+        return DeviceClass::Unknown;
     };
+    let name = resolved.file_name().and_then(|n| n.to_str()).unwrap_or_default().to_string();
+
+    let rotational = [resolved.join("queue/rotational"), resolved.join("../queue/rotational")]
+        .iter()
+        .find_map(|p| std::fs::read_to_string(p).ok());
+    classify_block_device(&name, rotational.as_deref())
+}
+
+fn classify_block_device(name: &str, rotational: Option<&str>) -> DeviceClass {
+    match rotational.map(str::trim) {
+        _ if name.starts_with("nvme") => DeviceClass::Nvme,
+        Some("1") => DeviceClass::Hdd,
+        Some("0") if !name.starts_with("dm-") && !name.starts_with("loop") => DeviceClass::Ssd,
+        _ => DeviceClass::Unknown,
+    }
+}
+
+/// Where the filesystem with `uuid` is mounted right now, if anywhere.
+///
+/// Resolves `/dev/disk/by-uuid/<uuid>` to its block device and looks that
+/// device up in `/proc/self/mounts`.
+pub fn find_mount_by_uuid(uuid: &str) -> Option<String> {
+    let device = Path::new("/dev/disk/by-uuid").join(uuid).canonicalize().ok()?;
+    let mounts = std::fs::read_to_string("/proc/self/mounts").ok()?;
+    mount_target_for_device(&mounts, &device)
+}
 
-    let number_part = size_str
-        .trim_end_matches(|c: char| !c.is_numeric() && c != '.')
-        .parse::<f64>()
-        .map_err(|e| OrdneError::Config(format!("Invalid size format: {}", e)))?;
+/// First mount point of `device` in `/proc/self/mounts` contents.
+fn mount_target_for_device(mounts: &str, device: &Path) -> Option<String> {
+    mounts.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let source = Path::new(fields.next()?);
+        let target = fields.next()?;
+        let source = source.canonicalize().unwrap_or_else(|_| source.to_path_buf());
+        (source == device).then(|| target.replace("\\040", " "))
+    })
+}
+
+/// Device or other source mounted at the innermost mount holding `path`,
+/// e.g. `/dev/sdb1`.
+pub fn mount_source_of<P: AsRef<Path>>(path: P) -> Option<String> {
+    let mounts = std::fs::read_to_string("/proc/self/mounts").ok()?;
+    let path = path.as_ref();
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    mount_entry_for(&mounts, &path).map(|(source, _)| source.to_string())
+}
 
-    Ok((number_part * multiplier as f64) as i64)
+/// Options of the innermost mount containing `path`, from `/proc/self/mounts`.
+fn mount_options_for<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    mount_entry_for(mounts, path).map(|(_, options)| options)
+}
+
+/// Source and options of the innermost mount in `/proc/self/mounts` holding `path`.
+fn mount_entry_for<'a>(mounts: &'a str, path: &Path) -> Option<(&'a str, &'a str)> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let source = fields.next()?;
+            let target = fields.next()?;
+            let options = fields.nth(1)?;
+            Some((target.replace("\\040", " "), source, options))
+        })
+        .filter(|(target, _, _)| path.starts_with(target))
+        .max_by_key(|(target, _, _)| target.len())
+        .map(|(_, source, options)| (source, options))
 }
 
 #[cfg(test)]
@@ -196,12 +335,53 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_parse_size() {
-        assert_eq!(parse_size("1024").unwrap(), 1024);
-        assert_eq!(parse_size("1K").unwrap(), 1024);
-        assert_eq!(parse_size("1M").unwrap(), 1024 * 1024);
-        assert_eq!(parse_size("1G").unwrap(), 1024 * 1024 * 1024);
-        assert_eq!(parse_size("1.5G").unwrap(), (1.5 * 1024.0 * 1024.0 * 1024.0) as i64);
+    fn test_mount_options_for_innermost_mount() {
+        let mounts = "/dev/sda1 / ext4 rw,relatime 0 0\n\
+                      /dev/sdb1 /mnt/my\\040disk ext4 rw,noatime 0 0\n";
+
+        assert_eq!(
+            mount_options_for(mounts, Path::new("/home/user")),
+            Some("rw,relatime")
+        );
+        assert_eq!(
+            mount_options_for(mounts, Path::new("/mnt/my disk/photos")),
+            Some("rw,noatime")
+        );
+        assert_eq!(
+            mount_entry_for(mounts, Path::new("/mnt/my disk/photos")).map(|(source, _)| source),
+            Some("/dev/sdb1")
+        );
+    }
+
+    #[test]
+    fn test_mount_target_for_device() {
+        let mounts = "/dev/sda1 / ext4 rw 0 0\n\
+                      /dev/sdz9 /media/user/My\\040Disk ext4 rw 0 0\n";
+
+        assert_eq!(
+            mount_target_for_device(mounts, Path::new("/dev/sdz9")),
+            Some("/media/user/My Disk".to_string())
+        );
+        assert_eq!(mount_target_for_device(mounts, Path::new("/dev/sdq1")), None);
+    }
+
+    #[test]
+    fn test_classify_block_device() {
+        assert_eq!(classify_block_device("nvme0n1p2", Some("0\n")), DeviceClass::Nvme);
+        assert_eq!(classify_block_device("sda1", Some("1\n")), DeviceClass::Hdd);
+        assert_eq!(classify_block_device("sdb", Some("0\n")), DeviceClass::Ssd);
+        assert_eq!(classify_block_device("dm-0", Some("0\n")), DeviceClass::Unknown);
+        assert_eq!(classify_block_device("sdc", None), DeviceClass::Unknown);
+    }
+
+    #[test]
+    fn test_is_encrypted_stack() {
+        // A filesystem on LUKS: the mapping, then the partition holding it.
+        assert!(is_encrypted_stack("crypt ext4\npart crypto_LUKS\ndisk\n"));
+        // LVM on LUKS.
+        assert!(is_encrypted_stack("lvm   xfs\ncrypt LVM2_member\npart  crypto_LUKS\ndisk\n"));
+        assert!(!is_encrypted_stack("part ext4\ndisk\n"));
+        assert!(!is_encrypted_stack(""));
     }
 
     #[test]
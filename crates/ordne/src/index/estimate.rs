@@ -0,0 +1,205 @@
+//! Estimating duplicate waste on a drive without hashing every file.
+//!
+//! Files can only be duplicates of files with the same size, so grouping by
+//! size gives an upper bound on the bytes duplicates waste. A random sample of
+//! those size groups is then checked with partial hashes (the head and tail of
+//! each file, see [`hash_file_sample`]) and the share of candidate bytes that
+//! turned out to be duplicated is scaled up to the whole drive. The interval
+//! is the usual ratio-estimator confidence interval for sampling groups
+//! without replacement.
+
+use crate::db::files::list_files_by_drive;
+use crate::db::{File, SqliteDatabase};
+use crate::error::Result;
+use crate::index::hasher::hash_file_sample;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// z-score of a two-sided 95% confidence interval.
+pub(crate) const Z_95: f64 = 1.96;
+
+#[derive(Debug, Clone, Copy)]
+pub struct EstimateOptions {
+    /// Size groups to check. All groups are checked when there are fewer.
+    pub sample_groups: usize,
+    /// Bytes read from the start and from the end of each sampled file.
+    pub sample_bytes: u64,
+}
+
+impl Default for EstimateOptions {
+    fn default() -> Self {
+        Self {
+            sample_groups: 200,
+            sample_bytes: 64 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DedupEstimate {
+    pub files: u64,
+    pub total_bytes: i64,
+    /// Sizes shared by more than one file.
+    pub candidate_groups: usize,
+    /// Waste if every file sharing a size were a duplicate; the upper bound.
+    pub candidate_bytes: i64,
+    pub sampled_groups: usize,
+    pub files_sampled: u64,
+    pub bytes_read: u64,
+    pub estimated_waste_bytes: i64,
+    /// 95% confidence interval of the waste.
+    pub lower_bound_bytes: i64,
+    pub upper_bound_bytes: i64,
+    /// Every candidate group was checked, so the interval is only as wide as
+    /// partial hashes are uncertain.
+    pub exact: bool,
+}
+
+/// Estimate the bytes duplicates waste on `drive_id` from sizes and a sample
+/// of partial hashes. Files that cannot be read count as unique.
+pub fn estimate_duplicate_waste(
+    db: &SqliteDatabase,
+    drive_id: i64,
+    options: EstimateOptions,
+) -> Result<DedupEstimate> {
+    let mut estimate = DedupEstimate::default();
+    let mut by_size: BTreeMap<i64, Vec<File>> = BTreeMap::new();
+    for file in list_files_by_drive(db.conn(), drive_id)? {
+        if file.is_symlink || file.size_bytes <= 0 {
+            continue;
+        }
+        estimate.files += 1;
+        estimate.total_bytes += file.size_bytes;
+        by_size.entry(file.size_bytes).or_default().push(file);
+    }
+
+    let mut groups: Vec<(i64, Vec<File>)> = by_size.into_iter().filter(|(_, files)| files.len() > 1).collect();
+    estimate.candidate_groups = groups.len();
+    estimate.candidate_bytes = groups.iter().map(|(size, files)| candidate_waste(*size, files)).sum();
+    if groups.is_empty() {
+        estimate.exact = true;
+        return Ok(estimate);
+    }
+
+    // A fixed pseudo-random order, so repeated estimates read the same files.
+    groups.sort_by_cached_key(|(size, _)| *blake3::hash(&size.to_le_bytes()).as_bytes());
+    groups.truncate(options.sample_groups.max(1));
+    estimate.sampled_groups = groups.len();
+    estimate.exact = estimate.sampled_groups == estimate.candidate_groups;
+
+    let mut samples = Vec::with_capacity(groups.len());
+    for (size, files) in &groups {
+        let waste = sampled_waste(*size, files, options.sample_bytes, &mut estimate);
+        samples.push((candidate_waste(*size, files) as f64, waste as f64));
+    }
+
+    let (estimated, margin) = ratio_estimate(
+        &samples,
+        estimate.candidate_groups,
+        estimate.candidate_bytes as f64,
+    );
+    let upper = estimate.candidate_bytes as f64;
+    estimate.estimated_waste_bytes = estimated.clamp(0.0, upper) as i64;
+    estimate.lower_bound_bytes = (estimated - margin).clamp(0.0, upper) as i64;
+    estimate.upper_bound_bytes = (estimated + margin).clamp(0.0, upper) as i64;
+    Ok(estimate)
+}
+
+fn candidate_waste(size: i64, files: &[File]) -> i64 {
+    size * (files.len() as i64 - 1)
+}
+
+/// Waste within one size group: every file beyond the first per content.
+/// Catalogued blake3 hashes are used when all files have one.
+fn sampled_waste(size: i64, files: &[File], sample_bytes: u64, estimate: &mut DedupEstimate) -> i64 {
+    let catalogued: Option<Vec<String>> = files.iter().map(|f| f.blake3_hash.clone()).collect();
+    let keys = catalogued.unwrap_or_else(|| {
+        files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| {
+                estimate.files_sampled += 1;
+                estimate.bytes_read += (sample_bytes * 2).min(size as u64);
+                hash_file_sample(&file.abs_path, sample_bytes).unwrap_or_else(|e| {
+                    log::debug!("Could not sample {}: {}", file.abs_path, e);
+                    format!("unreadable:{}", i)
+                })
+            })
+            .collect()
+    });
+
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    for key in keys {
+        *counts.entry(key).or_default() += 1;
+    }
+    counts.values().map(|n| size * (n - 1)).sum()
+}
+
+/// Ratio estimate of total waste and its 95% margin, from `(candidate, waste)`
+/// pairs sampled out of `population` groups whose candidates sum to `total`.
+fn ratio_estimate(samples: &[(f64, f64)], population: usize, total: f64) -> (f64, f64) {
+    let n = samples.len() as f64;
+    let candidates: f64 = samples.iter().map(|(c, _)| c).sum();
+    let waste: f64 = samples.iter().map(|(_, w)| w).sum();
+    if candidates == 0.0 {
+        return (0.0, 0.0);
+    }
+    let ratio = waste / candidates;
+    let estimated = ratio * total;
+    if samples.len() >= population {
+        return (estimated, 0.0);
+    }
+    if samples.len() < 2 {
+        return (estimated, total);
+    }
+
+    let residuals: f64 = samples.iter().map(|(c, w)| (w - ratio * c).powi(2)).sum::<f64>() / (n - 1.0);
+    let big_n = population as f64;
+    let variance = big_n * big_n * (1.0 - n / big_n) * residuals / n;
+    (estimated, Z_95 * variance.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_estimate_counts_sampled_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute(
+                "INSERT INTO drives (id, label, role, is_online, backend, mount_path) VALUES (1, 'data', 'source', 1, 'local', ?1)",
+                [dir.path().to_str().unwrap()],
+            )
+            .unwrap();
+
+        // Two copies of one file, a same-sized file with other content and a unique size.
+        let files = [("a", "duplicate"), ("b", "duplicate"), ("c", "different"), ("d", "unique size")];
+        for (id, (name, content)) in files.iter().enumerate() {
+            std::fs::write(dir.path().join(name), content).unwrap();
+            db.conn()
+                .execute(
+                    "INSERT INTO files (id, drive_id, path, filename, size_bytes) VALUES (?1, 1, ?2, ?2, ?3)",
+                    rusqlite::params![id as i64 + 1, name, content.len() as i64],
+                )
+                .unwrap();
+        }
+
+        let estimate = estimate_duplicate_waste(&db, 1, EstimateOptions::default()).unwrap();
+        assert_eq!(estimate.candidate_groups, 1);
+        assert_eq!(estimate.candidate_bytes, 18);
+        assert_eq!(estimate.files_sampled, 3);
+        assert!(estimate.exact);
+        assert_eq!(estimate.estimated_waste_bytes, 9);
+        assert_eq!((estimate.lower_bound_bytes, estimate.upper_bound_bytes), (9, 9));
+
+        // Half the candidate bytes of a sample are waste: the estimate scales
+        // to the population and the interval widens with residual variance.
+        let (estimated, margin) = ratio_estimate(&[(100.0, 100.0), (100.0, 0.0)], 10, 1000.0);
+        assert_eq!(estimated, 500.0);
+        assert!(margin > 0.0 && margin < 1000.0);
+    }
+}
@@ -0,0 +1,146 @@
+//! gitignore-style exclusions for scans.
+//!
+//! Patterns come from `[scan] exclude` in a drive's `.ordne/ordne.toml`, or
+//! `[dedup] ignore` in `~/.config/ordne/ordne.toml` for files left out of
+//! duplicate groups, and match paths relative to the drive root:
+//!
+//! - `*.tmp` (no `/`) matches a name at any depth
+//! - `/scratch` or `cache/thumbs` (with a `/`) is anchored to the drive root
+//! - a trailing `/` (`node_modules/`) matches directories only
+//! - a leading `!` re-includes something an earlier pattern excluded
+//!
+//! The last matching pattern wins. An excluded directory is not walked, so
+//! nothing below it can be re-included.
+
+use crate::error::{OrdneError, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    negated: bool,
+    dir_only: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeRules {
+    set: Option<GlobSet>,
+    patterns: Vec<Pattern>,
+}
+
+impl ExcludeRules {
+    pub fn new<I, S>(patterns: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = GlobSetBuilder::new();
+        let mut parsed = Vec::new();
+
+        for raw in patterns {
+            let raw = raw.as_ref().trim();
+            if raw.is_empty() || raw.starts_with('#') {
+                continue;
+            }
+            let (negated, rest) = match raw.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, raw),
+            };
+            let (dir_only, rest) = match rest.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, rest),
+            };
+            let glob = if rest.contains('/') {
+                rest.trim_start_matches('/').to_string()
+            } else {
+                format!("**/{}", rest)
+            };
+
+            let glob = GlobBuilder::new(&glob)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| OrdneError::Config(format!("Invalid exclude pattern '{}': {}", raw, e)))?;
+            builder.add(glob);
+            parsed.push(Pattern { negated, dir_only });
+        }
+
+        if parsed.is_empty() {
+            return Ok(Self::default());
+        }
+        let set = builder
+            .build()
+            .map_err(|e| OrdneError::Config(format!("Failed to build exclude patterns: {}", e)))?;
+        Ok(Self { set: Some(set), patterns: parsed })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Whether `path`, relative to the drive root, should be skipped.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let Some(set) = &self.set else {
+            return false;
+        };
+        set.matches(path)
+            .into_iter()
+            .rev()
+            .map(|i| &self.patterns[i])
+            .find(|pattern| is_dir || !pattern.dir_only)
+            .is_some_and(|pattern| !pattern.negated)
+    }
+
+    /// Whether a file at `path` is excluded, by itself or by one of its
+    /// directories, for callers that look at catalogued files instead of
+    /// walking the tree.
+    pub fn excludes_file(&self, path: &Path) -> bool {
+        if self.set.is_none() {
+            return false;
+        }
+        let mut dirs: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .filter(|dir| !dir.as_os_str().is_empty() && dir.parent().is_some())
+            .collect();
+        dirs.reverse();
+        dirs.into_iter().any(|dir| self.is_excluded(dir, true)) || self.is_excluded(path, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_style_patterns() {
+        let rules = ExcludeRules::new([
+            "# build output",
+            "*.tmp",
+            "node_modules/",
+            "/scratch",
+            "photos/cache/*",
+            "!keep.tmp",
+        ])
+        .unwrap();
+
+        assert!(rules.is_excluded(Path::new("a.tmp"), false));
+        assert!(rules.is_excluded(Path::new("deep/down/b.tmp"), false));
+        assert!(!rules.is_excluded(Path::new("deep/keep.tmp"), false));
+
+        assert!(rules.is_excluded(Path::new("web/node_modules"), true));
+        assert!(!rules.is_excluded(Path::new("web/node_modules"), false));
+
+        assert!(rules.is_excluded(Path::new("scratch"), true));
+        assert!(!rules.is_excluded(Path::new("work/scratch"), true));
+
+        assert!(rules.is_excluded(Path::new("photos/cache/x.jpg"), false));
+        assert!(!rules.is_excluded(Path::new("photos/cache/sub/x.jpg"), false));
+        assert!(!rules.is_excluded(Path::new("photos/x.jpg"), false));
+
+        assert!(rules.excludes_file(Path::new("web/node_modules/pkg/index.js")));
+        assert!(!rules.excludes_file(Path::new("web/src/index.js")));
+
+        assert!(ExcludeRules::new(["[unclosed"]).is_err());
+        assert!(ExcludeRules::default().is_empty());
+    }
+}
@@ -0,0 +1,244 @@
+//! File lists curated outside ordne, as the selection for a plan.
+//!
+//! A list is plain text with one path per line, or a CSV (by its `.csv`
+//! extension) whose `path` column holds them, the first column when no
+//! header names one. Paths may be absolute, under a drive's mount path, or
+//! relative to a drive root; relative paths are looked up on the given drive,
+//! or on every drive when none is given. Blank lines and `#` comments are
+//! skipped. Lines that match no catalogued file are returned with the reason,
+//! so an edited spreadsheet never silently drops files from a plan.
+
+use crate::db::files::{get_file_by_abs_path, get_files_by_path};
+use crate::db::{Drive, File, FileStatus, SqliteDatabase};
+use crate::error::{OrdneError, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnmatchedLine {
+    /// 1-based line number in the list.
+    pub line: usize,
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FileListResult {
+    /// Catalogued files, in list order, each once.
+    pub files: Vec<File>,
+    pub unmatched: Vec<UnmatchedLine>,
+    /// Lines naming a file listed earlier.
+    pub repeated: usize,
+}
+
+/// Read the list at `path` and resolve its lines against the catalog,
+/// relative paths against `drive` when given.
+pub fn resolve_file_list<P: AsRef<Path>>(
+    db: &SqliteDatabase,
+    path: P,
+    drive: Option<&Drive>,
+) -> Result<FileListResult> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)?;
+    let csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let entries = if csv {
+        csv_paths(&content)?
+    } else {
+        text_paths(&content)
+    };
+    if entries.is_empty() {
+        return Err(OrdneError::UserInput(format!(
+            "{} lists no paths",
+            path.display()
+        )));
+    }
+
+    let mut result = FileListResult::default();
+    let mut seen = HashSet::new();
+    for (line, listed) in entries {
+        match resolve_path(db, &listed, drive)? {
+            Ok(file) if !seen.insert(file.id) => result.repeated += 1,
+            Ok(file) => result.files.push(file),
+            Err(reason) => result.unmatched.push(UnmatchedLine {
+                line,
+                path: listed,
+                reason,
+            }),
+        }
+    }
+    Ok(result)
+}
+
+/// The catalogued file for one listed path, or why there is none.
+fn resolve_path(
+    db: &SqliteDatabase,
+    listed: &str,
+    drive: Option<&Drive>,
+) -> Result<std::result::Result<File, String>> {
+    let candidates = if listed.starts_with('/') {
+        get_file_by_abs_path(db.conn(), listed)?
+            .into_iter()
+            .collect()
+    } else {
+        let relative = listed.trim_start_matches("./");
+        get_files_by_path(db.conn(), relative, drive.map(|d| d.id))?
+    };
+
+    let candidates: Vec<File> = match drive {
+        Some(drive) => candidates
+            .into_iter()
+            .filter(|f| f.drive_id == drive.id)
+            .collect(),
+        None => candidates,
+    };
+    let file = match candidates.len() {
+        0 => return Ok(Err("not in the index".to_string())),
+        1 => candidates.into_iter().next().unwrap(),
+        n => {
+            return Ok(Err(format!(
+                "on {} drives; give --source-drive or an absolute path",
+                n
+            )))
+        }
+    };
+    if file.status == FileStatus::SourceRemoved {
+        return Ok(Err("already removed from its source".to_string()));
+    }
+    Ok(Ok(file))
+}
+
+fn text_paths(content: &str) -> Vec<(usize, String)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(line, path)| (line, path.to_string()))
+        .collect()
+}
+
+fn csv_paths(content: &str) -> Result<Vec<(usize, String)>> {
+    let mut rows = content
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'));
+
+    let Some((first_line, first)) = rows.next() else {
+        return Ok(Vec::new());
+    };
+    let header = split_csv_line(first, first_line)?;
+    let path_column = header
+        .iter()
+        .position(|field| field.trim().eq_ignore_ascii_case("path"));
+
+    let mut paths = Vec::new();
+    if path_column.is_none() {
+        if let Some(path) = header.into_iter().next().filter(|p| !p.is_empty()) {
+            paths.push((first_line, path));
+        }
+    }
+    let column = path_column.unwrap_or(0);
+    for (line, row) in rows {
+        let fields = split_csv_line(row, line)?;
+        match fields.into_iter().nth(column).filter(|p| !p.is_empty()) {
+            Some(path) => paths.push((line, path)),
+            None => {
+                return Err(OrdneError::UserInput(format!(
+                    "Line {} has no path in column {}",
+                    line,
+                    column + 1
+                )))
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// Split one CSV record, honouring double quotes and `""` escapes.
+fn split_csv_line(line: &str, number: usize) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(OrdneError::UserInput(format!(
+            "Line {} has an unterminated quoted field",
+            number
+        )));
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_file_list_resolves_paths_and_reports_unmatched_lines() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, mount_path, role, is_online, backend) VALUES (1, 'nas', '/mnt/nas', 'source', 1, 'local');
+                 INSERT INTO drives (id, label, mount_path, role, is_online, backend) VALUES (2, 'usb', '/mnt/usb', 'backup', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes, status)
+                 VALUES (1, 1, 'photos/a, b.jpg', 'a, b.jpg', 10, 'indexed'),
+                        (2, 1, 'docs/c.pdf', 'c.pdf', 10, 'indexed'),
+                        (3, 2, 'docs/c.pdf', 'c.pdf', 10, 'indexed'),
+                        (4, 1, 'old.txt', 'old.txt', 10, 'source_removed');",
+            )
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let list = dir.path().join("paths.txt");
+        std::fs::write(
+            &list,
+            "# picked by hand\n/mnt/nas/photos/a, b.jpg\n\n./docs/c.pdf\nold.txt\nmissing.txt\nphotos/a, b.jpg\n",
+        )
+        .unwrap();
+        let result = resolve_file_list(&db, &list, None).unwrap();
+        assert_eq!(
+            result.files.iter().map(|f| f.id).collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert_eq!(result.repeated, 1);
+        let reasons: Vec<(usize, &str)> = result
+            .unmatched
+            .iter()
+            .map(|u| (u.line, u.reason.as_str()))
+            .collect();
+        assert_eq!(reasons[0].0, 4);
+        assert!(reasons[0].1.starts_with("on 2 drives"));
+        assert_eq!(reasons[1], (5, "already removed from its source"));
+        assert_eq!(reasons[2], (6, "not in the index"));
+
+        // A drive settles relative paths; CSV lists take the path column.
+        let nas = db.get_drive("nas").unwrap().unwrap();
+        let csv = dir.path().join("picks.csv");
+        std::fs::write(&csv, "size,path\n10,docs/c.pdf\n10,\"photos/a, b.jpg\"\n").unwrap();
+        let result = resolve_file_list(&db, &csv, Some(&nas)).unwrap();
+        assert_eq!(
+            result.files.iter().map(|f| f.id).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+        assert!(result.unmatched.is_empty());
+    }
+}
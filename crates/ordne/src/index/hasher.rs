@@ -1,52 +1,338 @@
+use crate::db::HashAlgorithm;
 use crate::error::{OrdneError, Result};
+use crate::index::device::{device_class_of, DeviceClass};
 use md5::{Digest, Md5};
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::Read;
+use std::os::unix::io::AsRawFd;
 use std::path::Path;
+use std::sync::{Mutex, RwLock};
 
-const BUFFER_SIZE: usize = 8192;
+/// Alignment `O_DIRECT` requires of buffers, offsets and lengths on Linux.
+const DIRECT_IO_ALIGN: usize = 4096;
 
-/// Computes MD5 hash of a file
-///
-/// Uses streaming implementation for memory efficiency with large files.
-/// Reads the file in 8KB chunks to minimize memory usage.
-pub fn hash_file_md5<P: AsRef<Path>>(path: P) -> Result<String> {
+/// How file contents are read for hashing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashSettings {
+    /// Bytes read per call, and fed to the hasher at a time.
+    pub buffer_size: usize,
+    /// Map files into memory instead of reading them.
+    pub mmap: bool,
+    /// Bypass the page cache with `O_DIRECT`, where the filesystem allows it.
+    pub direct_io: bool,
+}
+
+impl Default for HashSettings {
+    fn default() -> Self {
+        Self {
+            buffer_size: 8192,
+            mmap: false,
+            direct_io: false,
+        }
+    }
+}
+
+impl HashSettings {
+    pub fn label(&self) -> String {
+        let mode = if self.mmap {
+            "mmap"
+        } else if self.direct_io {
+            "direct"
+        } else {
+            "buffered"
+        };
+        format!("{} {}", mode, crate::util::format::format_bytes(self.buffer_size as i64))
+    }
+}
+
+/// [`HashSettings`] from `[hashing]` in `ordne.toml`, optionally different
+/// per [`DeviceClass`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HashTuning {
+    pub default: HashSettings,
+    pub per_class: HashMap<DeviceClass, HashSettings>,
+}
+
+impl HashTuning {
+    pub fn for_class(&self, class: DeviceClass) -> HashSettings {
+        self.per_class.get(&class).copied().unwrap_or(self.default)
+    }
+}
+
+static TUNING: RwLock<Option<HashTuning>> = RwLock::new(None);
+/// Device class per `st_dev`, so sysfs is read once per filesystem.
+static DEVICE_CLASSES: Mutex<Option<HashMap<u64, DeviceClass>>> = Mutex::new(None);
+
+/// Use `tuning` for every hash computed from now on.
+pub fn configure(tuning: HashTuning) {
+    *TUNING.write().unwrap_or_else(|e| e.into_inner()) = Some(tuning);
+}
+
+/// Settings for hashing `file`, by the class of the device it is on.
+fn settings_for(file: &File) -> HashSettings {
+    use std::os::unix::fs::MetadataExt;
+
+    let tuning = TUNING.read().unwrap_or_else(|e| e.into_inner());
+    let Some(tuning) = tuning.as_ref() else {
+        return HashSettings::default();
+    };
+    if tuning.per_class.is_empty() {
+        return tuning.default;
+    }
+    let Ok(dev) = file.metadata().map(|m| m.dev()) else {
+        return tuning.default;
+    };
+    let mut classes = DEVICE_CLASSES.lock().unwrap_or_else(|e| e.into_inner());
+    let class = *classes.get_or_insert_with(HashMap::new).entry(dev).or_insert_with(|| device_class_of(dev));
+    tuning.for_class(class)
+}
+
+/// Open a file for hashing without bumping its access time, so hashing does
+/// not make every file look recently used. `O_NOATIME` is only allowed for
+/// the file's owner; anyone else falls back to a plain open.
+fn open_for_hashing(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOATIME)
+        .open(path)
+        .or_else(|_| File::open(path))
+}
+
+enum Hasher {
+    Md5(Md5),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    fn finalize(self) -> String {
+        match self {
+            Hasher::Md5(h) => format!("{:x}", h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Size and modification time of a file, to tell whether it was written to
+/// between two points in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub len: u64,
+    pub mtime: (i64, i64),
+}
+
+impl FileStamp {
+    pub fn of(metadata: &std::fs::Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        Self {
+            len: metadata.len(),
+            mtime: (metadata.mtime(), metadata.mtime_nsec()),
+        }
+    }
+
+    /// Error with [`OrdneError::FileChanged`] if `path` no longer has this stamp.
+    pub fn check_unchanged(&self, path: &Path) -> Result<()> {
+        let current = std::fs::metadata(path).map(|m| Self::of(&m));
+        if current.ok() != Some(*self) {
+            return Err(OrdneError::FileChanged { path: path.to_path_buf() });
+        }
+        Ok(())
+    }
+}
+
+/// Hash an open file with the configured [`HashSettings`] for its device.
+pub(crate) fn digest_file(path: &Path, file: File, algorithm: HashAlgorithm) -> Result<String> {
+    let settings = settings_for(&file);
+    digest_file_with(path, file, algorithm, &settings, &mut |_| {})
+}
+
+/// Hash `path` with explicit settings, for comparing them.
+pub fn hash_file_with<P: AsRef<Path>>(path: P, algorithm: HashAlgorithm, settings: &HashSettings) -> Result<String> {
     let path = path.as_ref();
-    let file = File::open(path).map_err(|_e| OrdneError::FileNotFound(path.to_path_buf()))?;
-    let mut reader = BufReader::with_capacity(BUFFER_SIZE, file);
-    let mut hasher = Md5::new();
-    let mut buffer = [0u8; BUFFER_SIZE];
+    let file = open_for_hashing(path).map_err(|_| OrdneError::FileNotFound(path.to_path_buf()))?;
+    digest_file_with(path, file, algorithm, settings, &mut |_| {})
+}
+
+/// Hash `file`, opened from `path`. A file written to while it is read (an
+/// active download, say) has no meaningful hash, so a change in size or
+/// modification time between the start and the end of the read is an
+/// [`OrdneError::FileChanged`] error rather than a hash.
+fn digest_file_with(
+    path: &Path,
+    mut file: File,
+    algorithm: HashAlgorithm,
+    settings: &HashSettings,
+    progress: &mut dyn FnMut(usize),
+) -> Result<String> {
+    let before = FileStamp::of(&file.metadata()?);
+    let mut hasher = Hasher::new(algorithm);
+    read_chunks(&mut file, settings, &mut |chunk| {
+        hasher.update(chunk);
+        crate::util::progress::add_bytes_processed(chunk.len() as u64);
+        progress(chunk.len());
+    })?;
+    if FileStamp::of(&file.metadata()?) != before {
+        return Err(OrdneError::FileChanged { path: path.to_path_buf() });
+    }
+    Ok(hasher.finalize())
+}
+
+/// Feed the contents of `file` to `update` in chunks of at most
+/// `settings.buffer_size`. Mapping or `O_DIRECT` fall back to plain reads
+/// where the file or filesystem does not support them.
+fn read_chunks(file: &mut File, settings: &HashSettings, update: &mut dyn FnMut(&[u8])) -> Result<()> {
+    let buffer_size = settings.buffer_size.max(DIRECT_IO_ALIGN);
+
+    if settings.mmap {
+        let len = file.metadata()?.len() as usize;
+        if len > 0 {
+            if let Ok(mapping) = Mapping::new(file, len) {
+                mapping.as_slice().chunks(buffer_size).for_each(update);
+                return Ok(());
+            }
+        }
+    }
+
+    let mut direct = settings.direct_io && set_direct_io(file, true);
+    // O_DIRECT needs an aligned buffer and aligned read lengths.
+    let mut storage = vec![0u8; buffer_size.next_multiple_of(DIRECT_IO_ALIGN) + DIRECT_IO_ALIGN];
+    let offset = storage.as_ptr().align_offset(DIRECT_IO_ALIGN);
+    let buffer = &mut storage[offset..offset + buffer_size.next_multiple_of(DIRECT_IO_ALIGN)];
+    let buffer = if direct { buffer } else { &mut buffer[..buffer_size] };
 
     loop {
-        let bytes_read = reader.read(&mut buffer)?;
+        let bytes_read = match file.read(buffer) {
+            Ok(n) => n,
+            // Some filesystems accept the flag but refuse the reads.
+            Err(e) if direct && e.raw_os_error() == Some(libc::EINVAL) => {
+                direct = false;
+                set_direct_io(file, false);
+                continue;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
         if bytes_read == 0 {
             break;
         }
-        hasher.update(&buffer[..bytes_read]);
+        update(&buffer[..bytes_read]);
     }
+    Ok(())
+}
 
-    let result = hasher.finalize();
-    Ok(format!("{:x}", result))
+fn set_direct_io(file: &File, on: bool) -> bool {
+    let fd = file.as_raw_fd();
+    // SAFETY: fcntl on a file descriptor we own; failure is reported, not UB.
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 {
+            return false;
+        }
+        let flags = if on { flags | libc::O_DIRECT } else { flags & !libc::O_DIRECT };
+        libc::fcntl(fd, libc::F_SETFL, flags) == 0
+    }
+}
+
+/// A read-only private mapping of a whole file.
+///
+/// A file truncated by another process while mapped raises SIGBUS, which is
+/// why mapping is opt-in.
+struct Mapping {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mapping {
+    fn new(file: &File, len: usize) -> std::io::Result<Self> {
+        // SAFETY: a fresh read-only mapping of `len` bytes of an open file;
+        // the result is checked before use.
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0)
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: advice on the mapping created above; failure is harmless.
+        unsafe { libc::madvise(ptr, len, libc::MADV_SEQUENTIAL) };
+        Ok(Self { ptr, len })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        // SAFETY: the mapping is `len` readable bytes and lives as long as `self`.
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: unmapping exactly the region mapped in `new`.
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+/// Computes MD5 hash of a file
+///
+/// Streams the file with the configured [`HashSettings`], so memory use
+/// stays at one buffer whatever the file size.
+pub fn hash_file_md5<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    let file = open_for_hashing(path).map_err(|_e| OrdneError::FileNotFound(path.to_path_buf()))?;
+    digest_file(path, file, HashAlgorithm::Md5)
 }
 
 /// Computes blake3 hash of a file
 ///
-/// Uses streaming implementation for memory efficiency with large files.
-/// Reads the file in 8KB chunks to minimize memory usage.
+/// Streams the file with the configured [`HashSettings`], so memory use
+/// stays at one buffer whatever the file size.
 pub fn hash_file_blake3<P: AsRef<Path>>(path: P) -> Result<String> {
     let path = path.as_ref();
-    let file = File::open(path).map_err(|_| OrdneError::FileNotFound(path.to_path_buf()))?;
-    let mut reader = BufReader::with_capacity(BUFFER_SIZE, file);
+    let file = open_for_hashing(path).map_err(|_| OrdneError::FileNotFound(path.to_path_buf()))?;
+    digest_file(path, file, HashAlgorithm::Blake3)
+}
+
+/// Hashes the first and last `sample_bytes` of a file with blake3.
+///
+/// Much cheaper than a full hash on large files. Files whose samples differ
+/// cannot be identical; files whose samples match very likely are, provided
+/// they also have the same size.
+pub fn hash_file_sample<P: AsRef<Path>>(path: P, sample_bytes: u64) -> Result<String> {
+    use std::io::{Seek, SeekFrom};
+
+    let path = path.as_ref();
+    let mut file = open_for_hashing(path).map_err(|_| OrdneError::FileNotFound(path.to_path_buf()))?;
+    let len = file.metadata()?.len();
     let mut hasher = blake3::Hasher::new();
-    let mut buffer = [0u8; BUFFER_SIZE];
+    let mut buffer = vec![0u8; sample_bytes.min(len) as usize];
 
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes_read]);
+    file.read_exact(&mut buffer)?;
+    hasher.update(&buffer);
+    if len > sample_bytes {
+        let tail_start = len.saturating_sub(sample_bytes).max(sample_bytes);
+        buffer.truncate((len - tail_start) as usize);
+        file.seek(SeekFrom::Start(tail_start))?;
+        file.read_exact(&mut buffer)?;
+        hasher.update(&buffer);
     }
+    crate::util::progress::add_bytes_processed(sample_bytes.saturating_mul(2).min(len));
 
     Ok(hasher.finalize().to_hex().to_string())
 }
@@ -62,25 +348,15 @@ pub fn hash_file_md5_with_progress<P: AsRef<Path>>(
     progress: ProgressCallback,
 ) -> Result<String> {
     let path = path.as_ref();
-    let file = File::open(path).map_err(|_| OrdneError::FileNotFound(path.to_path_buf()))?;
+    let file = open_for_hashing(path).map_err(|_| OrdneError::FileNotFound(path.to_path_buf()))?;
     let total_size = file.metadata()?.len();
-    let mut reader = BufReader::with_capacity(BUFFER_SIZE, file);
-    let mut hasher = Md5::new();
-    let mut buffer = [0u8; BUFFER_SIZE];
+    let settings = settings_for(&file);
     let mut bytes_processed = 0u64;
 
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes_read]);
-        bytes_processed += bytes_read as u64;
+    digest_file_with(path, file, HashAlgorithm::Md5, &settings, &mut |n| {
+        bytes_processed += n as u64;
         progress(bytes_processed, total_size);
-    }
-
-    let result = hasher.finalize();
-    Ok(format!("{:x}", result))
+    })
 }
 
 /// Verifies that a file matches an expected hash
@@ -178,6 +454,21 @@ mod tests {
         assert_eq!(hash.len(), 32);
     }
 
+    #[test]
+    fn test_read_modes_agree() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        temp_file.write_all(&data).unwrap();
+        temp_file.flush().unwrap();
+
+        let expected = blake3::hash(&data).to_hex().to_string();
+        for (mmap, direct_io) in [(false, false), (true, false), (false, true)] {
+            let settings = HashSettings { buffer_size: 65536, mmap, direct_io };
+            let hash = hash_file_with(temp_file.path(), HashAlgorithm::Blake3, &settings).unwrap();
+            assert_eq!(hash, expected, "{}", settings.label());
+        }
+    }
+
     #[test]
     fn test_hash_with_progress() {
         let mut temp_file = NamedTempFile::new().unwrap();
@@ -191,4 +482,21 @@ mod tests {
 
         assert_eq!(hash, "5eb63bbbe01eeed093cb22bb8f5acdc3");
     }
+
+    #[test]
+    fn test_file_stamp_detects_writes() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(b"partial download").unwrap();
+        temp_file.flush().unwrap();
+
+        let stamp = FileStamp::of(&temp_file.as_file().metadata().unwrap());
+        stamp.check_unchanged(temp_file.path()).unwrap();
+
+        temp_file.write_all(b" and more").unwrap();
+        temp_file.flush().unwrap();
+        assert!(matches!(
+            stamp.check_unchanged(temp_file.path()),
+            Err(OrdneError::FileChanged { .. })
+        ));
+    }
 }
@@ -0,0 +1,236 @@
+//! Catalog files from any source of metadata.
+//!
+//! [`scan_directory`](super::scan_directory) walks a local filesystem, but a
+//! custom walker, a remote listing or another inventory tool can feed the
+//! catalog the same way: collect a [`FileEntry`] per file and hand them to an
+//! [`Indexer`]. Paths are normalized to the drive root and rows are built and
+//! inserted exactly as a scan would.
+
+use super::ScanStats;
+use crate::db::{Database, File, FileStatus, Priority};
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// Metadata for one file, however it was collected.
+#[derive(Debug, Clone, Default)]
+pub struct FileEntry {
+    /// Absolute under the drive root, or already relative to it.
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub modified_at: Option<DateTime<Utc>>,
+    pub accessed_at: Option<DateTime<Utc>>,
+    pub inode: Option<u64>,
+    pub device_num: Option<u64>,
+    pub nlinks: Option<u32>,
+    pub owner_uid: Option<u32>,
+    pub is_symlink: bool,
+    pub symlink_target: Option<String>,
+    pub git_remote_url: Option<String>,
+}
+
+impl FileEntry {
+    pub fn new(path: impl Into<PathBuf>, size_bytes: u64) -> Self {
+        Self {
+            path: path.into(),
+            size_bytes,
+            ..Default::default()
+        }
+    }
+}
+
+/// Inserts [`FileEntry`] values into the catalog for one drive.
+pub struct Indexer<'a> {
+    db: &'a mut dyn Database,
+    drive_id: i64,
+    root: PathBuf,
+    stats: ScanStats,
+}
+
+impl<'a> Indexer<'a> {
+    /// Index into `drive_id`, with paths relative to the drive's mount path.
+    pub fn new(db: &'a mut dyn Database, drive_id: i64) -> Result<Self> {
+        let root = db
+            .get_drive_by_id(drive_id)?
+            .and_then(|drive| drive.mount_path)
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        Ok(Self {
+            db,
+            drive_id,
+            root,
+            stats: ScanStats::default(),
+        })
+    }
+
+    /// Strip this root from absolute entry paths instead of the mount path,
+    /// e.g. for a remote listing rooted elsewhere.
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Index a single entry.
+    pub fn add(&mut self, entry: FileEntry) -> Result<()> {
+        let file = self.file_from_entry(entry);
+        let (size_bytes, is_symlink, has_remote) =
+            (file.size_bytes, file.is_symlink, file.git_remote_url.is_some());
+
+        self.db.add_file(&file)?;
+
+        self.stats.files_scanned += 1;
+        self.stats.bytes_scanned += size_bytes as u64;
+        if is_symlink {
+            self.stats.symlinks_found += 1;
+        }
+        if has_remote {
+            self.stats.git_repos_found += 1;
+        }
+        Ok(())
+    }
+
+    /// Index every entry, stopping at the first database error.
+    pub fn index<I: IntoIterator<Item = FileEntry>>(mut self, entries: I) -> Result<ScanStats> {
+        for entry in entries {
+            self.add(entry)?;
+        }
+        Ok(self.finish())
+    }
+
+    pub fn stats(&self) -> &ScanStats {
+        &self.stats
+    }
+
+    pub(crate) fn stats_mut(&mut self) -> &mut ScanStats {
+        &mut self.stats
+    }
+
+    pub fn finish(self) -> ScanStats {
+        self.stats
+    }
+
+    pub(crate) fn relative_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn file_from_entry(&self, entry: FileEntry) -> File {
+        let relative_path = self.relative_path(&entry.path);
+        let abs_path = if entry.path.is_absolute() {
+            entry.path.clone()
+        } else {
+            self.root.join(&entry.path)
+        };
+
+        let filename = entry
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = entry
+            .path
+            .extension()
+            .map(|e| e.to_string_lossy().to_string());
+
+        File {
+            id: 0,
+            drive_id: self.drive_id,
+            path: relative_path,
+            abs_path: abs_path.to_string_lossy().to_string(),
+            filename,
+            extension,
+            size_bytes: if entry.is_symlink { 0 } else { entry.size_bytes as i64 },
+            md5_hash: None,
+            blake3_hash: None,
+            created_at: None,
+            modified_at: entry.modified_at,
+            accessed_at: entry.accessed_at,
+            inode: entry.inode.map(|i| i as i64),
+            device_num: entry.device_num.map(|d| d as i64),
+            nlinks: entry.nlinks.map(|n| n as i32),
+            owner_uid: entry.owner_uid.map(i64::from),
+            mime_type: None,
+            is_symlink: entry.is_symlink,
+            symlink_target: entry.symlink_target,
+            git_remote_url: entry.git_remote_url,
+            category: None,
+            subcategory: None,
+            target_path: None,
+            target_drive_id: None,
+            priority: Priority::Normal,
+            duplicate_group: None,
+            is_original: false,
+            rmlint_type: None,
+            status: FileStatus::Indexed,
+            migrated_to: None,
+            migrated_to_drive: None,
+            migrated_at: None,
+            verified_hash: None,
+            error: None,
+            indexed_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SqliteDatabase;
+
+    #[test]
+    fn test_index_entries_from_a_listing() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute(
+                "INSERT INTO drives (id, label, mount_path, role, is_online, backend)
+                 VALUES (1, 'nas', '/mnt/nas', 'source', 1, 'local')",
+                [],
+            )
+            .unwrap();
+
+        let listing = vec![
+            FileEntry::new("/mnt/nas/photos/a.jpg", 100),
+            FileEntry::new("docs/b.pdf", 50),
+            FileEntry {
+                is_symlink: true,
+                symlink_target: Some("photos/a.jpg".to_string()),
+                ..FileEntry::new("/mnt/nas/latest.jpg", 100)
+            },
+        ];
+        let stats = Indexer::new(&mut db, 1).unwrap().index(listing).unwrap();
+        assert_eq!(stats.files_scanned, 3);
+        assert_eq!(stats.bytes_scanned, 150);
+        assert_eq!(stats.symlinks_found, 1);
+
+        let paths: Vec<(String, String, Option<String>)> = db
+            .conn()
+            .prepare("SELECT path, filename, extension FROM files ORDER BY path")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(paths[0], ("docs/b.pdf".to_string(), "b.pdf".to_string(), Some("pdf".to_string())));
+        assert_eq!(paths[2].0, "photos/a.jpg");
+
+        // A listing rooted somewhere other than the mount path.
+        let stats = Indexer::new(&mut db, 1)
+            .unwrap()
+            .with_root("remote:backup")
+            .index([FileEntry::new("remote:backup/c.txt", 1)])
+            .unwrap();
+        assert_eq!(stats.files_scanned, 1);
+        let count: i64 = db
+            .conn()
+            .query_row("SELECT COUNT(*) FROM files WHERE path = 'c.txt'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,127 @@
+//! Hash manifests for drives that are rarely or never mounted.
+//!
+//! A cold drive is known only from the catalog, so its hashes are what make
+//! it useful for dedup and replica checks. A manifest in `md5sum`/`b3sum`
+//! format (`<hash>  <path>`, one file per line) fills them in without
+//! rescanning: the hash length picks MD5 (32) or BLAKE3 (64), and paths may
+//! be relative to the drive root or absolute under its mount path.
+
+use crate::db::{Drive, SqliteDatabase};
+use crate::error::{OrdneError, Result};
+use std::path::Path;
+
+#[derive(Debug, Default, Clone)]
+pub struct ManifestImportResult {
+    pub files_updated: usize,
+    /// Manifest paths with no catalogued file on the drive.
+    pub unmatched: Vec<String>,
+    pub lines_skipped: usize,
+}
+
+pub fn import_hash_manifest<P: AsRef<Path>>(
+    db: &SqliteDatabase,
+    drive: &Drive,
+    path: P,
+) -> Result<ManifestImportResult> {
+    let content = std::fs::read_to_string(path)?;
+    let mut result = ManifestImportResult::default();
+
+    for line in content.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((hash, file_path)) = parse_line(line) else {
+            result.lines_skipped += 1;
+            continue;
+        };
+        let column = match hash.len() {
+            32 => "md5_hash",
+            64 => "blake3_hash",
+            _ => {
+                result.lines_skipped += 1;
+                continue;
+            }
+        };
+
+        let relative = relative_path(drive, file_path);
+        let rows = db.conn().execute(
+            &format!("UPDATE files SET {} = ?1 WHERE drive_id = ?2 AND path = ?3", column),
+            (hash.to_ascii_lowercase(), drive.id, &relative),
+        )?;
+        if rows == 0 {
+            result.unmatched.push(relative);
+        } else {
+            result.files_updated += rows;
+        }
+    }
+
+    if result.files_updated == 0 && result.unmatched.is_empty() {
+        return Err(OrdneError::Config("Manifest has no usable lines".to_string()));
+    }
+
+    Ok(result)
+}
+
+/// Split `<hash>  <path>` or `<hash> *<path>` (binary mode).
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let (hash, rest) = line.split_once(' ')?;
+    let path = rest.strip_prefix(' ').or_else(|| rest.strip_prefix('*'))?;
+    if path.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((hash, path))
+}
+
+fn relative_path(drive: &Drive, path: &str) -> String {
+    if let Some(mount) = drive.mount_path.as_deref() {
+        let mount = mount.trim_end_matches('/');
+        if let Some(rest) = path.strip_prefix(mount).and_then(|r| r.strip_prefix('/')) {
+            return rest.to_string();
+        }
+    }
+    path.trim_start_matches("./").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_fills_in_catalog_hashes() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, mount_path, role, is_online, backend) VALUES (1, 'vault', '/mnt/vault', 'backup', 0, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes, status)
+                 VALUES (1, 1, 'photos/a.jpg', 'a.jpg', 10, 'indexed'),
+                        (2, 1, 'b.txt', 'b.txt', 10, 'indexed');",
+            )
+            .unwrap();
+        let drive = db.get_drive("vault").unwrap().unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        let manifest = temp_dir.path().join("vault.md5");
+        std::fs::write(
+            &manifest,
+            format!(
+                "{md5}  ./photos/a.jpg\n{b3} */mnt/vault/b.txt\n{md5}  gone.txt\nnot a manifest line\n",
+                md5 = "D41D8CD98F00B204E9800998ECF8427E",
+                b3 = "a".repeat(64)
+            ),
+        )
+        .unwrap();
+
+        let result = import_hash_manifest(&db, &drive, &manifest).unwrap();
+        assert_eq!(result.files_updated, 2);
+        assert_eq!(result.unmatched, vec!["gone.txt".to_string()]);
+        assert_eq!(result.lines_skipped, 1);
+
+        let a = db.get_file(1).unwrap().unwrap();
+        assert_eq!(a.md5_hash.as_deref(), Some("d41d8cd98f00b204e9800998ecf8427e"));
+        let b = db.get_file(2).unwrap().unwrap();
+        assert_eq!(b.blake3_hash, Some("a".repeat(64)));
+    }
+}
@@ -1,13 +1,37 @@
+pub mod bench;
+pub mod catalog_check;
 pub mod device;
+pub mod exclude;
+pub mod file_list;
 pub mod hasher;
+pub mod indexer;
+pub mod manifest;
 pub mod dedup;
+pub mod estimate;
 pub mod rmlint;
 pub mod rmlint_import;
 pub mod scanner;
 
-pub use device::{DeviceInfo, discover_device};
-pub use dedup::{refresh_duplicates_for_drive, DedupAlgorithm, DedupRefreshResult};
-pub use hasher::{hash_file_md5, hash_file_blake3, verify_hash};
-pub use rmlint::{RmlintParser, RmlintLint, RmlintLintType, parse_rmlint_output};
+pub use bench::{benchmark_hashing, candidate_settings, sample_files, HashBenchResult};
+pub use catalog_check::{check_catalog, Drift, DriftKind, DriveDrift};
+pub use device::{
+    DeviceClass, DeviceInfo, atime_is_reliable, device_class_of_path, discover_device, find_mount_by_uuid,
+};
+pub use exclude::ExcludeRules;
+pub use file_list::{resolve_file_list, FileListResult, UnmatchedLine};
+pub use dedup::{
+    refresh_all_duplicates, refresh_duplicates_for_drive, DedupAlgorithm, DedupRefreshResult,
+    OriginalStrategy,
+};
+pub use estimate::{estimate_duplicate_waste, DedupEstimate, EstimateOptions};
+pub use hasher::{
+    hash_file_md5, hash_file_blake3, hash_file_sample, hash_file_with, verify_hash, FileStamp, HashSettings,
+    HashTuning,
+};
+pub use indexer::{FileEntry, Indexer};
+pub use manifest::{import_hash_manifest, ManifestImportResult};
+pub use rmlint::{
+    is_rmlint_available, parse_rmlint_output, run_rmlint, RmlintLint, RmlintLintType, RmlintParser,
+};
 pub use rmlint_import::{import_rmlint_output, RmlintImportOptions, RmlintImportResult};
-pub use scanner::{scan_directory, ScanStats, ScanOptions};
+pub use scanner::{retry_scan_errors, scan_directory, RetryStats, ScanFailure, ScanStats, ScanOptions};
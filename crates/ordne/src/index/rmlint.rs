@@ -1,7 +1,8 @@
-use crate::error::{Result};
+use crate::error::{OrdneError, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -86,20 +87,54 @@ impl RmlintParser {
     }
 
     /// Parses rmlint JSON output from a string
+    ///
+    /// rmlint's `-o json` writes one JSON array: a header object, the lints
+    /// (pretty-printed over several lines each), then a footer object. Header
+    /// and footer carry no `type` and are skipped. Output that is not an array
+    /// is read as one object per line. Content that yields no lint at all is
+    /// an error, so a layout this parser does not understand is never
+    /// mistaken for "no duplicates".
     pub fn parse_string(&mut self, content: &str) -> Result<()> {
-        let lines: Vec<&str> = content.lines().collect();
+        match serde_json::from_str::<Vec<serde_json::Value>>(content) {
+            Ok(entries) => self.parse_entries(entries),
+            Err(_) => self.parse_lines(content),
+        }
+    }
+
+    fn parse_entries(&mut self, entries: Vec<serde_json::Value>) -> Result<()> {
+        for (idx, entry) in entries.into_iter().enumerate() {
+            if entry.get("type").is_none() {
+                continue;
+            }
+            let lint = serde_json::from_value::<RmlintLint>(entry).map_err(|e| {
+                OrdneError::Config(format!("rmlint entry {} is not a valid lint: {}", idx + 1, e))
+            })?;
+            self.lints.push(lint);
+        }
+        Ok(())
+    }
 
-        for line in lines {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with("//") {
+    fn parse_lines(&mut self, content: &str) -> Result<()> {
+        let mut content_lines = 0;
+        let mut parsed = 0;
+        for line in content.lines() {
+            let line = line.trim().trim_end_matches(',');
+            if line.is_empty() || line.starts_with("//") || line == "[" || line == "]" {
                 continue;
             }
+            content_lines += 1;
 
             if let Ok(lint) = serde_json::from_str::<RmlintLint>(line) {
                 self.lints.push(lint);
+                parsed += 1;
             }
         }
 
+        if content_lines > 0 && parsed == 0 {
+            return Err(OrdneError::Config(
+                "No rmlint lints found: expected rmlint's JSON output (-o json)".to_string(),
+            ));
+        }
         Ok(())
     }
 
@@ -119,7 +154,7 @@ impl RmlintParser {
                 if let Some(checksum) = &lint.checksum {
                     groups
                         .entry(checksum.clone())
-                        .or_insert_with(Vec::new)
+                        .or_default()
                         .push(lint.clone());
                 }
             }
@@ -206,6 +241,53 @@ pub struct RmlintStatistics {
     pub other_lints: usize,
 }
 
+/// Run rmlint over `paths`, writing its JSON report to `output`.
+pub fn run_rmlint(paths: &[PathBuf], output: &Path) -> Result<()> {
+    if paths.is_empty() {
+        return Err(OrdneError::Config("rmlint needs at least one path".to_string()));
+    }
+
+    let output_str = output.to_str().ok_or_else(|| {
+        OrdneError::Config("Invalid rmlint output path encoding".to_string())
+    })?;
+
+    let mut cmd = Command::new("rmlint");
+    cmd.arg("-o").arg(format!("json:{}", output_str));
+    cmd.args(paths);
+    cmd.stdout(Stdio::null());
+    cmd.stderr(Stdio::piped());
+
+    log::info!("Executing rmlint: {:?}", cmd);
+
+    let result = cmd.output().map_err(|e| OrdneError::ExternalTool {
+        tool: "rmlint".to_string(),
+        message: format!("Failed to execute rmlint: {}", e),
+    })?;
+
+    if !result.status.success() {
+        return Err(OrdneError::ExternalTool {
+            tool: "rmlint".to_string(),
+            message: format!(
+                "rmlint failed with exit code {}: {}",
+                result.status.code().unwrap_or(-1),
+                String::from_utf8_lossy(&result.stderr)
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn is_rmlint_available() -> bool {
+    Command::new("rmlint")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
 /// Convenience function to parse rmlint output from a file
 pub fn parse_rmlint_output<P: AsRef<Path>>(path: P) -> Result<RmlintParser> {
     let mut parser = RmlintParser::new();
@@ -247,6 +329,89 @@ mod tests {
         assert_eq!(parser.lints().len(), 3);
     }
 
+    #[test]
+    fn test_parse_json_array_output() {
+        let json = r#"[
+{"description":"rmlint json-dump of lint files","cwd":"/tmp","args":"rmlint /tmp"},
+{"type":"duplicate_file","path":"/tmp/file1.txt","size":1024,"checksum":"abc123","is_original":true},
+{"type":"duplicate_file","path":"/tmp/file2.txt","size":1024,"checksum":"abc123","is_original":false},
+{"aborted":false,"progress":100,"total_files":2}
+]
+"#;
+
+        let mut parser = RmlintParser::new();
+        parser.parse_string(json).unwrap();
+
+        assert_eq!(parser.lints().len(), 2);
+        assert_eq!(parser.extract_duplicate_groups().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_pretty_printed_rmlint_output() {
+        // As written by `rmlint -o json:report.json` (rmlint 2.10).
+        let json = r#"[
+{
+  "description": "rmlint json-dump of lint files",
+  "cwd": "/home/user/",
+  "args": "rmlint -o json:/tmp/report.json /srv/photos",
+  "version": "2.10.2",
+  "rev": "7e2a1f8",
+  "progress": 0,
+  "checksum_type": "blake2b"
+},
+{
+  "id": 85761024,
+  "type": "duplicate_file",
+  "progress": 100,
+  "checksum": "5d8e0cd3b1c4a2b3f8f4fb20c9d1ca3d8b1cc5e3f3a0e1f8c4c1d2b0e9f7a6b5",
+  "path": "/srv/photos/2019/img_001.jpg",
+  "size": 2048,
+  "depth": 3,
+  "inode": 1443912,
+  "disk_id": 2049,
+  "is_original": true,
+  "mtime": 1571234567.123456
+},
+{
+  "id": 85761088,
+  "type": "duplicate_file",
+  "progress": 100,
+  "checksum": "5d8e0cd3b1c4a2b3f8f4fb20c9d1ca3d8b1cc5e3f3a0e1f8c4c1d2b0e9f7a6b5",
+  "path": "/srv/photos/backup/img_001.jpg",
+  "size": 2048,
+  "depth": 3,
+  "inode": 1443977,
+  "disk_id": 2049,
+  "is_original": false,
+  "mtime": 1571234567.123456
+},
+{
+  "aborted": false,
+  "progress": 100,
+  "total_files": 2,
+  "ignored_files": 0,
+  "ignored_folders": 0,
+  "duplicates": 1,
+  "duplicate_sets": 1,
+  "total_lint_size": 2048
+}
+]
+"#;
+
+        let mut parser = RmlintParser::new();
+        parser.parse_string(json).unwrap();
+
+        assert_eq!(parser.lints().len(), 2);
+        let groups = parser.extract_duplicate_groups();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files.len(), 2);
+        assert!(groups[0].original_idx.is_some());
+
+        // Output it cannot read is an error, not an empty import.
+        assert!(RmlintParser::new().parse_string("{\n  \"type\": \"duplicate_file\",\n").is_err());
+        assert!(RmlintParser::new().parse_string("").is_ok());
+    }
+
     #[test]
     fn test_extract_duplicate_groups() {
         let json = r#"{"type":"duplicate_file","path":"/tmp/file1.txt","size":1024,"checksum":"abc123","is_original":true}
@@ -174,6 +174,8 @@ mod tests {
             role: DriveRole::Source,
             is_online: true,
             is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
             backend: Backend::Local,
             rclone_remote: None,
             scanned_at: None,
@@ -187,18 +189,20 @@ mod tests {
         let file = File {
             id: 0,
             drive_id,
-            path: abs_path.to_string(),
+            path: abs_path.trim_start_matches("/mnt/drive1/").to_string(),
             abs_path: abs_path.to_string(),
-            filename: abs_path.split('/').last().unwrap().to_string(),
+            filename: abs_path.rsplit('/').next().unwrap().to_string(),
             extension: None,
             size_bytes: size,
             md5_hash: None,
             blake3_hash: None,
             created_at: None,
             modified_at: None,
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -1,7 +1,16 @@
-use crate::db::{Database, File, FileStatus, Priority};
+use crate::db::owners::record_owners;
+use crate::db::scan_errors::{list_scan_errors, replace_scan_errors, ScanError};
+use super::exclude::ExcludeRules;
+use super::indexer::{FileEntry, Indexer};
+use crate::config::load_drive_config;
+use crate::db::{Database, Drive, SqliteDatabase};
 use crate::error::{OrdneError, Result};
+use crate::util::users::user_name;
 use chrono::Utc;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs;
+use std::io;
 use std::os::unix::fs::MetadataExt;
 use std::path::{Path};
 use walkdir::{DirEntry, WalkDir};
@@ -14,34 +23,55 @@ pub struct ScanStats {
     pub symlinks_found: usize,
     pub git_repos_found: usize,
     pub errors: usize,
+    /// Files and directories skipped by the drive's exclude patterns.
+    pub excluded: usize,
+    /// Access times were recorded for this scan.
+    pub atime_captured: bool,
+    /// The scanned directory, relative to the drive root.
+    pub scope: String,
+    /// Paths that could not be read, for `db::scan_errors`.
+    pub failures: Vec<ScanFailure>,
+    /// Owner uids seen, with their login name on this host where there is
+    /// one, for `db::owners`.
+    pub owners: BTreeMap<u32, Option<String>>,
+}
+
+/// A path the scan could not read.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanFailure {
+    /// Relative to the drive root.
+    pub path: String,
+    /// `permission_denied`, `not_found`, `io` or `loop`.
+    pub kind: String,
+    pub message: String,
 }
 
 /// Options for filesystem scanning
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ScanOptions {
     pub follow_symlinks: bool,
     pub max_depth: Option<usize>,
     pub include_hidden: bool,
-}
-
-impl Default for ScanOptions {
-    fn default() -> Self {
-        Self {
-            follow_symlinks: false,
-            max_depth: None,
-            include_hidden: false,
-        }
-    }
+    /// Record access times. Ignored on `noatime` mounts, where they are stale.
+    pub capture_atime: bool,
 }
 
 /// Scans a directory and inserts file records into the database
 ///
 /// This performs a recursive directory walk, collecting metadata for each file:
 /// - Size, mtime, inode, permissions
+/// - atime, when requested and the mount keeps it up to date
 /// - Symlink detection and target resolution
 /// - Hardlink detection (nlinks > 1)
 /// - Git remote URL extraction from `.git/config` files
 ///
+/// Paths matching `[scan] exclude` in the drive's `.ordne/ordne.toml` are
+/// skipped; see [`super::exclude`].
+///
+/// Unreadable paths are skipped and collected in [`ScanStats::failures`];
+/// store them with [`crate::db::scan_errors::replace_scan_errors`]. Owner
+/// names go in [`ScanStats::owners`], for [`crate::db::owners::record_owners`].
+///
 /// Returns statistics about the scan operation.
 pub fn scan_directory<P: AsRef<Path>>(
     db: &mut dyn Database,
@@ -50,41 +80,152 @@ pub fn scan_directory<P: AsRef<Path>>(
     options: ScanOptions,
 ) -> Result<ScanStats> {
     let path = path.as_ref();
-    let mut stats = ScanStats::default();
 
     if !path.exists() {
         return Err(OrdneError::FileNotFound(path.to_path_buf()));
     }
 
+    // Paths are stored relative to the drive root, even when only a
+    // subdirectory is scanned.
+    let mut indexer = Indexer::new(db, drive_id)?;
+    let mount = indexer.root();
+    if mount.as_os_str().is_empty() || !path.starts_with(mount) {
+        indexer = indexer.with_root(path);
+    }
+
+    let scope = indexer.relative_path(path);
+    let stats = indexer.stats_mut();
+    stats.atime_captured = options.capture_atime && super::device::atime_is_reliable(path);
+    stats.scope = scope;
+
+    let root = indexer.root().to_path_buf();
+    let exclude = match load_drive_config(&root)? {
+        Some(config) => ExcludeRules::new(&config.exclude)?,
+        None => ExcludeRules::default(),
+    };
+
     let mut walker = WalkDir::new(path).follow_links(options.follow_symlinks);
 
     if let Some(max_depth) = options.max_depth {
         walker = walker.max_depth(max_depth);
     }
 
-    for entry in walker.into_iter() {
+    let mut excluded = 0;
+    let entries = walker.into_iter().filter_entry(|entry| {
+        let skip = !exclude.is_empty()
+            && entry.path().strip_prefix(&root).is_ok_and(|relative| {
+                exclude.is_excluded(relative, entry.file_type().is_dir())
+            });
+        excluded += usize::from(skip);
+        !skip
+    });
+
+    for entry in entries {
         match entry {
             Ok(entry) => {
-                if let Err(e) = process_entry(db, drive_id, &entry, &mut stats, &options, path) {
-                    eprintln!("Error processing {}: {}", entry.path().display(), e);
-                    stats.errors += 1;
+                if let Err(e) = process_entry(&mut indexer, &entry, &options, path) {
+                    log::warn!("Error processing {}: {}", entry.path().display(), e);
+                    let kind = match &e {
+                        OrdneError::Io(io_err) => io_error_kind(io_err),
+                        _ => "io",
+                    };
+                    let failed = indexer.relative_path(entry.path());
+                    indexer.stats_mut().record_failure(failed, kind, e.to_string());
                 }
             }
             Err(e) => {
-                eprintln!("Walk error: {}", e);
-                stats.errors += 1;
+                log::warn!("Walk error: {}", e);
+                let kind = if e.loop_ancestor().is_some() {
+                    "loop"
+                } else {
+                    e.io_error().map_or("io", io_error_kind)
+                };
+                let failed = e
+                    .path()
+                    .map_or_else(|| indexer.stats().scope.clone(), |p| indexer.relative_path(p));
+                indexer.stats_mut().record_failure(failed, kind, e.to_string());
+            }
+        }
+    }
+
+    indexer.stats_mut().excluded = excluded;
+    Ok(indexer.finish())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RetryStats {
+    pub paths_retried: usize,
+    pub files_scanned: usize,
+    /// Previously failed paths that are now readable or gone.
+    pub resolved: usize,
+    /// Errors still recorded for the drive, including new ones found below
+    /// directories that became readable.
+    pub remaining: Vec<ScanError>,
+}
+
+/// Re-walk only the paths recorded in `scan_errors` for a drive.
+///
+/// A path under another failed path is covered by rescanning its ancestor.
+/// Paths that no longer exist are cleared.
+pub fn retry_scan_errors(
+    db: &mut SqliteDatabase,
+    drive: &Drive,
+    options: ScanOptions,
+) -> Result<RetryStats> {
+    let mount = drive
+        .mount_path
+        .as_deref()
+        .map(Path::new)
+        .ok_or_else(|| OrdneError::Config(format!("Drive '{}' has no mount path", drive.label)))?;
+
+    let before = list_scan_errors(db.conn(), drive.id)?;
+    let mut stats = RetryStats::default();
+    let mut retried: Vec<&str> = Vec::new();
+
+    // Sorted by path, so ancestors come first.
+    for error in &before {
+        let covered = retried.iter().any(|done| {
+            done.is_empty() || error.path.strip_prefix(done).is_some_and(|rest| rest.starts_with('/'))
+        });
+        if covered {
+            continue;
+        }
+        retried.push(&error.path);
+        stats.paths_retried += 1;
+
+        let full = mount.join(&error.path);
+        match fs::symlink_metadata(&full) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                replace_scan_errors(db.conn(), drive.id, &error.path, &[])?;
+            }
+            Err(e) => {
+                let failure = ScanFailure {
+                    path: error.path.clone(),
+                    kind: io_error_kind(&e).to_string(),
+                    message: e.to_string(),
+                };
+                replace_scan_errors(db.conn(), drive.id, &error.path, &[failure])?;
+            }
+            Ok(_) => {
+                let scan = scan_directory(db, drive.id, &full, options.clone())?;
+                stats.files_scanned += scan.files_scanned;
+                replace_scan_errors(db.conn(), drive.id, &scan.scope, &scan.failures)?;
+                record_owners(db.conn(), &scan.owners)?;
             }
         }
     }
 
+    stats.remaining = list_scan_errors(db.conn(), drive.id)?;
+    stats.resolved = before
+        .iter()
+        .filter(|old| !stats.remaining.iter().any(|e| e.path == old.path))
+        .count();
     Ok(stats)
 }
 
 fn process_entry(
-    db: &mut dyn Database,
-    drive_id: i64,
+    indexer: &mut Indexer,
     entry: &DirEntry,
-    stats: &mut ScanStats,
     options: &ScanOptions,
     base_path: &Path,
 ) -> Result<()> {
@@ -99,7 +240,7 @@ fn process_entry(
     }
 
     if entry.file_type().is_dir() {
-        stats.dirs_scanned += 1;
+        indexer.stats_mut().dirs_scanned += 1;
         return Ok(());
     }
 
@@ -107,95 +248,69 @@ fn process_entry(
         return Ok(());
     }
 
-    let metadata = match entry.metadata() {
-        Ok(m) => m,
-        Err(e) => {
-            eprintln!("Failed to get metadata for {}: {}", path.display(), e);
-            return Ok(());
-        }
-    };
+    let metadata = entry.metadata().map_err(io::Error::from)?;
 
     let is_symlink = metadata.is_symlink() || entry.path_is_symlink();
     let symlink_target = if is_symlink {
-        stats.symlinks_found += 1;
         fs::read_link(path).ok().map(|p| p.to_string_lossy().to_string())
     } else {
         None
     };
 
-    let relative_path = path
-        .strip_prefix(base_path)
-        .unwrap_or(path)
-        .to_string_lossy()
-        .to_string();
-
-    let filename = path
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_default();
-
-    let extension = path
-        .extension()
-        .map(|e| e.to_string_lossy().to_string());
-
-    let git_remote_url = if filename == "config" && path.parent().and_then(|p| p.file_name()).map(|n| n == ".git").unwrap_or(false) {
+    let in_git_dir = path.parent().and_then(|p| p.file_name()).is_some_and(|n| n == ".git");
+    let git_remote_url = if in_git_dir && path.file_name().is_some_and(|n| n == "config") {
         extract_git_remote(path).ok()
     } else {
         None
     };
 
-    if git_remote_url.is_some() {
-        stats.git_repos_found += 1;
-    }
+    indexer
+        .stats_mut()
+        .owners
+        .entry(metadata.uid())
+        .or_insert_with(|| user_name(metadata.uid()));
 
-    let size_bytes = if is_symlink { 0 } else { metadata.len() as i64 };
-
-    let file = File {
-        id: 0,
-        drive_id,
-        path: relative_path,
-        abs_path: path.to_string_lossy().to_string(),
-        filename,
-        extension,
-        size_bytes,
-        md5_hash: None,
-        blake3_hash: None,
-        created_at: None,
+    indexer.add(FileEntry {
+        path: path.to_path_buf(),
+        size_bytes: metadata.len(),
         modified_at: metadata.modified().ok().map(|t| {
             let duration = t.duration_since(std::time::UNIX_EPOCH).unwrap();
             chrono::DateTime::from_timestamp(duration.as_secs() as i64, 0)
                 .unwrap()
                 .with_timezone(&Utc)
         }),
-        inode: Some(metadata.ino() as i64),
-        device_num: Some(metadata.dev() as i64),
-        nlinks: Some(metadata.nlink() as i32),
-        mime_type: None,
+        accessed_at: if indexer.stats().atime_captured {
+            chrono::DateTime::from_timestamp(metadata.atime(), 0)
+        } else {
+            None
+        },
+        inode: Some(metadata.ino()),
+        device_num: Some(metadata.dev()),
+        nlinks: Some(metadata.nlink() as u32),
+        owner_uid: Some(metadata.uid()),
         is_symlink,
         symlink_target,
         git_remote_url,
-        category: None,
-        subcategory: None,
-        target_path: None,
-        target_drive_id: None,
-        priority: Priority::Normal,
-        duplicate_group: None,
-        is_original: false,
-        rmlint_type: None,
-        status: FileStatus::Indexed,
-        migrated_to: None,
-        migrated_to_drive: None,
-        migrated_at: None,
-        verified_hash: None,
-        error: None,
-        indexed_at: Utc::now(),
-    };
+    })
+}
 
-    db.add_file(&file)?;
-    stats.files_scanned += 1;
-    stats.bytes_scanned += size_bytes as u64;
+impl ScanStats {
+    fn record_failure(&mut self, path: String, kind: &str, message: String) {
+        self.errors += 1;
+        self.failures.push(ScanFailure {
+            path,
+            kind: kind.to_string(),
+            message,
+        });
+    }
+}
 
-    Ok(())
+fn io_error_kind(e: &io::Error) -> &'static str {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => "permission_denied",
+        io::ErrorKind::NotFound => "not_found",
+        _ => "io",
+    }
 }
 
 /// Extracts Git remote URL from a .git/config file
@@ -243,6 +358,8 @@ mod tests {
             role: crate::db::DriveRole::Source,
             is_online: true,
             is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
             backend: crate::db::Backend::Local,
             rclone_remote: None,
             scanned_at: None,
@@ -278,6 +395,8 @@ mod tests {
             role: crate::db::DriveRole::Source,
             is_online: true,
             is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
             backend: crate::db::Backend::Local,
             rclone_remote: None,
             scanned_at: None,
@@ -289,6 +408,18 @@ mod tests {
 
         assert_eq!(stats.files_scanned, 2);
         assert!(stats.bytes_scanned > 0);
+
+        let uid = std::fs::metadata(&file1).unwrap().uid();
+        assert_eq!(stats.owners.keys().collect::<Vec<_>>(), vec![&uid]);
+        let owners: Vec<Option<i64>> = db
+            .conn()
+            .prepare("SELECT owner_uid FROM files")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(owners, vec![Some(uid as i64); 2]);
     }
 
     #[test]
@@ -313,6 +444,8 @@ mod tests {
             role: crate::db::DriveRole::Source,
             is_online: true,
             is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
             backend: crate::db::Backend::Local,
             rclone_remote: None,
             scanned_at: None,
@@ -326,6 +459,114 @@ mod tests {
         assert_eq!(stats.dirs_scanned, 2);
     }
 
+    #[test]
+    fn test_retry_scan_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut db = create_test_db();
+
+        let subdir = temp_dir.path().join("subdir");
+        fs::create_dir(&subdir).unwrap();
+        StdFile::create(subdir.join("file.txt")).unwrap().write_all(b"content").unwrap();
+        StdFile::create(temp_dir.path().join("skipped.txt")).unwrap();
+
+        let drive_id = db
+            .add_drive(&crate::db::Drive {
+                id: 0,
+                label: "test".to_string(),
+                device_id: None,
+                device_path: None,
+                uuid: None,
+                mount_path: Some(temp_dir.path().to_string_lossy().to_string()),
+                fs_type: None,
+                total_bytes: None,
+                role: crate::db::DriveRole::Source,
+                is_online: true,
+                is_readonly: false,
+                is_cold: false,
+                is_encrypted: false,
+                backend: crate::db::Backend::Local,
+                rclone_remote: None,
+                scanned_at: None,
+                added_at: Utc::now(),
+            })
+            .unwrap();
+        let failure = |path: &str| ScanFailure {
+            path: path.to_string(),
+            kind: "permission_denied".to_string(),
+            message: "Permission denied (os error 13)".to_string(),
+        };
+        replace_scan_errors(
+            db.conn(),
+            drive_id,
+            "",
+            &[failure("subdir"), failure("subdir/file.txt"), failure("gone")],
+        )
+        .unwrap();
+
+        let drive = db.get_drive("test").unwrap().unwrap();
+        let stats = retry_scan_errors(&mut db, &drive, ScanOptions::default()).unwrap();
+
+        // subdir/file.txt is covered by rescanning subdir.
+        assert_eq!(stats.paths_retried, 2);
+        assert_eq!(stats.files_scanned, 1);
+        assert_eq!(stats.resolved, 3);
+        assert!(stats.remaining.is_empty());
+        // Only the failed paths were walked.
+        let indexed: Vec<String> = db
+            .conn()
+            .prepare("SELECT path FROM files")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(indexed, vec!["subdir/file.txt".to_string()]);
+    }
+
+    #[test]
+    fn test_scan_skips_drive_excludes() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut db = create_test_db();
+
+        fs::create_dir_all(temp_dir.path().join(".ordne")).unwrap();
+        fs::write(
+            temp_dir.path().join(crate::config::DRIVE_CONFIG_FILE),
+            "[scan]\nexclude = [\"*.tmp\", \"node_modules/\"]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(temp_dir.path().join("web/node_modules/pkg")).unwrap();
+        StdFile::create(temp_dir.path().join("web/node_modules/pkg/index.js")).unwrap();
+        StdFile::create(temp_dir.path().join("web/app.js")).unwrap();
+        StdFile::create(temp_dir.path().join("web/build.tmp")).unwrap();
+
+        let drive_id = db
+            .add_drive(&crate::db::Drive {
+                id: 0,
+                label: "test".to_string(),
+                device_id: None,
+                device_path: None,
+                uuid: None,
+                mount_path: Some(temp_dir.path().to_string_lossy().to_string()),
+                fs_type: None,
+                total_bytes: None,
+                role: crate::db::DriveRole::Source,
+                is_online: true,
+                is_readonly: false,
+                is_cold: false,
+                is_encrypted: false,
+                backend: crate::db::Backend::Local,
+                rclone_remote: None,
+                scanned_at: None,
+                added_at: Utc::now(),
+            })
+            .unwrap();
+
+        let stats = scan_directory(&mut db, drive_id, temp_dir.path().join("web"), ScanOptions::default()).unwrap();
+
+        assert_eq!(stats.files_scanned, 1);
+        assert_eq!(stats.excluded, 2);
+    }
+
     #[test]
     fn test_extract_git_remote() {
         let temp_dir = TempDir::new().unwrap();
@@ -360,6 +601,8 @@ mod tests {
             role: crate::db::DriveRole::Source,
             is_online: true,
             is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
             backend: crate::db::Backend::Local,
             rclone_remote: None,
             scanned_at: None,
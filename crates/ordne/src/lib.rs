@@ -4,28 +4,38 @@ pub mod db;
 pub mod error;
 pub mod index;
 pub mod migrate;
+pub mod pipeline;
 pub mod policy;
+pub mod roles;
+pub mod undo;
 pub mod util;
 
 pub use config::Config;
 pub use db::{
-    AuditDatabase, AuditLogEntry, Backend, Database, Drive, DriveRole, DuplicateGroup, File,
-    FileStatus, MigrationPlan, MigrationStep, PlanStatus, PlansDatabase, Priority, SqliteDatabase,
-    StepAction, StepStatus,
+    Attribution, AuditDatabase, AuditLogEntry, Backend, ClassificationSuggestion, Database, Drive,
+    DriveRole, DuplicateGroup, File, FileSnapshot, FileStatus, HashAlgorithm, MemoryDatabase,
+    MigrationPlan, MigrationStep,
+    PlanStatus, PlanSummary, PlansDatabase, Priority, SqliteDatabase, StepAction, StepDirectory, StepOrdering,
+    StepPage, StepQuery, StepStatus, SuggestionStatus, VerificationDatabase, VerificationFailure,
 };
 pub use error::{OrdneError, Result};
 pub use classify::{
-    ClassificationRule, ClassificationRules, RuleMatch, RuleType, RuleEngine,
-    InteractiveClassifier, ClassificationBatch,
+    ClassificationRule, ClassificationRules, DriveRuleEngines, RuleMatch, RuleType, RuleEngine,
+    InteractiveClassifier, ClassificationBatch, suggestion_batches,
+    ExtensionAnomaly, find_anomalies, rules_for_anomalies,
 };
 pub use index::{
-    DeviceInfo, ScanStats, ScanOptions, discover_device, hash_file_md5, hash_file_blake3,
+    DeviceInfo, FileEntry, Indexer, ScanStats, ScanOptions, discover_device, hash_file_md5, hash_file_blake3,
     scan_directory, import_rmlint_output, RmlintImportOptions, RmlintImportResult,
 };
 pub use migrate::{
-    EngineOptions, MigrationEngine, Planner, PlannerOptions, RollbackEngine, SpaceInfo,
+    CrossDriveDedupOptions, DedupResolution, EngineOptions, ExecutionWindow, IdlePolicy, MigrationEngine, OffloadLayout,
+    EncryptionWarning, PlacementConstraint, PlacementTarget, Planner, PlannerOptions, RollbackEngine,
+    SpaceInfo, WhatIf, WhatIfReport, plan_encryption_warnings, reconcile_plan, DriveReconciliation,
 };
+pub use pipeline::{Ordne, OrdneBuilder};
 pub use policy::{
-    apply_policy, load_effective_policy, Policy, PolicyApplyResult, PolicyClassification, PolicyPlan,
+    apply_policy, apply_policy_with, load_effective_policy, Policy, PolicyApplyResult, PolicyClassification, PolicyPlan,
     PolicySafety, PolicySchedule, PolicyScope,
 };
+pub use util::clock::Clock;
@@ -1,53 +1,134 @@
 mod cli;
-mod util;
+use ordne_lib::util;
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 use ordne_lib::Result;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
 
-fn main() -> Result<()> {
-    let cli = cli::Cli::parse();
-
-    let mut db = cli::init_database(cli.db)?;
+fn run() -> Result<()> {
+    let matches = cli::Cli::command().get_matches();
+    let cli = cli::Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if cli.no_color {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
 
+    // These need neither the database nor the config file.
     match cli.command {
+        cli::Commands::Completions { shell } => return cli::generate::print_completions(shell),
+        cli::Commands::Man { ref out_dir } => return cli::generate::print_man(out_dir.as_deref()),
+        _ => {}
+    }
+
+    let config = ordne_lib::Config::new(cli.db.clone())?;
+    let settings = config.settings();
+    if cli.command.hashes_files() {
+        ordne_lib::index::hasher::configure(settings.hash_tuning()?);
+    }
+
+    // Bench does not need the database, so it works before one exists.
+    if let cli::Commands::Bench { action } = cli.command {
+        return cli::bench::handle_bench_command(action);
+    }
+
+    let mut db = cli::init_database(&config, &settings)?;
+    cli::drive::reconcile_mounts(&mut db)?;
+
+    let timer = cli::summary::CommandTimer::start(&db, cli::command_name(&matches))?;
+
+    let result = match cli.command {
         cli::Commands::Drive { action } => {
             cli::drive::handle_drive_command(&mut db, action, cli.verbose)
         }
 
-        cli::Commands::Scan { drive_label, path, all } => {
-            cli::scan::handle_scan_command(&mut db, drive_label, path, all, cli.verbose)
+        cli::Commands::Scan { action: Some(action), .. } => {
+            cli::scan::handle_scan_subcommand(&db, action)
+        }
+
+        cli::Commands::Scan { action: None, drive_label: Some(label), retry_errors: true, atime, .. } => {
+            cli::scan::handle_retry_errors(&mut db, &label, atime)
+        }
+
+        cli::Commands::Scan { action: None, drive_label, path, all, atime, .. } => {
+            cli::scan::handle_scan_command(&mut db, drive_label, path, all, atime, cli.verbose)
         }
 
         cli::Commands::Status { space } => {
-            cli::status::handle_status_command(&db, space)
+            cli::status::handle_status_command(&db, &settings, space)
         }
 
         cli::Commands::Query { action } => {
             cli::query::handle_query_command(&db, action)
         }
 
-        cli::Commands::Classify { config, auto } => {
-            cli::classify::handle_classify_command(&mut db, config, auto, cli.verbose)
+        cli::Commands::Classify { config, auto, suggestions, min_confidence } => {
+            if suggestions {
+                cli::classify::handle_review_suggestions(&mut db, min_confidence, cli.verbose)
+            } else {
+                cli::classify::handle_classify_command(&mut db, config, auto, cli.verbose)
+            }
         }
 
         cli::Commands::Plan { action } => {
-            cli::plan::handle_plan_command(&mut db, action, cli.verbose)
+            cli::plan::handle_plan_command(&mut db, &settings, action, cli.verbose)
         }
 
-        cli::Commands::Migrate { plan_id, dry_run, execute } => {
-            cli::migrate::handle_migrate_command(&mut db, plan_id, dry_run, execute, cli.verbose)
+        cli::Commands::Migrate { plan_id, dry_run, execute, parallel, per_device, verify_after, window, bwlimit, when_idle, max_load } => {
+            cli::migrate::handle_migrate_command(
+                &mut db,
+                &settings,
+                plan_id,
+                dry_run,
+                execute,
+                parallel,
+                per_device,
+                verify_after,
+                window.as_deref(),
+                bwlimit.as_deref(),
+                when_idle.as_deref(),
+                max_load,
+                cli.verbose,
+            )
         }
 
         cli::Commands::Rollback { plan_id } => {
-            cli::migrate::handle_rollback_command(&mut db, plan_id, cli.verbose)
+            cli::migrate::handle_rollback_command(&mut db, &settings, plan_id, cli.verbose)
+        }
+
+        cli::Commands::Verify { action: Some(action), .. } => {
+            cli::verify::handle_verify_subcommand(&db, &settings, action)
+        }
+
+        cli::Commands::Verify { action: None, drive, due, plan } => {
+            if due {
+                cli::verify::handle_verify_due_command(&mut db)
+            } else if let Some(plan_id) = plan {
+                cli::verify::handle_verify_plan_command(&mut db, plan_id)
+            } else {
+                cli::verify::handle_verify_command(&mut db, drive, cli.verbose)
+            }
         }
 
-        cli::Commands::Verify { drive } => {
-            cli::verify::handle_verify_command(&mut db, drive, cli.verbose)
+        cli::Commands::Report { action } => {
+            cli::report::handle_report_command(&db, action)
         }
 
-        cli::Commands::Report => {
-            cli::report::handle_report_command(&db)
+        cli::Commands::Inbox { ack, ack_all } => {
+            cli::inbox::handle_inbox_command(&mut db, ack, ack_all)
+        }
+
+        cli::Commands::Undo { within, yes } => {
+            cli::undo::handle_undo_command(&mut db, &within, yes)
         }
 
         cli::Commands::Export { format, output } => {
@@ -55,11 +136,15 @@ fn main() -> Result<()> {
         }
 
         cli::Commands::Policy { action } => {
-            cli::policy::handle_policy_command(&mut db, action, cli.verbose)
+            cli::policy::handle_policy_command(&mut db, &settings, action, cli.verbose)
         }
 
         cli::Commands::RunPolicy { path, dry_run, execute } => {
-            cli::run_policy::handle_run_policy_command(&mut db, path, dry_run, execute)
+            cli::run_policy::handle_run_policy_command(&mut db, &settings, path, dry_run, execute)
+        }
+
+        cli::Commands::WhatIf { plans, policy, json } => {
+            cli::whatif::handle_what_if_command(&db, &settings, plans, policy, json)
         }
 
         cli::Commands::Rmlint { action } => {
@@ -67,7 +152,12 @@ fn main() -> Result<()> {
         }
 
         cli::Commands::Dedup { action } => {
-            cli::dedup::handle_dedup_command(&mut db, action, cli.verbose)
+            cli::dedup::handle_dedup_command(&mut db, &settings, action, cli.verbose)
         }
-    }
+
+        cli::Commands::Completions { .. } | cli::Commands::Man { .. } | cli::Commands::Bench { .. } => Ok(()),
+    };
+
+    timer.finish(&db, &result, cli.summary)?;
+    result
 }
@@ -0,0 +1,357 @@
+//! Mounting the drives a plan needs, for unattended runs.
+//!
+//! A disk that sleeps between runs can be listed under `[automount]` in
+//! `ordne.toml`. Before a plan executes, listed drives its steps touch are
+//! mounted by filesystem UUID with `udisksctl` (udisks2), marked online and
+//! remapped if they came up at a new mount point. Once the plan has run and
+//! its copies are verified, the drives ordne mounted are unmounted again and,
+//! with `after = "power-off"`, powered down. Drives that were already mounted
+//! are left as they were.
+
+use crate::db::drives::reconcile_mount_paths;
+use crate::db::{AuditDatabase, AuditLogEntry, Backend, Database, Drive, PlansDatabase};
+use crate::error::{OrdneError, Result};
+use crate::index::find_mount_by_uuid;
+use crate::util::clock::Clock;
+use std::collections::BTreeSet;
+use std::process::Command;
+
+/// What happens to a drive ordne mounted once the plan has run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AfterPlan {
+    /// Leave it mounted and online.
+    Stay,
+    #[default]
+    Unmount,
+    /// Unmount it and power the disk down.
+    PowerOff,
+}
+
+impl AfterPlan {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AfterPlan::Stay => "stay",
+            AfterPlan::Unmount => "unmount",
+            AfterPlan::PowerOff => "power-off",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "stay" => Ok(AfterPlan::Stay),
+            "unmount" => Ok(AfterPlan::Unmount),
+            "power-off" => Ok(AfterPlan::PowerOff),
+            _ => Err(OrdneError::Config(format!(
+                "Invalid [automount] after '{}'. Use 'stay', 'unmount' or 'power-off'",
+                value
+            ))),
+        }
+    }
+}
+
+/// The `[automount]` section of `ordne.toml`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Automount {
+    /// Labels of the drives ordne may mount.
+    pub drives: Vec<String>,
+    pub after: AfterPlan,
+}
+
+impl Automount {
+    /// UUID to mount `drive` by, if it is a local drive listed for automount.
+    /// Cold drives stay offline by definition and are never mounted.
+    fn uuid_of<'a>(&self, drive: &'a Drive) -> Option<&'a str> {
+        if drive.backend != Backend::Local || drive.is_cold || !self.drives.contains(&drive.label) {
+            return None;
+        }
+        drive.uuid.as_deref()
+    }
+}
+
+/// Mount the `[automount]` drives that the pending steps of `plan_ids` touch,
+/// call `run`, then unmount or power down the drives that were mounted.
+/// Audit entries for the drives are stamped by `clock`.
+///
+/// The drives are released however `run` ends. When `run` succeeds, a failure
+/// to release a drive is returned; otherwise it is logged and `run`'s error
+/// is returned.
+pub fn with_plan_drives<D: Database + PlansDatabase + AuditDatabase, T>(
+    db: &mut D,
+    plan_ids: &[i64],
+    automount: &Automount,
+    clock: &Clock,
+    run: impl FnOnce(&mut D) -> Result<T>,
+) -> Result<T> {
+    if automount.drives.is_empty() {
+        return run(db);
+    }
+
+    let mounted = mount_plan_drives(db, plan_ids, automount, clock, &udisksctl)?;
+    let result = run(db);
+    let released = release_drives(db, &mounted, automount.after, clock, &udisksctl);
+    match (result, released) {
+        (Ok(value), Ok(())) => Ok(value),
+        (Ok(_), Err(e)) => Err(e),
+        (Err(e), released) => {
+            if let Err(release_error) = released {
+                log::warn!("{}", release_error);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Runs a `udisksctl` action on a filesystem UUID; [`udisksctl`] outside tests.
+type Udisks<'a> = &'a dyn Fn(&str, &str) -> Result<()>;
+
+/// Mount the listed drives the plans need and mark them online. Returns the
+/// drives that were mounted here; on any failure, those are released again.
+fn mount_plan_drives<D: Database + PlansDatabase + AuditDatabase>(
+    db: &mut D,
+    plan_ids: &[i64],
+    automount: &Automount,
+    clock: &Clock,
+    udisks: Udisks,
+) -> Result<Vec<Drive>> {
+    let mut drive_ids = BTreeSet::new();
+    for plan_id in plan_ids {
+        for step in db.get_pending_steps(*plan_id)? {
+            drive_ids.insert(step.source_drive_id);
+            drive_ids.extend(step.dest_drive_id);
+        }
+    }
+
+    let mut mounted = Vec::new();
+    for drive_id in drive_ids {
+        if let Err(e) = mount_drive(db, drive_id, automount, clock, udisks, &mut mounted) {
+            if let Err(release_error) = release_drives(db, &mounted, automount.after, clock, udisks)
+            {
+                log::warn!("{}", release_error);
+            }
+            return Err(e);
+        }
+    }
+    Ok(mounted)
+}
+
+/// Mount one drive if it is listed and not already mounted, and mark it
+/// online. A drive mounted here is pushed to `mounted` as soon as udisksctl
+/// has mounted it, so a later failure still releases it.
+fn mount_drive<D: Database + AuditDatabase>(
+    db: &mut D,
+    drive_id: i64,
+    automount: &Automount,
+    clock: &Clock,
+    udisks: Udisks,
+    mounted: &mut Vec<Drive>,
+) -> Result<()> {
+    let Some(drive) = db.get_drive_by_id(drive_id)? else {
+        return Ok(());
+    };
+    let Some(uuid) = automount.uuid_of(&drive).map(str::to_string) else {
+        return Ok(());
+    };
+    let was_mounted = find_mount_by_uuid(&uuid).is_some();
+    if drive.is_online && was_mounted {
+        return Ok(());
+    }
+    if !was_mounted {
+        udisks("mount", &uuid)?;
+        mounted.push(drive.clone());
+    }
+
+    reconcile_mount_paths(db, clock, find_mount_by_uuid)?;
+    db.update_drive_online_status(&drive.label, true)?;
+    if !was_mounted {
+        let mount = find_mount_by_uuid(&uuid).unwrap_or_default();
+        log_drive_event(
+            db,
+            clock,
+            &drive,
+            "drive_mounted",
+            format!("Mounted drive '{}' at {} for a plan", drive.label, mount),
+        )?;
+    }
+    Ok(())
+}
+
+/// Unmount, and with [`AfterPlan::PowerOff`] power down, every drive in
+/// `drives`, marking each offline. Returns the first failure, after trying all.
+fn release_drives<D: Database + AuditDatabase>(
+    db: &mut D,
+    drives: &[Drive],
+    after: AfterPlan,
+    clock: &Clock,
+    udisks: Udisks,
+) -> Result<()> {
+    if after == AfterPlan::Stay {
+        return Ok(());
+    }
+
+    let mut first_error = None;
+    for drive in drives {
+        let uuid = drive.uuid.as_deref().unwrap_or_default();
+        let released = udisks("unmount", uuid)
+            .and_then(|()| db.update_drive_online_status(&drive.label, false))
+            .and_then(|()| {
+                log_drive_event(
+                    db,
+                    clock,
+                    drive,
+                    "drive_unmounted",
+                    format!("Unmounted drive '{}' after its plan ran", drive.label),
+                )
+            })
+            .and_then(|()| match after {
+                AfterPlan::PowerOff => udisks("power-off", uuid).and_then(|()| {
+                    log_drive_event(
+                        db,
+                        clock,
+                        drive,
+                        "drive_powered_off",
+                        format!("Powered down drive '{}'", drive.label),
+                    )
+                }),
+                _ => Ok(()),
+            });
+        if let Err(e) = released {
+            log::warn!("Could not release drive '{}': {}", drive.label, e);
+            first_error.get_or_insert(e);
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Run `udisksctl <action>` on the block device with filesystem `uuid`,
+/// failing rather than prompting when polkit would ask for a password.
+fn udisksctl(action: &str, uuid: &str) -> Result<()> {
+    let device = format!("/dev/disk/by-uuid/{}", uuid);
+    let output = Command::new("udisksctl")
+        .args([action, "--block-device", &device, "--no-user-interaction"])
+        .output()
+        .map_err(|e| OrdneError::ExternalTool {
+            tool: "udisksctl".to_string(),
+            message: format!("Failed to execute udisksctl: {}", e),
+        })?;
+
+    if !output.status.success() {
+        return Err(OrdneError::ExternalTool {
+            tool: "udisksctl".to_string(),
+            message: format!(
+                "{} {}: {}",
+                action,
+                device,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn log_drive_event<D: AuditDatabase>(
+    db: &mut D,
+    clock: &Clock,
+    drive: &Drive,
+    action: &str,
+    details: String,
+) -> Result<()> {
+    db.log_audit(&AuditLogEntry {
+        id: 0,
+        timestamp: clock.now(),
+        action: action.to_string(),
+        file_id: None,
+        plan_id: None,
+        drive_id: Some(drive.id),
+        details: Some(details),
+        agent_mode: None,
+        before_state: None,
+        after_state: None,
+        actor: None,
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SqliteDatabase;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_automount_covers_listed_local_drives() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, uuid, role, is_online, backend) VALUES (1, 'archive', 'a-uuid', 'offload', 0, 'local');
+                 INSERT INTO drives (id, label, uuid, role, is_online, backend) VALUES (2, 'nas', 'n-uuid', 'source', 1, 'local');
+                 INSERT INTO drives (id, label, uuid, role, is_online, backend, is_cold) VALUES (3, 'vault', 'v-uuid', 'backup', 0, 'local', 1);
+                 INSERT INTO drives (id, label, role, is_online, backend) VALUES (4, 'spare', 'offload', 0, 'local');
+                 INSERT INTO drives (id, label, role, is_online, backend, rclone_remote) VALUES (5, 'cloud', 'offload', 0, 'rclone', 'b2');",
+            )
+            .unwrap();
+        let automount = Automount {
+            drives: ["archive", "vault", "spare", "cloud"]
+                .map(String::from)
+                .to_vec(),
+            after: AfterPlan::PowerOff,
+        };
+
+        // Listed, local, not cold and with a UUID to mount by.
+        let drives = db.list_drives().unwrap();
+        let covered: Vec<(&str, &str)> = drives
+            .iter()
+            .filter_map(|d| automount.uuid_of(d).map(|uuid| (d.label.as_str(), uuid)))
+            .collect();
+        assert_eq!(covered, vec![("archive", "a-uuid")]);
+
+        // Without listed drives, the plan simply runs.
+        assert_eq!(
+            with_plan_drives(
+                &mut db,
+                &[1],
+                &Automount::default(),
+                &Clock::system(),
+                |_| Ok(7)
+            )
+            .unwrap(),
+            7
+        );
+    }
+
+    #[test]
+    fn test_mount_failure_after_udisksctl_releases_mounted_drives() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, uuid, role, is_online, backend) VALUES (1, 'nas', 'n-uuid', 'source', 1, 'local');
+                 INSERT INTO drives (id, label, uuid, role, is_online, backend) VALUES (2, 'archive', 'ordne-test-a-uuid', 'offload', 0, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes) VALUES (1, 1, 'a', 'a', 1);
+                 INSERT INTO migration_plans (id, status) VALUES (1, 'approved');
+                 INSERT INTO migration_steps (plan_id, step_order, file_id, action, source_path, source_drive_id, dest_drive_id, status)
+                 VALUES (1, 1, 1, 'copy', 'a', 1, 2, 'pending');
+                 DROP TABLE audit_log;",
+            )
+            .unwrap();
+        let automount = Automount {
+            drives: vec!["archive".to_string()],
+            after: AfterPlan::Unmount,
+        };
+
+        // udisksctl mounts the drive, then logging the mount fails: the
+        // drive is unmounted again rather than left behind.
+        let calls = RefCell::new(Vec::new());
+        let udisks = |action: &str, uuid: &str| {
+            calls.borrow_mut().push(format!("{} {}", action, uuid));
+            Ok(())
+        };
+        let result = mount_plan_drives(&mut db, &[1], &automount, &Clock::system(), &udisks);
+        assert!(result.is_err());
+        assert_eq!(
+            calls.into_inner(),
+            vec!["mount ordne-test-a-uuid", "unmount ordne-test-a-uuid"]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Content-addressed layout for archive drives.
+//!
+//! Files are stored as `objects/<first two hash chars>/<hash>`, so two files
+//! can never collide on a path and identical content is stored once. Since
+//! the object names mean nothing to a person, every object written is also
+//! recorded in `index.tsv` at the drive root: one line per original file with
+//! its hash, size, source drive and path on that drive.
+
+use crate::error::Result;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+pub const OBJECTS_DIR: &str = "objects";
+pub const INDEX_FILE: &str = "index.tsv";
+
+const INDEX_HEADER: &str = "# hash\tsize_bytes\tsource_drive\tpath\n";
+
+/// How offloaded files are laid out on the target drive.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OffloadLayout {
+    /// Same relative path as on the source drive.
+    #[default]
+    Mirror,
+    /// `objects/<prefix>/<hash>`, see the module docs.
+    ContentAddressed,
+}
+
+impl OffloadLayout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OffloadLayout::Mirror => "mirror",
+            OffloadLayout::ContentAddressed => "cas",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "mirror" => Ok(OffloadLayout::Mirror),
+            "cas" => Ok(OffloadLayout::ContentAddressed),
+            _ => Err(crate::error::OrdneError::Config(format!(
+                "Invalid layout: {} (expected mirror or cas)",
+                s
+            ))),
+        }
+    }
+}
+
+/// Where the object for `hash` lives under `mount`.
+pub fn object_path(mount: &str, hash: &str) -> String {
+    let hash = hash.to_ascii_lowercase();
+    let prefix = hash.get(..2).unwrap_or(&hash);
+    format!(
+        "{}/{}/{}/{}",
+        mount.trim_end_matches('/'),
+        OBJECTS_DIR,
+        prefix,
+        hash
+    )
+}
+
+/// Append one file to the drive's index, creating it with a header if needed.
+pub fn append_index_entry(
+    mount: &str,
+    hash: &str,
+    size_bytes: i64,
+    source_drive: &str,
+    path: &str,
+) -> Result<()> {
+    let index_path = Path::new(mount).join(INDEX_FILE);
+    let is_new = !index_path.exists();
+
+    let mut index = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&index_path)?;
+    if is_new {
+        index.write_all(INDEX_HEADER.as_bytes())?;
+    }
+
+    // Tabs and newlines in paths would break the columns.
+    let path = path.replace(['\t', '\n'], " ");
+    writeln!(
+        index,
+        "{}\t{}\t{}\t{}",
+        hash.to_ascii_lowercase(),
+        size_bytes,
+        source_drive,
+        path
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_object_path_and_index() {
+        assert_eq!(
+            object_path("/mnt/archive/", "ABCDEF"),
+            "/mnt/archive/objects/ab/abcdef"
+        );
+
+        let temp_dir = TempDir::new().unwrap();
+        let mount = temp_dir.path().to_str().unwrap();
+        append_index_entry(mount, "abcdef", 12, "nas", "photos/a.jpg").unwrap();
+        append_index_entry(mount, "abcdef", 12, "nas", "photos/copy\tof a.jpg").unwrap();
+
+        let index = std::fs::read_to_string(temp_dir.path().join(INDEX_FILE)).unwrap();
+        let lines: Vec<_> = index.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with('#'));
+        assert_eq!(lines[2], "abcdef\t12\tnas\tphotos/copy of a.jpg");
+    }
+}
@@ -0,0 +1,399 @@
+//! Cleaning up after a plan that aborted mid-way.
+//!
+//! An aborted run leaves steps that were started but never completed: rsync
+//! keeps the partial destination of an interrupted copy (`--partial`), and
+//! a copy or delete may have finished just before the failure without being
+//! recorded. [`cleanup_plan`] checks each of those steps against the
+//! filesystem. Work that is verifiably done marks the step completed,
+//! partial destinations are removed and the step goes back to pending. Only a
+//! destination written since the step's last attempt started counts as
+//! partial; ordne did not write anything older. Anything it cannot settle
+//! safely, such as a move whose source is gone and whose destination does not
+//! match, or a completed step whose result has disappeared, is reported and
+//! left alone.
+
+use crate::db::{
+    Attribution, AuditDatabase, AuditLogEntry, Backend, Database, HashAlgorithm, MigrationStep,
+    PlanStatus, PlansDatabase, StepAction, StepStatus,
+};
+use crate::error::{OrdneError, Result};
+use crate::migrate::hash;
+use crate::util::clock::Clock;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Something cleanup found but could not settle.
+#[derive(Debug, Clone, Serialize)]
+pub struct Inconsistency {
+    pub step_id: i64,
+    pub path: String,
+    pub problem: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanupReport {
+    pub plan_id: i64,
+    pub dry_run: bool,
+    /// Partial destinations removed, or that would be in a dry run.
+    pub removed_files: Vec<String>,
+    /// Steps whose work turned out to be done, marked completed.
+    pub completed_steps: Vec<i64>,
+    /// Steps that did not finish, reset to pending.
+    pub reset_steps: Vec<i64>,
+    pub inconsistencies: Vec<Inconsistency>,
+    /// Plan status afterwards: `approved` to run the rest, `completed` when
+    /// every step is done, unchanged while anything is inconsistent.
+    pub plan_status: String,
+}
+
+enum Settle {
+    Unchanged,
+    Done,
+    Reset { remove: Option<PathBuf> },
+    Inconsistent(String),
+}
+
+/// Reconcile the steps of aborted plan `plan_id` with the filesystem and
+/// remove partial destinations. With `dry_run`, only report what would be
+/// done. With `safe_mode` on, only the dry run is allowed. The audit entry
+/// is stamped by `clock`.
+pub fn cleanup_plan<D: Database + PlansDatabase + AuditDatabase>(
+    db: &mut D,
+    plan_id: i64,
+    dry_run: bool,
+    safe_mode: bool,
+    clock: &Clock,
+    attribution: Option<&Attribution>,
+) -> Result<CleanupReport> {
+    let plan = db.get_plan(plan_id)?.ok_or(OrdneError::PlanNotFound(plan_id))?;
+    if plan.status != PlanStatus::Aborted {
+        return Err(OrdneError::UserInput(format!(
+            "Plan {} is {}; only aborted plans are cleaned up (interrupted plans resume with 'ordne migrate')",
+            plan_id,
+            plan.status.as_str()
+        )));
+    }
+    if !dry_run && safe_mode {
+        return Err(OrdneError::SafeMode(format!("cleanup of plan {}", plan_id)));
+    }
+
+    let mut report = CleanupReport {
+        plan_id,
+        dry_run,
+        plan_status: plan.status.as_str().to_string(),
+        ..Default::default()
+    };
+    let (mut files, mut bytes) = (plan.completed_files, plan.completed_bytes);
+
+    let attempts: HashMap<i64, DateTime<Utc>> = db
+        .get_step_log(plan_id)?
+        .into_iter()
+        .filter_map(|entry| entry.started_at.map(|at| (entry.step_id, at)))
+        .collect();
+    let steps = db.get_steps_for_plan(plan_id)?;
+    for step in &steps {
+        let backend = match step.dest_drive_id {
+            Some(id) => db.get_drive_by_id(id)?.map_or(Backend::Local, |d| d.backend),
+            None => Backend::Local,
+        };
+        match settle_step(step, backend, attempts.get(&step.id).copied())? {
+            Settle::Unchanged => {}
+            Settle::Done => {
+                report.completed_steps.push(step.id);
+                if !dry_run {
+                    db.update_step_status(step.id, StepStatus::Completed, None)?;
+                    db.mark_step_executed(step.id)?;
+                    files += 1;
+                    bytes += db.get_file(step.file_id)?.map_or(0, |f| f.size_bytes);
+                }
+            }
+            Settle::Reset { remove } => {
+                if let Some(path) = remove {
+                    if !dry_run {
+                        fs::remove_file(&path)?;
+                    }
+                    report.removed_files.push(path.to_string_lossy().to_string());
+                }
+                report.reset_steps.push(step.id);
+                if !dry_run {
+                    db.update_step_status(step.id, StepStatus::Pending, None)?;
+                }
+            }
+            Settle::Inconsistent(problem) => report.inconsistencies.push(Inconsistency {
+                step_id: step.id,
+                path: step.dest_path.clone().unwrap_or_else(|| step.source_path.clone()),
+                problem,
+            }),
+        }
+    }
+
+    if dry_run {
+        return Ok(report);
+    }
+
+    db.update_plan_progress(plan_id, files, bytes)?;
+    if report.inconsistencies.is_empty() {
+        let done = db
+            .get_steps_for_plan(plan_id)?
+            .iter()
+            .all(|s| matches!(s.status, StepStatus::Completed | StepStatus::RolledBack));
+        let status = if done { PlanStatus::Completed } else { PlanStatus::Approved };
+        db.update_plan_status(plan_id, status)?;
+        report.plan_status = status.as_str().to_string();
+    }
+
+    db.log_audit(&AuditLogEntry {
+        id: 0,
+        timestamp: clock.now(),
+        action: "plan_cleaned_up".to_string(),
+        file_id: None,
+        plan_id: Some(plan_id),
+        drive_id: None,
+        details: Some(format!(
+            "Removed {} partial files, completed {} steps, reset {} steps; {} inconsistencies remain",
+            report.removed_files.len(),
+            report.completed_steps.len(),
+            report.reset_steps.len(),
+            report.inconsistencies.len()
+        )),
+        agent_mode: Some(attribution.map_or("manual", |a| a.agent_mode.as_str()).to_string()),
+        before_state: Some(serde_json::json!({ "status": plan.status.as_str() }).to_string()),
+        after_state: serde_json::to_string(&report).ok(),
+        actor: attribution.map(|a| a.actor.clone()),
+    })?;
+
+    Ok(report)
+}
+
+/// Settle one step. `attempt_started` is when its last attempt began, if
+/// one was recorded.
+fn settle_step(step: &MigrationStep, backend: Backend, attempt_started: Option<DateTime<Utc>>) -> Result<Settle> {
+    let source = Path::new(&step.source_path);
+    let dest = step.dest_path.as_deref().map(Path::new);
+
+    match step.status {
+        StepStatus::Pending | StepStatus::RolledBack => Ok(Settle::Unchanged),
+        StepStatus::Completed => Ok(match (step.action, dest) {
+            (StepAction::Delete, _) if source.exists() => {
+                Settle::Inconsistent("Deleted, but the file is still there".to_string())
+            }
+            (StepAction::Copy | StepAction::Move | StepAction::Hardlink | StepAction::Symlink, Some(dest))
+                if backend == Backend::Local && fs::symlink_metadata(dest).is_err() =>
+            {
+                Settle::Inconsistent("Completed, but its destination is missing".to_string())
+            }
+            _ => Settle::Unchanged,
+        }),
+        StepStatus::InProgress | StepStatus::Failed | StepStatus::Volatile => {
+            settle_unfinished(step, source, dest, backend, attempt_started)
+        }
+    }
+}
+
+/// A step that was started and did not finish.
+fn settle_unfinished(
+    step: &MigrationStep,
+    source: &Path,
+    dest: Option<&Path>,
+    backend: Backend,
+    attempt_started: Option<DateTime<Utc>>,
+) -> Result<Settle> {
+    if step.action == StepAction::Delete {
+        return Ok(if source.exists() { Settle::Reset { remove: None } } else { Settle::Done });
+    }
+
+    let Some(dest) = dest else {
+        return Ok(Settle::Inconsistent("Step has no destination path".to_string()));
+    };
+    // rclone uploads under a temporary name, so a remote has nothing partial to remove.
+    if backend == Backend::Rclone {
+        return Ok(Settle::Reset { remove: None });
+    }
+    if fs::symlink_metadata(dest).is_err() {
+        return Ok(if source.exists() {
+            Settle::Reset { remove: None }
+        } else {
+            Settle::Inconsistent("Neither the source nor the destination exists".to_string())
+        });
+    }
+
+    match step.action {
+        StepAction::Copy | StepAction::Move => {
+            let is_move = step.action == StepAction::Move;
+            Ok(match (destination_matches(step, source, dest)?, source.exists()) {
+                // The copy of a move finished but its source was not removed;
+                // running the step again copies it once more and removes it.
+                (Some(true), true) if is_move => Settle::Reset { remove: None },
+                (Some(true), _) => Settle::Done,
+                (Some(false), true) if written_since(dest, attempt_started) => {
+                    Settle::Reset { remove: Some(dest.to_path_buf()) }
+                }
+                (Some(false), true) => Settle::Inconsistent(
+                    "The destination does not match and was not written by the step's last attempt".to_string(),
+                ),
+                (Some(false), false) => Settle::Inconsistent(
+                    "The source is gone and the destination does not match its hash".to_string(),
+                ),
+                (None, _) => Settle::Inconsistent(
+                    "The destination exists but no hash or source is left to check it against".to_string(),
+                ),
+            })
+        }
+        StepAction::Hardlink => {
+            let linked = match (fs::metadata(source), fs::metadata(dest)) {
+                (Ok(s), Ok(d)) => s.dev() == d.dev() && s.ino() == d.ino(),
+                _ => false,
+            };
+            Ok(if linked {
+                Settle::Done
+            } else {
+                Settle::Inconsistent("The destination exists but is not a hard link to the source".to_string())
+            })
+        }
+        StepAction::Symlink => Ok(if fs::read_link(dest).is_ok_and(|target| target == source) {
+            Settle::Done
+        } else {
+            Settle::Inconsistent("The destination exists but is not a symlink to the source".to_string())
+        }),
+        StepAction::Delete => unreachable!("deletes are settled above"),
+    }
+}
+
+/// Whether `dest` was modified at or after `attempt_started`, so the attempt
+/// may have written it. Never true without a recorded attempt.
+fn written_since(dest: &Path, attempt_started: Option<DateTime<Utc>>) -> bool {
+    let Some(started) = attempt_started else {
+        return false;
+    };
+    fs::metadata(dest)
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| DateTime::<Utc>::from(modified) >= started)
+}
+
+/// Whether `dest` holds the step's content: against its recorded hash, or
+/// the source's when none was recorded. `None` when there is neither.
+fn destination_matches(step: &MigrationStep, source: &Path, dest: &Path) -> Result<Option<bool>> {
+    if let Some(pre_hash) = &step.pre_hash {
+        let algorithm = HashAlgorithm::resolve(step.hash_algorithm, pre_hash)?;
+        return hash::verify_hash(dest, pre_hash, algorithm).map(Some);
+    }
+    if !source.exists() {
+        return Ok(None);
+    }
+    let expected = hash::compute_hash(source, HashAlgorithm::Blake3)?;
+    hash::verify_hash(dest, &expected, HashAlgorithm::Blake3).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::SqliteDatabase;
+
+    #[test]
+    fn test_cleanup_removes_partial_copies_and_reconciles_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        fs::write(path("a"), "content a").unwrap();
+        fs::write(path("b"), "content b").unwrap();
+        fs::write(path("a.copy"), "content a").unwrap();
+        fs::write(path("b.copy"), "cont").unwrap();
+        fs::write(path("gone.copy"), "partial").unwrap();
+        let hash_a = hash::compute_hash(path("a"), HashAlgorithm::Blake3).unwrap();
+
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'data', 'source', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes) VALUES (1, 1, 'a', 'a', 9), (2, 1, 'b', 'b', 9);
+                 INSERT INTO migration_plans (id, status, total_files, total_bytes) VALUES (1, 'aborted', 4, 36);",
+            )
+            .unwrap();
+        let steps = [
+            ("copy", path("a"), path("a.copy"), "failed", Some(hash_a.as_str())),
+            ("copy", path("b"), path("b.copy"), "in_progress", None),
+            ("move", path("gone"), path("gone.copy"), "failed", Some(hash_a.as_str())),
+            ("delete", path("deleted"), String::new(), "in_progress", None),
+        ];
+        for (order, (action, source, dest, status, pre_hash)) in steps.iter().enumerate() {
+            db.conn()
+                .execute(
+                    "INSERT INTO migration_steps (plan_id, step_order, file_id, action, source_path, source_drive_id,
+                                                  dest_path, dest_drive_id, status, pre_hash, hash_algorithm)
+                     VALUES (1, ?1, 1, ?2, ?3, 1, NULLIF(?4, ''), 1, ?5, ?6, 'blake3')",
+                    rusqlite::params![order as i64 + 1, action, source, dest, status, pre_hash],
+                )
+                .unwrap();
+        }
+
+        // Only the in-progress copy of b got as far as an attempt.
+        db.conn()
+            .execute_batch("UPDATE migration_steps SET started_at = '2000-01-01T00:00:00.000Z' WHERE id = 2;")
+            .unwrap();
+
+        let clock = Clock::fixed(DateTime::parse_from_rfc3339("2026-03-01T12:00:00Z").unwrap().into());
+        let preview = cleanup_plan(&mut db, 1, true, false, &clock, None).unwrap();
+        assert_eq!(preview.removed_files, vec![path("b.copy")]);
+        assert!(Path::new(&path("b.copy")).exists());
+
+        assert!(cleanup_plan(&mut db, 1, false, true, &clock, None).is_err());
+        assert!(Path::new(&path("b.copy")).exists());
+        let report = cleanup_plan(&mut db, 1, false, false, &clock, None).unwrap();
+        assert_eq!(report.completed_steps, vec![1, 4]);
+        assert_eq!(report.reset_steps, vec![2]);
+        assert!(!Path::new(&path("b.copy")).exists());
+        // The source of the move is gone, so its partial copy is all that is left.
+        assert_eq!(report.inconsistencies.len(), 1);
+        assert_eq!(report.inconsistencies[0].step_id, 3);
+        assert!(Path::new(&path("gone.copy")).exists());
+        assert_eq!(report.plan_status, "aborted");
+        assert_eq!(db.get_plan(1).unwrap().unwrap().completed_files, 2);
+        let audit = db.get_audit_entries_for_plan(1).unwrap();
+        assert_eq!(audit[0].action, "plan_cleaned_up");
+        assert_eq!(audit[0].timestamp, clock.now());
+
+        assert!(cleanup_plan(&mut db, 2, true, false, &clock, None).is_err());
+    }
+
+    #[test]
+    fn test_cleanup_keeps_destinations_the_step_did_not_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        fs::write(path("a"), "content a").unwrap();
+        fs::write(path("a.copy"), "someone else's file").unwrap();
+        let hash_a = hash::compute_hash(path("a"), HashAlgorithm::Blake3).unwrap();
+
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'data', 'source', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes) VALUES (1, 1, 'a', 'a', 9);
+                 INSERT INTO migration_plans (id, status, total_files, total_bytes) VALUES (1, 'aborted', 2, 18);",
+            )
+            .unwrap();
+        // One copy failed before it was ever attempted, the other's attempt
+        // started after the destination was last written.
+        for (order, started_at) in [(1, None), (2, Some("2999-01-01T00:00:00.000Z"))] {
+            db.conn()
+                .execute(
+                    "INSERT INTO migration_steps (plan_id, step_order, file_id, action, source_path, source_drive_id,
+                                                  dest_path, dest_drive_id, status, pre_hash, hash_algorithm, started_at)
+                     VALUES (1, ?1, 1, 'copy', ?2, 1, ?3, 1, 'failed', ?4, 'blake3', ?5)",
+                    rusqlite::params![order, path("a"), path("a.copy"), hash_a, started_at],
+                )
+                .unwrap();
+        }
+
+        let report = cleanup_plan(&mut db, 1, false, false, &Clock::default(), None).unwrap();
+        assert!(report.removed_files.is_empty());
+        assert!(report.reset_steps.is_empty());
+        assert_eq!(report.inconsistencies.len(), 2);
+        assert!(Path::new(&path("a.copy")).exists());
+        assert_eq!(report.plan_status, "aborted");
+    }
+}
@@ -0,0 +1,62 @@
+//! Keeping encrypted data encrypted.
+//!
+//! Drives are marked encrypted when they sit on LUKS (see
+//! [`crate::index::discover_device`]) or by hand. Plans may copy Critical
+//! files off an encrypted drive onto one that is not; that is allowed but
+//! flagged, while categories a policy lists under
+//! `[safety] require_encryption` are refused outright by the planner.
+
+use crate::error::Result;
+use crate::util::format::format_bytes;
+use rusqlite::Connection;
+use serde::Serialize;
+
+/// Critical files a plan copies from an encrypted drive to an unencrypted one.
+#[derive(Debug, Clone, Serialize)]
+pub struct EncryptionWarning {
+    pub source_drive: String,
+    pub target_drive: String,
+    pub file_count: i64,
+    pub total_bytes: i64,
+}
+
+impl EncryptionWarning {
+    pub fn message(&self) -> String {
+        format!(
+            "{} Critical files ({}) go from encrypted '{}' to unencrypted '{}'",
+            self.file_count,
+            format_bytes(self.total_bytes),
+            self.source_drive,
+            self.target_drive
+        )
+    }
+}
+
+/// Copies and moves in `plan_id` that would leave Critical data unencrypted,
+/// per source and target drive.
+pub fn plan_encryption_warnings(conn: &Connection, plan_id: i64) -> Result<Vec<EncryptionWarning>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.label, t.label, COUNT(*), COALESCE(SUM(f.size_bytes), 0)
+         FROM migration_steps m
+         JOIN files f ON f.id = m.file_id
+         JOIN drives s ON s.id = m.source_drive_id
+         JOIN drives t ON t.id = m.dest_drive_id
+         WHERE m.plan_id = ?1 AND m.action IN ('copy', 'move') AND f.priority = 'critical'
+           AND s.is_encrypted = 1 AND t.is_encrypted = 0
+         GROUP BY s.id, t.id
+         ORDER BY s.label, t.label",
+    )?;
+
+    let warnings = stmt
+        .query_map([plan_id], |row| {
+            Ok(EncryptionWarning {
+                source_drive: row.get(0)?,
+                target_drive: row.get(1)?,
+                file_count: row.get(2)?,
+                total_bytes: row.get(3)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(warnings)
+}
@@ -1,12 +1,23 @@
+use crate::config::Settings;
+use crate::db::audit::FileSnapshot;
 use crate::db::{
-    AuditDatabase, AuditLogEntry, Backend, Database, MigrationStep, PlanStatus, PlansDatabase,
-    StepAction, StepStatus,
+    Attribution, AuditDatabase, AuditLogEntry, Backend, Database, File, HashAlgorithm,
+    MigrationPlan, MigrationStep, PlanSession, PlanStatus, PlansDatabase, Priority, SessionOwner,
+    SpacePhase, StepAction, StepOrdering, StepStatus, VerificationDatabase, VerificationFailure,
 };
 use crate::error::{OrdneError, Result};
-use crate::migrate::{hash, rclone, rsync, space};
-use chrono::Utc;
+use crate::index::hasher::FileStamp;
+use crate::migrate::graph::{self, StepGraph};
+use crate::migrate::idle::{IdleMonitor, IdlePolicy};
+use crate::migrate::scheduler::{physical_device_key, StepScheduler};
+use crate::migrate::window::ExecutionWindow;
+use crate::migrate::{cas, hash, rclone, rsync, space};
+use crate::util::clock::Clock;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
 
 #[derive(Debug, Clone)]
 pub struct EngineOptions {
@@ -14,6 +25,28 @@ pub struct EngineOptions {
     pub verify_hashes: bool,
     pub retry_count: u32,
     pub enforce_safety: bool,
+    /// Maximum number of steps running at once. 1 executes sequentially.
+    pub max_parallel_steps: usize,
+    /// Maximum number of concurrent steps touching the same physical device.
+    pub per_device_concurrency: usize,
+    /// Recorded on the audit entries written while executing.
+    pub attribution: Option<Attribution>,
+    /// Schedule a re-read of every destination this long after the plan
+    /// completes. `None` schedules nothing.
+    pub verify_after: Option<chrono::Duration>,
+    /// Only start steps inside this daily window; wait for it otherwise.
+    pub window: Option<ExecutionWindow>,
+    /// Cap copy throughput, in KiB/s.
+    pub bandwidth_limit_kbps: Option<u64>,
+    /// Only start steps while nobody is using the machine; wait otherwise.
+    pub idle: Option<IdlePolicy>,
+    /// Stamps audit entries and failures, and decides when windows are open
+    /// and re-verification is due.
+    pub clock: Clock,
+    /// Run as a dry run whatever `dry_run` says, normally
+    /// [`crate::config::Settings::safe_mode`]. By default only
+    /// `ORDNE_SAFE_MODE` turns it on.
+    pub safe_mode: bool,
 }
 
 impl Default for EngineOptions {
@@ -23,18 +56,74 @@ impl Default for EngineOptions {
             verify_hashes: true,
             retry_count: 3,
             enforce_safety: true,
+            max_parallel_steps: 1,
+            per_device_concurrency: 1,
+            attribution: None,
+            verify_after: None,
+            window: None,
+            bandwidth_limit_kbps: None,
+            idle: None,
+            clock: Clock::default(),
+            safe_mode: Settings::default().safe_mode(),
         }
     }
 }
 
-pub struct MigrationEngine<'a, D: Database + PlansDatabase + AuditDatabase> {
+/// A step resolved against the database so it can run without it.
+#[derive(Debug, Clone)]
+struct StepJob {
+    step: MigrationStep,
+    dest_backend: Backend,
+    rclone_remote: Option<String>,
+}
+
+/// Result of running a step, persisted by the engine afterwards.
+#[derive(Debug, Clone, Default)]
+struct StepOutcome {
+    bytes: i64,
+    pre_hash: Option<String>,
+    post_hash: Option<String>,
+    hash_algorithm: Option<HashAlgorithm>,
+}
+
+/// Progress of the running execution session, checkpointed after each step.
+#[derive(Debug, Clone, Copy)]
+struct Session {
+    id: i64,
+    files: i64,
+    bytes: i64,
+}
+
+pub struct MigrationEngine<'a, D: Database + PlansDatabase + AuditDatabase + VerificationDatabase> {
     db: &'a mut D,
     options: EngineOptions,
+    session: Option<Session>,
+    idle: Option<IdleMonitor>,
 }
 
-impl<'a, D: Database + PlansDatabase + AuditDatabase> MigrationEngine<'a, D> {
-    pub fn new(db: &'a mut D, options: EngineOptions) -> Self {
-        Self { db, options }
+impl<'a, D: Database + PlansDatabase + AuditDatabase + VerificationDatabase> MigrationEngine<'a, D> {
+    /// With [`EngineOptions::safe_mode`] the engine always dry-runs.
+    pub fn new(db: &'a mut D, mut options: EngineOptions) -> Self {
+        if !options.dry_run && options.safe_mode {
+            log::warn!("Safe mode is on; running as a dry run");
+            options.dry_run = true;
+        }
+        let idle = options.idle.map(IdleMonitor::new);
+        Self {
+            db,
+            options,
+            session: None,
+            idle,
+        }
+    }
+
+    fn agent_mode(&self, default: &str) -> Option<String> {
+        let attribution = self.options.attribution.as_ref();
+        Some(attribution.map_or(default, |a| a.agent_mode.as_str()).to_string())
+    }
+
+    fn actor(&self) -> Option<String> {
+        self.options.attribution.as_ref().map(|a| a.actor.clone())
     }
 
     pub fn execute_plan(&mut self, plan_id: i64) -> Result<()> {
@@ -43,10 +132,26 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> MigrationEngine<'a, D> {
             .get_plan(plan_id)?
             .ok_or(OrdneError::PlanNotFound(plan_id))?;
 
-        if plan.status != PlanStatus::Approved {
+        // Paused and in-progress plans resume from their last checkpoint.
+        if !matches!(
+            plan.status,
+            PlanStatus::Approved | PlanStatus::Queued | PlanStatus::Paused | PlanStatus::InProgress
+        ) {
             return Err(OrdneError::PlanNotApproved(plan_id));
         }
 
+        if !self.options.dry_run {
+            self.take_over_plan(plan_id)?;
+        }
+
+        let pending = self.db.get_pending_steps(plan_id)?;
+        let offline = self.offline_drives(&pending)?;
+        if !offline.is_empty() {
+            return self.queue_plan(plan_id, offline.join(", "));
+        }
+
+        self.preflight(&pending)?;
+
         if self.options.dry_run {
             log::info!("DRY RUN: Would execute plan {}", plan_id);
             return self.dry_run_plan(plan_id);
@@ -54,59 +159,71 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> MigrationEngine<'a, D> {
 
         self.db
             .update_plan_status(plan_id, PlanStatus::InProgress)?;
-
+        self.record_space_usage(plan_id, SpacePhase::Before)?;
+
+        let (action, details) = if plan.completed_files > 0 {
+            (
+                "plan_execution_resumed",
+                format!(
+                    "Resuming at {} of {} files, {} bytes done",
+                    plan.completed_files, plan.total_files, plan.completed_bytes
+                ),
+            )
+        } else {
+            ("plan_execution_started", "Starting plan execution".to_string())
+        };
         self.db.log_audit(&AuditLogEntry {
             id: 0,
-            timestamp: Utc::now(),
-            action: "plan_execution_started".to_string(),
+            timestamp: self.options.clock.now(),
+            action: action.to_string(),
             file_id: None,
             plan_id: Some(plan_id),
             drive_id: None,
-            details: Some("Starting plan execution".to_string()),
-            agent_mode: Some("automated".to_string()),
+            details: Some(details),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
         })?;
 
-        let steps = self.db.get_pending_steps(plan_id)?;
-        let mut completed_files = 0;
-        let mut completed_bytes = 0i64;
+        let graph = StepGraph::new(&self.db.get_plan_dependencies(plan_id)?);
+        let steps = self.execution_order(&plan, &graph, self.db.get_pending_steps(plan_id)?)?;
 
-        for step in steps {
-            match self.execute_step(&step) {
-                Ok(step_bytes) => {
-                    completed_files += 1;
-                    completed_bytes += step_bytes;
-                    self.db
-                        .update_plan_progress(plan_id, completed_files, completed_bytes)?;
-                }
-                Err(e) => {
-                    log::error!("Step {} failed: {}", step.id, e);
-                    self.db
-                        .update_step_status(step.id, StepStatus::Failed, Some(e.to_string()))?;
-
-                    self.db.log_audit(&AuditLogEntry {
-                        id: 0,
-                        timestamp: Utc::now(),
-                        action: "step_failed".to_string(),
-                        file_id: Some(step.file_id),
-                        plan_id: Some(plan_id),
-                        drive_id: Some(step.source_drive_id),
-                        details: Some(format!("Step failed: {}", e)),
-                        agent_mode: Some("automated".to_string()),
-                    })?;
+        let done = (plan.completed_files, plan.completed_bytes);
+        self.begin_session(plan_id)?;
+        let result = self
+            .run_steps(plan_id, steps, &graph, done)
+            .and_then(|done| self.retry_volatile_steps(plan_id, &plan, &graph, done));
 
-                    self.db
-                        .update_plan_status(plan_id, PlanStatus::Aborted)?;
-                    return Err(e);
-                }
+        let (completed_files, completed_bytes) = match result {
+            Ok(totals) => totals,
+            Err(e) => {
+                self.end_session("failed")?;
+                self.db
+                    .update_plan_status(plan_id, PlanStatus::Aborted)?;
+                return Err(e);
             }
+        };
+
+        let volatile = self.volatile_step_count(plan_id)?;
+        if volatile > 0 {
+            self.end_session("files_changing")?;
+            self.db.update_plan_status(plan_id, PlanStatus::Paused)?;
+            return self.log_pause_event(plan_id, "plan_paused", format!(
+                "{} files were still changing after {} files, {} bytes; run the plan again to retry them",
+                volatile, completed_files, completed_bytes
+            ));
         }
 
+        self.end_session("completed")?;
+        self.record_space_usage(plan_id, SpacePhase::After)?;
+
         self.db
             .update_plan_status(plan_id, PlanStatus::Completed)?;
 
         self.db.log_audit(&AuditLogEntry {
             id: 0,
-            timestamp: Utc::now(),
+            timestamp: self.options.clock.now(),
             action: "plan_execution_completed".to_string(),
             file_id: None,
             plan_id: Some(plan_id),
@@ -115,223 +232,756 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> MigrationEngine<'a, D> {
                 "Completed {} files, {} bytes",
                 completed_files, completed_bytes
             )),
-            agent_mode: Some("automated".to_string()),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
         })?;
 
+        if let Some(delay) = self.options.verify_after {
+            self.schedule_reverification(plan_id, delay)?;
+        }
+
         Ok(())
     }
 
-    fn execute_step(&mut self, step: &MigrationStep) -> Result<i64> {
-        log::info!(
-            "Executing step {}: {:?} {} -> {:?}",
-            step.id,
-            step.action,
-            step.source_path,
-            step.dest_path
-        );
+    /// Labels of offline drives that pending steps read from or write to.
+    fn offline_drives(&self, steps: &[MigrationStep]) -> Result<Vec<String>> {
+        let drive_ids: std::collections::BTreeSet<i64> = steps
+            .iter()
+            .flat_map(|s| std::iter::once(s.source_drive_id).chain(s.dest_drive_id))
+            .collect();
+
+        let mut labels = Vec::new();
+        for drive_id in drive_ids {
+            if let Some(drive) = self.db.get_drive_by_id(drive_id)? {
+                if !drive.is_online {
+                    labels.push(drive.label);
+                }
+            }
+        }
+        Ok(labels)
+    }
 
-        self.db
-            .update_step_status(step.id, StepStatus::InProgress, None)?;
-
-        let result = match step.action {
-            StepAction::Copy => self.execute_copy(step),
-            StepAction::Move => self.execute_move(step),
-            StepAction::Delete => self.execute_delete(step),
-            StepAction::Hardlink => self.execute_hardlink(step),
-            StepAction::Symlink => self.execute_symlink(step),
-        };
+    /// Leave the plan for later instead of failing it: cold drives are
+    /// offline by design, and the plan can run as soon as they are connected.
+    fn queue_plan(&mut self, plan_id: i64, drives: String) -> Result<()> {
+        if !self.options.dry_run {
+            self.db.update_plan_status(plan_id, PlanStatus::Queued)?;
+            self.db.log_audit(&AuditLogEntry {
+                id: 0,
+                timestamp: self.options.clock.now(),
+                action: "plan_queued".to_string(),
+                file_id: None,
+                plan_id: Some(plan_id),
+                drive_id: None,
+                details: Some(format!("Waiting for drives: {}", drives)),
+                agent_mode: self.agent_mode("automated"),
+                before_state: None,
+                after_state: None,
+                actor: self.actor(),
+            })?;
+        }
+        Err(OrdneError::PlanQueued { plan_id, drives })
+    }
 
-        match result {
-            Ok(bytes) => {
-                self.db
-                    .update_step_status(step.id, StepStatus::Completed, None)?;
-                self.db.mark_step_executed(step.id)?;
+    /// Queue a verification job if the plan wrote anything worth re-reading.
+    fn schedule_reverification(&mut self, plan_id: i64, delay: chrono::Duration) -> Result<()> {
+        let copied = self
+            .db
+            .get_steps_for_plan(plan_id)?
+            .iter()
+            .any(|s| matches!(s.action, StepAction::Copy | StepAction::Move));
+        if !copied {
+            return Ok(());
+        }
 
-                self.db.log_audit(&AuditLogEntry {
-                    id: 0,
-                    timestamp: Utc::now(),
-                    action: format!("step_completed_{}", step.action.as_str()),
-                    file_id: Some(step.file_id),
-                    plan_id: Some(step.plan_id),
-                    drive_id: Some(step.source_drive_id),
-                    details: Some(format!("Step {} completed successfully", step.id)),
-                    agent_mode: Some("automated".to_string()),
-                })?;
+        let due_at = self.options.clock.now() + delay;
+        let job_id = self.db.schedule_verification_job(plan_id, due_at)?;
 
-                Ok(bytes)
-            }
-            Err(e) => {
-                self.db
-                    .update_step_status(step.id, StepStatus::Failed, Some(e.to_string()))?;
-                Err(e)
-            }
-        }
+        self.db.log_audit(&AuditLogEntry {
+            id: 0,
+            timestamp: self.options.clock.now(),
+            action: "verification_scheduled".to_string(),
+            file_id: None,
+            plan_id: Some(plan_id),
+            drive_id: None,
+            details: Some(format!("Job {} due at {}", job_id, due_at.to_rfc3339())),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
+        })?;
+
+        Ok(())
     }
 
-    fn execute_copy(&mut self, step: &MigrationStep) -> Result<i64> {
-        let source_path = Path::new(&step.source_path);
-        let dest_path = step
-            .dest_path
-            .as_ref()
-            .ok_or_else(|| OrdneError::Migration("No destination path".to_string()))?;
-        let dest_path = Path::new(dest_path);
+    fn in_window(&self) -> bool {
+        self.options
+            .window
+            .is_none_or(|w| w.contains(self.options.clock.local_time()))
+    }
+
+    /// Sleep until the execution window opens, if one is set and closed.
+    fn wait_for_window(&mut self, plan_id: i64) -> Result<()> {
+        let Some(window) = self.options.window else {
+            return Ok(());
+        };
+        let Some(wait) = window.time_until_open(self.options.clock.local_time()) else {
+            return Ok(());
+        };
 
-        if !source_path.exists() {
-            return Err(OrdneError::FileNotFound(source_path.to_path_buf()));
+        log::info!("Outside execution window {}; pausing plan {} for {}", window, plan_id, wait);
+        let session = self.session;
+        self.end_session("window_closed")?;
+        self.db.update_plan_status(plan_id, PlanStatus::Paused)?;
+        self.log_pause_event(plan_id, "plan_paused", format!(
+            "Outside window {} after {} files, {} bytes this session; resuming in {} minutes",
+            window,
+            session.map_or(0, |s| s.files),
+            session.map_or(0, |s| s.bytes),
+            wait.num_minutes()
+        ))?;
+
+        while let Some(wait) = window.time_until_open(self.options.clock.local_time()) {
+            self.options.clock.sleep(wait.max(chrono::Duration::seconds(1)));
         }
 
-        let file_size = fs::metadata(source_path)?.len() as i64;
+        self.db.update_plan_status(plan_id, PlanStatus::InProgress)?;
+        self.begin_session(plan_id)?;
+        self.log_pause_event(plan_id, "plan_resumed", format!("Window {} opened", window))
+    }
 
-        let pre_hash = if self.options.verify_hashes {
-            let hash = hash::compute_blake3_hash(source_path)?;
-            self.db.update_step_hashes(step.id, hash.clone(), None)?;
-            hash
-        } else {
-            step.pre_hash
-                .clone()
-                .ok_or_else(|| OrdneError::Migration("No pre-hash available".to_string()))?
+    fn machine_busy(&mut self) -> bool {
+        self.idle.as_mut().is_some_and(|m| m.busy_reason().is_some())
+    }
+
+    /// Sleep while someone is using the machine, if an idle policy is set.
+    fn wait_for_idle(&mut self, plan_id: i64) -> Result<()> {
+        let Some(reason) = self.idle.as_mut().and_then(|m| m.busy_reason()) else {
+            return Ok(());
         };
 
-        if let Some(parent) = dest_path.parent() {
-            fs::create_dir_all(parent)?;
+        log::info!("Machine busy ({}); pausing plan {} until idle", reason, plan_id);
+        let session = self.session;
+        self.end_session("machine_busy")?;
+        self.db.update_plan_status(plan_id, PlanStatus::Paused)?;
+        self.log_pause_event(plan_id, "plan_paused", format!(
+            "Machine busy ({}) after {} files, {} bytes this session; resuming when idle",
+            reason,
+            session.map_or(0, |s| s.files),
+            session.map_or(0, |s| s.bytes)
+        ))?;
+
+        if let Some(monitor) = self.idle.as_mut() {
+            monitor.wait();
         }
 
-        let dest_drive = self
+        self.db.update_plan_status(plan_id, PlanStatus::InProgress)?;
+        self.begin_session(plan_id)?;
+        self.log_pause_event(plan_id, "plan_resumed", "Machine idle".to_string())
+    }
+
+    /// Refuse a plan whose open session belongs to a process that is still
+    /// running. Otherwise no run owns the plan: sessions whose owner died are
+    /// ended as `interrupted`, and the step a killed run left `in_progress`
+    /// goes back to pending. It simply reruns: copies start over and deletes
+    /// skip files already gone.
+    fn take_over_plan(&mut self, plan_id: i64) -> Result<()> {
+        let open: Vec<PlanSession> = self
             .db
-            .get_drive_by_id(
-                step.dest_drive_id
-                    .ok_or_else(|| OrdneError::Migration("No destination drive".to_string()))?,
-            )?
-            .ok_or_else(|| OrdneError::DriveNotFound("destination".to_string()))?;
-
-        match dest_drive.backend {
-            Backend::Local => {
-                rsync::copy_file(source_path, dest_path)?;
-            }
-            Backend::Rclone => {
-                let remote = dest_drive
-                    .rclone_remote
-                    .as_ref()
-                    .ok_or_else(|| OrdneError::Migration("No rclone remote".to_string()))?;
-                let remote_path = dest_path
-                    .to_str()
-                    .ok_or_else(|| OrdneError::Migration("Invalid path".to_string()))?;
-                rclone::copy_to_remote(source_path, remote, remote_path)?;
-            }
+            .list_plan_sessions(plan_id)?
+            .into_iter()
+            .filter(|s| s.ended_at.is_none())
+            .collect();
+        if let Some(owner) = open.iter().filter_map(|s| s.owner.as_ref()).find(|o| o.is_alive()) {
+            return Err(OrdneError::PlanRunning {
+                plan_id,
+                pid: owner.pid,
+                host: owner.host.clone(),
+            });
+        }
+
+        for session in &open {
+            log::warn!("Plan {} session {} was interrupted; taking it over", plan_id, session.id);
+            self.db.end_plan_session(session.id, "interrupted")?;
         }
+        self.db.reset_interrupted_steps(plan_id)?;
+        Ok(())
+    }
+
+    fn begin_session(&mut self, plan_id: i64) -> Result<()> {
+        let id = self.db.start_plan_session(plan_id, &SessionOwner::current())?;
+        self.session = Some(Session { id, files: 0, bytes: 0 });
+        Ok(())
+    }
+
+    fn end_session(&mut self, reason: &str) -> Result<()> {
+        if let Some(session) = self.session.take() {
+            self.db.end_plan_session(session.id, reason)?;
+        }
+        Ok(())
+    }
 
-        if self.options.verify_hashes && dest_drive.backend == Backend::Local {
-            hash::verify_destination(dest_path, &pre_hash)?;
+    /// Persist progress after a completed step, so an interrupted or paused
+    /// plan resumes exactly where it stopped.
+    fn checkpoint(
+        &mut self,
+        plan_id: i64,
+        step_id: i64,
+        step_bytes: i64,
+        totals: (i32, i64),
+    ) -> Result<()> {
+        self.db.update_plan_progress(plan_id, totals.0, totals.1)?;
+        if let Some(session) = self.session.as_mut() {
+            session.files += 1;
+            session.bytes += step_bytes;
+            let session = *session;
             self.db
-                .update_step_hashes(step.id, pre_hash.clone(), Some(pre_hash))?;
+                .update_plan_session(session.id, session.files, session.bytes, step_id)?;
         }
+        Ok(())
+    }
+
+    fn log_pause_event(&mut self, plan_id: i64, action: &str, details: String) -> Result<()> {
+        self.db.log_audit(&AuditLogEntry {
+            id: 0,
+            timestamp: self.options.clock.now(),
+            action: action.to_string(),
+            file_id: None,
+            plan_id: Some(plan_id),
+            drive_id: None,
+            details: Some(details),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
+        })?;
+        Ok(())
+    }
 
-        Ok(file_size)
+    fn run_steps(
+        &mut self,
+        plan_id: i64,
+        steps: Vec<MigrationStep>,
+        graph: &StepGraph,
+        done: (i32, i64),
+    ) -> Result<(i32, i64)> {
+        if self.options.max_parallel_steps > 1 {
+            self.execute_parallel(plan_id, steps, graph, done)
+        } else {
+            self.execute_sequential(plan_id, steps, graph, done)
+        }
     }
 
-    fn execute_move(&mut self, step: &MigrationStep) -> Result<i64> {
-        let bytes = self.execute_copy(step)?;
+    /// Give steps skipped because their source was changing one more try,
+    /// now that the rest of the plan has run. Steps waiting on them were left
+    /// pending and run with them.
+    fn retry_volatile_steps(
+        &mut self,
+        plan_id: i64,
+        plan: &MigrationPlan,
+        graph: &StepGraph,
+        done: (i32, i64),
+    ) -> Result<(i32, i64)> {
+        let volatile = self.volatile_step_count(plan_id)?;
+        if volatile == 0 {
+            return Ok(done);
+        }
+
+        log::info!("Retrying {} steps whose source files were changing", volatile);
+        self.db.reset_interrupted_steps(plan_id)?;
+        let steps = self.execution_order(plan, graph, self.db.get_pending_steps(plan_id)?)?;
+        self.run_steps(plan_id, steps, graph, done)
+    }
 
-        let step = self
+    fn volatile_step_count(&self, plan_id: i64) -> Result<usize> {
+        Ok(self
             .db
-            .get_step(step.id)?
-            .ok_or_else(|| OrdneError::Migration("Step disappeared".to_string()))?;
+            .get_steps_for_plan(plan_id)?
+            .iter()
+            .filter(|s| s.status == StepStatus::Volatile)
+            .count())
+    }
 
-        if step.status != StepStatus::Completed {
-            return Err(OrdneError::Migration(
-                "Copy step did not complete".to_string(),
-            ));
+    fn execute_sequential(
+        &mut self,
+        plan_id: i64,
+        steps: Vec<MigrationStep>,
+        graph: &StepGraph,
+        done: (i32, i64),
+    ) -> Result<(i32, i64)> {
+        let (mut completed_files, mut completed_bytes) = done;
+        // Volatile steps and the steps that depend on them, directly or not.
+        let mut deferred = HashSet::new();
+
+        for step in steps {
+            if graph.dependencies(step.id).iter().any(|d| deferred.contains(d)) {
+                deferred.insert(step.id);
+                continue;
+            }
+            self.wait_for_window(plan_id)?;
+            self.wait_for_idle(plan_id)?;
+            match self.execute_step(&step) {
+                Ok(step_bytes) => {
+                    completed_files += 1;
+                    completed_bytes += step_bytes;
+                    self.checkpoint(plan_id, step.id, step_bytes, (completed_files, completed_bytes))?;
+                }
+                Err(e @ OrdneError::FileChanged { .. }) => {
+                    self.record_volatile_step(plan_id, &step, &e)?;
+                    deferred.insert(step.id);
+                }
+                Err(e) => {
+                    self.record_step_failure(plan_id, &step, &e)?;
+                    return Err(e);
+                }
+            }
         }
 
-        let source_path = Path::new(&step.source_path);
+        Ok((completed_files, completed_bytes))
+    }
+
+    /// Run steps on worker threads, keeping different physical devices busy
+    /// at the same time while never exceeding `per_device_concurrency` on any
+    /// one of them. Database access stays on the calling thread.
+    fn execute_parallel(
+        &mut self,
+        plan_id: i64,
+        steps: Vec<MigrationStep>,
+        graph: &StepGraph,
+        done: (i32, i64),
+    ) -> Result<(i32, i64)> {
+        let devices = self.step_devices(&steps)?;
+        let mut scheduler =
+            StepScheduler::new(steps, graph, devices, self.options.per_device_concurrency);
+        let max_workers = self.options.max_parallel_steps;
+
+        let (mut completed_files, mut completed_bytes) = done;
+        let mut failure: Option<OrdneError> = None;
+        let mut volatile = 0usize;
+
+        thread::scope(|scope| -> Result<()> {
+            let (tx, rx) = mpsc::channel();
+            let mut in_flight = 0usize;
+
+            loop {
+                if in_flight == 0 && failure.is_none() && scheduler.pending() > 0 {
+                    self.wait_for_window(plan_id)?;
+                    self.wait_for_idle(plan_id)?;
+                }
+
+                // Outside the window or while the machine is in use, running
+                // steps finish but no new ones start.
+                while failure.is_none()
+                    && in_flight < max_workers
+                    && self.in_window()
+                    && !self.machine_busy()
+                {
+                    let Some(step) = scheduler.next_ready() else {
+                        break;
+                    };
+
+                    let job = match self.start_step(&step) {
+                        Ok(job) => job,
+                        Err(e) => {
+                            scheduler.fail(step.id);
+                            self.record_step_failure(plan_id, &step, &e)?;
+                            failure = Some(e);
+                            break;
+                        }
+                    };
+
+                    let tx = tx.clone();
+                    let options = self.options.clone();
+                    scope.spawn(move || {
+                        let result = run_step(&job, &options);
+                        let _ = tx.send((job.step, result));
+                    });
+                    in_flight += 1;
+                }
+
+                if in_flight == 0 {
+                    break;
+                }
 
-        if self.options.enforce_safety && self.options.verify_hashes {
-            if let Some(pre_hash) = &step.pre_hash {
-                hash::verify_source_unchanged(source_path, pre_hash)?;
+                let (step, result) = rx
+                    .recv()
+                    .map_err(|_| OrdneError::Migration("Worker thread disconnected".to_string()))?;
+                in_flight -= 1;
+
+                match self.finish_step(&step, result) {
+                    Ok(step_bytes) => {
+                        scheduler.complete(step.id);
+                        completed_files += 1;
+                        completed_bytes += step_bytes;
+                        self.checkpoint(plan_id, step.id, step_bytes, (completed_files, completed_bytes))?;
+                    }
+                    // Its dependents stay blocked in the scheduler until the retry.
+                    Err(e @ OrdneError::FileChanged { .. }) => {
+                        scheduler.fail(step.id);
+                        self.record_volatile_step(plan_id, &step, &e)?;
+                        volatile += 1;
+                    }
+                    Err(e) => {
+                        scheduler.fail(step.id);
+                        self.record_step_failure(plan_id, &step, &e)?;
+                        if failure.is_none() {
+                            failure = Some(e);
+                        }
+                    }
+                }
             }
+
+            Ok(())
+        })?;
+
+        if let Some(e) = failure {
+            return Err(e);
         }
 
-        fs::remove_file(source_path)?;
+        if scheduler.pending() > 0 && volatile == 0 {
+            return Err(OrdneError::Migration(format!(
+                "{} steps could not be scheduled",
+                scheduler.pending()
+            )));
+        }
 
-        Ok(bytes)
+        Ok((completed_files, completed_bytes))
     }
 
-    fn execute_delete(&mut self, step: &MigrationStep) -> Result<i64> {
-        let source_path = Path::new(&step.source_path);
+    /// Devices each step occupies: the source drive and, if any, the destination.
+    fn step_devices(&self, steps: &[MigrationStep]) -> Result<HashMap<i64, Vec<String>>> {
+        let keys: HashMap<i64, String> = self
+            .db
+            .list_drives()?
+            .iter()
+            .map(|d| (d.id, physical_device_key(d)))
+            .collect();
+
+        Ok(steps
+            .iter()
+            .map(|step| {
+                let mut devices = Vec::new();
+                for drive_id in std::iter::once(step.source_drive_id).chain(step.dest_drive_id) {
+                    let key = keys
+                        .get(&drive_id)
+                        .cloned()
+                        .unwrap_or_else(|| format!("drive:{}", drive_id));
+                    if !devices.contains(&key) {
+                        devices.push(key);
+                    }
+                }
+                (step.id, devices)
+            })
+            .collect())
+    }
 
-        if !source_path.exists() {
-            log::warn!("File already deleted: {}", step.source_path);
-            return Ok(0);
-        }
+    fn record_step_failure(&mut self, plan_id: i64, step: &MigrationStep, e: &OrdneError) -> Result<()> {
+        log::error!("Step {} failed: {}", step.id, e);
+        self.db
+            .update_step_status(step.id, StepStatus::Failed, Some(e.to_string()))?;
+
+        self.db.log_audit(&AuditLogEntry {
+            id: 0,
+            timestamp: self.options.clock.now(),
+            action: "step_failed".to_string(),
+            file_id: Some(step.file_id),
+            plan_id: Some(plan_id),
+            drive_id: Some(step.source_drive_id),
+            details: Some(format!("Step failed: {}", e)),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
+        })?;
 
-        let file_size = fs::metadata(source_path)?.len() as i64;
+        let (path, actual_hash) = match e {
+            OrdneError::DestinationVerification { path } => (path.display().to_string(), None),
+            OrdneError::HashMismatch { actual, .. } => (
+                step.dest_path.clone().unwrap_or_else(|| step.source_path.clone()),
+                Some(actual.clone()),
+            ),
+            _ => return Ok(()),
+        };
+        self.db.record_verification_failure(&VerificationFailure {
+            id: 0,
+            file_id: Some(step.file_id),
+            plan_id: Some(plan_id),
+            step_id: Some(step.id),
+            drive_id: step.dest_drive_id.or(Some(step.source_drive_id)),
+            path,
+            expected_hash: step.pre_hash.clone(),
+            actual_hash,
+            reason: "mismatch".to_string(),
+            detected_at: self.options.clock.now(),
+            acknowledged_at: None,
+        })?;
 
-        if self.options.enforce_safety {
-            if let Some(expected_hash) = &step.pre_hash {
-                hash::verify_source_unchanged(source_path, expected_hash)?;
-            } else {
-                return Err(OrdneError::Migration(
-                    "Cannot delete without hash verification".to_string(),
-                ));
+        Ok(())
+    }
+
+    /// Skip a step whose source was written to while it was read, to retry
+    /// it once the rest of the plan has run.
+    fn record_volatile_step(&mut self, plan_id: i64, step: &MigrationStep, e: &OrdneError) -> Result<()> {
+        log::warn!("Step {} skipped: {}", step.id, e);
+        self.db
+            .update_step_status(step.id, StepStatus::Volatile, Some(e.to_string()))?;
+
+        self.db.log_audit(&AuditLogEntry {
+            id: 0,
+            timestamp: self.options.clock.now(),
+            action: "step_volatile".to_string(),
+            file_id: Some(step.file_id),
+            plan_id: Some(plan_id),
+            drive_id: Some(step.source_drive_id),
+            details: Some(format!("Step skipped: {}", e)),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
+        })?;
+        Ok(())
+    }
+
+    fn execute_step(&mut self, step: &MigrationStep) -> Result<i64> {
+        let job = self.start_step(step)?;
+        let result = run_step(&job, &self.options);
+        self.finish_step(step, result)
+    }
+
+    /// Check the step may run, resolve its destination and mark it in progress.
+    fn start_step(&mut self, step: &MigrationStep) -> Result<StepJob> {
+        log::info!(
+            "Executing step {}: {:?} {} -> {:?}",
+            step.id,
+            step.action,
+            step.source_path,
+            step.dest_path
+        );
+
+        self.check_dependencies(step)?;
+
+        let (dest_backend, rclone_remote) = match step.action {
+            StepAction::Copy | StepAction::Move => {
+                let dest_drive = self
+                    .db
+                    .get_drive_by_id(
+                        step.dest_drive_id
+                            .ok_or_else(|| OrdneError::Migration("No destination drive".to_string()))?,
+                    )?
+                    .ok_or_else(|| OrdneError::DriveNotFound("destination".to_string()))?;
+                (dest_drive.backend, dest_drive.rclone_remote)
             }
-        }
+            _ => (Backend::Local, None),
+        };
 
-        fs::remove_file(source_path)?;
+        self.db.start_step_attempt(step.id)?;
 
-        Ok(file_size)
+        Ok(StepJob {
+            step: step.clone(),
+            dest_backend,
+            rclone_remote,
+        })
     }
 
-    fn execute_hardlink(&mut self, step: &MigrationStep) -> Result<i64> {
-        let source_path = Path::new(&step.source_path);
-        let dest_path = step
-            .dest_path
-            .as_ref()
-            .ok_or_else(|| OrdneError::Migration("No destination path".to_string()))?;
-        let dest_path = Path::new(dest_path);
+    /// Persist the outcome of a step that has run.
+    fn finish_step(&mut self, step: &MigrationStep, result: Result<StepOutcome>) -> Result<i64> {
+        match result {
+            Ok(outcome) => {
+                let mut executed = step.clone();
+                if let (Some(pre_hash), Some(algorithm)) =
+                    (outcome.pre_hash, outcome.hash_algorithm)
+                {
+                    executed.pre_hash = Some(pre_hash.clone());
+                    executed.post_hash = outcome.post_hash.clone();
+                    executed.hash_algorithm = Some(algorithm);
+                    self.db
+                        .update_step_hashes(step.id, pre_hash, outcome.post_hash, algorithm)?;
+                }
 
-        if !source_path.exists() {
-            return Err(OrdneError::FileNotFound(source_path.to_path_buf()));
-        }
+                if matches!(step.action, StepAction::Copy | StepAction::Move) {
+                    crate::util::progress::add_bytes_processed(outcome.bytes.max(0) as u64);
+                }
+
+                let file = self.db.get_file(step.file_id)?;
+                let (before, after) = step_snapshots(&executed, file.as_ref(), Some(outcome.bytes));
+                if step.action == StepAction::Delete {
+                    if let Some(file) = &file {
+                        self.index_offloaded_file(step, file)?;
+                    }
+                }
+
+                self.db.finish_step_attempt(step.id, Some(outcome.bytes))?;
+                self.db
+                    .update_step_status(step.id, StepStatus::Completed, None)?;
+                self.db.mark_step_executed(step.id)?;
+
+                self.db.log_audit(&AuditLogEntry {
+                    id: 0,
+                    timestamp: self.options.clock.now(),
+                    action: format!("step_completed_{}", step.action.as_str()),
+                    file_id: Some(step.file_id),
+                    plan_id: Some(step.plan_id),
+                    drive_id: Some(step.source_drive_id),
+                    details: Some(format!("Step {} completed successfully", step.id)),
+                    agent_mode: self.agent_mode("automated"),
+                    before_state: before.to_json(),
+                    after_state: after.to_json(),
+                    actor: self.actor(),
+                })?;
 
-        if let Some(parent) = dest_path.parent() {
-            fs::create_dir_all(parent)?;
+                Ok(outcome.bytes)
+            }
+            Err(e) => {
+                self.db.finish_step_attempt(step.id, None)?;
+                self.db
+                    .update_step_status(step.id, StepStatus::Failed, Some(e.to_string()))?;
+                Err(e)
+            }
         }
+    }
 
-        fs::hard_link(source_path, dest_path)?;
+    /// Record a file in the archive index once its source is gone, if it was
+    /// offloaded into a content-addressed object.
+    ///
+    /// Indexing on delete rather than on copy covers files whose content was
+    /// already stored by another file's copy, and keeps re-copies of an object
+    /// from adding it twice.
+    fn index_offloaded_file(&self, step: &MigrationStep, file: &File) -> Result<()> {
+        for dep_id in self.db.get_step_dependencies(step.id)? {
+            let Some(copy) = self.db.get_step(dep_id)? else {
+                continue;
+            };
+            let (Some(dest_path), Some(drive_id)) = (copy.dest_path.as_deref(), copy.dest_drive_id)
+            else {
+                continue;
+            };
+            let Some(mount) = self
+                .db
+                .get_drive_by_id(drive_id)?
+                .filter(|d| d.backend == Backend::Local)
+                .and_then(|d| d.mount_path)
+            else {
+                continue;
+            };
+            let Some(hash) = Path::new(dest_path).file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if cas::object_path(&mount, hash) != dest_path {
+                continue;
+            }
 
-        let file_size = fs::metadata(source_path)?.len() as i64;
-        Ok(file_size)
+            let source_label = self
+                .db
+                .get_drive_by_id(file.drive_id)?
+                .map(|d| d.label)
+                .unwrap_or_default();
+            cas::append_index_entry(&mount, hash, file.size_bytes, &source_label, &file.path)?;
+        }
+        Ok(())
     }
 
-    fn execute_symlink(&mut self, step: &MigrationStep) -> Result<i64> {
-        let source_path = Path::new(&step.source_path);
-        let dest_path = step
-            .dest_path
-            .as_ref()
-            .ok_or_else(|| OrdneError::Migration("No destination path".to_string()))?;
-        let dest_path = Path::new(dest_path);
+    fn check_dependencies(&self, step: &MigrationStep) -> Result<()> {
+        for dep_id in self.db.get_step_dependencies(step.id)? {
+            let dependency = self.db.get_step(dep_id)?.ok_or_else(|| {
+                OrdneError::Migration(format!("Dependency step {} not found", dep_id))
+            })?;
+            graph::check_dependency(step, &dependency)?;
+        }
+        Ok(())
+    }
 
-        if !source_path.exists() {
-            return Err(OrdneError::FileNotFound(source_path.to_path_buf()));
+    /// Fail before touching anything if a local destination cannot hold the
+    /// number of files and directories the plan will create.
+    fn preflight(&self, steps: &[MigrationStep]) -> Result<()> {
+        let mut dest_paths: HashMap<i64, Vec<&str>> = HashMap::new();
+        for step in steps {
+            if !matches!(step.action, StepAction::Copy | StepAction::Move) {
+                continue;
+            }
+            if let (Some(drive_id), Some(dest_path)) = (step.dest_drive_id, step.dest_path.as_deref()) {
+                dest_paths.entry(drive_id).or_default().push(dest_path);
+            }
         }
 
-        if let Some(parent) = dest_path.parent() {
-            fs::create_dir_all(parent)?;
+        for (drive_id, paths) in dest_paths {
+            let Some(drive) = self.db.get_drive_by_id(drive_id)? else {
+                continue;
+            };
+            let Some(mount) = drive.mount_path.as_deref() else {
+                continue;
+            };
+            if drive.backend != Backend::Local || !Path::new(mount).exists() {
+                continue;
+            }
+
+            let required = space::estimate_inodes(paths);
+            log::info!("Preflight: {} needs about {} inodes", drive.label, required);
+            space::verify_sufficient_inodes(mount, required)?;
         }
 
-        #[cfg(unix)]
-        std::os::unix::fs::symlink(source_path, dest_path)?;
+        Ok(())
+    }
 
-        #[cfg(not(unix))]
-        return Err(OrdneError::Migration(
-            "Symlinks not supported on this platform".to_string(),
-        ));
+    /// Record used bytes on every mounted local drive the plan touches, for
+    /// [`crate::migrate::reconcile_plan`]. Drives that cannot be read are skipped.
+    fn record_space_usage(&mut self, plan_id: i64, phase: SpacePhase) -> Result<()> {
+        let mut drive_ids: Vec<i64> = Vec::new();
+        for step in self.db.get_steps_for_plan(plan_id)? {
+            drive_ids.push(step.source_drive_id);
+            drive_ids.extend(step.dest_drive_id);
+        }
+        drive_ids.sort_unstable();
+        drive_ids.dedup();
+
+        for drive_id in drive_ids {
+            let Some(mount) = self
+                .db
+                .get_drive_by_id(drive_id)?
+                .filter(|d| d.backend == Backend::Local)
+                .and_then(|d| d.mount_path)
+            else {
+                continue;
+            };
+            match space::get_free_space(&mount) {
+                Ok(info) => {
+                    self.db
+                        .record_space_usage(plan_id, drive_id, phase, info.used_bytes as i64)?;
+                }
+                Err(e) => log::warn!("Could not read space on {}: {}", mount, e),
+            }
+        }
+        Ok(())
+    }
 
-        Ok(0)
+    /// Order `steps` by their dependencies and the plan's [`StepOrdering`].
+    fn execution_order(
+        &self,
+        plan: &MigrationPlan,
+        graph: &StepGraph,
+        steps: Vec<MigrationStep>,
+    ) -> Result<Vec<MigrationStep>> {
+        match plan.step_ordering {
+            StepOrdering::Planned => graph.execution_order(steps),
+            StepOrdering::Priority => {
+                let mut priorities = HashMap::new();
+                for step in &steps {
+                    if let Some(file) = self.db.get_file(step.file_id)? {
+                        priorities.insert(step.id, file.priority);
+                    }
+                }
+                graph.execution_order_by(steps, |s| {
+                    priorities.get(&s.id).copied().unwrap_or(Priority::Normal)
+                })
+            }
+        }
     }
 
     fn dry_run_plan(&mut self, plan_id: i64) -> Result<()> {
-        let steps = self.db.get_steps_for_plan(plan_id)?;
+        let plan = self.db.get_plan(plan_id)?.ok_or(OrdneError::PlanNotFound(plan_id))?;
+        let graph = StepGraph::new(&self.db.get_plan_dependencies(plan_id)?);
+        let steps = self.execution_order(&plan, &graph, self.db.get_steps_for_plan(plan_id)?)?;
 
         log::info!("DRY RUN: Plan {} has {} steps", plan_id, steps.len());
 
@@ -348,15 +998,14 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> MigrationEngine<'a, D> {
                     if let Some(parent) = Path::new(dest_path).parent() {
                         if let Some(dest_drive_id) = step.dest_drive_id {
                             if let Ok(Some(drive)) = self.db.get_drive_by_id(dest_drive_id) {
-                                if drive.backend == Backend::Local {
-                                    if parent.exists() {
-                                        let space_info = space::get_free_space(parent)?;
-                                        log::info!(
-                                            "DRY RUN: Destination has {} bytes free (max safe: {})",
-                                            space_info.free_bytes,
-                                            space_info.max_safe_write_bytes()
-                                        );
-                                    }
+                                if drive.backend == Backend::Local && parent.exists() {
+                                    let space_info = space::get_free_space(parent)?;
+                                    log::info!(
+                                        "DRY RUN: Destination has {} bytes writable, {} reserved for root (max safe: {})",
+                                        space_info.writable_bytes(),
+                                        space_info.reserved_bytes(),
+                                        space_info.max_safe_write_bytes()
+                                    );
                                 }
                             }
                         }
@@ -369,9 +1018,258 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> MigrationEngine<'a, D> {
     }
 }
 
+/// File state before and after a step, for its audit entry.
+///
+/// The "before" side comes from the catalog when the file is known; the
+/// "after" side describes where the data ended up once the step ran.
+pub fn step_snapshots(
+    step: &MigrationStep,
+    file: Option<&File>,
+    bytes: Option<i64>,
+) -> (FileSnapshot, FileSnapshot) {
+    let before = file.map(FileSnapshot::from_file).unwrap_or_else(|| FileSnapshot {
+        path: step.source_path.clone(),
+        size_bytes: bytes,
+        hash: step.pre_hash.clone(),
+        status: "unknown".to_string(),
+    });
+
+    let dest = step.dest_path.clone().unwrap_or_else(|| step.source_path.clone());
+    let copied_hash = step.post_hash.clone().or_else(|| before.hash.clone());
+    let after = match step.action {
+        StepAction::Copy => FileSnapshot {
+            path: dest,
+            size_bytes: bytes,
+            hash: copied_hash,
+            status: "copied".to_string(),
+        },
+        StepAction::Move => FileSnapshot {
+            path: dest,
+            size_bytes: bytes,
+            hash: copied_hash,
+            status: "moved".to_string(),
+        },
+        StepAction::Delete => FileSnapshot {
+            path: step.source_path.clone(),
+            size_bytes: None,
+            hash: None,
+            status: "deleted".to_string(),
+        },
+        StepAction::Hardlink => FileSnapshot {
+            path: dest,
+            size_bytes: bytes,
+            hash: before.hash.clone(),
+            status: "hardlinked".to_string(),
+        },
+        StepAction::Symlink => FileSnapshot {
+            path: dest,
+            size_bytes: None,
+            hash: None,
+            status: "symlinked".to_string(),
+        },
+    };
+
+    (before, after)
+}
+
+/// Perform the filesystem work for a step. Safe to call from worker threads.
+fn run_step(job: &StepJob, options: &EngineOptions) -> Result<StepOutcome> {
+    let step = &job.step;
+    match step.action {
+        StepAction::Copy => execute_copy(job, options),
+        StepAction::Move => execute_move(job, options),
+        StepAction::Delete => execute_delete(step, options).map(|bytes| StepOutcome {
+            bytes,
+            ..Default::default()
+        }),
+        StepAction::Hardlink => execute_hardlink(step).map(|bytes| StepOutcome {
+            bytes,
+            ..Default::default()
+        }),
+        StepAction::Symlink => execute_symlink(step).map(|bytes| StepOutcome {
+            bytes,
+            ..Default::default()
+        }),
+    }
+}
+
+fn execute_copy(job: &StepJob, options: &EngineOptions) -> Result<StepOutcome> {
+    let step = &job.step;
+    let source_path = Path::new(&step.source_path);
+    let dest_path = step
+        .dest_path
+        .as_ref()
+        .ok_or_else(|| OrdneError::Migration("No destination path".to_string()))?;
+    let dest_path = Path::new(dest_path);
+
+    if !source_path.exists() {
+        return Err(OrdneError::FileNotFound(source_path.to_path_buf()));
+    }
+
+    let metadata = fs::metadata(source_path)?;
+    let file_size = metadata.len() as i64;
+    let stamp = FileStamp::of(&metadata);
+
+    // Fresh hashes are always BLAKE3, whatever the catalog recorded.
+    let pre_hash = if options.verify_hashes {
+        hash::compute_hash(source_path, HashAlgorithm::Blake3)?
+    } else {
+        step.pre_hash
+            .clone()
+            .ok_or_else(|| OrdneError::Migration("No pre-hash available".to_string()))?
+    };
+
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match job.dest_backend {
+        Backend::Local => {
+            let rsync_options = rsync::RsyncOptions {
+                bwlimit_kbps: options.bandwidth_limit_kbps,
+                ..Default::default()
+            };
+            rsync::copy_file_with_options(source_path, dest_path, &rsync_options)?;
+        }
+        Backend::Rclone => {
+            let remote = job
+                .rclone_remote
+                .as_ref()
+                .ok_or_else(|| OrdneError::Migration("No rclone remote".to_string()))?;
+            let remote_path = dest_path
+                .to_str()
+                .ok_or_else(|| OrdneError::Migration("Invalid path".to_string()))?;
+            let rclone_options = rclone::RcloneOptions {
+                bwlimit_kbps: options.bandwidth_limit_kbps,
+                ..Default::default()
+            };
+            rclone::copy_to_remote_with_options(source_path, remote, remote_path, &rclone_options)?;
+        }
+    }
+
+    // A source written to during the copy leaves a destination that matches
+    // neither its old nor its new content.
+    stamp.check_unchanged(source_path)?;
+
+    if !options.verify_hashes {
+        return Ok(StepOutcome {
+            bytes: file_size,
+            ..Default::default()
+        });
+    }
+
+    // rclone copies with --checksum, so the remote side is verified by rclone itself.
+    if job.dest_backend == Backend::Local {
+        hash::verify_destination(dest_path, &pre_hash, HashAlgorithm::Blake3)?;
+    }
+
+    Ok(StepOutcome {
+        bytes: file_size,
+        pre_hash: Some(pre_hash.clone()),
+        post_hash: Some(pre_hash),
+        hash_algorithm: Some(HashAlgorithm::Blake3),
+    })
+}
+
+fn execute_move(job: &StepJob, options: &EngineOptions) -> Result<StepOutcome> {
+    let outcome = execute_copy(job, options)?;
+    let source_path = Path::new(&job.step.source_path);
+
+    if options.enforce_safety && options.verify_hashes {
+        let recorded = match &outcome.pre_hash {
+            Some(pre_hash) => Some((pre_hash, outcome.hash_algorithm)),
+            None => job.step.pre_hash.as_ref().map(|h| (h, job.step.hash_algorithm)),
+        };
+        if let Some((pre_hash, algorithm)) = recorded {
+            let algorithm = HashAlgorithm::resolve(algorithm, pre_hash)?;
+            hash::verify_source_unchanged(source_path, pre_hash, algorithm)?;
+        }
+    }
+
+    fs::remove_file(source_path)?;
+
+    Ok(outcome)
+}
+
+fn execute_delete(step: &MigrationStep, options: &EngineOptions) -> Result<i64> {
+    let source_path = Path::new(&step.source_path);
+
+    if !source_path.exists() {
+        log::warn!("File already deleted: {}", step.source_path);
+        return Ok(0);
+    }
+
+    let file_size = fs::metadata(source_path)?.len() as i64;
+
+    if options.enforce_safety {
+        if let Some(expected_hash) = &step.pre_hash {
+            let algorithm = HashAlgorithm::resolve(step.hash_algorithm, expected_hash)?;
+            hash::verify_source_unchanged(source_path, expected_hash, algorithm)?;
+        } else {
+            return Err(OrdneError::SafetyViolation(
+                "Cannot delete without hash verification".to_string(),
+            ));
+        }
+    }
+
+    fs::remove_file(source_path)?;
+
+    Ok(file_size)
+}
+
+fn execute_hardlink(step: &MigrationStep) -> Result<i64> {
+    let source_path = Path::new(&step.source_path);
+    let dest_path = step
+        .dest_path
+        .as_ref()
+        .ok_or_else(|| OrdneError::Migration("No destination path".to_string()))?;
+    let dest_path = Path::new(dest_path);
+
+    if !source_path.exists() {
+        return Err(OrdneError::FileNotFound(source_path.to_path_buf()));
+    }
+
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::hard_link(source_path, dest_path)?;
+
+    let file_size = fs::metadata(source_path)?.len() as i64;
+    Ok(file_size)
+}
+
+fn execute_symlink(step: &MigrationStep) -> Result<i64> {
+    let source_path = Path::new(&step.source_path);
+    let dest_path = step
+        .dest_path
+        .as_ref()
+        .ok_or_else(|| OrdneError::Migration("No destination path".to_string()))?;
+    let dest_path = Path::new(dest_path);
+
+    if !source_path.exists() {
+        return Err(OrdneError::FileNotFound(source_path.to_path_buf()));
+    }
+
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(source_path, dest_path)?;
+
+    #[cfg(not(unix))]
+    return Err(OrdneError::Migration(
+        "Symlinks not supported on this platform".to_string(),
+    ));
+
+    Ok(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Utc;
     use crate::db::{Backend, Drive, DriveRole, File, FileStatus, Priority, SqliteDatabase};
     use std::fs;
     use tempfile::TempDir;
@@ -395,6 +1293,8 @@ mod tests {
             role: DriveRole::Source,
             is_online: true,
             is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
             backend: Backend::Local,
             rclone_remote: None,
             scanned_at: None,
@@ -426,9 +1326,11 @@ mod tests {
             blake3_hash: None,
             created_at: Some(Utc::now()),
             modified_at: Some(Utc::now()),
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: None,
             is_symlink: false,
             symlink_target: None,
@@ -452,13 +1354,12 @@ mod tests {
 
         // Insert file into database before creating planner
         db.conn().execute(
-            "INSERT INTO files (drive_id, path, abs_path, filename, extension, size_bytes,
+            "INSERT INTO files (drive_id, path, filename, extension, size_bytes,
                                priority, status, indexed_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             rusqlite::params![
                 file.drive_id,
                 &file.path,
-                &file.abs_path,
                 &file.filename,
                 &file.extension,
                 file.size_bytes,
@@ -482,6 +1383,7 @@ mod tests {
             verify_hashes: false,
             retry_count: 1,
             enforce_safety: false,
+            ..Default::default()
         };
         let mut engine = MigrationEngine::new(&mut db, engine_opts);
 
@@ -513,6 +1415,7 @@ mod tests {
             status: StepStatus::Pending,
             pre_hash: None,
             post_hash: None,
+            hash_algorithm: None,
             executed_at: None,
             error: None,
             step_order: 0,
@@ -523,10 +1426,515 @@ mod tests {
             verify_hashes: true,
             retry_count: 1,
             enforce_safety: true,
+            ..Default::default()
         };
-        let mut engine = MigrationEngine::new(&mut db, engine_opts);
 
-        let result = engine.execute_delete(&step);
+        let result = execute_delete(&step, &engine_opts);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_delete_blocked_until_copy_verified() {
+        let mut db = create_test_db();
+        let temp_dir = TempDir::new().unwrap();
+
+        let drive = create_test_drive(&mut db, "source", temp_dir.path().to_str().unwrap());
+
+        let source_file = temp_dir.path().join("offload.txt");
+        fs::write(&source_file, b"test content").unwrap();
+        let source_path = source_file.to_str().unwrap().to_string();
+        let hash = hash::compute_blake3_hash(&source_file).unwrap();
+
+        db.conn().execute(
+            "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+             VALUES (1, ?1, 'offload.txt', 'offload.txt', 12, 'indexed', datetime('now'))",
+            rusqlite::params![drive],
+        ).unwrap();
+
+        let plan_id = db.create_plan(&crate::db::MigrationPlan {
+            id: 0,
+            created_at: Utc::now(),
+            description: None,
+            source_drive_id: Some(drive),
+            target_drive_id: Some(drive),
+            status: PlanStatus::Approved,
+            total_files: 1,
+            total_bytes: 12,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: None,
+            step_ordering: Default::default(),
+        }).unwrap();
+
+        let copy = MigrationStep {
+            id: 0,
+            plan_id,
+            file_id: 1,
+            action: StepAction::Copy,
+            source_path: source_path.clone(),
+            source_drive_id: drive,
+            dest_path: Some(temp_dir.path().join("copy.txt").to_str().unwrap().to_string()),
+            dest_drive_id: Some(drive),
+            status: StepStatus::Completed,
+            pre_hash: Some(hash.clone()),
+            post_hash: None,
+            hash_algorithm: None,
+            executed_at: None,
+            error: None,
+            step_order: 0,
+        };
+        let copy_id = db.add_step(&copy).unwrap();
+        db.update_step_status(copy_id, StepStatus::Completed, None).unwrap();
+
+        let delete_id = db.add_step(&MigrationStep {
+            action: StepAction::Delete,
+            dest_path: None,
+            dest_drive_id: None,
+            status: StepStatus::Pending,
+            step_order: 1,
+            ..copy
+        }).unwrap();
+        db.add_step_dependency(delete_id, copy_id).unwrap();
+
+        let engine_opts = EngineOptions {
+            dry_run: false,
+            verify_hashes: true,
+            retry_count: 1,
+            enforce_safety: true,
+            ..Default::default()
+        };
+        let result = MigrationEngine::new(&mut db, engine_opts).execute_plan(plan_id);
+        assert!(result.is_err());
+        assert!(source_file.exists());
+
+        // Once the copy is verified the delete is allowed to proceed.
+        db.update_step_hashes(copy_id, hash.clone(), Some(hash), HashAlgorithm::Blake3).unwrap();
+        db.update_step_status(delete_id, StepStatus::Pending, None).unwrap();
+        db.update_plan_status(plan_id, PlanStatus::Approved).unwrap();
+
+        let engine_opts = EngineOptions {
+            dry_run: false,
+            verify_hashes: true,
+            retry_count: 1,
+            enforce_safety: true,
+            ..Default::default()
+        };
+        MigrationEngine::new(&mut db, engine_opts).execute_plan(plan_id).unwrap();
+        assert!(!source_file.exists());
+
+        let entry = db
+            .get_audit_entries_for_plan(plan_id)
+            .unwrap()
+            .into_iter()
+            .find(|e| e.action == "step_completed_delete")
+            .unwrap();
+        let before: FileSnapshot = serde_json::from_str(&entry.before_state.unwrap()).unwrap();
+        let after: FileSnapshot = serde_json::from_str(&entry.after_state.unwrap()).unwrap();
+        assert_eq!(before.path, source_path);
+        assert_eq!(before.status, "indexed");
+        assert_eq!(after.status, "deleted");
+    }
+
+    #[test]
+    fn test_parallel_execution_across_devices() {
+        let mut db = create_test_db();
+        let temp_dir = TempDir::new().unwrap();
+        let drive_a = create_test_drive(&mut db, "a", temp_dir.path().to_str().unwrap());
+        let drive_b = create_test_drive(&mut db, "b", temp_dir.path().to_str().unwrap());
+
+        let plan_id = db.create_plan(&crate::db::MigrationPlan {
+            id: 0,
+            created_at: Utc::now(),
+            description: None,
+            source_drive_id: Some(drive_a),
+            target_drive_id: None,
+            status: PlanStatus::Approved,
+            total_files: 4,
+            total_bytes: 0,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: None,
+            step_ordering: Default::default(),
+        }).unwrap();
+
+        let mut paths = Vec::new();
+        for i in 0..4 {
+            let path = temp_dir.path().join(format!("file{}.txt", i));
+            fs::write(&path, format!("content {}", i)).unwrap();
+            let drive = if i % 2 == 0 { drive_a } else { drive_b };
+            db.conn().execute(
+                "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+                 VALUES (?1, ?2, ?3, ?3, 9, 'indexed', datetime('now'))",
+                rusqlite::params![i, drive, format!("file{}.txt", i)],
+            ).unwrap();
+            db.add_step(&MigrationStep {
+                id: 0,
+                plan_id,
+                file_id: i,
+                action: StepAction::Delete,
+                source_path: path.to_str().unwrap().to_string(),
+                source_drive_id: drive,
+                dest_path: None,
+                dest_drive_id: None,
+                status: StepStatus::Pending,
+                pre_hash: Some(hash::compute_blake3_hash(&path).unwrap()),
+                post_hash: None,
+                hash_algorithm: None,
+                executed_at: None,
+                error: None,
+                step_order: i as i32,
+            }).unwrap();
+            paths.push(path);
+        }
+
+        let engine_opts = EngineOptions {
+            dry_run: false,
+            max_parallel_steps: 4,
+            per_device_concurrency: 1,
+            ..Default::default()
+        };
+        MigrationEngine::new(&mut db, engine_opts).execute_plan(plan_id).unwrap();
+
+        assert!(paths.iter().all(|p| !p.exists()));
+        let plan = db.get_plan(plan_id).unwrap().unwrap();
+        assert_eq!(plan.status, PlanStatus::Completed);
+        assert_eq!(plan.completed_files, 4);
+    }
+
+    #[test]
+    fn test_priority_ordering_runs_critical_first() {
+        let mut db = create_test_db();
+        let temp_dir = TempDir::new().unwrap();
+        let drive = create_test_drive(&mut db, "a", temp_dir.path().to_str().unwrap());
+
+        let plan_id = db.create_plan(&crate::db::MigrationPlan {
+            id: 0,
+            created_at: Utc::now(),
+            description: None,
+            source_drive_id: Some(drive),
+            target_drive_id: None,
+            status: PlanStatus::Approved,
+            total_files: 3,
+            total_bytes: 0,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: None,
+            step_ordering: StepOrdering::Priority,
+        }).unwrap();
+
+        for (i, priority) in ["trash", "normal", "critical"].into_iter().enumerate() {
+            let path = temp_dir.path().join(format!("{}.txt", priority));
+            fs::write(&path, priority).unwrap();
+            db.conn().execute(
+                "INSERT INTO files (id, drive_id, path, filename, size_bytes, priority, status, indexed_at)
+                 VALUES (?1, ?2, ?3, ?3, 9, ?4, 'indexed', datetime('now'))",
+                rusqlite::params![i, drive, format!("{}.txt", priority), priority],
+            ).unwrap();
+            db.add_step(&MigrationStep {
+                id: 0,
+                plan_id,
+                file_id: i as i64,
+                action: StepAction::Delete,
+                source_path: path.to_str().unwrap().to_string(),
+                source_drive_id: drive,
+                dest_path: None,
+                dest_drive_id: None,
+                status: StepStatus::Pending,
+                pre_hash: Some(hash::compute_blake3_hash(&path).unwrap()),
+                post_hash: None,
+                hash_algorithm: None,
+                executed_at: None,
+                error: None,
+                step_order: i as i32,
+            }).unwrap();
+        }
+
+        let engine_opts = EngineOptions { dry_run: false, ..Default::default() };
+        MigrationEngine::new(&mut db, engine_opts).execute_plan(plan_id).unwrap();
+
+        let mut stmt = db
+            .conn()
+            .prepare("SELECT file_id FROM audit_log WHERE action = 'step_completed_delete' ORDER BY id")
+            .unwrap();
+        let order: Vec<i64> = stmt.query_map([], |row| row.get(0)).unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(order, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_plan_on_cold_drive_queues_until_online() {
+        let mut db = create_test_db();
+        let temp_dir = TempDir::new().unwrap();
+        let drive = create_test_drive(&mut db, "vault", temp_dir.path().to_str().unwrap());
+        crate::db::drives::set_drive_cold(db.conn(), drive, true).unwrap();
+
+        let path = temp_dir.path().join("old.txt");
+        fs::write(&path, b"test content").unwrap();
+        db.conn().execute(
+            "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+             VALUES (1, ?1, 'old.txt', 'old.txt', 12, 'indexed', datetime('now'))",
+            rusqlite::params![drive],
+        ).unwrap();
+
+        let plan_id = db.create_plan(&crate::db::MigrationPlan {
+            id: 0,
+            created_at: Utc::now(),
+            description: None,
+            source_drive_id: Some(drive),
+            target_drive_id: None,
+            status: PlanStatus::Approved,
+            total_files: 1,
+            total_bytes: 12,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: None,
+            step_ordering: Default::default(),
+        }).unwrap();
+        db.add_step(&MigrationStep {
+            id: 0,
+            plan_id,
+            file_id: 1,
+            action: StepAction::Delete,
+            source_path: path.to_str().unwrap().to_string(),
+            source_drive_id: drive,
+            dest_path: None,
+            dest_drive_id: None,
+            status: StepStatus::Pending,
+            pre_hash: Some(hash::compute_blake3_hash(&path).unwrap()),
+            post_hash: None,
+            hash_algorithm: None,
+            executed_at: None,
+            error: None,
+            step_order: 0,
+        }).unwrap();
+
+        let engine_opts = EngineOptions {
+            dry_run: false,
+            ..Default::default()
+        };
+        let result = MigrationEngine::new(&mut db, engine_opts.clone()).execute_plan(plan_id);
+        assert!(matches!(result, Err(OrdneError::PlanQueued { .. })));
+        assert!(path.exists());
+        assert_eq!(db.get_plan(plan_id).unwrap().unwrap().status, PlanStatus::Queued);
+
+        db.update_drive_online_status("vault", true).unwrap();
+        MigrationEngine::new(&mut db, engine_opts).execute_plan(plan_id).unwrap();
+        assert!(!path.exists());
+        assert_eq!(db.get_plan(plan_id).unwrap().unwrap().status, PlanStatus::Completed);
+    }
+
+    #[test]
+    fn test_closed_window_advances_fixed_clock() {
+        let mut db = create_test_db();
+        let temp_dir = TempDir::new().unwrap();
+        let drive = create_test_drive(&mut db, "nas", temp_dir.path().to_str().unwrap());
+
+        let path = temp_dir.path().join("old.txt");
+        fs::write(&path, b"test content").unwrap();
+        db.conn().execute(
+            "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+             VALUES (1, ?1, 'old.txt', 'old.txt', 12, 'indexed', datetime('now'))",
+            rusqlite::params![drive],
+        ).unwrap();
+
+        let plan_id = db.create_plan(&crate::db::MigrationPlan {
+            id: 0,
+            created_at: Utc::now(),
+            description: None,
+            source_drive_id: Some(drive),
+            target_drive_id: None,
+            status: PlanStatus::Approved,
+            total_files: 1,
+            total_bytes: 12,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: None,
+            step_ordering: Default::default(),
+        }).unwrap();
+        db.add_step(&MigrationStep {
+            id: 0,
+            plan_id,
+            file_id: 1,
+            action: StepAction::Delete,
+            source_path: path.to_str().unwrap().to_string(),
+            source_drive_id: drive,
+            dest_path: None,
+            dest_drive_id: None,
+            status: StepStatus::Pending,
+            pre_hash: Some(hash::compute_blake3_hash(&path).unwrap()),
+            post_hash: None,
+            hash_algorithm: None,
+            executed_at: None,
+            error: None,
+            step_order: 0,
+        }).unwrap();
+
+        // Time is frozen (as with ORDNE_NOW) two hours before the window opens.
+        let start = Utc::now();
+        let clock = Clock::fixed(start);
+        let opens = clock.local_time() + chrono::Duration::hours(2);
+        let engine_opts = EngineOptions {
+            dry_run: false,
+            window: Some(ExecutionWindow {
+                start: opens,
+                end: opens + chrono::Duration::hours(1),
+            }),
+            clock: clock.clone(),
+            ..Default::default()
+        };
+        MigrationEngine::new(&mut db, engine_opts).execute_plan(plan_id).unwrap();
+
+        assert!(!path.exists());
+        assert_eq!(db.get_plan(plan_id).unwrap().unwrap().status, PlanStatus::Completed);
+        assert_eq!(clock.now() - start, chrono::Duration::hours(2));
+    }
+
+    #[test]
+    fn test_interrupted_plan_resumes_from_checkpoint() {
+        let mut db = create_test_db();
+        let temp_dir = TempDir::new().unwrap();
+        let drive = create_test_drive(&mut db, "nas", temp_dir.path().to_str().unwrap());
+
+        let plan_id = db.create_plan(&crate::db::MigrationPlan {
+            id: 0,
+            created_at: Utc::now(),
+            description: None,
+            source_drive_id: Some(drive),
+            target_drive_id: None,
+            status: PlanStatus::Approved,
+            total_files: 3,
+            total_bytes: 36,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: None,
+            step_ordering: Default::default(),
+        }).unwrap();
+
+        let mut steps = Vec::new();
+        for i in 0..3 {
+            let path = temp_dir.path().join(format!("{}.txt", i));
+            fs::write(&path, b"test content").unwrap();
+            db.conn().execute(
+                "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+                 VALUES (?1, ?2, ?3, ?3, 12, 'indexed', datetime('now'))",
+                rusqlite::params![i + 1, drive, format!("{}.txt", i)],
+            ).unwrap();
+            steps.push(db.add_step(&MigrationStep {
+                id: 0,
+                plan_id,
+                file_id: i + 1,
+                action: StepAction::Delete,
+                source_path: path.to_str().unwrap().to_string(),
+                source_drive_id: drive,
+                dest_path: None,
+                dest_drive_id: None,
+                status: StepStatus::Pending,
+                pre_hash: Some(hash::compute_blake3_hash(&path).unwrap()),
+                post_hash: None,
+                hash_algorithm: None,
+                executed_at: None,
+                error: None,
+                step_order: i as i32,
+            }).unwrap());
+        }
+
+        // A previous night completed the first step and was killed during the second.
+        fs::remove_file(temp_dir.path().join("0.txt")).unwrap();
+        db.update_step_status(steps[0], StepStatus::Completed, None).unwrap();
+        db.update_step_status(steps[1], StepStatus::InProgress, None).unwrap();
+        db.update_plan_progress(plan_id, 1, 12).unwrap();
+        db.update_plan_status(plan_id, PlanStatus::InProgress).unwrap();
+
+        let engine_opts = EngineOptions {
+            dry_run: false,
+            ..Default::default()
+        };
+        MigrationEngine::new(&mut db, engine_opts).execute_plan(plan_id).unwrap();
+
+        let plan = db.get_plan(plan_id).unwrap().unwrap();
+        assert_eq!(plan.status, PlanStatus::Completed);
+        assert_eq!((plan.completed_files, plan.completed_bytes), (3, 36));
+
+        let sessions = db.list_plan_sessions(plan_id).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].files_completed, 2);
+        assert_eq!(sessions[0].bytes_completed, 24);
+        assert_eq!(sessions[0].last_step_id, Some(steps[2]));
+        assert_eq!(sessions[0].end_reason.as_deref(), Some("completed"));
+        assert!(sessions[0].ended_at.is_some());
+        assert_eq!(sessions[0].owner, Some(SessionOwner::current()));
+    }
+
+    #[test]
+    fn test_running_plan_is_refused_until_its_owner_dies() {
+        let mut db = create_test_db();
+        let temp_dir = TempDir::new().unwrap();
+        let drive = create_test_drive(&mut db, "nas", temp_dir.path().to_str().unwrap());
+        let path = temp_dir.path().join("a.txt");
+        fs::write(&path, b"test content").unwrap();
+        db.conn()
+            .execute(
+                "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+                 VALUES (1, ?1, 'a.txt', 'a.txt', 12, 'indexed', datetime('now'))",
+                [drive],
+            )
+            .unwrap();
+        let plan_id = db.create_plan(&crate::db::MigrationPlan {
+            id: 0,
+            created_at: Utc::now(),
+            description: None,
+            source_drive_id: Some(drive),
+            target_drive_id: None,
+            status: PlanStatus::InProgress,
+            total_files: 1,
+            total_bytes: 12,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: None,
+            step_ordering: Default::default(),
+        }).unwrap();
+        let step = db.add_step(&MigrationStep {
+            id: 0,
+            plan_id,
+            file_id: 1,
+            action: StepAction::Delete,
+            source_path: path.to_str().unwrap().to_string(),
+            source_drive_id: drive,
+            dest_path: None,
+            dest_drive_id: None,
+            status: StepStatus::InProgress,
+            pre_hash: Some(hash::compute_blake3_hash(&path).unwrap()),
+            post_hash: None,
+            hash_algorithm: None,
+            executed_at: None,
+            error: None,
+            step_order: 0,
+        }).unwrap();
+
+        // Another process on this host (init never exits) is running the plan.
+        let host = SessionOwner::current().host;
+        let session = db
+            .start_plan_session(plan_id, &SessionOwner { pid: 1, host: host.clone() })
+            .unwrap();
+        let engine_opts = EngineOptions {
+            dry_run: false,
+            ..Default::default()
+        };
+        let err = MigrationEngine::new(&mut db, engine_opts.clone())
+            .execute_plan(plan_id)
+            .unwrap_err();
+        assert!(matches!(err, OrdneError::PlanRunning { pid: 1, .. }));
+        assert_eq!(db.get_step(step).unwrap().unwrap().status, StepStatus::InProgress);
+        assert!(path.exists());
+
+        // Once that process is gone, its session is ended and the step reruns.
+        db.conn()
+            .execute("UPDATE plan_sessions SET owner_pid = ?1 WHERE id = ?2", (1 << 23, session))
+            .unwrap();
+        MigrationEngine::new(&mut db, engine_opts).execute_plan(plan_id).unwrap();
+        assert_eq!(db.get_plan(plan_id).unwrap().unwrap().status, PlanStatus::Completed);
+        assert!(!path.exists());
+        let sessions = db.list_plan_sessions(plan_id).unwrap();
+        assert_eq!(sessions[0].end_reason.as_deref(), Some("interrupted"));
+        assert_eq!(sessions[1].end_reason.as_deref(), Some("completed"));
+    }
 }
@@ -0,0 +1,169 @@
+//! Machine-readable execution logs.
+//!
+//! A plan's log is its header, its execution sessions and one entry per step
+//! with timings, bytes moved, attempts and hash results, taken from the
+//! catalog. JSON keeps the whole structure; CSV has one row per step for
+//! spreadsheets and external record-keeping.
+
+use crate::db::{MigrationPlan, PlanSession, PlansDatabase, StepLogEntry};
+use crate::error::{OrdneError, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionLog {
+    pub plan: MigrationPlan,
+    pub sessions: Vec<PlanSession>,
+    pub steps: Vec<StepLogEntry>,
+    pub exported_at: DateTime<Utc>,
+}
+
+const CSV_HEADER: &str = "step_id,step_order,action,status,source_path,dest_path,started_at,\
+finished_at,duration_ms,bytes,attempts,retries,pre_hash,post_hash,hash_algorithm,hash_verified,\
+error";
+
+impl ExecutionLog {
+    pub fn load(db: &dyn PlansDatabase, plan_id: i64) -> Result<Self> {
+        let plan = db.get_plan(plan_id)?.ok_or(OrdneError::PlanNotFound(plan_id))?;
+        Ok(Self {
+            plan,
+            sessions: db.list_plan_sessions(plan_id)?,
+            steps: db.get_step_log(plan_id)?,
+            exported_at: Utc::now(),
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| OrdneError::Config(format!("Failed to serialize execution log: {}", e)))
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+
+        let timestamp = |t: Option<DateTime<Utc>>| {
+            t.map(|t| t.to_rfc3339_opts(SecondsFormat::Millis, true))
+                .unwrap_or_default()
+        };
+        let number = |n: Option<i64>| n.map(|n| n.to_string()).unwrap_or_default();
+
+        for step in &self.steps {
+            let fields = [
+                step.step_id.to_string(),
+                step.step_order.to_string(),
+                step.action.as_str().to_string(),
+                step.status.as_str().to_string(),
+                step.source_path.clone(),
+                step.dest_path.clone().unwrap_or_default(),
+                timestamp(step.started_at),
+                timestamp(step.finished_at),
+                number(step.duration_ms),
+                number(step.bytes),
+                step.attempts.to_string(),
+                step.retries().to_string(),
+                step.pre_hash.clone().unwrap_or_default(),
+                step.post_hash.clone().unwrap_or_default(),
+                step.hash_algorithm.map(|a| a.as_str().to_string()).unwrap_or_default(),
+                step.hash_verified.map(|v| v.to_string()).unwrap_or_default(),
+                step.error.clone().unwrap_or_default(),
+            ];
+            let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+
+        csv
+    }
+}
+
+/// Quote a field if it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        Database, HashAlgorithm, MigrationStep, PlanStatus, SqliteDatabase, StepAction, StepStatus,
+    };
+
+    #[test]
+    fn test_execution_log_records_attempts_and_exports_csv() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'nas', 'source', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes, status)
+                 VALUES (1, 1, 'a,b.txt', 'a,b.txt', 10, 'indexed');",
+            )
+            .unwrap();
+        let plan_id = db
+            .create_plan(&MigrationPlan {
+                id: 0,
+                created_at: Utc::now(),
+                description: None,
+                source_drive_id: Some(1),
+                target_drive_id: Some(1),
+                status: PlanStatus::Approved,
+                total_files: 1,
+                total_bytes: 10,
+                completed_files: 0,
+                completed_bytes: 0,
+                created_by: None,
+                step_ordering: Default::default(),
+            })
+            .unwrap();
+        let step_id = db
+            .add_step(&MigrationStep {
+                id: 0,
+                plan_id,
+                file_id: 1,
+                action: StepAction::Copy,
+                source_path: "/mnt/nas/a,b.txt".to_string(),
+                source_drive_id: 1,
+                dest_path: Some("/mnt/backup/a,b.txt".to_string()),
+                dest_drive_id: Some(1),
+                status: StepStatus::Pending,
+                pre_hash: None,
+                post_hash: None,
+                hash_algorithm: None,
+                executed_at: None,
+                error: None,
+                step_order: 0,
+            })
+            .unwrap();
+
+        // A failed attempt, then a successful retry.
+        db.start_step_attempt(step_id).unwrap();
+        db.finish_step_attempt(step_id, None).unwrap();
+        db.start_step_attempt(step_id).unwrap();
+        db.update_step_hashes(step_id, "h1".to_string(), Some("h1".to_string()), HashAlgorithm::Md5)
+            .unwrap();
+        db.finish_step_attempt(step_id, Some(10)).unwrap();
+        db.update_step_status(step_id, StepStatus::Completed, None).unwrap();
+
+        let log = ExecutionLog::load(&db, plan_id).unwrap();
+        let step = &log.steps[0];
+        assert_eq!(step.attempts, 2);
+        assert_eq!(step.retries(), 1);
+        assert_eq!(step.bytes, Some(10));
+        assert_eq!(step.hash_verified, Some(true));
+        assert!(step.duration_ms.is_some_and(|ms| ms >= 0));
+
+        let csv = log.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        let row = lines.next().unwrap();
+        assert!(row.starts_with(&format!("{},0,copy,completed,\"/mnt/nas/a,b.txt\",", step_id)));
+        assert!(row.ends_with(",10,2,1,h1,h1,md5,true,"));
+
+        assert!(log.to_json().unwrap().contains("\"attempts\": 2"));
+    }
+}
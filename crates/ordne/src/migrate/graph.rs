@@ -0,0 +1,204 @@
+//! Dependency graph between the steps of a migration plan.
+//!
+//! `step_order` only describes a preferred sequence. Safety-relevant ordering
+//! (for example "delete the source only after its copy is verified") is
+//! recorded as explicit edges in `step_dependencies` and enforced here.
+
+use crate::db::{MigrationStep, StepAction, StepStatus};
+use crate::error::{OrdneError, Result};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[derive(Debug, Clone, Default)]
+pub struct StepGraph {
+    deps: HashMap<i64, Vec<i64>>,
+}
+
+impl StepGraph {
+    /// Build a graph from `(step_id, depends_on)` edges.
+    pub fn new(edges: &[(i64, i64)]) -> Self {
+        let mut deps: HashMap<i64, Vec<i64>> = HashMap::new();
+        for &(step_id, depends_on) in edges {
+            deps.entry(step_id).or_default().push(depends_on);
+        }
+        Self { deps }
+    }
+
+    pub fn dependencies(&self, step_id: i64) -> &[i64] {
+        self.deps.get(&step_id).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Order steps so every step comes after its dependencies, breaking ties
+    /// by `step_order`. Dependencies on steps outside `steps` (for example
+    /// ones already completed) do not constrain the order.
+    pub fn execution_order(&self, steps: Vec<MigrationStep>) -> Result<Vec<MigrationStep>> {
+        self.execution_order_by(steps, |_| ())
+    }
+
+    /// Like [`Self::execution_order`], but among steps whose dependencies are
+    /// done, the lowest `rank` goes first and `step_order` breaks ties.
+    pub fn execution_order_by<K: Ord + Copy>(
+        &self,
+        steps: Vec<MigrationStep>,
+        rank: impl Fn(&MigrationStep) -> K,
+    ) -> Result<Vec<MigrationStep>> {
+        let mut remaining: HashMap<i64, usize> = HashMap::new();
+        let mut dependents: HashMap<i64, Vec<i64>> = HashMap::new();
+        let ids: HashSet<i64> = steps.iter().map(|s| s.id).collect();
+
+        for step in &steps {
+            let pending: Vec<i64> = self
+                .dependencies(step.id)
+                .iter()
+                .copied()
+                .filter(|d| ids.contains(d))
+                .collect();
+            remaining.insert(step.id, pending.len());
+            for dep in pending {
+                dependents.entry(dep).or_default().push(step.id);
+            }
+        }
+
+        let mut by_id: HashMap<i64, MigrationStep> = steps.into_iter().map(|s| (s.id, s)).collect();
+        let key = |s: &MigrationStep| (rank(s), s.step_order, s.id);
+        let mut ready: BTreeSet<(K, i32, i64)> = by_id
+            .values()
+            .filter(|s| remaining[&s.id] == 0)
+            .map(key)
+            .collect();
+
+        let mut ordered = Vec::with_capacity(by_id.len());
+        while let Some((_, _, id)) = ready.pop_first() {
+            for dependent in dependents.remove(&id).unwrap_or_default() {
+                let count = remaining.get_mut(&dependent).expect("dependent step tracked");
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(key(&by_id[&dependent]));
+                }
+            }
+            ordered.push(by_id.remove(&id).expect("ready step present"));
+        }
+
+        if !by_id.is_empty() {
+            let mut cycle: Vec<i64> = by_id.keys().copied().collect();
+            cycle.sort_unstable();
+            return Err(OrdneError::Migration(format!(
+                "Dependency cycle between steps {:?}",
+                cycle
+            )));
+        }
+
+        Ok(ordered)
+    }
+}
+
+/// Check that `dependency` allows `step` to run.
+///
+/// A dependency must have completed. A delete that depends on a copy or move
+/// additionally requires the copy to be verified, i.e. its post-copy hash was
+/// recorded and matches the source hash taken before copying.
+pub fn check_dependency(step: &MigrationStep, dependency: &MigrationStep) -> Result<()> {
+    if dependency.status != StepStatus::Completed {
+        return Err(OrdneError::Migration(format!(
+            "Step {} is blocked: dependency step {} is {}",
+            step.id,
+            dependency.id,
+            dependency.status.as_str()
+        )));
+    }
+
+    let copies_data = matches!(dependency.action, StepAction::Copy | StepAction::Move);
+    if step.action == StepAction::Delete && copies_data {
+        let verified = dependency.post_hash.is_some() && dependency.post_hash == dependency.pre_hash;
+        if !verified {
+            return Err(OrdneError::Migration(format!(
+                "Step {} is blocked: copy step {} has not been verified",
+                step.id, dependency.id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(id: i64, action: StepAction, order: i32) -> MigrationStep {
+        MigrationStep {
+            id,
+            plan_id: 1,
+            file_id: id,
+            action,
+            source_path: format!("/src/{}", id),
+            source_drive_id: 1,
+            dest_path: None,
+            dest_drive_id: None,
+            status: StepStatus::Pending,
+            pre_hash: Some("abc".to_string()),
+            post_hash: None,
+            hash_algorithm: None,
+            executed_at: None,
+            error: None,
+            step_order: order,
+        }
+    }
+
+    #[test]
+    fn test_execution_order_respects_dependencies() {
+        // Delete (order 0) depends on copy (order 5); unrelated step has order 1.
+        let steps = vec![
+            step(1, StepAction::Delete, 0),
+            step(2, StepAction::Copy, 5),
+            step(3, StepAction::Copy, 1),
+        ];
+        let graph = StepGraph::new(&[(1, 2)]);
+
+        let ordered: Vec<i64> = graph.execution_order(steps).unwrap().iter().map(|s| s.id).collect();
+        assert_eq!(ordered, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_execution_order_by_rank() {
+        // Copy 3 ranks first, but delete 1 still waits for copy 2.
+        let steps = vec![
+            step(1, StepAction::Delete, 0),
+            step(2, StepAction::Copy, 1),
+            step(3, StepAction::Copy, 2),
+            step(4, StepAction::Copy, 3),
+        ];
+        let graph = StepGraph::new(&[(1, 2)]);
+        let rank = |s: &MigrationStep| match s.id {
+            3 => 0,
+            4 => 2,
+            _ => 1,
+        };
+
+        let ordered: Vec<i64> = graph.execution_order_by(steps, rank).unwrap().iter().map(|s| s.id).collect();
+        assert_eq!(ordered, vec![3, 2, 1, 4]);
+    }
+
+    #[test]
+    fn test_execution_order_detects_cycles() {
+        let steps = vec![step(1, StepAction::Copy, 0), step(2, StepAction::Copy, 1)];
+        let graph = StepGraph::new(&[(1, 2), (2, 1)]);
+        assert!(graph.execution_order(steps).is_err());
+    }
+
+    #[test]
+    fn test_delete_requires_verified_copy() {
+        let delete = step(2, StepAction::Delete, 1);
+        let mut copy = step(1, StepAction::Copy, 0);
+
+        assert!(check_dependency(&delete, &copy).is_err());
+
+        copy.status = StepStatus::Completed;
+        assert!(check_dependency(&delete, &copy).is_err());
+
+        copy.post_hash = Some("other".to_string());
+        assert!(check_dependency(&delete, &copy).is_err());
+
+        copy.post_hash = copy.pre_hash.clone();
+        assert!(check_dependency(&delete, &copy).is_ok());
+    }
+}
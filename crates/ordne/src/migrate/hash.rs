@@ -1,86 +1,58 @@
+use crate::db::HashAlgorithm;
 use crate::error::{OrdneError, Result};
-use md5::Digest;
 use std::fs::File;
-use std::io::{BufReader, Read};
 use std::path::Path;
 
 pub fn compute_blake3_hash<P: AsRef<Path>>(path: P) -> Result<String> {
     let path = path.as_ref();
-    let file = File::open(path).map_err(|e| {
-        log::error!("Failed to open file for hashing: {}: {}", path.display(), e);
-        e
-    })?;
-
-    let mut reader = BufReader::new(file);
-    let mut hasher = blake3::Hasher::new();
-    let mut buffer = [0u8; 8192];
-
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes_read]);
-    }
-
-    let hash = hasher.finalize();
-    Ok(hash.to_hex().to_string())
+    crate::index::hasher::digest_file(path, open(path)?, HashAlgorithm::Blake3)
 }
 
 pub fn compute_md5_hash<P: AsRef<Path>>(path: P) -> Result<String> {
     let path = path.as_ref();
-    let file = File::open(path).map_err(|e| {
+    crate::index::hasher::digest_file(path, open(path)?, HashAlgorithm::Md5)
+}
+
+fn open(path: &Path) -> Result<File> {
+    File::open(path).map_err(|e| {
         log::error!("Failed to open file for hashing: {}: {}", path.display(), e);
-        e
-    })?;
-
-    let mut reader = BufReader::new(file);
-    let mut hasher = md5::Md5::new();
-    let mut buffer = [0u8; 8192];
-
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        hasher.update(&buffer[..bytes_read]);
-    }
+        e.into()
+    })
+}
 
-    let hash = hasher.finalize();
-    Ok(format!("{:x}", hash))
+pub fn compute_hash<P: AsRef<Path>>(path: P, algorithm: HashAlgorithm) -> Result<String> {
+    match algorithm {
+        HashAlgorithm::Blake3 => compute_blake3_hash(path),
+        HashAlgorithm::Md5 => compute_md5_hash(path),
+    }
 }
 
-pub fn verify_hash<P: AsRef<Path>>(path: P, expected_hash: &str) -> Result<bool> {
+/// Hash `path` with `algorithm` and compare. Errors if `expected_hash` is not
+/// a digest of that algorithm, rather than comparing across algorithms.
+pub fn verify_hash<P: AsRef<Path>>(
+    path: P,
+    expected_hash: &str,
+    algorithm: HashAlgorithm,
+) -> Result<bool> {
     let path = path.as_ref();
 
     if !path.exists() {
         return Ok(false);
     }
 
-    let computed = if expected_hash.len() == 64 {
-        compute_blake3_hash(path)?
-    } else if expected_hash.len() == 32 {
-        compute_md5_hash(path)?
-    } else {
-        return Err(OrdneError::Migration(format!(
-            "Invalid hash length: {}",
-            expected_hash.len()
-        )));
-    };
-
-    Ok(computed.eq_ignore_ascii_case(expected_hash))
+    check_digest(expected_hash, algorithm)?;
+    Ok(compute_hash(path, algorithm)?.eq_ignore_ascii_case(expected_hash))
 }
 
-pub fn verify_source_unchanged<P: AsRef<Path>>(path: P, expected_hash: &str) -> Result<()> {
-    let path = path.as_ref();
-
-    if !verify_hash(path, expected_hash)? {
-        let actual_hash = if expected_hash.len() == 64 {
-            compute_blake3_hash(path)?
-        } else {
-            compute_md5_hash(path)?
-        };
+pub fn verify_source_unchanged<P: AsRef<Path>>(
+    path: P,
+    expected_hash: &str,
+    algorithm: HashAlgorithm,
+) -> Result<()> {
+    check_digest(expected_hash, algorithm)?;
+    let actual_hash = compute_hash(path, algorithm)?;
 
+    if !actual_hash.eq_ignore_ascii_case(expected_hash) {
         return Err(OrdneError::SourceChanged {
             expected: expected_hash.to_string(),
             actual: actual_hash,
@@ -90,10 +62,14 @@ pub fn verify_source_unchanged<P: AsRef<Path>>(path: P, expected_hash: &str) ->
     Ok(())
 }
 
-pub fn verify_destination<P: AsRef<Path>>(path: P, expected_hash: &str) -> Result<()> {
+pub fn verify_destination<P: AsRef<Path>>(
+    path: P,
+    expected_hash: &str,
+    algorithm: HashAlgorithm,
+) -> Result<()> {
     let path = path.as_ref();
 
-    if !verify_hash(path, expected_hash)? {
+    if !verify_hash(path, expected_hash, algorithm)? {
         return Err(OrdneError::DestinationVerification {
             path: path.to_path_buf(),
         });
@@ -102,6 +78,17 @@ pub fn verify_destination<P: AsRef<Path>>(path: P, expected_hash: &str) -> Resul
     Ok(())
 }
 
+fn check_digest(hash: &str, algorithm: HashAlgorithm) -> Result<()> {
+    if hash.len() != algorithm.hex_len() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(OrdneError::Migration(format!(
+            "Not a {} hash: {}",
+            algorithm.as_str(),
+            hash
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,14 +128,30 @@ mod tests {
         fs::write(&file_path, b"test content").unwrap();
 
         let hash = compute_blake3_hash(&file_path).unwrap();
-        assert!(verify_hash(&file_path, &hash).unwrap());
+        assert!(verify_hash(&file_path, &hash, HashAlgorithm::Blake3).unwrap());
 
         // Invalid hash length should return an error
-        assert!(verify_hash(&file_path, "invalid_hash").is_err());
+        assert!(verify_hash(&file_path, "invalid_hash", HashAlgorithm::Blake3).is_err());
 
         // Wrong but properly-sized hash should return Ok(false)
         let wrong_hash = "0000000000000000000000000000000000000000000000000000000000000000";
-        assert!(!verify_hash(&file_path, wrong_hash).unwrap());
+        assert!(!verify_hash(&file_path, wrong_hash, HashAlgorithm::Blake3).unwrap());
+    }
+
+    #[test]
+    fn test_verify_hash_uses_the_given_algorithm() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test.txt");
+        fs::write(&file_path, b"test content").unwrap();
+
+        let md5 = compute_md5_hash(&file_path).unwrap();
+        assert!(verify_hash(&file_path, &md5, HashAlgorithm::Md5).unwrap());
+        assert_eq!(compute_hash(&file_path, HashAlgorithm::Md5).unwrap(), md5);
+
+        // An MD5 digest recorded as BLAKE3 is an error, not a mismatch.
+        assert!(verify_hash(&file_path, &md5, HashAlgorithm::Blake3).is_err());
+        assert!(verify_source_unchanged(&file_path, &md5, HashAlgorithm::Blake3).is_err());
+        verify_source_unchanged(&file_path, &md5, HashAlgorithm::Md5).unwrap();
     }
 
     #[test]
@@ -158,11 +161,11 @@ mod tests {
         fs::write(&file_path, b"test content").unwrap();
 
         let hash = compute_blake3_hash(&file_path).unwrap();
-        let result = verify_source_unchanged(&file_path, &hash);
+        let result = verify_source_unchanged(&file_path, &hash, HashAlgorithm::Blake3);
         assert!(result.is_ok());
 
         fs::write(&file_path, b"modified content").unwrap();
-        let result = verify_source_unchanged(&file_path, &hash);
+        let result = verify_source_unchanged(&file_path, &hash, HashAlgorithm::Blake3);
         assert!(result.is_err());
     }
 
@@ -173,11 +176,11 @@ mod tests {
         fs::write(&file_path, b"test content").unwrap();
 
         let hash = compute_blake3_hash(&file_path).unwrap();
-        let result = verify_destination(&file_path, &hash);
+        let result = verify_destination(&file_path, &hash, HashAlgorithm::Blake3);
         assert!(result.is_ok());
 
         fs::write(&file_path, b"modified content").unwrap();
-        let result = verify_destination(&file_path, &hash);
+        let result = verify_destination(&file_path, &hash, HashAlgorithm::Blake3);
         assert!(result.is_err());
     }
 }
@@ -0,0 +1,166 @@
+//! Staying out of the way on desktop machines.
+//!
+//! With an [`IdlePolicy`] the engine only starts steps while nobody is using
+//! the machine: no keyboard or mouse input for `min_idle`, and a load average
+//! under `max_load`. Running steps finish; new ones wait until the machine is
+//! idle again. Input idle time comes from `xprintidle` (X11) or GNOME's idle
+//! monitor over D-Bus (Wayland). Where neither answers, only the load average
+//! counts.
+
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// How long a probe result is reused, so short steps do not spawn a process each.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IdlePolicy {
+    /// Input idle time required before steps start. `None` ignores input.
+    pub min_idle: Option<Duration>,
+    /// 1-minute load average above which the machine counts as busy.
+    pub max_load: Option<f64>,
+    /// How often a paused engine checks whether the machine is idle again.
+    pub poll_interval: Duration,
+}
+
+impl IdlePolicy {
+    pub fn new(min_idle: Option<Duration>, max_load: Option<f64>) -> Self {
+        Self {
+            min_idle,
+            max_load,
+            poll_interval: Duration::from_secs(30),
+        }
+    }
+
+    /// Why the machine is busy right now, or `None` if steps may start.
+    pub fn busy_reason(&self) -> Option<String> {
+        let idle = self.min_idle.and_then(|_| user_idle_time());
+        let load = self.max_load.and_then(|_| load_average());
+        self.judge(idle, load)
+    }
+
+    fn judge(&self, idle: Option<Duration>, load: Option<f64>) -> Option<String> {
+        if let (Some(min_idle), Some(idle)) = (self.min_idle, idle) {
+            if idle < min_idle {
+                return Some(format!("user active {}s ago", idle.as_secs()));
+            }
+        }
+        if let (Some(max_load), Some(load)) = (self.max_load, load) {
+            if load > max_load {
+                return Some(format!("load average {:.2} above {:.2}", load, max_load));
+            }
+        }
+        None
+    }
+}
+
+/// An [`IdlePolicy`] with its last probe result.
+#[derive(Debug, Clone)]
+pub struct IdleMonitor {
+    pub policy: IdlePolicy,
+    last: Option<(Instant, Option<String>)>,
+}
+
+impl IdleMonitor {
+    pub fn new(policy: IdlePolicy) -> Self {
+        Self { policy, last: None }
+    }
+
+    /// [`IdlePolicy::busy_reason`], probed at most every few seconds.
+    pub fn busy_reason(&mut self) -> Option<String> {
+        match &self.last {
+            Some((at, reason)) if at.elapsed() < RECHECK_INTERVAL => reason.clone(),
+            _ => {
+                let reason = self.policy.busy_reason();
+                self.last = Some((Instant::now(), reason.clone()));
+                reason
+            }
+        }
+    }
+
+    /// Sleep until the machine is idle.
+    pub fn wait(&mut self) {
+        loop {
+            self.last = None;
+            if self.busy_reason().is_none() {
+                return;
+            }
+            std::thread::sleep(self.policy.poll_interval);
+        }
+    }
+}
+
+/// Time since the last keyboard or mouse input in the desktop session.
+pub fn user_idle_time() -> Option<Duration> {
+    xprintidle().or_else(gnome_idle_time)
+}
+
+fn xprintidle() -> Option<Duration> {
+    let output = Command::new("xprintidle").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let millis = String::from_utf8_lossy(&output.stdout).trim().parse().ok()?;
+    Some(Duration::from_millis(millis))
+}
+
+fn gnome_idle_time() -> Option<Duration> {
+    let output = Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.gnome.Mutter.IdleMonitor",
+            "--object-path",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "--method",
+            "org.gnome.Mutter.IdleMonitor.GetIdletime",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_gdbus_uint64(&String::from_utf8_lossy(&output.stdout)).map(Duration::from_millis)
+}
+
+/// The value of a `gdbus call` reply such as `(uint64 12345,)`.
+fn parse_gdbus_uint64(reply: &str) -> Option<u64> {
+    reply
+        .trim()
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .trim_end_matches(',')
+        .strip_prefix("uint64 ")?
+        .parse()
+        .ok()
+}
+
+/// The 1-minute load average.
+pub fn load_average() -> Option<f64> {
+    parse_loadavg(&std::fs::read_to_string("/proc/loadavg").ok()?)
+}
+
+fn parse_loadavg(contents: &str) -> Option<f64> {
+    contents.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_busy_while_active_or_loaded() {
+        let policy = IdlePolicy::new(Some(Duration::from_secs(300)), Some(2.0));
+
+        assert!(policy.judge(Some(Duration::from_secs(10)), Some(0.5)).unwrap().contains("user active"));
+        assert!(policy.judge(Some(Duration::from_secs(600)), Some(3.5)).unwrap().contains("load average"));
+        assert_eq!(policy.judge(Some(Duration::from_secs(600)), Some(0.5)), None);
+        // Without an idle source only the load counts.
+        assert_eq!(policy.judge(None, Some(0.5)), None);
+
+        assert_eq!(parse_gdbus_uint64("(uint64 12345,)\n"), Some(12345));
+        assert_eq!(parse_gdbus_uint64("Error: no such service"), None);
+        assert_eq!(parse_loadavg("0.52 0.58 0.59 1/467 12345\n"), Some(0.52));
+    }
+}
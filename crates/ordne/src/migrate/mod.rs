@@ -1,12 +1,40 @@
+pub mod automount;
+pub mod cas;
+pub mod cleanup;
+pub mod encryption;
 pub mod engine;
+pub mod exec_log;
+pub mod graph;
 pub mod hash;
+pub mod idle;
+pub mod placement;
 pub mod planner;
 pub mod rclone;
+pub mod reconcile;
+pub mod repair;
+pub mod reverify;
 pub mod rollback;
 pub mod rsync;
+pub mod scheduler;
 pub mod space;
+pub mod whatif;
+pub mod window;
 
+pub use automount::{with_plan_drives, AfterPlan, Automount};
+pub use cas::OffloadLayout;
+pub use cleanup::{cleanup_plan, CleanupReport, Inconsistency};
+pub use encryption::{plan_encryption_warnings, EncryptionWarning};
 pub use engine::{EngineOptions, MigrationEngine};
-pub use planner::{Planner, PlannerOptions};
+pub use exec_log::ExecutionLog;
+pub use graph::StepGraph;
+pub use idle::IdlePolicy;
+pub use placement::{PlacementConstraint, PlacementTarget};
+pub use planner::{CrossDriveDedupOptions, DedupResolution, DeleteLimits, Planner, PlannerOptions};
+pub use reconcile::{reconcile_plan, DriveReconciliation};
+pub use repair::{find_repairs, Repair, RepairSet};
+pub use reverify::{run_due_verification_jobs, run_verification_job, VerificationJobResult};
 pub use rollback::RollbackEngine;
+pub use scheduler::StepScheduler;
 pub use space::{get_free_space, verify_sufficient_space, SpaceInfo};
+pub use whatif::{CatalogUsage, DriveUsage, DuplicateSummary, WhatIf, WhatIfReport};
+pub use window::ExecutionWindow;
@@ -0,0 +1,258 @@
+//! Placement of files across a pool of target drives.
+//!
+//! Files are packed first-fit, largest first, onto targets in the order given
+//! so archive disks fill one after another. Placement constraints bind files
+//! of a category (or category/subcategory) into a single unit that must land
+//! on one drive, so a year of photos is never split across disks.
+
+use crate::db::File;
+use crate::error::{OrdneError, Result};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub struct PlacementTarget {
+    pub drive_id: i64,
+    pub mount_path: String,
+    /// Bytes that may be written to the drive. `None` means unbounded.
+    pub capacity_bytes: Option<u64>,
+}
+
+/// "Keep these files on the same drive."
+///
+/// Written as `category`, `category/subcategory` or `category/*`. The last
+/// form keeps each subcategory together without binding them to each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacementConstraint {
+    pub category: String,
+    pub subcategory: SubcategoryMatch,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubcategoryMatch {
+    /// Every file in the category forms one unit.
+    Any,
+    /// Only files with this subcategory form the unit.
+    Exact(String),
+    /// Each subcategory forms its own unit.
+    Each,
+}
+
+impl PlacementConstraint {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (category, subcategory) = match s.split_once('/') {
+            Some((category, "*")) => (category, SubcategoryMatch::Each),
+            Some((category, sub)) if !sub.is_empty() => {
+                (category, SubcategoryMatch::Exact(sub.to_string()))
+            }
+            Some(_) => return Err(invalid_constraint(s)),
+            None => (s, SubcategoryMatch::Any),
+        };
+
+        if category.is_empty() {
+            return Err(invalid_constraint(s));
+        }
+
+        Ok(Self {
+            category: category.to_string(),
+            subcategory,
+        })
+    }
+
+    /// Unit key for a file bound by this constraint, if it is.
+    fn unit_key(&self, file: &File) -> Option<String> {
+        if file.category.as_deref() != Some(self.category.as_str()) {
+            return None;
+        }
+
+        match (&self.subcategory, file.subcategory.as_deref()) {
+            (SubcategoryMatch::Any, _) => Some(self.category.clone()),
+            (SubcategoryMatch::Exact(want), Some(sub)) if want == sub => {
+                Some(format!("{}/{}", self.category, sub))
+            }
+            (SubcategoryMatch::Each, Some(sub)) => Some(format!("{}/{}", self.category, sub)),
+            _ => None,
+        }
+    }
+}
+
+fn invalid_constraint(s: &str) -> OrdneError {
+    OrdneError::Config(format!(
+        "Invalid placement constraint '{}' (expected category, category/subcategory or category/*)",
+        s
+    ))
+}
+
+/// Assign every file to a target index, honoring `constraints`.
+pub fn place_files(
+    files: Vec<File>,
+    targets: &[PlacementTarget],
+    constraints: &[PlacementConstraint],
+) -> Result<Vec<(File, usize)>> {
+    if targets.is_empty() {
+        return Err(OrdneError::Config("No target drives to place files on".to_string()));
+    }
+
+    let mut grouped: BTreeMap<String, Vec<File>> = BTreeMap::new();
+    let mut units: Vec<(Option<String>, Vec<File>)> = Vec::new();
+
+    for file in files {
+        match constraints.iter().find_map(|c| c.unit_key(&file)) {
+            Some(key) => grouped.entry(key).or_default().push(file),
+            None => units.push((None, vec![file])),
+        }
+    }
+    units.extend(grouped.into_iter().map(|(key, files)| (Some(key), files)));
+
+    let unit_size = |files: &[File]| files.iter().map(|f| f.size_bytes.max(0) as u64).sum::<u64>();
+    units.sort_by_key(|(_, files)| std::cmp::Reverse(unit_size(files)));
+
+    let mut remaining: Vec<Option<u64>> = targets.iter().map(|t| t.capacity_bytes).collect();
+    let mut placed = Vec::new();
+
+    for (key, files) in units {
+        let size = unit_size(&files);
+        let idx = remaining
+            .iter()
+            .position(|r| r.is_none_or(|r| r >= size))
+            .ok_or_else(|| match &key {
+                Some(key) => OrdneError::Migration(format!(
+                    "Files in '{}' ({} bytes) must stay together but do not fit on any target drive",
+                    key, size
+                )),
+                None => OrdneError::Migration(format!(
+                    "{} ({} bytes) does not fit on any target drive",
+                    files[0].path, size
+                )),
+            })?;
+
+        if let Some(r) = remaining[idx].as_mut() {
+            *r -= size;
+        }
+        placed.extend(files.into_iter().map(|f| (f, idx)));
+    }
+
+    Ok(placed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{FileStatus, Priority};
+    use chrono::Utc;
+
+    fn file(id: i64, category: &str, subcategory: Option<&str>, size: i64) -> File {
+        File {
+            id,
+            drive_id: 1,
+            path: format!("f{}", id),
+            abs_path: format!("/src/f{}", id),
+            filename: format!("f{}", id),
+            extension: None,
+            size_bytes: size,
+            md5_hash: None,
+            blake3_hash: None,
+            created_at: None,
+            modified_at: None,
+            accessed_at: None,
+            inode: None,
+            device_num: None,
+            nlinks: None,
+            owner_uid: None,
+            mime_type: None,
+            is_symlink: false,
+            symlink_target: None,
+            git_remote_url: None,
+            category: Some(category.to_string()),
+            subcategory: subcategory.map(str::to_string),
+            target_path: None,
+            target_drive_id: None,
+            priority: Priority::Normal,
+            duplicate_group: None,
+            is_original: false,
+            rmlint_type: None,
+            status: FileStatus::Classified,
+            migrated_to: None,
+            migrated_to_drive: None,
+            migrated_at: None,
+            verified_hash: None,
+            error: None,
+            indexed_at: Utc::now(),
+        }
+    }
+
+    fn target(drive_id: i64, capacity: u64) -> PlacementTarget {
+        PlacementTarget {
+            drive_id,
+            mount_path: format!("/mnt/{}", drive_id),
+            capacity_bytes: Some(capacity),
+        }
+    }
+
+    #[test]
+    fn test_parse_constraint() {
+        assert_eq!(
+            PlacementConstraint::parse("photos/2019").unwrap().subcategory,
+            SubcategoryMatch::Exact("2019".to_string())
+        );
+        assert_eq!(
+            PlacementConstraint::parse("photos/*").unwrap().subcategory,
+            SubcategoryMatch::Each
+        );
+        assert_eq!(
+            PlacementConstraint::parse("photos").unwrap().subcategory,
+            SubcategoryMatch::Any
+        );
+        assert!(PlacementConstraint::parse("/2019").is_err());
+        assert!(PlacementConstraint::parse("photos/").is_err());
+    }
+
+    #[test]
+    fn test_constrained_files_share_a_drive() {
+        let files = vec![
+            file(1, "photos", Some("2019"), 40),
+            file(2, "photos", Some("2019"), 40),
+            file(3, "photos", Some("2020"), 40),
+            file(4, "photos", Some("2020"), 40),
+        ];
+        let targets = vec![target(10, 100), target(11, 100)];
+        let constraints = vec![PlacementConstraint::parse("photos/*").unwrap()];
+
+        let placed = place_files(files, &targets, &constraints).unwrap();
+        let drive_of = |id: i64| placed.iter().find(|(f, _)| f.id == id).unwrap().1;
+
+        assert_eq!(drive_of(1), drive_of(2));
+        assert_eq!(drive_of(3), drive_of(4));
+        assert_ne!(drive_of(1), drive_of(3));
+    }
+
+    #[test]
+    fn test_unconstrained_files_fill_in_order() {
+        let files = vec![
+            file(1, "docs", None, 60),
+            file(2, "docs", None, 60),
+            file(3, "docs", None, 30),
+        ];
+        let targets = vec![target(10, 100), target(11, 100)];
+
+        let placed = place_files(files, &targets, &[]).unwrap();
+        let drive_of = |id: i64| placed.iter().find(|(f, _)| f.id == id).unwrap().1;
+
+        assert_eq!(drive_of(1), 0);
+        assert_eq!(drive_of(2), 1);
+        assert_eq!(drive_of(3), 0);
+    }
+
+    #[test]
+    fn test_unit_too_large_for_any_drive() {
+        let files = vec![
+            file(1, "photos", Some("2019"), 60),
+            file(2, "photos", Some("2019"), 60),
+        ];
+        let targets = vec![target(10, 100), target(11, 100)];
+        let constraints = vec![PlacementConstraint::parse("photos/2019").unwrap()];
+
+        let err = place_files(files, &targets, &constraints).unwrap_err();
+        assert!(err.to_string().contains("photos/2019"));
+    }
+}
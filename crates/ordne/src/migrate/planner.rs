@@ -1,16 +1,43 @@
 use crate::db::{
-    AuditDatabase, AuditLogEntry, Database, File, MigrationPlan, MigrationStep,
-    PlanStatus, PlansDatabase, StepAction, StepStatus,
+    Attribution, AuditDatabase, AuditLogEntry, Backend, Database, Drive, DriveRole, File,
+    FileStatus, MigrationPlan, MigrationStep, PlanStatus, PlansDatabase, Priority, StepAction,
+    StepOrdering, StepStatus,
 };
-use crate::error::Result;
+use crate::error::{OrdneError, Result};
+use crate::migrate::cas::{self, OffloadLayout};
+use crate::migrate::placement::{self, PlacementConstraint, PlacementTarget};
+use crate::migrate::repair::Repair;
 use crate::migrate::space;
-use chrono::Utc;
+use crate::util::clock::Clock;
+use crate::undo::DuplicateGroupSnapshot;
+use crate::util::format::format_bytes;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone)]
 pub struct PlannerOptions {
     pub max_batch_size_bytes: Option<u64>,
     pub enforce_space_limits: bool,
     pub dry_run: bool,
+    /// Recorded as `created_by` on plans and on their audit entries.
+    pub attribution: Option<Attribution>,
+    /// Copies dedup plans leave of any content with a Critical copy.
+    pub min_critical_copies: usize,
+    /// Drives each category must stay on, normally `[replicas]` from
+    /// `ordne.toml` (see [`crate::config::Settings::planner_options`]).
+    pub min_replicas: HashMap<String, usize>,
+    /// Categories that may only be copied to encrypted drives.
+    pub require_encryption: Vec<String>,
+    /// Recorded on plans; the engine runs their steps in this order.
+    pub step_ordering: StepOrdering,
+    /// Stamps plans, steps and audit entries.
+    pub clock: Clock,
+    /// Ceilings on deletions, normally `[delete_limits]` from `ordne.toml`
+    /// (see [`crate::config::Settings::planner_options`]). None by default.
+    pub delete_limits: DeleteLimits,
+    /// Bytes the plans of the attributed actor may delete in 24 hours, such
+    /// as an MCP agent's `--max-delete-per-day`. No limit by default.
+    pub actor_delete_bytes_per_day: Option<u64>,
 }
 
 impl Default for PlannerOptions {
@@ -19,10 +46,95 @@ impl Default for PlannerOptions {
             max_batch_size_bytes: None,
             enforce_space_limits: true,
             dry_run: true,
+            attribution: None,
+            min_critical_copies: 2,
+            min_replicas: HashMap::new(),
+            require_encryption: Vec::new(),
+            step_ordering: StepOrdering::default(),
+            clock: Clock::default(),
+            delete_limits: DeleteLimits::default(),
+            actor_delete_bytes_per_day: None,
         }
     }
 }
 
+/// Hard ceilings on how many files and bytes a single plan, and all plans
+/// created in the last 24 hours, may delete. Plans over a ceiling are
+/// refused rather than trimmed, so a runaway selection has to be split into
+/// smaller plans, each reviewed and approved on its own. `None` is no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeleteLimits {
+    pub max_files_per_plan: Option<u64>,
+    pub max_bytes_per_plan: Option<u64>,
+    pub max_files_per_day: Option<u64>,
+    pub max_bytes_per_day: Option<u64>,
+}
+
+/// A reviewed duplicate group: the copy to keep and the copies to remove.
+#[derive(Debug, Clone)]
+pub struct DedupResolution {
+    pub group_id: i64,
+    pub keeper: File,
+    pub duplicates: Vec<File>,
+    /// The group's original and resolution before the review, put back if
+    /// the plan is undone.
+    pub previous_original_id: Option<i64>,
+    pub previous_resolution: Option<String>,
+}
+
+/// Drive-role rules for resolving duplicates that span several drives.
+///
+/// Copies on `remove_roles` drives are removed only when at least one copy
+/// survives on a `keep_roles` drive. Drives with any other role are left alone.
+#[derive(Debug, Clone)]
+pub struct CrossDriveDedupOptions {
+    pub keep_roles: Vec<DriveRole>,
+    pub remove_roles: Vec<DriveRole>,
+    /// Replace extra copies on the same kept drive with hardlinks to one copy.
+    pub hardlink_within_drive: bool,
+}
+
+impl Default for CrossDriveDedupOptions {
+    fn default() -> Self {
+        Self {
+            keep_roles: vec![DriveRole::Source, DriveRole::Backup],
+            remove_roles: vec![DriveRole::Offload],
+            hardlink_within_drive: false,
+        }
+    }
+}
+
+impl CrossDriveDedupOptions {
+    /// Build options from role names, falling back to the defaults for empty lists.
+    pub fn from_role_names(keep: &[String], remove: &[String], hardlink: bool) -> Result<Self> {
+        let defaults = Self::default();
+        let parse = |names: &[String], fallback: Vec<DriveRole>| -> Result<Vec<DriveRole>> {
+            if names.is_empty() {
+                return Ok(fallback);
+            }
+            names.iter().map(|n| DriveRole::from_str(n.trim())).collect()
+        };
+
+        let options = Self {
+            keep_roles: parse(keep, defaults.keep_roles)?,
+            remove_roles: parse(remove, defaults.remove_roles)?,
+            hardlink_within_drive: hardlink,
+        };
+        options.validate()?;
+        Ok(options)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(role) = self.keep_roles.iter().find(|r| self.remove_roles.contains(r)) {
+            return Err(OrdneError::Config(format!(
+                "Drive role '{}' cannot be both kept and removed",
+                role.as_str()
+            )));
+        }
+        Ok(())
+    }
+}
+
 pub struct Planner<'a, D: Database + PlansDatabase + AuditDatabase> {
     db: &'a mut D,
     options: PlannerOptions,
@@ -33,13 +145,278 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
         Self { db, options }
     }
 
+    /// Change the offload batch size for the plans created after this call.
+    pub fn set_max_batch_size(&mut self, max_batch_size_bytes: Option<u64>) {
+        self.options.max_batch_size_bytes = max_batch_size_bytes;
+    }
+
+    fn agent_mode(&self, default: &str) -> Option<String> {
+        let attribution = self.options.attribution.as_ref();
+        Some(attribution.map_or(default, |a| a.agent_mode.as_str()).to_string())
+    }
+
+    fn actor(&self) -> Option<String> {
+        self.options.attribution.as_ref().map(|a| a.actor.clone())
+    }
+
+    fn replica_requirements(&self) -> HashMap<String, usize> {
+        self.options.min_replicas.clone()
+    }
+
+    /// Refuse plans whose deletions, given as (files, bytes) per plan about to
+    /// be created, exceed the delete limits for one plan or, together with
+    /// the plans of the last 24 hours, for a day.
+    fn check_delete_limits(&self, plans: &[(usize, i64)]) -> Result<()> {
+        self.check_actor_delete_budget(plans)?;
+        let limits = self.options.delete_limits;
+        if limits == DeleteLimits::default() {
+            return Ok(());
+        }
+        let split = "split it into smaller plans and approve each one";
+
+        for &(files, bytes) in plans {
+            if let Some(max) = limits.max_files_per_plan.filter(|max| files as u64 > *max) {
+                return Err(OrdneError::SafetyViolation(format!(
+                    "Plan would delete {} files, over the limit of {} per plan; {}",
+                    files, max, split
+                )));
+            }
+            if let Some(max) = limits.max_bytes_per_plan.filter(|max| bytes.max(0) as u64 > *max) {
+                return Err(OrdneError::SafetyViolation(format!(
+                    "Plan would delete {}, over the limit of {} per plan; {}",
+                    format_bytes(bytes),
+                    format_bytes(max as i64),
+                    split
+                )));
+            }
+        }
+
+        if limits.max_files_per_day.is_none() && limits.max_bytes_per_day.is_none() {
+            return Ok(());
+        }
+        let (day_files, day_bytes) = self
+            .db
+            .get_delete_totals_since(self.options.clock.now() - chrono::Duration::days(1), None)?;
+        let files = day_files + plans.iter().map(|(files, _)| *files as i64).sum::<i64>();
+        let bytes = day_bytes + plans.iter().map(|(_, bytes)| *bytes).sum::<i64>();
+        if let Some(max) = limits.max_files_per_day.filter(|max| files.max(0) as u64 > *max) {
+            return Err(OrdneError::SafetyViolation(format!(
+                "Plans of the last 24 hours would delete {} files ({} already planned), over the limit of {} per day",
+                files, day_files, max
+            )));
+        }
+        if let Some(max) = limits.max_bytes_per_day.filter(|max| bytes.max(0) as u64 > *max) {
+            return Err(OrdneError::SafetyViolation(format!(
+                "Plans of the last 24 hours would delete {} ({} already planned), over the limit of {} per day",
+                format_bytes(bytes),
+                format_bytes(day_bytes),
+                format_bytes(max as i64)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Refuse plans that would take the attributed actor's deletions of the
+    /// last 24 hours over [`PlannerOptions::actor_delete_bytes_per_day`].
+    fn check_actor_delete_budget(&self, plans: &[(usize, i64)]) -> Result<()> {
+        let (Some(max), Some(actor)) = (self.options.actor_delete_bytes_per_day, self.actor()) else {
+            return Ok(());
+        };
+        let (_, used) = self
+            .db
+            .get_delete_totals_since(self.options.clock.now() - chrono::Duration::days(1), Some(&actor))?;
+        let requested: i64 = plans.iter().map(|(_, bytes)| *bytes).sum();
+        if (used + requested).max(0) as u64 > max {
+            return Err(OrdneError::SafetyViolation(format!(
+                "Deletion budget exceeded: {} requested, {} of {} used by '{}' in the last 24 hours",
+                format_bytes(requested),
+                format_bytes(used),
+                format_bytes(max as i64),
+                actor
+            )));
+        }
+        Ok(())
+    }
+
+    /// Refuse to place files of a `require_encryption` category on a drive
+    /// that is not encrypted. `copies` pairs each file with its target drive.
+    fn check_encrypted_targets<'f>(
+        &self,
+        copies: impl IntoIterator<Item = (&'f File, i64)>,
+    ) -> Result<()> {
+        let required = &self.options.require_encryption;
+        if required.is_empty() {
+            return Ok(());
+        }
+
+        let mut encrypted: HashMap<i64, (String, bool)> = HashMap::new();
+        let mut refused = 0;
+        let mut targets = Vec::new();
+        for (file, drive_id) in copies {
+            if !file.category.as_ref().is_some_and(|c| required.contains(c)) {
+                continue;
+            }
+            let (label, is_encrypted) = match encrypted.entry(drive_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let drive = self
+                        .db
+                        .get_drive_by_id(drive_id)?
+                        .ok_or_else(|| OrdneError::DriveNotFound(format!("id {}", drive_id)))?;
+                    entry.insert((drive.label, drive.is_encrypted))
+                }
+            };
+            if !*is_encrypted {
+                refused += 1;
+                if !targets.contains(label) {
+                    targets.push(label.clone());
+                }
+            }
+        }
+
+        if refused > 0 {
+            return Err(OrdneError::SafetyViolation(format!(
+                "{} files in categories requiring encryption ({}) would go to unencrypted drive {}",
+                refused,
+                required.join(", "),
+                targets.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    /// Record the `duplicates` of `file` as covered by its copy step.
+    fn record_satisfied(
+        &mut self,
+        plan_id: i64,
+        file: &File,
+        step_id: i64,
+        duplicates: &HashMap<String, Vec<File>>,
+    ) -> Result<()> {
+        let Some(copies) = file.content_hash().and_then(|hash| duplicates.get(&hash)) else {
+            return Ok(());
+        };
+        for copy in copies {
+            self.db.add_satisfied_file(plan_id, copy.id, step_id)?;
+        }
+        Ok(())
+    }
+
+    /// The `deletions` from one duplicate `group` that keep its content
+    /// redundant, the earliest deletions going first:
+    ///
+    /// - Critical copies are never deleted, and a group with any Critical
+    ///   copy keeps at least `min_critical_copies` copies.
+    /// - The last copy on a drive is kept if deleting it would leave the
+    ///   content on fewer drives than its categories require.
+    fn retained_deletions<'f>(
+        &self,
+        group: &[&File],
+        deletions: Vec<&'f File>,
+        requirements: &HashMap<String, usize>,
+    ) -> Vec<&'f File> {
+        let mut kept: Vec<&File> = deletions
+            .into_iter()
+            .filter(|f| f.priority != Priority::Critical)
+            .collect();
+
+        if is_critical(group) {
+            let allowed = group.len().saturating_sub(self.options.min_critical_copies.max(1));
+            if kept.len() > allowed {
+                log::info!(
+                    "Keeping {} extra copies of Critical content for redundancy",
+                    kept.len() - allowed
+                );
+                kept.truncate(allowed);
+            }
+        }
+
+        let required = required_drives(requirements, group.iter().copied());
+        if required > 0 {
+            let mut per_drive: HashMap<i64, usize> = HashMap::new();
+            for file in group {
+                *per_drive.entry(file.drive_id).or_default() += 1;
+            }
+            kept.retain(|file| {
+                let copies = per_drive.get(&file.drive_id).copied().unwrap_or(0);
+                if copies <= 1 && per_drive.len() <= required {
+                    log::info!("Keeping {}: content must stay on {} drives", file.abs_path, required);
+                    return false;
+                }
+                if copies <= 1 {
+                    per_drive.remove(&file.drive_id);
+                } else {
+                    per_drive.insert(file.drive_id, copies - 1);
+                }
+                true
+            });
+        }
+        kept
+    }
+
+    /// The `files` an offload to `offload_drive_id` can remove without
+    /// leaving their content on fewer drives than their category requires,
+    /// which happens when the offload drive already holds a copy.
+    fn offloadable_files(
+        &self,
+        files: Vec<File>,
+        offload_drive_id: i64,
+        requirements: &HashMap<String, usize>,
+    ) -> Result<Vec<File>> {
+        if requirements.is_empty() {
+            return Ok(files);
+        }
+
+        let offloaded: std::collections::HashSet<i64> = files.iter().map(|f| f.id).collect();
+        let mut kept = Vec::with_capacity(files.len());
+        for file in files {
+            let Some(hash) = file.content_hash() else {
+                kept.push(file);
+                continue;
+            };
+            if required_drives(requirements, std::iter::once(&file)) == 0 {
+                kept.push(file);
+                continue;
+            }
+
+            let copies: Vec<File> = self
+                .db
+                .get_files_by_hash(&hash)?
+                .into_iter()
+                .filter(|f| f.status != FileStatus::SourceRemoved)
+                .collect();
+            let required = required_drives(requirements, copies.iter().chain([&file]));
+
+            let before: std::collections::HashSet<i64> =
+                copies.iter().map(|f| f.drive_id).chain([file.drive_id]).collect();
+            let after: std::collections::HashSet<i64> = copies
+                .iter()
+                .filter(|f| !offloaded.contains(&f.id))
+                .map(|f| f.drive_id)
+                .chain([offload_drive_id])
+                .collect();
+
+            if after.len() < required && after.len() < before.len() {
+                log::warn!(
+                    "Not offloading {}: content must stay on {} drives",
+                    file.abs_path,
+                    required
+                );
+                continue;
+            }
+            kept.push(file);
+        }
+        Ok(kept)
+    }
+
     pub fn create_delete_trash_plan(&mut self, files: Vec<File>) -> Result<i64> {
         let total_files = files.len() as i32;
         let total_bytes: i64 = files.iter().map(|f| f.size_bytes).sum();
+        self.check_delete_limits(&[(files.len(), total_bytes)])?;
 
         let plan = MigrationPlan {
             id: 0,
-            created_at: Utc::now(),
+            created_at: self.options.clock.now(),
             description: Some(format!("Delete {} trash files", total_files)),
             source_drive_id: None,
             target_drive_id: None,
@@ -48,6 +425,8 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
             total_bytes,
             completed_files: 0,
             completed_bytes: 0,
+            created_by: self.actor(),
+            step_ordering: self.options.step_ordering,
         };
 
         let plan_id = self.db.create_plan(&plan)?;
@@ -65,6 +444,7 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
                 status: StepStatus::Pending,
                 pre_hash: None,
                 post_hash: None,
+                hash_algorithm: None,
                 executed_at: None,
                 error: None,
                 step_order: order as i32,
@@ -75,13 +455,16 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
 
         self.db.log_audit(&AuditLogEntry {
             id: 0,
-            timestamp: Utc::now(),
+            timestamp: self.options.clock.now(),
             action: "plan_created".to_string(),
             file_id: None,
             plan_id: Some(plan_id),
             drive_id: None,
             details: Some(format!("Delete trash plan: {} files", total_files)),
-            agent_mode: Some("automated".to_string()),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
         })?;
 
         Ok(plan_id)
@@ -94,12 +477,24 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
             ));
         }
 
+        let requirements = self.replica_requirements();
+        let group: Vec<&File> = std::iter::once(original).chain(&duplicate_files).collect();
+        let duplicate_files =
+            self.retained_deletions(&group, duplicate_files.iter().collect(), &requirements);
+        if duplicate_files.is_empty() {
+            return Err(OrdneError::SafetyViolation(
+                "No duplicates can be deleted without dropping below the required copies"
+                    .to_string(),
+            ));
+        }
+
         let total_files = duplicate_files.len() as i32;
         let total_bytes: i64 = duplicate_files.iter().map(|f| f.size_bytes).sum();
+        self.check_delete_limits(&[(duplicate_files.len(), total_bytes)])?;
 
         let plan = MigrationPlan {
             id: 0,
-            created_at: Utc::now(),
+            created_at: self.options.clock.now(),
             description: Some(format!(
                 "Deduplicate {} files (keep original: {})",
                 total_files, original.abs_path
@@ -111,6 +506,8 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
             total_bytes,
             completed_files: 0,
             completed_bytes: 0,
+            created_by: self.actor(),
+            step_ordering: self.options.step_ordering,
         };
 
         let plan_id = self.db.create_plan(&plan)?;
@@ -126,7 +523,8 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
                 dest_path: None,
                 dest_drive_id: None,
                 status: StepStatus::Pending,
-                pre_hash: file.blake3_hash.clone().or_else(|| file.md5_hash.clone()),
+                pre_hash: file.content_hash(),
+                hash_algorithm: file.content_hash_algorithm(),
                 post_hash: None,
                 executed_at: None,
                 error: None,
@@ -138,7 +536,7 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
 
         self.db.log_audit(&AuditLogEntry {
             id: 0,
-            timestamp: Utc::now(),
+            timestamp: self.options.clock.now(),
             action: "plan_created".to_string(),
             file_id: None,
             plan_id: Some(plan_id),
@@ -147,12 +545,278 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
                 "Deduplication plan: {} duplicates, original: {}",
                 total_files, original.abs_path
             )),
-            agent_mode: Some("automated".to_string()),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
+        })?;
+
+        Ok(plan_id)
+    }
+
+    /// Create a single dedup plan covering several reviewed duplicate groups.
+    pub fn create_grouped_dedup_plan(&mut self, resolutions: &[DedupResolution]) -> Result<i64> {
+        let requirements = self.replica_requirements();
+        let deletions: Vec<&File> = resolutions
+            .iter()
+            .flat_map(|r| {
+                let group: Vec<&File> = std::iter::once(&r.keeper).chain(&r.duplicates).collect();
+                self.retained_deletions(&group, r.duplicates.iter().collect(), &requirements)
+            })
+            .collect();
+        let total_files = deletions.len();
+        if total_files == 0 {
+            return Err(crate::error::OrdneError::Migration(
+                "No duplicate files provided".to_string(),
+            ));
+        }
+
+        let total_bytes: i64 = deletions.iter().map(|f| f.size_bytes).sum();
+        self.check_delete_limits(&[(total_files, total_bytes)])?;
+
+        let plan = MigrationPlan {
+            id: 0,
+            created_at: self.options.clock.now(),
+            description: Some(format!(
+                "Deduplicate {} files across {} reviewed groups",
+                total_files,
+                resolutions.len()
+            )),
+            source_drive_id: None,
+            target_drive_id: None,
+            status: PlanStatus::Draft,
+            total_files: total_files as i32,
+            total_bytes,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: self.actor(),
+            step_ordering: self.options.step_ordering,
+        };
+
+        let plan_id = self.db.create_plan(&plan)?;
+
+        for (order, file) in deletions.iter().enumerate() {
+            let step = MigrationStep {
+                id: 0,
+                plan_id,
+                file_id: file.id,
+                action: StepAction::Delete,
+                source_path: file.abs_path.clone(),
+                source_drive_id: file.drive_id,
+                dest_path: None,
+                dest_drive_id: None,
+                status: StepStatus::Pending,
+                pre_hash: file.content_hash(),
+                hash_algorithm: file.content_hash_algorithm(),
+                post_hash: None,
+                executed_at: None,
+                error: None,
+                step_order: order as i32,
+            };
+
+            self.db.add_step(&step)?;
+        }
+
+        let group_ids: Vec<String> = resolutions.iter().map(|r| r.group_id.to_string()).collect();
+        let groups: Vec<DuplicateGroupSnapshot> = resolutions
+            .iter()
+            .map(|r| DuplicateGroupSnapshot {
+                group_id: r.group_id,
+                original_id: r.previous_original_id,
+                resolution: r.previous_resolution.clone(),
+            })
+            .collect();
+        self.db.log_audit(&AuditLogEntry {
+            id: 0,
+            timestamp: self.options.clock.now(),
+            action: "plan_created".to_string(),
+            file_id: None,
+            plan_id: Some(plan_id),
+            drive_id: None,
+            details: Some(format!(
+                "Reviewed deduplication plan: {} duplicates, groups: {}",
+                total_files,
+                group_ids.join(",")
+            )),
+            agent_mode: self.agent_mode("manual"),
+            before_state: serde_json::to_string(&groups).ok(),
+            after_state: None,
+            actor: self.actor(),
         })?;
 
         Ok(plan_id)
     }
 
+    /// Resolve cross-drive duplicate groups by drive role, creating one plan
+    /// per drive that loses copies. Returns the created plan IDs.
+    pub fn create_cross_drive_dedup_plans(
+        &mut self,
+        groups: Vec<Vec<File>>,
+        options: &CrossDriveDedupOptions,
+    ) -> Result<Vec<i64>> {
+        options.validate()?;
+        let requirements = self.replica_requirements();
+
+        let drives: HashMap<i64, Drive> = self
+            .db
+            .list_drives()?
+            .into_iter()
+            .map(|d| (d.id, d))
+            .collect();
+        let role_of = |file: &File| drives.get(&file.drive_id).map(|d| d.role);
+
+        // drive_id -> (steps without plan_id, bytes reclaimed)
+        let mut per_drive: BTreeMap<i64, (Vec<MigrationStep>, i64)> = BTreeMap::new();
+        let mut delete_sizes: HashMap<i64, i64> = HashMap::new();
+
+        for files in groups {
+            let keepers: Vec<&File> = files
+                .iter()
+                .filter(|f| role_of(f).is_some_and(|r| options.keep_roles.contains(&r)))
+                .collect();
+            if keepers.is_empty() {
+                continue;
+            }
+
+            let removable: Vec<&File> = files
+                .iter()
+                .filter(|f| role_of(f).is_some_and(|r| options.remove_roles.contains(&r)))
+                .collect();
+            let group: Vec<&File> = files.iter().collect();
+            for file in self.retained_deletions(&group, removable, &requirements) {
+                let entry = per_drive.entry(file.drive_id).or_default();
+                entry.0.push(dedup_delete_step(file));
+                entry.1 += file.size_bytes;
+                delete_sizes.insert(file.id, file.size_bytes);
+            }
+
+            // Hardlinked copies share one set of blocks, which is no
+            // redundancy for Critical content.
+            if !options.hardlink_within_drive || is_critical(&group) {
+                continue;
+            }
+
+            let mut by_drive: BTreeMap<i64, Vec<&File>> = BTreeMap::new();
+            for file in keepers {
+                by_drive.entry(file.drive_id).or_default().push(file);
+            }
+
+            for (drive_id, copies) in by_drive {
+                let local = drives.get(&drive_id).is_some_and(|d| d.backend == Backend::Local);
+                let anchor = copies[0];
+                if !local || copies.len() < 2 || anchor.device_num.is_none() {
+                    continue;
+                }
+
+                for copy in &copies[1..] {
+                    let same_device = copy.device_num == anchor.device_num;
+                    let already_linked = copy.inode.is_some() && copy.inode == anchor.inode;
+                    if !same_device || already_linked {
+                        continue;
+                    }
+
+                    let entry = per_drive.entry(drive_id).or_default();
+                    entry.0.push(dedup_delete_step(copy));
+                    delete_sizes.insert(copy.id, copy.size_bytes);
+                    entry.0.push(MigrationStep {
+                        action: StepAction::Hardlink,
+                        source_path: anchor.abs_path.clone(),
+                        dest_path: Some(copy.abs_path.clone()),
+                        dest_drive_id: Some(drive_id),
+                        pre_hash: anchor.content_hash(),
+                        hash_algorithm: anchor.content_hash_algorithm(),
+                        ..dedup_delete_step(copy)
+                    });
+                    entry.1 += copy.size_bytes;
+                }
+            }
+        }
+
+        // Only the delete steps count towards the limits, not the hardlinks.
+        let scope: Vec<(usize, i64)> = per_drive
+            .values()
+            .map(|(steps, _)| {
+                let deletes = steps.iter().filter(|s| s.action == StepAction::Delete);
+                deletes.fold((0, 0), |(files, bytes), step| {
+                    (files + 1, bytes + delete_sizes.get(&step.file_id).copied().unwrap_or(0))
+                })
+            })
+            .collect();
+        self.check_delete_limits(&scope)?;
+
+        let mut plan_ids = Vec::new();
+        for (drive_id, (steps, total_bytes)) in per_drive {
+            let label = drives
+                .get(&drive_id)
+                .map(|d| d.label.clone())
+                .unwrap_or_else(|| drive_id.to_string());
+            let deletes = steps.iter().filter(|s| s.action == StepAction::Delete).count();
+            let hardlinks = steps.len() - deletes;
+
+            let plan = MigrationPlan {
+                id: 0,
+                created_at: self.options.clock.now(),
+                description: Some(format!(
+                    "Cross-drive dedup on {}: {} deletions, {} hardlinks",
+                    label, deletes, hardlinks
+                )),
+                source_drive_id: Some(drive_id),
+                target_drive_id: None,
+                status: PlanStatus::Draft,
+                total_files: deletes as i32,
+                total_bytes,
+                completed_files: 0,
+                completed_bytes: 0,
+                created_by: self.actor(),
+                step_ordering: self.options.step_ordering,
+            };
+
+            let plan_id = self.db.create_plan(&plan)?;
+
+            let mut previous_delete = None;
+            for (order, step) in steps.into_iter().enumerate() {
+                let action = step.action;
+                let step_id = self.db.add_step(&MigrationStep {
+                    plan_id,
+                    step_order: order as i32,
+                    ..step
+                })?;
+
+                // A hardlink replaces the copy deleted by the step just before it.
+                match action {
+                    StepAction::Delete => previous_delete = Some(step_id),
+                    StepAction::Hardlink => {
+                        if let Some(delete_id) = previous_delete.take() {
+                            self.db.add_step_dependency(step_id, delete_id)?;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            self.db.log_audit(&AuditLogEntry {
+                id: 0,
+                timestamp: self.options.clock.now(),
+                action: "plan_created".to_string(),
+                file_id: None,
+                plan_id: Some(plan_id),
+                drive_id: Some(drive_id),
+                details: Some(format!(
+                    "Cross-drive dedup plan: {} deletions, {} hardlinks, {} bytes reclaimed",
+                    deletes, hardlinks, total_bytes
+                )),
+                agent_mode: self.agent_mode("automated"),
+                before_state: None,
+                after_state: None,
+                actor: self.actor(),
+            })?;
+
+            plan_ids.push(plan_id);
+        }
+
+        Ok(plan_ids)
+    }
+
     pub fn create_migrate_plan(
         &mut self,
         files: Vec<File>,
@@ -165,20 +829,29 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
             ));
         }
 
+        let (files, duplicates) = split_duplicates(files);
+        self.check_encrypted_targets(files.iter().map(|f| (f, target_drive_id)))?;
+
         let source_drive_id = files[0].drive_id;
         let total_files = files.len() as i32;
         let total_bytes: i64 = files.iter().map(|f| f.size_bytes).sum();
 
         if self.options.enforce_space_limits {
             space::verify_sufficient_space(target_mount, total_bytes as u64)?;
+            let inodes = space::estimate_inodes(
+                files.iter().map(|f| f.target_path.as_deref().unwrap_or(&f.path)),
+            );
+            space::verify_sufficient_inodes(target_mount, inodes)?;
         }
 
         let plan = MigrationPlan {
             id: 0,
-            created_at: Utc::now(),
+            created_at: self.options.clock.now(),
             description: Some(format!(
-                "Migrate {} files to target drive {}",
-                total_files, target_drive_id
+                "Migrate {} files to target drive {}{}",
+                total_files,
+                target_drive_id,
+                duplicates_note(&duplicates)
             )),
             source_drive_id: Some(source_drive_id),
             target_drive_id: Some(target_drive_id),
@@ -187,6 +860,8 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
             total_bytes,
             completed_files: 0,
             completed_bytes: 0,
+            created_by: self.actor(),
+            step_ordering: self.options.step_ordering,
         };
 
         let plan_id = self.db.create_plan(&plan)?;
@@ -207,19 +882,21 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
                 dest_path: Some(format!("{}/{}", target_mount, target_path)),
                 dest_drive_id: Some(target_drive_id),
                 status: StepStatus::Pending,
-                pre_hash: file.blake3_hash.clone().or_else(|| file.md5_hash.clone()),
+                pre_hash: file.content_hash(),
+                hash_algorithm: file.content_hash_algorithm(),
                 post_hash: None,
                 executed_at: None,
                 error: None,
                 step_order: order as i32,
             };
 
-            self.db.add_step(&step)?;
+            let step_id = self.db.add_step(&step)?;
+            self.record_satisfied(plan_id, file, step_id, &duplicates)?;
         }
 
         self.db.log_audit(&AuditLogEntry {
             id: 0,
-            timestamp: Utc::now(),
+            timestamp: self.options.clock.now(),
             action: "plan_created".to_string(),
             file_id: None,
             plan_id: Some(plan_id),
@@ -228,99 +905,388 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
                 "Migration plan: {} files, {} bytes",
                 total_files, total_bytes
             )),
-            agent_mode: Some("automated".to_string()),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
         })?;
 
         Ok(plan_id)
     }
 
-    pub fn create_offload_plan(
+    /// Migrate files onto a pool of target drives, keeping constrained
+    /// categories together on one drive.
+    pub fn create_pool_migrate_plan(
         &mut self,
         files: Vec<File>,
-        offload_drive_id: i64,
-        offload_mount: &str,
+        mut targets: Vec<PlacementTarget>,
+        constraints: &[PlacementConstraint],
     ) -> Result<i64> {
         if files.is_empty() {
             return Err(crate::error::OrdneError::Migration(
-                "No files provided for offload".to_string(),
+                "No files provided for migration".to_string(),
             ));
         }
 
+        if self.options.enforce_space_limits {
+            for target in targets.iter_mut().filter(|t| t.capacity_bytes.is_none()) {
+                let space_info = space::get_free_space(&target.mount_path)?;
+                target.capacity_bytes = Some(space_info.max_safe_write_bytes());
+            }
+        }
+
+        let (files, duplicates) = split_duplicates(files);
         let source_drive_id = files[0].drive_id;
         let total_files = files.len() as i32;
         let total_bytes: i64 = files.iter().map(|f| f.size_bytes).sum();
+        let placed = placement::place_files(files, &targets, constraints)?;
+        self.check_encrypted_targets(placed.iter().map(|(f, idx)| (f, targets[*idx].drive_id)))?;
 
         if self.options.enforce_space_limits {
-            space::verify_sufficient_space(offload_mount, total_bytes as u64)?;
+            for (idx, target) in targets.iter().enumerate() {
+                let inodes = space::estimate_inodes(
+                    placed
+                        .iter()
+                        .filter(|(_, i)| *i == idx)
+                        .map(|(f, _)| f.target_path.as_deref().unwrap_or(&f.path)),
+                );
+                space::verify_sufficient_inodes(&target.mount_path, inodes)?;
+            }
         }
 
         let plan = MigrationPlan {
             id: 0,
-            created_at: Utc::now(),
+            created_at: self.options.clock.now(),
             description: Some(format!(
-                "Offload {} low-priority files to drive {}",
-                total_files, offload_drive_id
+                "Migrate {} files across {} target drives{}",
+                total_files,
+                targets.len(),
+                duplicates_note(&duplicates)
             )),
             source_drive_id: Some(source_drive_id),
-            target_drive_id: Some(offload_drive_id),
+            target_drive_id: None,
             status: PlanStatus::Draft,
             total_files,
             total_bytes,
             completed_files: 0,
             completed_bytes: 0,
+            created_by: self.actor(),
+            step_ordering: self.options.step_ordering,
         };
 
         let plan_id = self.db.create_plan(&plan)?;
 
-        for (order, file) in files.iter().enumerate() {
-            let copy_step = MigrationStep {
+        for (order, (file, idx)) in placed.iter().enumerate() {
+            let target = &targets[*idx];
+            let target_path = file
+                .target_path
+                .clone()
+                .unwrap_or_else(|| file.path.clone());
+
+            let step = MigrationStep {
                 id: 0,
                 plan_id,
                 file_id: file.id,
                 action: StepAction::Copy,
                 source_path: file.abs_path.clone(),
                 source_drive_id: file.drive_id,
-                dest_path: Some(format!("{}/{}", offload_mount, file.path)),
-                dest_drive_id: Some(offload_drive_id),
+                dest_path: Some(format!("{}/{}", target.mount_path, target_path)),
+                dest_drive_id: Some(target.drive_id),
                 status: StepStatus::Pending,
-                pre_hash: file.blake3_hash.clone().or_else(|| file.md5_hash.clone()),
+                pre_hash: file.content_hash(),
+                hash_algorithm: file.content_hash_algorithm(),
                 post_hash: None,
                 executed_at: None,
                 error: None,
-                step_order: (order * 2) as i32,
+                step_order: order as i32,
             };
 
-            self.db.add_step(&copy_step)?;
+            let step_id = self.db.add_step(&step)?;
+            self.record_satisfied(plan_id, file, step_id, &duplicates)?;
+        }
 
-            let delete_step = MigrationStep {
+        self.db.log_audit(&AuditLogEntry {
+            id: 0,
+            timestamp: self.options.clock.now(),
+            action: "plan_created".to_string(),
+            file_id: None,
+            plan_id: Some(plan_id),
+            drive_id: None,
+            details: Some(format!(
+                "Pool migration plan: {} files, {} bytes, {} targets, {} placement constraints",
+                total_files,
+                total_bytes,
+                targets.len(),
+                constraints.len()
+            )),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
+        })?;
+
+        Ok(plan_id)
+    }
+
+    pub fn create_offload_plan(
+        &mut self,
+        files: Vec<File>,
+        offload_drive_id: i64,
+        offload_mount: &str,
+    ) -> Result<i64> {
+        self.offload_plan(files, offload_drive_id, offload_mount, OffloadLayout::Mirror)
+    }
+
+    /// Offload into the content-addressed layout (see [`crate::migrate::cas`]).
+    ///
+    /// Files with identical content share one object, so only the first is
+    /// copied; the deletes of the others depend on that copy.
+    pub fn create_cas_offload_plan(
+        &mut self,
+        files: Vec<File>,
+        offload_drive_id: i64,
+        offload_mount: &str,
+    ) -> Result<i64> {
+        self.offload_plan(files, offload_drive_id, offload_mount, OffloadLayout::ContentAddressed)
+    }
+
+    fn offload_plan(
+        &mut self,
+        files: Vec<File>,
+        offload_drive_id: i64,
+        offload_mount: &str,
+        layout: OffloadLayout,
+    ) -> Result<i64> {
+        if files.is_empty() {
+            return Err(crate::error::OrdneError::Migration(
+                "No files provided for offload".to_string(),
+            ));
+        }
+
+        let requirements = self.replica_requirements();
+        let files = self.offloadable_files(files, offload_drive_id, &requirements)?;
+        if files.is_empty() {
+            return Err(OrdneError::Migration(
+                "No files can be offloaded without dropping below their required replicas"
+                    .to_string(),
+            ));
+        }
+
+        self.check_encrypted_targets(files.iter().map(|f| (f, offload_drive_id)))?;
+
+        let cas = layout == OffloadLayout::ContentAddressed;
+        if cas {
+            let unhashed = files.iter().filter(|f| f.content_hash().is_none()).count();
+            if unhashed > 0 {
+                return Err(OrdneError::Migration(format!(
+                    "Content-addressed offload needs hashes; {} files have none",
+                    unhashed
+                )));
+            }
+        }
+
+        let source_drive_id = files[0].drive_id;
+        let total_files = files.len() as i32;
+        let total_bytes: i64 = files.iter().map(|f| f.size_bytes).sum();
+        self.check_delete_limits(&[(files.len(), total_bytes)])?;
+
+        if self.options.enforce_space_limits {
+            let needed_bytes: i64 = if cas {
+                let mut seen = std::collections::HashSet::new();
+                files
+                    .iter()
+                    .filter(|f| seen.insert(f.content_hash()))
+                    .map(|f| f.size_bytes)
+                    .sum()
+            } else {
+                total_bytes
+            };
+            space::verify_sufficient_space(offload_mount, needed_bytes as u64)?;
+            space::verify_sufficient_inodes(
+                offload_mount,
+                space::estimate_inodes(files.iter().map(|f| f.path.as_str())),
+            )?;
+        }
+
+        let plan = MigrationPlan {
+            id: 0,
+            created_at: self.options.clock.now(),
+            description: Some(format!(
+                "Offload {} low-priority files to drive {}{}",
+                total_files,
+                offload_drive_id,
+                if cas { " (content-addressed)" } else { "" }
+            )),
+            source_drive_id: Some(source_drive_id),
+            target_drive_id: Some(offload_drive_id),
+            status: PlanStatus::Draft,
+            total_files,
+            total_bytes,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: self.actor(),
+            step_ordering: self.options.step_ordering,
+        };
+
+        let plan_id = self.db.create_plan(&plan)?;
+
+        // Copy and delete batch by batch, largest files first, so the source
+        // drive regains space as the plan runs rather than only at the end.
+        let mut order = 0;
+        let mut objects: HashMap<String, i64> = HashMap::new();
+        let by_priority = self.options.step_ordering == StepOrdering::Priority;
+        for batch in offload_batches(&files, self.options.max_batch_size_bytes, by_priority) {
+            let mut copy_ids = Vec::with_capacity(batch.len());
+            for file in &batch {
+                let dest_path = match (layout, file.content_hash()) {
+                    (OffloadLayout::ContentAddressed, Some(hash)) => {
+                        if let Some(&copy_id) = objects.get(&hash) {
+                            copy_ids.push(copy_id);
+                            continue;
+                        }
+                        cas::object_path(offload_mount, &hash)
+                    }
+                    _ => format!("{}/{}", offload_mount, file.path),
+                };
+
+                let copy_step = MigrationStep {
+                    id: 0,
+                    plan_id,
+                    file_id: file.id,
+                    action: StepAction::Copy,
+                    source_path: file.abs_path.clone(),
+                    source_drive_id: file.drive_id,
+                    dest_path: Some(dest_path),
+                    dest_drive_id: Some(offload_drive_id),
+                    status: StepStatus::Pending,
+                    pre_hash: file.content_hash(),
+                    hash_algorithm: file.content_hash_algorithm(),
+                    post_hash: None,
+                    executed_at: None,
+                    error: None,
+                    step_order: order,
+                };
+                order += 1;
+
+                let copy_id = self.db.add_step(&copy_step)?;
+                if let (true, Some(hash)) = (cas, file.content_hash()) {
+                    objects.insert(hash, copy_id);
+                }
+                copy_ids.push(copy_id);
+            }
+
+            for (file, copy_id) in batch.iter().zip(copy_ids) {
+                let delete_step = MigrationStep {
+                    id: 0,
+                    plan_id,
+                    file_id: file.id,
+                    action: StepAction::Delete,
+                    source_path: file.abs_path.clone(),
+                    source_drive_id: file.drive_id,
+                    dest_path: None,
+                    dest_drive_id: None,
+                    status: StepStatus::Pending,
+                    pre_hash: file.content_hash(),
+                    hash_algorithm: file.content_hash_algorithm(),
+                    post_hash: None,
+                    executed_at: None,
+                    error: None,
+                    step_order: order,
+                };
+                order += 1;
+
+                let delete_id = self.db.add_step(&delete_step)?;
+                self.db.add_step_dependency(delete_id, copy_id)?;
+            }
+        }
+
+        self.db.log_audit(&AuditLogEntry {
+            id: 0,
+            timestamp: self.options.clock.now(),
+            action: "plan_created".to_string(),
+            file_id: None,
+            plan_id: Some(plan_id),
+            drive_id: Some(offload_drive_id),
+            details: Some(format!("Offload plan: {} files", total_files)),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
+        })?;
+
+        Ok(plan_id)
+    }
+
+    /// Rewrite failed destinations from the verified sources in `repairs`.
+    pub fn create_recopy_plan(&mut self, repairs: &[Repair]) -> Result<i64> {
+        if repairs.is_empty() {
+            return Err(OrdneError::Migration(
+                "No repairable verification failures".to_string(),
+            ));
+        }
+
+        let total_files = repairs.len() as i32;
+        let total_bytes: i64 = repairs.iter().map(|r| r.size_bytes).sum();
+        let failure_ids: Vec<String> = repairs.iter().map(|r| r.failure_id.to_string()).collect();
+
+        let plan = MigrationPlan {
+            id: 0,
+            created_at: self.options.clock.now(),
+            description: Some(format!(
+                "Re-copy {} files that failed verification",
+                total_files
+            )),
+            source_drive_id: None,
+            target_drive_id: None,
+            status: PlanStatus::Draft,
+            total_files,
+            total_bytes,
+            completed_files: 0,
+            completed_bytes: 0,
+            created_by: self.actor(),
+            step_ordering: self.options.step_ordering,
+        };
+
+        let plan_id = self.db.create_plan(&plan)?;
+
+        for (order, repair) in repairs.iter().enumerate() {
+            let step = MigrationStep {
                 id: 0,
                 plan_id,
-                file_id: file.id,
-                action: StepAction::Delete,
-                source_path: file.abs_path.clone(),
-                source_drive_id: file.drive_id,
-                dest_path: None,
-                dest_drive_id: None,
+                file_id: repair.file_id,
+                action: StepAction::Copy,
+                source_path: repair.source_path.clone(),
+                source_drive_id: repair.source_drive_id,
+                dest_path: Some(repair.dest_path.clone()),
+                dest_drive_id: repair.dest_drive_id,
                 status: StepStatus::Pending,
-                pre_hash: file.blake3_hash.clone().or_else(|| file.md5_hash.clone()),
+                pre_hash: Some(repair.expected_hash.clone()),
+                hash_algorithm: Some(repair.hash_algorithm),
                 post_hash: None,
                 executed_at: None,
                 error: None,
-                step_order: (order * 2 + 1) as i32,
+                step_order: order as i32,
             };
 
-            self.db.add_step(&delete_step)?;
+            self.db.add_step(&step)?;
         }
 
         self.db.log_audit(&AuditLogEntry {
             id: 0,
-            timestamp: Utc::now(),
+            timestamp: self.options.clock.now(),
             action: "plan_created".to_string(),
             file_id: None,
             plan_id: Some(plan_id),
-            drive_id: Some(offload_drive_id),
-            details: Some(format!("Offload plan: {} files", total_files)),
-            agent_mode: Some("automated".to_string()),
+            drive_id: None,
+            details: Some(format!(
+                "Re-copy plan: {} files for verification failures {}",
+                total_files,
+                failure_ids.join(", ")
+            )),
+            agent_mode: self.agent_mode("automated"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
         })?;
 
         Ok(plan_id)
@@ -332,22 +1298,133 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> Planner<'a, D> {
 
         self.db.log_audit(&AuditLogEntry {
             id: 0,
-            timestamp: Utc::now(),
+            timestamp: self.options.clock.now(),
             action: "plan_approved".to_string(),
             file_id: None,
             plan_id: Some(plan_id),
             drive_id: None,
             details: Some("Plan approved for execution".to_string()),
-            agent_mode: Some("manual".to_string()),
+            agent_mode: self.agent_mode("manual"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
         })?;
 
         Ok(())
     }
 }
 
+/// The most drives any category among `files` must be on; 0 for none.
+fn required_drives<'f>(
+    requirements: &HashMap<String, usize>,
+    files: impl IntoIterator<Item = &'f File>,
+) -> usize {
+    files
+        .into_iter()
+        .filter_map(|f| f.category.as_ref().and_then(|c| requirements.get(c)))
+        .copied()
+        .max()
+        .unwrap_or(0)
+}
+
+fn is_critical(group: &[&File]) -> bool {
+    group.iter().any(|f| f.priority == Priority::Critical)
+}
+
+fn dedup_delete_step(file: &File) -> MigrationStep {
+    MigrationStep {
+        id: 0,
+        plan_id: 0,
+        file_id: file.id,
+        action: StepAction::Delete,
+        source_path: file.abs_path.clone(),
+        source_drive_id: file.drive_id,
+        dest_path: None,
+        dest_drive_id: None,
+        status: StepStatus::Pending,
+        pre_hash: file.content_hash(),
+        hash_algorithm: file.content_hash_algorithm(),
+        post_hash: None,
+        executed_at: None,
+        error: None,
+        step_order: 0,
+    }
+}
+
+/// Keep one file per content hash for copying, preferring the group's
+/// original, and return the others keyed by hash. Unhashed files are all kept.
+fn split_duplicates(files: Vec<File>) -> (Vec<File>, HashMap<String, Vec<File>>) {
+    let mut keeper: HashMap<String, usize> = HashMap::new();
+    for (idx, file) in files.iter().enumerate() {
+        if let Some(hash) = file.content_hash() {
+            let current = keeper.entry(hash).or_insert(idx);
+            if file.is_original && !files[*current].is_original {
+                *current = idx;
+            }
+        }
+    }
+
+    let mut kept = Vec::with_capacity(keeper.len());
+    let mut duplicates: HashMap<String, Vec<File>> = HashMap::new();
+    for (idx, file) in files.into_iter().enumerate() {
+        match file.content_hash() {
+            Some(hash) if keeper[&hash] != idx => duplicates.entry(hash).or_default().push(file),
+            _ => kept.push(file),
+        }
+    }
+    (kept, duplicates)
+}
+
+fn duplicates_note(duplicates: &HashMap<String, Vec<File>>) -> String {
+    let count: usize = duplicates.values().map(Vec::len).sum();
+    if count == 0 {
+        String::new()
+    } else {
+        format!(" ({} duplicates satisfied by identical copies)", count)
+    }
+}
+
+/// Split offload files into batches of at most `max_bytes`, largest files
+/// first. Without a limit every file is its own batch. A file larger than the
+/// limit still gets a batch of its own.
+///
+/// `by_priority` sorts by priority before size, so no batch holds a file more
+/// important than one in an earlier batch and the engine's priority ordering
+/// never has to run a later batch's copies before an earlier batch's deletes.
+fn offload_batches(files: &[File], max_bytes: Option<u64>, by_priority: bool) -> Vec<Vec<&File>> {
+    let mut sorted: Vec<&File> = files.iter().collect();
+    if by_priority {
+        sorted.sort_by_key(|f| (f.priority, std::cmp::Reverse(f.size_bytes)));
+    } else {
+        sorted.sort_by_key(|f| std::cmp::Reverse(f.size_bytes));
+    }
+
+    let Some(max_bytes) = max_bytes else {
+        return sorted.into_iter().map(|f| vec![f]).collect();
+    };
+
+    let mut batches: Vec<Vec<&File>> = Vec::new();
+    let mut current: Vec<&File> = Vec::new();
+    let mut current_bytes = 0u64;
+    for file in sorted {
+        let size = file.size_bytes.max(0) as u64;
+        if !current.is_empty() && current_bytes + size > max_bytes {
+            batches.push(std::mem::take(&mut current));
+            current_bytes = 0;
+        }
+        current.push(file);
+        current_bytes += size;
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Utc;
     use crate::db::{FileStatus, Priority, SqliteDatabase};
 
     fn create_test_db() -> SqliteDatabase {
@@ -369,9 +1446,11 @@ mod tests {
             blake3_hash: None,
             created_at: Some(Utc::now()),
             modified_at: Some(Utc::now()),
+            accessed_at: None,
             inode: None,
             device_num: None,
             nlinks: None,
+            owner_uid: None,
             mime_type: Some("text/plain".to_string()),
             is_symlink: false,
             symlink_target: None,
@@ -396,14 +1475,13 @@ mod tests {
 
     fn insert_test_file_to_db(db: &SqliteDatabase, file: &File) -> i64 {
         db.conn().execute(
-            "INSERT INTO files (id, drive_id, path, abs_path, filename, extension, size_bytes,
+            "INSERT INTO files (id, drive_id, path, filename, extension, size_bytes,
                                md5_hash, mime_type, priority, status, indexed_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
             rusqlite::params![
                 file.id,
                 file.drive_id,
                 &file.path,
-                &file.abs_path,
                 &file.filename,
                 &file.extension,
                 file.size_bytes,
@@ -453,6 +1531,88 @@ mod tests {
         assert_eq!(steps[0].action, StepAction::Delete);
     }
 
+    #[test]
+    fn test_delete_limits_refuse_oversized_plans() {
+        let mut db = create_test_db();
+        db.conn().execute(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'drive1', 'source', 1, 'local')",
+            [],
+        ).unwrap();
+        let files: Vec<File> = (1..=5)
+            .map(|id| create_test_file(id, 1, &format!("trash{}.txt", id), 1500))
+            .collect();
+        for file in &files {
+            insert_test_file_to_db(&db, file);
+        }
+
+        let options = PlannerOptions {
+            delete_limits: DeleteLimits {
+                max_files_per_plan: Some(2),
+                max_bytes_per_day: Some(5000),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut planner = Planner::new(&mut db, options);
+
+        let err = planner.create_delete_trash_plan(files[..3].to_vec()).unwrap_err();
+        assert!(err.to_string().contains("split it into smaller plans"));
+
+        let first = planner.create_delete_trash_plan(files[..2].to_vec()).unwrap();
+        // 3000 bytes planned already; another 3000 would pass 5000 for the day.
+        let err = planner.create_delete_trash_plan(files[2..4].to_vec()).unwrap_err();
+        assert!(err.to_string().contains("per day"));
+        assert!(planner.create_delete_trash_plan(files[2..3].to_vec()).is_ok());
+
+        // Aborted plans no longer count against the day.
+        db.update_plan_status(first, PlanStatus::Aborted).unwrap();
+        let mut planner = Planner::new(&mut db, PlannerOptions {
+            delete_limits: DeleteLimits { max_bytes_per_day: Some(5000), ..Default::default() },
+            ..Default::default()
+        });
+        assert!(planner.create_delete_trash_plan(files[3..5].to_vec()).is_ok());
+    }
+
+    #[test]
+    fn test_actor_delete_budget_refuses_before_creating_plans() {
+        let mut db = create_test_db();
+        db.conn().execute(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'drive1', 'source', 1, 'local')",
+            [],
+        ).unwrap();
+        let files: Vec<File> = (1..=3)
+            .map(|id| create_test_file(id, 1, &format!("trash{}.txt", id), 600))
+            .collect();
+        for file in &files {
+            insert_test_file_to_db(&db, file);
+        }
+        let budget = |actor: &str| PlannerOptions {
+            attribution: Some(Attribution::agent(actor)),
+            actor_delete_bytes_per_day: Some(1000),
+            ..Default::default()
+        };
+
+        Planner::new(&mut db, budget("claude-1"))
+            .create_delete_trash_plan(files[..1].to_vec())
+            .unwrap();
+        let err = Planner::new(&mut db, budget("claude-1"))
+            .create_delete_trash_plan(files[1..2].to_vec())
+            .unwrap_err();
+        assert!(err.to_string().contains("Deletion budget exceeded"));
+        // Nothing is stored for the refused request.
+        let plans: i64 = db.conn().query_row("SELECT COUNT(*) FROM migration_plans", [], |row| row.get(0)).unwrap();
+        assert_eq!(plans, 1);
+
+        // Each actor has a budget of its own; without attribution there is none.
+        assert!(Planner::new(&mut db, budget("other"))
+            .create_delete_trash_plan(files[1..2].to_vec())
+            .is_ok());
+        let unattributed = PlannerOptions { actor_delete_bytes_per_day: Some(1000), ..Default::default() };
+        assert!(Planner::new(&mut db, unattributed)
+            .create_delete_trash_plan(files[2..3].to_vec())
+            .is_ok());
+    }
+
     #[test]
     fn test_create_dedup_plan() {
         let mut db = create_test_db();
@@ -486,6 +1646,192 @@ mod tests {
         assert_eq!(plan.status, PlanStatus::Draft);
     }
 
+    #[test]
+    fn test_planner_stamps_with_clock() {
+        let mut db = create_test_db();
+        db.conn().execute(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'drive1', 'source', 1, 'local')",
+            [],
+        ).unwrap();
+
+        let original = create_test_file(1, 1, "original.txt", 1000);
+        let duplicate = create_test_file(2, 1, "dup.txt", 1000);
+        insert_test_file_to_db(&db, &original);
+        insert_test_file_to_db(&db, &duplicate);
+
+        let at = chrono::DateTime::parse_from_rfc3339("2031-06-01T08:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let options = PlannerOptions {
+            clock: Clock::fixed(at),
+            ..Default::default()
+        };
+        let plan_id = Planner::new(&mut db, options)
+            .create_dedup_plan(vec![duplicate], &original)
+            .unwrap();
+
+        assert_eq!(db.get_plan(plan_id).unwrap().unwrap().created_at, at);
+        let entries = db.get_audit_entries_for_plan(plan_id).unwrap();
+        assert!(!entries.is_empty());
+        assert!(entries.iter().all(|e| e.timestamp == at));
+    }
+
+    #[test]
+    fn test_create_grouped_dedup_plan() {
+        let mut db = create_test_db();
+
+        db.conn().execute(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'drive1', 'source', 1, 'local')",
+            [],
+        ).unwrap();
+
+        let files: Vec<File> = (1..=5)
+            .map(|id| create_test_file(id, 1, &format!("file{}.txt", id), 100 * id))
+            .collect();
+        for file in &files {
+            insert_test_file_to_db(&db, file);
+        }
+
+        let resolutions = vec![
+            DedupResolution {
+                group_id: 1,
+                keeper: files[0].clone(),
+                duplicates: vec![files[1].clone(), files[2].clone()],
+                previous_original_id: None,
+                previous_resolution: None,
+            },
+            DedupResolution {
+                group_id: 2,
+                keeper: files[3].clone(),
+                duplicates: vec![files[4].clone()],
+                previous_original_id: Some(4),
+                previous_resolution: None,
+            },
+        ];
+
+        let mut planner = Planner::new(&mut db, PlannerOptions::default());
+        let plan_id = planner.create_grouped_dedup_plan(&resolutions).unwrap();
+
+        let plan = db.get_plan(plan_id).unwrap().unwrap();
+        assert_eq!(plan.total_files, 3);
+        assert_eq!(plan.total_bytes, 200 + 300 + 500);
+
+        let steps = db.get_steps_for_plan(plan_id).unwrap();
+        let file_ids: Vec<i64> = steps.iter().map(|s| s.file_id).collect();
+        assert_eq!(file_ids, vec![2, 3, 5]);
+        assert!(steps.iter().all(|s| s.action == StepAction::Delete));
+
+        let mut planner = Planner::new(&mut db, PlannerOptions::default());
+        assert!(planner.create_grouped_dedup_plan(&[]).is_err());
+    }
+
+    #[test]
+    fn test_dedup_keeps_critical_copies() {
+        let mut db = create_test_db();
+        db.conn().execute(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'drive1', 'source', 1, 'local')",
+            [],
+        ).unwrap();
+
+        let files: Vec<File> = (1..=4)
+            .map(|id| create_test_file(id, 1, &format!("file{}.txt", id), 100))
+            .collect();
+        for file in &files {
+            insert_test_file_to_db(&db, file);
+        }
+
+        // A Critical duplicate is never deleted, even though it is not the original.
+        let mut critical = files[2].clone();
+        critical.priority = Priority::Critical;
+        let mut planner = Planner::new(&mut db, PlannerOptions::default());
+        let plan_id = planner
+            .create_dedup_plan(vec![files[1].clone(), critical.clone(), files[3].clone()], &files[0])
+            .unwrap();
+        let file_ids: Vec<i64> = db.get_steps_for_plan(plan_id).unwrap().iter().map(|s| s.file_id).collect();
+        assert_eq!(file_ids, vec![2, 4]);
+
+        // A Critical original keeps three of the four copies.
+        let mut original = files[0].clone();
+        original.priority = Priority::Critical;
+        let options = PlannerOptions { min_critical_copies: 3, ..Default::default() };
+        let mut planner = Planner::new(&mut db, options);
+        let plan_id = planner
+            .create_dedup_plan(files[1..].to_vec(), &original)
+            .unwrap();
+        let plan = db.get_plan(plan_id).unwrap().unwrap();
+        assert_eq!(plan.total_files, 1);
+
+        let options = PlannerOptions { min_critical_copies: 4, ..Default::default() };
+        let mut planner = Planner::new(&mut db, options);
+        assert!(planner.create_dedup_plan(files[1..].to_vec(), &original).is_err());
+    }
+
+    #[test]
+    fn test_cross_drive_dedup_plans_by_role() {
+        let mut db = create_test_db();
+
+        for (id, label, role) in [(1, "src", "source"), (2, "bak", "backup"), (3, "off", "offload")] {
+            db.conn().execute(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (?1, ?2, ?3, 1, 'local')",
+                rusqlite::params![id, label, role],
+            ).unwrap();
+        }
+
+        let mut on_source = create_test_file(1, 1, "a.txt", 100);
+        on_source.device_num = Some(7);
+        on_source.inode = Some(10);
+        let mut source_copy = create_test_file(2, 1, "copy/a.txt", 100);
+        source_copy.device_num = Some(7);
+        source_copy.inode = Some(11);
+        let on_backup = create_test_file(3, 2, "a.txt", 100);
+        let on_offload = create_test_file(4, 3, "a.txt", 100);
+        // Only exists on offload drives: must never be removed.
+        let orphan_a = create_test_file(5, 3, "b.txt", 50);
+        let orphan_b = create_test_file(6, 3, "b2.txt", 50);
+
+        for file in [&on_source, &source_copy, &on_backup, &on_offload, &orphan_a, &orphan_b] {
+            insert_test_file_to_db(&db, file);
+        }
+
+        let groups = vec![
+            vec![on_source, source_copy, on_backup, on_offload],
+            vec![orphan_a, orphan_b],
+        ];
+
+        let options = CrossDriveDedupOptions {
+            hardlink_within_drive: true,
+            ..Default::default()
+        };
+        let mut planner = Planner::new(&mut db, PlannerOptions::default());
+        let plan_ids = planner.create_cross_drive_dedup_plans(groups, &options).unwrap();
+        assert_eq!(plan_ids.len(), 2);
+
+        let source_plan = db.get_plan(plan_ids[0]).unwrap().unwrap();
+        assert_eq!(source_plan.source_drive_id, Some(1));
+        let steps = db.get_steps_for_plan(plan_ids[0]).unwrap();
+        let actions: Vec<StepAction> = steps.iter().map(|s| s.action).collect();
+        assert_eq!(actions, vec![StepAction::Delete, StepAction::Hardlink]);
+        assert_eq!(steps[1].source_path, "/mnt/drive/a.txt");
+        assert_eq!(steps[1].dest_path.as_deref(), Some("/mnt/drive/copy/a.txt"));
+
+        let offload_plan = db.get_plan(plan_ids[1]).unwrap().unwrap();
+        assert_eq!(offload_plan.source_drive_id, Some(3));
+        let steps = db.get_steps_for_plan(plan_ids[1]).unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].file_id, 4);
+    }
+
+    #[test]
+    fn test_cross_drive_options_reject_overlap() {
+        let keep = vec!["offload".to_string()];
+        let remove = vec!["source".to_string(), "offload".to_string()];
+        assert!(CrossDriveDedupOptions::from_role_names(&keep, &remove, false).is_err());
+
+        let options = CrossDriveDedupOptions::from_role_names(&keep, &["source".to_string()], false).unwrap();
+        assert_eq!(options.keep_roles, vec![DriveRole::Offload]);
+        assert_eq!(options.remove_roles, vec![DriveRole::Source]);
+    }
+
     #[test]
     fn test_approve_plan() {
         let mut db = create_test_db();
@@ -512,4 +1858,279 @@ mod tests {
         let plan = db.get_plan(plan_id).unwrap().unwrap();
         assert_eq!(plan.status, PlanStatus::Approved);
     }
+
+    #[test]
+    fn test_plan_attribution() {
+        let mut db = create_test_db();
+
+        db.conn().execute(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'drive1', 'source', 1, 'local')",
+            [],
+        ).unwrap();
+
+        let files = vec![create_test_file(1, 1, "test.txt", 1000)];
+        for file in &files {
+            insert_test_file_to_db(&db, file);
+        }
+
+        let options = PlannerOptions {
+            attribution: Some(Attribution::agent("desktop-agent/1.0")),
+            ..Default::default()
+        };
+        let plan_id = Planner::new(&mut db, options).create_delete_trash_plan(files).unwrap();
+
+        let plan = db.get_plan(plan_id).unwrap().unwrap();
+        assert_eq!(plan.created_by.as_deref(), Some("desktop-agent/1.0"));
+
+        let entries = db.get_audit_entries_for_plan(plan_id).unwrap();
+        assert_eq!(entries[0].agent_mode.as_deref(), Some("agent"));
+        assert_eq!(entries[0].actor.as_deref(), Some("desktop-agent/1.0"));
+    }
+
+    #[test]
+    fn test_pool_migrate_plan_keeps_categories_together() {
+        let mut db = create_test_db();
+
+        db.conn().execute_batch(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'src', 'source', 1, 'local');
+             INSERT INTO drives (id, label, role, is_online, backend) VALUES (2, 'arch1', 'target', 1, 'local');
+             INSERT INTO drives (id, label, role, is_online, backend) VALUES (3, 'arch2', 'target', 1, 'local');",
+        ).unwrap();
+
+        let mut files = Vec::new();
+        for (id, sub) in [(1, "2019"), (2, "2020"), (3, "2019"), (4, "2020")] {
+            let mut file = create_test_file(id, 1, &format!("photo{}.jpg", id), 40);
+            file.category = Some("photos".to_string());
+            file.subcategory = Some(sub.to_string());
+            file.md5_hash = Some(format!("hash{}", id));
+            insert_test_file_to_db(&db, &file);
+            files.push(file);
+        }
+
+        let targets = vec![
+            PlacementTarget { drive_id: 2, mount_path: "/mnt/arch1".to_string(), capacity_bytes: Some(100) },
+            PlacementTarget { drive_id: 3, mount_path: "/mnt/arch2".to_string(), capacity_bytes: Some(100) },
+        ];
+        let constraints = vec![PlacementConstraint::parse("photos/*").unwrap()];
+
+        let options = PlannerOptions {
+            enforce_space_limits: false,
+            ..Default::default()
+        };
+        let plan_id = Planner::new(&mut db, options)
+            .create_pool_migrate_plan(files, targets, &constraints)
+            .unwrap();
+
+        let steps = db.get_steps_for_plan(plan_id).unwrap();
+        assert_eq!(steps.len(), 4);
+        let dest_of = |file_id: i64| steps.iter().find(|s| s.file_id == file_id).unwrap().dest_drive_id;
+        assert_eq!(dest_of(1), dest_of(3));
+        assert_eq!(dest_of(2), dest_of(4));
+        assert_ne!(dest_of(1), dest_of(2));
+    }
+
+    #[test]
+    fn test_migrate_plan_copies_each_content_once() {
+        let mut db = create_test_db();
+        db.conn().execute_batch(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'src', 'source', 1, 'local');
+             INSERT INTO drives (id, label, role, is_online, backend) VALUES (2, 'arch', 'target', 1, 'local');",
+        ).unwrap();
+
+        let photo = |id: i64, hash: &str, is_original: bool| {
+            let mut file = create_test_file(id, 1, &format!("p{}.jpg", id), 10);
+            file.md5_hash = Some(hash.to_string());
+            file.is_original = is_original;
+            file
+        };
+        let files = vec![photo(1, "a", false), photo(2, "a", true), photo(3, "a", false), photo(4, "b", false)];
+        for file in &files {
+            insert_test_file_to_db(&db, file);
+        }
+
+        let options = PlannerOptions { enforce_space_limits: false, ..Default::default() };
+        let plan_id = Planner::new(&mut db, options)
+            .create_migrate_plan(files, 2, "/mnt/arch")
+            .unwrap();
+
+        let steps = db.get_steps_for_plan(plan_id).unwrap();
+        let copied: Vec<i64> = steps.iter().map(|s| s.file_id).collect();
+        assert_eq!(copied, vec![2, 4]);
+        let plan = db.get_plan(plan_id).unwrap().unwrap();
+        assert_eq!((plan.total_files, plan.total_bytes), (2, 20));
+
+        let satisfied = db.get_satisfied_files(plan_id).unwrap();
+        assert_eq!(satisfied.iter().map(|f| f.file_id).collect::<Vec<_>>(), vec![1, 3]);
+        assert!(satisfied.iter().all(|f| f.step_id == steps[0].id));
+        let summary = db.get_plan_summary(plan_id).unwrap();
+        assert_eq!((summary.satisfied_files, summary.satisfied_bytes), (2, 20));
+    }
+
+    #[test]
+    fn test_offload_plan_frees_space_in_batches() {
+        let mut db = create_test_db();
+
+        db.conn().execute_batch(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'src', 'source', 1, 'local');
+             INSERT INTO drives (id, label, role, is_online, backend) VALUES (2, 'cold', 'offload', 1, 'local');",
+        ).unwrap();
+
+        let mut files = Vec::new();
+        for (id, size) in [(1, 10), (2, 60), (3, 30), (4, 50)] {
+            let file = create_test_file(id, 1, &format!("f{}.bin", id), size);
+            insert_test_file_to_db(&db, &file);
+            files.push(file);
+        }
+
+        let options = PlannerOptions {
+            max_batch_size_bytes: Some(100),
+            enforce_space_limits: false,
+            ..Default::default()
+        };
+        let plan_id = Planner::new(&mut db, options)
+            .create_offload_plan(files, 2, "/mnt/cold")
+            .unwrap();
+
+        let steps = db.get_steps_for_plan(plan_id).unwrap();
+        let order: Vec<(StepAction, i64)> = steps.iter().map(|s| (s.action, s.file_id)).collect();
+        assert_eq!(
+            order,
+            vec![
+                (StepAction::Copy, 2),
+                (StepAction::Delete, 2),
+                (StepAction::Copy, 4),
+                (StepAction::Copy, 3),
+                (StepAction::Copy, 1),
+                (StepAction::Delete, 4),
+                (StepAction::Delete, 3),
+                (StepAction::Delete, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encryption_required_and_flagged() {
+        let mut db = create_test_db();
+        db.conn().execute_batch(
+            "INSERT INTO drives (id, label, role, is_online, backend, is_encrypted) VALUES (1, 'vault', 'source', 1, 'local', 1);
+             INSERT INTO drives (id, label, role, is_online, backend, is_encrypted) VALUES (2, 'usb', 'target', 1, 'local', 0);
+             INSERT INTO drives (id, label, role, is_online, backend, is_encrypted) VALUES (3, 'safe', 'target', 1, 'local', 1);",
+        ).unwrap();
+
+        let mut taxes = create_test_file(1, 1, "taxes.pdf", 10);
+        taxes.category = Some("finance".to_string());
+        taxes.priority = Priority::Critical;
+        insert_test_file_to_db(&db, &taxes);
+
+        let options = PlannerOptions {
+            enforce_space_limits: false,
+            require_encryption: vec!["finance".to_string()],
+            ..Default::default()
+        };
+        let mut planner = Planner::new(&mut db, options.clone());
+        let refused = planner.create_migrate_plan(vec![taxes.clone()], 2, "/mnt/usb");
+        assert!(matches!(refused, Err(OrdneError::SafetyViolation(_))));
+        let plan_id = planner.create_migrate_plan(vec![taxes.clone()], 3, "/mnt/safe").unwrap();
+        assert!(crate::migrate::plan_encryption_warnings(db.conn(), plan_id).unwrap().is_empty());
+
+        // Without the requirement the copy is allowed, but flagged.
+        let options = PlannerOptions { require_encryption: Vec::new(), ..options };
+        let plan_id = Planner::new(&mut db, options)
+            .create_migrate_plan(vec![taxes], 2, "/mnt/usb")
+            .unwrap();
+        let warnings = crate::migrate::plan_encryption_warnings(db.conn(), plan_id).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!((warnings[0].source_drive.as_str(), warnings[0].target_drive.as_str()), ("vault", "usb"));
+        assert_eq!(warnings[0].file_count, 1);
+    }
+
+    #[test]
+    fn test_planners_keep_required_replicas() {
+        let mut db = create_test_db();
+        db.conn().execute_batch(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'src', 'source', 1, 'local');
+             INSERT INTO drives (id, label, role, is_online, backend) VALUES (2, 'cold', 'offload', 1, 'local');",
+        ).unwrap();
+
+        let photo = |id: i64, drive_id: i64, hash: &str| {
+            let mut file = create_test_file(id, drive_id, &format!("p{}.jpg", id), 10);
+            file.md5_hash = Some(hash.to_string());
+            file.category = Some("photos".to_string());
+            file
+        };
+        let a = "a".repeat(32);
+        let (a_src, a_cold, a_src_copy) = (photo(1, 1, &a), photo(2, 2, &a), photo(3, 1, &a));
+        let b_src = photo(4, 1, &"b".repeat(32));
+        for file in [&a_src, &a_cold, &a_src_copy, &b_src] {
+            insert_test_file_to_db(&db, file);
+        }
+
+        let options = || PlannerOptions {
+            enforce_space_limits: false,
+            min_replicas: HashMap::from([("photos".to_string(), 2)]),
+            ..Default::default()
+        };
+
+        // Dedup removes the extra copy on src but not the only one on cold.
+        let plan_id = Planner::new(&mut db, options())
+            .create_dedup_plan(vec![a_cold.clone(), a_src_copy.clone()], &a_src)
+            .unwrap();
+        let file_ids: Vec<i64> = db.get_steps_for_plan(plan_id).unwrap().iter().map(|s| s.file_id).collect();
+        assert_eq!(file_ids, vec![3]);
+
+        // Offloading both copies on src would leave a.jpg on cold alone; b.jpg just moves.
+        let plan_id = Planner::new(&mut db, options())
+            .create_offload_plan(vec![a_src.clone(), a_src_copy.clone(), b_src], 2, "/mnt/cold")
+            .unwrap();
+        let steps = db.get_steps_for_plan(plan_id).unwrap();
+        assert!(steps.iter().all(|s| s.file_id == 4));
+
+        assert!(Planner::new(&mut db, options())
+            .create_offload_plan(vec![a_src, a_src_copy], 2, "/mnt/cold")
+            .is_err());
+    }
+
+    #[test]
+    fn test_cas_offload_copies_each_object_once() {
+        let mut db = create_test_db();
+
+        db.conn().execute_batch(
+            "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'src', 'source', 1, 'local');
+             INSERT INTO drives (id, label, role, is_online, backend) VALUES (2, 'cold', 'offload', 1, 'local');",
+        ).unwrap();
+
+        let mut files = Vec::new();
+        for (id, hash) in [(1, "aa11"), (2, "BB22"), (3, "aa11")] {
+            let mut file = create_test_file(id, 1, &format!("f{}.bin", id), 10);
+            file.blake3_hash = Some(hash.to_string());
+            insert_test_file_to_db(&db, &file);
+            files.push(file);
+        }
+
+        let options = PlannerOptions {
+            enforce_space_limits: false,
+            ..Default::default()
+        };
+        let plan_id = Planner::new(&mut db, options)
+            .create_cas_offload_plan(files, 2, "/mnt/cold")
+            .unwrap();
+
+        let steps = db.get_steps_for_plan(plan_id).unwrap();
+        let copies: Vec<_> = steps.iter().filter(|s| s.action == StepAction::Copy).collect();
+        let mut dests: Vec<_> = copies.iter().filter_map(|s| s.dest_path.as_deref()).collect();
+        dests.sort();
+        assert_eq!(
+            dests,
+            vec!["/mnt/cold/objects/aa/aa11", "/mnt/cold/objects/bb/bb22"]
+        );
+
+        let shared = copies.iter().find(|s| s.file_id == 1).unwrap().id;
+        for step in steps.iter().filter(|s| s.action == StepAction::Delete) {
+            let deps = db.get_step_dependencies(step.id).unwrap();
+            if step.file_id != 2 {
+                assert_eq!(deps, vec![shared]);
+            }
+        }
+        assert_eq!(steps.iter().filter(|s| s.action == StepAction::Delete).count(), 3);
+    }
 }
@@ -9,6 +9,8 @@ pub struct RcloneOptions {
     pub progress: bool,
     pub transfers: Option<u32>,
     pub checkers: Option<u32>,
+    /// Transfer rate cap in KiB/s.
+    pub bwlimit_kbps: Option<u64>,
 }
 
 impl Default for RcloneOptions {
@@ -19,6 +21,7 @@ impl Default for RcloneOptions {
             progress: true,
             transfers: Some(4),
             checkers: Some(8),
+            bwlimit_kbps: None,
         }
     }
 }
@@ -59,6 +62,11 @@ pub fn execute_rclone_copy(
         cmd.arg(checkers.to_string());
     }
 
+    if let Some(kbps) = options.bwlimit_kbps {
+        cmd.arg("--bwlimit");
+        cmd.arg(format!("{}K", kbps));
+    }
+
     cmd.arg(source);
     cmd.arg(dest);
 
@@ -120,6 +128,11 @@ pub fn execute_rclone_move(
         cmd.arg(checkers.to_string());
     }
 
+    if let Some(kbps) = options.bwlimit_kbps {
+        cmd.arg("--bwlimit");
+        cmd.arg(format!("{}K", kbps));
+    }
+
     cmd.arg(source);
     cmd.arg(dest);
 
@@ -152,13 +165,21 @@ pub fn execute_rclone_move(
 }
 
 pub fn copy_to_remote(local_path: &Path, remote: &str, remote_path: &str) -> Result<()> {
+    copy_to_remote_with_options(local_path, remote, remote_path, &RcloneOptions::default())
+}
+
+pub fn copy_to_remote_with_options(
+    local_path: &Path,
+    remote: &str,
+    remote_path: &str,
+    options: &RcloneOptions,
+) -> Result<()> {
     let source = local_path.to_str().ok_or_else(|| {
         OrdneError::Migration("Invalid local path encoding".to_string())
     })?;
     let dest = format!("{}:{}", remote, remote_path);
 
-    let options = RcloneOptions::default();
-    let result = execute_rclone_copy(source, &dest, &options)?;
+    let result = execute_rclone_copy(source, &dest, options)?;
 
     if !result.success {
         return Err(OrdneError::ExternalTool {
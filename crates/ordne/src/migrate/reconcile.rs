@@ -0,0 +1,169 @@
+//! Checking the planner's space estimates against the filesystem.
+//!
+//! The engine records each local drive's used bytes (statvfs) before a plan
+//! runs and after it completes. Here the change is compared with what the
+//! plan's completed steps should have done: copies add their file sizes to
+//! the destination, deletes free them on the source. Hard links, sparse
+//! files, reflinks, compression and snapshots all make the two differ, as
+//! does other activity on the drive while the plan ran.
+
+use crate::db::{Database, PlansDatabase, StepAction, StepStatus};
+use crate::error::Result;
+use crate::util::format::format_bytes;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Differences below this many bytes are never reported.
+const MIN_DISCREPANCY_BYTES: i64 = 1024 * 1024;
+/// Differences below this share of the predicted change are never reported.
+const MIN_DISCREPANCY_RATIO: f64 = 0.05;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DriveReconciliation {
+    pub drive_id: i64,
+    pub label: String,
+    /// Change in used bytes the completed steps account for; negative frees space.
+    pub predicted_bytes: i64,
+    /// Change statvfs measured, if both readings exist.
+    pub actual_bytes: Option<i64>,
+    /// Bytes of deleted files that had other hard links and so stay allocated.
+    pub hardlinked_bytes: i64,
+    /// Likely causes, when actual and predicted differ noticeably.
+    pub notes: Vec<String>,
+}
+
+impl DriveReconciliation {
+    /// Measured minus predicted change, when larger than the tolerance.
+    pub fn discrepancy(&self) -> Option<i64> {
+        let actual = self.actual_bytes?;
+        let diff = actual - self.predicted_bytes;
+        let tolerance = MIN_DISCREPANCY_BYTES
+            .max((self.predicted_bytes.abs() as f64 * MIN_DISCREPANCY_RATIO) as i64);
+        (diff.abs() > tolerance).then_some(diff)
+    }
+}
+
+/// Predicted against measured space per drive for `plan_id`. Empty when the
+/// plan has not been executed since space tracking was added.
+pub fn reconcile_plan<D: Database + PlansDatabase>(db: &D, plan_id: i64) -> Result<Vec<DriveReconciliation>> {
+    let usage = db.get_space_usage(plan_id)?;
+    if usage.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut predicted: BTreeMap<i64, i64> = BTreeMap::new();
+    let mut hardlinked: BTreeMap<i64, i64> = BTreeMap::new();
+    let mut copied: BTreeMap<i64, bool> = BTreeMap::new();
+    for step in db.get_steps_for_plan(plan_id)? {
+        if step.status != StepStatus::Completed {
+            continue;
+        }
+        let Some(file) = db.get_file(step.file_id)? else {
+            continue;
+        };
+        let size = file.size_bytes;
+        match step.action {
+            StepAction::Copy | StepAction::Move => {
+                if let Some(dest) = step.dest_drive_id {
+                    *predicted.entry(dest).or_default() += size;
+                    copied.insert(dest, true);
+                }
+                if step.action == StepAction::Move {
+                    *predicted.entry(step.source_drive_id).or_default() -= size;
+                }
+            }
+            StepAction::Delete => {
+                *predicted.entry(step.source_drive_id).or_default() -= size;
+                if file.nlinks.is_some_and(|n| n > 1) {
+                    *hardlinked.entry(step.source_drive_id).or_default() += size;
+                }
+            }
+            StepAction::Hardlink | StepAction::Symlink => {}
+        }
+    }
+
+    let mut report = Vec::with_capacity(usage.len());
+    for row in usage {
+        let label = db
+            .get_drive_by_id(row.drive_id)?
+            .map_or_else(|| format!("#{}", row.drive_id), |d| d.label);
+        let mut drive = DriveReconciliation {
+            drive_id: row.drive_id,
+            label,
+            predicted_bytes: predicted.get(&row.drive_id).copied().unwrap_or(0),
+            actual_bytes: row.used_before.zip(row.used_after).map(|(before, after)| after - before),
+            hardlinked_bytes: hardlinked.get(&row.drive_id).copied().unwrap_or(0),
+            notes: Vec::new(),
+        };
+        drive.notes = explain(&drive, copied.contains_key(&row.drive_id));
+        report.push(drive);
+    }
+    Ok(report)
+}
+
+fn explain(drive: &DriveReconciliation, received_copies: bool) -> Vec<String> {
+    let Some(diff) = drive.discrepancy() else {
+        return Vec::new();
+    };
+
+    let mut notes = Vec::new();
+    if drive.hardlinked_bytes > 0 && diff > 0 {
+        notes.push(format!(
+            "{} of deleted files had other hard links and is still allocated",
+            format_bytes(drive.hardlinked_bytes)
+        ));
+    }
+    if diff < 0 && received_copies {
+        notes.push("copies took less space than their size: sparse files, compression or reflinks".to_string());
+    } else if diff > 0 && drive.predicted_bytes < 0 {
+        notes.push("less space was freed than deleted: snapshots or open files may still hold it".to_string());
+    } else if diff > 0 {
+        notes.push("more space was used than copied: block overhead or other writes during the run".to_string());
+    } else {
+        notes.push("more space was freed than planned: other deletions during the run".to_string());
+    }
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{SpacePhase, SqliteDatabase};
+
+    const MB: i64 = 1024 * 1024;
+
+    #[test]
+    fn test_reconcile_flags_hardlinked_deletes() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'src', 'source', 1, 'local');
+                 INSERT INTO drives (id, label, role, is_online, backend) VALUES (2, 'dst', 'target', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes, nlinks)
+                 VALUES (1, 1, 'a', 'a', 104857600, 1), (2, 1, 'b', 'b', 104857600, 2);
+                 INSERT INTO migration_plans (id, status) VALUES (1, 'completed');
+                 INSERT INTO migration_steps (plan_id, step_order, file_id, action, source_path, source_drive_id, dest_drive_id, status)
+                 VALUES (1, 1, 1, 'copy', 'a', 1, 2, 'completed'), (1, 2, 2, 'delete', 'b', 1, NULL, 'completed');",
+            )
+            .unwrap();
+
+        // The copy landed as predicted; the delete freed nothing.
+        db.record_space_usage(1, 1, SpacePhase::Before, 500 * MB).unwrap();
+        db.record_space_usage(1, 1, SpacePhase::After, 500 * MB).unwrap();
+        db.record_space_usage(1, 2, SpacePhase::Before, 0).unwrap();
+        db.record_space_usage(1, 2, SpacePhase::After, 100 * MB).unwrap();
+        // A resumed plan keeps its first reading.
+        db.record_space_usage(1, 2, SpacePhase::Before, 50 * MB).unwrap();
+
+        let report = reconcile_plan(&db, 1).unwrap();
+        let (src, dst) = (&report[0], &report[1]);
+        assert_eq!((src.predicted_bytes, src.actual_bytes), (-100 * MB, Some(0)));
+        assert_eq!(src.discrepancy(), Some(100 * MB));
+        assert_eq!(src.hardlinked_bytes, 100 * MB);
+        assert!(src.notes[0].contains("hard links"));
+        assert_eq!((dst.predicted_bytes, dst.actual_bytes), (100 * MB, Some(100 * MB)));
+        assert_eq!(dst.discrepancy(), None);
+        assert!(dst.notes.is_empty());
+    }
+}
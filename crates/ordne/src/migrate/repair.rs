@@ -0,0 +1,236 @@
+//! Corrective copies for verification failures.
+//!
+//! A failed destination is repaired from the copy's original source if it is
+//! still intact, otherwise from any other replica with the expected hash. Every
+//! candidate is re-hashed before it is trusted; a replica that merely shares a
+//! hash in the catalog may have rotted as well.
+
+use crate::db::files::list_files_by_hash;
+use crate::db::{Database, HashAlgorithm, PlansDatabase, SqliteDatabase, VerificationFailure};
+use crate::error::Result;
+use crate::migrate::hash;
+use std::path::Path;
+
+/// A verified source for rewriting one failed destination.
+#[derive(Debug, Clone)]
+pub struct Repair {
+    pub failure_id: i64,
+    pub file_id: i64,
+    pub source_path: String,
+    pub source_drive_id: i64,
+    pub dest_path: String,
+    pub dest_drive_id: Option<i64>,
+    pub expected_hash: String,
+    pub hash_algorithm: HashAlgorithm,
+    pub size_bytes: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RepairSet {
+    pub repairs: Vec<Repair>,
+    /// Failures no intact copy was found for, with the reason.
+    pub unresolved: Vec<(VerificationFailure, String)>,
+}
+
+/// Find an intact source for each failure.
+pub fn find_repairs(db: &SqliteDatabase, failures: &[VerificationFailure]) -> Result<RepairSet> {
+    let mut set = RepairSet::default();
+
+    for failure in failures {
+        match find_repair(db, failure)? {
+            Ok(repair) => set.repairs.push(repair),
+            Err(reason) => set.unresolved.push((failure.clone(), reason)),
+        }
+    }
+
+    Ok(set)
+}
+
+fn find_repair(
+    db: &SqliteDatabase,
+    failure: &VerificationFailure,
+) -> Result<std::result::Result<Repair, String>> {
+    let Some(expected) = failure.expected_hash.clone() else {
+        return Ok(Err("no recorded hash to repair against".to_string()));
+    };
+
+    // (file id, path, drive id, size), the copy's own source first.
+    let mut candidates: Vec<(i64, String, i64, i64)> = Vec::new();
+    let mut recorded_algorithm = None;
+
+    if let Some(step) = failure.step_id.map(|id| db.get_step(id)).transpose()?.flatten() {
+        let size = db.get_file(step.file_id)?.map_or(0, |f| f.size_bytes);
+        recorded_algorithm = step.hash_algorithm;
+        candidates.push((step.file_id, step.source_path, step.source_drive_id, size));
+    }
+
+    let Ok(algorithm) = HashAlgorithm::resolve(recorded_algorithm, &expected) else {
+        return Ok(Err("recorded hash is of an unknown algorithm".to_string()));
+    };
+
+    for replica in list_files_by_hash(db.conn(), &expected)? {
+        candidates.push((replica.id, replica.abs_path, replica.drive_id, replica.size_bytes));
+    }
+
+    for (file_id, path, drive_id, size_bytes) in candidates {
+        if path == failure.path || !drive_online(db, drive_id)? {
+            continue;
+        }
+        if !hash::verify_hash(Path::new(&path), &expected, algorithm).unwrap_or(false) {
+            continue;
+        }
+
+        return Ok(Ok(Repair {
+            failure_id: failure.id,
+            file_id: failure.file_id.unwrap_or(file_id),
+            source_path: path,
+            source_drive_id: drive_id,
+            dest_path: failure.path.clone(),
+            dest_drive_id: failure.drive_id,
+            expected_hash: expected,
+            hash_algorithm: algorithm,
+            size_bytes,
+        }));
+    }
+
+    Ok(Err("no intact source or replica found".to_string()))
+}
+
+fn drive_online(db: &SqliteDatabase, drive_id: i64) -> Result<bool> {
+    Ok(db.get_drive_by_id(drive_id)?.is_some_and(|d| d.is_online))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Backend, Drive, DriveRole, MigrationStep, PlansDatabase, StepAction, StepStatus};
+    use crate::migrate::{Planner, PlannerOptions};
+    use chrono::Utc;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn add_drive(db: &mut SqliteDatabase, label: &str, mount: &Path) -> i64 {
+        db.add_drive(&Drive {
+            id: 0,
+            label: label.to_string(),
+            device_id: None,
+            device_path: None,
+            uuid: None,
+            mount_path: Some(mount.to_str().unwrap().to_string()),
+            fs_type: None,
+            total_bytes: None,
+            role: DriveRole::Backup,
+            is_online: true,
+            is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
+            backend: Backend::Local,
+            rclone_remote: None,
+            scanned_at: None,
+            added_at: Utc::now(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_repair_from_replica_when_source_is_gone() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        let (src, dst, backup) = (
+            temp_dir.path().join("src"),
+            temp_dir.path().join("dst"),
+            temp_dir.path().join("backup"),
+        );
+        for dir in [&src, &dst, &backup] {
+            fs::create_dir_all(dir).unwrap();
+        }
+
+        fs::write(backup.join("a.txt"), b"test content").unwrap();
+        fs::write(dst.join("a.txt"), b"bit rot").unwrap();
+        let expected = hash::compute_blake3_hash(backup.join("a.txt")).unwrap();
+
+        let src_id = add_drive(&mut db, "src", &src);
+        let dst_id = add_drive(&mut db, "dst", &dst);
+        let backup_id = add_drive(&mut db, "backup", &backup);
+        db.conn()
+            .execute(
+                "INSERT INTO files (id, drive_id, path, filename, size_bytes, blake3_hash, status)
+                 VALUES (1, ?1, 'a.txt', 'a.txt', 12, ?3, 'indexed'),
+                        (2, ?2, 'a.txt', 'a.txt', 12, ?3, 'indexed')",
+                rusqlite::params![src_id, backup_id, &expected],
+            )
+            .unwrap();
+
+        let plan_id = db
+            .create_plan(&crate::db::MigrationPlan {
+                id: 0,
+                created_at: Utc::now(),
+                description: None,
+                source_drive_id: Some(src_id),
+                target_drive_id: Some(dst_id),
+                status: crate::db::PlanStatus::Completed,
+                total_files: 1,
+                total_bytes: 12,
+                completed_files: 1,
+                completed_bytes: 12,
+                created_by: None,
+                step_ordering: Default::default(),
+            })
+            .unwrap();
+        let step_id = db
+            .add_step(&MigrationStep {
+                id: 0,
+                plan_id,
+                file_id: 1,
+                action: StepAction::Move,
+                source_path: src.join("a.txt").to_str().unwrap().to_string(),
+                source_drive_id: src_id,
+                dest_path: Some(dst.join("a.txt").to_str().unwrap().to_string()),
+                dest_drive_id: Some(dst_id),
+                status: StepStatus::Completed,
+                pre_hash: Some(expected.clone()),
+                post_hash: None,
+                hash_algorithm: None,
+                executed_at: None,
+                error: None,
+                step_order: 0,
+            })
+            .unwrap();
+
+        let failure = VerificationFailure {
+            id: 7,
+            file_id: Some(1),
+            plan_id: Some(plan_id),
+            step_id: Some(step_id),
+            drive_id: Some(dst_id),
+            path: dst.join("a.txt").to_str().unwrap().to_string(),
+            expected_hash: Some(expected.clone()),
+            actual_hash: None,
+            reason: "mismatch".to_string(),
+            detected_at: Utc::now(),
+            acknowledged_at: None,
+        };
+        let no_hash = VerificationFailure {
+            id: 8,
+            expected_hash: None,
+            ..failure.clone()
+        };
+
+        let set = find_repairs(&db, &[failure, no_hash]).unwrap();
+        assert_eq!(set.unresolved.len(), 1);
+        assert_eq!(set.unresolved[0].0.id, 8);
+        assert_eq!(set.repairs.len(), 1);
+        let repair = &set.repairs[0];
+        assert_eq!(repair.source_drive_id, backup_id);
+        assert_eq!(repair.dest_drive_id, Some(dst_id));
+
+        let plan_id = Planner::new(&mut db, PlannerOptions::default())
+            .create_recopy_plan(&set.repairs)
+            .unwrap();
+        let steps = db.get_steps_for_plan(plan_id).unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].action, StepAction::Copy);
+        assert_eq!(steps[0].pre_hash.as_deref(), Some(expected.as_str()));
+    }
+}
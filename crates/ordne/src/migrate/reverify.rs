@@ -0,0 +1,235 @@
+//! Delayed re-verification of completed plans.
+//!
+//! A copy that hashes correctly straight after writing can still go bad
+//! before the source is removed: a failing disk, a flaky cable, a cache that
+//! was never flushed. A verification job re-reads every destination of a
+//! plan some time after it finished. Failures are recorded for the inbox and
+//! reset the copy step's post-copy hash, so any delete that depends on the
+//! copy is blocked until the copy is redone.
+
+use crate::db::{
+    AuditDatabase, AuditLogEntry, Backend, Database, HashAlgorithm, MigrationStep, PlansDatabase,
+    StepAction, StepStatus, VerificationDatabase, VerificationFailure, VerificationJob,
+};
+use crate::error::Result;
+use crate::migrate::hash;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationJobResult {
+    pub job_id: i64,
+    pub plan_id: i64,
+    pub files_checked: i64,
+    pub files_failed: i64,
+}
+
+/// Run every pending job due by `now`.
+pub fn run_due_verification_jobs<D>(db: &mut D, now: DateTime<Utc>) -> Result<Vec<VerificationJobResult>>
+where
+    D: Database + PlansDatabase + AuditDatabase + VerificationDatabase,
+{
+    let jobs = db.list_pending_verification_jobs(Some(now))?;
+    jobs.iter().map(|job| run_verification_job(db, job)).collect()
+}
+
+/// Re-read the destinations of a job's plan and record the outcome.
+pub fn run_verification_job<D>(db: &mut D, job: &VerificationJob) -> Result<VerificationJobResult>
+where
+    D: Database + PlansDatabase + AuditDatabase + VerificationDatabase,
+{
+    let mut checked = 0;
+    let mut failed = 0;
+
+    for step in db.get_steps_for_plan(job.plan_id)? {
+        let Some((dest_path, expected)) = verifiable_copy(db, &step)? else {
+            continue;
+        };
+
+        checked += 1;
+        // An unrecognizable hash fails verification below as unreadable.
+        let algorithm = HashAlgorithm::resolve(step.hash_algorithm, &expected)
+            .unwrap_or(HashAlgorithm::Blake3);
+        let Some((reason, actual)) = recheck(Path::new(&dest_path), &expected, algorithm) else {
+            continue;
+        };
+
+        failed += 1;
+        db.record_verification_failure(&VerificationFailure {
+            id: 0,
+            file_id: Some(step.file_id),
+            plan_id: Some(step.plan_id),
+            step_id: Some(step.id),
+            drive_id: step.dest_drive_id,
+            path: dest_path,
+            expected_hash: Some(expected.clone()),
+            actual_hash: actual.clone(),
+            reason: reason.to_string(),
+            detected_at: Utc::now(),
+            acknowledged_at: None,
+        })?;
+        let pre_hash = step.pre_hash.clone().unwrap_or(expected);
+        db.update_step_hashes(step.id, pre_hash, actual, algorithm)?;
+    }
+
+    db.complete_verification_job(job.id, checked, failed)?;
+
+    db.log_audit(&AuditLogEntry {
+        id: 0,
+        timestamp: Utc::now(),
+        action: "verification_job_completed".to_string(),
+        file_id: None,
+        plan_id: Some(job.plan_id),
+        drive_id: None,
+        details: Some(format!(
+            "Job {}: {} destinations checked, {} failed",
+            job.id, checked, failed
+        )),
+        agent_mode: Some("automated".to_string()),
+        before_state: None,
+        after_state: None,
+        actor: None,
+    })?;
+
+    Ok(VerificationJobResult {
+        job_id: job.id,
+        plan_id: job.plan_id,
+        files_checked: checked,
+        files_failed: failed,
+    })
+}
+
+/// Destination path and expected hash of a completed, locally written copy.
+fn verifiable_copy<D: Database>(db: &D, step: &MigrationStep) -> Result<Option<(String, String)>> {
+    if !matches!(step.action, StepAction::Copy | StepAction::Move)
+        || step.status != StepStatus::Completed
+    {
+        return Ok(None);
+    }
+    let (Some(dest_path), Some(expected)) = (&step.dest_path, &step.post_hash) else {
+        return Ok(None);
+    };
+
+    if let Some(drive_id) = step.dest_drive_id {
+        let local = db
+            .get_drive_by_id(drive_id)?
+            .is_none_or(|drive| drive.backend == Backend::Local);
+        if !local {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some((dest_path.clone(), expected.clone())))
+}
+
+/// Failure reason and the hash actually found, or `None` if the copy is intact.
+fn recheck(
+    path: &Path,
+    expected: &str,
+    algorithm: HashAlgorithm,
+) -> Option<(&'static str, Option<String>)> {
+    if !path.exists() {
+        return Some(("missing", None));
+    }
+
+    match hash::verify_hash(path, expected, algorithm) {
+        Ok(true) => None,
+        Ok(false) => Some(("mismatch", hash::compute_hash(path, algorithm).ok())),
+        Err(_) => Some(("unreadable", None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{MigrationPlan, PlanStatus, SqliteDatabase};
+    use crate::migrate::graph;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reverify_blocks_delete_of_corrupted_copy() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+
+        let dest = temp_dir.path().join("copy.txt");
+        fs::write(&dest, b"test content").unwrap();
+        let hash = hash::compute_blake3_hash(&dest).unwrap();
+
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'src', 'source', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes, status)
+                 VALUES (1, 1, 'copy.txt', 'copy.txt', 12, 'indexed');",
+            )
+            .unwrap();
+
+        let plan_id = db
+            .create_plan(&MigrationPlan {
+                id: 0,
+                created_at: Utc::now(),
+                description: None,
+                source_drive_id: Some(1),
+                target_drive_id: None,
+                status: PlanStatus::Completed,
+                total_files: 1,
+                total_bytes: 12,
+                completed_files: 1,
+                completed_bytes: 12,
+                created_by: None,
+                step_ordering: Default::default(),
+            })
+            .unwrap();
+
+        let mut copy = MigrationStep {
+            id: 0,
+            plan_id,
+            file_id: 1,
+            action: StepAction::Copy,
+            source_path: "/src/copy.txt".to_string(),
+            source_drive_id: 1,
+            dest_path: Some(dest.to_str().unwrap().to_string()),
+            dest_drive_id: None,
+            status: StepStatus::Completed,
+            pre_hash: Some(hash.clone()),
+            post_hash: None,
+            hash_algorithm: None,
+            executed_at: None,
+            error: None,
+            step_order: 0,
+        };
+        copy.id = db.add_step(&copy).unwrap();
+        db.update_step_hashes(copy.id, hash.clone(), Some(hash.clone()), HashAlgorithm::Blake3)
+            .unwrap();
+        db.update_step_status(copy.id, StepStatus::Completed, None)
+            .unwrap();
+
+        let job_id = db.schedule_verification_job(plan_id, Utc::now()).unwrap();
+        let results = run_due_verification_jobs(&mut db, Utc::now()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!((results[0].files_checked, results[0].files_failed), (1, 0));
+
+        fs::write(&dest, b"bit rot").unwrap();
+        db.schedule_verification_job(plan_id, Utc::now()).unwrap();
+        let results = run_due_verification_jobs(&mut db, Utc::now()).unwrap();
+        assert_ne!(results[0].job_id, job_id);
+        assert_eq!(results[0].files_failed, 1);
+
+        let failures = db.list_unacknowledged_failures().unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].step_id, Some(copy.id));
+        assert_eq!(failures[0].reason, "mismatch");
+
+        let delete = MigrationStep {
+            id: 99,
+            action: StepAction::Delete,
+            dest_path: None,
+            status: StepStatus::Pending,
+            ..copy.clone()
+        };
+        let rechecked = db.get_step(copy.id).unwrap().unwrap();
+        assert!(graph::check_dependency(&delete, &rechecked).is_err());
+    }
+}
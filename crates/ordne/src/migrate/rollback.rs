@@ -1,26 +1,70 @@
+use crate::config::Settings;
 use crate::db::{
-    AuditDatabase, AuditLogEntry, Backend, Database, MigrationStep, PlansDatabase, StepAction,
-    StepStatus,
+    Attribution, AuditDatabase, AuditLogEntry, Backend, Database, HashAlgorithm, MigrationStep,
+    PlansDatabase, StepAction, StepStatus,
 };
 use crate::error::{OrdneError, Result};
+use crate::migrate::engine::step_snapshots;
 use crate::migrate::{hash, rclone, rsync};
-use chrono::Utc;
+use crate::util::clock::Clock;
 use std::fs;
 use std::path::Path;
 
 pub struct RollbackEngine<'a, D: Database + PlansDatabase + AuditDatabase> {
     db: &'a mut D,
     verify_hashes: bool,
+    attribution: Option<Attribution>,
+    clock: Clock,
+    safe_mode: bool,
 }
 
 impl<'a, D: Database + PlansDatabase + AuditDatabase> RollbackEngine<'a, D> {
     pub fn new(db: &'a mut D, verify_hashes: bool) -> Self {
-        Self { db, verify_hashes }
+        Self {
+            db,
+            verify_hashes,
+            attribution: None,
+            clock: Clock::default(),
+            safe_mode: Settings::default().safe_mode(),
+        }
+    }
+
+    /// Record who requested the rollback on its audit entries.
+    pub fn with_attribution(mut self, attribution: Attribution) -> Self {
+        self.attribution = Some(attribution);
+        self
+    }
+
+    /// Stamp audit entries from `clock` rather than `ORDNE_NOW` or the system time.
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Refuse every rollback while `safe_mode` is on, normally
+    /// [`Settings::safe_mode`]. By default only `ORDNE_SAFE_MODE` turns it on.
+    pub fn with_safe_mode(mut self, safe_mode: bool) -> Self {
+        self.safe_mode = safe_mode;
+        self
+    }
+
+    fn agent_mode(&self, default: &str) -> Option<String> {
+        let attribution = self.attribution.as_ref();
+        Some(attribution.map_or(default, |a| a.agent_mode.as_str()).to_string())
+    }
+
+    fn actor(&self) -> Option<String> {
+        self.attribution.as_ref().map(|a| a.actor.clone())
     }
 
     pub fn rollback_plan(&mut self, plan_id: i64) -> Result<()> {
         log::info!("Starting rollback for plan {}", plan_id);
 
+        // Rollback has no dry run, so safe mode refuses it outright.
+        if self.safe_mode {
+            return Err(OrdneError::SafeMode(format!("rollback of plan {}", plan_id)));
+        }
+
         let _plan = self
             .db
             .get_plan(plan_id)?
@@ -28,13 +72,16 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> RollbackEngine<'a, D> {
 
         self.db.log_audit(&AuditLogEntry {
             id: 0,
-            timestamp: Utc::now(),
+            timestamp: self.clock.now(),
             action: "rollback_started".to_string(),
             file_id: None,
             plan_id: Some(plan_id),
             drive_id: None,
             details: Some("Starting plan rollback".to_string()),
-            agent_mode: Some("manual".to_string()),
+            agent_mode: self.agent_mode("manual"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
         })?;
 
         let steps = self.db.get_steps_for_plan(plan_id)?;
@@ -54,15 +101,23 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> RollbackEngine<'a, D> {
                     self.db
                         .update_step_status(step.id, StepStatus::RolledBack, None)?;
 
+                    // Rolling back turns the step's "after" state back into its "before".
+                    let file = self.db.get_file(step.file_id)?;
+                    let size = file.as_ref().map(|f| f.size_bytes);
+                    let (original, executed) = step_snapshots(step, file.as_ref(), size);
+
                     self.db.log_audit(&AuditLogEntry {
                         id: 0,
-                        timestamp: Utc::now(),
+                        timestamp: self.clock.now(),
                         action: "step_rolled_back".to_string(),
                         file_id: Some(step.file_id),
                         plan_id: Some(plan_id),
                         drive_id: Some(step.source_drive_id),
                         details: Some(format!("Step {} rolled back successfully", step.id)),
-                        agent_mode: Some("manual".to_string()),
+                        agent_mode: self.agent_mode("manual"),
+                        before_state: executed.to_json(),
+                        after_state: original.to_json(),
+                        actor: self.actor(),
                     })?;
                 }
                 Err(e) => {
@@ -70,13 +125,16 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> RollbackEngine<'a, D> {
 
                     self.db.log_audit(&AuditLogEntry {
                         id: 0,
-                        timestamp: Utc::now(),
+                        timestamp: self.clock.now(),
                         action: "step_rollback_failed".to_string(),
                         file_id: Some(step.file_id),
                         plan_id: Some(plan_id),
                         drive_id: Some(step.source_drive_id),
                         details: Some(format!("Step rollback failed: {}", e)),
-                        agent_mode: Some("manual".to_string()),
+                        agent_mode: self.agent_mode("manual"),
+                        before_state: None,
+                        after_state: None,
+                        actor: self.actor(),
                     })?;
 
                     return Err(e);
@@ -86,7 +144,7 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> RollbackEngine<'a, D> {
 
         self.db.log_audit(&AuditLogEntry {
             id: 0,
-            timestamp: Utc::now(),
+            timestamp: self.clock.now(),
             action: "rollback_completed".to_string(),
             file_id: None,
             plan_id: Some(plan_id),
@@ -95,7 +153,10 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> RollbackEngine<'a, D> {
                 "Rollback completed for {} steps",
                 completed_steps.len()
             )),
-            agent_mode: Some("manual".to_string()),
+            agent_mode: self.agent_mode("manual"),
+            before_state: None,
+            after_state: None,
+            actor: self.actor(),
         })?;
 
         Ok(())
@@ -128,7 +189,8 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> RollbackEngine<'a, D> {
         if dest_path.exists() {
             if self.verify_hashes {
                 if let Some(post_hash) = &step.post_hash {
-                    hash::verify_destination(dest_path, post_hash)?;
+                    let algorithm = HashAlgorithm::resolve(step.hash_algorithm, post_hash)?;
+                    hash::verify_destination(dest_path, post_hash, algorithm)?;
                 }
             }
 
@@ -158,7 +220,8 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> RollbackEngine<'a, D> {
 
         if self.verify_hashes {
             if let Some(pre_hash) = &step.pre_hash {
-                hash::verify_destination(dest_path, pre_hash)?;
+                let algorithm = HashAlgorithm::resolve(step.hash_algorithm, pre_hash)?;
+                hash::verify_destination(dest_path, pre_hash, algorithm)?;
             }
         }
 
@@ -193,7 +256,8 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> RollbackEngine<'a, D> {
 
         if self.verify_hashes && dest_drive.backend == Backend::Local {
             if let Some(pre_hash) = &step.pre_hash {
-                hash::verify_destination(source_path, pre_hash)?;
+                let algorithm = HashAlgorithm::resolve(step.hash_algorithm, pre_hash)?;
+                hash::verify_destination(source_path, pre_hash, algorithm)?;
             }
         }
 
@@ -254,14 +318,12 @@ impl<'a, D: Database + PlansDatabase + AuditDatabase> RollbackEngine<'a, D> {
         let steps = self.db.get_steps_for_plan(plan_id)?;
 
         for step in steps {
-            if step.status == StepStatus::Completed {
-                if step.action == StepAction::Delete {
-                    log::warn!(
-                        "Cannot rollback plan {}: contains completed delete operations",
-                        plan_id
-                    );
-                    return Ok(false);
-                }
+            if step.status == StepStatus::Completed && step.action == StepAction::Delete {
+                log::warn!(
+                    "Cannot rollback plan {}: contains completed delete operations",
+                    plan_id
+                );
+                return Ok(false);
             }
         }
 
@@ -274,8 +336,17 @@ mod tests {
     use super::*;
     use crate::db::{Backend, Drive, DriveRole, PlanStatus, SqliteDatabase};
     use std::fs;
+    use chrono::{DateTime, Utc};
     use tempfile::TempDir;
 
+    fn test_clock() -> Clock {
+        Clock::fixed(
+            DateTime::parse_from_rfc3339("2025-03-01T12:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+        )
+    }
+
     fn create_test_db() -> SqliteDatabase {
         let mut db = SqliteDatabase::open_in_memory().unwrap();
         db.initialize().unwrap();
@@ -295,10 +366,12 @@ mod tests {
             role: DriveRole::Source,
             is_online: true,
             is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
             backend: Backend::Local,
             rclone_remote: None,
             scanned_at: None,
-            added_at: Utc::now(),
+            added_at: test_clock().now(),
         };
 
         db.add_drive(&drive).unwrap()
@@ -329,7 +402,8 @@ mod tests {
             status: StepStatus::Completed,
             pre_hash: None,
             post_hash: None,
-            executed_at: Some(Utc::now()),
+            hash_algorithm: None,
+            executed_at: Some(test_clock().now()),
             error: None,
             step_order: 0,
         };
@@ -357,19 +431,19 @@ mod tests {
 
         // Create required files
         db.conn().execute(
-            "INSERT INTO files (id, drive_id, path, abs_path, filename, size_bytes, status, indexed_at)
-             VALUES (1, 1, '/source/file.txt', '/mnt/drive1/file.txt', 'file.txt', 1000, 'indexed', datetime('now'))",
+            "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+             VALUES (1, 1, 'file.txt', 'file.txt', 1000, 'indexed', datetime('now'))",
             [],
         ).unwrap();
         db.conn().execute(
-            "INSERT INTO files (id, drive_id, path, abs_path, filename, size_bytes, status, indexed_at)
-             VALUES (2, 1, '/source/file2.txt', '/mnt/drive1/file2.txt', 'file2.txt', 1000, 'indexed', datetime('now'))",
+            "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+             VALUES (2, 1, 'file2.txt', 'file2.txt', 1000, 'indexed', datetime('now'))",
             [],
         ).unwrap();
 
         let plan = crate::db::MigrationPlan {
             id: 0,
-            created_at: Utc::now(),
+            created_at: test_clock().now(),
             description: Some("Test plan".to_string()),
             source_drive_id: Some(1),
             target_drive_id: Some(2),
@@ -378,6 +452,8 @@ mod tests {
             total_bytes: 1000,
             completed_files: 1,
             completed_bytes: 1000,
+            created_by: None,
+            step_ordering: Default::default(),
         };
 
         let plan_id = db.create_plan(&plan).unwrap();
@@ -394,7 +470,8 @@ mod tests {
             status: StepStatus::Completed,
             pre_hash: None,
             post_hash: None,
-            executed_at: Some(Utc::now()),
+            hash_algorithm: None,
+            executed_at: Some(test_clock().now()),
             error: None,
             step_order: 0,
         };
@@ -419,7 +496,8 @@ mod tests {
             status: StepStatus::Completed,
             pre_hash: None,
             post_hash: None,
-            executed_at: Some(Utc::now()),
+            hash_algorithm: None,
+            executed_at: Some(test_clock().now()),
             error: None,
             step_order: 1,
         };
@@ -432,4 +510,77 @@ mod tests {
             assert!(!can_rollback);
         }
     }
+
+    #[test]
+    fn test_rollback_audit_uses_clock() {
+        let mut db = create_test_db();
+        let temp_dir = TempDir::new().unwrap();
+        let drive = create_test_drive(&mut db, "nas", temp_dir.path().to_str().unwrap());
+        db.conn().execute(
+            "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, indexed_at)
+             VALUES (1, ?1, 'a.txt', 'a.txt', 12, 'indexed', datetime('now'))",
+            [drive],
+        ).unwrap();
+
+        let source = temp_dir.path().join("a.txt");
+        let dest = temp_dir.path().join("copy.txt");
+        fs::write(&source, b"test content").unwrap();
+        fs::write(&dest, b"test content").unwrap();
+
+        let plan_id = db.create_plan(&crate::db::MigrationPlan {
+            id: 0,
+            created_at: test_clock().now(),
+            description: None,
+            source_drive_id: Some(drive),
+            target_drive_id: Some(drive),
+            status: PlanStatus::Completed,
+            total_files: 1,
+            total_bytes: 12,
+            completed_files: 1,
+            completed_bytes: 12,
+            created_by: None,
+            step_ordering: Default::default(),
+        }).unwrap();
+        db.add_step(&MigrationStep {
+            id: 0,
+            plan_id,
+            file_id: 1,
+            action: StepAction::Copy,
+            source_path: source.to_str().unwrap().to_string(),
+            source_drive_id: drive,
+            dest_path: Some(dest.to_str().unwrap().to_string()),
+            dest_drive_id: Some(drive),
+            status: StepStatus::Completed,
+            pre_hash: None,
+            post_hash: None,
+            hash_algorithm: None,
+            executed_at: Some(test_clock().now()),
+            error: None,
+            step_order: 0,
+        }).unwrap();
+
+        let clock = test_clock();
+        clock.advance(chrono::Duration::days(2));
+        assert!(RollbackEngine::new(&mut db, false)
+            .with_safe_mode(true)
+            .rollback_plan(plan_id)
+            .is_err());
+        assert!(dest.exists());
+        RollbackEngine::new(&mut db, false)
+            .with_safe_mode(false)
+            .with_clock(clock.clone())
+            .rollback_plan(plan_id)
+            .unwrap();
+        assert!(!dest.exists());
+
+        let stamps: Vec<String> = db.conn()
+            .prepare("SELECT timestamp FROM audit_log WHERE plan_id = ?1")
+            .unwrap()
+            .query_map([plan_id], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(stamps.len(), 3);
+        assert!(stamps.iter().all(|t| t.starts_with("2025-03-03")));
+    }
 }
@@ -11,6 +11,8 @@ pub struct RsyncOptions {
     pub verbose: bool,
     pub progress: bool,
     pub delete_after: bool,
+    /// Transfer rate cap in KiB/s.
+    pub bwlimit_kbps: Option<u64>,
 }
 
 impl Default for RsyncOptions {
@@ -23,6 +25,7 @@ impl Default for RsyncOptions {
             verbose: true,
             progress: true,
             delete_after: false,
+            bwlimit_kbps: None,
         }
     }
 }
@@ -75,6 +78,10 @@ pub fn execute_rsync<S: AsRef<Path>, D: AsRef<Path>>(
         cmd.arg("--delete-after");
     }
 
+    if let Some(kbps) = options.bwlimit_kbps {
+        cmd.arg(format!("--bwlimit={}", kbps));
+    }
+
     cmd.arg(source.to_str().ok_or_else(|| {
         OrdneError::Migration("Invalid source path encoding".to_string())
     })?);
@@ -111,8 +118,15 @@ pub fn execute_rsync<S: AsRef<Path>, D: AsRef<Path>>(
 }
 
 pub fn copy_file<S: AsRef<Path>, D: AsRef<Path>>(source: S, dest: D) -> Result<()> {
-    let options = RsyncOptions::default();
-    let result = execute_rsync(source.as_ref(), dest.as_ref(), &options)?;
+    copy_file_with_options(source, dest, &RsyncOptions::default())
+}
+
+pub fn copy_file_with_options<S: AsRef<Path>, D: AsRef<Path>>(
+    source: S,
+    dest: D,
+    options: &RsyncOptions,
+) -> Result<()> {
+    let result = execute_rsync(source.as_ref(), dest.as_ref(), options)?;
 
     if !result.success {
         return Err(OrdneError::ExternalTool {
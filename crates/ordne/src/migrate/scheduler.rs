@@ -0,0 +1,403 @@
+//! Device-aware scheduling of migration steps.
+//!
+//! Steps are handed out in dependency order, but a step is only dispatched
+//! while every physical device it touches (source and destination) has a free
+//! slot. This keeps several target drives busy at once without ever running
+//! more than `per_device_limit` transfers against the same disk.
+//!
+//! Steps whose dependencies are done wait in a ready heap ordered by their
+//! position in the plan. A step that finds one of its devices full is parked
+//! on that device's wait list and returns to the heap when a slot frees up,
+//! so each dispatch only looks at steps that can plausibly run.
+
+use crate::db::{Backend, Drive, MigrationStep};
+use crate::migrate::graph::StepGraph;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+const SYS_CLASS_BLOCK: &str = "/sys/class/block";
+
+/// Steps by their position in the plan, lowest first.
+type OrderHeap = BinaryHeap<Reverse<usize>>;
+
+pub struct StepScheduler {
+    steps: Vec<Option<MigrationStep>>,
+    positions: HashMap<i64, usize>,
+    pending: usize,
+    ready: OrderHeap,
+    waiting: HashMap<String, OrderHeap>,
+    remaining_deps: HashMap<i64, usize>,
+    dependents: HashMap<i64, Vec<i64>>,
+    devices: HashMap<i64, Vec<String>>,
+    active: HashMap<String, usize>,
+    per_device_limit: usize,
+}
+
+impl StepScheduler {
+    /// `steps` should already be in execution order; `devices` maps a step ID
+    /// to the device keys it occupies while running.
+    pub fn new(
+        steps: Vec<MigrationStep>,
+        graph: &StepGraph,
+        devices: HashMap<i64, Vec<String>>,
+        per_device_limit: usize,
+    ) -> Self {
+        let ids: HashSet<i64> = steps.iter().map(|s| s.id).collect();
+        let mut remaining_deps = HashMap::new();
+        let mut dependents: HashMap<i64, Vec<i64>> = HashMap::new();
+        let mut ready = OrderHeap::new();
+
+        for (pos, step) in steps.iter().enumerate() {
+            let deps: Vec<i64> = graph
+                .dependencies(step.id)
+                .iter()
+                .copied()
+                .filter(|d| ids.contains(d))
+                .collect();
+            if deps.is_empty() {
+                ready.push(Reverse(pos));
+            }
+            remaining_deps.insert(step.id, deps.len());
+            for dep in deps {
+                dependents.entry(dep).or_default().push(step.id);
+            }
+        }
+
+        Self {
+            positions: steps
+                .iter()
+                .enumerate()
+                .map(|(pos, s)| (s.id, pos))
+                .collect(),
+            pending: steps.len(),
+            steps: steps.into_iter().map(Some).collect(),
+            ready,
+            waiting: HashMap::new(),
+            remaining_deps,
+            dependents,
+            devices,
+            active: HashMap::new(),
+            per_device_limit: per_device_limit.max(1),
+        }
+    }
+
+    /// Take the first queued step whose dependencies are done and whose
+    /// devices all have a free slot. The step's device slots are reserved.
+    pub fn next_ready(&mut self) -> Option<MigrationStep> {
+        while let Some(Reverse(pos)) = self.ready.pop() {
+            let Some(step_id) = self.steps[pos].as_ref().map(|s| s.id) else {
+                continue;
+            };
+            let devices = self.devices_for(step_id);
+
+            if let Some(busy) = devices.iter().find(|d| self.is_full(d)) {
+                self.waiting
+                    .entry(busy.clone())
+                    .or_default()
+                    .push(Reverse(pos));
+                // The step may have been woken for a slot it cannot use;
+                // hand that slot on to the next step waiting for it.
+                let free: Vec<&String> = devices.iter().filter(|d| !self.is_full(d)).collect();
+                for device in free {
+                    self.wake(device);
+                }
+                continue;
+            }
+
+            for device in devices {
+                *self.active.entry(device).or_default() += 1;
+            }
+            self.pending -= 1;
+            return self.steps[pos].take();
+        }
+        None
+    }
+
+    /// Release a finished step's devices and unblock its dependents.
+    pub fn complete(&mut self, step_id: i64) {
+        self.release(step_id);
+        for dependent in self.dependents.remove(&step_id).unwrap_or_default() {
+            if let Some(count) = self.remaining_deps.get_mut(&dependent) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    if let Some(&pos) = self.positions.get(&dependent) {
+                        self.ready.push(Reverse(pos));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Release a failed step's devices. Its dependents stay blocked.
+    pub fn fail(&mut self, step_id: i64) {
+        self.release(step_id);
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    fn release(&mut self, step_id: i64) {
+        for device in self.devices_for(step_id) {
+            if let Some(count) = self.active.get_mut(&device) {
+                *count = count.saturating_sub(1);
+            }
+            self.wake(&device);
+        }
+    }
+
+    /// Move the earliest step waiting on `device` back to the ready heap.
+    fn wake(&mut self, device: &str) {
+        if let Some(Reverse(pos)) = self.waiting.get_mut(device).and_then(|w| w.pop()) {
+            self.ready.push(Reverse(pos));
+        }
+    }
+
+    fn is_full(&self, device: &str) -> bool {
+        self.active.get(device).copied().unwrap_or(0) >= self.per_device_limit
+    }
+
+    fn devices_for(&self, step_id: i64) -> Vec<String> {
+        self.devices.get(&step_id).cloned().unwrap_or_default()
+    }
+}
+
+/// Key identifying the physical device behind a drive.
+///
+/// Partitions of the same block device share a key, so `/dev/sdb1` and
+/// `/dev/sdb2` are throttled together. Remotes are keyed by rclone remote.
+pub fn physical_device_key(drive: &Drive) -> String {
+    device_key(drive, Path::new(SYS_CLASS_BLOCK))
+}
+
+fn device_key(drive: &Drive, sys_block: &Path) -> String {
+    if drive.backend == Backend::Rclone {
+        let remote = drive.rclone_remote.as_deref().unwrap_or(&drive.label);
+        return format!("rclone:{}", remote);
+    }
+
+    if let Some(path) = drive.device_path.as_deref() {
+        return parent_disk(path, sys_block);
+    }
+
+    if let Some(uuid) = drive.uuid.as_deref() {
+        return format!("uuid:{}", uuid);
+    }
+
+    format!("drive:{}", drive.id)
+}
+
+/// The whole disk a block device belongs to, as `/dev/<name>`.
+///
+/// The kernel lists partitions under their disk in sysfs, so a partition's
+/// parent directory names the disk whatever its naming scheme (`sdb1`,
+/// `nvme0n1p1`, `mmcblk0p1`). Whole disks, device-mapper nodes and devices
+/// sysfs does not know are returned as given.
+fn parent_disk(device_path: &str, sys_block: &Path) -> String {
+    let name = fs::canonicalize(device_path)
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .or_else(|| {
+            Path::new(device_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+        });
+    let Some(name) = name else {
+        return device_path.to_string();
+    };
+
+    let entry = sys_block.join(&name);
+    if !entry.exists() {
+        return device_path.to_string();
+    }
+    if !entry.join("partition").exists() {
+        return format!("/dev/{}", name);
+    }
+    fs::canonicalize(entry.join(".."))
+        .ok()
+        .and_then(|p| {
+            p.file_name()
+                .map(|n| format!("/dev/{}", n.to_string_lossy()))
+        })
+        .unwrap_or_else(|| device_path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DriveRole, StepAction, StepStatus};
+    use chrono::Utc;
+
+    fn step(id: i64, order: i32) -> MigrationStep {
+        MigrationStep {
+            id,
+            plan_id: 1,
+            file_id: id,
+            action: StepAction::Copy,
+            source_path: format!("/src/{}", id),
+            source_drive_id: 1,
+            dest_path: None,
+            dest_drive_id: None,
+            status: StepStatus::Pending,
+            pre_hash: None,
+            post_hash: None,
+            hash_algorithm: None,
+            executed_at: None,
+            error: None,
+            step_order: order,
+        }
+    }
+
+    fn devices(entries: &[(i64, &[&str])]) -> HashMap<i64, Vec<String>> {
+        entries
+            .iter()
+            .map(|(id, devs)| (*id, devs.iter().map(|d| d.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_keeps_devices_busy_within_limit() {
+        let steps = vec![step(1, 0), step(2, 1), step(3, 2)];
+        let devs = devices(&[
+            (1, &["/dev/sda", "usb-a"]),
+            (2, &["/dev/sdb", "usb-a"]),
+            (3, &["/dev/sdb", "usb-b"]),
+        ]);
+        let mut scheduler = StepScheduler::new(steps, &StepGraph::default(), devs, 1);
+
+        // Step 2 has to wait for usb-a, but step 3 can use usb-b meanwhile.
+        assert_eq!(scheduler.next_ready().map(|s| s.id), Some(1));
+        assert_eq!(scheduler.next_ready().map(|s| s.id), Some(3));
+        assert!(scheduler.next_ready().is_none());
+
+        // Freeing usb-a is not enough while step 3 still holds /dev/sdb.
+        scheduler.complete(1);
+        assert!(scheduler.next_ready().is_none());
+        scheduler.complete(3);
+        assert_eq!(scheduler.next_ready().map(|s| s.id), Some(2));
+        assert_eq!(scheduler.pending(), 0);
+    }
+
+    #[test]
+    fn test_freed_slot_passes_to_next_waiter() {
+        let steps = vec![step(1, 0), step(2, 1), step(3, 2), step(4, 3)];
+        let devs = devices(&[(1, &["a"]), (2, &["a", "b"]), (3, &["a"]), (4, &["b"])]);
+        let mut scheduler = StepScheduler::new(steps, &StepGraph::default(), devs, 1);
+
+        assert_eq!(scheduler.next_ready().map(|s| s.id), Some(1));
+        assert_eq!(scheduler.next_ready().map(|s| s.id), Some(4));
+        assert!(scheduler.next_ready().is_none());
+
+        // Step 2 is first in line for `a` but still needs `b`, so step 3
+        // takes the freed slot instead.
+        scheduler.complete(1);
+        assert_eq!(scheduler.next_ready().map(|s| s.id), Some(3));
+        scheduler.complete(4);
+        assert!(scheduler.next_ready().is_none());
+        scheduler.complete(3);
+        assert_eq!(scheduler.next_ready().map(|s| s.id), Some(2));
+        assert_eq!(scheduler.pending(), 0);
+    }
+
+    #[test]
+    fn test_waits_for_dependencies() {
+        let steps = vec![step(1, 0), step(2, 1)];
+        let graph = StepGraph::new(&[(2, 1)]);
+        let mut scheduler = StepScheduler::new(steps, &graph, HashMap::new(), 4);
+
+        assert_eq!(scheduler.next_ready().map(|s| s.id), Some(1));
+        assert!(scheduler.next_ready().is_none());
+
+        scheduler.fail(1);
+        assert!(scheduler.next_ready().is_none());
+        assert_eq!(scheduler.pending(), 1);
+    }
+
+    /// A sysfs block class where each entry links to its device directory,
+    /// partitions nested under their disk.
+    fn fake_sys_block(disks: &[(&str, &[&str])]) -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        let block = root.path().join("block");
+        fs::create_dir(&block).unwrap();
+        for (disk, partitions) in disks {
+            let disk_dir = root.path().join("devices").join(disk);
+            fs::create_dir_all(&disk_dir).unwrap();
+            std::os::unix::fs::symlink(&disk_dir, block.join(disk)).unwrap();
+            for partition in *partitions {
+                let partition_dir = disk_dir.join(partition);
+                fs::create_dir(&partition_dir).unwrap();
+                fs::write(partition_dir.join("partition"), "1\n").unwrap();
+                std::os::unix::fs::symlink(&partition_dir, block.join(partition)).unwrap();
+            }
+        }
+        root
+    }
+
+    #[test]
+    fn test_physical_device_key() {
+        let sys = fake_sys_block(&[
+            ("sdb", &["sdb1", "sdb2"]),
+            ("nvme0n1", &["nvme0n1p1", "nvme0n1p3"]),
+        ]);
+        let sys_block = sys.path().join("block");
+        let mut drive = Drive {
+            id: 3,
+            label: "usb".to_string(),
+            device_id: None,
+            device_path: Some("/dev/sdb2".to_string()),
+            uuid: Some("1234".to_string()),
+            mount_path: None,
+            fs_type: None,
+            total_bytes: None,
+            role: DriveRole::Target,
+            is_online: true,
+            is_readonly: false,
+            is_cold: false,
+            is_encrypted: false,
+            backend: Backend::Local,
+            rclone_remote: None,
+            scanned_at: None,
+            added_at: Utc::now(),
+        };
+        assert_eq!(device_key(&drive, &sys_block), "/dev/sdb");
+
+        drive.device_path = Some("/dev/nvme0n1p3".to_string());
+        assert_eq!(device_key(&drive, &sys_block), "/dev/nvme0n1");
+
+        drive.device_path = Some("/dev/mapper/data".to_string());
+        assert_eq!(device_key(&drive, &sys_block), "/dev/mapper/data");
+
+        drive.device_path = None;
+        assert_eq!(device_key(&drive, &sys_block), "uuid:1234");
+
+        drive.backend = Backend::Rclone;
+        drive.rclone_remote = Some("b2".to_string());
+        assert_eq!(device_key(&drive, &sys_block), "rclone:b2");
+    }
+
+    #[test]
+    fn test_parent_disk_follows_sysfs() {
+        let sys = fake_sys_block(&[
+            ("nvme0n1", &["nvme0n1p1"]),
+            ("mmcblk0", &["mmcblk0p1"]),
+            ("md0", &[]),
+            ("loop0", &[]),
+        ]);
+        let sys_block = sys.path().join("block");
+
+        // A whole NVMe disk and its partition are the same device.
+        assert_eq!(parent_disk("/dev/nvme0n1", &sys_block), "/dev/nvme0n1");
+        assert_eq!(parent_disk("/dev/nvme0n1p1", &sys_block), "/dev/nvme0n1");
+
+        // Whole disks ending in a digit keep their index.
+        assert_eq!(parent_disk("/dev/mmcblk0", &sys_block), "/dev/mmcblk0");
+        assert_eq!(parent_disk("/dev/mmcblk0p1", &sys_block), "/dev/mmcblk0");
+        assert_eq!(parent_disk("/dev/md0", &sys_block), "/dev/md0");
+        assert_eq!(parent_disk("/dev/loop0", &sys_block), "/dev/loop0");
+
+        // Unknown to sysfs, e.g. a drive that is not attached.
+        assert_eq!(parent_disk("/dev/sdz9", &sys_block), "/dev/sdz9");
+    }
+}
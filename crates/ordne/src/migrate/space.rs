@@ -6,20 +6,49 @@ const SAFETY_HEADROOM_PERCENT: f64 = 0.50;
 #[derive(Debug, Clone)]
 pub struct SpaceInfo {
     pub total_bytes: u64,
+    /// Free blocks including those reserved for root (`f_bfree`).
     pub free_bytes: u64,
     pub used_bytes: u64,
+    /// Free blocks unprivileged users may write (`f_bavail`).
     pub available_bytes: u64,
+    /// What the current user's block quota still allows on this filesystem,
+    /// or `None` when no quota applies.
+    pub quota_available_bytes: Option<u64>,
+    /// Total inodes; 0 on filesystems that allocate them dynamically (btrfs, many network mounts).
+    pub total_inodes: u64,
+    /// Inodes available to unprivileged users (`f_favail`).
+    pub available_inodes: u64,
 }
 
 impl SpaceInfo {
+    /// Free space only root may use; ext4 reserves 5% by default.
+    pub fn reserved_bytes(&self) -> u64 {
+        self.free_bytes.saturating_sub(self.available_bytes)
+    }
+
+    /// Bytes the current user can actually write: the space not reserved for
+    /// root, capped by their quota.
+    pub fn writable_bytes(&self) -> u64 {
+        self.quota_available_bytes
+            .map_or(self.available_bytes, |quota| quota.min(self.available_bytes))
+    }
+
     pub fn max_safe_write_bytes(&self) -> u64 {
-        let max_use = (self.free_bytes as f64 * SAFETY_HEADROOM_PERCENT) as u64;
-        max_use.min(self.available_bytes)
+        (self.writable_bytes() as f64 * SAFETY_HEADROOM_PERCENT) as u64
     }
 
     pub fn can_safely_write(&self, bytes: u64) -> bool {
         bytes <= self.max_safe_write_bytes()
     }
+
+    /// Inodes that may be consumed, or `None` when the filesystem has no fixed limit.
+    pub fn inode_limit(&self) -> Option<u64> {
+        (self.total_inodes > 0).then_some(self.available_inodes)
+    }
+
+    pub fn can_create_inodes(&self, count: u64) -> bool {
+        self.inode_limit().is_none_or(|limit| count <= limit)
+    }
 }
 
 pub fn get_free_space<P: AsRef<Path>>(path: P) -> Result<SpaceInfo> {
@@ -65,6 +94,9 @@ pub fn get_free_space<P: AsRef<Path>>(path: P) -> Result<SpaceInfo> {
             free_bytes,
             used_bytes,
             available_bytes,
+            quota_available_bytes: user_quota_available(path),
+            total_inodes: stat.f_files as u64,
+            available_inodes: stat.f_favail as u64,
         })
     }
 
@@ -76,6 +108,46 @@ pub fn get_free_space<P: AsRef<Path>>(path: P) -> Result<SpaceInfo> {
     }
 }
 
+/// Bytes the effective user may still write under their block quota on the
+/// filesystem holding `path`. `None` when quotas are off, no limit is set,
+/// or the source of the mount is not a device `quotactl` understands.
+#[cfg(target_os = "linux")]
+fn user_quota_available(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+
+    /// `USRQUOTA` from `<sys/quota.h>`.
+    const USRQUOTA: libc::c_int = 0;
+    /// Quota limits are counted in blocks of this many bytes.
+    const QUOTA_BLOCK_SIZE: u64 = 1024;
+
+    let device = CString::new(crate::index::device::mount_source_of(path)?).ok()?;
+    // SAFETY: geteuid cannot fail.
+    let uid = unsafe { libc::geteuid() };
+    // SAFETY: a zeroed dqblk is a valid value for quotactl to fill in.
+    let mut quota: libc::dqblk = unsafe { std::mem::zeroed() };
+    // SAFETY: `device` is a valid C string and `quota` a dqblk, as Q_GETQUOTA expects.
+    let result = unsafe {
+        libc::quotactl(
+            libc::QCMD(libc::Q_GETQUOTA, USRQUOTA),
+            device.as_ptr(),
+            uid as libc::c_int,
+            &mut quota as *mut libc::dqblk as *mut libc::c_char,
+        )
+    };
+    if result != 0 || quota.dqb_valid & (libc::QIF_BLIMITS | libc::QIF_SPACE) == 0 {
+        return None;
+    }
+
+    // Writes fail at the hard limit; past the soft limit only after a grace period.
+    let limit = match (quota.dqb_bsoftlimit, quota.dqb_bhardlimit) {
+        (0, 0) => return None,
+        (0, hard) => hard,
+        (soft, 0) => soft,
+        (soft, hard) => soft.min(hard),
+    };
+    Some((limit * QUOTA_BLOCK_SIZE).saturating_sub(quota.dqb_curspace))
+}
+
 pub fn calculate_batch_size(files: &[(i64, u64)], max_bytes: u64) -> Vec<i64> {
     let mut batch = Vec::new();
     let mut total_bytes = 0u64;
@@ -105,6 +177,37 @@ pub fn verify_sufficient_space<P: AsRef<Path>>(path: P, required_bytes: u64) ->
     Ok(())
 }
 
+/// Inodes needed to write `paths`: one per file plus one per distinct parent
+/// directory, since directories may have to be created too.
+pub fn estimate_inodes<'a, I>(paths: I) -> u64
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut files = 0u64;
+    let mut dirs = std::collections::HashSet::new();
+    for path in paths {
+        files += 1;
+        if let Some(parent) = Path::new(path).parent() {
+            dirs.insert(parent.to_path_buf());
+        }
+    }
+    files + dirs.len() as u64
+}
+
+pub fn verify_sufficient_inodes<P: AsRef<Path>>(path: P, required_inodes: u64) -> Result<()> {
+    let space_info = get_free_space(&path)?;
+
+    if !space_info.can_create_inodes(required_inodes) {
+        return Err(OrdneError::InsufficientInodes {
+            path: path.as_ref().to_path_buf(),
+            available: space_info.available_inodes,
+            required: required_inodes,
+        });
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,12 +231,38 @@ mod tests {
             free_bytes: 500_000_000,
             used_bytes: 500_000_000,
             available_bytes: 500_000_000,
+            quota_available_bytes: None,
+            total_inodes: 1_000,
+            available_inodes: 10,
         };
 
         let max_safe = space_info.max_safe_write_bytes();
         assert_eq!(max_safe, 250_000_000);
         assert!(space_info.can_safely_write(200_000_000));
         assert!(!space_info.can_safely_write(300_000_000));
+        assert!(space_info.can_create_inodes(10));
+        assert!(!space_info.can_create_inodes(11));
+    }
+
+    #[test]
+    fn test_reserved_space_and_quota_are_not_writable() {
+        // 97% full counting root's 5% reserve as free would still look like room.
+        let mut space_info = SpaceInfo {
+            total_bytes: 1_000_000_000,
+            free_bytes: 80_000_000,
+            used_bytes: 920_000_000,
+            available_bytes: 30_000_000,
+            quota_available_bytes: None,
+            total_inodes: 0,
+            available_inodes: 0,
+        };
+        assert_eq!(space_info.reserved_bytes(), 50_000_000);
+        assert_eq!(space_info.writable_bytes(), 30_000_000);
+        assert_eq!(space_info.max_safe_write_bytes(), 15_000_000);
+
+        space_info.quota_available_bytes = Some(10_000_000);
+        assert_eq!(space_info.writable_bytes(), 10_000_000);
+        assert!(!space_info.can_safely_write(6_000_000));
     }
 
     #[test]
@@ -169,4 +298,22 @@ mod tests {
         let result = verify_sufficient_space(temp_dir.path(), unsafe_amount);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_inode_estimate_and_dynamic_filesystems() {
+        let paths = ["/mnt/a/x/1", "/mnt/a/x/2", "/mnt/a/y/3"];
+        assert_eq!(estimate_inodes(paths.iter().copied()), 5);
+
+        let dynamic = SpaceInfo {
+            total_bytes: 1,
+            free_bytes: 1,
+            used_bytes: 0,
+            available_bytes: 1,
+            quota_available_bytes: None,
+            total_inodes: 0,
+            available_inodes: 0,
+        };
+        assert_eq!(dynamic.inode_limit(), None);
+        assert!(dynamic.can_create_inodes(1_000_000));
+    }
 }
@@ -0,0 +1,288 @@
+//! What-if analysis on a throwaway copy of the catalog.
+//!
+//! The catalog is copied into an in-memory database and plans are applied to
+//! the copy's file rows only: a copy adds a row on the destination drive, a
+//! delete marks its file removed, and links add nothing since they take no
+//! space. Comparing per-drive usage and duplicate counts before and after
+//! shows what a policy or plan would achieve without touching the real
+//! database or any disk.
+
+use crate::config::Settings;
+use crate::db::{Database, MigrationPlan, MigrationStep, PlansDatabase, SqliteDatabase, StepAction};
+use crate::error::{OrdneError, Result};
+use crate::policy::{apply_policy, Policy};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DriveUsage {
+    pub label: String,
+    pub capacity_bytes: Option<i64>,
+    /// Bytes of catalogued files; anything never scanned is not counted.
+    pub used_bytes: i64,
+    pub file_count: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DuplicateSummary {
+    /// Hashes held by more than one file.
+    pub groups: i64,
+    pub duplicate_files: i64,
+    /// Bytes beyond the first copy of each group.
+    pub wasted_bytes: i64,
+    pub cross_drive_groups: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogUsage {
+    pub drives: Vec<DriveUsage>,
+    pub duplicates: DuplicateSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WhatIfReport {
+    /// Plans applied, as they exist in the overlay. Plans created from a
+    /// policy were never written to the real database.
+    pub plans: Vec<MigrationPlan>,
+    pub steps_applied: usize,
+    pub before: CatalogUsage,
+    pub after: CatalogUsage,
+}
+
+pub struct WhatIf {
+    db: SqliteDatabase,
+    before: CatalogUsage,
+    plan_ids: Vec<i64>,
+    steps_applied: usize,
+}
+
+impl WhatIf {
+    pub fn new(catalog: &SqliteDatabase) -> Result<Self> {
+        let db = catalog.in_memory_copy()?;
+        let before = catalog_usage(&db)?;
+        Ok(Self {
+            db,
+            before,
+            plan_ids: Vec::new(),
+            steps_applied: 0,
+        })
+    }
+
+    /// The overlay database, e.g. to classify files before applying a policy.
+    pub fn db_mut(&mut self) -> &mut SqliteDatabase {
+        &mut self.db
+    }
+
+    /// Apply a plan's pending steps to the overlay, returning how many applied.
+    pub fn apply_plan(&mut self, plan_id: i64) -> Result<usize> {
+        self.db
+            .get_plan(plan_id)?
+            .ok_or(OrdneError::PlanNotFound(plan_id))?;
+
+        let steps = self.db.get_pending_steps(plan_id)?;
+        for step in &steps {
+            self.apply_step(step)?;
+        }
+
+        self.plan_ids.push(plan_id);
+        self.steps_applied += steps.len();
+        Ok(steps.len())
+    }
+
+    /// Create the policy's plans in the overlay, under the same `settings`
+    /// as a real run, and apply them.
+    pub fn apply_policy(&mut self, policy: &Policy, settings: &Settings) -> Result<Vec<i64>> {
+        let result = apply_policy(&mut self.db, policy, None, settings)?;
+        for plan_id in &result.plan_ids {
+            self.apply_plan(*plan_id)?;
+        }
+        Ok(result.plan_ids)
+    }
+
+    pub fn report(&self) -> Result<WhatIfReport> {
+        let mut plans = Vec::new();
+        for plan_id in &self.plan_ids {
+            plans.extend(self.db.get_plan(*plan_id)?);
+        }
+
+        Ok(WhatIfReport {
+            plans,
+            steps_applied: self.steps_applied,
+            before: self.before.clone(),
+            after: catalog_usage(&self.db)?,
+        })
+    }
+
+    fn apply_step(&mut self, step: &MigrationStep) -> Result<()> {
+        match step.action {
+            StepAction::Copy => self.add_copy(step),
+            StepAction::Move => {
+                self.add_copy(step)?;
+                self.remove(step.file_id)
+            }
+            StepAction::Delete => self.remove(step.file_id),
+            StepAction::Hardlink | StepAction::Symlink => Ok(()),
+        }
+    }
+
+    fn add_copy(&mut self, step: &MigrationStep) -> Result<()> {
+        let (Some(dest_drive_id), Some(dest_path)) = (step.dest_drive_id, step.dest_path.as_deref())
+        else {
+            return Ok(());
+        };
+        let mount = self
+            .db
+            .get_drive_by_id(dest_drive_id)?
+            .and_then(|d| d.mount_path);
+        let path = mount
+            .as_deref()
+            .and_then(|m| dest_path.strip_prefix(m.trim_end_matches('/')))
+            .map_or(dest_path, |p| p.trim_start_matches('/'));
+
+        self.db.conn().execute(
+            "INSERT INTO files (drive_id, path, filename, extension, size_bytes, md5_hash,
+                                blake3_hash, category, subcategory, priority, status, indexed_at)
+             SELECT ?1, ?2, filename, extension, size_bytes, md5_hash,
+                    blake3_hash, category, subcategory, priority, 'indexed', datetime('now')
+             FROM files WHERE id = ?3
+             ON CONFLICT(drive_id, path) DO UPDATE SET
+                size_bytes = excluded.size_bytes,
+                md5_hash = excluded.md5_hash,
+                blake3_hash = excluded.blake3_hash,
+                status = 'indexed'",
+            (dest_drive_id, path, step.file_id),
+        )?;
+        Ok(())
+    }
+
+    fn remove(&mut self, file_id: i64) -> Result<()> {
+        self.db.conn().execute(
+            "UPDATE files SET status = 'source_removed' WHERE id = ?1",
+            [file_id],
+        )?;
+        Ok(())
+    }
+}
+
+/// Per-drive usage and duplicate counts over files still present.
+pub fn catalog_usage(db: &SqliteDatabase) -> Result<CatalogUsage> {
+    let mut stmt = db.conn().prepare(
+        "SELECT d.label, d.total_bytes, COUNT(f.id), COALESCE(SUM(f.size_bytes), 0)
+         FROM drives d
+         LEFT JOIN files f ON f.drive_id = d.id AND f.status != 'source_removed'
+         GROUP BY d.id
+         ORDER BY d.label",
+    )?;
+    let drives = stmt
+        .query_map([], |row| {
+            Ok(DriveUsage {
+                label: row.get(0)?,
+                capacity_bytes: row.get(1)?,
+                file_count: row.get(2)?,
+                used_bytes: row.get(3)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let duplicates = db.conn().query_row(
+        "SELECT COUNT(*), COALESCE(SUM(n), 0), COALESCE(SUM(waste), 0), COALESCE(SUM(drives > 1), 0)
+         FROM (
+             SELECT COUNT(*) AS n,
+                    SUM(size_bytes) - MAX(size_bytes) AS waste,
+                    COUNT(DISTINCT drive_id) AS drives
+             FROM files
+             WHERE status != 'source_removed' AND COALESCE(blake3_hash, md5_hash) IS NOT NULL
+             GROUP BY COALESCE(blake3_hash, md5_hash)
+             HAVING COUNT(*) > 1
+         )",
+        [],
+        |row| {
+            Ok(DuplicateSummary {
+                groups: row.get(0)?,
+                duplicate_files: row.get(1)?,
+                wasted_bytes: row.get(2)?,
+                cross_drive_groups: row.get(3)?,
+            })
+        },
+    )?;
+
+    Ok(CatalogUsage { drives, duplicates })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{MigrationPlan, PlanStatus, StepStatus};
+    use chrono::Utc;
+
+    #[test]
+    fn test_what_if_leaves_catalog_untouched() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, mount_path, total_bytes, role, is_online, backend)
+                 VALUES (1, 'nas', '/mnt/nas', 1000, 'source', 1, 'local'),
+                        (2, 'archive', '/mnt/archive', 1000, 'offload', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes, blake3_hash, status)
+                 VALUES (1, 1, 'a.jpg', 'a.jpg', 100, 'h1', 'indexed'),
+                        (2, 1, 'copy/a.jpg', 'a.jpg', 100, 'h1', 'indexed'),
+                        (3, 1, 'b.mov', 'b.mov', 300, 'h2', 'indexed');",
+            )
+            .unwrap();
+
+        let plan_id = db
+            .create_plan(&MigrationPlan {
+                id: 0,
+                created_at: Utc::now(),
+                description: None,
+                source_drive_id: Some(1),
+                target_drive_id: Some(2),
+                status: PlanStatus::Draft,
+                total_files: 2,
+                total_bytes: 400,
+                completed_files: 0,
+                completed_bytes: 0,
+                created_by: None,
+                step_ordering: Default::default(),
+            })
+            .unwrap();
+        let step = |file_id, action, source: &str, dest: Option<&str>, order| MigrationStep {
+            id: 0,
+            plan_id,
+            file_id,
+            action,
+            source_path: source.to_string(),
+            source_drive_id: 1,
+            dest_path: dest.map(str::to_string),
+            dest_drive_id: dest.map(|_| 2),
+            status: StepStatus::Pending,
+            pre_hash: None,
+            post_hash: None,
+            hash_algorithm: None,
+            executed_at: None,
+            error: None,
+            step_order: order,
+        };
+        db.add_step(&step(2, StepAction::Delete, "/mnt/nas/copy/a.jpg", None, 0)).unwrap();
+        db.add_step(&step(3, StepAction::Move, "/mnt/nas/b.mov", Some("/mnt/archive/b.mov"), 1))
+            .unwrap();
+
+        let mut what_if = WhatIf::new(&db).unwrap();
+        assert_eq!(what_if.apply_plan(plan_id).unwrap(), 2);
+        let report = what_if.report().unwrap();
+
+        let used = |usage: &CatalogUsage, label: &str| {
+            usage.drives.iter().find(|d| d.label == label).unwrap().used_bytes
+        };
+        assert_eq!(used(&report.before, "nas"), 500);
+        assert_eq!(used(&report.after, "nas"), 100);
+        assert_eq!(used(&report.after, "archive"), 300);
+        assert_eq!(report.before.duplicates.groups, 1);
+        assert_eq!(report.before.duplicates.wasted_bytes, 100);
+        assert_eq!(report.after.duplicates.groups, 0);
+
+        // Neither the files nor the steps changed in the real catalog.
+        assert_eq!(used(&catalog_usage(&db).unwrap(), "nas"), 500);
+        assert_eq!(db.get_pending_steps(plan_id).unwrap().len(), 2);
+    }
+}
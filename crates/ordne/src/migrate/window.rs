@@ -0,0 +1,80 @@
+//! Daily execution windows.
+//!
+//! A window such as `01:00-06:00` limits when the engine starts new steps.
+//! Outside it, running steps finish and the engine sleeps until the window
+//! next opens, so a migration spanning several nights needs no attention.
+//! Windows may wrap past midnight (`22:00-06:00`) and use local time.
+
+use crate::error::{OrdneError, Result};
+use chrono::{Duration, NaiveTime};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExecutionWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl ExecutionWindow {
+    /// Parse `HH:MM-HH:MM`.
+    pub fn parse(s: &str) -> Result<Self> {
+        let invalid = || OrdneError::Config(format!("Invalid window '{}' (expected HH:MM-HH:MM)", s));
+        let (start, end) = s.trim().split_once('-').ok_or_else(invalid)?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").map_err(|_| invalid())?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").map_err(|_| invalid())?;
+        if start == end {
+            return Err(OrdneError::Config(format!("Window '{}' is empty", s)));
+        }
+        Ok(Self { start, end })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// How long until the window opens, or `None` if it is open at `now`.
+    pub fn time_until_open(&self, now: NaiveTime) -> Option<Duration> {
+        if self.contains(now) {
+            return None;
+        }
+        let wait = self.start.signed_duration_since(now);
+        Some(if wait < Duration::zero() { wait + Duration::days(1) } else { wait })
+    }
+}
+
+impl fmt::Display for ExecutionWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_windows_including_past_midnight() {
+        let night = ExecutionWindow::parse("01:00-06:00").unwrap();
+        assert!(night.contains(at(1, 0)));
+        assert!(!night.contains(at(6, 0)));
+        assert_eq!(night.time_until_open(at(3, 0)), None);
+        assert_eq!(night.time_until_open(at(23, 30)), Some(Duration::minutes(90)));
+        assert_eq!(night.time_until_open(at(0, 15)), Some(Duration::minutes(45)));
+
+        let wrapping = ExecutionWindow::parse("22:00-06:00").unwrap();
+        assert!(wrapping.contains(at(23, 0)) && wrapping.contains(at(5, 59)));
+        assert_eq!(wrapping.time_until_open(at(12, 0)), Some(Duration::hours(10)));
+        assert_eq!(wrapping.to_string(), "22:00-06:00");
+
+        assert!(ExecutionWindow::parse("01:00").is_err());
+        assert!(ExecutionWindow::parse("02:00-02:00").is_err());
+    }
+}
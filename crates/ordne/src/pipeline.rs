@@ -0,0 +1,317 @@
+//! One-call access to the catalog pipeline for applications embedding ordne.
+//!
+//! [`Ordne`] wraps the steps the CLI runs one command at a time: register
+//! drives, scan them, classify with rules, plan, approve and execute. Each
+//! call does what the matching `ordne` command does, without the output.
+//!
+//! ```no_run
+//! use ordne_lib::{DriveRole, Ordne};
+//!
+//! fn main() -> ordne_lib::Result<()> {
+//!     let mut ordne = Ordne::builder()
+//!         .db_path("catalog.db")
+//!         .rules_file("rules.toml")
+//!         .dry_run(false)
+//!         .open()?;
+//!
+//!     ordne.add_drive("nas", "/mnt/nas", DriveRole::Source)?;
+//!     ordne.add_drive("archive", "/mnt/archive", DriveRole::Offload)?;
+//!
+//!     let stats = ordne.scan("nas")?;
+//!     println!("{} files indexed", stats.files_scanned);
+//!     ordne.classify()?;
+//!
+//!     let plan_id = ordne.plan_migrate("photos", "archive")?;
+//!     ordne.approve(plan_id)?;
+//!     ordne.execute(plan_id)
+//! }
+//! ```
+
+use crate::classify::{ClassificationRules, DriveRuleEngines};
+use crate::config::Config;
+use crate::db::drives::{mark_drive_scanned, register_drive};
+use crate::db::files::{get_files_by_category, list_unclassified_files, update_file_classification};
+use crate::db::owners::record_owners;
+use crate::db::scan_errors::replace_scan_errors;
+use crate::db::{Attribution, Backend, Database, DriveRole, SqliteDatabase};
+use crate::error::{OrdneError, Result};
+use crate::index::{discover_device, scan_directory, ScanOptions, ScanStats};
+use crate::migrate::{EngineOptions, MigrationEngine, Planner, PlannerOptions};
+use crate::util::clock::Clock;
+use std::path::{Path, PathBuf};
+
+/// Options for [`Ordne`]; start from [`Ordne::builder`].
+#[derive(Debug, Clone)]
+pub struct OrdneBuilder {
+    db_path: Option<PathBuf>,
+    rules: Option<ClassificationRules>,
+    rules_path: Option<PathBuf>,
+    scan_options: ScanOptions,
+    engine_options: EngineOptions,
+    attribution: Option<Attribution>,
+    clock: Clock,
+}
+
+impl OrdneBuilder {
+    /// Database to open; defaults to `ORDNE_DB` or the XDG data directory.
+    pub fn db_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.db_path = Some(path.into());
+        self
+    }
+
+    /// Classification rules; defaults to those in `ordne.toml`, if any.
+    pub fn rules(mut self, rules: ClassificationRules) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// Load classification rules from a TOML file when opening.
+    pub fn rules_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.rules_path = Some(path.into());
+        self
+    }
+
+    pub fn scan_options(mut self, options: ScanOptions) -> Self {
+        self.scan_options = options;
+        self
+    }
+
+    /// Options for executing plans. Dry runs are the default.
+    pub fn engine_options(mut self, options: EngineOptions) -> Self {
+        self.engine_options = options;
+        self
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.engine_options.dry_run = dry_run;
+        self
+    }
+
+    /// Recorded on plans and audit entries as who made the change.
+    pub fn attribution(mut self, attribution: Attribution) -> Self {
+        self.attribution = Some(attribution);
+        self
+    }
+
+    /// Clock for plan and audit timestamps, execution windows and age
+    /// rules; defaults to the system clock or `ORDNE_NOW`.
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn open(self) -> Result<Ordne> {
+        let config = Config::new(self.db_path)?;
+        config.ensure_db_directory()?;
+        let mut db = SqliteDatabase::open(&config.db_path)?;
+        db.initialize()?;
+        let settings = config.settings();
+        crate::config::attach_ledger(&mut db, &config.db_path, &settings)?;
+
+        let rules_path = self.rules_path.or(config.config_path);
+        let rules = match (self.rules, rules_path) {
+            (Some(rules), _) => rules,
+            (None, Some(path)) => ClassificationRules::from_file(path)?,
+            (None, None) => ClassificationRules::default(),
+        };
+
+        let mut engine_options = self.engine_options;
+        engine_options.attribution = self.attribution.clone();
+        engine_options.clock = self.clock.clone();
+        engine_options.safe_mode |= settings.safe_mode();
+
+        Ok(Ordne {
+            db,
+            rules,
+            scan_options: self.scan_options,
+            engine_options,
+            attribution: self.attribution,
+            clock: self.clock,
+            planner_options: settings.planner_options()?,
+        })
+    }
+}
+
+/// The catalog plus everything needed to act on it.
+pub struct Ordne {
+    db: SqliteDatabase,
+    rules: ClassificationRules,
+    scan_options: ScanOptions,
+    engine_options: EngineOptions,
+    attribution: Option<Attribution>,
+    clock: Clock,
+    /// `[replicas]` and `[delete_limits]` from `ordne.toml`.
+    planner_options: PlannerOptions,
+}
+
+impl Ordne {
+    pub fn builder() -> OrdneBuilder {
+        OrdneBuilder {
+            db_path: None,
+            rules: None,
+            rules_path: None,
+            scan_options: ScanOptions::default(),
+            engine_options: EngineOptions::default(),
+            attribution: None,
+            clock: Clock::default(),
+        }
+    }
+
+    /// The underlying database, for anything the pipeline does not cover.
+    pub fn db(&self) -> &SqliteDatabase {
+        &self.db
+    }
+
+    pub fn db_mut(&mut self) -> &mut SqliteDatabase {
+        &mut self.db
+    }
+
+    /// Register a local drive, or return the id of the one with this label.
+    pub fn add_drive(&mut self, label: &str, mount_path: impl AsRef<Path>, role: DriveRole) -> Result<i64> {
+        if let Some(drive) = self.db.get_drive(label)? {
+            return Ok(drive.id);
+        }
+        let device_info = discover_device(mount_path.as_ref())?;
+        register_drive(self.db.conn(), label, &device_info, role, Backend::Local)
+    }
+
+    /// Scan a whole drive, recording unreadable paths as `ordne scan` does.
+    pub fn scan(&mut self, label: &str) -> Result<ScanStats> {
+        let drive = self
+            .db
+            .get_drive(label)?
+            .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+        if !drive.is_online {
+            return Err(OrdneError::DriveOffline(label.to_string()));
+        }
+        let mount_path = drive
+            .mount_path
+            .ok_or_else(|| OrdneError::Config(format!("Drive '{}' has no mount path", label)))?;
+
+        let stats = scan_directory(&mut self.db, drive.id, &mount_path, self.scan_options.clone())?;
+        mark_drive_scanned(self.db.conn(), drive.id)?;
+        replace_scan_errors(self.db.conn(), drive.id, &stats.scope, &stats.failures)?;
+        record_owners(self.db.conn(), &stats.owners)?;
+        Ok(stats)
+    }
+
+    /// Classify unclassified files with the rules, returning how many matched.
+    pub fn classify(&mut self) -> Result<usize> {
+        let engine = DriveRuleEngines::new(&self.db, &self.rules, &self.clock)?;
+        if engine.is_empty() {
+            return Ok(0);
+        }
+        let mut classified = 0;
+        for file in list_unclassified_files(self.db.conn(), None, None)? {
+            if let Some(rule_match) = engine.classify(&file)? {
+                update_file_classification(
+                    self.db.conn(),
+                    file.id,
+                    &rule_match.category,
+                    rule_match.subcategory.as_deref(),
+                    rule_match.priority,
+                )?;
+                classified += 1;
+            }
+        }
+        Ok(classified)
+    }
+
+    /// Plan copying a category to a drive, keeping the sources.
+    pub fn plan_migrate(&mut self, category: &str, target: &str) -> Result<i64> {
+        let (files, drive_id, mount) = self.plan_inputs(category, target)?;
+        self.planner().create_migrate_plan(files, drive_id, &mount)
+    }
+
+    /// Plan moving a category to a drive, removing the sources once verified.
+    pub fn plan_offload(&mut self, category: &str, target: &str) -> Result<i64> {
+        let (files, drive_id, mount) = self.plan_inputs(category, target)?;
+        self.planner().create_offload_plan(files, drive_id, &mount)
+    }
+
+    pub fn approve(&mut self, plan_id: i64) -> Result<()> {
+        self.planner().approve_plan(plan_id)
+    }
+
+    /// Execute an approved plan with the configured engine options.
+    pub fn execute(&mut self, plan_id: i64) -> Result<()> {
+        MigrationEngine::new(&mut self.db, self.engine_options.clone()).execute_plan(plan_id)
+    }
+
+    fn planner(&mut self) -> Planner<'_, SqliteDatabase> {
+        let options = PlannerOptions {
+            dry_run: false,
+            attribution: self.attribution.clone(),
+            clock: self.clock.clone(),
+            ..self.planner_options.clone()
+        };
+        Planner::new(&mut self.db, options)
+    }
+
+    fn plan_inputs(&self, category: &str, target: &str) -> Result<(Vec<crate::db::File>, i64, String)> {
+        let drive = self
+            .db
+            .get_drive(target)?
+            .ok_or_else(|| OrdneError::DriveNotFound(target.to_string()))?;
+        let mount = drive
+            .mount_path
+            .ok_or_else(|| OrdneError::Config(format!("Target drive '{}' has no mount path", target)))?;
+        let files: Vec<_> = get_files_by_category(self.db.conn(), category)?
+            .into_iter()
+            .filter(|f| f.drive_id != drive.id)
+            .collect();
+        if files.is_empty() {
+            return Err(OrdneError::Config(format!("No files in category '{}'", category)));
+        }
+        Ok((files, drive.id, mount))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{PlanStatus, PlansDatabase};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pipeline_from_scan_to_dry_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let (src, dst) = (temp_dir.path().join("src"), temp_dir.path().join("dst"));
+        std::fs::create_dir_all(src.join("docs")).unwrap();
+        std::fs::create_dir_all(&dst).unwrap();
+        std::fs::write(src.join("docs/a.txt"), b"hello").unwrap();
+        std::fs::write(src.join("b.bin"), b"data").unwrap();
+
+        let rules = ClassificationRules::from_toml(
+            r#"
+            [rules.text]
+            type = "extension"
+            extensions = ["txt"]
+            category = "documents"
+            "#,
+        )
+        .unwrap();
+        let mut ordne = Ordne::builder()
+            .db_path(temp_dir.path().join("ordne.db"))
+            .rules(rules)
+            .open()
+            .unwrap();
+
+        ordne.add_drive("src", &src, DriveRole::Source).unwrap();
+        let dst_id = ordne.add_drive("dst", &dst, DriveRole::Backup).unwrap();
+        assert_eq!(ordne.add_drive("dst", &dst, DriveRole::Backup).unwrap(), dst_id);
+
+        assert_eq!(ordne.scan("src").unwrap().files_scanned, 2);
+        assert_eq!(ordne.classify().unwrap(), 1);
+
+        let plan_id = ordne.plan_migrate("documents", "dst").unwrap();
+        ordne.approve(plan_id).unwrap();
+        ordne.execute(plan_id).unwrap();
+
+        // Dry run by default: the plan ran but nothing was copied.
+        let plan = ordne.db().get_plan(plan_id).unwrap().unwrap();
+        assert_eq!(plan.total_files, 1);
+        assert_eq!(plan.status, PlanStatus::Approved);
+        assert!(!dst.join("docs/a.txt").exists());
+    }
+}
@@ -1,9 +1,14 @@
 use crate::error::{OrdneError, Result};
 use crate::{
     classify::{ClassificationRule, ClassificationRules},
+    config::Settings,
+    db::duplicates::list_cross_drive_group_files,
     db::files::{get_files_by_category, get_files_by_category_and_drive, list_files_by_duplicate_group},
-    migrate::{Planner, PlannerOptions},
-    Database, Priority, SqliteDatabase,
+    migrate::{
+        CrossDriveDedupOptions, ExecutionWindow, IdlePolicy, PlacementConstraint, PlacementTarget, Planner,
+        PlannerOptions,
+    },
+    Attribution, Database, Priority, SqliteDatabase,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -52,6 +57,19 @@ pub struct PolicyPlan {
     pub category_filter: Option<String>,
     pub duplicate_group: Option<i64>,
     pub original_file: Option<i64>,
+    #[serde(default)]
+    pub keep_roles: Vec<String>,
+    #[serde(default)]
+    pub remove_roles: Vec<String>,
+    pub hardlink: Option<bool>,
+    /// Additional target drives; with `target_drive` they form a pool (migrate).
+    #[serde(default)]
+    pub target_drives: Vec<String>,
+    /// Categories that must land on a single target drive (migrate).
+    #[serde(default)]
+    pub keep_together: Vec<String>,
+    /// Copy and delete in batches of this size, e.g. "20GB" (offload).
+    pub batch_size: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,12 +77,58 @@ pub struct PolicySafety {
     pub require_approval: Option<bool>,
     pub max_bytes_per_run: Option<String>,
     pub dry_run_only: Option<bool>,
+    /// Categories whose files may only be copied to encrypted drives.
+    #[serde(default)]
+    pub require_encryption: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PolicySchedule {
     pub cron: Option<String>,
     pub timezone: Option<String>,
+    /// Daily local-time window for executing plans, e.g. "01:00-06:00".
+    pub window: Option<String>,
+    /// Copy rate cap, e.g. "20MB" (per second).
+    pub bandwidth_limit: Option<String>,
+    /// Only start steps after this long without keyboard or mouse input, e.g. "10m".
+    pub when_idle: Option<String>,
+    /// Only start steps while the 1-minute load average is at most this.
+    pub max_load: Option<f64>,
+}
+
+impl PolicySchedule {
+    pub fn execution_window(&self) -> Result<Option<ExecutionWindow>> {
+        self.window.as_deref().map(ExecutionWindow::parse).transpose()
+    }
+
+    /// `bandwidth_limit` in KiB/s.
+    pub fn bandwidth_limit_kbps(&self) -> Result<Option<u64>> {
+        self.bandwidth_limit
+            .as_deref()
+            .map(|s| {
+                crate::util::units::parse_bytes(s)
+                    .map(|b| (b as u64 / 1024).max(1))
+                    .map_err(|e| OrdneError::Config(format!("Invalid bandwidth_limit: {}", e)))
+            })
+            .transpose()
+    }
+
+    /// `when_idle` and `max_load` as an engine idle policy, if either is set.
+    pub fn idle_policy(&self) -> Result<Option<IdlePolicy>> {
+        let min_idle = self
+            .when_idle
+            .as_deref()
+            .map(|s| {
+                crate::util::units::parse_duration(s)
+                    .map(|d| d.to_std().unwrap_or_default())
+                    .map_err(|e| OrdneError::Config(format!("Invalid when_idle: {}", e)))
+            })
+            .transpose()?;
+        if min_idle.is_none() && self.max_load.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(IdlePolicy::new(min_idle, self.max_load)))
+    }
 }
 
 impl Policy {
@@ -91,16 +155,33 @@ impl Policy {
             }
         }
 
+        if let Some(schedule) = &self.schedule {
+            schedule.execution_window()?;
+            schedule.bandwidth_limit_kbps()?;
+            schedule.idle_policy()?;
+        }
+
         for plan in self.plans.values() {
             if plan.plan_type.trim().is_empty() {
                 return Err(OrdneError::Config("Plan type cannot be empty".to_string()));
             }
             if !is_valid_plan_type(&plan.plan_type) {
                 return Err(OrdneError::Config(format!(
-                    "Invalid plan type: {} (valid: delete-trash, dedup, migrate, offload)",
+                    "Invalid plan type: {} (valid: delete-trash, dedup, cross-drive-dedup, migrate, offload)",
                     plan.plan_type
                 )));
             }
+            for constraint in &plan.keep_together {
+                PlacementConstraint::parse(constraint)?;
+            }
+            parse_batch_size(plan)?;
+            if plan.plan_type == "cross-drive-dedup" {
+                CrossDriveDedupOptions::from_role_names(
+                    &plan.keep_roles,
+                    &plan.remove_roles,
+                    plan.hardlink.unwrap_or(false),
+                )?;
+            }
         }
 
         Ok(())
@@ -108,7 +189,10 @@ impl Policy {
 }
 
 fn is_valid_plan_type(plan_type: &str) -> bool {
-    matches!(plan_type, "delete-trash" | "dedup" | "migrate" | "offload")
+    matches!(
+        plan_type,
+        "delete-trash" | "dedup" | "cross-drive-dedup" | "migrate" | "offload"
+    )
 }
 
 #[derive(Debug, Clone)]
@@ -116,17 +200,47 @@ pub struct PolicyApplyResult {
     pub plan_ids: Vec<i64>,
 }
 
+/// Create the policy's plans, keeping the `[replicas]` and staying within
+/// the `[delete_limits]` of `settings`.
 pub fn apply_policy(
     db: &mut SqliteDatabase,
     policy: &Policy,
+    attribution: Option<Attribution>,
+    settings: &Settings,
+) -> Result<PolicyApplyResult> {
+    let options = PlannerOptions {
+        attribution,
+        ..settings.planner_options()?
+    };
+    apply_policy_with(db, policy, options)
+}
+
+/// [`apply_policy`] planning with `options`, such as those of an agent with a
+/// delete budget. The policy's `require_encryption` is added to them.
+pub fn apply_policy_with(
+    db: &mut SqliteDatabase,
+    policy: &Policy,
+    options: PlannerOptions,
 ) -> Result<PolicyApplyResult> {
     policy.validate()?;
 
+    #[allow(clippy::large_enum_variant)]
     enum PlanInput {
         DeleteTrash { files: Vec<crate::File> },
         Dedup { duplicates: Vec<crate::File>, original: crate::File },
+        CrossDriveDedup { groups: Vec<Vec<crate::File>>, options: CrossDriveDedupOptions },
         Migrate { files: Vec<crate::File>, target_id: i64, target_mount: String },
-        Offload { files: Vec<crate::File>, target_id: i64, target_mount: String },
+        PoolMigrate {
+            files: Vec<crate::File>,
+            targets: Vec<PlacementTarget>,
+            constraints: Vec<PlacementConstraint>,
+        },
+        Offload {
+            files: Vec<crate::File>,
+            target_id: i64,
+            target_mount: String,
+            batch_size: Option<u64>,
+        },
     }
 
     let mut inputs = Vec::new();
@@ -168,14 +282,41 @@ pub fn apply_policy(
 
                 PlanInput::Dedup { duplicates, original }
             }
+            "cross-drive-dedup" => {
+                let options = CrossDriveDedupOptions::from_role_names(
+                    &plan.keep_roles,
+                    &plan.remove_roles,
+                    plan.hardlink.unwrap_or(false),
+                )?;
+                let groups = list_cross_drive_group_files(db.conn())?;
+                PlanInput::CrossDriveDedup { groups, options }
+            }
             "migrate" | "offload" => {
-                let target_label = plan.target_drive.as_deref().ok_or_else(|| {
-                    OrdneError::Config("target_drive is required".to_string())
-                })?;
-                let target = db.get_drive(target_label)?
-                    .ok_or_else(|| OrdneError::DriveNotFound(target_label.to_string()))?;
-                let target_mount = target.mount_path.clone()
-                    .ok_or_else(|| OrdneError::Config("Target drive has no mount path".to_string()))?;
+                let labels: Vec<&str> = plan
+                    .target_drive
+                    .iter()
+                    .chain(plan.target_drives.iter())
+                    .map(String::as_str)
+                    .collect();
+                if labels.is_empty() {
+                    return Err(OrdneError::Config("target_drive is required".to_string()));
+                }
+                let pooled = labels.len() > 1 || !plan.keep_together.is_empty();
+                if pooled && plan.plan_type == "offload" {
+                    return Err(OrdneError::Config(
+                        "Offload plans take a single target_drive".to_string(),
+                    ));
+                }
+
+                let mut targets = Vec::new();
+                for label in labels {
+                    let target = db.get_drive(label)?
+                        .ok_or_else(|| OrdneError::DriveNotFound(label.to_string()))?;
+                    let mount_path = target.mount_path.clone().ok_or_else(|| {
+                        OrdneError::Config(format!("Target drive '{}' has no mount path", label))
+                    })?;
+                    targets.push(PlacementTarget { drive_id: target.id, mount_path, capacity_bytes: None });
+                }
 
                 let category = plan.category_filter.as_deref().ok_or_else(|| {
                     OrdneError::Config("category_filter is required".to_string())
@@ -186,10 +327,25 @@ pub fn apply_policy(
                     return Err(OrdneError::Config("No files matched category filter".to_string()));
                 }
 
-                if plan.plan_type == "migrate" {
-                    PlanInput::Migrate { files, target_id: target.id, target_mount }
+                if pooled {
+                    let constraints = plan
+                        .keep_together
+                        .iter()
+                        .map(|c| PlacementConstraint::parse(c))
+                        .collect::<Result<Vec<_>>>()?;
+                    PlanInput::PoolMigrate { files, targets, constraints }
                 } else {
-                    PlanInput::Offload { files, target_id: target.id, target_mount }
+                    let target = targets.remove(0);
+                    if plan.plan_type == "migrate" {
+                        PlanInput::Migrate { files, target_id: target.drive_id, target_mount: target.mount_path }
+                    } else {
+                        PlanInput::Offload {
+                            files,
+                            target_id: target.drive_id,
+                            target_mount: target.mount_path,
+                            batch_size: parse_batch_size(plan)?,
+                        }
+                    }
                 }
             }
             _ => {
@@ -207,20 +363,34 @@ pub fn apply_policy(
         max_batch_size_bytes: None,
         enforce_space_limits: true,
         dry_run: false,
+        require_encryption: policy
+            .safety
+            .as_ref()
+            .map(|s| s.require_encryption.clone())
+            .unwrap_or_default(),
+        ..options
     };
     let mut planner = Planner::new(db, planner_options);
 
     let mut plan_ids = Vec::new();
     for input in inputs {
         let plan_id = match input {
+            PlanInput::CrossDriveDedup { groups, options } => {
+                plan_ids.extend(planner.create_cross_drive_dedup_plans(groups, &options)?);
+                continue;
+            }
             PlanInput::DeleteTrash { files } => planner.create_delete_trash_plan(files)?,
             PlanInput::Dedup { duplicates, original } => planner.create_dedup_plan(duplicates, &original)?,
             PlanInput::Migrate { files, target_id, target_mount } => {
                 planner.create_migrate_plan(files, target_id, &target_mount)?
             }
-            PlanInput::Offload { files, target_id, target_mount } => {
+            PlanInput::Offload { files, target_id, target_mount, batch_size } => {
+                planner.set_max_batch_size(batch_size);
                 planner.create_offload_plan(files, target_id, &target_mount)?
             }
+            PlanInput::PoolMigrate { files, targets, constraints } => {
+                planner.create_pool_migrate_plan(files, targets, &constraints)?
+            }
         };
 
         plan_ids.push(plan_id);
@@ -229,6 +399,17 @@ pub fn apply_policy(
     Ok(PolicyApplyResult { plan_ids })
 }
 
+fn parse_batch_size(plan: &PolicyPlan) -> Result<Option<u64>> {
+    plan.batch_size
+        .as_deref()
+        .map(|s| {
+            crate::util::units::parse_bytes(s)
+                .map(|b| b as u64)
+                .map_err(|e| OrdneError::Config(format!("Invalid batch_size: {}", e)))
+        })
+        .transpose()
+}
+
 fn collect_files_by_category(
     db: &SqliteDatabase,
     policy: &Policy,
@@ -291,13 +472,13 @@ pub fn load_effective_policy(
     let policy = Policy::load_from_file(policy_path)?;
     policy.validate()?;
 
-    let mut rules = ClassificationRules { rules: HashMap::new() };
+    let mut rules = ClassificationRules::default();
 
     let mut sources = Vec::new();
     sources.push(config_policy_path()?);
 
     if let Some(root) = policy_root_from_scope(db, &policy)? {
-        sources.push(root.join(".ordne/ordne.toml"));
+        sources.push(root.join(crate::config::DRIVE_CONFIG_FILE));
     }
 
     sources.push(policy_path.to_path_buf());
@@ -307,8 +488,7 @@ pub fn load_effective_policy(
             continue;
         }
         let content = std::fs::read_to_string(&path)?;
-        let mut parsed = ClassificationRules::from_toml(&content)?;
-        rules.rules.extend(parsed.rules.drain());
+        rules.merge(ClassificationRules::from_toml(&content)?);
     }
 
     if !policy.rules.is_empty() {
@@ -361,4 +541,46 @@ mod tests {
 
         assert!(policy.validate().is_err());
     }
+
+    #[test]
+    fn validate_rejects_bad_placement_constraint() {
+        let policy: Policy = toml::from_str(
+            r#"
+            version = "0.1"
+            name = "archive"
+
+            [plans.photos]
+            type = "migrate"
+            target_drive = "arch1"
+            target_drives = ["arch2"]
+            category_filter = "photos"
+            keep_together = ["photos/"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(policy.validate().is_err());
+    }
+
+    #[test]
+    fn schedule_window_bandwidth_and_idle() {
+        let parse = |schedule: &str| -> Policy {
+            toml::from_str(&format!("version = \"0.1\"\nname = \"nightly\"\n[schedule]\n{}", schedule))
+                .unwrap()
+        };
+
+        let policy = parse("window = \"01:00-06:00\"\nbandwidth_limit = \"20MB\"");
+        policy.validate().unwrap();
+        let schedule = policy.schedule.unwrap();
+        assert_eq!(schedule.execution_window().unwrap().unwrap().to_string(), "01:00-06:00");
+        assert_eq!(schedule.bandwidth_limit_kbps().unwrap(), Some(20 * 1024));
+        assert_eq!(schedule.idle_policy().unwrap(), None);
+
+        let idle = parse("when_idle = \"10m\"\nmax_load = 1.5").schedule.unwrap().idle_policy().unwrap().unwrap();
+        assert_eq!(idle.min_idle, Some(std::time::Duration::from_secs(600)));
+        assert_eq!(idle.max_load, Some(1.5));
+
+        assert!(parse("window = \"1am-6am\"").validate().is_err());
+        assert!(parse("when_idle = \"soon\"").validate().is_err());
+    }
 }
@@ -0,0 +1,295 @@
+//! Changing the role of a registered drive.
+//!
+//! Roles decide which copy of a duplicate group is kept as its original
+//! (source before backup, target and offload drives), which copies
+//! cross-drive dedup removes (those on offload drives, by default) and which
+//! drives backup queries look at. [`assess_role_change`] works out what a new
+//! role would change before anything is written: plans that have not
+//! finished were made for the old role, and unresolved duplicate groups may
+//! now prefer an original on a different drive. [`apply_role_change`] sets
+//! the role, moves those originals and records both in the audit log as
+//! `drive_role_changed`.
+
+use crate::db::drives::set_drive_role;
+use crate::db::duplicates::{list_duplicate_groups, set_duplicate_group_original};
+use crate::db::files::list_files_by_duplicate_group;
+use crate::db::{
+    Attribution, AuditDatabase, AuditLogEntry, Database, Drive, DriveRole, PlanStatus, PlansDatabase,
+    SqliteDatabase,
+};
+use crate::error::{OrdneError, Result};
+use crate::index::dedup::role_rank;
+use crate::util::clock::Clock;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A plan that has not finished and reads from or writes to the drive.
+#[derive(Debug, Clone, Serialize)]
+pub struct AffectedPlan {
+    pub plan_id: i64,
+    pub status: String,
+    pub description: Option<String>,
+    pub reads_from_drive: bool,
+    pub writes_to_drive: bool,
+}
+
+/// A duplicate group whose original moves to a copy on another drive.
+#[derive(Debug, Clone, Serialize)]
+pub struct OriginalMove {
+    pub group_id: i64,
+    pub from_file_id: i64,
+    pub to_file_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoleChange {
+    pub drive_id: i64,
+    pub label: String,
+    pub from: DriveRole,
+    pub to: DriveRole,
+    pub affected_plans: Vec<AffectedPlan>,
+    pub original_moves: Vec<OriginalMove>,
+    /// Consequences worth confirming before the change is applied.
+    pub warnings: Vec<String>,
+}
+
+/// What giving `drive` the role `to` would change. Nothing is written.
+pub fn assess_role_change(db: &SqliteDatabase, drive: &Drive, to: DriveRole) -> Result<RoleChange> {
+    if drive.role == to {
+        return Err(OrdneError::UserInput(format!(
+            "Drive '{}' already has role {}",
+            drive.label,
+            to.as_str()
+        )));
+    }
+
+    let mut change = RoleChange {
+        drive_id: drive.id,
+        label: drive.label.clone(),
+        from: drive.role,
+        to,
+        affected_plans: affected_plans(db, drive.id)?,
+        original_moves: original_moves(db, drive.id, to)?,
+        warnings: Vec::new(),
+    };
+
+    for plan in &change.affected_plans {
+        let usage = match (plan.reads_from_drive, plan.writes_to_drive) {
+            (true, true) => "reads from and writes to",
+            (true, false) => "reads from",
+            _ => "writes to",
+        };
+        change.warnings.push(format!(
+            "Plan {} ({}) {} this drive and was created while it was {}; review it with 'ordne plan show {}'",
+            plan.plan_id,
+            plan.status,
+            usage,
+            change.from.as_str(),
+            plan.plan_id
+        ));
+    }
+
+    if !change.original_moves.is_empty() {
+        change.warnings.push(format!(
+            "{} duplicate groups will keep their original on another drive",
+            change.original_moves.len()
+        ));
+    }
+
+    if to == DriveRole::Offload {
+        let removable = copies_kept_elsewhere(db, drive.id)?;
+        if removable > 0 {
+            change.warnings.push(format!(
+                "{} files on this drive have a copy on a source or backup drive; cross-drive dedup plans will now remove them from this drive",
+                removable
+            ));
+        }
+    } else if change.from == DriveRole::Offload {
+        change
+            .warnings
+            .push("Cross-drive dedup plans will no longer remove copies from this drive".to_string());
+    }
+
+    Ok(change)
+}
+
+/// Set the role assessed in `change`, move duplicate originals and log it, all
+/// in one transaction.
+pub fn apply_role_change(
+    db: &mut SqliteDatabase,
+    change: &RoleChange,
+    clock: &Clock,
+    attribution: Option<&Attribution>,
+) -> Result<()> {
+    db.in_transaction(|db| write_role_change(db, change, clock, attribution))
+}
+
+fn write_role_change(
+    db: &mut SqliteDatabase,
+    change: &RoleChange,
+    clock: &Clock,
+    attribution: Option<&Attribution>,
+) -> Result<()> {
+    set_drive_role(db.conn(), change.drive_id, change.to)?;
+    for moved in &change.original_moves {
+        set_duplicate_group_original(db.conn(), moved.group_id, moved.to_file_id)?;
+    }
+
+    db.log_audit(&AuditLogEntry {
+        id: 0,
+        timestamp: clock.now(),
+        action: "drive_role_changed".to_string(),
+        file_id: None,
+        plan_id: None,
+        drive_id: Some(change.drive_id),
+        details: Some(format!(
+            "Drive '{}' changed from {} to {}; {} duplicate originals moved, {} unfinished plans use it",
+            change.label,
+            change.from.as_str(),
+            change.to.as_str(),
+            change.original_moves.len(),
+            change.affected_plans.len()
+        )),
+        agent_mode: attribution.map(|a| a.agent_mode.clone()),
+        before_state: Some(serde_json::json!({ "role": change.from.as_str() }).to_string()),
+        after_state: Some(
+            serde_json::json!({
+                "role": change.to.as_str(),
+                "original_moves": change.original_moves,
+            })
+            .to_string(),
+        ),
+        actor: attribution.map(|a| a.actor.clone()),
+    })?;
+    Ok(())
+}
+
+fn affected_plans(db: &SqliteDatabase, drive_id: i64) -> Result<Vec<AffectedPlan>> {
+    let mut plans = Vec::new();
+    for plan in db.list_plans(None)? {
+        if matches!(plan.status, PlanStatus::Completed | PlanStatus::Aborted) {
+            continue;
+        }
+        let (reads, writes): (bool, bool) = db.conn().query_row(
+            "SELECT EXISTS(SELECT 1 FROM migration_steps WHERE plan_id = ?1 AND source_drive_id = ?2),
+                    EXISTS(SELECT 1 FROM migration_steps WHERE plan_id = ?1 AND dest_drive_id = ?2)",
+            (plan.id, drive_id),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let reads = reads || plan.source_drive_id == Some(drive_id);
+        let writes = writes || plan.target_drive_id == Some(drive_id);
+        if reads || writes {
+            plans.push(AffectedPlan {
+                plan_id: plan.id,
+                status: plan.status.as_str().to_string(),
+                description: plan.description,
+                reads_from_drive: reads,
+                writes_to_drive: writes,
+            });
+        }
+    }
+    Ok(plans)
+}
+
+/// Unresolved groups with a copy on `drive_id` whose original is no longer
+/// on the best-ranked drive once the drive has `role`. The new original is
+/// the first-catalogued copy on the best-ranked drive, as a `drive-role`
+/// refresh would pick it.
+fn original_moves(db: &SqliteDatabase, drive_id: i64, role: DriveRole) -> Result<Vec<OriginalMove>> {
+    let mut roles: HashMap<i64, DriveRole> = db.list_drives()?.into_iter().map(|d| (d.id, d.role)).collect();
+    roles.insert(drive_id, role);
+    let rank = |drive: i64| role_rank(roles.get(&drive).copied());
+
+    let mut moves = Vec::new();
+    for group in list_duplicate_groups(db.conn())? {
+        let unresolved = group.resolution.as_deref().is_none_or(|r| r == "pending");
+        if !unresolved || !group.drives_involved.contains(&drive_id) {
+            continue;
+        }
+        let Some(current) = group.original_id else {
+            continue;
+        };
+
+        let files = list_files_by_duplicate_group(db.conn(), group.group_id)?;
+        let Some(current_rank) = files.iter().find(|f| f.id == current).map(|f| rank(f.drive_id)) else {
+            continue;
+        };
+        let Some(best) = files.iter().min_by_key(|f| (rank(f.drive_id), f.id)) else {
+            continue;
+        };
+        if rank(best.drive_id) < current_rank {
+            moves.push(OriginalMove {
+                group_id: group.group_id,
+                from_file_id: current,
+                to_file_id: best.id,
+            });
+        }
+    }
+    Ok(moves)
+}
+
+/// Files on `drive_id` whose content also sits on a source or backup drive.
+fn copies_kept_elsewhere(db: &SqliteDatabase, drive_id: i64) -> Result<i64> {
+    Ok(db.conn().query_row(
+        "SELECT COUNT(*) FROM files f
+         WHERE f.drive_id = ?1 AND f.duplicate_group IS NOT NULL
+           AND EXISTS (
+               SELECT 1 FROM files other JOIN drives d ON d.id = other.drive_id
+               WHERE other.duplicate_group = f.duplicate_group
+                 AND other.drive_id != ?1
+                 AND d.role IN ('source', 'backup'))",
+        [drive_id],
+        |row| row.get(0),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_demoting_source_moves_originals_and_flags_plans() {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'old', 'source', 1, 'local');
+                 INSERT INTO drives (id, label, role, is_online, backend) VALUES (2, 'nas', 'backup', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes)
+                 VALUES (1, 1, 'a', 'a', 10), (2, 2, 'a', 'a', 10);
+                 INSERT INTO duplicate_groups (group_id, hash, file_count, total_waste_bytes, original_id, drives_involved, cross_drive)
+                 VALUES (1, 'h', 2, 10, 1, '[1,2]', 1);
+                 UPDATE files SET duplicate_group = 1, is_original = (id = 1);
+                 INSERT INTO migration_plans (id, status, total_files, total_bytes) VALUES (1, 'approved', 1, 10), (2, 'completed', 1, 10);
+                 INSERT INTO migration_steps (plan_id, step_order, file_id, action, source_path, source_drive_id, status)
+                 VALUES (1, 1, 1, 'delete', 'a', 1, 'pending'), (2, 1, 1, 'delete', 'a', 1, 'completed');",
+            )
+            .unwrap();
+
+        let drive = db.get_drive("old").unwrap().unwrap();
+        assert!(assess_role_change(&db, &drive, DriveRole::Source).is_err());
+
+        let change = assess_role_change(&db, &drive, DriveRole::Offload).unwrap();
+        assert_eq!(change.affected_plans.len(), 1);
+        assert!(change.affected_plans[0].reads_from_drive);
+        assert_eq!(change.original_moves.len(), 1);
+        assert_eq!(change.original_moves[0].to_file_id, 2);
+        assert!(change.warnings.iter().any(|w| w.contains("cross-drive dedup")));
+
+        let now = chrono::DateTime::parse_from_rfc3339("2026-03-01T12:00:00Z").unwrap().into();
+        apply_role_change(&mut db, &change, &Clock::fixed(now), None).unwrap();
+        assert_eq!(db.get_drive("old").unwrap().unwrap().role, DriveRole::Offload);
+        let original: i64 = db
+            .conn()
+            .query_row("SELECT original_id FROM duplicate_groups WHERE group_id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(original, 2);
+        let logged: i64 = db
+            .conn()
+            .query_row("SELECT COUNT(*) FROM audit_log WHERE action = 'drive_role_changed'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(logged, 1);
+        let entry = db.get_audit_entries(None, None, None).unwrap().into_iter().next().unwrap();
+        assert_eq!(entry.timestamp, now);
+    }
+}
@@ -0,0 +1,534 @@
+//! Taking back the last catalog change made from the command line.
+//!
+//! Classification batches, plan creation and drive registration are written
+//! to the audit log as `files_classified`, `plan_created` and `drive_added`.
+//! Classification entries carry the previous category, priority and status
+//! of every file they changed, and what the batch set them to; a file changed
+//! again since is left alone on undo. Reviewed dedup plans carry the previous
+//! original and resolution of the groups they resolved. [`pending_undo`]
+//! finds the newest such entry
+//! that has not been undone yet; [`undo`] reverts it and logs an
+//! `operation_undone` entry pointing back at it, so the same operation is
+//! never reverted twice and the history stays complete.
+//!
+//! Only the catalog is touched. Plans that have started executing and drives
+//! that already have indexed files are refused.
+
+use crate::db::files::get_file;
+use crate::db::verification::{format_timestamp, parse_timestamp};
+use crate::db::{
+    Attribution, AuditDatabase, AuditLogEntry, Database, File, PlanStatus, PlansDatabase, SqliteDatabase,
+};
+use crate::error::{OrdneError, Result};
+use crate::util::clock::Clock;
+use crate::util::format::format_duration;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A file's classification before a batch changed it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassificationSnapshot {
+    pub file_id: i64,
+    pub category: Option<String>,
+    pub subcategory: Option<String>,
+    pub priority: String,
+    pub status: String,
+    /// Suggestion the change resolved, returned to `pending` on undo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion_id: Option<i64>,
+}
+
+impl ClassificationSnapshot {
+    pub fn from_file(file: &File) -> Self {
+        Self {
+            file_id: file.id,
+            category: file.category.clone(),
+            subcategory: file.subcategory.clone(),
+            priority: file.priority.as_str().to_string(),
+            status: file.status.as_str().to_string(),
+            suggestion_id: None,
+        }
+    }
+
+    pub fn with_suggestion(mut self, suggestion_id: i64) -> Self {
+        self.suggestion_id = Some(suggestion_id);
+        self
+    }
+
+    /// Whether `file` still has the classification in this snapshot.
+    fn matches(&self, file: &File) -> bool {
+        let current = Self::from_file(file);
+        (&current.category, &current.subcategory, &current.priority, &current.status)
+            == (&self.category, &self.subcategory, &self.priority, &self.status)
+    }
+}
+
+/// A duplicate group's original and resolution before a reviewed dedup plan
+/// resolved it, restored when the plan is undone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateGroupSnapshot {
+    pub group_id: i64,
+    pub original_id: Option<i64>,
+    pub resolution: Option<String>,
+}
+
+/// Record a classification batch, once it has been written, so it can be
+/// undone. Empty batches are not logged.
+pub fn log_classification_batch(
+    db: &mut SqliteDatabase,
+    clock: &Clock,
+    before: &[ClassificationSnapshot],
+    details: String,
+) -> Result<()> {
+    log_attributed_classification_batch(db, clock, None, None, before, details)
+}
+
+/// [`log_classification_batch`] for a batch made by `attribution`, such as an
+/// agent, rather than by whoever the session is attributed to.
+pub fn log_attributed_classification_batch(
+    db: &mut SqliteDatabase,
+    clock: &Clock,
+    attribution: Option<&Attribution>,
+    drive_id: Option<i64>,
+    before: &[ClassificationSnapshot],
+    details: String,
+) -> Result<()> {
+    if before.is_empty() {
+        return Ok(());
+    }
+    let mut after = Vec::with_capacity(before.len());
+    for snapshot in before {
+        if let Some(file) = get_file(db.conn(), snapshot.file_id)? {
+            after.push(ClassificationSnapshot::from_file(&file));
+        }
+    }
+    db.log_audit(&AuditLogEntry {
+        id: 0,
+        timestamp: clock.now(),
+        action: "files_classified".to_string(),
+        file_id: None,
+        plan_id: None,
+        drive_id,
+        details: Some(details),
+        agent_mode: attribution.map(|a| a.agent_mode.clone()),
+        before_state: serde_json::to_string(before).ok(),
+        after_state: serde_json::to_string(&after).ok(),
+        actor: attribution.map(|a| a.actor.clone()),
+    })?;
+    Ok(())
+}
+
+/// Files `actor` classified in batches logged at or after `since`.
+pub fn classified_files_since(db: &SqliteDatabase, actor: &str, since: DateTime<Utc>) -> Result<u64> {
+    let mut stmt = db.conn().prepare(
+        "SELECT before_state FROM audit_log
+         WHERE action = 'files_classified' AND actor = ?1 AND timestamp >= ?2",
+    )?;
+    let states = stmt
+        .query_map((actor, format_timestamp(&since)), |row| row.get::<_, Option<String>>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(states
+        .into_iter()
+        .flatten()
+        .filter_map(|s| serde_json::from_str::<Vec<ClassificationSnapshot>>(&s).ok())
+        .map(|batch| batch.len() as u64)
+        .sum())
+}
+
+/// Record a newly registered drive so it can be undone.
+pub fn log_drive_added(db: &mut SqliteDatabase, clock: &Clock, drive_id: i64, label: &str) -> Result<()> {
+    db.log_audit(&AuditLogEntry {
+        id: 0,
+        timestamp: clock.now(),
+        action: "drive_added".to_string(),
+        file_id: None,
+        plan_id: None,
+        drive_id: Some(drive_id),
+        details: Some(format!("Drive '{}' added", label)),
+        agent_mode: None,
+        before_state: None,
+        after_state: None,
+        actor: None,
+    })?;
+    Ok(())
+}
+
+/// The newest undoable operation not made by an agent and not undone yet.
+/// Fails when it is older than `within`, so an old change is never reverted
+/// by surprise; `None` when there is nothing to undo.
+pub fn pending_undo(db: &SqliteDatabase, within: Duration, now: DateTime<Utc>) -> Result<Option<AuditLogEntry>> {
+    let undone = undone_entry_ids(db)?;
+    let mut stmt = db.conn().prepare(
+        "SELECT id, timestamp, action, file_id, plan_id, drive_id, details, agent_mode,
+                before_state, after_state, actor
+         FROM audit_log
+         WHERE action IN ('files_classified', 'plan_created', 'drive_added')
+           AND (agent_mode IS NULL OR agent_mode != 'agent')
+         ORDER BY id DESC",
+    )?;
+    let mut entries = stmt.query_map([], |row| {
+        Ok(AuditLogEntry {
+            id: row.get(0)?,
+            timestamp: parse_timestamp(&row.get::<_, String>(1)?).unwrap_or_else(Utc::now),
+            action: row.get(2)?,
+            file_id: row.get(3)?,
+            plan_id: row.get(4)?,
+            drive_id: row.get(5)?,
+            details: row.get(6)?,
+            agent_mode: row.get(7)?,
+            before_state: row.get(8)?,
+            after_state: row.get(9)?,
+            actor: row.get(10)?,
+        })
+    })?;
+    let entry = loop {
+        match entries.next().transpose()? {
+            Some(entry) if undone.contains(&entry.id) => continue,
+            entry => break entry,
+        }
+    };
+
+    match entry {
+        Some(entry) if now - entry.timestamp > within => Err(OrdneError::UserInput(format!(
+            "The last operation ({}, {} ago) is outside the undo window of {}",
+            entry.details.as_deref().unwrap_or(&entry.action),
+            format_duration((now - entry.timestamp).to_std().unwrap_or_default()),
+            format_duration(within.to_std().unwrap_or_default())
+        ))),
+        entry => Ok(entry),
+    }
+}
+
+/// Revert `entry` and log, stamped by `clock`, that it was undone. Returns a
+/// description of what changed.
+pub fn undo(db: &mut SqliteDatabase, clock: &Clock, entry: &AuditLogEntry) -> Result<String> {
+    let summary = match entry.action.as_str() {
+        "files_classified" => undo_classification(db, entry)?,
+        "plan_created" => undo_plan(db, entry)?,
+        "drive_added" => undo_drive(db, entry)?,
+        other => {
+            return Err(OrdneError::UserInput(format!("'{}' cannot be undone", other)));
+        }
+    };
+
+    db.log_audit(&AuditLogEntry {
+        id: 0,
+        timestamp: clock.now(),
+        action: "operation_undone".to_string(),
+        file_id: None,
+        plan_id: entry.plan_id,
+        drive_id: entry.drive_id,
+        details: Some(summary.clone()),
+        agent_mode: None,
+        before_state: Some(serde_json::json!({ "audit_id": entry.id }).to_string()),
+        after_state: None,
+        actor: None,
+    })?;
+    Ok(summary)
+}
+
+fn undone_entry_ids(db: &SqliteDatabase) -> Result<Vec<i64>> {
+    let mut stmt = db
+        .conn()
+        .prepare("SELECT before_state FROM audit_log WHERE action = 'operation_undone'")?;
+    let states = stmt
+        .query_map([], |row| row.get::<_, Option<String>>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    Ok(states
+        .into_iter()
+        .flatten()
+        .filter_map(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+        .filter_map(|v| v.get("audit_id").and_then(|id| id.as_i64()))
+        .collect())
+}
+
+fn undo_classification(db: &mut SqliteDatabase, entry: &AuditLogEntry) -> Result<String> {
+    let before: Vec<ClassificationSnapshot> = entry
+        .before_state
+        .as_deref()
+        .and_then(|s| serde_json::from_str(s).ok())
+        .ok_or_else(|| {
+            OrdneError::UserInput("This classification was recorded without its previous state".to_string())
+        })?;
+
+    // What the batch wrote, keyed by file. Batches logged before this was
+    // recorded are restored as they are.
+    let after: Option<HashMap<i64, ClassificationSnapshot>> = entry
+        .after_state
+        .as_deref()
+        .and_then(|s| serde_json::from_str::<Vec<ClassificationSnapshot>>(s).ok())
+        .map(|after| after.into_iter().map(|s| (s.file_id, s)).collect());
+
+    let tx = db.conn_mut().transaction()?;
+    let (mut restored, mut changed) = (0, 0);
+    for snapshot in &before {
+        let Some(file) = get_file(&tx, snapshot.file_id)? else {
+            continue;
+        };
+        if let Some(after) = &after {
+            if !after.get(&file.id).is_some_and(|written| written.matches(&file)) {
+                changed += 1;
+                continue;
+            }
+        }
+        tx.execute(
+            "UPDATE files SET category = ?1, subcategory = ?2, priority = ?3, status = ?4 WHERE id = ?5",
+            (
+                &snapshot.category,
+                &snapshot.subcategory,
+                &snapshot.priority,
+                &snapshot.status,
+                snapshot.file_id,
+            ),
+        )?;
+        if let Some(suggestion_id) = snapshot.suggestion_id {
+            tx.execute(
+                "UPDATE classification_suggestions SET status = 'pending', reviewed_at = NULL WHERE id = ?1",
+                [suggestion_id],
+            )?;
+        }
+        restored += 1;
+    }
+    tx.commit()?;
+
+    if changed > 0 {
+        return Ok(format!(
+            "Restored the previous classification of {} files; {} changed since and were left as they are",
+            restored, changed
+        ));
+    }
+    Ok(format!("Restored the previous classification of {} files", restored))
+}
+
+fn undo_plan(db: &mut SqliteDatabase, entry: &AuditLogEntry) -> Result<String> {
+    let plan_id = entry
+        .plan_id
+        .ok_or_else(|| OrdneError::UserInput("Audit entry has no plan".to_string()))?;
+    let Some(plan) = db.get_plan(plan_id)? else {
+        return Err(OrdneError::PlanNotFound(plan_id));
+    };
+    let started: bool = db.conn().query_row(
+        "SELECT EXISTS(SELECT 1 FROM plan_sessions WHERE plan_id = ?1)",
+        [plan_id],
+        |row| row.get(0),
+    )?;
+    if started
+        || plan.completed_files > 0
+        || !matches!(plan.status, PlanStatus::Draft | PlanStatus::Approved | PlanStatus::Queued)
+    {
+        return Err(OrdneError::UserInput(format!(
+            "Plan {} has already run ({}); use 'ordne rollback {}' instead",
+            plan_id,
+            plan.status.as_str(),
+            plan_id
+        )));
+    }
+
+    let tx = db.conn_mut().transaction()?;
+    tx.execute(
+        "DELETE FROM step_dependencies WHERE step_id IN (SELECT id FROM migration_steps WHERE plan_id = ?1)",
+        [plan_id],
+    )?;
+    for table in ["plan_satisfied_files", "plan_space_usage", "verification_jobs", "migration_steps"] {
+        tx.execute(&format!("DELETE FROM {} WHERE plan_id = ?1", table), [plan_id])?;
+    }
+    tx.execute("DELETE FROM migration_plans WHERE id = ?1", [plan_id])?;
+    // A reviewed dedup plan resolved its groups; they go back to review.
+    let groups: Vec<DuplicateGroupSnapshot> = entry
+        .before_state
+        .as_deref()
+        .and_then(|s| serde_json::from_str(s).ok())
+        .unwrap_or_default();
+    for group in &groups {
+        tx.execute(
+            "UPDATE duplicate_groups SET original_id = ?1, resolution = ?2 WHERE group_id = ?3",
+            (group.original_id, &group.resolution, group.group_id),
+        )?;
+        tx.execute(
+            "UPDATE files SET is_original = (id IS ?1) WHERE duplicate_group = ?2",
+            (group.original_id, group.group_id),
+        )?;
+    }
+    tx.commit()?;
+
+    if !groups.is_empty() {
+        return Ok(format!(
+            "Deleted plan {} ({} files) and returned {} duplicate groups to review",
+            plan_id,
+            plan.total_files,
+            groups.len()
+        ));
+    }
+    Ok(format!("Deleted plan {} ({} files)", plan_id, plan.total_files))
+}
+
+fn undo_drive(db: &mut SqliteDatabase, entry: &AuditLogEntry) -> Result<String> {
+    let drive_id = entry
+        .drive_id
+        .ok_or_else(|| OrdneError::UserInput("Audit entry has no drive".to_string()))?;
+    let Some(drive) = db.get_drive_by_id(drive_id)? else {
+        return Err(OrdneError::UserInput(format!("Drive {} no longer exists", drive_id)));
+    };
+    let file_count: i64 = db.conn().query_row(
+        "SELECT COUNT(*) FROM files WHERE drive_id = ?1",
+        [drive_id],
+        |row| row.get(0),
+    )?;
+    if file_count > 0 {
+        return Err(OrdneError::UserInput(format!(
+            "Drive '{}' already has {} indexed files; remove it with 'ordne drive remove {}'",
+            drive.label, file_count, drive.label
+        )));
+    }
+
+    let in_plans: bool = db.conn().query_row(
+        "SELECT EXISTS(SELECT 1 FROM migration_steps WHERE source_drive_id = ?1 OR dest_drive_id = ?1)
+             OR EXISTS(SELECT 1 FROM migration_plans WHERE source_drive_id = ?1 OR target_drive_id = ?1)",
+        [drive_id],
+        |row| row.get(0),
+    )?;
+    if in_plans {
+        return Err(OrdneError::UserInput(format!(
+            "Drive '{}' is used by a plan; undo or delete that plan first",
+            drive.label
+        )));
+    }
+
+    db.conn().execute("DELETE FROM drives WHERE id = ?1", [drive_id])?;
+    Ok(format!("Removed drive '{}'", drive.label))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::files::update_file_classification;
+    use crate::db::Priority;
+
+    fn setup_db() -> SqliteDatabase {
+        let mut db = SqliteDatabase::open_in_memory().unwrap();
+        db.initialize().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO drives (id, label, role, is_online, backend) VALUES (1, 'data', 'source', 1, 'local');
+                 INSERT INTO files (id, drive_id, path, filename, size_bytes, status) VALUES (1, 1, 'a', 'a', 10, 'indexed');",
+            )
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_undo_reverts_latest_operation_once() {
+        let mut db = setup_db();
+        let clock = Clock::default();
+        let now = clock.now();
+
+        let file = get_file(db.conn(), 1).unwrap().unwrap();
+        update_file_classification(db.conn(), 1, "photos", None, Priority::Critical).unwrap();
+        log_classification_batch(&mut db, &clock, &[ClassificationSnapshot::from_file(&file)], "1 file".to_string())
+            .unwrap();
+        log_drive_added(&mut db, &clock, 2, "spare").unwrap();
+        db.conn()
+            .execute("INSERT INTO drives (id, label, role, is_online, backend) VALUES (2, 'spare', 'target', 1, 'local')", [])
+            .unwrap();
+
+        // Newest first: the drive, then the classification.
+        let entry = pending_undo(&db, Duration::hours(1), now).unwrap().unwrap();
+        assert_eq!(entry.action, "drive_added");
+        undo(&mut db, &clock, &entry).unwrap();
+        assert!(db.get_drive("spare").unwrap().is_none());
+
+        let entry = pending_undo(&db, Duration::hours(1), now).unwrap().unwrap();
+        assert_eq!(entry.action, "files_classified");
+        undo(&mut db, &clock, &entry).unwrap();
+        let file = get_file(db.conn(), 1).unwrap().unwrap();
+        assert_eq!(file.category, None);
+        assert_eq!(file.priority, Priority::Normal);
+
+        assert!(pending_undo(&db, Duration::hours(1), now).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_undo_refuses_outside_window() {
+        let mut db = setup_db();
+        let clock = Clock::fixed(Utc::now());
+        log_drive_added(&mut db, &clock, 1, "data").unwrap();
+
+        let later = clock.now() + Duration::hours(2);
+        assert!(matches!(
+            pending_undo(&db, Duration::hours(1), later),
+            Err(OrdneError::UserInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_undo_classification_leaves_files_changed_since() {
+        let mut db = setup_db();
+        db.conn()
+            .execute("INSERT INTO files (id, drive_id, path, filename, size_bytes, status) VALUES (2, 1, 'b', 'b', 10, 'indexed')", [])
+            .unwrap();
+        let clock = Clock::fixed(DateTime::parse_from_rfc3339("2026-03-01T12:00:00Z").unwrap().into());
+
+        let before: Vec<_> = [1, 2]
+            .map(|id| ClassificationSnapshot::from_file(&get_file(db.conn(), id).unwrap().unwrap()))
+            .to_vec();
+        for id in [1, 2] {
+            update_file_classification(db.conn(), id, "photos", None, Priority::Critical).unwrap();
+        }
+        log_classification_batch(&mut db, &clock, &before, "2 files".to_string()).unwrap();
+        // A plan picked up the second file after the batch.
+        db.conn().execute("UPDATE files SET status = 'planned' WHERE id = 2", []).unwrap();
+
+        let entry = pending_undo(&db, Duration::hours(1), clock.now()).unwrap().unwrap();
+        let summary = undo(&mut db, &clock, &entry).unwrap();
+        assert!(summary.contains("1 changed since"));
+        assert_eq!(get_file(db.conn(), 1).unwrap().unwrap().category, None);
+        let second = get_file(db.conn(), 2).unwrap().unwrap();
+        assert_eq!(second.category.as_deref(), Some("photos"));
+        assert_eq!(second.status.as_str(), "planned");
+
+        let timestamp: String = db
+            .conn()
+            .query_row("SELECT timestamp FROM audit_log WHERE action = 'operation_undone'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(parse_timestamp(&timestamp), Some(clock.now()));
+    }
+
+    #[test]
+    fn test_undo_reviewed_dedup_plan_reopens_its_groups() {
+        let mut db = setup_db();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO files (id, drive_id, path, filename, size_bytes, status, duplicate_group)
+                 VALUES (2, 1, 'b', 'b', 10, 'indexed', 1), (3, 1, 'c', 'c', 10, 'indexed', 1);
+                 INSERT INTO duplicate_groups (group_id, hash, file_count, total_waste_bytes, drives_involved)
+                 VALUES (1, 'h', 2, 10, '[1]');",
+            )
+            .unwrap();
+        let group = crate::db::duplicates::get_duplicate_group(db.conn(), 1).unwrap().unwrap();
+        let resolution = crate::migrate::DedupResolution {
+            group_id: 1,
+            keeper: get_file(db.conn(), 2).unwrap().unwrap(),
+            duplicates: vec![get_file(db.conn(), 3).unwrap().unwrap()],
+            previous_original_id: group.original_id,
+            previous_resolution: group.resolution,
+        };
+        crate::migrate::Planner::new(&mut db, Default::default())
+            .create_grouped_dedup_plan(&[resolution])
+            .unwrap();
+        crate::db::duplicates::set_duplicate_group_original(db.conn(), 1, 2).unwrap();
+        crate::db::duplicates::update_duplicate_group_resolution(db.conn(), 1, "user_resolved").unwrap();
+        assert!(crate::db::duplicates::list_unresolved_duplicate_groups(db.conn()).unwrap().is_empty());
+
+        let entry = pending_undo(&db, Duration::hours(1), Utc::now()).unwrap().unwrap();
+        assert_eq!(entry.action, "plan_created");
+        undo(&mut db, &Clock::default(), &entry).unwrap();
+
+        let groups = crate::db::duplicates::list_unresolved_duplicate_groups(db.conn()).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].original_id, None);
+        assert!(!get_file(db.conn(), 2).unwrap().unwrap().is_original);
+    }
+}
@@ -36,35 +36,10 @@ pub fn format_duration(duration: Duration) -> String {
 }
 
 pub fn format_timestamp(dt: &DateTime<Utc>) -> String {
-    let local: DateTime<Local> = DateTime::from(dt.clone());
+    let local: DateTime<Local> = DateTime::from(*dt);
     local.format("%Y-%m-%d %H:%M:%S").to_string()
 }
 
-pub fn parse_size_string(s: &str) -> Result<i64, String> {
-    let s = s.trim().to_uppercase();
-
-    let (num_str, multiplier) = if let Some(stripped) = s.strip_suffix("TB") {
-        (stripped, 1024_i64.pow(4))
-    } else if let Some(stripped) = s.strip_suffix("GB") {
-        (stripped, 1024_i64.pow(3))
-    } else if let Some(stripped) = s.strip_suffix("MB") {
-        (stripped, 1024_i64.pow(2))
-    } else if let Some(stripped) = s.strip_suffix("KB") {
-        (stripped, 1024)
-    } else if let Some(stripped) = s.strip_suffix("B") {
-        (stripped, 1)
-    } else {
-        (&*s, 1)
-    };
-
-    let num: f64 = num_str
-        .trim()
-        .parse()
-        .map_err(|_| format!("Invalid size value: {}", s))?;
-
-    Ok((num * multiplier as f64) as i64)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -84,13 +59,4 @@ mod tests {
         assert_eq!(format_duration(Duration::from_secs(90)), "1m 30s");
         assert_eq!(format_duration(Duration::from_secs(3665)), "1h 1m 5s");
     }
-
-    #[test]
-    fn test_parse_size_string() {
-        assert_eq!(parse_size_string("512B").unwrap(), 512);
-        assert_eq!(parse_size_string("1KB").unwrap(), 1024);
-        assert_eq!(parse_size_string("1.5 MB").unwrap(), 1_572_864);
-        assert_eq!(parse_size_string("2GB").unwrap(), 2_147_483_648);
-        assert_eq!(parse_size_string("100").unwrap(), 100);
-    }
 }
@@ -1,2 +1,6 @@
+pub mod clock;
 pub mod format;
+pub mod process;
 pub mod progress;
+pub mod units;
+pub mod users;
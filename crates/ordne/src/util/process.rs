@@ -0,0 +1,44 @@
+//! This host and the processes running on it.
+//!
+//! Plan sessions record the process that runs them, so a second run can tell
+//! a plan that is still executing from one a crash left behind.
+
+use std::ffi::CStr;
+use std::io;
+
+/// This host's name, or an empty string if it cannot be read.
+pub fn host_name() -> String {
+    let mut buf = [0 as libc::c_char; 256];
+    let rc = unsafe { libc::gethostname(buf.as_mut_ptr(), buf.len() - 1) };
+    if rc != 0 {
+        return String::new();
+    }
+    let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    name.to_string_lossy().into_owned()
+}
+
+/// Whether a process with `pid` exists on this host, whoever owns it.
+pub fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if pid <= 0 {
+        return false;
+    }
+    let rc = unsafe { libc::kill(pid, 0) };
+    rc == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_running() {
+        assert!(is_running(std::process::id()));
+        assert!(!is_running(0));
+        // Above the kernel's largest possible pid.
+        assert!(!is_running(1 << 23));
+        assert!(!host_name().is_empty());
+    }
+}
@@ -1,4 +1,16 @@
 use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Bytes hashed or copied by this process, for the command summary footer.
+static BYTES_PROCESSED: AtomicU64 = AtomicU64::new(0);
+
+pub fn add_bytes_processed(bytes: u64) {
+    BYTES_PROCESSED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+pub fn bytes_processed() -> u64 {
+    BYTES_PROCESSED.load(Ordering::Relaxed)
+}
 
 pub fn create_progress_bar(total: u64, message: &str) -> ProgressBar {
     let pb = ProgressBar::new(total);
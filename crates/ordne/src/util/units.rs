@@ -0,0 +1,191 @@
+//! Parsing of human-friendly sizes and durations.
+//!
+//! Every size or age that a user types, on the command line, in a policy or
+//! in classification rules, goes through here, so `500GB`, `2TiB`, `90d` and
+//! `12h` mean the same thing everywhere. Size suffixes are binary whether or
+//! not they carry the `i` (`1KB` and `1KiB` are both 1024 bytes), matching
+//! what `df` and `du` report.
+
+use serde::{Deserialize, Deserializer};
+
+const SIZE_UNITS: &[(&[&str], i64)] = &[
+    (&["", "b"], 1),
+    (&["k", "kb", "kib"], 1 << 10),
+    (&["m", "mb", "mib"], 1 << 20),
+    (&["g", "gb", "gib"], 1 << 30),
+    (&["t", "tb", "tib"], 1 << 40),
+    (&["p", "pb", "pib"], 1 << 50),
+];
+
+const DURATION_UNITS: &[(&[&str], i64)] = &[
+    (&["s", "sec", "secs", "second", "seconds"], 1),
+    (&["m", "min", "mins", "minute", "minutes"], 60),
+    (&["h", "hr", "hrs", "hour", "hours"], 3_600),
+    (&["d", "day", "days"], 86_400),
+    (&["w", "wk", "wks", "week", "weeks"], 604_800),
+];
+
+/// Split `s` into a non-negative number and its (lowercased) unit.
+fn split_quantity(s: &str, what: &str) -> Result<(f64, String), String> {
+    let trimmed = s.trim();
+    let unit_start = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(unit_start);
+
+    if trimmed.starts_with('-') {
+        return Err(format!("{} '{}' must not be negative", what, s));
+    }
+    let number: f64 = number.parse().map_err(|_| {
+        format!(
+            "{} '{}' must be a number followed by an optional unit",
+            what, s
+        )
+    })?;
+    Ok((number, unit.trim().to_lowercase()))
+}
+
+fn scale(number: f64, multiplier: i64, what: &str, s: &str) -> Result<i64, String> {
+    let value = number * multiplier as f64;
+    if value >= i64::MAX as f64 {
+        return Err(format!("{} '{}' is too large", what, s));
+    }
+    Ok(value as i64)
+}
+
+/// Parse a size like `512`, `1.5MB`, `500GB` or `2TiB` into bytes.
+pub fn parse_bytes(s: &str) -> Result<i64, String> {
+    let (number, unit) = split_quantity(s, "size")?;
+    let multiplier = SIZE_UNITS
+        .iter()
+        .find(|(names, _)| names.contains(&unit.as_str()))
+        .map(|(_, multiplier)| *multiplier)
+        .ok_or_else(|| {
+            format!(
+                "unknown size unit '{}' in '{}' (use B, KB, MB, GB, TB, PB or KiB..PiB)",
+                unit, s
+            )
+        })?;
+    scale(number, multiplier, "size", s)
+}
+
+fn parse_seconds(s: &str, bare_unit_secs: i64) -> Result<i64, String> {
+    let (number, unit) = split_quantity(s, "duration")?;
+    let multiplier = if unit.is_empty() {
+        bare_unit_secs
+    } else {
+        DURATION_UNITS
+            .iter()
+            .find(|(names, _)| names.contains(&unit.as_str()))
+            .map(|(_, multiplier)| *multiplier)
+            .ok_or_else(|| {
+                format!(
+                    "unknown duration unit '{}' in '{}' (use s, m, h, d or w)",
+                    unit, s
+                )
+            })?
+    };
+    scale(number, multiplier, "duration", s)
+}
+
+/// Parse a delay like `90s`, `30m`, `12h`, `7d` or `2w`. A bare number is hours.
+pub fn parse_duration(s: &str) -> Result<chrono::Duration, String> {
+    parse_seconds(s, 3_600).map(chrono::Duration::seconds)
+}
+
+/// Parse an age like `90d`, `12w` or `36h` into whole days, rounding down.
+/// A bare number is days.
+pub fn parse_days(s: &str) -> Result<i64, String> {
+    parse_seconds(s, 86_400).map(|secs| secs / 86_400)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrText {
+    Number(i64),
+    Text(String),
+}
+
+fn deserialize_with<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    parse: fn(&str) -> Result<i64, String>,
+) -> Result<Option<i64>, D::Error> {
+    match Option::<NumberOrText>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(NumberOrText::Number(n)) if n < 0 => Err(serde::de::Error::custom(format!(
+            "{} must not be negative",
+            n
+        ))),
+        Some(NumberOrText::Number(n)) => Ok(Some(n)),
+        Some(NumberOrText::Text(s)) => parse(&s).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// For `#[serde(deserialize_with)]`: a byte count as a number or a size string.
+pub fn deserialize_opt_bytes<'de, D: Deserializer<'de>>(d: D) -> Result<Option<i64>, D::Error> {
+    deserialize_with(d, parse_bytes)
+}
+
+/// For `#[serde(deserialize_with)]`: a day count as a number or a duration string.
+pub fn deserialize_opt_days<'de, D: Deserializer<'de>>(d: D) -> Result<Option<i64>, D::Error> {
+    deserialize_with(d, parse_days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_bytes("512B").unwrap(), 512);
+        assert_eq!(parse_bytes("1KB").unwrap(), 1024);
+        assert_eq!(parse_bytes("1.5 MB").unwrap(), 1_572_864);
+        assert_eq!(parse_bytes("2GB").unwrap(), 2_147_483_648);
+        assert_eq!(parse_bytes("500gb").unwrap(), 500 << 30);
+        assert_eq!(parse_bytes("2TiB").unwrap(), 2 << 40);
+        assert_eq!(
+            parse_bytes("1.5G").unwrap(),
+            (1.5 * 1024.0 * 1024.0 * 1024.0) as i64
+        );
+        assert_eq!(parse_bytes("100").unwrap(), 100);
+
+        assert!(parse_bytes("12XB")
+            .unwrap_err()
+            .contains("unknown size unit 'xb'"));
+        assert!(parse_bytes("-1GB").unwrap_err().contains("negative"));
+        assert!(parse_bytes("GB").is_err());
+        assert!(parse_bytes("99999PB").unwrap_err().contains("too large"));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(
+            parse_duration("90s").unwrap(),
+            chrono::Duration::seconds(90)
+        );
+        assert_eq!(
+            parse_duration("30m").unwrap(),
+            chrono::Duration::minutes(30)
+        );
+        assert_eq!(
+            parse_duration("12 hours").unwrap(),
+            chrono::Duration::hours(12)
+        );
+        assert_eq!(parse_duration("7d").unwrap(), chrono::Duration::days(7));
+        assert_eq!(parse_duration("2w").unwrap(), chrono::Duration::days(14));
+        assert_eq!(
+            parse_duration("1.5").unwrap(),
+            chrono::Duration::minutes(90)
+        );
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("-1h").is_err());
+        assert!(parse_duration("3y")
+            .unwrap_err()
+            .contains("unknown duration unit 'y'"));
+
+        assert_eq!(parse_days("90").unwrap(), 90);
+        assert_eq!(parse_days("90d").unwrap(), 90);
+        assert_eq!(parse_days("12w").unwrap(), 84);
+        assert_eq!(parse_days("36h").unwrap(), 1);
+    }
+}